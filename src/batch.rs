@@ -0,0 +1,460 @@
+//! Batch mode: checks all programs of a directory and summarizes the results.
+//!
+//! Each program is checked by a separate `specbmc` process, such that crashing or
+//! diverging checks don't affect the others. All arguments (except the batch arguments)
+//! are forwarded to the individual checks. A sibling environment file (same file name with
+//! extension `yaml`) takes precedence over the environment file given on the command line.
+//!
+//! Verdicts are printed as soon as the individual checks finish and the report file is rewritten
+//! after each of them, such that long batch runs produce usable output early.
+
+use colored::*;
+use serde::Serialize;
+use specbmc::error::Result;
+use specbmc::util::DumpToFile;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// Arguments which are consumed by the batch mode itself (all of them take a value).
+const BATCH_ARGUMENTS: &[&str] = &["--batch", "--jobs", "--batch-report"];
+
+/// Arguments which are replaced by sibling environment files (all of them take a value).
+const ENVIRONMENT_ARGUMENTS: &[&str] = &["-e", "--env"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub enum Verdict {
+    #[serde(rename = "safe")]
+    Safe,
+    #[serde(rename = "leak")]
+    Leak,
+    #[serde(rename = "error")]
+    Error,
+}
+
+impl Verdict {
+    fn from_exit_code(code: Option<i32>) -> Self {
+        match code {
+            Some(0) => Self::Safe,
+            Some(2) => Self::Leak,
+            _ => Self::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    file: PathBuf,
+    environment: Option<PathBuf>,
+    verdict: Verdict,
+    seconds: f64,
+}
+
+/// Checks all programs found in `dir` with `jobs` checks running in parallel.
+///
+/// Returns the exit code of the batch: 1 if any check failed, 2 if any leak was found, 0 otherwise.
+pub fn run_batch(dir: &Path, jobs: usize, report_file: Option<&Path>) -> Result<i32> {
+    let programs = discover_programs(dir)?;
+    if programs.is_empty() {
+        return Err(format!("No programs found in '{}'", dir.display()).into());
+    }
+
+    let executable = std::env::current_exe()?;
+    let forwarded_arguments = forwarded_arguments(std::env::args().skip(1));
+
+    println!(
+        "Checking {} programs ({} in parallel) ...",
+        programs.len(),
+        jobs
+    );
+
+    let total = programs.len();
+    let queue = Arc::new(Mutex::new(programs.into_iter().collect::<VecDeque<_>>()));
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let report_file = report_file.map(Path::to_path_buf);
+
+    let workers: Vec<_> = (0..jobs.max(1))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let results = Arc::clone(&results);
+            let executable = executable.clone();
+            let forwarded_arguments = forwarded_arguments.clone();
+            let report_file = report_file.clone();
+            thread::spawn(move || -> std::result::Result<(), String> {
+                loop {
+                    let program = match queue.lock().unwrap().pop_front() {
+                        Some(program) => program,
+                        None => return Ok(()),
+                    };
+                    let result = check(&executable, &forwarded_arguments, &program);
+
+                    // The lock also serializes the console output and report updates of the workers
+                    let mut results = results.lock().unwrap();
+                    record_result(&mut results, result, total, report_file.as_deref())
+                        .map_err(|err| err.to_string())?;
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| "Batch worker terminated unexpectedly")??;
+    }
+
+    let mut results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+    results.sort_by(|a, b| a.file.cmp(&b.file));
+
+    print_summary(&results);
+
+    if let Some(path) = &report_file {
+        write_report(path, &results)?;
+    }
+
+    let any = |verdict| results.iter().any(|result| result.verdict == verdict);
+    Ok(if any(Verdict::Error) {
+        1
+    } else if any(Verdict::Leak) {
+        2
+    } else {
+        0
+    })
+}
+
+/// Records the result of a finished check (out of `total` checks),
+/// i.e. prints its verdict and rewrites the report file (if given) with all results so far.
+fn record_result(
+    results: &mut Vec<BatchResult>,
+    result: BatchResult,
+    total: usize,
+    report_file: Option<&Path>,
+) -> Result<()> {
+    println!(
+        "   [{:>width$}/{}] {} {} ({:.2}s)",
+        results.len() + 1,
+        total,
+        verdict_style(result.verdict),
+        result.file.display(),
+        result.seconds,
+        width = total.to_string().len()
+    );
+    results.push(result);
+    if let Some(path) = report_file {
+        write_report(path, results)?;
+    }
+    Ok(())
+}
+
+/// Writes the results (finished so far) into the report file.
+///
+/// The report is written into a temporary file first and then moved, such that readers
+/// polling the report during a running batch never see a partially written file.
+fn write_report(path: &Path, results: &[BatchResult]) -> Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    serde_json::to_string_pretty(results)?.dump_to_file(&partial)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Returns all muasm programs and ELF files in `dir` (recursively), sorted by path.
+fn discover_programs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut programs = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if is_muasm_file(&path) || is_elf_file(&path) {
+                programs.push(path);
+            }
+        }
+    }
+    programs.sort();
+    Ok(programs)
+}
+
+fn is_muasm_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("muasm")
+}
+
+fn is_elf_file(path: &Path) -> bool {
+    use std::io::Read;
+    let mut magic = [0; 4];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_or(false, |_| magic == *b"\x7fELF")
+}
+
+/// Removes the batch arguments from the command line arguments.
+fn forwarded_arguments<I: Iterator<Item = String>>(arguments: I) -> Vec<String> {
+    let mut forwarded = Vec::new();
+    let mut arguments = arguments.peekable();
+    while let Some(argument) = arguments.next() {
+        let name = argument.split('=').next().unwrap_or_default();
+        if BATCH_ARGUMENTS.contains(&name) {
+            if !argument.contains('=') {
+                arguments.next(); // skip value
+            }
+            continue;
+        }
+        forwarded.push(argument);
+    }
+    forwarded
+}
+
+fn check(executable: &Path, arguments: &[String], program: &Path) -> BatchResult {
+    let environment = Some(program.with_extension("yaml")).filter(|path| path.is_file());
+
+    let mut command = Command::new(executable);
+    match &environment {
+        Some(environment) => {
+            command.args(without_environment_argument(arguments));
+            command.arg("--env").arg(environment);
+        }
+        None => {
+            command.args(arguments);
+        }
+    }
+    // Counterexamples of parallel checks would overwrite each other
+    command.arg("--skip-cex").arg(program);
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+
+    let start = Instant::now();
+    let status = command.status();
+    let seconds = start.elapsed().as_secs_f64();
+
+    BatchResult {
+        file: program.to_path_buf(),
+        environment,
+        verdict: Verdict::from_exit_code(status.ok().and_then(|status| status.code())),
+        seconds,
+    }
+}
+
+fn without_environment_argument(arguments: &[String]) -> Vec<String> {
+    let mut remaining = Vec::new();
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let name = argument.split('=').next().unwrap_or_default();
+        if ENVIRONMENT_ARGUMENTS.contains(&name) {
+            if !argument.contains('=') {
+                arguments.next(); // skip value
+            }
+            continue;
+        }
+        remaining.push(argument.clone());
+    }
+    remaining
+}
+
+fn verdict_style(verdict: Verdict) -> ColoredString {
+    match verdict {
+        Verdict::Safe => "safe ".green(),
+        Verdict::Leak => "leak ".red(),
+        Verdict::Error => "error".yellow(),
+    }
+}
+
+fn print_summary(results: &[BatchResult]) {
+    let width = results
+        .iter()
+        .map(|result| result.file.display().to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max("FILE".len());
+
+    println!();
+    println!(
+        "{:<width$}  {:<6} {:>10}",
+        "FILE",
+        "RESULT",
+        "TIME",
+        width = width
+    );
+    for result in results {
+        println!(
+            "{:<width$}  {:<6} {:>9.2}s",
+            result.file.display(),
+            verdict_style(result.verdict),
+            result.seconds,
+            width = width
+        );
+    }
+
+    let count = |verdict| {
+        results
+            .iter()
+            .filter(|result| result.verdict == verdict)
+            .count()
+    };
+    let total: f64 = results.iter().map(|result| result.seconds).sum();
+    println!();
+    println!(
+        "{} safe, {} leaks, {} errors (total time: {:.2}s)",
+        count(Verdict::Safe),
+        count(Verdict::Leak),
+        count(Verdict::Error),
+        total
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_arguments_are_not_forwarded() {
+        // Given:
+        let arguments = strings(&[
+            "--batch", "dir", "--jobs=4", "-e", "env.yaml", "--unwind", "2",
+        ]);
+
+        // When:
+        let forwarded = forwarded_arguments(arguments.into_iter());
+
+        // Then:
+        assert_eq!(forwarded, strings(&["-e", "env.yaml", "--unwind", "2"]));
+    }
+
+    #[test]
+    fn test_sibling_environment_replaces_environment_argument() {
+        // Given:
+        let arguments = strings(&["--env=a.yaml", "--unwind", "2", "-e", "b.yaml"]);
+
+        // When:
+        let remaining = without_environment_argument(&arguments);
+
+        // Then:
+        assert_eq!(remaining, strings(&["--unwind", "2"]));
+    }
+
+    #[test]
+    fn test_verdict_from_exit_code() {
+        // Given: exit codes of safe, leaking, failing and killed checks
+        let codes = vec![Some(0), Some(2), Some(1), None];
+
+        // When:
+        let verdicts: Vec<Verdict> = codes.into_iter().map(Verdict::from_exit_code).collect();
+
+        // Then:
+        assert_eq!(
+            verdicts,
+            vec![Verdict::Safe, Verdict::Leak, Verdict::Error, Verdict::Error]
+        );
+    }
+
+    #[test]
+    fn test_programs_are_discovered_recursively() {
+        // Given:
+        let dir = std::env::temp_dir().join(format!("specbmc-batch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.muasm"), "skip\n").unwrap();
+        fs::write(dir.join("b.yaml"), "").unwrap();
+        fs::write(dir.join("sub").join("a.o"), b"\x7fELF\x02\x01").unwrap();
+        fs::write(dir.join("sub").join("notes.txt"), "").unwrap();
+
+        // When:
+        let programs = discover_programs(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Then:
+        assert_eq!(
+            programs.unwrap(),
+            vec![dir.join("b.muasm"), dir.join("sub").join("a.o")]
+        );
+    }
+
+    fn result(file: &str, verdict: Verdict) -> BatchResult {
+        BatchResult {
+            file: PathBuf::from(file),
+            environment: None,
+            verdict,
+            seconds: 1.5,
+        }
+    }
+
+    fn read_report(path: &Path) -> Vec<(String, String)> {
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        report
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| {
+                (
+                    result["file"].as_str().unwrap().to_owned(),
+                    result["verdict"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_report_is_rewritten_after_each_check() {
+        // Given:
+        let dir = std::env::temp_dir().join(format!("specbmc-report-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.json");
+        let mut results = Vec::new();
+
+        // When:
+        record_result(
+            &mut results,
+            result("b.muasm", Verdict::Leak),
+            2,
+            Some(&path),
+        )
+        .unwrap();
+        let after_first = read_report(&path);
+        record_result(
+            &mut results,
+            result("a.muasm", Verdict::Safe),
+            2,
+            Some(&path),
+        )
+        .unwrap();
+        let after_second = read_report(&path);
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Then: the report contains the results in the order the checks finished
+        assert_eq!(after_first, vec![("b.muasm".to_owned(), "leak".to_owned())]);
+        assert_eq!(
+            after_second,
+            vec![
+                ("b.muasm".to_owned(), "leak".to_owned()),
+                ("a.muasm".to_owned(), "safe".to_owned()),
+            ]
+        );
+        assert_eq!(files, 1, "the partial report has been moved");
+    }
+
+    #[test]
+    fn test_results_are_recorded_without_report_file() {
+        // Given:
+        let mut results = Vec::new();
+
+        // When:
+        record_result(&mut results, result("a.muasm", Verdict::Error), 1, None).unwrap();
+
+        // Then:
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].verdict, Verdict::Error);
+    }
+}
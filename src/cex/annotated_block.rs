@@ -1,6 +1,8 @@
 use crate::cex::{AnnotatedElement, AnnotatedInstruction};
+use crate::expr::Variable;
 use crate::hir;
 use falcon::graph;
+use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Clone, Debug, Default)]
@@ -123,6 +125,22 @@ impl AnnotatedBlock {
         &mut self.element
     }
 
+    /// Removes all annotated instruction values which are identical in both compositions.
+    pub fn elide_identical_state(&mut self) {
+        self.block_mut()
+            .instructions_mut()
+            .iter_mut()
+            .for_each(AnnotatedInstruction::elide_identical_state);
+    }
+
+    /// Renames the variables of all instructions as given by `names`.
+    pub fn rename_variables(&mut self, names: &HashMap<Variable, Variable>) {
+        self.block_mut()
+            .instructions_mut()
+            .iter_mut()
+            .for_each(|inst| inst.rename_variables(names));
+    }
+
     /// Returns whether this `AnnotatedBlock` is executed in any composition.
     pub fn executed(&self) -> bool {
         self.annotations
@@ -1,6 +1,8 @@
 use crate::cex::AnnotatedElement;
+use crate::expr::Variable;
 use crate::hir::Edge;
 use falcon::graph;
+use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Clone, Debug, Default)]
@@ -29,6 +31,15 @@ impl AnnotatedEdge {
         &self.element
     }
 
+    /// Renames the variables of the edge condition as given by `names`.
+    pub fn rename_variables(&mut self, names: &HashMap<Variable, Variable>) {
+        for var in self.element.variables_read_mut() {
+            if let Some(name) = names.get(var) {
+                *var = name.clone();
+            }
+        }
+    }
+
     /// Returns whether this `Edge` is executed in any composition.
     pub fn executed(&self) -> bool {
         self.annotations
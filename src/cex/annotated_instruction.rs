@@ -1,4 +1,4 @@
-use crate::cex::{AnnotatedElement, Effect};
+use crate::cex::{AnnotatedElement, ComponentState, Composition, Effect};
 use crate::expr::{Constant, Expression, Operator, Sort, Variable};
 use crate::hir::{Instruction, Operation};
 use std::collections::HashMap;
@@ -11,6 +11,8 @@ pub struct Annotation {
     assignments: Vec<(Expression, Constant)>,
     /// Effects produced by the underlying instruction.
     effects: Vec<Effect>,
+    /// Component contents after the effects (only if the instruction has effects)
+    components: Option<ComponentState>,
     /// Configuration
     configuration: HashMap<Variable, Constant>,
 }
@@ -24,6 +26,10 @@ impl Annotation {
         self.effects.push(effect);
     }
 
+    pub fn set_components(&mut self, components: ComponentState) {
+        self.components = Some(components);
+    }
+
     pub fn add_variable_configuration(&mut self, var: Variable, value: Constant) {
         self.configuration.insert(var, value);
     }
@@ -36,9 +42,57 @@ impl Annotation {
         &self.effects
     }
 
+    pub fn components(&self) -> Option<&ComponentState> {
+        self.components.as_ref()
+    }
+
     pub fn configuration(&self) -> &HashMap<Variable, Constant> {
         &self.configuration
     }
+
+    /// Returns true if this `Annotation` holds neither assignments, effects, components nor configuration.
+    pub fn is_empty(&self) -> bool {
+        self.assignments.is_empty()
+            && self.effects.is_empty()
+            && self.components.is_none()
+            && self.configuration.is_empty()
+    }
+
+    /// Renames the variables of all entries as given by `names`.
+    fn rename_variables(&mut self, names: &HashMap<Variable, Variable>) {
+        for (target, _) in self.assignments.iter_mut() {
+            rename_variables(target.variables_mut(), names);
+        }
+        self.configuration = self
+            .configuration
+            .drain()
+            .map(|(var, value)| (names.get(&var).cloned().unwrap_or(var), value))
+            .collect();
+    }
+
+    /// Removes all entries which are identical in all `others`.
+    fn retain_differences(&mut self, others: &[Self]) {
+        self.assignments.retain(|(target, value)| {
+            !others.iter().all(|other| {
+                other.assignments.iter().any(|(other_target, other_value)| {
+                    target == other_target && value == other_value
+                })
+            })
+        });
+        self.effects
+            .retain(|effect| !others.iter().all(|other| other.effects.contains(effect)));
+        if others
+            .iter()
+            .all(|other| other.components == self.components)
+        {
+            self.components = None;
+        }
+        self.configuration.retain(|var, value| {
+            !others
+                .iter()
+                .all(|other| other.configuration.get(var) == Some(value))
+        });
+    }
 }
 
 pub type AnnotatedInstruction = AnnotatedElement<Instruction, Annotation>;
@@ -48,6 +102,42 @@ impl AnnotatedInstruction {
     pub fn instruction(&self) -> &Instruction {
         &self.element
     }
+
+    /// Renames the variables of the instruction and its annotations as given by `names`.
+    pub fn rename_variables(&mut self, names: &HashMap<Variable, Variable>) {
+        rename_variables(self.element.variables_read_mut(), names);
+        rename_variables(self.element.variables_written_mut(), names);
+        for annotation in self.annotations.values_mut() {
+            annotation.rename_variables(names);
+        }
+    }
+
+    /// Removes all annotated values which are identical in all compositions,
+    /// such that only the diverging state remains.
+    pub fn elide_identical_state(&mut self) {
+        if self.annotations.len() < 2 {
+            return;
+        }
+
+        let snapshot: Vec<(Composition, Annotation)> = self
+            .annotations
+            .iter()
+            .map(|(composition, annotation)| (*composition, annotation.clone()))
+            .collect();
+
+        for (composition, annotation) in self.annotations.iter_mut() {
+            let others: Vec<Annotation> = snapshot
+                .iter()
+                .filter(|(other, _)| other != composition)
+                .map(|(_, other)| other.clone())
+                .collect();
+            annotation.retain_differences(&others);
+        }
+
+        if self.annotations.values().all(Annotation::is_empty) {
+            self.annotations.clear();
+        }
+    }
 }
 
 impl fmt::Display for AnnotatedInstruction {
@@ -65,11 +155,23 @@ impl fmt::Display for AnnotatedInstruction {
             for effect in &annotation.effects {
                 writeln!(f, " - {}# {}", composition, effect)?;
             }
+
+            if let Some(components) = &annotation.components {
+                writeln!(f, " - {}% {}", composition, components)?;
+            }
         }
         Ok(())
     }
 }
 
+fn rename_variables(variables: Vec<&mut Variable>, names: &HashMap<Variable, Variable>) {
+    for var in variables {
+        if let Some(name) = names.get(var) {
+            *var = name.clone();
+        }
+    }
+}
+
 fn instantiate_operation(op: &Operation, config: &HashMap<Variable, Constant>) -> Operation {
     let mut instantiated_op = op.clone();
     instantiated_op
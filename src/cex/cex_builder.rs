@@ -1,10 +1,12 @@
 use crate::cex::{
-    AnnotatedBlock, AnnotatedEdge, Composition, ControlFlowGraph, CounterExample, Effect,
+    AnnotatedBlock, AnnotatedEdge, ComponentState, Composition, ControlFlowGraph, CounterExample,
+    Effect, SpeculationWindowUsage,
 };
 use crate::error::Result;
 use crate::expr::{Constant, Expression, Sort, Variable};
 use crate::hir;
 use crate::solver::Model;
+use std::collections::HashMap;
 
 pub fn build_counter_example(
     program: &hir::InlinedProgram,
@@ -14,16 +16,66 @@ pub fn build_counter_example(
 
     let cfg = program.control_flow_graph();
 
-    for &composition in &[Composition::A, Composition::B] {
+    for composition in Composition::all(program.self_compositions()) {
         let trace = extract_trace(cfg, model, composition)?;
         add_trace_info(&mut cex, model, &trace, composition)?;
+        cex.set_speculation_window_usage(composition, speculation_window_usage(cfg, &trace)?);
     }
 
     cex.control_flow_graph_mut().simplify();
+    cex.set_ssa_names(ssa_names(cfg));
 
     Ok(cex)
 }
 
+/// Maps the SSA versions of the program variables back to their original names,
+/// qualified by the address of the defining instruction (e.g. `rax@0x4011F2`).
+///
+/// Versions which aren't defined by an instruction with address (e.g. inputs or phi nodes)
+/// are mapped to their plain name. Names which would be ambiguous aren't mapped at all,
+/// such that those variables keep their SSA identifier.
+fn ssa_names(cfg: &hir::ControlFlowGraph) -> HashMap<Variable, Variable> {
+    let mut definition_addresses: HashMap<&Variable, u64> = HashMap::new();
+    for block in cfg.blocks() {
+        for inst in block.instructions() {
+            if let Some(address) = inst.address() {
+                for var in inst.variables_written() {
+                    definition_addresses.insert(var, address);
+                }
+            }
+        }
+    }
+
+    let mut candidates: HashMap<String, Vec<&Variable>> = HashMap::new();
+    let variables = cfg
+        .blocks()
+        .into_iter()
+        .flat_map(hir::Block::variables)
+        .chain(cfg.edges().into_iter().flat_map(hir::Edge::variables_read));
+    for var in variables.filter(|var| var.version().is_some()) {
+        let name = match definition_addresses.get(var) {
+            Some(address) => format!("{}@0x{:X}", var.name(), address),
+            None => var.name().to_owned(),
+        };
+        let versions = candidates.entry(name).or_default();
+        if !versions.contains(&var) {
+            versions.push(var);
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter_map(|(name, versions)| match versions.as_slice() {
+            [var] => {
+                let mut original = Variable::new(name, var.sort().clone());
+                original.set_rollback_persistent(var.is_rollback_persistent());
+                Some(((*var).clone(), original))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 fn extract_trace(
     cfg: &hir::ControlFlowGraph,
     model: &dyn Model,
@@ -56,6 +108,54 @@ fn extract_trace(
     }
 }
 
+/// Counts the instructions executed by each transient execution of the trace.
+///
+/// A transient execution starts with a speculate edge and ends with a rollback edge.
+/// Nested transient executions share the speculation window with the outer ones,
+/// their instructions are therefore counted for all of them.
+fn speculation_window_usage(
+    cfg: &hir::ControlFlowGraph,
+    trace: &[usize],
+) -> Result<Vec<SpeculationWindowUsage>> {
+    let mut usage = Vec::new();
+    let mut active: Vec<(Option<u64>, usize)> = Vec::new();
+
+    for (position, &index) in trace.iter().enumerate() {
+        if position > 0 {
+            let head = trace[position - 1];
+            let edge = cfg.edge(head, index)?;
+            if edge.labels().is_rollback() {
+                if let Some((location, used)) = active.pop() {
+                    usage.push(SpeculationWindowUsage::new(location, used));
+                }
+            }
+            if edge.labels().is_speculate() {
+                let location = cfg
+                    .block(head)?
+                    .instructions()
+                    .iter()
+                    .rev()
+                    .find_map(hir::Instruction::address);
+                active.push((location, 0));
+            }
+        }
+
+        let block = cfg.block(index)?;
+        if block.is_transient() {
+            let count = block.instruction_count_ignoring_pseudo_instructions();
+            for (_, used) in active.iter_mut() {
+                *used += count;
+            }
+        }
+    }
+
+    while let Some((location, used)) = active.pop() {
+        usage.push(SpeculationWindowUsage::new(location, used));
+    }
+
+    Ok(usage)
+}
+
 fn create_cex_from(program: &hir::InlinedProgram) -> Result<CounterExample> {
     let mut cex_cfg = ControlFlowGraph::new();
 
@@ -80,6 +180,8 @@ fn add_trace_info(
     composition: Composition,
 ) -> Result<()> {
     let cfg = cex.control_flow_graph_mut();
+    // Cache, BTB and PHT are rollback-persistent, their contents therefore accumulate along the trace
+    let mut components = ComponentState::new();
 
     for index in trace {
         let annotated_block = cfg.block_mut(*index)?;
@@ -91,14 +193,19 @@ fn add_trace_info(
         for annotated_inst in annotated_block.block_mut().instructions_mut() {
             let inst = annotated_inst.instruction().clone();
 
-            inst.effects()
+            let effects: Vec<Effect> = inst
+                .effects()
                 .iter()
                 .filter_map(|effect| eval_effect(effect, model, composition))
-                .for_each(|effect| {
-                    annotated_inst
-                        .annotation_mut(composition)
-                        .add_effect(effect);
-                });
+                .collect();
+            if !effects.is_empty() {
+                let annotation = annotated_inst.annotation_mut(composition);
+                for effect in effects {
+                    components.apply(&effect);
+                    annotation.add_effect(effect);
+                }
+                annotation.set_components(components.clone());
+            }
 
             for var in inst.variables_read() {
                 if let Some(value) = var.evaluate(model, composition) {
@@ -131,6 +238,8 @@ fn add_trace_info(
         edge.annotation_mut(composition).mark_as_executed();
     }
 
+    cex.set_components(composition, components);
+
     Ok(())
 }
 
@@ -149,6 +258,9 @@ fn eval_effect(
         hir::Effect::CacheFetch { address, bit_width } => address
             .evaluate(model, composition)
             .map(|address| Effect::cache_fetch(address, *bit_width)),
+        hir::Effect::CacheEvict { address, bit_width } => address
+            .evaluate(model, composition)
+            .map(|address| Effect::cache_evict(address, *bit_width)),
         hir::Effect::BranchTarget { location, target } => {
             match (
                 location.evaluate(model, composition),
@@ -172,6 +284,8 @@ fn eval_effect(
                 _ => None,
             }
         }
+        // The memory dependence predictor isn't observable, its training is therefore not shown
+        hir::Effect::StoreLocation { .. } | hir::Effect::LoadDependence { .. } => None,
     }
 }
 
@@ -207,4 +321,5 @@ fn skip_eval(sort: &Sort) -> bool {
         || sort.is_predictor()
         || sort.is_branch_target_buffer()
         || sort.is_pattern_history_table()
+        || sort.is_memory_dependence_predictor()
 }
@@ -0,0 +1,118 @@
+use crate::cex::Effect;
+use crate::expr::{CacheAddresses, CacheValue};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Contents of the observable microarchitectural components (cache, BTB and PHT)
+/// as far as they are changed by the effects of a counterexample.
+///
+/// The initial contents are unknown, the cache therefore holds the fetched (and not evicted) addresses,
+/// the BTB and PHT hold the trained entries.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComponentState {
+    cache: CacheValue,
+    /// Trained branch targets by branch location
+    branch_targets: BTreeMap<u64, u64>,
+    /// Trained branch conditions (taken/not-taken) by branch location
+    branch_conditions: BTreeMap<u64, bool>,
+}
+
+impl ComponentState {
+    pub fn new() -> Self {
+        Self {
+            cache: CacheValue::empty(),
+            branch_targets: BTreeMap::new(),
+            branch_conditions: BTreeMap::new(),
+        }
+    }
+
+    /// Updates the contents by the given effect.
+    pub fn apply(&mut self, effect: &Effect) {
+        match effect {
+            Effect::CacheFetch { address, bit_width } => {
+                if let Ok(address) = u64::try_from(address) {
+                    for byte in 0..(*bit_width / 8) as u64 {
+                        self.cache.fetch(address.wrapping_add(byte));
+                    }
+                }
+            }
+            Effect::CacheEvict { address, bit_width } => {
+                if let Ok(address) = u64::try_from(address) {
+                    for byte in 0..(*bit_width / 8) as u64 {
+                        self.cache.evict(address.wrapping_add(byte));
+                    }
+                }
+            }
+            Effect::BranchTarget { location, target } => {
+                if let (Ok(location), Ok(target)) = (u64::try_from(location), u64::try_from(target))
+                {
+                    self.branch_targets.insert(location, target);
+                }
+            }
+            Effect::BranchCondition {
+                location,
+                condition,
+            } => {
+                if let (Ok(location), Ok(condition)) =
+                    (u64::try_from(location), bool::try_from(condition))
+                {
+                    self.branch_conditions.insert(location, condition);
+                }
+            }
+        }
+    }
+
+    pub fn cache(&self) -> &CacheValue {
+        &self.cache
+    }
+
+    pub fn branch_targets(&self) -> &BTreeMap<u64, u64> {
+        &self.branch_targets
+    }
+
+    pub fn branch_conditions(&self) -> &BTreeMap<u64, bool> {
+        &self.branch_conditions
+    }
+
+    /// Returns true if no component is changed.
+    pub fn is_empty(&self) -> bool {
+        let cache_is_empty = match self.cache.addresses() {
+            CacheAddresses::FetchedIntoEmptyCache(addresses)
+            | CacheAddresses::EvictedFromFullCache(addresses) => addresses.is_empty(),
+        };
+        cache_is_empty && self.branch_targets.is_empty() && self.branch_conditions.is_empty()
+    }
+}
+
+impl Default for ComponentState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ComponentState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cache {}", self.cache)?;
+        if !self.branch_targets.is_empty() {
+            let targets: Vec<String> = self
+                .branch_targets
+                .iter()
+                .map(|(location, target)| format!("0x{:X} → 0x{:X}", location, target))
+                .collect();
+            write!(f, ", btb {{{}}}", targets.join(", "))?;
+        }
+        if !self.branch_conditions.is_empty() {
+            let conditions: Vec<String> = self
+                .branch_conditions
+                .iter()
+                .map(|(location, taken)| {
+                    let direction = if *taken { "taken" } else { "not taken" };
+                    format!("0x{:X}: {}", location, direction)
+                })
+                .collect();
+            write!(f, ", pht {{{}}}", conditions.join(", "))?;
+        }
+        Ok(())
+    }
+}
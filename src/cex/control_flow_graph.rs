@@ -1,7 +1,9 @@
 use crate::cex::{AnnotatedBlock, AnnotatedEdge};
 use crate::error::Result;
+use crate::expr::Variable;
 use crate::util::RenderGraph;
 use falcon::graph;
+use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Clone, Debug)]
@@ -95,6 +97,23 @@ impl ControlFlowGraph {
             self.remove_block(block_index).unwrap()
         }
     }
+
+    /// Reduces the annotations to the state which differs between the compositions.
+    pub fn elide_identical_state(&mut self) {
+        self.blocks_mut()
+            .into_iter()
+            .for_each(AnnotatedBlock::elide_identical_state);
+    }
+
+    /// Renames the variables of all blocks and edges as given by `names`.
+    pub fn rename_variables(&mut self, names: &HashMap<Variable, Variable>) {
+        for block in self.blocks_mut() {
+            block.rename_variables(names);
+        }
+        for edge in self.edges_mut() {
+            edge.rename_variables(names);
+        }
+    }
 }
 
 impl Default for ControlFlowGraph {
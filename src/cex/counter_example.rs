@@ -1,14 +1,74 @@
-use crate::cex::ControlFlowGraph;
+use crate::cex::{ComponentState, Composition, ControlFlowGraph};
+use crate::expr::Variable;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
+/// Part of the speculation window used by a single transient execution of the counterexample.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SpeculationWindowUsage {
+    /// Address of the speculating instruction, if known.
+    location: Option<u64>,
+    /// Number of (non-pseudo) instructions executed transiently.
+    used: usize,
+}
+
+impl SpeculationWindowUsage {
+    pub fn new(location: Option<u64>, used: usize) -> Self {
+        Self { location, used }
+    }
+
+    pub fn location(&self) -> Option<u64> {
+        self.location
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct CounterExample {
     control_flow_graph: ControlFlowGraph,
+    /// Original (non-SSA) names of the program variables.
+    ssa_names: HashMap<Variable, Variable>,
+    /// Transient executions of each composition in execution order.
+    speculation_window_usage: BTreeMap<Composition, Vec<SpeculationWindowUsage>>,
+    /// Final component contents of each composition.
+    components: BTreeMap<Composition, ComponentState>,
 }
 
 impl CounterExample {
     pub fn new(control_flow_graph: ControlFlowGraph) -> Self {
-        Self { control_flow_graph }
+        Self {
+            control_flow_graph,
+            ssa_names: HashMap::new(),
+            speculation_window_usage: BTreeMap::new(),
+            components: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the speculation window usage of each transient execution in the given composition.
+    pub fn speculation_window_usage(&self, composition: Composition) -> &[SpeculationWindowUsage] {
+        self.speculation_window_usage
+            .get(&composition)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set_speculation_window_usage(
+        &mut self,
+        composition: Composition,
+        usage: Vec<SpeculationWindowUsage>,
+    ) {
+        self.speculation_window_usage.insert(composition, usage);
+    }
+
+    /// Returns the component contents at the end of the given composition.
+    pub fn components(&self, composition: Composition) -> Option<&ComponentState> {
+        self.components.get(&composition)
+    }
+
+    pub fn set_components(&mut self, composition: Composition, components: ComponentState) {
+        self.components.insert(composition, components);
     }
 
     pub fn control_flow_graph(&self) -> &ControlFlowGraph {
@@ -18,6 +78,20 @@ impl CounterExample {
     pub fn control_flow_graph_mut(&mut self) -> &mut ControlFlowGraph {
         &mut self.control_flow_graph
     }
+
+    /// Sets the mapping from SSA variables to their original names.
+    pub fn set_ssa_names(&mut self, ssa_names: HashMap<Variable, Variable>) {
+        self.ssa_names = ssa_names;
+    }
+
+    /// Renames all SSA variables back to their original names for better readability.
+    ///
+    /// Analyses which match variables between the program and the counterexample
+    /// (e.g. `GdbScript` or `LeakReport`) have to be done before.
+    pub fn destruct_ssa(&mut self) {
+        let ssa_names = std::mem::take(&mut self.ssa_names);
+        self.control_flow_graph.rename_variables(&ssa_names);
+    }
 }
 
 impl fmt::Display for CounterExample {
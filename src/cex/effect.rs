@@ -5,6 +5,8 @@ use std::fmt;
 pub enum Effect {
     /// Memory at given address is fetched into the Cache
     CacheFetch { address: Constant, bit_width: usize },
+    /// Memory at given address is evicted from the Cache
+    CacheEvict { address: Constant, bit_width: usize },
     /// Branch target is tracked in the Branch Target Buffer
     BranchTarget {
         location: Constant,
@@ -23,6 +25,11 @@ impl Effect {
         Self::CacheFetch { address, bit_width }
     }
 
+    /// Create a new `Effect::CacheEvict`.
+    pub fn cache_evict(address: Constant, bit_width: usize) -> Self {
+        Self::CacheEvict { address, bit_width }
+    }
+
     /// Create a new `Effect::BranchTarget`.
     pub fn branch_target(location: Constant, target: Constant) -> Self {
         Self::BranchTarget { location, target }
@@ -43,6 +50,9 @@ impl fmt::Display for Effect {
             Self::CacheFetch { address, bit_width } => {
                 write!(f, "cache_fetch({}, {})", address, bit_width)
             }
+            Self::CacheEvict { address, bit_width } => {
+                write!(f, "cache_evict({}, {})", address, bit_width)
+            }
             Self::BranchTarget { location, target } => {
                 write!(f, "branch_target({}, {})", location, target)
             }
@@ -0,0 +1,243 @@
+use crate::cex::{AnnotatedInstruction, Composition, CounterExample};
+use crate::environment::{AddressRange, MemoryRegion, SecurityPolicy};
+use crate::expr::{Constant, Variable};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A GDB script which allows to reproduce the leaking path of a `CounterExample` in a debugger.
+///
+/// The script sets the initial register values of the chosen composition (taken from the model),
+/// places breakpoints at all addresses where the two compositions diverge
+/// and adds watchpoints on all high-security memory regions.
+#[derive(Clone, Debug)]
+pub struct GdbScript {
+    composition: Composition,
+    registers: BTreeMap<String, u64>,
+    breakpoints: BTreeSet<u64>,
+    watchpoints: Vec<AddressRange>,
+}
+
+impl GdbScript {
+    pub fn new(
+        counter_example: &CounterExample,
+        policy: &SecurityPolicy,
+        composition: Composition,
+    ) -> Self {
+        let mut registers = InitialValues::default();
+        let mut breakpoints = BTreeSet::new();
+
+        for block in counter_example.control_flow_graph().blocks() {
+            for inst in block.block().instructions() {
+                if let Some(annotation) = inst.annotation(&composition) {
+                    for (var, value) in annotation.configuration() {
+                        registers.add(var, value);
+                    }
+                }
+
+                if let Some(address) = inst.instruction().address() {
+                    if diverges(inst) {
+                        breakpoints.insert(address);
+                    }
+                }
+            }
+        }
+
+        let mut watchpoints: Vec<AddressRange> = policy
+            .memory
+            .high
+            .iter()
+            .filter_map(MemoryRegion::address_range)
+            .collect();
+        watchpoints.sort_by_key(|range| (range.start(), range.end()));
+
+        Self {
+            composition,
+            registers: registers.into_values(),
+            breakpoints,
+            watchpoints,
+        }
+    }
+
+    /// Returns the composition whose initial state is used by this script.
+    pub fn composition(&self) -> Composition {
+        self.composition
+    }
+
+    /// Returns the addresses at which breakpoints are set.
+    pub fn breakpoints(&self) -> &BTreeSet<u64> {
+        &self.breakpoints
+    }
+}
+
+impl fmt::Display for GdbScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# Generated by SpecBMC (composition {})",
+            self.composition
+        )?;
+        writeln!(f, "set pagination off")?;
+        writeln!(f, "starti")?;
+        writeln!(f)?;
+
+        writeln!(f, "# Initial register values")?;
+        for (register, value) in &self.registers {
+            writeln!(f, "set ${} = 0x{:x}", register, value)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "# Divergence points between compositions")?;
+        for address in &self.breakpoints {
+            writeln!(f, "break *0x{:x}", address)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "# Secret memory regions")?;
+        for range in &self.watchpoints {
+            let size = range.end().saturating_sub(range.start());
+            if size == 0 {
+                continue;
+            }
+            writeln!(
+                f,
+                "watch -location *(unsigned char (*)[{}]) 0x{:x}",
+                size,
+                range.start()
+            )?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "continue")
+    }
+}
+
+/// Collects the value of each register before its first (SSA) definition.
+#[derive(Default)]
+pub(super) struct InitialValues {
+    values: BTreeMap<String, (Option<usize>, u64)>,
+}
+
+impl InitialValues {
+    pub fn add(&mut self, var: &Variable, value: &Constant) {
+        if !is_register(var) {
+            return;
+        }
+
+        let value = match u64::try_from(value) {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+
+        let entry = self
+            .values
+            .entry(var.name().to_owned())
+            .or_insert((var.version(), value));
+        if var.version() < entry.0 {
+            *entry = (var.version(), value);
+        }
+    }
+
+    pub fn into_values(self) -> BTreeMap<String, u64> {
+        self.values
+            .into_iter()
+            .map(|(name, (_, value))| (name, value))
+            .collect()
+    }
+}
+
+/// Internal variables (prefixed with `_`) and lifter temporaries aren't registers.
+fn is_register(var: &Variable) -> bool {
+    !var.name().starts_with('_') && !var.name().starts_with("temp_") && var.sort().is_bit_vector()
+}
+
+/// Returns true if the instruction behaves differently in any of the compositions.
+pub(super) fn diverges(inst: &AnnotatedInstruction) -> bool {
+    let mut effects = inst
+        .annotations()
+        .map(|(_, annotation)| annotation.effects().as_slice());
+
+    match effects.next() {
+        Some(first) => effects.any(|other| other != first),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cex::annotated_block::Block;
+    use crate::cex::{AnnotatedBlock, ControlFlowGraph, Effect};
+    use crate::environment::SecurityLevel;
+    use crate::expr::{BitVector, Sort};
+    use crate::hir::Instruction;
+
+    fn versioned(name: &str, version: usize) -> Variable {
+        let mut var = Variable::new(name, Sort::word());
+        var.set_version(Some(version));
+        var
+    }
+
+    #[test]
+    fn test_initial_value_is_the_value_of_the_first_definition() {
+        // Given:
+        let mut values = InitialValues::default();
+
+        // When:
+        values.add(&versioned("rax", 2), &Constant::bit_vector_u64(2, 64));
+        values.add(&versioned("rax", 1), &Constant::bit_vector_u64(1, 64));
+        values.add(&versioned("rax", 3), &Constant::bit_vector_u64(3, 64));
+        values.add(&versioned("_pc", 0), &Constant::bit_vector_u64(4, 64));
+        values.add(&versioned("temp_0", 0), &Constant::bit_vector_u64(5, 64));
+
+        // Then:
+        let expected: BTreeMap<String, u64> = vec![("rax".to_owned(), 1)].into_iter().collect();
+        assert_eq!(values.into_values(), expected);
+    }
+
+    #[test]
+    fn test_script_breaks_at_divergence_and_watches_secret_memory() {
+        // Given: a load at 0x10 which fetches different addresses in A and B
+        let mut load = Instruction::load(
+            BitVector::word_variable("v"),
+            BitVector::word_variable("x").into(),
+        )
+        .unwrap();
+        load.set_address(Some(0x10));
+        let mut inst = AnnotatedInstruction::new(load);
+        for (composition, address) in vec![(Composition::A, 0x100), (Composition::B, 0x200)] {
+            let annotation = inst.annotation_mut(composition);
+            annotation.add_effect(Effect::cache_fetch(
+                Constant::bit_vector_u64(address, 64),
+                64,
+            ));
+            annotation.add_variable_configuration(
+                versioned("rdi", 0),
+                Constant::bit_vector_u64(address, 64),
+            );
+        }
+        let mut block = Block::new(0);
+        block.add_instructions(inst);
+        let mut cfg = ControlFlowGraph::new();
+        cfg.add_block(AnnotatedBlock::new(block)).unwrap();
+        let cex = CounterExample::new(cfg);
+
+        let mut policy = SecurityPolicy::default();
+        policy.memory.default_level = SecurityLevel::Low;
+        policy
+            .memory
+            .high
+            .insert(MemoryRegion::Range(AddressRange::new(0x200, 0x208)));
+
+        // When:
+        let script = GdbScript::new(&cex, &policy, Composition::B);
+
+        // Then:
+        let breakpoints: BTreeSet<u64> = vec![0x10].into_iter().collect();
+        assert_eq!(script.breakpoints(), &breakpoints);
+        let script = script.to_string();
+        assert!(script.contains("set $rdi = 0x200\n"));
+        assert!(script.contains("break *0x10\n"));
+        assert!(script.contains("watch -location *(unsigned char (*)[8]) 0x200\n"));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::cex::gdb_script::{diverges, InitialValues};
+use crate::cex::{AnnotatedInstruction, Composition, CounterExample};
+use crate::environment::{Environment, MemoryRegion, SecurityLevel, SecurityPolicy, WORD_SIZE};
+use crate::error::Result;
+use crate::expr::{Expression, Fold, Variable};
+use crate::hir::Operation;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+
+/// Register used by the harness to initialize memory.
+const HARNESS_REGISTER: &str = "litmus_value";
+
+/// A litmus test reproducing the leak of a `CounterExample` of a muasm program.
+///
+/// The harness program is the original program prefixed with the initial values of all
+/// public registers and public memory locations (taken from the model),
+/// while secret inputs stay unconstrained such that the harness still leaks when checked again.
+/// The expectation is an environment file which expects the harness to be unsafe
+/// and lists the diverging observations of the counterexample as witness.
+#[derive(Clone, Debug)]
+pub struct LitmusHarness {
+    registers: BTreeMap<String, u64>,
+    memory: BTreeMap<u64, u64>,
+    witness: Vec<Divergence>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Divergence {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<u64>,
+    transient: bool,
+    /// Observations per composition
+    observations: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct Expectation<'a> {
+    expect: &'static str,
+    witness: &'a [Divergence],
+}
+
+impl LitmusHarness {
+    pub fn new(counter_example: &CounterExample, policy: &SecurityPolicy) -> Self {
+        let mut registers: BTreeMap<Composition, InitialValues> = BTreeMap::new();
+        let mut memory: BTreeMap<Composition, BTreeMap<u64, Option<u64>>> = BTreeMap::new();
+        let mut witness = Vec::new();
+
+        for block in counter_example.control_flow_graph().blocks() {
+            for inst in block.block().instructions() {
+                for (composition, annotation) in inst.annotations() {
+                    let initial_values = registers.entry(*composition).or_default();
+                    for (var, value) in annotation.configuration() {
+                        initial_values.add(var, value);
+                    }
+
+                    if block.is_transient() {
+                        continue;
+                    }
+                    if let Some((address, value)) = loaded_value(inst, composition) {
+                        // Loads of the same location with different values are ambiguous
+                        // (e.g. because of preceding stores), such locations are left unconstrained.
+                        memory
+                            .entry(*composition)
+                            .or_default()
+                            .entry(address)
+                            .and_modify(|v| {
+                                if *v != Some(value) {
+                                    *v = None
+                                }
+                            })
+                            .or_insert(Some(value));
+                    }
+                }
+
+                if let Some(divergence) = divergence(inst, block.is_transient()) {
+                    witness.push(divergence);
+                }
+            }
+        }
+
+        let registers: Vec<BTreeMap<String, u64>> = registers
+            .into_iter()
+            .map(|(_, values)| values.into_values())
+            .collect();
+        let public_registers = agreeing_values(&registers)
+            .into_iter()
+            .filter(|(register, _)| is_low_register(register, policy))
+            .collect();
+
+        let memory: Vec<BTreeMap<u64, u64>> = memory
+            .into_iter()
+            .map(|(_, locations)| {
+                locations
+                    .into_iter()
+                    .filter_map(|(address, value)| Some((address, value?)))
+                    .collect()
+            })
+            .collect();
+        let public_memory = agreeing_values(&memory)
+            .into_iter()
+            .filter(|(address, _)| is_low_memory(*address, policy))
+            .collect();
+
+        witness.sort_by_key(|divergence| divergence.address);
+
+        Self {
+            registers: public_registers,
+            memory: public_memory,
+            witness,
+        }
+    }
+
+    /// Returns the harness program, given the source of the original muasm program.
+    pub fn program(&self, source: &str) -> String {
+        let mut program = String::new();
+        program.push_str("% Litmus harness generated by SpecBMC\n");
+        program.push_str("% Expected to be unsafe, diverging observations at:\n");
+        for divergence in &self.witness {
+            let execution = if divergence.transient {
+                "transient"
+            } else {
+                "architectural"
+            };
+            match divergence.address {
+                Some(address) => {
+                    program.push_str(&format!("%   {} ({})\n", address, execution));
+                }
+                None => program.push_str(&format!("%   unknown address ({})\n", execution)),
+            }
+        }
+
+        program.push_str("% Initial public registers\n");
+        for (register, value) in &self.registers {
+            program.push_str(&format!("    {} <- {}\n", register, value));
+        }
+
+        program.push_str("% Initial public memory\n");
+        for (address, value) in &self.memory {
+            program.push_str(&format!("    {} <- {}\n", HARNESS_REGISTER, value));
+            program.push_str(&format!("    store {}, {}\n", HARNESS_REGISTER, address));
+        }
+
+        program.push_str("% Original program\n");
+        program.push_str(source);
+        program
+    }
+
+    /// Returns the expectation, i.e. the given environment extended by the expected verdict
+    /// and the witness of the leak.
+    pub fn expectation(&self, env: &Environment) -> Result<String> {
+        let mut value = serde_yaml::to_value(env)?;
+        let test = serde_yaml::to_value(Expectation {
+            expect: "unsafe",
+            witness: &self.witness,
+        })?;
+        if let Some(mapping) = value.as_mapping_mut() {
+            mapping.insert("test".into(), test);
+        }
+        Ok(serde_yaml::to_string(&value)?)
+    }
+}
+
+/// Returns the address and the value read by the load instruction in the given composition.
+fn loaded_value(inst: &AnnotatedInstruction, composition: &Composition) -> Option<(u64, u64)> {
+    let (variable, address) = match inst.instruction().operation() {
+        Operation::Load {
+            variable, address, ..
+        } => (variable, address),
+        _ => return None,
+    };
+    let annotation = inst.annotation(composition)?;
+
+    let values: HashMap<Variable, Expression> = annotation
+        .configuration()
+        .iter()
+        .map(|(var, value)| {
+            (
+                var.clone(),
+                Expression::constant(value.clone(), var.sort().clone()),
+            )
+        })
+        .collect();
+    let mut address = address.substitute(&values);
+    address.fold();
+    let address = u64::try_from(&address).ok()?;
+
+    let value = annotation
+        .assignments()
+        .iter()
+        .find(|(target, _)| target.variables().contains(&variable))
+        .and_then(|(_, value)| u64::try_from(value).ok())?;
+
+    Some((address, value))
+}
+
+/// Returns the observations of the instruction if they differ between the compositions.
+fn divergence(inst: &AnnotatedInstruction, transient: bool) -> Option<Divergence> {
+    if !diverges(inst) {
+        return None;
+    }
+
+    let observations = inst
+        .annotations()
+        .map(|(composition, annotation)| {
+            let effects = annotation.effects().iter().map(ToString::to_string);
+            (composition.name(), effects.collect())
+        })
+        .collect();
+
+    Some(Divergence {
+        address: inst.instruction().address(),
+        transient,
+        observations,
+    })
+}
+
+/// Returns the entries which are present with the same value in all maps.
+fn agreeing_values<K: Ord + Clone>(maps: &[BTreeMap<K, u64>]) -> BTreeMap<K, u64> {
+    let (first, others) = match maps.split_first() {
+        Some(split) => split,
+        None => return BTreeMap::new(),
+    };
+    first
+        .iter()
+        .filter(|(key, value)| others.iter().all(|other| other.get(key) == Some(value)))
+        .map(|(key, value)| (key.clone(), *value))
+        .collect()
+}
+
+fn is_low_register(register: &str, policy: &SecurityPolicy) -> bool {
+    let registers = &policy.registers;
+    match registers.default_level {
+        SecurityLevel::Low => !registers.high.contains(register),
+        SecurityLevel::High => registers.low.contains(register),
+    }
+}
+
+/// Checks if all bytes of the (muasm) word at the given address have low security.
+fn is_low_memory(address: u64, policy: &SecurityPolicy) -> bool {
+    let memory = &policy.memory;
+    let word_size = (WORD_SIZE / 8) as u64;
+    (address..address.saturating_add(word_size)).all(|byte| match memory.default_level {
+        SecurityLevel::Low => !in_regions(&memory.high, byte),
+        SecurityLevel::High => in_regions(&memory.low, byte),
+    })
+}
+
+fn in_regions(regions: &HashSet<MemoryRegion>, address: u64) -> bool {
+    regions.iter().any(|region| {
+        region.address_range().map_or(false, |range| {
+            range.start() <= address && address < range.end()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cex::annotated_block::Block;
+    use crate::cex::{AnnotatedBlock, ControlFlowGraph, Effect};
+    use crate::environment::AddressRange;
+    use crate::expr::{BitVector, Constant};
+    use crate::hir::Instruction;
+
+    fn load(address: u64) -> AnnotatedInstruction {
+        let mut load = Instruction::load(
+            BitVector::word_variable("v"),
+            BitVector::word_variable("x").into(),
+        )
+        .unwrap();
+        load.set_address(Some(address));
+        AnnotatedInstruction::new(load)
+    }
+
+    fn word(value: u64) -> Constant {
+        Constant::bit_vector_u64(value, 64)
+    }
+
+    /// Architectural load of public memory followed by a transient load of diverging addresses.
+    fn counter_example() -> CounterExample {
+        let mut public_load = load(0x10);
+        for (composition, secret) in vec![(Composition::A, 1), (Composition::B, 2)] {
+            let annotation = public_load.annotation_mut(composition);
+            annotation.add_variable_configuration(BitVector::word_variable("x"), word(0x100));
+            annotation.add_variable_configuration(BitVector::word_variable("y"), word(5));
+            annotation.add_variable_configuration(BitVector::word_variable("s"), word(secret));
+            annotation.add_assignment(BitVector::word_variable("v").into(), word(7));
+        }
+        let mut block0 = Block::new(0);
+        block0.add_instructions(public_load);
+
+        let mut leaking_load = load(0x20);
+        for (composition, address) in vec![(Composition::A, 0x300), (Composition::B, 0x400)] {
+            leaking_load
+                .annotation_mut(composition)
+                .add_effect(Effect::cache_fetch(word(address), 64));
+        }
+        let mut block1 = Block::new(1);
+        block1.set_transient(true);
+        block1.add_instructions(leaking_load);
+
+        let mut cfg = ControlFlowGraph::new();
+        cfg.add_block(AnnotatedBlock::new(block0)).unwrap();
+        cfg.add_block(AnnotatedBlock::new(block1)).unwrap();
+        CounterExample::new(cfg)
+    }
+
+    #[test]
+    fn test_harness_initializes_public_registers_and_memory_only() {
+        // Given: public registers and the word at 0x100 as public memory
+        let cex = counter_example();
+        let mut policy = SecurityPolicy::default();
+        policy.registers.high.insert("x".to_owned());
+        policy
+            .memory
+            .low
+            .insert(MemoryRegion::Range(AddressRange::new(0x100, 0x108)));
+
+        // When:
+        let harness = LitmusHarness::new(&cex, &policy);
+        let program = harness.program("    skip\n");
+
+        // Then: `s` differs between the compositions and `x` is secret, both stay unconstrained
+        assert!(program.contains("    y <- 5\n"));
+        assert!(!program.contains("    s <- "));
+        assert!(!program.contains("    x <- "));
+        assert!(program.contains("    litmus_value <- 7\n    store litmus_value, 256\n"));
+        assert!(program.contains("%   32 (transient)\n"));
+        assert!(program.ends_with("% Original program\n    skip\n"));
+    }
+
+    #[test]
+    fn test_secret_memory_stays_unconstrained() {
+        // Given: the loaded word is only partially public
+        let cex = counter_example();
+        let mut policy = SecurityPolicy::default();
+        policy
+            .memory
+            .low
+            .insert(MemoryRegion::Range(AddressRange::new(0x100, 0x104)));
+
+        // When:
+        let harness = LitmusHarness::new(&cex, &policy);
+
+        // Then:
+        assert!(harness.memory.is_empty());
+        assert_eq!(harness.witness.len(), 1);
+    }
+
+    #[test]
+    fn test_expectation_expects_unsafe_with_witness() {
+        // Given:
+        let harness = LitmusHarness::new(&counter_example(), &SecurityPolicy::default());
+
+        // When:
+        let expectation = harness.expectation(&Environment::default()).unwrap();
+
+        // Then:
+        let value: serde_yaml::Value = serde_yaml::from_str(&expectation).unwrap();
+        assert_eq!(value["test"]["expect"].as_str(), Some("unsafe"));
+        assert_eq!(value["test"]["witness"][0]["address"].as_u64(), Some(0x20));
+        assert_eq!(
+            value["test"]["witness"][0]["transient"].as_bool(),
+            Some(true)
+        );
+    }
+}
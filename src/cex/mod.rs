@@ -5,41 +5,72 @@ mod annotated_block;
 mod annotated_edge;
 mod annotated_instruction;
 mod cex_builder;
+mod component_state;
 mod control_flow_graph;
 mod counter_example;
 mod effect;
+mod gdb_script;
+mod litmus_harness;
+mod report;
+mod stored_counter_example;
+mod timeline;
 
 pub use self::annotated_block::AnnotatedBlock;
 pub use self::annotated_edge::AnnotatedEdge;
 pub use self::annotated_instruction::AnnotatedInstruction;
 pub use self::cex_builder::build_counter_example;
+pub use self::component_state::ComponentState;
 pub use self::control_flow_graph::ControlFlowGraph;
-pub use self::counter_example::CounterExample;
+pub use self::counter_example::{CounterExample, SpeculationWindowUsage};
 pub use self::effect::Effect;
+pub use self::gdb_script::GdbScript;
+pub use self::litmus_harness::LitmusHarness;
+pub use self::report::{Leak, LeakComparison, LeakKind, LeakReport, Severity};
+pub use self::stored_counter_example::StoredCounterExample;
+pub use self::timeline::Timeline;
 
+/// A single execution of a k-way self-composition.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
-pub enum Composition {
-    A = 0,
-    B = 1,
-}
+pub struct Composition(usize);
 
 impl Composition {
+    pub const A: Self = Self(0);
+    pub const B: Self = Self(1);
+
+    const COLORS: [&'static str; 6] = [
+        "#ed403cff",
+        "#0465b2ff",
+        "#2e9e44ff",
+        "#f28e1cff",
+        "#8e44adff",
+        "#17a2b8ff",
+    ];
+
+    pub fn new(number: usize) -> Self {
+        Self(number)
+    }
+
+    /// Returns all compositions of a `count`-way self-composition.
+    pub fn all(count: usize) -> impl Iterator<Item = Self> {
+        (0..count).map(Self)
+    }
+
     pub fn number(self) -> usize {
-        self as usize
+        self.0
     }
 
-    pub fn name(&self) -> &'static str {
-        match self {
-            Self::A => "A",
-            Self::B => "B",
+    /// Compositions are named A, B, C, ..., Z and Z1, Z2, ... afterwards.
+    pub fn name(&self) -> String {
+        const LETTERS: usize = 26;
+        if self.0 < LETTERS {
+            ((b'A' + self.0 as u8) as char).to_string()
+        } else {
+            format!("Z{}", self.0 - LETTERS + 1)
         }
     }
 
     pub fn color(&self) -> &'static str {
-        match self {
-            Self::A => "#ed403cff",
-            Self::B => "#0465b2ff",
-        }
+        Self::COLORS[self.0 % Self::COLORS.len()]
     }
 }
 
@@ -53,7 +84,7 @@ impl fmt::Display for Composition {
 pub struct AnnotatedElement<Element, Annotation> {
     /// The underlying element.
     element: Element,
-    /// Is this edge executed in composition A?
+    /// Annotations per composition.
     annotations: BTreeMap<Composition, Annotation>,
 }
 
@@ -72,4 +103,8 @@ impl<Element, Annotation: Default> AnnotatedElement<Element, Annotation> {
     pub fn annotation_mut(&mut self, composition: Composition) -> &mut Annotation {
         self.annotations.entry(composition).or_default()
     }
+
+    pub fn annotations(&self) -> impl Iterator<Item = (&Composition, &Annotation)> {
+        self.annotations.iter()
+    }
 }
@@ -0,0 +1,619 @@
+//! Classification of the leaks shown by a counterexample.
+//!
+//! Each point at which the compositions diverge is reported as a leak, classified by
+//! whether it occurs during transient or architectural execution, whether the attacker observes
+//! a load address (cache) or the control flow (branch predictors, program counter)
+//! and the secret memory regions whose values flow into the compositions.
+
+use crate::cex::{AnnotatedInstruction, Composition, CounterExample, Effect};
+use crate::environment::{AddressRange, SecurityLevel, SecurityPolicy};
+use crate::error::Result;
+use crate::hir::Operation;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+pub enum Severity {
+    #[serde(rename = "low")]
+    Low,
+    #[serde(rename = "medium")]
+    Medium,
+    #[serde(rename = "high")]
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+pub enum LeakKind {
+    /// The attacker observes a secret-dependent load address (e.g. through the cache).
+    #[serde(rename = "load_address")]
+    LoadAddress,
+    /// The attacker observes secret-dependent control flow (e.g. through branch predictors).
+    #[serde(rename = "control_flow")]
+    ControlFlow,
+}
+
+impl fmt::Display for LeakKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LoadAddress => write!(f, "load-address leak"),
+            Self::ControlFlow => write!(f, "control-flow leak"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize)]
+pub struct Leak {
+    severity: Severity,
+    kind: LeakKind,
+    transient: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<u64>,
+    /// Symbol (function) containing the leaking instruction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<String>,
+}
+
+impl Leak {
+    pub(crate) fn new(kind: LeakKind, transient: bool, address: Option<u64>) -> Self {
+        // Architectural leaks are exploitable without any speculation,
+        // transient cache leaks are the classic Spectre gadgets.
+        let severity = match (transient, kind) {
+            (false, _) => Severity::High,
+            (true, LeakKind::LoadAddress) => Severity::Medium,
+            (true, LeakKind::ControlFlow) => Severity::Low,
+        };
+
+        Self {
+            severity,
+            kind,
+            transient,
+            address,
+            symbol: None,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn kind(&self) -> LeakKind {
+        self.kind
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+
+    pub fn address(&self) -> Option<u64> {
+        self.address
+    }
+
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+
+    /// Checks if both leaks are of the same type and originate from the same source,
+    /// which is the symbol if known for both leaks or the address otherwise.
+    ///
+    /// Matching by symbol allows to compare leaks of programs whose code layout differs.
+    pub fn matches(&self, other: &Self) -> bool {
+        if self.kind != other.kind || self.transient != other.transient {
+            return false;
+        }
+        match (&self.symbol, &other.symbol) {
+            (Some(symbol), Some(other_symbol)) => symbol == other_symbol,
+            _ => self.address == other.address,
+        }
+    }
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let execution = if self.transient {
+            "transient"
+        } else {
+            "architectural"
+        };
+        write!(f, "[{}] {} {}", self.severity, execution, self.kind)?;
+        if let Some(address) = self.address {
+            write!(f, " at 0x{:X}", address)?;
+        }
+        if let Some(symbol) = &self.symbol {
+            write!(f, " in {}", symbol)?;
+        }
+        Ok(())
+    }
+}
+
+/// Leaks of a counterexample, ranked by severity.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LeakReport {
+    leaks: Vec<Leak>,
+    /// Secret memory regions from which diverging values are loaded.
+    secret_sources: BTreeSet<String>,
+}
+
+impl LeakReport {
+    pub fn new(cex: &CounterExample, policy: &SecurityPolicy) -> Self {
+        let cfg = cex.control_flow_graph();
+
+        let mut leaks = HashSet::new();
+        let mut secret_sources = BTreeSet::new();
+
+        for block in cfg.blocks() {
+            let transient = block.is_transient();
+            for inst in block.block().instructions() {
+                let address = inst.instruction().address();
+                for kind in diverging_effects(inst) {
+                    leaks.insert(Leak::new(kind, transient, address));
+                }
+                if let Some(source) = secret_source(inst, policy) {
+                    secret_sources.insert(source);
+                }
+            }
+        }
+
+        // Edges taken by some but not all compositions which reached the branch
+        for edge in cfg.edges() {
+            let taken_by: BTreeSet<&Composition> = edge
+                .annotations()
+                .filter(|(_, annotation)| annotation.executed())
+                .map(|(composition, _)| composition)
+                .collect();
+            if taken_by.is_empty() {
+                continue;
+            }
+
+            let head = match cfg.block(edge.edge().head()) {
+                Ok(head) => head,
+                Err(_) => continue,
+            };
+            let reached_by: BTreeSet<&Composition> = head
+                .annotations()
+                .filter(|(_, annotation)| annotation.executed())
+                .map(|(composition, _)| composition)
+                .collect();
+
+            if taken_by != reached_by {
+                let address = head
+                    .block()
+                    .instructions()
+                    .iter()
+                    .rev()
+                    .find_map(|inst| inst.instruction().address());
+                leaks.insert(Leak::new(
+                    LeakKind::ControlFlow,
+                    head.is_transient(),
+                    address,
+                ));
+            }
+        }
+
+        let mut leaks: Vec<Leak> = leaks.into_iter().collect();
+        leaks.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.address.cmp(&b.address))
+                .then(a.kind.cmp(&b.kind))
+        });
+
+        Self {
+            leaks,
+            secret_sources,
+        }
+    }
+
+    /// Returns the leaks, most severe first.
+    pub fn leaks(&self) -> &[Leak] {
+        &self.leaks
+    }
+
+    pub fn secret_sources(&self) -> &BTreeSet<String> {
+        &self.secret_sources
+    }
+
+    /// Returns the severity of the most severe leak.
+    pub fn severity(&self) -> Option<Severity> {
+        self.leaks.first().map(Leak::severity)
+    }
+
+    /// Resolves the symbols of the leaking instructions (given by address).
+    pub fn resolve_symbols<F>(&mut self, symbol_of: F)
+    where
+        F: Fn(u64) -> Option<String>,
+    {
+        for leak in &mut self.leaks {
+            leak.symbol = leak.address.and_then(&symbol_of);
+        }
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for leak in &self.leaks {
+            writeln!(f, "{}", leak)?;
+        }
+        if !self.secret_sources.is_empty() {
+            let sources: Vec<&str> = self.secret_sources.iter().map(String::as_str).collect();
+            writeln!(f, "Secret sources: {}", sources.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Comparison of the leaks of an original and a hardened program.
+///
+/// A counterexample doesn't necessarily show all leaks of a program, therefore a leak which is only
+/// shown for one of both programs is re-checked in the other program before it is classified.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LeakComparison {
+    /// Leaks of the original program which are re-checked to be gone in the hardened program
+    fixed: Vec<Leak>,
+    /// Leaks which are present in both programs
+    remaining: Vec<Leak>,
+    /// Leaks of the hardened program which are re-checked to be absent in the original program
+    introduced: Vec<Leak>,
+    /// Leaks shown for one program which couldn't be re-checked in the other program
+    not_shown: Vec<Leak>,
+}
+
+impl LeakComparison {
+    /// Compares the leaks of both reports.
+    ///
+    /// `in_hardened` (`in_original`) re-checks whether a leak is present in the hardened (original) program,
+    /// it returns `None` if the leak can't be re-checked, e.g. because its location doesn't exist in the program.
+    pub fn new<H, O>(
+        original: &LeakReport,
+        hardened: &LeakReport,
+        mut in_hardened: H,
+        mut in_original: O,
+    ) -> Result<Self>
+    where
+        H: FnMut(&Leak) -> Result<Option<bool>>,
+        O: FnMut(&Leak) -> Result<Option<bool>>,
+    {
+        let mut comparison = Self::default();
+
+        for leak in original.leaks() {
+            let present = if hardened.leaks().iter().any(|other| leak.matches(other)) {
+                Some(true)
+            } else {
+                in_hardened(leak)?
+            };
+            match present {
+                Some(true) => comparison.remaining.push(leak.clone()),
+                Some(false) => comparison.fixed.push(leak.clone()),
+                None => comparison.not_shown.push(leak.clone()),
+            }
+        }
+
+        for leak in hardened.leaks() {
+            if original.leaks().iter().any(|other| leak.matches(other)) {
+                continue;
+            }
+            match in_original(leak)? {
+                Some(true) => comparison.remaining.push(leak.clone()),
+                Some(false) => comparison.introduced.push(leak.clone()),
+                None => comparison.not_shown.push(leak.clone()),
+            }
+        }
+
+        Ok(comparison)
+    }
+
+    pub fn fixed(&self) -> &[Leak] {
+        &self.fixed
+    }
+
+    pub fn remaining(&self) -> &[Leak] {
+        &self.remaining
+    }
+
+    pub fn introduced(&self) -> &[Leak] {
+        &self.introduced
+    }
+
+    pub fn not_shown(&self) -> &[Leak] {
+        &self.not_shown
+    }
+}
+
+impl fmt::Display for LeakComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for leak in &self.fixed {
+            writeln!(f, "fixed:      {}", leak)?;
+        }
+        for leak in &self.remaining {
+            writeln!(f, "remaining:  {}", leak)?;
+        }
+        for leak in &self.introduced {
+            writeln!(f, "introduced: {}", leak)?;
+        }
+        for leak in &self.not_shown {
+            writeln!(f, "not shown:  {}", leak)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the kinds of the effects which differ between the compositions.
+fn diverging_effects(inst: &AnnotatedInstruction) -> BTreeSet<LeakKind> {
+    let effects: Vec<&Vec<Effect>> = inst
+        .annotations()
+        .map(|(_, annotation)| annotation.effects())
+        .collect();
+
+    let mut kinds = BTreeSet::new();
+    for effect in effects.iter().flat_map(|effects| effects.iter()) {
+        if effects.iter().all(|other| other.contains(effect)) {
+            continue;
+        }
+        kinds.insert(match effect {
+            Effect::CacheFetch { .. } | Effect::CacheEvict { .. } => LeakKind::LoadAddress,
+            Effect::BranchTarget { .. } | Effect::BranchCondition { .. } => LeakKind::ControlFlow,
+        });
+    }
+    kinds
+}
+
+/// Returns the secret memory region the (load) instruction reads diverging values from.
+fn secret_source(inst: &AnnotatedInstruction, policy: &SecurityPolicy) -> Option<String> {
+    let variable = match inst.instruction().operation() {
+        Operation::Load { variable, .. } => variable,
+        _ => return None,
+    };
+
+    let mut values = inst.annotations().filter_map(|(_, annotation)| {
+        annotation
+            .assignments()
+            .iter()
+            .find(|(target, _)| target.variables().contains(&variable))
+            .map(|(_, value)| value)
+    });
+    let first = values.next()?;
+    if values.all(|value| value == first) {
+        return None;
+    }
+
+    let address = inst.annotations().find_map(|(_, annotation)| {
+        annotation.effects().iter().find_map(|effect| match effect {
+            Effect::CacheFetch { address, .. } => u64::try_from(address).ok(),
+            _ => None,
+        })
+    })?;
+
+    secret_region(address, policy)
+}
+
+fn secret_region(address: u64, policy: &SecurityPolicy) -> Option<String> {
+    let contains = |range: &AddressRange| range.start() <= address && address < range.end();
+
+    let memory = &policy.memory;
+    match memory.default_level {
+        SecurityLevel::Low => memory
+            .high
+            .iter()
+            .find(|region| {
+                region
+                    .address_range()
+                    .map_or(false, |range| contains(&range))
+            })
+            .map(ToString::to_string),
+        SecurityLevel::High => {
+            let is_low = memory.low.iter().any(|region| {
+                region
+                    .address_range()
+                    .map_or(false, |range| contains(&range))
+            });
+            if is_low {
+                None
+            } else {
+                Some("default high memory".to_owned())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cex::annotated_block::Block;
+    use crate::cex::{AnnotatedBlock, AnnotatedInstruction, ControlFlowGraph};
+    use crate::environment::{MemoryRegion, MemorySecurityPolicy};
+    use crate::expr::{BitVector, Constant};
+    use crate::hir::Instruction;
+
+    /// Transient `load v, [x]` which loads different values from different addresses in A and B.
+    fn transient_load_counter_example() -> CounterExample {
+        let v = BitVector::word_variable("v");
+        let mut load = Instruction::load(v.clone(), BitVector::word_variable("x").into()).unwrap();
+        load.set_address(Some(0x10));
+
+        let mut inst = AnnotatedInstruction::new(load);
+        for (composition, address, value) in
+            vec![(Composition::A, 0x100, 1), (Composition::B, 0x200, 2)]
+        {
+            let annotation = inst.annotation_mut(composition);
+            annotation.add_effect(Effect::cache_fetch(
+                Constant::bit_vector_u64(address, 64),
+                64,
+            ));
+            annotation.add_assignment(v.clone().into(), Constant::bit_vector_u64(value, 64));
+        }
+
+        let mut block = Block::new(0);
+        block.set_transient(true);
+        block.add_instructions(inst);
+
+        let mut cfg = ControlFlowGraph::new();
+        cfg.add_block(AnnotatedBlock::new(block)).unwrap();
+        CounterExample::new(cfg)
+    }
+
+    #[test]
+    fn test_diverging_transient_load_is_reported_as_medium_load_address_leak() {
+        // Given:
+        let cex = transient_load_counter_example();
+
+        // When:
+        let report = LeakReport::new(&cex, &SecurityPolicy::default());
+
+        // Then:
+        assert_eq!(
+            report.leaks(),
+            &[Leak::new(LeakKind::LoadAddress, true, Some(0x10))]
+        );
+        assert_eq!(report.severity(), Some(Severity::Medium));
+        assert!(report.secret_sources().contains("default high memory"));
+    }
+
+    #[test]
+    fn test_low_memory_is_no_secret_source() {
+        // Given: the loaded addresses are low
+        let cex = transient_load_counter_example();
+        let mut policy = SecurityPolicy::default();
+        policy.memory = MemorySecurityPolicy {
+            default_level: SecurityLevel::Low,
+            low: HashSet::default(),
+            high: HashSet::default(),
+        };
+
+        // When:
+        let report = LeakReport::new(&cex, &policy);
+
+        // Then:
+        assert_eq!(report.leaks().len(), 1);
+        assert!(report.secret_sources().is_empty());
+    }
+
+    #[test]
+    fn test_secret_region_is_the_high_region_containing_the_address() {
+        // Given:
+        let region = MemoryRegion::Range(AddressRange::new(0x100, 0x110));
+        let mut policy = SecurityPolicy::default();
+        policy.memory.default_level = SecurityLevel::Low;
+        policy.memory.high.insert(region.clone());
+
+        // When:
+        let inside = secret_region(0x108, &policy);
+        let outside = secret_region(0x110, &policy);
+
+        // Then:
+        assert_eq!(inside, Some(region.to_string()));
+        assert_eq!(outside, None);
+    }
+
+    #[test]
+    fn test_comparison_matches_leaks_by_symbol_across_layouts() {
+        // Given: the hardened program moved the leaking function
+        let mut original = LeakReport {
+            leaks: vec![
+                Leak::new(LeakKind::LoadAddress, true, Some(0x10)),
+                Leak::new(LeakKind::ControlFlow, true, Some(0x20)),
+            ],
+            secret_sources: BTreeSet::new(),
+        };
+        original.resolve_symbols(|address| Some(format!("f{:X}", address)));
+        let mut hardened = LeakReport {
+            leaks: vec![
+                Leak::new(LeakKind::LoadAddress, true, Some(0x30)),
+                Leak::new(LeakKind::LoadAddress, false, Some(0x40)),
+            ],
+            secret_sources: BTreeSet::new(),
+        };
+        hardened.resolve_symbols(|address| match address {
+            0x30 => Some("f10".to_owned()),
+            _ => None,
+        });
+
+        // When: re-checking confirms the unmatched leaks
+        let comparison = LeakComparison::new(
+            &original,
+            &hardened,
+            |_| Ok(Some(false)),
+            |_| Ok(Some(false)),
+        )
+        .unwrap();
+
+        // Then:
+        assert_eq!(comparison.remaining(), &original.leaks()[..1]);
+        assert_eq!(comparison.fixed(), &original.leaks()[1..]);
+        assert_eq!(comparison.introduced(), &hardened.leaks()[1..]);
+        assert!(comparison.not_shown().is_empty());
+    }
+
+    fn leaks_at(addresses: &[u64]) -> LeakReport {
+        LeakReport {
+            leaks: addresses
+                .iter()
+                .map(|address| Leak::new(LeakKind::LoadAddress, true, Some(*address)))
+                .collect(),
+            secret_sources: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_leak_missing_in_other_counter_example_is_only_fixed_if_rechecked() {
+        // Given: the hardened counterexample only shows the leak at 0x10
+        let original = leaks_at(&[0x10, 0x20, 0x30]);
+        let hardened = leaks_at(&[0x10]);
+
+        // When: 0x20 is still present, 0x30 can't be re-checked
+        let comparison = LeakComparison::new(
+            &original,
+            &hardened,
+            |leak| {
+                Ok(match leak.address() {
+                    Some(0x20) => Some(true),
+                    _ => None,
+                })
+            },
+            |_| unreachable!("all leaks of the hardened program are matched"),
+        )
+        .unwrap();
+
+        // Then:
+        assert_eq!(comparison.remaining(), &original.leaks()[..2]);
+        assert!(comparison.fixed().is_empty());
+        assert_eq!(comparison.not_shown(), &original.leaks()[2..]);
+    }
+
+    #[test]
+    fn test_leak_missing_in_original_counter_example_is_only_introduced_if_rechecked() {
+        // Given:
+        let original = LeakReport::default();
+        let hardened = leaks_at(&[0x10, 0x20, 0x30]);
+
+        // When: 0x10 is present in the original program as well, 0x30 can't be re-checked
+        let comparison = LeakComparison::new(
+            &original,
+            &hardened,
+            |_| unreachable!("the original program has no leaks"),
+            |leak| {
+                Ok(match leak.address() {
+                    Some(0x10) => Some(true),
+                    Some(0x20) => Some(false),
+                    _ => None,
+                })
+            },
+        )
+        .unwrap();
+
+        // Then:
+        assert_eq!(comparison.remaining(), &hardened.leaks()[..1]);
+        assert_eq!(comparison.introduced(), &hardened.leaks()[1..2]);
+        assert_eq!(comparison.not_shown(), &hardened.leaks()[2..]);
+    }
+}
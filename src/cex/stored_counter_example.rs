@@ -0,0 +1,59 @@
+use crate::cex::{build_counter_example, CounterExample};
+use crate::environment::SecurityPolicy;
+use crate::error::Result;
+use crate::hir::{ControlFlowGraph, InlinedProgram};
+use crate::solver::RecordedModel;
+use crate::util::DumpToFile;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// A counterexample stored on disk, such that it can be rendered again later on.
+///
+/// Instead of the counterexample itself, the (transformed) program and the recorded model
+/// are stored, the counterexample is rebuilt from them without solving again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredCounterExample {
+    program: ControlFlowGraph,
+    self_compositions: usize,
+    model: RecordedModel,
+    policy: SecurityPolicy,
+}
+
+impl StoredCounterExample {
+    pub fn new(program: &InlinedProgram, model: RecordedModel, policy: &SecurityPolicy) -> Self {
+        Self {
+            program: program.control_flow_graph().clone(),
+            self_compositions: program.self_compositions(),
+            model,
+            policy: policy.clone(),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|_| {
+            format!(
+                "Counterexample file '{}' could not be loaded",
+                path.display()
+            )
+        })?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn dump_to_file(&self, path: &Path) -> Result<()> {
+        serde_json::to_string(self)?.dump_to_file(path)
+    }
+
+    /// The security policy the counterexample was found with.
+    pub fn policy(&self) -> &SecurityPolicy {
+        &self.policy
+    }
+
+    /// Rebuilds the counterexample from the stored program and model.
+    pub fn counter_example(&self) -> Result<CounterExample> {
+        let mut program = InlinedProgram::new(self.program.clone());
+        program.set_self_compositions(self.self_compositions);
+        build_counter_example(&program, &self.model)
+    }
+}
@@ -0,0 +1,201 @@
+use crate::cex::{ComponentState, Composition, CounterExample, Effect};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A single instruction executed by the counterexample.
+#[derive(Clone, Debug)]
+struct Step {
+    address: Option<u64>,
+    disassembly: Option<String>,
+    operation: String,
+    transient: bool,
+    effects: Vec<Effect>,
+}
+
+/// A linear rendering of a `CounterExample`, listing the executed instructions of each composition
+/// in execution order along with their effects and the final component contents.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    steps: BTreeMap<Composition, Vec<Step>>,
+    components: BTreeMap<Composition, ComponentState>,
+}
+
+impl Timeline {
+    pub fn new(counter_example: &CounterExample) -> Self {
+        let cfg = counter_example.control_flow_graph();
+        // The counterexample CFG is acyclic, the ordering is only missing for broken graphs
+        let ordering = cfg
+            .graph()
+            .compute_topological_ordering()
+            .unwrap_or_default();
+
+        let mut steps: BTreeMap<Composition, Vec<Step>> = BTreeMap::new();
+        for index in ordering {
+            let block = match cfg.block(index) {
+                Ok(block) => block,
+                Err(_) => continue,
+            };
+
+            let executed_in = block
+                .annotations()
+                .filter(|(_, annotation)| annotation.executed())
+                .map(|(composition, _)| *composition);
+            for composition in executed_in {
+                let composition_steps = steps.entry(composition).or_default();
+                let mut last_address = None;
+                for inst in block.block().instructions() {
+                    let instruction = inst.instruction();
+                    let disassembly = instruction
+                        .disassembly()
+                        .filter(|_| instruction.address() != last_address)
+                        .map(String::from);
+                    last_address = instruction.address();
+
+                    composition_steps.push(Step {
+                        address: instruction.address(),
+                        disassembly,
+                        operation: instruction.operation().to_string(),
+                        transient: block.is_transient(),
+                        effects: inst
+                            .annotation(&composition)
+                            .map(|annotation| annotation.effects().clone())
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+        }
+
+        let components = steps
+            .keys()
+            .filter_map(|&composition| {
+                counter_example
+                    .components(composition)
+                    .map(|components| (composition, components.clone()))
+            })
+            .collect();
+
+        Self { steps, components }
+    }
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (composition, steps) in &self.steps {
+            writeln!(f, "Composition {}:", composition)?;
+            for (number, step) in steps.iter().enumerate() {
+                let address = match step.address {
+                    Some(address) => format!("0x{:X}", address),
+                    None => "-".to_owned(),
+                };
+                let marker = if step.transient { "T" } else { " " };
+                write!(
+                    f,
+                    "{:>5} {:>12} {} {}",
+                    number + 1,
+                    address,
+                    marker,
+                    step.operation
+                )?;
+                if let Some(disassembly) = &step.disassembly {
+                    write!(f, "  ; {}", disassembly)?;
+                }
+                if !step.effects.is_empty() {
+                    let effects: Vec<String> = step.effects.iter().map(Effect::to_string).collect();
+                    write!(f, "  # {}", effects.join(", "))?;
+                }
+                writeln!(f)?;
+            }
+            if let Some(components) = self.components.get(composition) {
+                writeln!(f, "Components {}: {}", composition, components)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cex::annotated_block::Block;
+    use crate::cex::{AnnotatedBlock, AnnotatedEdge, AnnotatedInstruction, ControlFlowGraph};
+    use crate::expr::{BitVector, Constant};
+    use crate::hir::{Edge, Instruction};
+
+    fn instruction(address: u64, disassembly: &str) -> AnnotatedInstruction {
+        let mut inst = Instruction::skip();
+        inst.set_address(Some(address));
+        inst.set_disassembly(Some(disassembly.to_owned()));
+        AnnotatedInstruction::new(inst)
+    }
+
+    /// Architectural block 0 executed by A and B, followed by transient block 1 executed by A only.
+    fn counter_example() -> CounterExample {
+        let mut block0 = Block::new(0);
+        block0.add_instructions(instruction(0x10, "cmp x, y"));
+        block0.add_instructions(instruction(0x10, "cmp x, y"));
+        let mut block0 = AnnotatedBlock::new(block0);
+        block0.annotation_mut(Composition::A).mark_as_executed();
+        block0.annotation_mut(Composition::B).mark_as_executed();
+
+        let mut load = AnnotatedInstruction::new(
+            Instruction::load(
+                BitVector::word_variable("v"),
+                BitVector::word_variable("x").into(),
+            )
+            .unwrap(),
+        );
+        load.annotation_mut(Composition::A)
+            .add_effect(Effect::cache_fetch(Constant::bit_vector_u64(0x100, 64), 64));
+        let mut block1 = Block::new(1);
+        block1.set_transient(true);
+        block1.add_instructions(load);
+        let mut block1 = AnnotatedBlock::new(block1);
+        block1.annotation_mut(Composition::A).mark_as_executed();
+
+        let mut cfg = ControlFlowGraph::new();
+        cfg.add_block(block1).unwrap();
+        cfg.add_block(block0).unwrap();
+        cfg.add_edge(AnnotatedEdge::new(Edge::new(0, 1, None)))
+            .unwrap();
+        CounterExample::new(cfg)
+    }
+
+    #[test]
+    fn test_steps_are_listed_in_execution_order_per_composition() {
+        // Given:
+        let cex = counter_example();
+
+        // When:
+        let timeline = Timeline::new(&cex);
+
+        // Then:
+        let steps_a = &timeline.steps[&Composition::A];
+        assert_eq!(
+            steps_a
+                .iter()
+                .map(|step| step.transient)
+                .collect::<Vec<_>>(),
+            vec![false, false, true]
+        );
+        assert_eq!(steps_a[2].effects.len(), 1);
+        assert_eq!(timeline.steps[&Composition::B].len(), 2);
+    }
+
+    #[test]
+    fn test_disassembly_is_shown_once_per_address() {
+        // Given:
+        let cex = counter_example();
+
+        // When:
+        let timeline = Timeline::new(&cex);
+
+        // Then:
+        let disassembly: Vec<Option<&str>> = timeline.steps[&Composition::B]
+            .iter()
+            .map(|step| step.disassembly.as_deref())
+            .collect();
+        assert_eq!(disassembly, vec![Some("cmp x, y"), None]);
+        assert_eq!(timeline.to_string().matches("cmp x, y").count(), 2);
+    }
+}
@@ -0,0 +1,282 @@
+//! Comparison of the leaks of two checked programs (e.g. original vs. hardened program)
+//! or of the same program checked under two observation models.
+//!
+//! A counterexample doesn't necessarily show all leaks of a program, therefore each leak which is only
+//! shown for one of both checks is re-checked in the other one, by checking only the observations at the
+//! location of the leak.
+
+use crate::cex::{self, Leak, LeakComparison, LeakReport};
+use crate::environment::Environment;
+use crate::error::Result;
+use crate::hir::{self, InlinedProgram};
+use crate::pipeline;
+use crate::solver::CheckResult;
+use std::collections::BTreeSet;
+
+/// Runs the whole verification pipeline on `program` without any output.
+///
+/// Returns the leaks of the program (if any), located by the symbols of `symbols`.
+pub fn leak_report(
+    env: &Environment,
+    program: &InlinedProgram,
+    symbols: &hir::Program,
+) -> Result<Option<LeakReport>> {
+    let (program, result) = pipeline::verify(env, program.clone())?;
+
+    match result {
+        CheckResult::AssertionsHold => Ok(None),
+        CheckResult::AssertionViolated { model } => {
+            let counter_example = cex::build_counter_example(&program, model.as_ref())?;
+            let mut report = LeakReport::new(&counter_example, &env.policy);
+            report.resolve_symbols(|address| {
+                symbols.function_containing(address).map(function_symbol)
+            });
+            Ok(Some(report))
+        }
+    }
+}
+
+/// Returns the symbol of `function`, which is its name or `sub_<address>` for unnamed functions.
+fn function_symbol(function: &hir::Function) -> String {
+    match function.name() {
+        Some(name) => name.to_owned(),
+        None => format!("sub_{:X}", function.address()),
+    }
+}
+
+/// A program checked for leaks, along with the environment it has been checked with.
+pub struct CheckedProgram {
+    env: Environment,
+    program: InlinedProgram,
+    symbols: hir::Program,
+    report: Option<LeakReport>,
+}
+
+impl CheckedProgram {
+    /// Checks `program` for leaks, see `leak_report`.
+    pub fn check(env: Environment, program: InlinedProgram, symbols: hir::Program) -> Result<Self> {
+        let report = leak_report(&env, &program, &symbols)?;
+        Ok(Self {
+            env,
+            program,
+            symbols,
+            report,
+        })
+    }
+
+    /// Returns the leaks shown by the counterexample, `None` if the program is safe.
+    pub fn report(&self) -> Option<&LeakReport> {
+        self.report.as_ref()
+    }
+
+    pub fn is_safe(&self) -> bool {
+        self.report.is_none()
+    }
+
+    /// Returns the addresses of this program at which `leak` (of another check) is re-checked.
+    ///
+    /// These are all instructions of the function with the symbol of the leak if it has one,
+    /// or the leaking instruction along with its successor (observing the effects of the leaking instruction).
+    pub fn leak_addresses(&self, leak: &Leak) -> BTreeSet<u64> {
+        let function_addresses: Option<BTreeSet<u64>> = leak.symbol().map(|symbol| {
+            self.symbols
+                .functions()
+                .into_iter()
+                .filter(|function| function_symbol(function) == symbol)
+                .flat_map(|function| function.control_flow_graph().blocks())
+                .flat_map(|block| block.instructions())
+                .filter_map(hir::Instruction::address)
+                .collect()
+        });
+
+        let mut addresses = BTreeSet::new();
+        for block in self.program.control_flow_graph().blocks() {
+            let mut block_addresses: Vec<u64> = block
+                .instructions()
+                .iter()
+                .filter_map(hir::Instruction::address)
+                .collect();
+            block_addresses.dedup();
+
+            for (index, address) in block_addresses.iter().enumerate() {
+                let is_leaking = match &function_addresses {
+                    Some(function_addresses) => function_addresses.contains(address),
+                    None => leak.address() == Some(*address),
+                };
+                if is_leaking {
+                    addresses.insert(*address);
+                    addresses.extend(block_addresses.get(index + 1));
+                }
+            }
+        }
+        addresses
+    }
+
+    /// Re-checks whether `leak` (of another check) is present in this program,
+    /// by checking only the observations at the location of the leak.
+    ///
+    /// Returns `None` if this program has no observation at the location of the leak.
+    pub fn has_leak(&self, leak: &Leak) -> Result<Option<bool>> {
+        if self.is_safe() {
+            return Ok(Some(false));
+        }
+
+        let addresses = self.leak_addresses(leak);
+        if addresses.is_empty() {
+            return Ok(None);
+        }
+
+        let result = pipeline::verify_observations_at(&self.env, self.program.clone(), &addresses)?;
+        Ok(result.map(|result| matches!(result, CheckResult::AssertionViolated { .. })))
+    }
+}
+
+/// Compares the leaks of both checks, leaks shown by one check only are re-checked in the other one.
+pub fn compare(original: &CheckedProgram, hardened: &CheckedProgram) -> Result<LeakComparison> {
+    LeakComparison::new(
+        &original.report.clone().unwrap_or_default(),
+        &hardened.report.clone().unwrap_or_default(),
+        |leak| hardened.has_leak(leak),
+        |leak| original.has_leak(leak),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cex::LeakKind;
+    use crate::pipeline::Stage;
+
+    fn load(env: &Environment, source: &str) -> (InlinedProgram, hir::Program) {
+        let program = pipeline::LoadMuasm::new().run(env, source).unwrap();
+        let inlined_program = pipeline::Inline::new().run(env, &program).unwrap();
+        (inlined_program, program)
+    }
+
+    fn leaky(env: Environment, source: &str) -> CheckedProgram {
+        let (program, symbols) = load(&env, source);
+        CheckedProgram {
+            env,
+            program,
+            symbols,
+            report: Some(LeakReport::default()),
+        }
+    }
+
+    #[test]
+    fn test_leak_is_rechecked_at_its_instruction_and_successor() {
+        // Given:
+        let checked = leaky(Environment::default(), "    x <- 1\n    load y, x\n");
+        let addresses: Vec<u64> = checked
+            .program
+            .control_flow_graph()
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions())
+            .filter_map(hir::Instruction::address)
+            .collect();
+        let (first, last) = (addresses[0], addresses[addresses.len() - 1]);
+
+        // When:
+        let leak_at = |address| {
+            checked.leak_addresses(&Leak::new(LeakKind::LoadAddress, true, Some(address)))
+        };
+
+        // Then:
+        assert_eq!(leak_at(first), vec![first, last].into_iter().collect());
+        assert_eq!(leak_at(last), vec![last].into_iter().collect());
+        assert!(leak_at(0x4200).is_empty());
+    }
+
+    #[test]
+    fn test_leak_at_unknown_location_can_not_be_rechecked() {
+        // Given:
+        let checked = leaky(Environment::default(), "    x <- 1\n    load y, x\n");
+
+        // When:
+        let has_leak = checked
+            .has_leak(&Leak::new(LeakKind::LoadAddress, true, Some(0x4200)))
+            .unwrap();
+
+        // Then:
+        assert_eq!(has_leak, None);
+    }
+
+    #[test]
+    fn test_safe_program_has_no_leak() {
+        // Given:
+        let mut checked = leaky(Environment::default(), "    x <- 1\n    load y, x\n");
+        checked.report = None;
+
+        // When:
+        let has_leak = checked
+            .has_leak(&Leak::new(LeakKind::LoadAddress, true, Some(0x4200)))
+            .unwrap();
+
+        // Then: nothing needs to be re-checked
+        assert_eq!(has_leak, Some(false));
+    }
+
+    #[cfg(feature = "z3")]
+    fn check_models(source: &str) -> (CheckedProgram, CheckedProgram) {
+        let mut env = Environment::default();
+        env.solver = crate::environment::Solver::Z3;
+        env.solver_backend = crate::environment::SolverBackend::Native;
+        env.architecture.branch_target_buffer = false;
+        env.architecture.pattern_history_table = false;
+        let (program, symbols) = load(&env, source);
+
+        let check = |model| {
+            let mut env = env.clone();
+            env.analysis.model = model;
+            CheckedProgram::check(env, program.clone(), symbols.clone()).unwrap()
+        };
+        (
+            check(crate::environment::Model::Components),
+            check(crate::environment::Model::ProgramCounter),
+        )
+    }
+
+    #[cfg(feature = "z3")]
+    const SPECTRE_V1: &str = "    cond <- x < array1_size
+    beqz cond, EndIf
+Then:
+    load v, array1 + x
+EndIf:
+    load tmp, array2 + v << 8
+";
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_both_models_find_spectre_v1() {
+        // Given:
+        let (components, pc) = check_models(SPECTRE_V1);
+
+        // When:
+        let comparison = compare(&components, &pc).unwrap();
+
+        // Then:
+        assert!(!components.is_safe());
+        assert!(!pc.is_safe());
+        assert!(!comparison.remaining().is_empty() || !comparison.not_shown().is_empty());
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_models_agree_on_fenced_spectre_v1() {
+        // Given:
+        let fenced = SPECTRE_V1.replace("EndIf:\n", "EndIf:\n    spbarr\n");
+        let (components, pc) = check_models(&fenced);
+
+        // When:
+        let comparison = compare(&components, &pc).unwrap();
+
+        // Then:
+        assert!(components.is_safe());
+        assert!(pc.is_safe());
+        assert!(comparison.remaining().is_empty());
+        assert!(comparison.fixed().is_empty());
+        assert!(comparison.introduced().is_empty());
+        assert!(comparison.not_shown().is_empty());
+    }
+}
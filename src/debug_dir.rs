@@ -0,0 +1,96 @@
+//! Debug directory (`--debug-dir`): collects the artifacts of all pipeline stages.
+//!
+//! Each run gets its own timestamped sub-directory, holding the CFG after each HIR transformation,
+//! the MIR, the LIR, the SMT formula and an `index.html` for navigating between them.
+
+use specbmc::error::Result;
+use specbmc::util::DumpToFile;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX_FILE: &str = "index.html";
+
+/// Sections of the index, given by the prefix of the artifact file names (in pipeline order).
+const SECTIONS: &[(&str, &str)] = &[
+    ("environment", "Environment"),
+    ("hir_", "HIR transformations"),
+    ("program.hir", "Transformed HIR"),
+    ("mir", "MIR"),
+    ("lir", "LIR"),
+    ("formula", "SMT formula"),
+];
+
+#[derive(Clone, Debug)]
+pub struct DebugDir {
+    path: PathBuf,
+}
+
+impl DebugDir {
+    /// Creates a new timestamped directory within `parent`.
+    pub fn create(parent: &Path) -> Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        fs::create_dir_all(parent)?;
+        let mut path = parent.join(format!("run-{}", timestamp));
+        let mut attempt = 1;
+        while path.exists() {
+            attempt += 1;
+            path = parent.join(format!("run-{}-{}", timestamp, attempt));
+        }
+        fs::create_dir(&path)?;
+
+        let debug_dir = Self { path };
+        debug_dir.update_index()?;
+        Ok(debug_dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of the artifact with the given file name.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Returns the file name of the CFG after the `step`-th HIR transformation.
+    pub fn hir_file_name(step: usize, transformation: &str) -> String {
+        format!("hir_{:02}_{}.dot", step, transformation)
+    }
+
+    /// Rewrites the index, such that it lists all artifacts written so far.
+    pub fn update_index(&self) -> Result<()> {
+        let mut files: Vec<String> = fs::read_dir(&self.path)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name != INDEX_FILE)
+            .collect();
+        files.sort();
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>SpecBMC Debug Artifacts</title>\n</head>\n<body>\n");
+        html.push_str("<h1>SpecBMC Debug Artifacts</h1>\n");
+        for (prefix, title) in SECTIONS {
+            let section: Vec<&String> = files
+                .iter()
+                .filter(|name| name.starts_with(prefix))
+                .collect();
+            if section.is_empty() {
+                continue;
+            }
+            html.push_str(&format!("<h2>{}</h2>\n<ul>\n", title));
+            for name in section {
+                html.push_str(&format!("<li><a href=\"{0}\">{0}</a></li>\n", name));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+
+        html.dump_to_file(&self.file(INDEX_FILE))
+    }
+}
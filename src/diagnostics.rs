@@ -0,0 +1,304 @@
+//! Structured diagnostics (warnings and notes) collected during the analysis.
+//!
+//! Diagnostics are reported from anywhere in the pipeline via `report`
+//! and collected by the driver via `take`.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Severity {
+    #[serde(rename = "note")]
+    Note,
+    #[serde(rename = "warning")]
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Note => write!(f, "note"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Code {
+    /// The control-flow graph is not reducible, loop unwinding may be unsound.
+    #[serde(rename = "W0001")]
+    IrreducibleControlFlowGraph,
+    /// A function could not be lifted and is missing in the program.
+    #[serde(rename = "W0002")]
+    LiftingFailed,
+    /// The binary has no valid program entry.
+    #[serde(rename = "W0003")]
+    MissingProgramEntry,
+    /// The inferred trip count of a loop exceeds the default unwinding bound.
+    #[serde(rename = "W0004")]
+    UnwindingBoundTooSmall,
+    /// An instruction is not supported by the lifter, its effects are over-approximated.
+    #[serde(rename = "W0005")]
+    UnsupportedInstruction,
+}
+
+impl Code {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::IrreducibleControlFlowGraph => "W0001",
+            Self::LiftingFailed => "W0002",
+            Self::MissingProgramEntry => "W0003",
+            Self::UnwindingBoundTooSmall => "W0004",
+            Self::UnsupportedInstruction => "W0005",
+        }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+/// The program location a `Diagnostic` refers to.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(function) = &self.function {
+            parts.push(format!("function {}", function));
+        }
+        if let Some(address) = self.address {
+            parts.push(format!("address 0x{:X}", address));
+        }
+        if let Some(block) = self.block {
+            parts.push(format!("block 0x{:X}", block));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    code: Code,
+    severity: Severity,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+impl Diagnostic {
+    pub fn new(code: Code, severity: Severity, message: String) -> Self {
+        Self {
+            code,
+            severity,
+            message,
+            location: None,
+        }
+    }
+
+    /// Create a new `Diagnostic` with `Severity::Warning`.
+    pub fn warning<S: Into<String>>(code: Code, message: S) -> Self {
+        Self::new(code, Severity::Warning, message.into())
+    }
+
+    /// Create a new `Diagnostic` with `Severity::Note`.
+    pub fn note<S: Into<String>>(code: Code, message: S) -> Self {
+        Self::new(code, Severity::Note, message.into())
+    }
+
+    /// Attaches the given location to this `Diagnostic`.
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " (at {})", location)?;
+        }
+        Ok(())
+    }
+}
+
+// Diagnostics are reported deep within the pipeline (loader, transformations),
+// collecting them thread-locally avoids passing a sink through every API.
+thread_local!(static DIAGNOSTICS: RefCell<Vec<Diagnostic>> = RefCell::new(Vec::new()));
+
+/// Reports the given `Diagnostic`.
+pub fn report(diagnostic: Diagnostic) {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.borrow_mut().push(diagnostic));
+}
+
+/// Takes all diagnostics reported so far.
+pub fn take() -> Vec<Diagnostic> {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.borrow_mut().drain(..).collect())
+}
+
+/// Serializes the given diagnostics into the JSON format of the diagnostics file.
+pub fn to_json(diagnostics: &[Diagnostic]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(diagnostics)?)
+}
+
+/// Fails if any of the given diagnostics is a warning (`--deny-warnings`).
+pub fn deny_warnings(diagnostics: &[Diagnostic]) -> Result<()> {
+    if diagnostics.iter().any(Diagnostic::is_warning) {
+        return Err("Aborting due to warnings (--deny-warnings)".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reported_diagnostics_are_taken_once() {
+        // Given:
+        take();
+        report(Diagnostic::note(Code::LiftingFailed, "a"));
+        report(Diagnostic::warning(Code::MissingProgramEntry, "b"));
+
+        // When:
+        let taken = take();
+
+        // Then:
+        assert_eq!(
+            taken,
+            vec![
+                Diagnostic::note(Code::LiftingFailed, "a"),
+                Diagnostic::warning(Code::MissingProgramEntry, "b"),
+            ]
+        );
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn test_deny_warnings_turns_warnings_into_an_error() {
+        // Given:
+        let warning = Diagnostic::warning(Code::UnsupportedInstruction, "unsupported");
+        let note = Diagnostic::note(Code::UnwindingBoundTooSmall, "too small");
+
+        // When:
+        let with_warning = deny_warnings(&[note.clone(), warning]);
+        let notes_only = deny_warnings(&[note]);
+
+        // Then:
+        assert_eq!(
+            with_warning.unwrap_err().to_string(),
+            "Aborting due to warnings (--deny-warnings)"
+        );
+        assert!(notes_only.is_ok());
+        assert!(deny_warnings(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_diagnostics_are_emitted_as_json() {
+        // Given:
+        let diagnostics = vec![
+            Diagnostic::warning(
+                Code::IrreducibleControlFlowGraph,
+                "CFG is not reducible, loop unwinding may be unsound",
+            )
+            .at(Location {
+                block: Some(3),
+                ..Location::default()
+            }),
+            Diagnostic::note(Code::UnwindingBoundTooSmall, "Loop 0x10 needs k=4"),
+        ];
+
+        // When:
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&diagnostics).unwrap()).unwrap();
+
+        // Then: codes are emitted by their ID, absent location fields are omitted
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "code": "W0001",
+                    "severity": "warning",
+                    "message": "CFG is not reducible, loop unwinding may be unsound",
+                    "location": { "block": 3 }
+                },
+                {
+                    "code": "W0004",
+                    "severity": "note",
+                    "message": "Loop 0x10 needs k=4"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_emitted_json_can_be_read_back() {
+        // Given:
+        let diagnostics = vec![
+            Diagnostic::warning(Code::LiftingFailed, "lifting failed").at(Location {
+                function: Some("main".to_owned()),
+                address: Some(0x401000),
+                block: None,
+            }),
+        ];
+
+        // When:
+        let json = to_json(&diagnostics).unwrap();
+
+        // Then:
+        let read_back: Vec<Diagnostic> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read_back, diagnostics);
+    }
+
+    #[test]
+    fn test_diagnostic_is_displayed_with_its_location() {
+        // Given:
+        let diagnostic = Diagnostic::warning(Code::LiftingFailed, "lifting failed").at(Location {
+            function: Some("main".to_owned()),
+            address: Some(0x401000),
+            block: None,
+        });
+
+        // When:
+        let displayed = diagnostic.to_string();
+
+        // Then:
+        assert_eq!(
+            displayed,
+            "warning[W0002]: lifting failed (at function main, address 0x401000)"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Self-test of the installation (`specbmc doctor`).
+//!
+//! Probes all supported solvers and the lifter, such that setup problems are reported upfront
+//! instead of surfacing as solver errors in the middle of an analysis.
+
+use colored::*;
+use specbmc::environment::{Environment, Solver, SolverBackend};
+use specbmc::error::Result;
+use specbmc::loader;
+use specbmc::solver::{self, SolverFeatures};
+
+const SOLVERS: &[(Solver, &str)] = &[
+    (Solver::Z3, "z3"),
+    (Solver::CVC4, "cvc4"),
+    (Solver::Yices2, "yices2"),
+];
+
+/// Returns the observation modes which can be used with a solver of the given features.
+fn usable_observations(features: &SolverFeatures) -> Vec<&'static str> {
+    let mut observations = vec!["sequential", "parallel", "full"];
+    if features.lists {
+        observations.push("trace");
+    } else {
+        observations.push("trace (bounded_trace only)");
+    }
+    observations
+}
+
+/// Checks the solvers and the lifter, fails if the solver configured in `env` or the lifter isn't usable.
+pub fn run(env: &Environment) -> Result<()> {
+    let mut configured_solver_usable = false;
+
+    println!("{}", "Solvers:".bold());
+    for &(solver, name) in SOLVERS {
+        let configured = if solver == env.solver {
+            " (configured)"
+        } else {
+            ""
+        };
+        match solver::probe_solver(solver) {
+            Ok(features) if features.bit_vector_arrays => {
+                println!(
+                    "  {} {}{}: {}",
+                    "ok".green(),
+                    name,
+                    configured,
+                    features.version
+                );
+                println!(
+                    "     datatypes: {}, lists: {}",
+                    yes_no(features.datatypes),
+                    yes_no(features.lists)
+                );
+                println!("     models: components, pc");
+                println!(
+                    "     observe: {}",
+                    usable_observations(&features).join(", ")
+                );
+                configured_solver_usable |= solver == env.solver;
+            }
+            Ok(features) => println!(
+                "  {} {}{}: {} doesn't support arrays of bit-vectors (QF_AUFBV)",
+                "error".red(),
+                name,
+                configured,
+                features.version
+            ),
+            Err(e) => println!(
+                "  {} {}{}: not usable ({})",
+                "error".red(),
+                name,
+                configured,
+                e
+            ),
+        }
+    }
+
+    let native = if cfg!(feature = "z3") {
+        "available".green()
+    } else {
+        "not available (compiled without feature 'z3')".yellow()
+    };
+    println!("  native z3 backend: {}", native);
+
+    println!("{}", "Lifter:".bold());
+    let lifter_usable = match loader::check_lifter() {
+        Ok(()) => {
+            println!("  {} lifted built-in x86-64 sample", "ok".green());
+            true
+        }
+        Err(e) => {
+            println!("  {} {}", "error".red(), e);
+            false
+        }
+    };
+
+    if env.solver_backend == SolverBackend::Process && !configured_solver_usable {
+        return Err(format!("The configured solver {} isn't usable", env.solver).into());
+    }
+    if !lifter_usable {
+        return Err("The lifter isn't usable".into());
+    }
+
+    Ok(())
+}
+
+fn yes_no(supported: bool) -> ColoredString {
+    if supported {
+        "yes".green()
+    } else {
+        "no".yellow()
+    }
+}
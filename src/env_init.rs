@@ -0,0 +1,138 @@
+//! Starter environment files generated from the assembly info of a binary (`specbmc env init`).
+//!
+//! The starter environment only sets the options which depend on the binary,
+//! policy templates are commented out as they require knowledge about the program.
+
+use specbmc::loader::AssemblyInfo;
+use std::fmt::Write;
+
+/// Preferred program entry if the binary contains a function with this name.
+const PREFERRED_ENTRY: &str = "main";
+
+/// Returns the suggested program entry, which is `main` if it exists
+/// and the (name of the) function at the entry point of the binary otherwise.
+fn suggested_entry(info: &AssemblyInfo) -> String {
+    if info
+        .functions
+        .iter()
+        .any(|f| f.name.as_deref() == Some(PREFERRED_ENTRY))
+    {
+        return PREFERRED_ENTRY.to_owned();
+    }
+
+    info.functions
+        .iter()
+        .find(|f| f.address == info.entry)
+        .and_then(|f| f.name.clone())
+        .unwrap_or_else(|| format!("0x{:X}", info.entry))
+}
+
+/// Returns the starter environment (YAML) for the binary `program` described by `info`.
+pub fn starter_environment(program: &str, info: &AssemblyInfo) -> String {
+    let entry = suggested_entry(info);
+    let mut yaml = String::new();
+
+    // Writing into a string can't fail
+    let mut line = |s: String| writeln!(yaml, "{}", s).unwrap();
+
+    line(format!(
+        "# Starter environment for '{}' (generated by `specbmc env init`)",
+        program
+    ));
+    line("# See README for all available options and their default values.".into());
+    line(String::new());
+
+    line("analysis:".into());
+    line(format!(
+        "  # Entry point of the binary is 0x{:X}, suggested entry: {}",
+        info.entry, entry
+    ));
+    line(format!("  program_entry: {:?}", entry));
+    let other_functions: Vec<&str> = info
+        .functions
+        .iter()
+        .filter_map(|f| f.name.as_deref())
+        .filter(|&name| name != entry)
+        .collect();
+    if !other_functions.is_empty() {
+        line(
+            "  # Other functions of the binary (alternative entries or not to be inlined):".into(),
+        );
+        line("  # inline_ignore:".into());
+        for name in other_functions {
+            line(format!("  #   - {:?}", name));
+        }
+    }
+    line(String::new());
+
+    line("architecture:".into());
+    line("  speculation_window: 100".into());
+    line(String::new());
+
+    line("policy:".into());
+    line("  registers:".into());
+    line("    default: low".into());
+    line("    # Registers holding secrets, e.g. the arguments of the entry function:".into());
+    line("    # high: [\"rdi\", \"rsi\"]".into());
+    line("  memory:".into());
+    line("    default: high".into());
+    line("    # Public memory locations:".into());
+    line("    # low:".into());
+    if info.data_symbols.is_empty() {
+        let (start, end) = info.memory_sections.first().map_or((0, 0), |section| {
+            (section.start_address, section.end_address)
+        });
+        line(format!("    #   - start: 0x{:X}", start));
+        line(format!("    #     end: 0x{:X}", end));
+    } else {
+        for symbol in &info.data_symbols {
+            line(format!(
+                "    #   - symbol: {} # 0x{:X} ({} bytes)",
+                symbol.name, symbol.address, symbol.size
+            ));
+        }
+    }
+
+    yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specbmc::environment::Environment;
+    use specbmc::loader::{FunctionInfo, SymbolInfo};
+    use std::path::Path;
+
+    #[test]
+    fn test_starter_environment_is_valid_and_suggests_main() {
+        // Given:
+        let info = AssemblyInfo {
+            entry: 0x1000,
+            functions: vec![
+                FunctionInfo {
+                    address: 0x1000,
+                    name: Some("_start".to_owned()),
+                },
+                FunctionInfo {
+                    address: 0x1100,
+                    name: Some("main".to_owned()),
+                },
+            ],
+            memory_sections: Vec::new(),
+            data_symbols: vec![SymbolInfo {
+                name: "secret_key".to_owned(),
+                address: 0x2000,
+                size: 32,
+            }],
+        };
+
+        // When:
+        let yaml = starter_environment("example.o", &info);
+
+        // Then:
+        let env = Environment::from_yaml(&yaml, Path::new("example.yaml")).unwrap();
+        assert_eq!(env.analysis.program_entry, Some("main".to_owned()));
+        assert!(yaml.contains("#   - \"_start\""));
+        assert!(yaml.contains("#   - symbol: secret_key # 0x2000 (32 bytes)"));
+    }
+}
@@ -1,7 +1,7 @@
-use crate::error::Result;
-use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
@@ -9,6 +9,7 @@ use std::path::Path;
 
 pub const SPECULATION_WINDOW_SIZE: usize = 10;
 pub const WORD_SIZE: usize = 64;
+pub const PAGE_SIZE: u64 = 4096;
 
 pub const STACK_POINTER: &str = "rsp";
 pub const BASE_POINTER: &str = "rbp";
@@ -55,6 +56,62 @@ impl fmt::Display for Solver {
     }
 }
 
+/// Value of a solver option, e.g. `7` for `smt.random_seed`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SolverOptionValue {
+    Boolean(bool),
+    Integer(u64),
+    Text(String),
+}
+
+impl fmt::Display for SolverOptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boolean(value) => write!(f, "{}", value),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Options passed to the solver when it is configured (e.g. random seeds), given per solver.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolverOptions {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub z3: BTreeMap<String, SolverOptionValue>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cvc4: BTreeMap<String, SolverOptionValue>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub yices2: BTreeMap<String, SolverOptionValue>,
+}
+
+impl SolverOptions {
+    /// Returns the options of the given solver.
+    pub fn of(&self, solver: Solver) -> &BTreeMap<String, SolverOptionValue> {
+        match solver {
+            Solver::Z3 => &self.z3,
+            Solver::CVC4 => &self.cvc4,
+            Solver::Yices2 => &self.yices2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SolverBackend {
+    #[serde(rename = "process")]
+    Process, // Communicate with the solver process via SMT-LIB
+    #[serde(rename = "native")]
+    Native, // Use the native solver API (only Z3, requires feature `z3`)
+}
+
+impl Default for SolverBackend {
+    fn default() -> Self {
+        Self::Process
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Check {
     #[serde(rename = "only_transient_leaks")]
@@ -63,6 +120,10 @@ pub enum Check {
     OnlyNormalExecutionLeaks,
     #[serde(rename = "all_leaks")]
     AllLeaks,
+    #[serde(rename = "speculative_memory_safety")]
+    SpeculativeMemorySafety, // Transient loads must not access memory outside of `valid_memory`
+    #[serde(rename = "speculative_interference")]
+    SpeculativeInterference, // Transient leaks incl. the (secret-dependent) point in time at which speculation resolves
 }
 
 impl Default for Check {
@@ -117,12 +178,14 @@ impl Default for Observe {
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Model {
     #[serde(rename = "components")]
     Components, // Components model: Observe Observe microarchitectual components like cache, branch-target buffer, ...
     #[serde(rename = "pc")]
     ProgramCounter, // PC model: Observe program counter and memory loads
+    #[serde(rename = "custom")]
+    Custom(String), // Custom model: Passed by library code via `pipeline::HirTransform::with_observation_model`
 }
 
 impl Default for Model {
@@ -131,36 +194,329 @@ impl Default for Model {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Compatibility {
+    #[serde(rename = "none")]
+    None, // Use the analysis and architecture settings as given
+    #[serde(rename = "spectector")]
+    Spectector, // Observation semantics of Spectector (speculative non-interference of muasm programs)
+}
+
+impl Default for Compatibility {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CallingConvention {
+    #[serde(rename = "system_v")]
+    SystemV, // System V AMD64 ABI (Linux, BSD, macOS)
+    #[serde(rename = "microsoft")]
+    Microsoft, // Microsoft x64 calling convention (Windows)
+    #[serde(rename = "aapcs64")]
+    Aapcs64, // Procedure Call Standard for the Arm 64-bit Architecture
+}
+
+impl CallingConvention {
+    /// Registers which are not preserved across function calls.
+    pub fn caller_saved_registers(&self) -> &'static [&'static str] {
+        match self {
+            Self::SystemV => &["rax", "rcx", "rdx", "rsi", "rdi", "r8", "r9", "r10", "r11"],
+            Self::Microsoft => &["rax", "rcx", "rdx", "r8", "r9", "r10", "r11"],
+            Self::Aapcs64 => &[
+                "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12",
+                "x13", "x14", "x15", "x16", "x17", "x30",
+            ],
+        }
+    }
+
+    /// Registers holding the first (integer or pointer) arguments, in argument order.
+    pub fn argument_registers(&self) -> &'static [&'static str] {
+        match self {
+            Self::SystemV => &["rdi", "rsi", "rdx", "rcx", "r8", "r9"],
+            Self::Microsoft => &["rcx", "rdx", "r8", "r9"],
+            Self::Aapcs64 => &["x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7"],
+        }
+    }
+
+    /// Register holding the (integer or pointer) return value.
+    pub fn return_register(&self) -> &'static str {
+        match self {
+            Self::SystemV | Self::Microsoft => "rax",
+            Self::Aapcs64 => "x0",
+        }
+    }
+
+    pub fn stack_pointer(&self) -> &'static str {
+        match self {
+            Self::SystemV | Self::Microsoft => STACK_POINTER,
+            Self::Aapcs64 => "sp",
+        }
+    }
+
+    /// Offset (in bytes) of the stack slot of the `index`-th argument relative to the stack pointer
+    /// at function entry, or `None` if the argument is passed in a register.
+    pub fn stack_argument_offset(&self, index: usize) -> Option<u64> {
+        let slot = index.checked_sub(self.argument_registers().len())? as u64;
+        let first_slot = match self {
+            // Skip the return address
+            Self::SystemV => 8,
+            // Skip the return address and the shadow space of the register arguments
+            Self::Microsoft => 8 + 32,
+            // The return address is passed in the link register
+            Self::Aapcs64 => 0,
+        };
+        Some(first_slot + slot * (WORD_SIZE / 8) as u64)
+    }
+}
+
+impl Default for CallingConvention {
+    fn default() -> Self {
+        Self::SystemV
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExternalCallPolicy {
+    #[serde(rename = "ignore")]
+    Ignore, // Keep the call as it is (callee has no effect)
+    #[serde(rename = "error")]
+    Error, // Abort analysis
+    #[serde(rename = "havoc_registers")]
+    HavocRegisters, // Havoc caller-saved registers
+    #[serde(rename = "havoc_memory")]
+    HavocMemory, // Havoc caller-saved registers and external call memory
+}
+
+impl Default for ExternalCallPolicy {
+    fn default() -> Self {
+        Self::HavocRegisters
+    }
+}
+
+/// Constraints on the speculation decisions of the (attacker-trained) predictor.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PredictorConstraints {
+    /// The predictor mispredicts at every speculating instruction
+    #[serde(default = "disabled")]
+    pub always_mispredict: bool,
+    /// Max. number of instructions at which the predictor mispredicts (unbounded if not given)
+    #[serde(default)]
+    pub max_mispredictions: Option<usize>,
+    /// Addresses of instructions at which the predictor never mispredicts
+    #[serde(default)]
+    pub never_speculate_at: BTreeSet<u64>,
+    /// The predictor mispredicts at most at a single instruction (the speculation source)
+    #[serde(default = "disabled")]
+    pub single_speculation: bool,
+}
+
+impl PredictorConstraints {
+    pub fn is_unconstrained(&self) -> bool {
+        !self.always_mispredict
+            && self.max_mispredictions.is_none()
+            && self.never_speculate_at.is_empty()
+            && !self.single_speculation
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InliningBudgetPolicy {
+    #[serde(rename = "error")]
+    Error, // Abort analysis
+    #[serde(rename = "havoc")]
+    Havoc, // Replace the call by a havoc summary (like an external call with `havoc_memory`)
+}
+
+impl Default for InliningBudgetPolicy {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum UnsupportedInstructionPolicy {
+    #[serde(rename = "error")]
+    Error, // Abort analysis
+    #[serde(rename = "havoc")]
+    Havoc, // Havoc the registers written by the instruction
+}
+
+impl Default for UnsupportedInstructionPolicy {
+    fn default() -> Self {
+        Self::Havoc
+    }
+}
+
+/// Allocator model of the heap (bump allocator).
+///
+/// Calls of the allocation functions return chunks with symbolic but non-overlapping (and aligned) bases
+/// within the heap region, calls of the deallocation functions have no effect (memory is never reused).
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeapModel {
+    /// Memory region of the heap, chunks are allocated within it
+    pub region: AddressRange,
+    /// Functions returning a chunk of the size given by their first argument
+    #[serde(default = "default_allocators")]
+    pub allocators: HashSet<String>,
+    /// Functions freeing the chunk given by their first argument
+    #[serde(default = "default_deallocators")]
+    pub deallocators: HashSet<String>,
+    /// Alignment of the chunk bases in bytes (power of two)
+    #[serde(default = "default_heap_alignment")]
+    pub alignment: u64,
+}
+
+impl Default for HeapModel {
+    fn default() -> Self {
+        Self {
+            region: AddressRange::empty(),
+            allocators: default_allocators(),
+            deallocators: default_deallocators(),
+            alignment: default_heap_alignment(),
+        }
+    }
+}
+
+/// Relational contract of a function, given as registers which are equal in all self-compositions.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunctionContract {
+    /// Registers which are low on entry (precondition), all other registers are high
+    #[serde(default)]
+    pub requires_low: HashSet<String>,
+    /// Registers which must be low on return (postcondition),
+    /// calls of the function only make these registers public
+    #[serde(default)]
+    pub ensures_low: HashSet<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Analysis {
     #[serde(default = "enabled")]
     pub spectre_pht: bool,
     #[serde(default = "disabled")]
     pub spectre_stl: bool,
+    /// Let transient indirect branches follow targets overwritten by speculative stores (Spectre-v1.1)
+    #[serde(default = "disabled")]
+    pub spectre_bcbs: bool,
+    /// Share a single transient copy among speculating instructions with identical transient behavior
+    #[serde(default = "disabled")]
+    pub share_transient_copies: bool,
+    /// Remove transient paths which are unreachable because of constant branch conditions
+    #[serde(default = "enabled")]
+    pub prune_constant_transient_branches: bool,
+    /// Only save/restore variables on rollback which are live at the rollback point
+    #[serde(default = "enabled")]
+    pub minimize_rollback: bool,
     #[serde(default)]
     pub check: Check,
     #[serde(default)]
     pub predictor_strategy: PredictorStrategy,
+    #[serde(default)]
+    pub predictor_constraints: PredictorConstraints,
+    /// Max. depth of nested speculation (speculation during transient execution which is rolled back on its own)
+    #[serde(default)]
+    pub nested_speculation_depth: usize,
     /// Default loop unwinding bound
     #[serde(default)]
     pub unwind: usize,
     /// Unwinding bounds for specific loops
     #[serde(default)]
     pub unwind_loop: BTreeMap<usize, usize>,
+    /// Infer unwinding bounds for loops with constant trip count
+    #[serde(default = "disabled")]
+    pub infer_unwind: bool,
     #[serde(default)]
     pub unwinding_guard: UnwindingGuard,
+    /// Make irreducible loops reducible by duplicating blocks before unwinding
+    #[serde(default = "enabled")]
+    pub split_irreducible: bool,
+    /// Unwind irreducible loops anyway (unsound), instead of failing
+    #[serde(default = "disabled")]
+    pub allow_irreducible: bool,
     #[serde(default)]
     pub recursion_limit: usize,
+    /// Max. depth of nested calls which are inlined (unbounded if not given)
+    #[serde(default)]
+    pub max_call_depth: Option<usize>,
+    /// Max. number of instructions of the inlined program (unbounded if not given)
+    #[serde(default)]
+    pub max_inlined_instructions: Option<usize>,
+    /// Max. number of basic blocks after each HIR transformation (unbounded if not given),
+    /// also checked by loop unwinding and transient execution before the CFG grows
+    #[serde(default)]
+    pub max_blocks: Option<usize>,
+    /// Max. number of LIR nodes (unbounded if not given)
+    #[serde(default)]
+    pub max_lir_nodes: Option<usize>,
+    /// Handling of calls which would exceed the inlining budget
+    #[serde(default)]
+    pub inlining_budget_exceeded: InliningBudgetPolicy,
+    /// Number of executions compared by the self-composition (k-safety, k >= 2)
+    #[serde(default = "default_self_compositions")]
+    pub self_compositions: usize,
+    /// Number of random executions which try to find a leak before solving (disabled if 0)
+    #[serde(default)]
+    pub simulation_runs: usize,
+    /// Seed of the random executions, the same seed gives the same executions
+    #[serde(default)]
+    pub simulation_seed: u64,
+    /// Try to prove the program secure by security type inference before solving
+    #[serde(default = "disabled")]
+    pub static_check: bool,
     #[serde(default = "disabled")]
     pub start_with_empty_cache: bool,
     #[serde(default)]
     pub observe: Observe,
+    /// Addresses of additional observation points (not supported by trace observations)
+    #[serde(default)]
+    pub observe_at: BTreeSet<u64>,
+    /// Encode traces as fixed-size arrays indexed by a step counter (bounded by the unrolled program)
+    #[serde(default = "disabled")]
+    pub bounded_trace: bool,
     #[serde(default)]
     pub model: Model,
+    /// Overrides the observation semantics to match another tool, for cross-tool comparisons
+    #[serde(default)]
+    pub compatibility: Compatibility,
     #[serde(default)]
     pub program_entry: Option<String>,
     #[serde(default)]
     pub inline_ignore: HashSet<String>,
+    /// Check each function with a contract on its own instead of inlining it into the program entry
+    #[serde(default = "disabled")]
+    pub modular: bool,
+    /// Relational pre-/postconditions of functions for modular checking (function name -> contract)
+    #[serde(default)]
+    pub contracts: BTreeMap<String, FunctionContract>,
+    /// Resolve the targets of indirect calls through code pointers (vtables, jump tables, ...)
+    #[serde(default = "enabled")]
+    pub resolve_indirect_calls: bool,
+    #[serde(default)]
+    pub calling_convention: CallingConvention,
+    /// Handling of calls which cannot be inlined (unknown or indirect targets)
+    #[serde(default)]
+    pub external_calls: ExternalCallPolicy,
+    /// Memory which may be modified by external calls
+    #[serde(default)]
+    pub external_call_memory: HashSet<AddressRange>,
+    /// Havoced registers and memory get public (instead of secret) unknown values
+    #[serde(default = "disabled")]
+    pub havoc_low: bool,
+    /// Handling of instructions which are not supported by the lifter
+    #[serde(default)]
+    pub unsupported_instructions: UnsupportedInstructionPolicy,
+    /// Memory which may be accessed by transient loads (speculative memory safety check)
+    #[serde(default)]
+    pub valid_memory: HashSet<MemoryRegion>,
+    /// Allocator model of the allocation functions (fully symbolic heap pointers if not given)
+    #[serde(default)]
+    pub heap: Option<HeapModel>,
 }
 
 impl Default for Analysis {
@@ -168,22 +524,163 @@ impl Default for Analysis {
         Self {
             spectre_pht: true,
             spectre_stl: false,
+            spectre_bcbs: false,
+            share_transient_copies: false,
+            prune_constant_transient_branches: true,
+            minimize_rollback: true,
             check: Check::default(),
             predictor_strategy: PredictorStrategy::default(),
+            predictor_constraints: PredictorConstraints::default(),
+            nested_speculation_depth: 0,
             unwind: 0,
             unwind_loop: BTreeMap::default(),
+            infer_unwind: false,
             unwinding_guard: UnwindingGuard::default(),
+            split_irreducible: true,
+            allow_irreducible: false,
             recursion_limit: 0,
+            max_call_depth: None,
+            max_inlined_instructions: None,
+            max_blocks: None,
+            max_lir_nodes: None,
+            inlining_budget_exceeded: InliningBudgetPolicy::default(),
+            self_compositions: default_self_compositions(),
+            simulation_runs: 0,
+            simulation_seed: 0,
+            static_check: false,
             start_with_empty_cache: false,
             observe: Observe::default(),
+            observe_at: BTreeSet::default(),
+            bounded_trace: false,
             model: Model::default(),
+            compatibility: Compatibility::default(),
             program_entry: None,
             inline_ignore: HashSet::default(),
+            modular: false,
+            contracts: BTreeMap::default(),
+            resolve_indirect_calls: true,
+            calling_convention: CallingConvention::default(),
+            external_calls: ExternalCallPolicy::default(),
+            external_call_memory: HashSet::default(),
+            havoc_low: false,
+            unsupported_instructions: UnsupportedInstructionPolicy::default(),
+            valid_memory: HashSet::default(),
+            heap: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CacheGranularity {
+    #[serde(rename = "address")]
+    Address, // Attacker observes accessed addresses exactly (byte granularity)
+    #[serde(rename = "line")]
+    Line, // Attacker observes accessed cache lines (e.g. Flush+Reload)
+    #[serde(rename = "page")]
+    Page, // Attacker observes accessed pages
+}
+
+impl Default for CacheGranularity {
+    fn default() -> Self {
+        Self::Address
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CacheReplacementPolicy {
+    #[serde(rename = "none")]
+    None, // The cache never evicts, it is the set of all fetched addresses
+    #[serde(rename = "lru")]
+    Lru, // Least recently used line of the accessed set is evicted
+    #[serde(rename = "plru")]
+    TreePlru, // Victim line of the accessed set is selected by a tree-based pseudo-LRU
+}
+
+impl Default for CacheReplacementPolicy {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Endianness {
+    #[serde(rename = "little")]
+    Little, // The least significant byte is stored at the lowest address
+    #[serde(rename = "big")]
+    Big, // The most significant byte is stored at the lowest address
+}
+
+impl Endianness {
+    /// Returns the address offsets of the bytes of a `bit_width` wide memory access,
+    /// ordered from the most to the least significant byte.
+    pub fn byte_offsets(self, bit_width: usize) -> Vec<usize> {
+        self.cell_offsets(bit_width, 8)
+    }
+
+    /// Returns the address offsets of the `cell_width` wide memory cells of a `bit_width` wide memory access,
+    /// ordered from the most to the least significant cell.
+    pub fn cell_offsets(self, bit_width: usize, cell_width: usize) -> Vec<usize> {
+        let cells = bit_width / cell_width;
+        match self {
+            Self::Little => (0..cells).rev().collect(),
+            Self::Big => (0..cells).collect(),
+        }
+    }
+}
+
+impl Default for Endianness {
+    fn default() -> Self {
+        Self::Little
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MemoryModel {
+    #[serde(rename = "array")]
+    Array, // Memory is a byte array, loads and stores are encoded with array select/store
+    #[serde(rename = "uf")]
+    UninterpretedFunction, // Stores are eliminated by instantiating read-over-write axioms on demand
+}
+
+impl Default for MemoryModel {
+    fn default() -> Self {
+        Self::Array
+    }
+}
+
+/// Kinds of accesses which are visible to the attacker (through the observed components).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObservedAccesses {
+    #[serde(default = "enabled")]
+    pub loads: bool,
+    #[serde(default = "enabled")]
+    pub stores: bool,
+    #[serde(default = "enabled")]
+    pub branch_targets: bool,
+    /// Are the outcomes of conditional branches observable (through the PHT)?
+    #[serde(default = "enabled")]
+    pub branch_conditions: bool,
+    /// Observe computed branch targets relative to the randomized image base (ASLR slide),
+    /// as the load address is public anyway (pc model only, requires `setup.code_pointer_registers`)
+    #[serde(default = "disabled")]
+    pub branch_targets_relative_to_image_base: bool,
+}
+
+impl Default for ObservedAccesses {
+    fn default() -> Self {
+        Self {
+            loads: true,
+            stores: true,
+            branch_targets: true,
+            branch_conditions: true,
+            branch_targets_relative_to_image_base: false,
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Architecture {
     #[serde(default = "enabled")]
     pub cache: bool,
@@ -191,8 +688,56 @@ pub struct Architecture {
     pub branch_target_buffer: bool,
     #[serde(rename = "pht", default = "enabled")]
     pub pattern_history_table: bool,
+    /// Stores are by-passed as predicted by the (trained) memory dependence predictor
+    #[serde(rename = "mdp", default = "disabled")]
+    pub memory_dependence_predictor: bool,
     #[serde(default = "default_speculation_window")]
     pub speculation_window: usize,
+    /// Bound the speculation window by a free parameter (at most `speculation_window`),
+    /// which allows checking multiple speculation windows within one solver session
+    #[serde(default = "disabled")]
+    pub parametric_speculation_window: bool,
+    #[serde(default)]
+    pub cache_granularity: CacheGranularity,
+    /// Size of a cache line in bytes (a power of two)
+    #[serde(
+        default = "default_cache_line_size",
+        deserialize_with = "deserialize_cache_line_size"
+    )]
+    pub cache_line_size: u64,
+    /// Replacement policy of a set-associative cache with bounded associativity,
+    /// requires `line` or `page` cache granularity
+    #[serde(default)]
+    pub cache_replacement: CacheReplacementPolicy,
+    #[serde(default = "default_cache_sets")]
+    pub cache_sets: usize,
+    #[serde(default = "default_cache_ways")]
+    pub cache_ways: usize,
+    /// Number of arbitrary cache lines evicted by a concurrently running attacker thread
+    /// in front of each observation (0 = no sibling thread)
+    #[serde(default)]
+    pub sibling_thread_evictions: usize,
+    #[serde(default)]
+    pub memory_model: MemoryModel,
+    #[serde(default)]
+    pub endianness: Endianness,
+    /// Width (in bits) of the value stored at a single memory address (8 = byte-addressable)
+    #[serde(default = "default_memory_cell_width")]
+    pub memory_cell_width: usize,
+    #[serde(default)]
+    pub observe: ObservedAccesses,
+}
+
+impl Architecture {
+    /// Returns the alignment of addresses observable via the cache,
+    /// or `None` if the full address is observable.
+    pub fn cache_address_alignment(&self) -> Option<u64> {
+        match self.cache_granularity {
+            CacheGranularity::Address => None,
+            CacheGranularity::Line => Some(self.cache_line_size),
+            CacheGranularity::Page => Some(PAGE_SIZE),
+        }
+    }
 }
 
 impl Default for Architecture {
@@ -201,7 +746,19 @@ impl Default for Architecture {
             cache: true,
             branch_target_buffer: true,
             pattern_history_table: true,
+            memory_dependence_predictor: false,
             speculation_window: default_speculation_window(),
+            parametric_speculation_window: false,
+            cache_granularity: CacheGranularity::default(),
+            cache_line_size: default_cache_line_size(),
+            cache_replacement: CacheReplacementPolicy::default(),
+            cache_sets: default_cache_sets(),
+            cache_ways: default_cache_ways(),
+            sibling_thread_evictions: 0,
+            memory_model: MemoryModel::default(),
+            endianness: Endianness::default(),
+            memory_cell_width: default_memory_cell_width(),
+            observe: ObservedAccesses::default(),
         }
     }
 }
@@ -210,6 +767,7 @@ pub type Address = u64;
 
 /// A (half-open) range bounded inclusively below and exclusively above (start..end).
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AddressRange {
     start: Address,
     end: Address,
@@ -224,9 +782,21 @@ impl AddressRange {
         Self { start: 0, end: 0 }
     }
 
+    pub fn start(&self) -> Address {
+        self.start
+    }
+
+    pub fn end(&self) -> Address {
+        self.end
+    }
+
     pub fn addresses(&self) -> impl Iterator<Item = Address> {
         self.start..self.end
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
 }
 
 impl Default for AddressRange {
@@ -235,6 +805,75 @@ impl Default for AddressRange {
     }
 }
 
+/// A memory region given either by an address range or by a symbol of the analyzed binary.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MemoryRegion {
+    Range(AddressRange),
+    Symbol {
+        /// Symbol name with optional size in bytes, e.g. `secret_key+32`
+        symbol: String,
+    },
+}
+
+impl MemoryRegion {
+    /// Returns the address range of this region, or `None` if the symbol is not resolved yet.
+    pub fn address_range(&self) -> Option<AddressRange> {
+        match self {
+            Self::Range(range) => Some(*range),
+            Self::Symbol { .. } => None,
+        }
+    }
+
+    /// Resolves the symbol of this region into an address range,
+    /// `lookup` gives the address and size of symbols.
+    pub fn resolve<F>(&self, lookup: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<(Address, u64)>,
+    {
+        let symbol = match self {
+            Self::Range(_) => return Ok(self.clone()),
+            Self::Symbol { symbol } => symbol,
+        };
+
+        let (name, size) = match symbol.split_once('+') {
+            Some((name, size)) => (name.trim(), Some(parse_size(size.trim())?)),
+            None => (symbol.trim(), None),
+        };
+
+        let (address, symbol_size) =
+            lookup(name).ok_or_else(|| format!("Symbol '{}' not found", name))?;
+
+        let size = size.unwrap_or(symbol_size);
+        if size == 0 {
+            return Err(format!(
+                "Symbol '{}' has unknown size, specify it explicitly (e.g. '{}+8')",
+                name, name
+            )
+            .into());
+        }
+
+        Ok(Self::Range(AddressRange::new(address, address + size)))
+    }
+}
+
+impl fmt::Display for MemoryRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Range(range) => write!(f, "0x{:X}..0x{:X}", range.start(), range.end()),
+            Self::Symbol { symbol } => write!(f, "{}", symbol),
+        }
+    }
+}
+
+fn parse_size(s: &str) -> Result<u64> {
+    let size = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse::<u64>(),
+    };
+    size.map_err(|_| format!("Invalid memory region size '{}'", s).into())
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum SecurityLevel {
     #[serde(rename = "low")]
@@ -243,7 +882,8 @@ pub enum SecurityLevel {
     High,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct GenericSecurityPolicy<T: Eq + std::hash::Hash> {
     #[serde(rename = "default")]
     pub default_level: SecurityLevel,
@@ -254,12 +894,37 @@ pub struct GenericSecurityPolicy<T: Eq + std::hash::Hash> {
 }
 
 pub type RegistersSecurityPolicy = GenericSecurityPolicy<String>;
-pub type MemorySecurityPolicy = GenericSecurityPolicy<AddressRange>;
+pub type MemorySecurityPolicy = GenericSecurityPolicy<MemoryRegion>;
 
-#[derive(Debug, Serialize, Deserialize)]
+impl MemorySecurityPolicy {
+    /// Resolves all symbolic memory regions into address ranges,
+    /// `lookup` gives the address and size of symbols.
+    pub fn resolve_symbols<F>(&mut self, lookup: F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<(Address, u64)>,
+    {
+        self.low = self
+            .low
+            .iter()
+            .map(|region| region.resolve(&lookup))
+            .collect::<Result<_>>()?;
+        self.high = self
+            .high
+            .iter()
+            .map(|region| region.resolve(&lookup))
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SecurityPolicy {
     pub registers: RegistersSecurityPolicy,
     pub memory: MemorySecurityPolicy,
+    /// Security levels of the program entry's arguments, located by `analysis.calling_convention`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<SecurityLevel>,
 }
 
 impl Default for SecurityPolicy {
@@ -275,11 +940,13 @@ impl Default for SecurityPolicy {
                 low: HashSet::default(),
                 high: HashSet::default(),
             },
+            arguments: Vec::default(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Setup {
     #[serde(default = "disabled")]
     pub init_stack: bool,
@@ -289,14 +956,61 @@ pub struct Setup {
     pub flag_content: HashMap<String, bool>,
     #[serde(rename = "memory", default)]
     pub memory_content: HashMap<u64, Vec<u8>>,
+    /// Load address of position-independent executables (binaries only)
+    #[serde(default)]
+    pub load_base: Option<Address>,
+    /// Initialize the locations of dynamic relocations with the relocated pointers
+    #[serde(default = "enabled")]
+    pub apply_relocations: bool,
+    /// Memory-mapped I/O regions (device registers)
+    #[serde(default)]
+    pub mmio: HashSet<MemoryRegion>,
+    /// Registers which hold run-time code pointers at program entry (e.g. callbacks),
+    /// i.e. the randomized image base plus a public offset
+    #[serde(default)]
+    pub code_pointer_registers: HashSet<String>,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Self {
+            init_stack: false,
+            register_content: HashMap::default(),
+            flag_content: HashMap::default(),
+            memory_content: HashMap::default(),
+            load_base: None,
+            apply_relocations: true,
+            mmio: HashSet::default(),
+            code_pointer_registers: HashSet::default(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Environment {
     #[serde(rename = "optimization", default)]
     pub optimization_level: OptimizationLevel,
     #[serde(default)]
     pub solver: Solver,
+    /// Solvers which are tried in order if the solver fails (e.g. crashes)
+    #[serde(default)]
+    pub solver_fallbacks: Vec<Solver>,
+    #[serde(default)]
+    pub solver_backend: SolverBackend,
+    #[serde(default)]
+    pub solver_options: SolverOptions,
+    /// Replace uninterpreted predictor functions by variables plus consistency constraints
+    /// (cache, BTB and PHT store chains are kept)
+    #[serde(default = "disabled")]
+    pub ackermannization: bool,
+    /// Max. size (number of operators) of the LIR subexpressions which are simplified by the solver
+    /// before encoding (disabled if not given, requires feature `z3`)
+    #[serde(default)]
+    pub solver_simplification: Option<usize>,
+    /// Let the solver produce unsat proofs (native backend only)
+    #[serde(default = "disabled")]
+    pub proof_generation: bool,
     #[serde(default)]
     pub analysis: Analysis,
     #[serde(default)]
@@ -309,6 +1023,12 @@ pub struct Environment {
     pub debug: bool,
     #[serde(default = "enabled")]
     pub generate_counterexample: bool,
+    /// Cache lifted binaries on disk and reuse them in later runs
+    #[serde(default = "enabled")]
+    pub lift_cache: bool,
+    /// Expectation of the regression tests, ignored by the analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test: Option<serde_yaml::Value>,
 }
 
 impl Environment {
@@ -316,7 +1036,229 @@ impl Environment {
         let file = File::open(path)
             .map_err(|_| format!("Environment file '{}' could not be loaded", path.display()))?;
         let reader = BufReader::new(file);
-        Ok(serde_yaml::from_reader(reader)?)
+        serde_yaml::from_reader(reader).map_err(|e| invalid_environment(path, &e))
+    }
+
+    /// Parses the environment from the given YAML source, `path` is only used for error reporting.
+    pub fn from_yaml(source: &str, path: &Path) -> Result<Environment> {
+        serde_yaml::from_str(source).map_err(|e| invalid_environment(path, &e))
+    }
+
+    /// Overrides the field given by the dotted `path` (e.g. `analysis.spectre_stl`)
+    /// with the YAML `value`.
+    pub fn set(&mut self, path: &str, value: &str) -> Result<()> {
+        let override_source = Path::new("--set");
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(value).map_err(|e| invalid_environment(override_source, &e))?;
+
+        let mut env = serde_yaml::to_value(&*self).map_err(|e| e.to_string())?;
+        let mut field = &mut env;
+        for key in path.split('.') {
+            field = field
+                .as_mapping_mut()
+                .and_then(|mapping| mapping.get_mut(&serde_yaml::Value::from(key)))
+                .ok_or_else(|| format!("Unknown environment field '{}'", path))?;
+        }
+        *field = value;
+
+        *self =
+            serde_yaml::from_value(env).map_err(|e| invalid_environment(override_source, &e))?;
+
+        Ok(())
+    }
+
+    /// Applies the settings implied by `analysis.compatibility`, overriding the given ones.
+    ///
+    /// Spectector checks speculative non-interference under the always-mispredict semantics:
+    /// The attacker observes the program counter and the addresses of all loads and stores,
+    /// transient leaks are only reported if the non-speculative traces are equal.
+    /// Memory is addressed in words, i.e. each address holds a full register value.
+    pub fn apply_compatibility(&mut self) {
+        match self.analysis.compatibility {
+            Compatibility::None => {}
+            Compatibility::Spectector => {
+                self.analysis.spectre_pht = true;
+                self.analysis.spectre_stl = false;
+                self.analysis.spectre_bcbs = false;
+                self.analysis.check = Check::OnlyTransientExecutionLeaks;
+                self.analysis.model = Model::ProgramCounter;
+                self.analysis.observe = Observe::Trace;
+                self.analysis.predictor_constraints.always_mispredict = true;
+                self.architecture.cache = true;
+                self.architecture.branch_target_buffer = true;
+                self.architecture.pattern_history_table = true;
+                self.architecture.cache_granularity = CacheGranularity::Address;
+                self.architecture.observe = ObservedAccesses::default();
+                self.architecture.memory_cell_width = WORD_SIZE;
+            }
+        }
+    }
+
+    /// Prints the environment as YAML, each field is annotated with the source of its value.
+    pub fn to_annotated_string(&self, sources: &EnvironmentSources) -> String {
+        let env = serde_yaml::to_value(self).unwrap();
+        let yaml = serde_yaml::to_string(self).unwrap();
+
+        // Keys of the enclosing mappings together with their indentation
+        let mut keys: Vec<(usize, &str)> = Vec::new();
+
+        let mut annotated = String::new();
+        for line in yaml.lines() {
+            let content = line.trim_start();
+            let indentation = line.len() - content.len();
+            keys.retain(|&(i, _)| i < indentation);
+
+            annotated.push_str(line);
+            if let Some((key, _)) = content
+                .split_once(':')
+                .filter(|_| !content.starts_with('-'))
+            {
+                keys.push((indentation, key));
+                let path: Vec<&str> = keys.iter().map(|&(_, key)| key).collect();
+                // Keys within sequences don't resolve and are annotated via their sequence
+                if lookup(&env, &path).map_or(false, |value| value.as_mapping().is_none()) {
+                    annotated.push_str(&format!("  # {}", sources.source_of(&path.join("."))));
+                }
+            }
+            annotated.push('\n');
+        }
+
+        annotated
+    }
+}
+
+fn lookup<'a>(value: &'a serde_yaml::Value, path: &[&str]) -> Option<&'a serde_yaml::Value> {
+    path.iter().try_fold(value, |value, &key| {
+        value.as_mapping()?.get(&serde_yaml::Value::from(key))
+    })
+}
+
+/// Where the value of an environment field comes from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Source {
+    Default,
+    File,
+    Argument,
+    /// Set via `--set PATH=VALUE`
+    Override,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File => write!(f, "env file"),
+            Self::Argument => write!(f, "command-line argument"),
+            Self::Override => write!(f, "--set"),
+        }
+    }
+}
+
+/// Tracks the source of each (dotted path of an) environment field, fields without source are defaults.
+#[derive(Debug, Clone, Default)]
+pub struct EnvironmentSources {
+    sources: BTreeMap<String, Source>,
+    /// The environment (as YAML) when changes were recorded last
+    checkpoint: Option<serde_yaml::Value>,
+}
+
+impl EnvironmentSources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records all fields given by the environment file as `Source::File`.
+    pub fn record_file(&mut self, path: &Path) -> Result<()> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|_| format!("Environment file '{}' could not be loaded", path.display()))?;
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(&source).map_err(|e| invalid_environment(path, &e))?;
+        self.record_fields(&value, "", Source::File);
+        Ok(())
+    }
+
+    /// Remembers the current state of the environment, later changes are recorded by `record_changes`.
+    pub fn checkpoint(&mut self, env: &Environment) {
+        self.checkpoint = Some(serde_yaml::to_value(env).unwrap());
+    }
+
+    /// Records all fields which changed since the last checkpoint with the given source.
+    pub fn record_changes(&mut self, env: &Environment, source: Source) {
+        let after = serde_yaml::to_value(env).unwrap();
+        if let Some(before) = self.checkpoint.take() {
+            self.record_differences(&before, &after, "", source);
+        }
+        self.checkpoint = Some(after);
+    }
+
+    pub fn record(&mut self, path: &str, source: Source) {
+        self.sources.insert(path.to_owned(), source);
+    }
+
+    /// Returns the source of the given field, which is the source of its closest recorded ancestor.
+    pub fn source_of(&self, path: &str) -> Source {
+        let mut path = path;
+        loop {
+            if let Some(&source) = self.sources.get(path) {
+                return source;
+            }
+            match path.rsplit_once('.') {
+                Some((parent, _)) => path = parent,
+                None => return Source::Default,
+            }
+        }
+    }
+
+    fn record_fields(&mut self, value: &serde_yaml::Value, path: &str, source: Source) {
+        match value.as_mapping() {
+            Some(mapping) => {
+                for (key, value) in mapping {
+                    if let Some(key) = key.as_str() {
+                        self.record_fields(value, &join_path(path, key), source);
+                    }
+                }
+            }
+            None => self.record(path, source),
+        }
+    }
+
+    fn record_differences(
+        &mut self,
+        before: &serde_yaml::Value,
+        after: &serde_yaml::Value,
+        path: &str,
+        source: Source,
+    ) {
+        match (before.as_mapping(), after.as_mapping()) {
+            (Some(before), Some(after)) => {
+                for (key, value) in after {
+                    if let Some(key) = key.as_str() {
+                        match before.get(&serde_yaml::Value::from(key)) {
+                            Some(previous) => self.record_differences(
+                                previous,
+                                value,
+                                &join_path(path, key),
+                                source,
+                            ),
+                            None => self.record(&join_path(path, key), source),
+                        }
+                    }
+                }
+            }
+            _ => {
+                if before != after {
+                    self.record(path, source);
+                }
+            }
+        }
+    }
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", parent, key)
     }
 }
 
@@ -325,12 +1267,20 @@ impl Default for Environment {
         Self {
             optimization_level: OptimizationLevel::default(),
             solver: Solver::default(),
+            solver_fallbacks: Vec::default(),
+            solver_backend: SolverBackend::default(),
+            solver_options: SolverOptions::default(),
+            ackermannization: false,
+            solver_simplification: None,
+            proof_generation: false,
             analysis: Analysis::default(),
             architecture: Architecture::default(),
             policy: SecurityPolicy::default(),
             setup: Setup::default(),
             debug: false,
             generate_counterexample: true,
+            lift_cache: true,
+            test: None,
         }
     }
 }
@@ -341,6 +1291,23 @@ impl fmt::Display for Environment {
     }
 }
 
+/// Turns the YAML error into an error pointing to the position of the offending key/value.
+fn invalid_environment(path: &Path, error: &serde_yaml::Error) -> Error {
+    let location = error.location().map(|l| (l.line(), l.column()));
+    let message = error.to_string();
+    let message = match location {
+        Some((line, column)) => message
+            .trim_end_matches(&format!(" at line {} column {}", line, column))
+            .to_owned(),
+        None => message,
+    };
+    Error::InvalidEnvironment {
+        path: path.to_path_buf(),
+        location,
+        message,
+    }
+}
+
 fn disabled() -> bool {
     false
 }
@@ -352,3 +1319,226 @@ fn enabled() -> bool {
 fn default_speculation_window() -> usize {
     100
 }
+
+fn default_cache_line_size() -> u64 {
+    64
+}
+
+/// Cache-line aligned addresses are computed by masking, which requires a power of two.
+fn deserialize_cache_line_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<u64, D::Error> {
+    let size = u64::deserialize(deserializer)?;
+    if !size.is_power_of_two() {
+        return Err(serde::de::Error::custom(format!(
+            "cache_line_size must be a power of two, but is {}",
+            size
+        )));
+    }
+    Ok(size)
+}
+
+fn default_memory_cell_width() -> usize {
+    8
+}
+
+fn default_cache_sets() -> usize {
+    64
+}
+
+fn default_cache_ways() -> usize {
+    8
+}
+
+fn default_self_compositions() -> usize {
+    2
+}
+
+fn default_allocators() -> HashSet<String> {
+    vec!["malloc".to_owned()].into_iter().collect()
+}
+
+fn default_deallocators() -> HashSet<String> {
+    vec!["free".to_owned()].into_iter().collect()
+}
+
+fn default_heap_alignment() -> u64 {
+    16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_key_is_reported_with_location() {
+        // Given:
+        let source = "architecture:\n  cache: true\n  chache_ways: 4\n";
+
+        // When:
+        let result = Environment::from_yaml(source, Path::new("env.yaml"));
+
+        // Then:
+        match result {
+            Err(Error::InvalidEnvironment {
+                location, message, ..
+            }) => {
+                assert_eq!(location.map(|(line, _)| line), Some(3));
+                assert!(message.contains("unknown field `chache_ways`"));
+            }
+            _ => panic!("expected invalid environment error"),
+        }
+    }
+
+    #[test]
+    fn test_cache_line_size_must_be_power_of_two() {
+        // Given:
+        let zero = "architecture:\n  cache_line_size: 0\n";
+        let odd = "architecture:\n  cache_line_size: 48\n";
+        let valid = "architecture:\n  cache_line_size: 128\n";
+
+        // When:
+        let zero = Environment::from_yaml(zero, Path::new("env.yaml"));
+        let odd = Environment::from_yaml(odd, Path::new("env.yaml"));
+        let valid = Environment::from_yaml(valid, Path::new("env.yaml"));
+
+        // Then:
+        assert!(matches!(zero, Err(Error::InvalidEnvironment { .. })));
+        assert!(matches!(odd, Err(Error::InvalidEnvironment { .. })));
+        assert_eq!(valid.unwrap().architecture.cache_line_size, 128);
+    }
+
+    #[test]
+    fn test_test_expectation_is_accepted() {
+        // Given:
+        let source = "analysis:\n  check: only_transient_leaks\ntest:\n  expect: unsafe\n";
+
+        // When:
+        let env = Environment::from_yaml(source, Path::new("env.yaml")).unwrap();
+
+        // Then:
+        assert_eq!(env.analysis.check, Check::OnlyTransientExecutionLeaks);
+        assert!(env.test.is_some());
+    }
+
+    #[test]
+    fn test_solver_options_keep_their_type() {
+        // Given:
+        let source = "solver_options:\n  z3:\n    smt.random_seed: 7\n    model.completion: true\n";
+
+        // When:
+        let env = Environment::from_yaml(source, Path::new("env.yaml")).unwrap();
+
+        // Then:
+        let options = env.solver_options.of(Solver::Z3);
+        assert_eq!(
+            options.get("smt.random_seed"),
+            Some(&SolverOptionValue::Integer(7))
+        );
+        assert_eq!(
+            options.get("model.completion").map(ToString::to_string),
+            Some("true".to_owned())
+        );
+        assert!(env.solver_options.of(Solver::Yices2).is_empty());
+    }
+
+    #[test]
+    fn test_set_overrides_nested_field() {
+        // Given:
+        let mut env = Environment::default();
+
+        // When:
+        env.set("analysis.spectre_stl", "true").unwrap();
+        env.set("architecture.speculation_window", "42").unwrap();
+
+        // Then:
+        assert!(env.analysis.spectre_stl);
+        assert_eq!(env.architecture.speculation_window, 42);
+    }
+
+    #[test]
+    fn test_annotate_fields_with_their_source() {
+        // Given:
+        let mut env = Environment::default();
+        let mut sources = EnvironmentSources::new();
+        sources.checkpoint(&env);
+        env.set("architecture.speculation_window", "42").unwrap();
+        sources.record_changes(&env, Source::Argument);
+
+        // When:
+        let annotated = env.to_annotated_string(&sources);
+
+        // Then:
+        assert!(annotated.contains("speculation_window: 42  # command-line argument\n"));
+        assert!(annotated.contains("spectre_pht: true  # default\n"));
+        assert!(!annotated.contains("architecture:  #"));
+    }
+
+    #[test]
+    fn test_spectector_compatibility_overrides_observation_semantics() {
+        // Given:
+        let source = "analysis:\n  compatibility: spectector\n  model: components\n\
+                      architecture:\n  cache: false\n  speculation_window: 20\n";
+        let mut env = Environment::from_yaml(source, Path::new("env.yaml")).unwrap();
+
+        // When:
+        env.apply_compatibility();
+
+        // Then:
+        assert_eq!(env.analysis.model, Model::ProgramCounter);
+        assert_eq!(env.analysis.observe, Observe::Trace);
+        assert_eq!(env.analysis.check, Check::OnlyTransientExecutionLeaks);
+        assert!(env.analysis.predictor_constraints.always_mispredict);
+        assert!(env.architecture.cache);
+        assert_eq!(env.architecture.memory_cell_width, WORD_SIZE);
+        assert_eq!(env.architecture.speculation_window, 20);
+    }
+
+    #[test]
+    fn test_custom_model_is_selected_by_name() {
+        // Given:
+        let source = "analysis:\n  model:\n    custom: cache_sets\n";
+
+        // When:
+        let env = Environment::from_yaml(source, Path::new("env.yaml")).unwrap();
+
+        // Then:
+        assert_eq!(env.analysis.model, Model::Custom("cache_sets".to_owned()));
+    }
+
+    #[test]
+    fn test_arguments_are_located_by_calling_convention() {
+        // Given:
+        let system_v = CallingConvention::SystemV;
+        let microsoft = CallingConvention::Microsoft;
+        let aapcs64 = CallingConvention::Aapcs64;
+
+        // When/Then: the 2nd argument is passed in a register, the 7th one on the stack (except for AAPCS64)
+        assert_eq!(system_v.argument_registers()[1], "rsi");
+        assert_eq!(system_v.stack_argument_offset(1), None);
+        assert_eq!(system_v.stack_argument_offset(6), Some(8));
+        assert_eq!(microsoft.argument_registers()[1], "rdx");
+        assert_eq!(microsoft.stack_argument_offset(6), Some(56));
+        assert_eq!(aapcs64.argument_registers()[1], "x1");
+        assert_eq!(aapcs64.stack_argument_offset(6), None);
+        assert_eq!(aapcs64.stack_argument_offset(8), Some(0));
+    }
+
+    #[test]
+    fn test_set_rejects_unknown_field_and_invalid_value() {
+        // Given:
+        let mut env = Environment::default();
+
+        // When:
+        let unknown = env.set("analysis.spectre_xyz", "true");
+        let invalid = env.set("architecture.speculation_window", "large");
+
+        // Then:
+        assert!(unknown.is_err());
+        assert!(matches!(invalid, Err(Error::InvalidEnvironment { .. })));
+        assert_eq!(
+            env.architecture.speculation_window,
+            default_speculation_window()
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::expr::{Sort, Variable};
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("No compatible loader found for '{}'", .0.display())]
+    NoCompatibleLoader(PathBuf),
+    #[error("{}{}: {message}", .path.display(), position(.location))]
+    InvalidEnvironment {
+        path: PathBuf,
+        /// Line and column (both 1-based)
+        location: Option<(usize, usize)>,
+        message: String,
+    },
+    #[error("{}:{line}: {message}", .path.display())]
+    InvalidHir {
+        path: PathBuf,
+        /// 1-based line
+        line: usize,
+        message: String,
+    },
+    #[error("Unsupported instruction '{instruction}' at {}", location(.address))]
+    UnsupportedInstruction {
+        instruction: String,
+        address: Option<u64>,
+    },
+    #[error("Call to unknown function: {instruction}")]
+    UnknownFunction {
+        instruction: String,
+        address: Option<u64>,
+    },
+    #[error("Expected {expected} but was {actual}")]
+    SortMismatch { expected: String, actual: Sort },
+    #[error("Sort {0} is not supported by the native Z3 backend")]
+    UnsupportedSort(Sort),
+    #[error("Invalid operands for {operator}")]
+    InvalidOperands { operator: String },
+    #[error("CFG entry must be set")]
+    MissingEntry,
+    #[error("CFG exit must be set")]
+    MissingExit,
+    #[error("Basic block with index {0} does not exist")]
+    BlockNotFound(usize),
+    #[error("CFG is not reducible, irreducible loops are entered at blocks {}", blocks(.0))]
+    IrreducibleControlFlowGraph(Vec<usize>),
+    #[error("{what} ({measured}) exceeds the budget `{budget}` of {limit}, {hint}")]
+    BudgetExceeded {
+        what: String,
+        budget: &'static str,
+        measured: usize,
+        limit: usize,
+        hint: &'static str,
+    },
+    #[error("{} to be unwound more than {max_bound} times, increase the max. unwinding bound", loops(.loop_id))]
+    UnwindingBoundExceeded {
+        /// Loop whose unwinding assertion is violated, `None` if it can't be attributed to a loop
+        loop_id: Option<usize>,
+        max_bound: usize,
+    },
+    #[error(
+        "Custom observation model '{0}' has to be passed to the HIR transformation explicitly"
+    )]
+    CustomObservationModel(String),
+    #[error("Observation of `{observation}` in program {program} has no counterpart in program {other_program}")]
+    MissingObservationCounterpart {
+        observation: String,
+        program: usize,
+        other_program: usize,
+    },
+    #[error(
+        "Unsupported memory access width of {width} bits (expected a multiple of {cell_width})"
+    )]
+    UnsupportedAccessWidth { width: usize, cell_width: usize },
+    #[error("Unsupported memory cell width of {0} bits")]
+    UnsupportedCellWidth(usize),
+    #[error("Basic block with index {0} is unreachable")]
+    UnreachableBlock(usize),
+    #[error("No instruction with index {index} found in block {block}")]
+    InstructionNotFound { block: usize, index: usize },
+    #[error("Index {index} is invalid for block {block}")]
+    InvalidInstructionIndex { block: usize, index: usize },
+    #[error("No node with index {0} found")]
+    NodeNotFound(usize),
+    #[error("Index {0} is invalid for inserting a node")]
+    InvalidNodeIndex(usize),
+    #[error("No phi node with index {index} found in block {block}")]
+    PhiNodeNotFound { block: usize, index: usize },
+    #[error("@{index}: Re-assignment of variable `{variable}`")]
+    VariableReassignment { index: usize, variable: Variable },
+    #[error("@{index}: Use of undefined variable `{variable}`")]
+    UndefinedVariable { index: usize, variable: Variable },
+    /// Variables of a node of a single composition refer to a composition.
+    #[error("{what} must not refer to a composition{}", hint(.hint))]
+    UnexpectedComposition {
+        what: &'static str,
+        hint: Option<&'static str>,
+    },
+    /// Variables of a hyper node (relating compositions) don't refer to a composition.
+    #[error("All {what} must refer to a composition")]
+    MissingComposition { what: &'static str },
+    #[error(transparent)]
+    Falcon(#[from] ::falcon::error::Error),
+    #[error(transparent)]
+    ParseBigIntError(#[from] ::num_bigint::ParseBigIntError),
+    #[error(transparent)]
+    RSmt2(#[from] ::rsmt2::errors::Error),
+    #[error(transparent)]
+    IOError(#[from] ::std::io::Error),
+    #[error(transparent)]
+    NullError(#[from] ::std::ffi::NulError),
+    #[error(transparent)]
+    SerdeYAML(#[from] ::serde_yaml::Error),
+    #[error(transparent)]
+    SerdeJSON(#[from] ::serde_json::Error),
+    /// Any other error which doesn't carry additional context.
+    #[error("{0}")]
+    Other(String),
+}
+
+fn location(address: &Option<u64>) -> String {
+    address.map_or_else(|| "unknown address".to_owned(), |a| format!("0x{:X}", a))
+}
+
+fn blocks(indices: &[usize]) -> String {
+    indices
+        .iter()
+        .map(|index| index.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn loops(loop_id: &Option<usize>) -> String {
+    loop_id.map_or_else(|| "Loops have".to_owned(), |id| format!("Loop {} has", id))
+}
+
+fn hint(hint: &Option<&'static str>) -> String {
+    hint.map_or_else(String::new, |hint| format!(", use {}", hint))
+}
+
+fn position(location: &Option<(usize, usize)>) -> String {
+    location.map_or_else(String::new, |(line, column)| {
+        format!(":{}:{}", line, column)
+    })
+}
+
+impl Error {
+    /// Checks if the error is caused by a failing solver (e.g. a crashed solver process),
+    /// such that another solver may succeed.
+    pub fn is_solver_failure(&self) -> bool {
+        matches!(self, Self::RSmt2(_) | Self::IOError(_))
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_owned())
+    }
+}
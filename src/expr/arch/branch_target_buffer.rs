@@ -1,8 +1,9 @@
 use crate::error::Result;
 use crate::expr::{Expression, Sort, Variable};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum BranchTargetBuffer {
     Track,
 }
@@ -1,10 +1,11 @@
 use crate::error::Result;
 use crate::expr::{Expression, Sort, Variable};
 use crate::util::CompactIterator;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Cache {
     Fetch(usize), // Fetch N bits into the cache
     Evict(usize), // Evict N bits from the cache
@@ -54,7 +55,7 @@ pub enum CacheAddresses {
     FetchedIntoEmptyCache(BTreeSet<u64>),
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct CacheValue {
     addresses: BTreeSet<u64>, // Holds evicted addresses if default is cached, or fetched addresses if default is not cached.
     default_is_cached: bool,
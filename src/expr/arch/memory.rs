@@ -1,9 +1,10 @@
 use crate::error::Result;
 use crate::expr::{Expression, Sort, Variable};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Memory {
     Store(usize),
     Load(usize),
@@ -50,7 +51,7 @@ impl Memory {
     }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct MemoryValue {
     content: BTreeMap<u64, u64>,
     default_byte: u8,
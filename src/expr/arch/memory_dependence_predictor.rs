@@ -0,0 +1,63 @@
+use crate::error::Result;
+use crate::expr::{Expression, Sort, Variable};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Memory dependence predictor (memory disambiguation), which predicts for each store
+/// whether younger loads are independent of it and therefore may bypass it speculatively.
+///
+/// The predictor is trained by store/load pairs: the location of the most recent store to
+/// each address is tracked, and a load from that address marks the store as dependent.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MemoryDependencePredictor {
+    Bypass,
+    Dependent,
+}
+
+impl fmt::Display for MemoryDependencePredictor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bypass => write!(f, "mdp-bypass"),
+            Self::Dependent => write!(f, "mdp-dependent"),
+        }
+    }
+}
+
+impl MemoryDependencePredictor {
+    pub fn variable() -> Variable {
+        let mut var = Variable::new("_mdp", Sort::memory_dependence_predictor());
+        var.set_rollback_persistent(true);
+        var
+    }
+
+    /// The location of the most recent store to each address.
+    pub fn store_locations_variable() -> Variable {
+        let mut var = Variable::new("_mdp_stores", Sort::array(Sort::word(), Sort::word()));
+        var.set_rollback_persistent(true);
+        var
+    }
+
+    /// Predicts whether the store at the given location may be bypassed.
+    pub fn bypass(mdp: Expression, location: Expression) -> Result<Expression> {
+        mdp.sort().expect_memory_dependence_predictor()?;
+        location.sort().expect_word()?;
+
+        Ok(Expression::new(
+            Self::Bypass.into(),
+            vec![mdp, location],
+            Sort::boolean(),
+        ))
+    }
+
+    /// Trains the predictor that loads depend on the store at the given location.
+    pub fn dependent(mdp: Expression, location: Expression) -> Result<Expression> {
+        mdp.sort().expect_memory_dependence_predictor()?;
+        location.sort().expect_word()?;
+
+        Ok(Expression::new(
+            Self::Dependent.into(),
+            vec![mdp, location],
+            Sort::memory_dependence_predictor(),
+        ))
+    }
+}
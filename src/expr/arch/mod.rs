@@ -1,11 +1,13 @@
 mod branch_target_buffer;
 mod cache;
 mod memory;
+mod memory_dependence_predictor;
 mod pattern_history_table;
 mod predictor;
 
 pub use self::branch_target_buffer::BranchTargetBuffer;
 pub use self::cache::{Cache, CacheAddresses, CacheValue};
 pub use self::memory::{Memory, MemoryValue};
+pub use self::memory_dependence_predictor::MemoryDependencePredictor;
 pub use self::pattern_history_table::PatternHistoryTable;
 pub use self::predictor::Predictor;
@@ -1,9 +1,10 @@
 use crate::environment;
 use crate::error::Result;
 use crate::expr::{Expression, Sort, Variable};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Predictor {
     SpeculationWindow,
     Speculate,
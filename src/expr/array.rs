@@ -1,9 +1,10 @@
 use crate::error::Result;
 use crate::expr::{Constant, Expression};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Array {
     Select,
     Store,
@@ -47,7 +48,7 @@ impl Array {
     }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct ArrayValue {
     entries: BTreeMap<Constant, Constant>,
     default_value: Option<Constant>,
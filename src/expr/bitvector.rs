@@ -3,9 +3,10 @@ use crate::error::Result;
 use crate::expr::{Constant, Expression, Sort, Variable};
 pub use falcon::il::Constant as BitVectorValue;
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum BitVector {
     ToBoolean,
     FromBoolean(usize),
@@ -1,9 +1,10 @@
 use crate::error::Result;
 use crate::expr::{ArrayValue, BitVectorValue, CacheValue, MemoryValue};
 use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Constant {
     Boolean(bool),
     Integer(u64),
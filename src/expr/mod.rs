@@ -1,4 +1,6 @@
 use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 
 mod arch;
@@ -25,7 +27,7 @@ pub use self::sort::Sort;
 pub use self::tuple::Tuple;
 pub use self::variable::Variable;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Operator {
     Variable(Variable),
     Constant(Constant),
@@ -45,6 +47,7 @@ pub enum Operator {
     Cache(Cache),
     BranchTargetBuffer(BranchTargetBuffer),
     PatternHistoryTable(PatternHistoryTable),
+    MemoryDependencePredictor(MemoryDependencePredictor),
 }
 
 macro_rules! impl_operator_from {
@@ -68,6 +71,7 @@ impl_operator_from!(Predictor);
 impl_operator_from!(Cache);
 impl_operator_from!(BranchTargetBuffer);
 impl_operator_from!(PatternHistoryTable);
+impl_operator_from!(MemoryDependencePredictor);
 
 impl fmt::Display for Operator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -89,11 +93,12 @@ impl fmt::Display for Operator {
             Self::Cache(op) => op.fmt(f),
             Self::BranchTargetBuffer(op) => op.fmt(f),
             Self::PatternHistoryTable(op) => op.fmt(f),
+            Self::MemoryDependencePredictor(op) => op.fmt(f),
         }
     }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Expression {
     operator: Operator,
     operands: Vec<Expression>,
@@ -214,6 +219,22 @@ impl Expression {
         variables
     }
 
+    /// Returns a copy of the expression in which each variable with a definition in `defs`
+    /// is replaced by its definition.
+    pub fn substitute(&self, defs: &HashMap<Variable, Expression>) -> Self {
+        if let Operator::Variable(variable) = &self.operator {
+            if let Some(value) = defs.get(variable) {
+                return value.clone();
+            }
+        }
+
+        let mut result = self.clone();
+        for operand in &mut result.operands {
+            *operand = operand.substitute(defs);
+        }
+        result
+    }
+
     pub fn subexpressions(&self) -> Vec<&Self> {
         vec![self]
             .into_iter()
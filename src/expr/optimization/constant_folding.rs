@@ -97,6 +97,14 @@ fn evaluate_bitvec(op: &BitVector, values: &[BitVectorValue]) -> Option<Expressi
         (UMod, [lhs, rhs]) => lhs.modu(rhs).map(BitVector::constant).ok(),
         (ZeroExtend(i), [v]) => v.zext(*i + v.bits()).map(BitVector::constant).ok(),
         (SignExtend(i), [v]) => v.sext(*i + v.bits()).map(BitVector::constant).ok(),
+        (ULt, [lhs, rhs]) => lhs.cmpltu(rhs).map(|v| Boolean::constant(v.is_one())).ok(),
+        (ULe, [lhs, rhs]) => rhs.cmpltu(lhs).map(|v| Boolean::constant(!v.is_one())).ok(),
+        (UGt, [lhs, rhs]) => rhs.cmpltu(lhs).map(|v| Boolean::constant(v.is_one())).ok(),
+        (UGe, [lhs, rhs]) => lhs.cmpltu(rhs).map(|v| Boolean::constant(!v.is_one())).ok(),
+        (SLt, [lhs, rhs]) => lhs.cmplts(rhs).map(|v| Boolean::constant(v.is_one())).ok(),
+        (SLe, [lhs, rhs]) => rhs.cmplts(lhs).map(|v| Boolean::constant(!v.is_one())).ok(),
+        (SGt, [lhs, rhs]) => rhs.cmplts(lhs).map(|v| Boolean::constant(v.is_one())).ok(),
+        (SGe, [lhs, rhs]) => lhs.cmplts(rhs).map(|v| Boolean::constant(!v.is_one())).ok(),
         _ => None,
     }
 }
@@ -188,4 +196,36 @@ mod tests {
         // THEN
         assert_eq!(expr, BitVector::constant_u64(42, 32));
     }
+
+    #[test]
+    fn test_fold_bitvec_unsigned_less_than() {
+        // GIVEN
+        let mut expr = BitVector::ult(
+            BitVector::constant_u64(1, 32),
+            BitVector::constant_u64(2, 32),
+        )
+        .unwrap();
+
+        // WHEN
+        expr.fold();
+
+        // THEN
+        assert_eq!(expr, Boolean::constant(true));
+    }
+
+    #[test]
+    fn test_fold_bitvec_signed_greater_equal() {
+        // GIVEN
+        let mut expr = BitVector::sge(
+            BitVector::constant_u64(0xFFFF_FFFF, 32),
+            BitVector::constant_u64(0, 32),
+        )
+        .unwrap();
+
+        // WHEN
+        expr.fold();
+
+        // THEN
+        assert_eq!(expr, Boolean::constant(false));
+    }
 }
@@ -0,0 +1,289 @@
+//! Differential Testing of Expression Optimizations
+//!
+//! Random Boolean and bit-vector expressions are optimized (simplified and folded),
+//! afterwards the optimized expression has to evaluate to the same value as the original one
+//! under random variable assignments.
+//! A wrong rewriting may silently turn a real leak into a "safe" verdict, as the optimizations are
+//! applied to all encoded formulas.
+//!
+//! Expressions are evaluated bottom-up by the simulation's evaluator, independent of the rewritings.
+//! The number of generated expressions can be increased by setting `SPECBMC_FUZZ_RUNS`,
+//! failures report the seed of the expression, which makes them reproducible.
+
+use crate::expr::{BitVector, Boolean, Constant, Expression, Fold, Simplify, Variable};
+use crate::solver::{evaluate, Random};
+use std::collections::HashMap;
+
+const DEFAULT_RUNS: u64 = 2000;
+const ASSIGNMENTS_PER_EXPRESSION: usize = 8;
+const MAX_DEPTH: usize = 4;
+const BITS: usize = 8;
+/// Max. number of previously generated expressions which may be reused as operands
+const MAX_REUSABLE: usize = 16;
+
+fn runs() -> u64 {
+    std::env::var("SPECBMC_FUZZ_RUNS")
+        .ok()
+        .and_then(|runs| runs.parse().ok())
+        .unwrap_or(DEFAULT_RUNS)
+}
+
+fn variables() -> Vec<Variable> {
+    vec![
+        BitVector::variable("x", BITS),
+        BitVector::variable("y", BITS),
+        Boolean::variable("a"),
+        Boolean::variable("b"),
+    ]
+}
+
+struct ExpressionGenerator {
+    rng: Random,
+    /// Previously generated expressions, reusing them triggers rewritings of equal operands (e.g. `e = e`)
+    generated: Vec<Expression>,
+}
+
+impl ExpressionGenerator {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: Random::new(seed),
+            generated: Vec::new(),
+        }
+    }
+
+    fn choose(&mut self, n: usize) -> usize {
+        (self.rng.next_u64() % n as u64) as usize
+    }
+
+    fn assignment(&mut self) -> HashMap<Variable, Constant> {
+        variables()
+            .into_iter()
+            .filter_map(|var| {
+                let value = self.rng.sample(var.sort())?;
+                Some((var, value))
+            })
+            .collect()
+    }
+
+    fn remember(&mut self, expr: Expression) -> Expression {
+        if self.generated.len() == MAX_REUSABLE {
+            self.generated.remove(0);
+        }
+        self.generated.push(expr.clone());
+        expr
+    }
+
+    fn reuse(&mut self, boolean: bool) -> Option<Expression> {
+        let candidates: Vec<Expression> = self
+            .generated
+            .iter()
+            .filter(|expr| expr.sort().is_boolean() == boolean)
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let index = self.choose(candidates.len());
+        Some(candidates[index].clone())
+    }
+
+    fn boolean(&mut self, depth: usize) -> Expression {
+        if depth == 0 || self.choose(4) == 0 {
+            return self.boolean_leaf();
+        }
+
+        let depth = depth - 1;
+        let expr = match self.choose(11) {
+            0 => Boolean::not(self.boolean(depth)).unwrap(),
+            1 => {
+                let operands: Vec<Expression> = (0..1 + self.choose(3))
+                    .map(|_| self.boolean(depth))
+                    .collect();
+                Boolean::conjunction(&operands).unwrap()
+            }
+            2 => {
+                let operands: Vec<Expression> = (0..1 + self.choose(3))
+                    .map(|_| self.boolean(depth))
+                    .collect();
+                Boolean::disjunction(&operands).unwrap()
+            }
+            3 => Boolean::imply(self.boolean(depth), self.boolean(depth)).unwrap(),
+            4 => Boolean::xor(self.boolean(depth), self.boolean(depth)).unwrap(),
+            5 => Expression::equal(self.boolean(depth), self.boolean(depth)).unwrap(),
+            6 => Expression::equal(self.bit_vector(depth), self.bit_vector(depth)).unwrap(),
+            7 => Expression::ite(
+                self.boolean(depth),
+                self.boolean(depth),
+                self.boolean(depth),
+            )
+            .unwrap(),
+            8 => BitVector::to_boolean(self.bit_vector(depth)).unwrap(),
+            _ => {
+                let lhs = self.bit_vector(depth);
+                let rhs = self.bit_vector(depth);
+                match self.choose(8) {
+                    0 => BitVector::ult(lhs, rhs),
+                    1 => BitVector::ule(lhs, rhs),
+                    2 => BitVector::ugt(lhs, rhs),
+                    3 => BitVector::uge(lhs, rhs),
+                    4 => BitVector::slt(lhs, rhs),
+                    5 => BitVector::sle(lhs, rhs),
+                    6 => BitVector::sgt(lhs, rhs),
+                    _ => BitVector::sge(lhs, rhs),
+                }
+                .unwrap()
+            }
+        };
+        self.remember(expr)
+    }
+
+    fn boolean_leaf(&mut self) -> Expression {
+        match self.choose(3) {
+            0 => Boolean::constant(self.choose(2) == 0),
+            1 => {
+                if let Some(expr) = self.reuse(true) {
+                    return expr;
+                }
+                Boolean::variable("a").into()
+            }
+            _ => {
+                if self.choose(2) == 0 {
+                    Boolean::variable("a").into()
+                } else {
+                    Boolean::variable("b").into()
+                }
+            }
+        }
+    }
+
+    fn bit_vector(&mut self, depth: usize) -> Expression {
+        if depth == 0 || self.choose(4) == 0 {
+            return self.bit_vector_leaf();
+        }
+
+        let depth = depth - 1;
+        let expr = match self.choose(7) {
+            0 => BitVector::not(self.bit_vector(depth)).unwrap(),
+            1 => BitVector::neg(self.bit_vector(depth)).unwrap(),
+            2 => Expression::ite(
+                self.boolean(depth),
+                self.bit_vector(depth),
+                self.bit_vector(depth),
+            )
+            .unwrap(),
+            3 => BitVector::from_boolean(BITS, self.boolean(depth)).unwrap(),
+            4 => {
+                // Nested extensions, truncated to the original width
+                let inner = BitVector::zero_extend(1 + self.choose(8), self.bit_vector(depth));
+                let outer = BitVector::zero_extend(1 + self.choose(8), inner.unwrap()).unwrap();
+                BitVector::extract(BITS - 1, 0, outer).unwrap()
+            }
+            5 => {
+                let inner = BitVector::sign_extend(1 + self.choose(8), self.bit_vector(depth));
+                let outer = BitVector::sign_extend(1 + self.choose(8), inner.unwrap()).unwrap();
+                let lowest_bit = self.choose(outer.sort().unwrap_bit_vector() - BITS + 1);
+                BitVector::extract(lowest_bit + BITS - 1, lowest_bit, outer).unwrap()
+            }
+            _ => {
+                let lhs = self.bit_vector(depth);
+                let rhs = self.bit_vector(depth);
+                match self.choose(9) {
+                    0 => BitVector::add(lhs, rhs),
+                    1 => BitVector::sub(lhs, rhs),
+                    2 => BitVector::mul(lhs, rhs),
+                    3 => BitVector::and(lhs, rhs),
+                    4 => BitVector::or(lhs, rhs),
+                    5 => BitVector::xor(lhs, rhs),
+                    6 => BitVector::shl(lhs, rhs),
+                    7 => BitVector::lshr(lhs, rhs),
+                    _ => BitVector::saturating_sub(lhs, rhs),
+                }
+                .unwrap()
+            }
+        };
+        self.remember(expr)
+    }
+
+    fn bit_vector_leaf(&mut self) -> Expression {
+        match self.choose(4) {
+            // zero and one trigger most rewritings (e.g. `e + 0`, `e * 1`)
+            0 => BitVector::constant_u64(self.choose(2) as u64, BITS),
+            1 => BitVector::constant_u64(self.rng.next_u64() % (1 << BITS), BITS),
+            2 => {
+                if let Some(expr) = self.reuse(false) {
+                    return expr;
+                }
+                BitVector::variable("x", BITS).into()
+            }
+            _ => {
+                if self.choose(2) == 0 {
+                    BitVector::variable("x", BITS).into()
+                } else {
+                    BitVector::variable("y", BITS).into()
+                }
+            }
+        }
+    }
+}
+
+/// Checks that `optimize` preserves the value of random expressions under random assignments.
+fn fuzz(optimize: impl Fn(&mut Expression)) {
+    let mut compared = 0;
+
+    for seed in 0..runs() {
+        let mut generator = ExpressionGenerator::new(seed);
+        let original = if generator.choose(2) == 0 {
+            generator.boolean(MAX_DEPTH)
+        } else {
+            generator.bit_vector(MAX_DEPTH)
+        };
+
+        let mut optimized = original.clone();
+        optimize(&mut optimized);
+        assert_eq!(
+            original.sort(),
+            optimized.sort(),
+            "Optimization changed the sort (seed {}):\n  {}\n  -> {}",
+            seed,
+            original,
+            optimized
+        );
+
+        for _ in 0..ASSIGNMENTS_PER_EXPRESSION {
+            let values = generator.assignment();
+            // Operators which can't be evaluated concretely are skipped
+            let (expected, actual) = match (
+                evaluate(&original, values.clone()),
+                evaluate(&optimized, values.clone()),
+            ) {
+                (Some(expected), Some(actual)) => (expected, actual),
+                _ => continue,
+            };
+            assert_eq!(
+                expected, actual,
+                "Optimization changed the value (seed {}):\n  {}\n  -> {}\n  with {:?}",
+                seed, original, optimized, values
+            );
+            compared += 1;
+        }
+    }
+
+    // Otherwise the harness would silently pass without checking anything
+    assert!(compared > 0);
+}
+
+#[test]
+fn fuzz_simplification_preserves_value() {
+    fuzz(|expr| {
+        expr.simplify();
+    });
+}
+
+#[test]
+fn fuzz_constant_folding_and_simplification_preserve_value() {
+    fuzz(|expr| {
+        expr.fold();
+        expr.simplify();
+        expr.fold();
+    });
+}
@@ -1,5 +1,7 @@
 mod constant_folding;
 mod expression_simplification;
+#[cfg(test)]
+mod fuzz;
 
 pub use constant_folding::Fold;
 pub use expression_simplification::Simplify;
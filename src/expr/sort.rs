@@ -1,8 +1,9 @@
 use crate::environment;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Sort {
     Boolean,
     Integer,
@@ -16,6 +17,7 @@ pub enum Sort {
     Cache,
     BranchTargetBuffer,
     PatternHistoryTable,
+    MemoryDependencePredictor,
 }
 
 impl Sort {
@@ -72,6 +74,10 @@ impl Sort {
         Self::PatternHistoryTable
     }
 
+    pub fn memory_dependence_predictor() -> Self {
+        Self::MemoryDependencePredictor
+    }
+
     pub fn is_boolean(&self) -> bool {
         matches!(self, Self::Boolean)
     }
@@ -120,11 +126,18 @@ impl Sort {
         matches!(self, Self::PatternHistoryTable)
     }
 
+    pub fn is_memory_dependence_predictor(&self) -> bool {
+        matches!(self, Self::MemoryDependencePredictor)
+    }
+
     pub fn expect_boolean(&self) -> Result<()> {
         if self.is_boolean() {
             Ok(())
         } else {
-            Err(format!("Expected Boolean but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Boolean".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -132,7 +145,10 @@ impl Sort {
         if self.is_integer() {
             Ok(())
         } else {
-            Err(format!("Expected Integer but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Integer".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -140,7 +156,10 @@ impl Sort {
         if self.is_bit_vector() {
             Ok(())
         } else {
-            Err(format!("Expected BitVec but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "BitVec".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -148,7 +167,10 @@ impl Sort {
         if self.is_word() {
             Ok(())
         } else {
-            Err(format!("Expected Word but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Word".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -156,7 +178,10 @@ impl Sort {
         if self.is_array() {
             Ok(())
         } else {
-            Err(format!("Expected Array but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Array".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -164,7 +189,10 @@ impl Sort {
         if self.is_list() {
             Ok(())
         } else {
-            Err(format!("Expected List but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "List".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -172,7 +200,10 @@ impl Sort {
         if self.is_tuple() {
             Ok(())
         } else {
-            Err(format!("Expected Tuple but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Tuple".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -180,7 +211,10 @@ impl Sort {
         if self.is_memory() {
             Ok(())
         } else {
-            Err(format!("Expected Memory but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Memory".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -188,7 +222,10 @@ impl Sort {
         if self.is_cache() {
             Ok(())
         } else {
-            Err(format!("Expected Cache but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Cache".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -196,7 +233,10 @@ impl Sort {
         if self.is_predictor() {
             Ok(())
         } else {
-            Err(format!("Expected Predictor but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "Predictor".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -204,7 +244,10 @@ impl Sort {
         if self.is_branch_target_buffer() {
             Ok(())
         } else {
-            Err(format!("Expected BranchTargetBuffer but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "BranchTargetBuffer".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -212,7 +255,21 @@ impl Sort {
         if self.is_pattern_history_table() {
             Ok(())
         } else {
-            Err(format!("Expected PatternHistoryTable but was {}", self).into())
+            Err(Error::SortMismatch {
+                expected: "PatternHistoryTable".to_owned(),
+                actual: self.clone(),
+            })
+        }
+    }
+
+    pub fn expect_memory_dependence_predictor(&self) -> Result<()> {
+        if self.is_memory_dependence_predictor() {
+            Ok(())
+        } else {
+            Err(Error::SortMismatch {
+                expected: "MemoryDependencePredictor".to_owned(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -220,7 +277,10 @@ impl Sort {
         if self == sort {
             Ok(())
         } else {
-            Err(format!("Expected {} but was {}", sort, self).into())
+            Err(Error::SortMismatch {
+                expected: sort.to_string(),
+                actual: self.clone(),
+            })
         }
     }
 
@@ -278,6 +338,7 @@ impl fmt::Display for Sort {
             Self::Cache => write!(f, "Cache"),
             Self::BranchTargetBuffer => write!(f, "BranchTargetBuffer"),
             Self::PatternHistoryTable => write!(f, "PatternHistoryTable"),
+            Self::MemoryDependencePredictor => write!(f, "MemoryDependencePredictor"),
         }
     }
 }
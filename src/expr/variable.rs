@@ -1,7 +1,8 @@
 use crate::expr::Sort;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Variable {
     name: String,
     sort: Box<Sort>,
@@ -1,8 +1,12 @@
-use crate::hir::{Block, Function, Instruction, Operation, Program};
+use crate::hir::analysis::indirect_call_targets;
+use crate::hir::{Function, Operation, Program};
 use falcon::graph;
+use std::collections::BTreeMap;
 
 /// Computes the call graph of the program.
-/// Indirect calls are omitted.
+///
+/// Indirect calls are resolved through the code pointers of the program (see `indirect_call_targets`),
+/// unresolved indirect calls may call any function whose address is taken.
 pub fn call_graph(program: &Program) -> CallGraph {
     let mut call_graph = CallGraph::new();
 
@@ -16,18 +20,16 @@ pub fn call_graph(program: &Program) -> CallGraph {
     }
 
     for func in program.functions() {
-        let mut call_targets = function_direct_call_targets(func);
+        // De-duplicated targets to avoid edge insertion conflicts
+        let call_targets = function_call_targets(program, func);
 
-        // De-duplicate targets to avoid edge insertion conflicts
-        call_targets.sort_unstable();
-        call_targets.dedup();
-
-        for target in call_targets {
+        for (target, indirect) in call_targets {
             if program.function_by_address(target).is_some() {
                 call_graph
                     .insert_edge(Call {
                         caller_address: func.address(),
                         callee_address: target,
+                        indirect,
                     })
                     .unwrap();
             }
@@ -63,6 +65,7 @@ impl graph::Vertex for FunctionInfo {
 pub struct Call {
     caller_address: u64,
     callee_address: u64,
+    indirect: bool,
 }
 
 impl Call {
@@ -73,6 +76,11 @@ impl Call {
     pub fn callee_address(&self) -> u64 {
         self.callee_address
     }
+
+    /// Returns true if the callee is only called indirectly by the caller.
+    pub fn is_indirect(&self) -> bool {
+        self.indirect
+    }
 }
 
 impl graph::Edge for Call {
@@ -85,32 +93,39 @@ impl graph::Edge for Call {
     }
 
     fn dot_label(&self) -> String {
-        String::default()
+        if self.indirect {
+            "indirect".to_owned()
+        } else {
+            String::default()
+        }
     }
 }
 
 pub type CallGraph = graph::Graph<FunctionInfo, Call>;
 
-fn function_direct_call_targets(function: &Function) -> Vec<u64> {
-    function
-        .control_flow_graph()
-        .blocks()
-        .into_iter()
-        .flat_map(block_direct_call_targets)
-        .collect()
-}
+/// Returns the call targets of the function, each with a flag telling if it is only called indirectly.
+fn function_call_targets(program: &Program, function: &Function) -> BTreeMap<u64, bool> {
+    let mut targets = BTreeMap::new();
 
-fn block_direct_call_targets(block: &Block) -> Vec<u64> {
-    block
-        .instructions()
-        .iter()
-        .filter_map(instruction_direct_call_targets)
-        .collect()
-}
+    for block in function.control_flow_graph().blocks() {
+        for (index, inst) in block.instructions().iter().enumerate() {
+            let target = match inst.operation() {
+                Operation::Call { target } => target,
+                _ => continue,
+            };
 
-fn instruction_direct_call_targets(inst: &Instruction) -> Option<u64> {
-    match inst.operation() {
-        Operation::Call { target } => target.try_into().ok(),
-        _ => None,
+            if let Ok(address) = target.try_into() {
+                targets.insert(address, false);
+                continue;
+            }
+
+            let indirect_targets = indirect_call_targets(program.code_pointers(), block, index)
+                .unwrap_or_else(|| program.address_taken_functions());
+            for address in indirect_targets {
+                targets.entry(address).or_insert(true);
+            }
+        }
     }
+
+    targets
 }
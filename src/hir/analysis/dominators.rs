@@ -0,0 +1,322 @@
+use crate::error::{Error, Result};
+use crate::hir::ControlFlowGraph;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Dominance information of a `ControlFlowGraph`.
+///
+/// For post-dominance (computed on the reversed CFG starting at the exit block) "dominates"
+/// reads as "post-dominates" and the dominance frontier is the post-dominance frontier
+/// (the control dependences of a block).
+#[derive(Clone, Debug)]
+pub struct Dominators {
+    root: usize,
+    /// Immediate dominator of each block reachable from the root (the root dominates itself).
+    immediate_dominators: HashMap<usize, usize>,
+    frontiers: HashMap<usize, BTreeSet<usize>>,
+}
+
+impl Dominators {
+    /// Returns the root of the dominator tree (entry or exit block).
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Returns whether the block is reachable from the root.
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.immediate_dominators.contains_key(&block)
+    }
+
+    /// Returns the immediate dominator of the block,
+    /// or `None` for the root and blocks unreachable from the root.
+    pub fn immediate_dominator(&self, block: usize) -> Option<usize> {
+        if block == self.root {
+            return None;
+        }
+        self.immediate_dominators.get(&block).copied()
+    }
+
+    /// Returns whether `a` dominates `b` (each block dominates itself).
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        let mut block = b;
+        loop {
+            if block == a {
+                return true;
+            }
+            match self.immediate_dominator(block) {
+                Some(idom) => block = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns whether `a` dominates `b` and `a` is not `b`.
+    pub fn strictly_dominates(&self, a: usize, b: usize) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// Returns all dominators of the block, starting with the block itself up to the root.
+    pub fn dominators(&self, block: usize) -> Vec<usize> {
+        let mut dominators = Vec::new();
+        if !self.is_reachable(block) {
+            return dominators;
+        }
+        let mut block = Some(block);
+        while let Some(b) = block {
+            dominators.push(b);
+            block = self.immediate_dominator(b);
+        }
+        dominators
+    }
+
+    /// Returns the children of the block in the dominator tree.
+    pub fn children(&self, block: usize) -> BTreeSet<usize> {
+        self.immediate_dominators
+            .iter()
+            .filter(|(&b, &idom)| b != self.root && idom == block)
+            .map(|(&b, _)| b)
+            .collect()
+    }
+
+    /// Returns the dominance frontier of the block.
+    pub fn dominance_frontier(&self, block: usize) -> Result<&BTreeSet<usize>> {
+        self.frontiers
+            .get(&block)
+            .ok_or(Error::UnreachableBlock(block))
+    }
+
+    /// Returns the iterated dominance frontier of the given blocks,
+    /// e.g. the blocks which require a phi node for a variable defined in `blocks`.
+    pub fn iterated_dominance_frontier<I>(&self, blocks: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let mut frontier = BTreeSet::new();
+        let mut work_list: Vec<usize> = blocks.into_iter().collect();
+        while let Some(block) = work_list.pop() {
+            if let Some(block_frontier) = self.frontiers.get(&block) {
+                for &df in block_frontier {
+                    if frontier.insert(df) {
+                        work_list.push(df);
+                    }
+                }
+            }
+        }
+        frontier
+    }
+}
+
+/// Computes the dominators of each block reachable from the CFG entry.
+pub fn dominators(cfg: &ControlFlowGraph) -> Result<Dominators> {
+    compute_dominators(
+        cfg.entry()?,
+        |block| cfg.successor_indices(block),
+        |block| cfg.predecessor_indices(block),
+    )
+}
+
+/// Computes the post-dominators of each block from which the CFG exit is reachable.
+pub fn post_dominators(cfg: &ControlFlowGraph) -> Result<Dominators> {
+    compute_dominators(
+        cfg.exit()?,
+        |block| cfg.predecessor_indices(block),
+        |block| cfg.successor_indices(block),
+    )
+}
+
+/// Computes the dominators with the iterative algorithm of Cooper, Harvey and Kennedy,
+/// see "A Simple, Fast Dominance Algorithm".
+fn compute_dominators<S, P>(root: usize, successors: S, predecessors: P) -> Result<Dominators>
+where
+    S: Fn(usize) -> Result<Vec<usize>>,
+    P: Fn(usize) -> Result<Vec<usize>>,
+{
+    let post_order = compute_post_order(root, &successors)?;
+    let post_order_number: HashMap<usize, usize> = post_order
+        .iter()
+        .enumerate()
+        .map(|(number, &block)| (block, number))
+        .collect();
+
+    let intersect = |idoms: &HashMap<usize, usize>, mut a: usize, mut b: usize| -> usize {
+        while a != b {
+            while post_order_number[&a] < post_order_number[&b] {
+                a = idoms[&a];
+            }
+            while post_order_number[&b] < post_order_number[&a] {
+                b = idoms[&b];
+            }
+        }
+        a
+    };
+
+    let mut idoms: HashMap<usize, usize> = HashMap::new();
+    idoms.insert(root, root);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in post_order.iter().rev().filter(|&&block| block != root) {
+            let mut new_idom = None;
+            for predecessor in predecessors(block)? {
+                if !idoms.contains_key(&predecessor) {
+                    continue; // not processed yet or unreachable
+                }
+                new_idom = Some(match new_idom {
+                    Some(idom) => intersect(&idoms, predecessor, idom),
+                    None => predecessor,
+                });
+            }
+            if let Some(new_idom) = new_idom {
+                if idoms.get(&block) != Some(&new_idom) {
+                    idoms.insert(block, new_idom);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut frontiers: HashMap<usize, BTreeSet<usize>> = post_order
+        .iter()
+        .map(|&block| (block, BTreeSet::new()))
+        .collect();
+    for &block in &post_order {
+        let reachable_predecessors: Vec<usize> = predecessors(block)?
+            .into_iter()
+            .filter(|predecessor| idoms.contains_key(predecessor))
+            .collect();
+        if reachable_predecessors.len() < 2 {
+            continue;
+        }
+        for predecessor in reachable_predecessors {
+            let mut runner = predecessor;
+            while runner != idoms[&block] {
+                frontiers.get_mut(&runner).unwrap().insert(block);
+                runner = idoms[&runner];
+            }
+        }
+    }
+
+    Ok(Dominators {
+        root,
+        immediate_dominators: idoms,
+        frontiers,
+    })
+}
+
+fn compute_post_order<S>(root: usize, successors: &S) -> Result<Vec<usize>>
+where
+    S: Fn(usize) -> Result<Vec<usize>>,
+{
+    let mut post_order = Vec::new();
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut stack: Vec<(usize, Vec<usize>)> = vec![(root, successors(root)?)];
+    visited.insert(root);
+
+    while let Some((block, pending)) = stack.last_mut() {
+        match pending.pop() {
+            Some(successor) => {
+                if visited.insert(successor) {
+                    let successor_successors = successors(successor)?;
+                    stack.push((successor, successor_successors));
+                }
+            }
+            None => {
+                post_order.push(*block);
+                stack.pop();
+            }
+        }
+    }
+
+    Ok(post_order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hir::Block;
+
+    //      0
+    //     / \
+    //    1   2
+    //     \ / \
+    //      3   4
+    //       \ /
+    //        5
+    fn diamond_cfg() -> ControlFlowGraph {
+        let mut cfg = ControlFlowGraph::new();
+        for index in 0..6 {
+            cfg.add_block(Block::new(index)).unwrap();
+        }
+        cfg.unconditional_edge(0, 1).unwrap();
+        cfg.unconditional_edge(0, 2).unwrap();
+        cfg.unconditional_edge(1, 3).unwrap();
+        cfg.unconditional_edge(2, 3).unwrap();
+        cfg.unconditional_edge(2, 4).unwrap();
+        cfg.unconditional_edge(3, 5).unwrap();
+        cfg.unconditional_edge(4, 5).unwrap();
+        cfg.set_entry(0).unwrap();
+        cfg.set_exit(5).unwrap();
+        cfg
+    }
+
+    #[test]
+    fn test_dominators() {
+        // GIVEN
+        let cfg = diamond_cfg();
+
+        // WHEN
+        let dominators = dominators(&cfg).unwrap();
+
+        // THEN
+        assert_eq!(dominators.immediate_dominator(0), None);
+        assert_eq!(dominators.immediate_dominator(3), Some(0));
+        assert_eq!(dominators.immediate_dominator(4), Some(2));
+        assert_eq!(dominators.immediate_dominator(5), Some(0));
+        assert!(dominators.dominates(2, 4));
+        assert!(dominators.dominates(4, 4));
+        assert!(!dominators.strictly_dominates(4, 4));
+        assert!(!dominators.dominates(1, 3));
+        assert_eq!(dominators.dominators(4), vec![4, 2, 0]);
+        assert_eq!(
+            dominators.children(0),
+            vec![1, 2, 3, 5].into_iter().collect()
+        );
+        assert_eq!(
+            dominators.dominance_frontier(1).unwrap(),
+            &vec![3].into_iter().collect()
+        );
+        assert_eq!(
+            dominators.dominance_frontier(2).unwrap(),
+            &vec![3, 5].into_iter().collect()
+        );
+        assert_eq!(
+            dominators.iterated_dominance_frontier(vec![1]),
+            vec![3, 5].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_post_dominators() {
+        // GIVEN
+        let cfg = diamond_cfg();
+
+        // WHEN
+        let post_dominators = post_dominators(&cfg).unwrap();
+
+        // THEN
+        assert_eq!(post_dominators.root(), 5);
+        assert_eq!(post_dominators.immediate_dominator(1), Some(3));
+        assert_eq!(post_dominators.immediate_dominator(2), Some(5));
+        assert_eq!(post_dominators.immediate_dominator(0), Some(5));
+        assert!(post_dominators.dominates(3, 1));
+        assert!(!post_dominators.dominates(3, 2));
+        // Block 3 is control dependent on the branches in block 0 and 2
+        assert_eq!(
+            post_dominators.dominance_frontier(3).unwrap(),
+            &vec![0, 2].into_iter().collect()
+        );
+    }
+}
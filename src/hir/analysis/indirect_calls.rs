@@ -0,0 +1,212 @@
+//! Indirect Call Resolution
+//!
+//! Resolves the targets of indirect calls (and indirect jumps) whose target is loaded from memory
+//! holding code pointers, e.g. vtables, function pointer tables or jump tables:
+//!
+//! - `call [table]` may only call the code pointer stored at `table`.
+//! - `call [table + index * 8]` may call any of the code pointers stored consecutively from `table` on.
+//!
+//! The load and the computation of its address must be located in the same block as the call.
+
+use crate::environment::WORD_SIZE;
+use crate::expr::{BitVector, Expression, Fold, Operator, Variable};
+use crate::hir::{Block, Operation};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
+
+/// Resolves the possible targets of the indirect call (or jump) at `index` in `block`,
+/// given the code pointers stored in memory (location to target).
+///
+/// Returns `None` if the instruction is no indirect call or if its targets are unknown.
+pub fn indirect_call_targets(
+    code_pointers: &BTreeMap<u64, u64>,
+    block: &Block,
+    index: usize,
+) -> Option<BTreeSet<u64>> {
+    let target = match block.instruction(index)?.operation() {
+        Operation::Call { target } | Operation::Branch { target } => target,
+        _ => return None,
+    };
+    if target.is_constant() {
+        return None;
+    }
+
+    let mut address = loaded_from(block, index, target)?;
+    address.fold();
+
+    let targets = match address.operator() {
+        Operator::Constant(_) => {
+            let location = u64::try_from(&address).ok()?;
+            code_pointers.get(&location).into_iter().cloned().collect()
+        }
+        Operator::BitVector(BitVector::Add) => {
+            let table = address
+                .operands()
+                .iter()
+                .find_map(|operand| u64::try_from(operand).ok())?;
+            jump_table_targets(code_pointers, table)
+        }
+        _ => BTreeSet::new(),
+    };
+
+    if targets.is_empty() {
+        None
+    } else {
+        Some(targets)
+    }
+}
+
+/// Returns the code pointers stored consecutively from `table` on.
+fn jump_table_targets(code_pointers: &BTreeMap<u64, u64>, table: u64) -> BTreeSet<u64> {
+    let pointer_size = (WORD_SIZE / 8) as u64;
+
+    let mut targets = BTreeSet::new();
+    let mut location = table;
+    while let Some(&target) = code_pointers.get(&location) {
+        targets.insert(target);
+        location += pointer_size;
+    }
+    targets
+}
+
+/// Returns the address from which `target` is loaded,
+/// in terms of the variable values on entry of the block.
+fn loaded_from(block: &Block, index: usize, target: &Expression) -> Option<Expression> {
+    // Values of the variables assigned before `index`
+    let mut values: HashMap<Variable, Expression> = HashMap::new();
+    // Addresses of the variables loaded before `index`
+    let mut loads: HashMap<Variable, Expression> = HashMap::new();
+
+    for inst in &block.instructions()[..index] {
+        // (variable, value or address, is load)
+        let definition = match inst.operation() {
+            Operation::Assign { variable, expr } => {
+                Some((variable, expr.substitute(&values), false))
+            }
+            Operation::Load {
+                variable, address, ..
+            } => Some((variable, address.substitute(&values), true)),
+            _ => None,
+        };
+
+        for variable in inst.variables_written() {
+            // Forget everything which depends on the old value of the overwritten variable
+            values.retain(|_, value| !value.variables().contains(&variable));
+            loads.retain(|_, address| !address.variables().contains(&variable));
+            values.remove(variable);
+            loads.remove(variable);
+        }
+
+        match definition {
+            Some((variable, value, false)) => {
+                values.insert(variable.clone(), value);
+            }
+            Some((variable, address, true)) => {
+                loads.insert(variable.clone(), address);
+            }
+            None => {}
+        }
+    }
+
+    match target.substitute(&values).operator() {
+        Operator::Variable(variable) => loads.get(variable).cloned(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_pointers() -> BTreeMap<u64, u64> {
+        vec![(0x2000, 0x100), (0x2008, 0x200), (0x2010, 0x300)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_call_through_single_code_pointer() {
+        // Given: rax = load(0x2008); rbx = rax; call rbx
+        let mut block = Block::new(0);
+        block
+            .load(
+                BitVector::word_variable("rax"),
+                BitVector::word_constant(0x2008),
+            )
+            .unwrap();
+        block
+            .assign(
+                BitVector::word_variable("rbx"),
+                BitVector::word_variable("rax").into(),
+            )
+            .unwrap();
+        block.call(BitVector::word_variable("rbx").into()).unwrap();
+
+        // When
+        let targets = indirect_call_targets(&code_pointers(), &block, 2);
+
+        // Then
+        assert_eq!(targets, Some(vec![0x200].into_iter().collect()));
+    }
+
+    #[test]
+    fn test_call_through_jump_table() {
+        // Given: rax = 0x2000 + rdi * 8; rax = load(rax); call rax
+        let mut block = Block::new(0);
+        block
+            .assign(
+                BitVector::word_variable("rax"),
+                BitVector::add(
+                    BitVector::word_constant(0x2000),
+                    BitVector::mul(
+                        BitVector::word_variable("rdi").into(),
+                        BitVector::word_constant(8),
+                    )
+                    .unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        block
+            .load(
+                BitVector::word_variable("rax"),
+                BitVector::word_variable("rax").into(),
+            )
+            .unwrap();
+        block.call(BitVector::word_variable("rax").into()).unwrap();
+
+        // When
+        let targets = indirect_call_targets(&code_pointers(), &block, 2);
+
+        // Then
+        assert_eq!(
+            targets,
+            Some(vec![0x100, 0x200, 0x300].into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn test_call_through_overwritten_variable_is_unresolved() {
+        // Given: rax = load(0x2000); rax = rdi; call rax
+        let mut block = Block::new(0);
+        block
+            .load(
+                BitVector::word_variable("rax"),
+                BitVector::word_constant(0x2000),
+            )
+            .unwrap();
+        block
+            .assign(
+                BitVector::word_variable("rax"),
+                BitVector::word_variable("rdi").into(),
+            )
+            .unwrap();
+        block.call(BitVector::word_variable("rax").into()).unwrap();
+
+        // When
+        let targets = indirect_call_targets(&code_pointers(), &block, 2);
+
+        // Then
+        assert_eq!(targets, None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::expr::Variable;
 use crate::hir::{Block, ControlFlowGraph};
 use std::collections::{HashMap, HashSet};
@@ -14,14 +14,14 @@ impl LiveVariables {
     pub fn live_at_entry(&self, block_index: usize) -> Result<&HashSet<Variable>> {
         self.live_in
             .get(&block_index)
-            .ok_or_else(|| format!("Basic block with index {} does not exist", block_index).into())
+            .ok_or(Error::BlockNotFound(block_index))
     }
 
     /// Returns the set of variables which are live on basic block exit.
     pub fn live_at_exit(&self, block_index: usize) -> Result<&HashSet<Variable>> {
         self.live_out
             .get(&block_index)
-            .ok_or_else(|| format!("Basic block with index {} does not exist", block_index).into())
+            .ok_or(Error::BlockNotFound(block_index))
     }
 }
 
@@ -0,0 +1,327 @@
+//! Loop Bound Inference
+//!
+//! Infers the trip count of loops with a constant-initialized counter, a constant step
+//! and a constant bound, e.g. `for (i = 0; i < 10; i++)`.
+//!
+//! Only loops with a single exit, which is either located in the loop header or in the
+//! (unique) latch block, are considered.
+
+use crate::error::Result;
+use crate::expr::{Expression, Fold, Variable};
+use crate::hir::{Block, ControlFlowGraph, Operation};
+use falcon::graph::LoopTree;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
+
+/// Loops with more iterations are treated as if their trip count would be unknown.
+const MAX_TRIP_COUNT: usize = 1024;
+
+/// Infers the unwinding bound for all loops (identified by their header) with constant trip count.
+///
+/// The unwinding bound is the number of times the back edge is taken before the loop is left.
+pub fn loop_bounds(cfg: &ControlFlowGraph, loop_tree: &LoopTree) -> Result<BTreeMap<usize, usize>> {
+    let mut bounds = BTreeMap::new();
+
+    for l in loop_tree.vertices() {
+        if let Some(bound) = loop_bound(cfg, l.header(), l.nodes())? {
+            bounds.insert(l.header(), bound);
+        }
+    }
+
+    Ok(bounds)
+}
+
+fn loop_bound(
+    cfg: &ControlFlowGraph,
+    header: usize,
+    nodes: &BTreeSet<usize>,
+) -> Result<Option<usize>> {
+    let latch = match in_loop_predecessors(cfg, header, nodes)?.as_slice() {
+        [latch] => *latch,
+        _ => return Ok(None),
+    };
+
+    let mut exits = Vec::new();
+    for &node in nodes {
+        for edge in cfg.edges_out(node)? {
+            if !nodes.contains(&edge.tail()) {
+                exits.push(edge);
+            }
+        }
+    }
+
+    let (exiting_block, exit_condition) = match exits.as_slice() {
+        [exit] => match exit.condition() {
+            Some(condition) => (exit.head(), condition),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    if exiting_block != header && exiting_block != latch {
+        return Ok(None);
+    }
+
+    // The exit condition in terms of the variable values on entry of the exiting block
+    let condition = exit_condition.substitute(&definitions(cfg.block(exiting_block)?));
+    let counter = match condition.variables().as_slice() {
+        [counter] => (*counter).clone(),
+        _ => return Ok(None),
+    };
+
+    let (update_block, update) = match counter_update(cfg, nodes, &counter)? {
+        Some(update) => update,
+        None => return Ok(None),
+    };
+    if update_block != header && update_block != latch {
+        return Ok(None);
+    }
+
+    let mut value = match initial_value(cfg, header, nodes, &counter)? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    // The header update is already applied once on entry of the latch
+    if exiting_block != header && update_block == header {
+        value = match evaluate(&update, &counter, &value) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+    }
+
+    for iteration in 0..=MAX_TRIP_COUNT {
+        let exit_taken = evaluate(&condition, &counter, &value)
+            .and_then(|condition| bool::try_from(&condition).ok());
+        match exit_taken {
+            Some(true) => return Ok(Some(iteration)),
+            Some(false) => {}
+            None => return Ok(None),
+        }
+
+        value = match evaluate(&update, &counter, &value) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+    }
+
+    Ok(None)
+}
+
+fn in_loop_predecessors(
+    cfg: &ControlFlowGraph,
+    header: usize,
+    nodes: &BTreeSet<usize>,
+) -> Result<Vec<usize>> {
+    Ok(cfg
+        .predecessor_indices(header)?
+        .into_iter()
+        .filter(|node| nodes.contains(node))
+        .collect())
+}
+
+/// Finds the (single) block in which the counter is updated and the counter value after the update
+/// in terms of the counter value on entry of the block.
+fn counter_update(
+    cfg: &ControlFlowGraph,
+    nodes: &BTreeSet<usize>,
+    counter: &Variable,
+) -> Result<Option<(usize, Expression)>> {
+    let mut update_blocks = Vec::new();
+    for &node in nodes {
+        let block = cfg.block(node)?;
+        if block.variables_written().contains(&counter) {
+            update_blocks.push(block);
+        }
+    }
+
+    let block = match update_blocks.as_slice() {
+        [block] => block,
+        _ => return Ok(None),
+    };
+
+    let update = match definitions(block).remove(counter) {
+        Some(update) => update,
+        None => return Ok(None),
+    };
+    if update.variables().iter().any(|var| *var != counter) {
+        return Ok(None);
+    }
+
+    Ok(Some((block.index(), update)))
+}
+
+/// Finds the constant counter value on loop entry
+/// by walking up the (unique) predecessors of the loop header.
+fn initial_value(
+    cfg: &ControlFlowGraph,
+    header: usize,
+    nodes: &BTreeSet<usize>,
+    counter: &Variable,
+) -> Result<Option<Expression>> {
+    let outside_predecessors: Vec<usize> = cfg
+        .predecessor_indices(header)?
+        .into_iter()
+        .filter(|node| !nodes.contains(node))
+        .collect();
+
+    let mut current = match outside_predecessors.as_slice() {
+        [predecessor] => *predecessor,
+        _ => return Ok(None),
+    };
+
+    let mut visited = BTreeSet::new();
+    while visited.insert(current) {
+        if let Some(mut value) = definitions(cfg.block(current)?).remove(counter) {
+            value.fold();
+            return Ok(if value.is_constant() {
+                Some(value)
+            } else {
+                None
+            });
+        }
+
+        current = match cfg.predecessor_indices(current)?.as_slice() {
+            [predecessor] => *predecessor,
+            _ => return Ok(None),
+        };
+    }
+
+    Ok(None)
+}
+
+/// Computes the value of each variable written in the block
+/// in terms of the variable values on entry of the block.
+///
+/// Variables with unknown values (e.g. loaded from memory) are mapped to `nondet`.
+fn definitions(block: &Block) -> HashMap<Variable, Expression> {
+    let mut defs = HashMap::new();
+
+    for inst in block.instructions() {
+        match inst.operation() {
+            Operation::Assign { variable, expr } => {
+                let value = expr.substitute(&defs);
+                defs.insert(variable.clone(), value);
+            }
+            operation => {
+                for variable in operation.variables_written() {
+                    defs.insert(
+                        variable.clone(),
+                        Expression::nondet(variable.sort().clone()),
+                    );
+                }
+            }
+        }
+    }
+
+    defs
+}
+
+/// Evaluates `expr` for the given counter value, returns `None` if the result is not constant.
+fn evaluate(expr: &Expression, counter: &Variable, value: &Expression) -> Option<Expression> {
+    let mut defs = HashMap::new();
+    defs.insert(counter.clone(), value.clone());
+
+    let mut result = expr.substitute(&defs);
+    result.fold();
+
+    if result.is_constant() {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Boolean};
+
+    fn expr_const(value: u64) -> Expression {
+        BitVector::constant_u64(value, 64)
+    }
+
+    fn counter() -> Variable {
+        BitVector::variable("i", 64)
+    }
+
+    fn loop_tree(cfg: &ControlFlowGraph) -> LoopTree {
+        cfg.graph().compute_loop_tree(cfg.entry().unwrap()).unwrap()
+    }
+
+    /// i = init; while (i < bound) { i = i + step }
+    fn while_loop(init: u64, bound: u64, step: u64) -> (ControlFlowGraph, usize) {
+        let mut cfg = ControlFlowGraph::new();
+
+        let block0 = cfg.new_block();
+        block0.assign(counter(), expr_const(init)).unwrap();
+        let block0_index = block0.index();
+
+        let block1_index = cfg.new_block().index();
+
+        let block2 = cfg.new_block();
+        block2
+            .assign(
+                counter(),
+                BitVector::add(counter().into(), expr_const(step)).unwrap(),
+            )
+            .unwrap();
+        let block2_index = block2.index();
+
+        let block3_index = cfg.new_block().index();
+
+        let condition = BitVector::ult(counter().into(), expr_const(bound)).unwrap();
+
+        cfg.unconditional_edge(block0_index, block1_index).unwrap();
+        cfg.conditional_edge(block1_index, block2_index, condition.clone())
+            .unwrap();
+        cfg.conditional_edge(block1_index, block3_index, Boolean::not(condition).unwrap())
+            .unwrap();
+        cfg.unconditional_edge(block2_index, block1_index).unwrap();
+
+        cfg.set_entry(block0_index).unwrap();
+        cfg.set_exit(block3_index).unwrap();
+
+        (cfg, block1_index)
+    }
+
+    #[test]
+    fn test_while_loop_with_constant_trip_count() {
+        let (cfg, header) = while_loop(0, 10, 1);
+
+        let bounds = loop_bounds(&cfg, &loop_tree(&cfg)).unwrap();
+
+        assert_eq!(bounds.get(&header), Some(&10));
+    }
+
+    #[test]
+    fn test_while_loop_which_is_never_entered() {
+        let (cfg, header) = while_loop(20, 10, 1);
+
+        let bounds = loop_bounds(&cfg, &loop_tree(&cfg)).unwrap();
+
+        assert_eq!(bounds.get(&header), Some(&0));
+    }
+
+    #[test]
+    fn test_while_loop_with_step_size() {
+        let (cfg, header) = while_loop(0, 10, 4);
+
+        let bounds = loop_bounds(&cfg, &loop_tree(&cfg)).unwrap();
+
+        assert_eq!(bounds.get(&header), Some(&3));
+    }
+
+    #[test]
+    fn test_loop_without_constant_initialization_has_no_bound() {
+        let (mut cfg, header) = while_loop(0, 10, 1);
+        let entry = cfg.entry().unwrap();
+        cfg.block_mut(entry)
+            .unwrap()
+            .load(counter(), expr_const(0x1000))
+            .unwrap();
+
+        let bounds = loop_bounds(&cfg, &loop_tree(&cfg)).unwrap();
+
+        assert_eq!(bounds.get(&header), None);
+    }
+}
@@ -1,7 +1,19 @@
 mod call_graph;
+mod dominators;
 mod global_variables;
+mod indirect_calls;
 mod live_variables;
+mod loop_bounds;
+mod reducibility;
+mod security_levels;
+mod speculation_window;
 
 pub use call_graph::{call_graph, CallGraph};
+pub use dominators::{dominators, post_dominators, Dominators};
 pub use global_variables::global_variables;
+pub use indirect_calls::indirect_call_targets;
 pub use live_variables::{live_variables, LiveVariables};
+pub use loop_bounds::loop_bounds;
+pub use reducibility::irreducible_edges;
+pub use security_levels::{security_levels, SecurityLevels};
+pub use speculation_window::max_transient_path_length;
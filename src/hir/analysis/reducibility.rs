@@ -0,0 +1,81 @@
+//! Reducibility
+//!
+//! A CFG is reducible iff each retreating edge of a depth-first traversal (starting at the entry)
+//! is a back edge, meaning that its tail dominates its head.
+//! Retreating edges whose tail doesn't dominate the head enter a loop at a second (or further) block.
+
+use crate::error::Result;
+use crate::hir::analysis::dominators;
+use crate::hir::ControlFlowGraph;
+use std::collections::{BTreeSet, HashSet};
+
+/// Returns all retreating edges `(head, tail)` which aren't back edges.
+///
+/// The tails are the blocks at which irreducible loops are entered,
+/// the result is empty iff the CFG is reducible.
+pub fn irreducible_edges(cfg: &ControlFlowGraph) -> Result<BTreeSet<(usize, usize)>> {
+    let dominators = dominators(cfg)?;
+    let entry = cfg.entry()?;
+
+    let mut irreducible_edges = BTreeSet::new();
+
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut on_stack: HashSet<usize> = HashSet::new();
+    let mut stack: Vec<(usize, Vec<usize>)> = Vec::new();
+
+    visited.insert(entry);
+    on_stack.insert(entry);
+    stack.push((entry, cfg.successor_indices(entry)?));
+
+    while let Some((block, successors)) = stack.last_mut() {
+        let block = *block;
+        match successors.pop() {
+            Some(successor) => {
+                if on_stack.contains(&successor) {
+                    if !dominators.dominates(successor, block) {
+                        irreducible_edges.insert((block, successor));
+                    }
+                } else if visited.insert(successor) {
+                    on_stack.insert(successor);
+                    stack.push((successor, cfg.successor_indices(successor)?));
+                }
+            }
+            None => {
+                on_stack.remove(&block);
+                stack.pop();
+            }
+        }
+    }
+
+    Ok(irreducible_edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_irreducible_edges_of_loop_with_two_entries() {
+        // Given: entry -> a, entry -> b, a <-> b
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        let a = cfg.new_block().index();
+        let b = cfg.new_block().index();
+        let exit = cfg.new_block().index();
+        cfg.unconditional_edge(entry, a).unwrap();
+        cfg.unconditional_edge(entry, b).unwrap();
+        cfg.unconditional_edge(a, b).unwrap();
+        cfg.unconditional_edge(b, a).unwrap();
+        cfg.unconditional_edge(b, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+
+        // When:
+        let edges = irreducible_edges(&cfg).unwrap();
+
+        // Then: exactly one edge enters the loop without being dominated
+        assert_eq!(edges.len(), 1);
+        let (head, tail) = edges.into_iter().next().unwrap();
+        assert!((head, tail) == (a, b) || (head, tail) == (b, a));
+    }
+}
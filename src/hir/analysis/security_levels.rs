@@ -0,0 +1,289 @@
+//! Security Level Inference
+//!
+//! A flow-sensitive information-flow type inference over the (transformed) HIR with the two-point
+//! lattice low < high. A program in which each observation only depends on low data is secure
+//! without any further (SMT-based) check.
+//!
+//! The inference requires an acyclic CFG (i.e. loops are unwound) in which each variable is
+//! initialized before it is read on all paths, as it is the case after the HIR transformations.
+//! Variables are high unless they are made indistinguishable in the block which defines them,
+//! their level only rises on re-assignment.
+//! Implicit flows are tracked by the level of the program counter, which is the join of the levels of
+//! all edge conditions leading to a block.
+
+use crate::environment::SecurityLevel;
+use crate::error::Result;
+use crate::expr::{Expression, Operator, Variable};
+use crate::hir::{ControlFlowGraph, Instruction, Operation};
+use std::collections::{BTreeMap, HashMap};
+
+fn join(a: SecurityLevel, b: SecurityLevel) -> SecurityLevel {
+    match (a, b) {
+        (SecurityLevel::Low, SecurityLevel::Low) => SecurityLevel::Low,
+        _ => SecurityLevel::High,
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct SecurityLevels {
+    levels: HashMap<Variable, SecurityLevel>,
+    definitions: HashMap<Variable, usize>,
+    violations: Vec<String>,
+}
+
+impl SecurityLevels {
+    /// Returns the inferred level of the given variable (high if the variable is unknown).
+    pub fn level(&self, variable: &Variable) -> SecurityLevel {
+        self.levels
+            .get(variable)
+            .copied()
+            .unwrap_or(SecurityLevel::High)
+    }
+
+    /// Returns the level of the given expression, i.e. the join of the levels of its variables.
+    ///
+    /// Nondeterministic values and variables of a specific self-composition are high.
+    pub fn expression_level(&self, expr: &Expression) -> SecurityLevel {
+        if expr.is_nondet() {
+            return SecurityLevel::High;
+        }
+        if let Operator::Variable(variable) = expr.operator() {
+            if variable.composition().is_some() {
+                return SecurityLevel::High;
+            }
+            return self.level(variable);
+        }
+        expr.operands()
+            .iter()
+            .fold(SecurityLevel::Low, |level, operand| {
+                join(level, self.expression_level(operand))
+            })
+    }
+
+    /// Tells whether the type check succeeded, i.e. the program is secure.
+    pub fn is_secure(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Returns the reasons why the type check failed.
+    ///
+    /// A failed type check doesn't mean that the program is insecure.
+    pub fn violations(&self) -> &[String] {
+        &self.violations
+    }
+
+    fn raise(&mut self, variable: &Variable, level: SecurityLevel, block: usize) {
+        self.definitions.insert(variable.clone(), block);
+        let joined = match self.levels.get(variable) {
+            Some(&current) => join(current, level),
+            None => level,
+        };
+        self.levels.insert(variable.clone(), joined);
+    }
+
+    fn infer_instruction(&mut self, instruction: &Instruction, pc: SecurityLevel, block: usize) {
+        match instruction.operation() {
+            Operation::Assign { variable, expr } => {
+                let level = join(pc, self.expression_level(expr));
+                self.raise(variable, level, block);
+            }
+            Operation::Store {
+                address,
+                expr,
+                memory_in,
+                memory_out,
+            } => {
+                let level = join(
+                    join(pc, self.level(memory_in)),
+                    join(self.expression_level(address), self.expression_level(expr)),
+                );
+                self.raise(memory_out, level, block);
+            }
+            Operation::Load {
+                variable,
+                address,
+                memory,
+            } => {
+                let level = join(join(pc, self.level(memory)), self.expression_level(address));
+                self.raise(variable, level, block);
+            }
+            Operation::Indistinguishable { expr } => {
+                // The assumed equality only holds on paths through this block, which are all paths
+                // of the variable if it is defined in this block (and the block is executed in all compositions)
+                if let (Operator::Variable(variable), SecurityLevel::Low) = (expr.operator(), pc) {
+                    if variable.composition().is_none()
+                        && self.definitions.get(variable) == Some(&block)
+                    {
+                        self.levels.insert(variable.clone(), SecurityLevel::Low);
+                    }
+                }
+            }
+            Operation::Observable { expr } => {
+                if join(pc, self.expression_level(expr)) == SecurityLevel::High {
+                    self.violate(instruction, "Observation may depend on high data");
+                }
+            }
+            Operation::Assert { .. } => {
+                self.violate(instruction, "Assertion can't be checked by types");
+            }
+            Operation::Assume { .. }
+            | Operation::Call { .. }
+            | Operation::Branch { .. }
+            | Operation::ConditionalBranch { .. }
+            | Operation::Skip
+            | Operation::Barrier { .. }
+            | Operation::CacheFlush { .. }
+            | Operation::Prefetch { .. } => {
+                // Either control flow (already part of the CFG) or effects (explicit after transformation),
+                // ignoring assumptions over-approximates the executions
+            }
+        }
+    }
+
+    fn violate(&mut self, instruction: &Instruction, reason: &str) {
+        match instruction.address() {
+            Some(address) => self
+                .violations
+                .push(format!("{} (at 0x{:X})", reason, address)),
+            None => self.violations.push(reason.to_owned()),
+        }
+    }
+}
+
+/// Infers the security levels of all variables of the acyclic `cfg`
+/// and checks that no observation depends on high data.
+pub fn security_levels(cfg: &ControlFlowGraph) -> Result<SecurityLevels> {
+    let mut levels = SecurityLevels::default();
+    let mut pc_levels: BTreeMap<usize, SecurityLevel> = BTreeMap::new();
+
+    let ordering = match cfg.graph().compute_topological_ordering() {
+        Ok(ordering) => ordering,
+        Err(_) => {
+            levels
+                .violations
+                .push("Control-flow graph is cyclic".to_owned());
+            return Ok(levels);
+        }
+    };
+
+    for index in ordering {
+        let mut pc = SecurityLevel::Low;
+        for edge in cfg.edges_in(index)? {
+            let condition_level = edge
+                .condition()
+                .map(|condition| levels.expression_level(condition))
+                .unwrap_or(SecurityLevel::Low);
+            let head_level = pc_levels
+                .get(&edge.head())
+                .copied()
+                .unwrap_or(SecurityLevel::High);
+            pc = join(pc, join(head_level, condition_level));
+        }
+        pc_levels.insert(index, pc);
+
+        let block = cfg.block(index)?;
+        for phi_node in block.phi_nodes() {
+            let level = phi_node
+                .incoming_variables()
+                .into_iter()
+                .fold(pc, |level, variable| join(level, levels.level(variable)));
+            levels.raise(phi_node.out(), level, index);
+        }
+
+        for instruction in block.instructions() {
+            levels.infer_instruction(instruction, pc, index);
+        }
+    }
+
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Boolean};
+
+    fn init_low(cfg: &mut ControlFlowGraph, block: usize, variable: Variable) {
+        let block = cfg.block_mut(block).unwrap();
+        block
+            .assign(
+                variable.clone(),
+                Expression::nondet(variable.sort().clone()),
+            )
+            .unwrap();
+        block.indistinguishable(variable.into());
+    }
+
+    #[test]
+    fn test_observation_of_low_data_is_secure() {
+        // Given: x is low, y := x + 1, observe(y)
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        init_low(&mut cfg, entry, BitVector::word_variable("x"));
+        let block = cfg.block_mut(entry).unwrap();
+        block
+            .assign(
+                BitVector::word_variable("y"),
+                BitVector::add(
+                    BitVector::word_variable("x").into(),
+                    BitVector::word_constant(1),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        block.observable(BitVector::word_variable("y").into());
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(entry).unwrap();
+
+        // When:
+        let levels = security_levels(&cfg).unwrap();
+
+        // Then:
+        assert!(levels.is_secure());
+        assert_eq!(
+            levels.level(&BitVector::word_variable("y")),
+            SecurityLevel::Low
+        );
+    }
+
+    #[test]
+    fn test_observation_under_high_branch_is_not_secure() {
+        // Given: x is low, s is high, entry -(s)-> then -> exit, entry -(!s)-> exit, observe(x) in then
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        init_low(&mut cfg, entry, BitVector::word_variable("x"));
+        cfg.block_mut(entry)
+            .unwrap()
+            .assign(
+                Boolean::variable("s"),
+                Expression::nondet(Boolean::variable("s").sort().clone()),
+            )
+            .unwrap();
+        let then = cfg.new_block();
+        then.observable(BitVector::word_variable("x").into());
+        let then = then.index();
+        let exit = cfg.new_block().index();
+        cfg.conditional_edge(entry, then, Boolean::variable("s").into())
+            .unwrap();
+        cfg.conditional_edge(
+            entry,
+            exit,
+            Boolean::not(Boolean::variable("s").into()).unwrap(),
+        )
+        .unwrap();
+        cfg.unconditional_edge(then, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+
+        // When:
+        let levels = security_levels(&cfg).unwrap();
+
+        // Then:
+        assert!(!levels.is_secure());
+        assert_eq!(levels.violations().len(), 1);
+        assert_eq!(
+            levels.level(&BitVector::word_variable("x")),
+            SecurityLevel::Low
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Speculation Window Utilization
+//!
+//! Determines how many instructions can be executed transiently at most,
+//! after the transient paths exceeding the speculation window have been pruned.
+
+use crate::error::Result;
+use crate::hir::ControlFlowGraph;
+use std::collections::BTreeMap;
+
+/// Returns the max. number of (non-pseudo) instructions on any transient path,
+/// which is the largest part of the speculation window which is actually reachable.
+///
+/// Nested transient executions share the window with the outer transient execution,
+/// their instructions are therefore counted towards the path of the outer one.
+pub fn max_transient_path_length(cfg: &ControlFlowGraph) -> Result<usize> {
+    let mut path_length: BTreeMap<usize, usize> = BTreeMap::new();
+
+    for index in cfg.graph().compute_topological_ordering()? {
+        let block = cfg.block(index)?;
+        if !block.is_transient() {
+            continue;
+        }
+
+        let longest_predecessor_path = cfg
+            .predecessor_indices(index)?
+            .into_iter()
+            .filter_map(|predecessor| path_length.get(&predecessor))
+            .max()
+            .cloned()
+            .unwrap_or_default();
+
+        path_length.insert(
+            index,
+            longest_predecessor_path + block.instruction_count_ignoring_pseudo_instructions(),
+        );
+    }
+
+    Ok(path_length.values().max().cloned().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Boolean};
+
+    #[test]
+    fn test_max_transient_path_length_takes_longest_transient_path() {
+        // Given: entry -> (t1 -> t2 | t3) -> exit, where t1..t3 are transient
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        let add_transient_block = |cfg: &mut ControlFlowGraph, instructions: usize| {
+            let block = cfg.new_block();
+            for _ in 0..instructions {
+                block
+                    .assign(BitVector::word_variable("x"), BitVector::word_constant(0))
+                    .unwrap();
+            }
+            block.set_transient();
+            block.index()
+        };
+        let t1 = add_transient_block(&mut cfg, 2);
+        let t2 = add_transient_block(&mut cfg, 3);
+        let t3 = add_transient_block(&mut cfg, 4);
+        let exit = cfg.new_block().index();
+
+        cfg.conditional_edge(entry, t1, Boolean::variable("a").into())
+            .unwrap();
+        cfg.conditional_edge(entry, t3, Boolean::variable("b").into())
+            .unwrap();
+        cfg.unconditional_edge(t1, t2).unwrap();
+        cfg.unconditional_edge(t2, exit).unwrap();
+        cfg.unconditional_edge(t3, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+
+        // When:
+        let length = max_transient_path_length(&cfg).unwrap();
+
+        // Then:
+        assert_eq!(length, 5);
+    }
+}
@@ -1,10 +1,11 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::expr::{Expression, Variable};
-use crate::hir::{Instruction, PhiNode};
+use crate::hir::{BarrierKind, Instruction, PhiNode};
 use falcon::graph;
+use serde::{Deserialize, Serialize};
 use std::{cmp, collections::HashSet, fmt};
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Block {
     /// The index of the block.
     index: usize,
@@ -110,7 +111,10 @@ impl Block {
     /// Deletes an `Instruction` by its index.
     pub fn remove_instruction(&mut self, index: usize) -> Result<Instruction> {
         if index >= self.instructions.len() {
-            return Err(format!("No instruction with index {} found", index).into());
+            return Err(Error::InstructionNotFound {
+                block: self.index,
+                index,
+            });
         }
         Ok(self.instructions.remove(index))
     }
@@ -128,7 +132,10 @@ impl Block {
     /// Inserts an `Instruction` at the given index.
     pub fn insert_instruction(&mut self, index: usize, instruction: Instruction) -> Result<()> {
         if index > self.instructions.len() {
-            return Err(format!("Index {} is invalid", index).into());
+            return Err(Error::InvalidInstructionIndex {
+                block: self.index,
+                index,
+            });
         }
         self.instructions.insert(index, instruction);
         Ok(())
@@ -151,7 +158,10 @@ impl Block {
     /// Only instructions with smaller index will remain in this `Block`.
     pub fn split_off_instructions_at(&mut self, index: usize) -> Result<Vec<Instruction>> {
         if index >= self.instructions.len() {
-            return Err(format!("No instruction with index {} found", index).into());
+            return Err(Error::InstructionNotFound {
+                block: self.index,
+                index,
+            });
         }
         Ok(self.instructions.split_off(index))
     }
@@ -199,7 +209,10 @@ impl Block {
     /// Deletes a `PhiNode` by its index.
     pub fn remove_phi_node(&mut self, index: usize) -> Result<PhiNode> {
         if index >= self.phi_nodes.len() {
-            return Err(format!("No phi node with index {} found", index).into());
+            return Err(Error::PhiNodeNotFound {
+                block: self.index,
+                index,
+            });
         }
         Ok(self.phi_nodes.remove(index))
     }
@@ -263,11 +276,23 @@ impl Block {
     }
 
     /// Adds a barrier operation to the end of this block.
-    pub fn barrier(&mut self) -> &mut Instruction {
-        self.instructions.push(Instruction::barrier());
+    pub fn barrier(&mut self, kind: BarrierKind) -> &mut Instruction {
+        self.instructions.push(Instruction::barrier(kind));
         self.instructions.last_mut().unwrap()
     }
 
+    /// Adds a cache flush operation to the end of this block.
+    pub fn cache_flush(&mut self, address: Expression) -> Result<&mut Instruction> {
+        self.instructions.push(Instruction::cache_flush(address)?);
+        Ok(self.instructions.last_mut().unwrap())
+    }
+
+    /// Adds a prefetch operation to the end of this block.
+    pub fn prefetch(&mut self, address: Expression) -> Result<&mut Instruction> {
+        self.instructions.push(Instruction::prefetch(address)?);
+        Ok(self.instructions.last_mut().unwrap())
+    }
+
     /// Adds an assert operation to the end of this block.
     pub fn assert(&mut self, condition: Expression) -> Result<&mut Instruction> {
         self.instructions.push(Instruction::assert(condition)?);
@@ -19,11 +19,13 @@
 
 //! A `ControlFlowGraph` is a directed `Graph` of `Block` and `Edge`.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::expr::{Boolean, Expression, Variable};
-use crate::hir::{Block, Edge};
+use crate::hir::{analysis, Block, Edge, Instruction};
+use crate::ir::Provenance;
 use crate::util::RenderGraph;
 use falcon::graph;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp;
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
@@ -72,13 +74,13 @@ impl ControlFlowGraph {
 
     /// Get the entry `Block` index of this `ControlFlowGraph`.
     pub fn entry(&self) -> Result<usize> {
-        self.entry.ok_or_else(|| "CFG entry must be set".into())
+        self.entry.ok_or(Error::MissingEntry)
     }
 
     /// Sets the entry point for this `ControlFlowGraph` to the given `Block` index.
     pub fn set_entry(&mut self, entry: usize) -> Result<()> {
         if !self.graph.has_vertex(entry) {
-            return Err("Index does not exist for set_entry".into());
+            return Err(Error::BlockNotFound(entry));
         }
         self.entry = Some(entry);
         Ok(())
@@ -86,13 +88,13 @@ impl ControlFlowGraph {
 
     /// Get the exit `Block` index of this `ControlFlowGraph`.
     pub fn exit(&self) -> Result<usize> {
-        self.exit.ok_or_else(|| "CFG exit must be set".into())
+        self.exit.ok_or(Error::MissingExit)
     }
 
     /// Sets the exit point for this `ControlFlowGraph` to the given `Block` index.
     pub fn set_exit(&mut self, exit: usize) -> Result<()> {
         if !self.graph.has_vertex(exit) {
-            return Err("Index does not exist for set_exit".into());
+            return Err(Error::BlockNotFound(exit));
         }
         self.exit = Some(exit);
         Ok(())
@@ -108,6 +110,16 @@ impl ControlFlowGraph {
         Ok(self.graph.successor_indices(index)?)
     }
 
+    /// Computes the dominators of each block (reachable from the entry).
+    pub fn dominators(&self) -> Result<analysis::Dominators> {
+        analysis::dominators(self)
+    }
+
+    /// Computes the post-dominators of each block (from which the exit is reachable).
+    pub fn post_dominators(&self) -> Result<analysis::Dominators> {
+        analysis::post_dominators(self)
+    }
+
     /// Get a `Block` by index.
     pub fn block(&self, index: usize) -> Result<&Block> {
         Ok(self.graph.vertex(index)?)
@@ -365,7 +377,16 @@ impl ControlFlowGraph {
                     predecessor.assume(negated_condition)?.labels_mut().pseudo();
                 }
                 RemovedEdgeGuard::AssertEdgeNotTaken => {
-                    predecessor.assert(negated_condition)?.labels_mut().pseudo();
+                    // the removed edge belongs to the branch at the end of the predecessor
+                    let address = predecessor
+                        .instructions()
+                        .last()
+                        .and_then(Instruction::address);
+                    predecessor
+                        .assert(negated_condition)?
+                        .set_provenance(Provenance::unwinding().with_address(address))
+                        .labels_mut()
+                        .pseudo();
                 }
                 RemovedEdgeGuard::Ignore => {}
             }
@@ -696,6 +717,59 @@ impl Default for ControlFlowGraph {
     }
 }
 
+/// Serialized form of a `ControlFlowGraph`, i.e. the graph as lists of blocks and edges.
+#[derive(Serialize)]
+struct SerializedControlFlowGraph<'a> {
+    blocks: Vec<&'a Block>,
+    edges: Vec<&'a Edge>,
+    entry: Option<usize>,
+    exit: Option<usize>,
+    next_index: usize,
+}
+
+#[derive(Deserialize)]
+struct DeserializedControlFlowGraph {
+    blocks: Vec<Block>,
+    edges: Vec<Edge>,
+    entry: Option<usize>,
+    exit: Option<usize>,
+    next_index: usize,
+}
+
+impl Serialize for ControlFlowGraph {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        SerializedControlFlowGraph {
+            blocks: self.blocks(),
+            edges: self.edges(),
+            entry: self.entry,
+            exit: self.exit,
+            next_index: self.next_index,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ControlFlowGraph {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let serialized = DeserializedControlFlowGraph::deserialize(deserializer)?;
+
+        let mut graph = graph::Graph::new();
+        for block in serialized.blocks {
+            graph.insert_vertex(block).map_err(de::Error::custom)?;
+        }
+        for edge in serialized.edges {
+            graph.insert_edge(edge).map_err(de::Error::custom)?;
+        }
+
+        Ok(Self {
+            graph,
+            entry: serialized.entry,
+            exit: serialized.exit,
+            next_index: serialized.next_index,
+        })
+    }
+}
+
 impl fmt::Display for ControlFlowGraph {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for block in self.blocks() {
@@ -724,6 +798,7 @@ fn edge_should_not_be_simplified(edge: &Edge) -> bool {
 mod tests {
     use super::*;
     use crate::expr::Boolean;
+    use crate::hir::BarrierKind;
 
     #[test]
     fn test_split_block_at_should_correctly_rewire_outgoing_edges_to_new_tail_block() {
@@ -735,8 +810,8 @@ mod tests {
 
         let block_index = {
             let block = cfg.new_block();
-            block.barrier(); // inst 0
-            block.barrier(); // inst 1
+            block.barrier(BarrierKind::Full); // inst 0
+            block.barrier(BarrierKind::Full); // inst 1
             block.index()
         };
 
@@ -782,9 +857,9 @@ mod tests {
 
         let block_index = {
             let block = cfg.new_block();
-            block.barrier().set_address(Some(0)); // inst 0
-            block.barrier().set_address(Some(1)); // inst 1
-            block.barrier().set_address(Some(2)); // inst 2
+            block.barrier(BarrierKind::Full).set_address(Some(0)); // inst 0
+            block.barrier(BarrierKind::Full).set_address(Some(1)); // inst 1
+            block.barrier(BarrierKind::Full).set_address(Some(2)); // inst 2
             block.index()
         };
 
@@ -810,8 +885,8 @@ mod tests {
 
         let block_index = {
             let block = cfg.new_block();
-            block.barrier(); // inst 0
-            block.barrier(); // inst 1
+            block.barrier(BarrierKind::Full); // inst 0
+            block.barrier(BarrierKind::Full); // inst 1
             block.index()
         };
 
@@ -832,8 +907,8 @@ mod tests {
 
         let block_index = {
             let block = cfg.new_block();
-            block.barrier(); // inst 0
-            block.barrier(); // inst 1
+            block.barrier(BarrierKind::Full); // inst 0
+            block.barrier(BarrierKind::Full); // inst 1
             block.index()
         };
 
@@ -854,8 +929,8 @@ mod tests {
 
         let block_index = {
             let block = cfg.new_block();
-            block.barrier(); // inst 0
-            block.barrier(); // inst 1
+            block.barrier(BarrierKind::Full); // inst 0
+            block.barrier(BarrierKind::Full); // inst 1
             block.index()
         };
 
@@ -1071,4 +1146,39 @@ mod tests {
 
         assert_eq!(expected_cfg, simplified_cfg);
     }
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        // Given: Conditional branch with a transient block
+        let mut cfg = ControlFlowGraph::new();
+        let head = cfg.new_block().index();
+        let then = {
+            let block = cfg.new_block();
+            block.set_transient();
+            block
+                .assign(Boolean::variable("x"), Boolean::constant(true))
+                .unwrap();
+            block.index()
+        };
+        let tail = cfg.new_block().index();
+        cfg.conditional_edge(head, then, Boolean::variable("c").into())
+            .unwrap()
+            .labels_mut()
+            .speculate();
+        cfg.unconditional_edge(head, tail).unwrap();
+        cfg.unconditional_edge(then, tail).unwrap();
+        cfg.set_entry(head).unwrap();
+        cfg.set_exit(tail).unwrap();
+
+        // When
+        let json = serde_json::to_string(&cfg).unwrap();
+        let deserialized: ControlFlowGraph = serde_json::from_str(&json).unwrap();
+
+        // Then
+        assert_eq!(cfg, deserialized);
+        assert_eq!(
+            cfg.new_block().index(),
+            deserialized.clone().new_block().index()
+        );
+    }
 }
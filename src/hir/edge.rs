@@ -1,11 +1,12 @@
 use crate::expr::{Expression, Variable};
 use bitflags::bitflags;
 use falcon::graph;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::Write as _;
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Serialize, Deserialize)]
     pub struct Labels: u32 {
         const TAKEN     = 0b00001;
         const SPECULATE = 0b00010;
@@ -108,7 +109,7 @@ impl fmt::Display for Labels {
     }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Edge {
     head: usize,
     tail: usize,
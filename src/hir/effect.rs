@@ -1,7 +1,8 @@
 use crate::expr::{Expression, Variable};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Effect {
     /// Conditional effect, meaning that the nested effect is only observable if the condition holds
     Conditional {
@@ -13,6 +14,11 @@ pub enum Effect {
         address: Expression,
         bit_width: usize,
     },
+    /// Memory at given address is evicted from the Cache
+    CacheEvict {
+        address: Expression,
+        bit_width: usize,
+    },
     /// Branch target is tracked in the Branch Target Buffer
     BranchTarget {
         location: Expression,
@@ -23,6 +29,13 @@ pub enum Effect {
         location: Expression,
         condition: Expression,
     },
+    /// Store location is tracked per address by the Memory Dependence Predictor
+    StoreLocation {
+        location: Expression,
+        address: Expression,
+    },
+    /// Load trains the Memory Dependence Predictor that it depends on the last store to the address
+    LoadDependence { address: Expression },
 }
 
 impl Effect {
@@ -31,6 +44,11 @@ impl Effect {
         Self::CacheFetch { address, bit_width }
     }
 
+    /// Create a new `Effect::CacheEvict`.
+    pub fn cache_evict(address: Expression, bit_width: usize) -> Self {
+        Self::CacheEvict { address, bit_width }
+    }
+
     /// Create a new `Effect::BranchTarget`.
     pub fn branch_target(location: Expression, target: Expression) -> Self {
         Self::BranchTarget { location, target }
@@ -44,6 +62,16 @@ impl Effect {
         }
     }
 
+    /// Create a new `Effect::StoreLocation`.
+    pub fn store_location(location: Expression, address: Expression) -> Self {
+        Self::StoreLocation { location, address }
+    }
+
+    /// Create a new `Effect::LoadDependence`.
+    pub fn load_dependence(address: Expression) -> Self {
+        Self::LoadDependence { address }
+    }
+
     /// Make self conditional
     pub fn only_if(self, condition: Expression) -> Self {
         Self::Conditional {
@@ -60,7 +88,9 @@ impl Effect {
                 .into_iter()
                 .chain(effect.variables().into_iter())
                 .collect(),
-            Self::CacheFetch { address, .. } => address.variables(),
+            Self::CacheFetch { address, .. } | Self::CacheEvict { address, .. } => {
+                address.variables()
+            }
             Self::BranchTarget { location, target } => location
                 .variables()
                 .into_iter()
@@ -74,6 +104,12 @@ impl Effect {
                 .into_iter()
                 .chain(condition.variables().into_iter())
                 .collect(),
+            Self::StoreLocation { location, address } => location
+                .variables()
+                .into_iter()
+                .chain(address.variables().into_iter())
+                .collect(),
+            Self::LoadDependence { address } => address.variables(),
         }
     }
 
@@ -85,7 +121,9 @@ impl Effect {
                 .into_iter()
                 .chain(effect.variables_mut().into_iter())
                 .collect(),
-            Self::CacheFetch { address, .. } => address.variables_mut(),
+            Self::CacheFetch { address, .. } | Self::CacheEvict { address, .. } => {
+                address.variables_mut()
+            }
             Self::BranchTarget { location, target } => location
                 .variables_mut()
                 .into_iter()
@@ -99,6 +137,12 @@ impl Effect {
                 .into_iter()
                 .chain(condition.variables_mut().into_iter())
                 .collect(),
+            Self::StoreLocation { location, address } => location
+                .variables_mut()
+                .into_iter()
+                .chain(address.variables_mut().into_iter())
+                .collect(),
+            Self::LoadDependence { address } => address.variables_mut(),
         }
     }
 
@@ -109,12 +153,14 @@ impl Effect {
                 .into_iter()
                 .chain(effect.expressions())
                 .collect(),
-            Self::CacheFetch { address, .. } => vec![address],
+            Self::CacheFetch { address, .. } | Self::CacheEvict { address, .. } => vec![address],
             Self::BranchTarget { location, target } => vec![location, target],
             Self::BranchCondition {
                 location,
                 condition,
             } => vec![location, condition],
+            Self::StoreLocation { location, address } => vec![location, address],
+            Self::LoadDependence { address } => vec![address],
         }
     }
 
@@ -125,12 +171,14 @@ impl Effect {
                 .into_iter()
                 .chain(effect.expressions_mut())
                 .collect(),
-            Self::CacheFetch { address, .. } => vec![address],
+            Self::CacheFetch { address, .. } | Self::CacheEvict { address, .. } => vec![address],
             Self::BranchTarget { location, target } => vec![location, target],
             Self::BranchCondition {
                 location,
                 condition,
             } => vec![location, condition],
+            Self::StoreLocation { location, address } => vec![location, address],
+            Self::LoadDependence { address } => vec![address],
         }
     }
 }
@@ -142,6 +190,9 @@ impl fmt::Display for Effect {
             Self::CacheFetch { address, bit_width } => {
                 write!(f, "cache_fetch({}, {})", address, bit_width)
             }
+            Self::CacheEvict { address, bit_width } => {
+                write!(f, "cache_evict({}, {})", address, bit_width)
+            }
             Self::BranchTarget { location, target } => {
                 write!(f, "branch_target({}, {})", location, target)
             }
@@ -149,6 +200,10 @@ impl fmt::Display for Effect {
                 location,
                 condition,
             } => write!(f, "branch_condition({}, {})", location, condition),
+            Self::StoreLocation { location, address } => {
+                write!(f, "store_location({}, {})", location, address)
+            }
+            Self::LoadDependence { address } => write!(f, "load_dependence({})", address),
         }
     }
 }
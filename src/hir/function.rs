@@ -1,7 +1,8 @@
 use crate::hir::ControlFlowGraph;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Function {
     address: u64,
     name: Option<String>,
@@ -1,14 +1,29 @@
 use crate::hir::ControlFlowGraph;
 use std::fmt;
 
+/// By default, we have a 2-safety hyperproperty
+pub const DEFAULT_SELF_COMPOSITIONS: usize = 2;
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct InlinedProgram {
     control_flow_graph: ControlFlowGraph,
+    self_compositions: usize, // The number of executions which are compared
 }
 
 impl InlinedProgram {
     pub fn new(control_flow_graph: ControlFlowGraph) -> Self {
-        Self { control_flow_graph }
+        Self {
+            control_flow_graph,
+            self_compositions: DEFAULT_SELF_COMPOSITIONS,
+        }
+    }
+
+    pub fn self_compositions(&self) -> usize {
+        self.self_compositions
+    }
+
+    pub fn set_self_compositions(&mut self, self_compositions: usize) {
+        self.self_compositions = self_compositions
     }
 
     pub fn control_flow_graph(&self) -> &ControlFlowGraph {
@@ -2,12 +2,14 @@
 //!
 use crate::error::Result;
 use crate::expr::{Expression, Variable};
-use crate::hir::{Effect, Operation};
+use crate::hir::{BarrierKind, Effect, Operation};
+use crate::ir::Provenance;
 use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Serialize, Deserialize)]
     pub struct Labels: u32 {
         const PSEUDO = 0b00001; // Instruction isn't part of the assembly
         const HELPER = 0b00010; // A single complex instruction may be described by multiple simpler instructions, which can be marked as helpers (e.g. update of the status register).
@@ -59,12 +61,46 @@ impl fmt::Display for Labels {
     }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+/// Records which transformation introduced an instruction (or the copy of it).
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Origin {
+    /// Copy of the loop body for the given (1-based) iteration, created by loop unwinding.
+    LoopIteration { loop_id: usize, iteration: usize },
+    /// Copy within the transient execution started by the speculating instruction at the given address.
+    /// Shared transient copies only record the first speculating instruction.
+    TransientExecution { address: u64 },
+    /// Pseudo instruction added by the named transformation.
+    Transform(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LoopIteration { loop_id, iteration } => {
+                write!(f, "loop 0x{:X} iteration {}", loop_id, iteration)
+            }
+            Self::TransientExecution { address } => {
+                write!(f, "transient execution of 0x{:X}", address)
+            }
+            Self::Transform(name) => write!(f, "added by {}", name),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Instruction {
     operation: Operation,
     effects: Vec<Effect>,
     address: Option<u64>,
     labels: Labels,
+    provenance: Option<Provenance>,
+    /// Origins in the order the transformations were applied
+    #[serde(default)]
+    origins: Vec<Origin>,
+    /// Disassembly (mnemonic + operands) of the machine instruction this `Instruction` was lifted from,
+    /// only set for the first `Instruction` of each machine instruction
+    #[serde(default)]
+    disassembly: Option<String>,
 }
 
 impl Instruction {
@@ -75,6 +111,9 @@ impl Instruction {
             effects: vec![],
             address: None,
             labels: Labels::default(),
+            provenance: None,
+            origins: Vec::new(),
+            disassembly: None,
         }
     }
 
@@ -114,8 +153,18 @@ impl Instruction {
     }
 
     /// Create a new `Barrier` instruction.
-    pub fn barrier() -> Self {
-        Self::new(Operation::barrier())
+    pub fn barrier(kind: BarrierKind) -> Self {
+        Self::new(Operation::barrier(kind))
+    }
+
+    /// Create a new `CacheFlush` instruction.
+    pub fn cache_flush(address: Expression) -> Result<Self> {
+        Ok(Self::new(Operation::cache_flush(address)?))
+    }
+
+    /// Create a new `Prefetch` instruction.
+    pub fn prefetch(address: Expression) -> Result<Self> {
+        Ok(Self::new(Operation::prefetch(address)?))
     }
 
     /// Create a new `Assert` instruction.
@@ -193,6 +242,37 @@ impl Instruction {
         self.address = address;
     }
 
+    /// Get the optional disassembly of the original machine instruction
+    pub fn disassembly(&self) -> Option<&str> {
+        self.disassembly.as_deref()
+    }
+
+    /// Set the optional disassembly of the original machine instruction
+    pub fn set_disassembly(&mut self, disassembly: Option<String>) {
+        self.disassembly = disassembly;
+    }
+
+    /// Get the optional assertion provenance for this `Instruction`
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Set the assertion provenance for this `Instruction`
+    pub fn set_provenance(&mut self, provenance: Provenance) -> &mut Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Get the transformations which introduced this `Instruction` (oldest first)
+    pub fn origins(&self) -> &[Origin] {
+        &self.origins
+    }
+
+    /// Record that this `Instruction` was introduced (or copied) by a transformation
+    pub fn add_origin(&mut self, origin: Origin) {
+        self.origins.push(origin);
+    }
+
     pub fn is_assign(&self) -> bool {
         self.operation.is_assign()
     }
@@ -302,9 +382,15 @@ impl fmt::Display for Instruction {
             write!(f, "{:X} ", address)?;
         }
         write!(f, "{}", self.operation)?;
+        if let Some(disassembly) = &self.disassembly {
+            write!(f, "  ; {}", disassembly)?;
+        }
         for effect in &self.effects {
             write!(f, "\n\t# {}", effect)?;
         }
+        for origin in &self.origins {
+            write!(f, "\n\t~ {}", origin)?;
+        }
         Ok(())
     }
 }
@@ -1,6 +1,9 @@
 use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 bitflags! {
+    #[derive(Serialize, Deserialize)]
     pub struct MemoryPermissions: u32 {
         const READ    = 0b001;
         const WRITE   = 0b010;
@@ -9,7 +12,7 @@ bitflags! {
 }
 
 /// A (half-open) range bounded inclusively below and exclusively above (start_address..end_address).
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct MemorySection {
     start_address: u64,
     end_address: u64,
@@ -42,15 +45,56 @@ impl MemorySection {
     }
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Default)]
+/// A named memory location (e.g. a global variable) from the symbol table of the binary.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Symbol {
+    name: String,
+    address: u64,
+    size: u64,
+}
+
+impl Symbol {
+    pub fn new(name: String, address: u64, size: u64) -> Self {
+        Self {
+            name,
+            address,
+            size,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// The size in bytes, or zero if unknown.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Memory {
     sections: Vec<MemorySection>,
+    symbols: Vec<Symbol>,
+    /// Pointers written by the dynamic loader (relocations), maps location to the relocated value.
+    #[serde(default)]
+    relocations: BTreeMap<u64, u64>,
+    /// Initialized data (e.g. muasm data sections), maps start address to the bytes.
+    #[serde(default)]
+    data: BTreeMap<u64, Vec<u8>>,
 }
 
 impl Memory {
     pub fn new() -> Self {
         Self {
             sections: Vec::default(),
+            symbols: Vec::default(),
+            relocations: BTreeMap::default(),
+            data: BTreeMap::default(),
         }
     }
 
@@ -61,4 +105,34 @@ impl Memory {
     pub fn sections(&self) -> &[MemorySection] {
         &self.sections
     }
+
+    pub fn insert_symbol(&mut self, symbol: Symbol) {
+        self.symbols.push(symbol)
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name() == name)
+    }
+
+    /// Records that the (pointer-sized) memory at `location` holds `value` once relocations are applied.
+    pub fn insert_relocation(&mut self, location: u64, value: u64) {
+        self.relocations.insert(location, value);
+    }
+
+    pub fn relocations(&self) -> &BTreeMap<u64, u64> {
+        &self.relocations
+    }
+
+    /// Records that the memory starting at `address` initially holds `bytes`.
+    pub fn insert_data(&mut self, address: u64, bytes: Vec<u8>) {
+        self.data.insert(address, bytes);
+    }
+
+    pub fn data(&self) -> &BTreeMap<u64, Vec<u8>> {
+        &self.data
+    }
 }
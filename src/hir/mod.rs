@@ -16,6 +16,7 @@ mod memory;
 mod operation;
 mod phi_node;
 mod program;
+pub mod text;
 pub mod transformation;
 mod translation;
 
@@ -25,8 +26,8 @@ pub use self::edge::Edge;
 pub use self::effect::Effect;
 pub use self::function::Function;
 pub use self::inlined_program::InlinedProgram;
-pub use self::instruction::Instruction;
-pub use self::memory::{Memory, MemoryPermissions, MemorySection};
-pub use self::operation::Operation;
+pub use self::instruction::{Instruction, Origin};
+pub use self::memory::{Memory, MemoryPermissions, MemorySection, Symbol};
+pub use self::operation::{BarrierKind, Operation};
 pub use self::phi_node::PhiNode;
 pub use self::program::{Program, ProgramEntry};
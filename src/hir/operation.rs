@@ -1,8 +1,46 @@
 use crate::error::Result;
 use crate::expr::{Expression, Memory, Variable};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+/// The kind of a barrier (fence) instruction.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BarrierKind {
+    /// Full serialization (e.g. mfence, cpuid): Stops any transient execution.
+    Full,
+    /// Load fence (e.g. lfence): Later instructions don't execute before all earlier ones completed,
+    /// therefore it stops any transient execution.
+    Load,
+    /// Store fence (e.g. sfence): Only orders stores among each other,
+    /// neither branch prediction nor store bypass is stopped by it.
+    Store,
+    /// Speculation-only barrier (e.g. csdb, muasm `spbarr`): Stops any transient execution,
+    /// but doesn't order (non-speculative) memory accesses.
+    Speculation,
+}
+
+impl BarrierKind {
+    /// Returns true if no instruction after the barrier executes transiently.
+    pub fn stops_speculation(&self) -> bool {
+        match self {
+            Self::Full | Self::Load | Self::Speculation => true,
+            Self::Store => false,
+        }
+    }
+}
+
+impl fmt::Display for BarrierKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Load => write!(f, "load"),
+            Self::Store => write!(f, "store"),
+            Self::Speculation => write!(f, "speculation"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Operation {
     /// Assign the value given in expression to the variable indicated.
     Assign {
@@ -33,8 +71,12 @@ pub enum Operation {
     },
     /// Does nothing aka. no operation.
     Skip,
-    /// Speculation Barrier
-    Barrier,
+    /// Barrier (fence) of the given kind, see `BarrierKind` for the semantics of each kind.
+    Barrier { kind: BarrierKind },
+    /// Flush the cache line holding the address from the cache (e.g. clflush).
+    CacheFlush { address: Expression },
+    /// Fetch the cache line holding the address into the cache (e.g. prefetcht0).
+    Prefetch { address: Expression },
     /// Assert that the condition is true.
     Assert { condition: Expression },
     /// Assume that the condition is true.
@@ -100,8 +142,20 @@ impl Operation {
     }
 
     /// Create a new `Operation::Barrier`
-    pub fn barrier() -> Self {
-        Self::Barrier
+    pub fn barrier(kind: BarrierKind) -> Self {
+        Self::Barrier { kind }
+    }
+
+    /// Create a new `Operation::CacheFlush`.
+    pub fn cache_flush(address: Expression) -> Result<Self> {
+        address.sort().expect_word()?;
+        Ok(Self::CacheFlush { address })
+    }
+
+    /// Create a new `Operation::Prefetch`.
+    pub fn prefetch(address: Expression) -> Result<Self> {
+        address.sort().expect_word()?;
+        Ok(Self::Prefetch { address })
     }
 
     /// Create a new `Operation::Assert`.
@@ -155,7 +209,15 @@ impl Operation {
     }
 
     pub fn is_barrier(&self) -> bool {
-        matches!(self, Self::Barrier)
+        matches!(self, Self::Barrier { .. })
+    }
+
+    pub fn is_cache_flush(&self) -> bool {
+        matches!(self, Self::CacheFlush { .. })
+    }
+
+    pub fn is_prefetch(&self) -> bool {
+        matches!(self, Self::Prefetch { .. })
     }
 
     pub fn is_assert(&self) -> bool {
@@ -197,13 +259,14 @@ impl Operation {
                 .chain(address.variables().into_iter())
                 .collect(),
             Self::Call { target } | Self::Branch { target } => target.variables(),
+            Self::CacheFlush { address } | Self::Prefetch { address } => address.variables(),
             Self::ConditionalBranch { condition, target } => condition
                 .variables()
                 .into_iter()
                 .chain(target.variables().into_iter())
                 .collect(),
             Self::Assert { condition } | Self::Assume { condition } => condition.variables(),
-            Self::Skip | Self::Barrier => Vec::new(),
+            Self::Skip | Self::Barrier { .. } => Vec::new(),
         }
     }
 
@@ -230,13 +293,14 @@ impl Operation {
                 .chain(address.variables_mut().into_iter())
                 .collect(),
             Self::Call { target } | Self::Branch { target } => target.variables_mut(),
+            Self::CacheFlush { address } | Self::Prefetch { address } => address.variables_mut(),
             Self::ConditionalBranch { condition, target } => condition
                 .variables_mut()
                 .into_iter()
                 .chain(target.variables_mut().into_iter())
                 .collect(),
             Self::Assert { condition } | Self::Assume { condition } => condition.variables_mut(),
-            Self::Skip | Self::Barrier => Vec::new(),
+            Self::Skip | Self::Barrier { .. } => Vec::new(),
         }
     }
 
@@ -249,7 +313,9 @@ impl Operation {
             | Self::Branch { .. }
             | Self::ConditionalBranch { .. }
             | Self::Skip
-            | Self::Barrier
+            | Self::Barrier { .. }
+            | Self::CacheFlush { .. }
+            | Self::Prefetch { .. }
             | Self::Assert { .. }
             | Self::Assume { .. }
             | Self::Observable { .. }
@@ -266,7 +332,9 @@ impl Operation {
             | Self::Branch { .. }
             | Self::ConditionalBranch { .. }
             | Self::Skip
-            | Self::Barrier
+            | Self::Barrier { .. }
+            | Self::CacheFlush { .. }
+            | Self::Prefetch { .. }
             | Self::Assert { .. }
             | Self::Assume { .. }
             | Self::Observable { .. }
@@ -291,9 +359,10 @@ impl Operation {
             Self::Store { address, expr, .. } => vec![address, expr],
             Self::Load { address, .. } => vec![address],
             Self::Call { target } | Self::Branch { target } => vec![target],
+            Self::CacheFlush { address } | Self::Prefetch { address } => vec![address],
             Self::ConditionalBranch { condition, target } => vec![condition, target],
             Self::Assert { condition } | Self::Assume { condition } => vec![condition],
-            Self::Skip | Self::Barrier => Vec::new(),
+            Self::Skip | Self::Barrier { .. } => Vec::new(),
         }
     }
 
@@ -306,9 +375,10 @@ impl Operation {
             Self::Store { address, expr, .. } => vec![address, expr],
             Self::Load { address, .. } => vec![address],
             Self::Call { target } | Self::Branch { target } => vec![target],
+            Self::CacheFlush { address } | Self::Prefetch { address } => vec![address],
             Self::ConditionalBranch { condition, target } => vec![condition, target],
             Self::Assert { condition } | Self::Assume { condition } => vec![condition],
-            Self::Skip | Self::Barrier => Vec::new(),
+            Self::Skip | Self::Barrier { .. } => Vec::new(),
         }
     }
 }
@@ -340,7 +410,9 @@ impl fmt::Display for Operation {
             Self::Assert { condition } => write!(f, "assert {}", condition),
             Self::Assume { condition } => write!(f, "assume {}", condition),
             Self::Skip => write!(f, "skip"),
-            Self::Barrier => write!(f, "barrier"),
+            Self::Barrier { kind } => write!(f, "barrier {}", kind),
+            Self::CacheFlush { address } => write!(f, "cache_flush {}", address),
+            Self::Prefetch { address } => write!(f, "prefetch {}", address),
             Self::Observable { expr } => write!(f, "observable({})", expr),
             Self::Indistinguishable { expr } => write!(f, "indistinguishable({})", expr),
         }
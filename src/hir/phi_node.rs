@@ -1,10 +1,11 @@
 //! `PhiNode` represents a phi node in the SSA form
 
 use crate::expr::Variable;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct PhiNode {
     incoming: BTreeMap<usize, Variable>, // Input from another block
     out: Variable,
@@ -34,6 +35,11 @@ impl PhiNode {
         self.incoming.contains_key(&block_index)
     }
 
+    /// Returns the incoming variables by their source block index.
+    pub fn incoming(&self) -> &BTreeMap<usize, Variable> {
+        &self.incoming
+    }
+
     pub fn incoming_variables(&self) -> Vec<&Variable> {
         self.incoming.values().collect()
     }
@@ -1,19 +1,36 @@
 use crate::error::Result;
 use crate::hir::{Function, Memory};
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ProgramEntry {
     Name(String),
     Address(u64),
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Default)]
+impl ProgramEntry {
+    /// Parses the entry given either as hex address (`0x` prefix) or as function name.
+    pub fn parse(s: &str) -> Self {
+        if s.starts_with("0x") {
+            let s = s.trim_start_matches("0x");
+            if let Ok(addr) = u64::from_str_radix(s, 16) {
+                return Self::Address(addr);
+            }
+        }
+
+        Self::Name(s.to_owned())
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Program {
     functions: BTreeMap<u64, Function>,
     entry: Option<ProgramEntry>,
     memory: Memory,
+    /// Code pointers stored in memory (e.g. vtables or jump tables), maps location to target.
+    code_pointers: BTreeMap<u64, u64>,
 }
 
 impl Program {
@@ -22,6 +39,7 @@ impl Program {
             functions: BTreeMap::new(),
             entry: None,
             memory: Memory::default(),
+            code_pointers: BTreeMap::new(),
         }
     }
 
@@ -41,6 +59,18 @@ impl Program {
         self.functions.values().find(|f| f.name() == Some(name))
     }
 
+    /// Returns the function containing an instruction with the given address.
+    pub fn function_containing(&self, address: u64) -> Option<&Function> {
+        self.functions.values().find(|f| {
+            f.control_flow_graph().blocks().iter().any(|block| {
+                block
+                    .instructions()
+                    .iter()
+                    .any(|inst| inst.address() == Some(address))
+            })
+        })
+    }
+
     pub fn insert_function(&mut self, func: Function) -> Result<()> {
         let addr = func.address();
         if self.functions.contains_key(&addr) {
@@ -92,6 +122,25 @@ impl Program {
     pub fn memory_mut(&mut self) -> &mut Memory {
         &mut self.memory
     }
+
+    /// Records that the memory at `location` holds a pointer to the function at `target`.
+    pub fn insert_code_pointer(&mut self, location: u64, target: u64) {
+        self.code_pointers.insert(location, target);
+    }
+
+    pub fn code_pointers(&self) -> &BTreeMap<u64, u64> {
+        &self.code_pointers
+    }
+
+    /// Returns the addresses of all functions whose address is stored in memory,
+    /// which are the possible targets of unresolved indirect calls.
+    pub fn address_taken_functions(&self) -> BTreeSet<u64> {
+        self.code_pointers
+            .values()
+            .filter(|&&target| self.functions.contains_key(&target))
+            .cloned()
+            .collect()
+    }
 }
 
 impl fmt::Display for Program {
@@ -0,0 +1,162 @@
+//! Textual HIR Format
+//!
+//! A stable plain-text representation of (transformed) HIR programs, which can be dumped,
+//! edited by hand and loaded again. Programs are written as S-expressions, e.g.:
+//!
+//! ```text
+//! ; SpecBMC HIR
+//! (self-compositions 2)
+//! (entry 0x0)
+//! (exit 0x1)
+//!
+//! (var _memory Memory)
+//! (var rax (BitVec 64))
+//!
+//! (block 0x0
+//!   (load rax#1 _memory#0 (bvadd rax#0 (_ bv8 64)) :address 0x401000 :effect (cache-fetch rax#0 64)))
+//! (block 0x1 :transient
+//!   (observable rax#1@0))
+//!
+//! (edge 0x0 0x1 :condition (bvult rax#1 (_ bv16 64)) :speculate)
+//! ```
+//!
+//! Variables are declared once by name (`(var NAME SORT [:rollback-persistent])`) and referenced
+//! as `NAME[#VERSION][@COMPOSITION]`, names containing special characters are enclosed in `|`.
+//! Expressions use the operator names of the HIR, the sort of each expression is inferred.
+//! Barriers are written as `(barrier KIND)` with the kinds `full`, `load`, `store` and `speculation`.
+//! The disassembly of the lifted machine instruction is kept as `:disassembly "TEXT"`.
+//! The transformation which introduced an instruction is kept as `:origin (loop ID ITERATION)`,
+//! `:origin (transient ADDRESS)` or `:origin (transform "NAME")`.
+
+mod parser;
+mod printer;
+
+pub use self::parser::parse_program;
+pub use self::printer::print_program;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::expr::{BitVector, Boolean, Expression, Memory, Sort, Variable};
+    use crate::hir::{
+        ControlFlowGraph, Effect, InlinedProgram, Instruction, Operation, Origin, PhiNode,
+    };
+    use crate::ir::Provenance;
+    use std::path::Path;
+
+    fn versioned(mut variable: Variable, version: usize) -> Variable {
+        variable.set_version(Some(version));
+        variable
+    }
+
+    #[test]
+    fn test_print_and_parse_roundtrip() {
+        // Given:
+        let rax = BitVector::word_variable("rax");
+        let odd_name = Variable::new("temp 0x10.1", Sort::boolean());
+        let mut persistent = Variable::new("_persistent", Sort::array(Sort::word(), Sort::word()));
+        persistent.set_rollback_persistent(true);
+
+        let mut cfg = ControlFlowGraph::new();
+
+        let block0 = cfg.new_block();
+        block0
+            .assign(
+                versioned(rax.clone(), 1),
+                BitVector::add(
+                    versioned(rax.clone(), 0).into(),
+                    BitVector::word_constant(8),
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .set_address(Some(0x401000));
+        block0.instructions_mut()[0].set_disassembly(Some("add rax, 8".to_owned()));
+        block0
+            .assign(odd_name.clone(), Boolean::constant(false))
+            .unwrap()
+            .labels_mut()
+            .pseudo()
+            .helper();
+        block0.instructions_mut()[1].add_origin(Origin::Transform("SSATransformation".to_owned()));
+        block0.instructions_mut().push({
+            let mut instruction = Instruction::new(Operation::Store {
+                address: versioned(rax.clone(), 1).into(),
+                expr: BitVector::extract(7, 0, rax.clone().into()).unwrap(),
+                memory_in: versioned(Memory::variable(), 0),
+                memory_out: versioned(Memory::variable(), 1),
+            });
+            instruction.add_effect(
+                Effect::cache_fetch(rax.clone().into(), 8).only_if(odd_name.clone().into()),
+            );
+            instruction.add_origin(Origin::LoopIteration {
+                loop_id: 3,
+                iteration: 2,
+            });
+            instruction.add_origin(Origin::TransientExecution { address: 0x401000 });
+            instruction
+        });
+        block0
+            .assign(persistent.clone(), persistent.clone().into())
+            .unwrap();
+        let block0 = block0.index();
+
+        let block1 = cfg.new_block();
+        let mut phi_node = PhiNode::new(versioned(rax.clone(), 2));
+        phi_node.add_incoming(versioned(rax.clone(), 1), block0);
+        block1.add_phi_node(phi_node);
+        block1
+            .assert(
+                Expression::equal(rax.self_compose(0).into(), rax.self_compose(1).into()).unwrap(),
+            )
+            .unwrap()
+            .set_provenance(
+                Provenance::observation()
+                    .with_address(Some(0x401004))
+                    .with_transform("observations"),
+            );
+        block1.set_transient();
+        block1.set_loop_id(Some(3));
+        let block1 = block1.index();
+
+        cfg.conditional_edge(block0, block1, odd_name.into())
+            .unwrap()
+            .labels_mut()
+            .speculate()
+            .taken();
+        cfg.set_entry(block0).unwrap();
+        cfg.set_exit(block1).unwrap();
+
+        let mut program = InlinedProgram::new(cfg);
+        program.set_self_compositions(3);
+
+        // When:
+        let text = print_program(&program).unwrap();
+        let parsed = parse_program(&text, Path::new("test.hir")).unwrap();
+
+        // Then:
+        assert_eq!(parsed, program);
+        assert_eq!(print_program(&parsed).unwrap(), text);
+        assert!(text.contains("(var |temp 0x10.1| Boolean)"));
+        assert!(text.contains(":rollback-persistent"));
+    }
+
+    #[test]
+    fn test_parse_reports_line_of_undeclared_variable() {
+        // Given:
+        let text = "(entry 0x0)\n(block 0x0\n  (assign x (_ bv1 64)))\n";
+
+        // When:
+        let result = parse_program(text, Path::new("test.hir"));
+
+        // Then:
+        match result {
+            Err(Error::InvalidHir { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(message, "Undeclared variable 'x'");
+            }
+            _ => panic!("Expected InvalidHir error"),
+        }
+    }
+}
@@ -0,0 +1,792 @@
+use crate::error::{Error, Result};
+use crate::expr::{
+    Array, BitVector, BitVectorValue, Boolean, BranchTargetBuffer, Cache, Expression, Integer,
+    List, Memory, MemoryDependencePredictor, PatternHistoryTable, Predictor, Sort, Tuple, Variable,
+};
+use crate::hir::{
+    BarrierKind, Block, ControlFlowGraph, Edge, Effect, InlinedProgram, Instruction, Operation,
+    Origin, PhiNode,
+};
+use crate::ir::{AssertionKind, Provenance};
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Parses a program given in the textual HIR format.
+///
+/// `path` is only used for error messages.
+pub fn parse_program(source: &str, path: &Path) -> Result<InlinedProgram> {
+    let parser = Parser {
+        path: path.to_owned(),
+        declarations: HashMap::new(),
+    };
+    parser.program(&read(source, path)?)
+}
+
+/// A symbolic expression, where each node knows the line it starts at.
+#[derive(Debug)]
+enum SExpr {
+    Atom(String, usize),
+    List(Vec<SExpr>, usize),
+}
+
+impl SExpr {
+    fn line(&self) -> usize {
+        match self {
+            Self::Atom(_, line) | Self::List(_, line) => *line,
+        }
+    }
+
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Self::Atom(atom, _) => Some(atom),
+            Self::List(..) => None,
+        }
+    }
+}
+
+/// Reads all symbolic expressions of `source`.
+///
+/// Comments start with `;` and last until the end of the line.
+/// Atoms may contain `|`-quoted parts and strings are kept as atoms (including the quotes).
+fn read(source: &str, path: &Path) -> Result<Vec<SExpr>> {
+    let error = |line: usize, message: &str| Error::InvalidHir {
+        path: path.to_owned(),
+        line,
+        message: message.to_owned(),
+    };
+
+    let mut stack: Vec<(Vec<SExpr>, usize)> = vec![(Vec::new(), 1)];
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            ';' => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' => stack.push((Vec::new(), line)),
+            ')' => {
+                if stack.len() == 1 {
+                    return Err(error(line, "Unexpected ')'"));
+                }
+                let (items, start) = stack.pop().unwrap();
+                stack.last_mut().unwrap().0.push(SExpr::List(items, start));
+            }
+            _ => {
+                let start = line;
+                let mut atom = String::from(c);
+                let mut delimiter = match c {
+                    '|' | '"' => Some(c),
+                    _ => None,
+                };
+                while let Some(&c) = chars.peek() {
+                    if delimiter.is_none() && (c.is_whitespace() || "();".contains(c)) {
+                        break;
+                    }
+                    chars.next();
+                    atom.push(c);
+                    match delimiter {
+                        Some(d) if d == c => delimiter = None,
+                        None if c == '|' || c == '"' => delimiter = Some(c),
+                        _ => {}
+                    }
+                    if c == '\n' {
+                        line += 1;
+                    }
+                }
+                if delimiter.is_some() {
+                    return Err(error(start, "Unterminated quote"));
+                }
+                stack.last_mut().unwrap().0.push(SExpr::Atom(atom, start));
+            }
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err(error(stack.last().unwrap().1, "Missing ')'"));
+    }
+    Ok(stack.pop().unwrap().0)
+}
+
+struct Parser {
+    path: PathBuf,
+    /// Sort and rollback persistence of the declared variables (by name).
+    declarations: HashMap<String, (Sort, bool)>,
+}
+
+impl Parser {
+    fn error<S: Into<String>>(&self, line: usize, message: S) -> Error {
+        Error::InvalidHir {
+            path: self.path.clone(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Attaches the line to errors which don't carry it yet (e.g. sort mismatches).
+    fn at<T>(&self, line: usize, result: Result<T>) -> Result<T> {
+        result.map_err(|e| match e {
+            Error::InvalidHir { .. } => e,
+            _ => self.error(line, e.to_string()),
+        })
+    }
+
+    fn program(mut self, forms: &[SExpr]) -> Result<InlinedProgram> {
+        let mut self_compositions = None;
+        let mut entry = None;
+        let mut exit = None;
+        let mut blocks = Vec::new();
+        let mut edges = Vec::new();
+
+        for form in forms {
+            let (head, args) = self.form(form)?;
+            match head {
+                "self-compositions" => {
+                    self_compositions = Some(self.number(self.single(form, args)?)? as usize)
+                }
+                "entry" => entry = Some(self.number(self.single(form, args)?)? as usize),
+                "exit" => exit = Some(self.number(self.single(form, args)?)? as usize),
+                "var" => self.declaration(form, args)?,
+                "block" => blocks.push(self.block(form, args)?),
+                "edge" => edges.push((form.line(), self.edge(form, args)?)),
+                _ => return Err(self.error(form.line(), format!("Unknown form '{}'", head))),
+            }
+        }
+
+        let mut cfg = ControlFlowGraph::new();
+        for (line, block) in blocks {
+            let result = cfg.add_block(block);
+            self.at(line, result)?;
+        }
+        for (line, edge) in edges {
+            let result = match edge.condition() {
+                Some(condition) => {
+                    cfg.conditional_edge(edge.head(), edge.tail(), condition.clone())
+                }
+                None => cfg.unconditional_edge(edge.head(), edge.tail()),
+            }
+            .map(|new_edge| *new_edge.labels_mut() = *edge.labels());
+            self.at(line, result)?;
+        }
+        if let Some(entry) = entry {
+            cfg.set_entry(entry)?;
+        }
+        if let Some(exit) = exit {
+            cfg.set_exit(exit)?;
+        }
+
+        let mut program = InlinedProgram::new(cfg);
+        if let Some(self_compositions) = self_compositions {
+            program.set_self_compositions(self_compositions);
+        }
+        Ok(program)
+    }
+
+    /// Splits a form `(head args...)` into its head and arguments.
+    fn form<'a>(&self, sexpr: &'a SExpr) -> Result<(&'a str, &'a [SExpr])> {
+        match sexpr {
+            SExpr::List(items, line) => match items.split_first() {
+                Some((SExpr::Atom(head, _), args)) => Ok((head.as_str(), args)),
+                _ => Err(self.error(*line, "Expected form (name arguments...)")),
+            },
+            SExpr::Atom(atom, line) => {
+                Err(self.error(*line, format!("Expected form but was '{}'", atom)))
+            }
+        }
+    }
+
+    fn single<'a>(&self, form: &SExpr, args: &'a [SExpr]) -> Result<&'a SExpr> {
+        self.arguments::<1>(form, args).map(|[arg]| arg)
+    }
+
+    fn arguments<'a, const N: usize>(
+        &self,
+        form: &SExpr,
+        args: &'a [SExpr],
+    ) -> Result<[&'a SExpr; N]> {
+        let args: Vec<&SExpr> = args.iter().collect();
+        args.try_into()
+            .map_err(|_| self.error(form.line(), format!("Expected {} argument(s)", N)))
+    }
+
+    fn number(&self, sexpr: &SExpr) -> Result<u64> {
+        let atom = sexpr
+            .atom()
+            .ok_or_else(|| self.error(sexpr.line(), "Expected number"))?;
+        let value = match atom.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => atom.parse(),
+        };
+        value.map_err(|_| self.error(sexpr.line(), format!("Invalid number '{}'", atom)))
+    }
+
+    fn sort(&self, sexpr: &SExpr) -> Result<Sort> {
+        if let Some(atom) = sexpr.atom() {
+            return match atom {
+                "Boolean" => Ok(Sort::boolean()),
+                "Integer" => Ok(Sort::integer()),
+                "Memory" => Ok(Sort::memory()),
+                "Predictor" => Ok(Sort::predictor()),
+                "Cache" => Ok(Sort::cache()),
+                "BranchTargetBuffer" => Ok(Sort::branch_target_buffer()),
+                "PatternHistoryTable" => Ok(Sort::pattern_history_table()),
+                "MemoryDependencePredictor" => Ok(Sort::memory_dependence_predictor()),
+                _ => Err(self.error(sexpr.line(), format!("Unknown sort '{}'", atom))),
+            };
+        }
+
+        let (head, args) = self.form(sexpr)?;
+        match head {
+            "BitVec" => Ok(Sort::bit_vector(
+                self.number(self.single(sexpr, args)?)? as usize
+            )),
+            "Array" => {
+                let [range, domain] = self.arguments(sexpr, args)?;
+                Ok(Sort::array(self.sort(range)?, self.sort(domain)?))
+            }
+            "List" => Ok(Sort::list(self.sort(self.single(sexpr, args)?)?)),
+            "Tuple" => Ok(Sort::tuple(
+                args.iter()
+                    .map(|field| self.sort(field))
+                    .collect::<Result<_>>()?,
+            )),
+            _ => Err(self.error(sexpr.line(), format!("Unknown sort '{}'", head))),
+        }
+    }
+
+    /// `(var NAME SORT [:rollback-persistent])`
+    fn declaration(&mut self, form: &SExpr, args: &[SExpr]) -> Result<()> {
+        let (name, sort, rollback_persistent) = match args {
+            [name, sort] => (name, sort, false),
+            [name, sort, SExpr::Atom(flag, _)] if flag == ":rollback-persistent" => {
+                (name, sort, true)
+            }
+            _ => {
+                return Err(self.error(
+                    form.line(),
+                    "Expected (var NAME SORT [:rollback-persistent])",
+                ))
+            }
+        };
+        let name = match name.atom().map(unquote) {
+            Some((name, "")) => name.to_owned(),
+            _ => return Err(self.error(name.line(), "Invalid variable name")),
+        };
+        let sort = self.sort(sort)?;
+        self.declarations.insert(name, (sort, rollback_persistent));
+        Ok(())
+    }
+
+    /// `NAME[#VERSION][@COMPOSITION]` of a declared variable
+    fn variable(&self, sexpr: &SExpr) -> Result<Variable> {
+        let atom = sexpr
+            .atom()
+            .ok_or_else(|| self.error(sexpr.line(), "Expected variable"))?;
+        let (name, suffix) = unquote(atom);
+        let (suffix, composition) = match suffix.split_once('@') {
+            Some((suffix, composition)) => (suffix, Some(composition)),
+            None => (suffix, None),
+        };
+        let version = match suffix {
+            "" => None,
+            _ => Some(suffix.strip_prefix('#').unwrap_or(suffix)),
+        };
+        let index = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| self.error(sexpr.line(), format!("Invalid variable '{}'", atom)))
+        };
+
+        let (sort, rollback_persistent) = self
+            .declarations
+            .get(name)
+            .ok_or_else(|| self.error(sexpr.line(), format!("Undeclared variable '{}'", name)))?;
+        let mut variable = Variable::new(name, sort.clone());
+        variable.set_rollback_persistent(*rollback_persistent);
+        variable.set_version(version.map(&index).transpose()?);
+        variable.set_composition(composition.map(&index).transpose()?);
+        Ok(variable)
+    }
+
+    fn expression(&self, sexpr: &SExpr) -> Result<Expression> {
+        let line = sexpr.line();
+        let items = match sexpr {
+            SExpr::Atom(atom, _) => {
+                return match atom.as_str() {
+                    "true" => Ok(Boolean::constant(true)),
+                    "false" => Ok(Boolean::constant(false)),
+                    _ if atom.starts_with(|c: char| c.is_ascii_digit()) => {
+                        Ok(Integer::constant(self.number(sexpr)?))
+                    }
+                    _ => Ok(self.variable(sexpr)?.into()),
+                };
+            }
+            SExpr::List(items, _) => items,
+        };
+
+        let (operator, args) = items
+            .split_first()
+            .ok_or_else(|| self.error(line, "Empty expression"))?;
+
+        // Leaves which carry their sort or value as arguments
+        match operator.atom() {
+            Some("_") => return self.bit_vector_constant(sexpr, args),
+            Some("nondet") => return Ok(Expression::nondet(self.sort(self.single(sexpr, args)?)?)),
+            Some("nil") => return Ok(List::nil(self.sort(self.single(sexpr, args)?)?)),
+            _ => {}
+        }
+
+        let operands = args
+            .iter()
+            .map(|arg| self.expression(arg))
+            .collect::<Result<Vec<_>>>()?;
+        let result = self.apply(operator, operands);
+        self.at(line, result)
+    }
+
+    /// `(_ bvVALUE WIDTH)`
+    fn bit_vector_constant(&self, sexpr: &SExpr, args: &[SExpr]) -> Result<Expression> {
+        let [value, width] = self.arguments(sexpr, args)?;
+        let value = value
+            .atom()
+            .and_then(|value| value.strip_prefix("bv"))
+            .and_then(|value| BigUint::parse_bytes(value.as_bytes(), 10))
+            .ok_or_else(|| self.error(sexpr.line(), "Expected (_ bvVALUE WIDTH)"))?;
+        let width = self.number(width)? as usize;
+        Ok(BitVector::constant(BitVectorValue::new_big(value, width)))
+    }
+
+    /// Applies the operator to the operands, the resulting sort is inferred from the operands.
+    fn apply(&self, operator: &SExpr, operands: Vec<Expression>) -> Result<Expression> {
+        let (name, params): (&str, Vec<usize>) = match operator {
+            SExpr::Atom(name, _) => (name.as_str(), Vec::new()),
+            SExpr::List(..) => {
+                let (name, args) = self.form(operator)?;
+                if name == "cast" {
+                    let sort = self.sort(self.single(operator, args)?)?;
+                    let [expr] = arity(name, operands)?;
+                    return Ok(Expression::cast(sort, expr));
+                }
+                let params = args
+                    .iter()
+                    .map(|arg| self.number(arg).map(|n| n as usize))
+                    .collect::<Result<_>>()?;
+                (name, params)
+            }
+        };
+
+        macro_rules! unary {
+            ($f:expr) => {{
+                let [a] = arity(name, operands)?;
+                $f(a)
+            }};
+        }
+        macro_rules! binary {
+            ($f:expr) => {{
+                let [a, b] = arity(name, operands)?;
+                $f(a, b)
+            }};
+        }
+        macro_rules! ternary {
+            ($f:expr) => {{
+                let [a, b, c] = arity(name, operands)?;
+                $f(a, b, c)
+            }};
+        }
+
+        match (name, params.as_slice()) {
+            ("ite", []) => ternary!(Expression::ite),
+            ("=", []) if operands.len() >= 2 => Expression::all_equal(&operands),
+            // Boolean
+            ("not", []) => unary!(Boolean::not),
+            ("=>", []) => binary!(Boolean::imply),
+            ("and", []) if !operands.is_empty() => Boolean::conjunction(&operands),
+            ("or", []) if !operands.is_empty() => Boolean::disjunction(&operands),
+            ("xor", []) => binary!(Boolean::xor),
+            // Integer
+            ("<", []) => binary!(Integer::lt),
+            (">", []) => binary!(Integer::gt),
+            ("<=", []) => binary!(Integer::lte),
+            (">=", []) => binary!(Integer::gte),
+            ("mod", []) => binary!(Integer::modulo),
+            ("div", []) => binary!(Integer::div),
+            ("abs", []) => unary!(Integer::abs),
+            ("*", []) => binary!(Integer::mul),
+            ("+", []) => binary!(Integer::add),
+            ("-", []) if operands.len() == 1 => unary!(Integer::neg),
+            ("-", []) => binary!(Integer::sub),
+            // Bit-Vector
+            ("bv2bool", []) => unary!(BitVector::to_boolean),
+            ("bool2bv", &[bits]) => unary!(|e| BitVector::from_boolean(bits, e)),
+            ("bvconcat", []) if operands.len() >= 2 => BitVector::concat(&operands),
+            ("bvextract", &[hi, lo]) if hi >= lo => {
+                unary!(|e| BitVector::extract(hi, lo, e))
+            }
+            ("bvtrunc", &[bits]) => unary!(|e| BitVector::truncate(bits, e)),
+            ("bvnot", []) => unary!(BitVector::not),
+            ("bvneg", []) => unary!(BitVector::neg),
+            ("bvand", []) => binary!(BitVector::and),
+            ("bvor", []) => binary!(BitVector::or),
+            ("bvadd", []) => binary!(BitVector::add),
+            ("bvmul", []) => binary!(BitVector::mul),
+            ("bvudiv", []) => binary!(BitVector::udiv),
+            ("bvurem", []) => binary!(BitVector::urem),
+            ("bvshl", []) => binary!(BitVector::shl),
+            ("bvlshr", []) => binary!(BitVector::lshr),
+            ("bvnand", []) => binary!(BitVector::nand),
+            ("bvnor", []) => binary!(BitVector::nor),
+            ("bvxor", []) => binary!(BitVector::xor),
+            ("bvxnor", []) => binary!(BitVector::xnor),
+            ("bvcomp", []) => binary!(|a: Expression, b: Expression| -> Result<Expression> {
+                a.sort().expect_bit_vector()?;
+                b.sort().expect_sort(a.sort())?;
+                Ok(Expression::new(
+                    BitVector::Comp.into(),
+                    vec![a, b],
+                    Sort::bit_vector(1),
+                ))
+            }),
+            ("bvsub", []) => binary!(BitVector::sub),
+            ("bvsatsub", []) => binary!(BitVector::saturating_sub),
+            ("bvsdiv", []) => binary!(BitVector::sdiv),
+            ("bvsrem", []) => binary!(BitVector::srem),
+            ("bvsmod", []) => binary!(BitVector::smod),
+            ("bvumod", []) => binary!(BitVector::umod),
+            ("bvashr", []) => binary!(BitVector::ashr),
+            ("bvrepeat", &[n]) => unary!(|e| BitVector::repeat(n, e)),
+            ("bvzext", &[n]) => unary!(|e| BitVector::zero_extend(n, e)),
+            ("bvsext", &[n]) => unary!(|e| BitVector::sign_extend(n, e)),
+            ("bvrotl", &[n]) => unary!(|e| BitVector::rotate_left(n, e)),
+            ("bvrotr", &[n]) => unary!(|e| BitVector::rotate_right(n, e)),
+            ("bvult", []) => binary!(BitVector::ult),
+            ("bvule", []) => binary!(BitVector::ule),
+            ("bvugt", []) => binary!(BitVector::ugt),
+            ("bvuge", []) => binary!(BitVector::uge),
+            ("bvslt", []) => binary!(BitVector::slt),
+            ("bvsle", []) => binary!(BitVector::sle),
+            ("bvsgt", []) => binary!(BitVector::sgt),
+            ("bvsge", []) => binary!(BitVector::sge),
+            // Array, List and Tuple
+            ("select", []) => binary!(Array::select),
+            ("store", []) => ternary!(Array::store),
+            ("cons", []) => binary!(List::cons),
+            ("head", []) => unary!(List::head),
+            ("tail", []) => unary!(List::tail),
+            ("tuple", []) => Tuple::make(operands),
+            (name, []) if name.starts_with("get-") => {
+                let index = name["get-".len()..]
+                    .parse()
+                    .map_err(|_| format!("Invalid tuple field '{}'", name))?;
+                unary!(|e| Tuple::get(e, index))
+            }
+            // Arch
+            ("load", &[bits]) => binary!(|m, a| Memory::load(bits, m, a)),
+            ("store", &[_]) => ternary!(Memory::store),
+            ("cache-fetch", &[bits]) => binary!(|c, a| Cache::fetch(bits, c, a)),
+            ("cache-evict", &[bits]) => binary!(|c, a| Cache::evict(bits, c, a)),
+            ("speculation-window", []) => binary!(Predictor::speculation_window),
+            ("speculate", []) => binary!(Predictor::speculate),
+            ("taken", []) => binary!(Predictor::taken),
+            ("btb-track", []) => ternary!(BranchTargetBuffer::track),
+            ("pht-taken", []) => binary!(PatternHistoryTable::taken),
+            ("pht-not-taken", []) => binary!(PatternHistoryTable::not_taken),
+            ("mdp-bypass", []) => binary!(MemoryDependencePredictor::bypass),
+            ("mdp-dependent", []) => binary!(MemoryDependencePredictor::dependent),
+            _ => Err(self.error(operator.line(), format!("Unknown operator '{}'", name))),
+        }
+    }
+
+    /// `(block INDEX [:transient] [:loop ID] PHI-NODES... INSTRUCTIONS...)`
+    fn block(&self, form: &SExpr, args: &[SExpr]) -> Result<(usize, Block)> {
+        let (index, items) = args
+            .split_first()
+            .ok_or_else(|| self.error(form.line(), "Expected block index"))?;
+        let mut block = Block::new(self.number(index)? as usize);
+
+        let mut items = items.iter();
+        while let Some(item) = items.next() {
+            match item.atom() {
+                Some(":transient") => block.set_transient(),
+                Some(":loop") => {
+                    let id = items
+                        .next()
+                        .ok_or_else(|| self.error(item.line(), "Expected loop id"))?;
+                    block.set_loop_id(Some(self.number(id)? as usize));
+                }
+                Some(atom) => {
+                    return Err(self.error(item.line(), format!("Unknown attribute '{}'", atom)))
+                }
+                None => {
+                    let (head, args) = self.form(item)?;
+                    if head == "phi" {
+                        block.add_phi_node(self.phi_node(item, args)?);
+                    } else {
+                        let instruction = self.instruction(item, head, args)?;
+                        block.instructions_mut().push(instruction);
+                    }
+                }
+            }
+        }
+
+        Ok((form.line(), block))
+    }
+
+    /// `(phi OUT (BLOCK VARIABLE)...)`
+    fn phi_node(&self, form: &SExpr, args: &[SExpr]) -> Result<PhiNode> {
+        let (out, incoming) = args
+            .split_first()
+            .ok_or_else(|| self.error(form.line(), "Expected phi output variable"))?;
+        let mut phi_node = PhiNode::new(self.variable(out)?);
+        for entry in incoming {
+            match entry {
+                SExpr::List(items, _) if items.len() == 2 => {
+                    let block_index = self.number(&items[0])? as usize;
+                    phi_node.add_incoming(self.variable(&items[1])?, block_index);
+                }
+                _ => return Err(self.error(entry.line(), "Expected (BLOCK VARIABLE)")),
+            }
+        }
+        Ok(phi_node)
+    }
+
+    /// `(OPERATION OPERANDS... [:address ADDRESS] [:disassembly "TEXT"] [:pseudo] [:helper] [:assertion PROVENANCE] [:effect EFFECT]... [:origin ORIGIN]...)`
+    fn instruction(&self, form: &SExpr, name: &str, args: &[SExpr]) -> Result<Instruction> {
+        let operand_count = match name {
+            "skip" => 0,
+            "barrier" | "call" | "branch" | "cache-flush" | "prefetch" | "assert" | "assume"
+            | "observable" | "indistinguishable" => 1,
+            "assign" | "branch-if" => 2,
+            "load" => 3,
+            "store" => 4,
+            _ => return Err(self.error(form.line(), format!("Unknown operation '{}'", name))),
+        };
+        if args.len() < operand_count {
+            return Err(self.error(
+                form.line(),
+                format!("Expected {} operand(s) for {}", operand_count, name),
+            ));
+        }
+        let (operands, attributes) = args.split_at(operand_count);
+
+        let expr = |index: usize| self.expression(&operands[index]);
+        let var = |index: usize| self.variable(&operands[index]);
+        let operation = match name {
+            "assign" => Operation::assign(var(0)?, expr(1)?),
+            "store" => Ok(Operation::Store {
+                memory_out: var(0)?,
+                memory_in: var(1)?,
+                address: expr(2)?,
+                expr: expr(3)?,
+            }),
+            "load" => Ok(Operation::Load {
+                variable: var(0)?,
+                memory: var(1)?,
+                address: expr(2)?,
+            }),
+            "call" => Operation::call(expr(0)?),
+            "branch" => Operation::branch(expr(0)?),
+            "branch-if" => Operation::conditional_branch(expr(0)?, expr(1)?),
+            "skip" => Ok(Operation::skip()),
+            "barrier" => Ok(Operation::barrier(self.barrier_kind(&operands[0])?)),
+            "cache-flush" => Operation::cache_flush(expr(0)?),
+            "prefetch" => Operation::prefetch(expr(0)?),
+            "assert" => Operation::assert(expr(0)?),
+            "assume" => Operation::assume(expr(0)?),
+            "observable" => Ok(Operation::observable(expr(0)?)),
+            _ => Ok(Operation::indistinguishable(expr(0)?)),
+        };
+        let mut instruction = Instruction::new(self.at(form.line(), operation)?);
+
+        let mut attributes = attributes.iter();
+        while let Some(attribute) = attributes.next() {
+            let mut value = || {
+                attributes
+                    .next()
+                    .ok_or_else(|| self.error(attribute.line(), "Missing attribute value"))
+            };
+            match attribute.atom() {
+                Some(":address") => instruction.set_address(Some(self.number(value()?)?)),
+                Some(":disassembly") => match value()?.atom() {
+                    Some(text) if text.starts_with('"') => {
+                        instruction.set_disassembly(Some(text.trim_matches('"').to_owned()))
+                    }
+                    _ => return Err(self.error(attribute.line(), "Expected disassembly string")),
+                },
+                Some(":pseudo") => {
+                    instruction.labels_mut().pseudo();
+                }
+                Some(":helper") => {
+                    instruction.labels_mut().helper();
+                }
+                Some(":assertion") => {
+                    instruction.set_provenance(self.provenance(value()?)?);
+                }
+                Some(":effect") => instruction.add_effect(self.effect(value()?)?),
+                Some(":origin") => instruction.add_origin(self.origin(value()?)?),
+                _ => return Err(self.error(attribute.line(), "Unknown instruction attribute")),
+            }
+        }
+
+        Ok(instruction)
+    }
+
+    fn barrier_kind(&self, sexpr: &SExpr) -> Result<BarrierKind> {
+        match sexpr.atom() {
+            Some("full") => Ok(BarrierKind::Full),
+            Some("load") => Ok(BarrierKind::Load),
+            Some("store") => Ok(BarrierKind::Store),
+            Some("speculation") => Ok(BarrierKind::Speculation),
+            _ => Err(self.error(sexpr.line(), "Unknown barrier kind")),
+        }
+    }
+
+    /// `(KIND [:address ADDRESS] [:transform "NAME"])`
+    fn provenance(&self, sexpr: &SExpr) -> Result<Provenance> {
+        let (kind, args) = self.form(sexpr)?;
+        let kind = match kind {
+            "observation" => AssertionKind::Observation,
+            "unwinding" => AssertionKind::Unwinding,
+            "user" => AssertionKind::User,
+            "memory-safety" => AssertionKind::MemorySafety,
+            _ => return Err(self.error(sexpr.line(), format!("Unknown assertion kind '{}'", kind))),
+        };
+        let mut provenance = Provenance::new(kind);
+        for pair in args.chunks(2) {
+            match (pair[0].atom(), pair.get(1).and_then(SExpr::atom)) {
+                (Some(":address"), Some(_)) => {
+                    provenance = provenance.with_address(Some(self.number(&pair[1])?))
+                }
+                (Some(":transform"), Some(name)) => {
+                    provenance = provenance.with_transform(name.trim_matches('"'))
+                }
+                _ => return Err(self.error(pair[0].line(), "Unknown assertion attribute")),
+            }
+        }
+        Ok(provenance)
+    }
+
+    /// `(loop LOOP-ID ITERATION)`, `(transient ADDRESS)` or `(transform "NAME")`
+    fn origin(&self, sexpr: &SExpr) -> Result<Origin> {
+        let (name, args) = self.form(sexpr)?;
+        let origin = match name {
+            "loop" => {
+                let [loop_id, iteration] = self.arguments(sexpr, args)?;
+                Origin::LoopIteration {
+                    loop_id: self.number(loop_id)? as usize,
+                    iteration: self.number(iteration)? as usize,
+                }
+            }
+            "transient" => Origin::TransientExecution {
+                address: self.number(self.single(sexpr, args)?)?,
+            },
+            "transform" => match self.single(sexpr, args)?.atom() {
+                Some(name) => Origin::Transform(name.trim_matches('"').to_owned()),
+                None => return Err(self.error(sexpr.line(), "Expected transform name")),
+            },
+            _ => return Err(self.error(sexpr.line(), format!("Unknown origin '{}'", name))),
+        };
+        Ok(origin)
+    }
+
+    fn effect(&self, sexpr: &SExpr) -> Result<Effect> {
+        let (name, args) = self.form(sexpr)?;
+        let effect = match name {
+            "only-if" => {
+                let [condition, effect] = self.arguments(sexpr, args)?;
+                self.effect(effect)?.only_if(self.expression(condition)?)
+            }
+            "cache-fetch" | "cache-evict" => {
+                let [address, bit_width] = self.arguments(sexpr, args)?;
+                let address = self.expression(address)?;
+                let bit_width = self.number(bit_width)? as usize;
+                if name == "cache-fetch" {
+                    Effect::cache_fetch(address, bit_width)
+                } else {
+                    Effect::cache_evict(address, bit_width)
+                }
+            }
+            "branch-target" => {
+                let [location, target] = self.arguments(sexpr, args)?;
+                Effect::branch_target(self.expression(location)?, self.expression(target)?)
+            }
+            "branch-condition" => {
+                let [location, condition] = self.arguments(sexpr, args)?;
+                Effect::branch_condition(self.expression(location)?, self.expression(condition)?)
+            }
+            "store-location" => {
+                let [location, address] = self.arguments(sexpr, args)?;
+                Effect::store_location(self.expression(location)?, self.expression(address)?)
+            }
+            "load-dependence" => {
+                Effect::load_dependence(self.expression(self.single(sexpr, args)?)?)
+            }
+            _ => return Err(self.error(sexpr.line(), format!("Unknown effect '{}'", name))),
+        };
+        Ok(effect)
+    }
+
+    /// `(edge HEAD TAIL [:condition EXPRESSION] [:taken] [:speculate] [:rollback] [:call] [:return])`
+    fn edge(&self, form: &SExpr, args: &[SExpr]) -> Result<Edge> {
+        if args.len() < 2 {
+            return Err(self.error(form.line(), "Expected (edge HEAD TAIL ...)"));
+        }
+        let mut edge = Edge::new(
+            self.number(&args[0])? as usize,
+            self.number(&args[1])? as usize,
+            None,
+        );
+
+        let mut attributes = args[2..].iter();
+        while let Some(attribute) = attributes.next() {
+            let labels = edge.labels_mut();
+            match attribute.atom() {
+                Some(":taken") => {
+                    labels.taken();
+                }
+                Some(":speculate") => {
+                    labels.speculate();
+                }
+                Some(":rollback") => {
+                    labels.rollback();
+                }
+                Some(":call") => {
+                    labels.call();
+                }
+                Some(":return") => {
+                    labels.r#return();
+                }
+                Some(":condition") => {
+                    let condition = attributes
+                        .next()
+                        .ok_or_else(|| self.error(attribute.line(), "Missing edge condition"))?;
+                    edge.set_condition(Some(self.expression(condition)?));
+                }
+                _ => return Err(self.error(attribute.line(), "Unknown edge attribute")),
+            }
+        }
+
+        Ok(edge)
+    }
+}
+
+/// Splits an atom into the (unquoted) name and the remaining suffix, e.g. `|a b|#1` into `a b` and `#1`.
+fn unquote(atom: &str) -> (&str, &str) {
+    if let Some(quoted) = atom.strip_prefix('|') {
+        if let Some(end) = quoted.find('|') {
+            return (&quoted[..end], &quoted[end + 1..]);
+        }
+    }
+    match atom.find(|c: char| c == '#' || c == '@') {
+        Some(start) => (&atom[..start], &atom[start..]),
+        None => (atom, ""),
+    }
+}
+
+fn arity<const N: usize>(operator: &str, operands: Vec<Expression>) -> Result<[Expression; N]> {
+    operands.try_into().map_err(|_| Error::InvalidOperands {
+        operator: operator.to_owned(),
+    })
+}
@@ -0,0 +1,355 @@
+use crate::error::Result;
+use crate::expr::{Constant, Expression, List, Operator, Sort, Variable};
+use crate::hir::{Block, Edge, Effect, InlinedProgram, Instruction, Operation, Origin, PhiNode};
+use crate::ir::{AssertionKind, Provenance};
+use std::collections::BTreeMap;
+
+/// Prints the program in the textual HIR format.
+pub fn print_program(program: &InlinedProgram) -> Result<String> {
+    let cfg = program.control_flow_graph();
+    let mut printer = Printer::default();
+
+    let mut body = String::new();
+    for block in cfg.blocks() {
+        body.push('\n');
+        body.push_str(&printer.block(block)?);
+        body.push('\n');
+    }
+    body.push('\n');
+    for edge in cfg.edges() {
+        body.push_str(&printer.edge(edge)?);
+        body.push('\n');
+    }
+
+    let mut text = String::from("; SpecBMC HIR\n");
+    text.push_str(&format!(
+        "(self-compositions {})\n",
+        program.self_compositions()
+    ));
+    if let Ok(entry) = cfg.entry() {
+        text.push_str(&format!("(entry 0x{:X})\n", entry));
+    }
+    if let Ok(exit) = cfg.exit() {
+        text.push_str(&format!("(exit 0x{:X})\n", exit));
+    }
+    text.push('\n');
+    for (name, (sort, rollback_persistent)) in &printer.declarations {
+        text.push_str(&format!("(var {} {}", symbol(name), print_sort(sort)));
+        if *rollback_persistent {
+            text.push_str(" :rollback-persistent");
+        }
+        text.push_str(")\n");
+    }
+    text.push_str(&body);
+
+    Ok(text)
+}
+
+fn print_sort(sort: &Sort) -> String {
+    match sort {
+        Sort::BitVector(width) => format!("(BitVec {})", width),
+        Sort::Array { range, domain } => {
+            format!("(Array {} {})", print_sort(range), print_sort(domain))
+        }
+        Sort::List { domain } => format!("(List {})", print_sort(domain)),
+        Sort::Tuple { fields } => {
+            let mut s = String::from("(Tuple");
+            for field in fields {
+                s.push(' ');
+                s.push_str(&print_sort(field));
+            }
+            s.push(')');
+            s
+        }
+        _ => sort.to_string(),
+    }
+}
+
+fn print_assertion_kind(kind: AssertionKind) -> &'static str {
+    match kind {
+        AssertionKind::Observation => "observation",
+        AssertionKind::Unwinding => "unwinding",
+        AssertionKind::User => "user",
+        AssertionKind::MemorySafety => "memory-safety",
+    }
+}
+
+/// Names which can't be written as plain symbols are enclosed in `|`.
+fn symbol(name: &str) -> String {
+    let is_plain = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == ':')
+        && !name.contains(|c: char| c.is_whitespace() || "()|;\"#@".contains(c))
+        && name != "true"
+        && name != "false";
+    if is_plain {
+        name.to_owned()
+    } else {
+        format!("|{}|", name)
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    /// Sort and rollback persistence of all printed variables (by name).
+    declarations: BTreeMap<String, (Sort, bool)>,
+}
+
+impl Printer {
+    fn variable(&mut self, variable: &Variable) -> Result<String> {
+        let declaration = (variable.sort().clone(), variable.is_rollback_persistent());
+        match self.declarations.get(variable.name()) {
+            Some(declared) if *declared != declaration => {
+                return Err(format!(
+                    "Variable '{}' is used with different sorts (or rollback persistence)",
+                    variable.name()
+                )
+                .into());
+            }
+            Some(_) => {}
+            None => {
+                self.declarations
+                    .insert(variable.name().to_owned(), declaration);
+            }
+        }
+
+        let mut s = symbol(variable.name());
+        if let Some(version) = variable.version() {
+            s.push_str(&format!("#{}", version));
+        }
+        if let Some(composition) = variable.composition() {
+            s.push_str(&format!("@{}", composition));
+        }
+        Ok(s)
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Result<String> {
+        match expr.operator() {
+            Operator::Variable(variable) => self.variable(variable),
+            Operator::Constant(constant) => match constant {
+                Constant::Boolean(value) => Ok(value.to_string()),
+                Constant::Integer(value) => Ok(value.to_string()),
+                Constant::BitVector(value) => {
+                    Ok(format!("(_ bv{} {})", value.value(), value.bits()))
+                }
+                _ => {
+                    Err(format!("Constants of sort {} can't be printed as HIR", expr.sort()).into())
+                }
+            },
+            Operator::Nondet => Ok(format!("(nondet {})", print_sort(expr.sort()))),
+            Operator::List(List::Nil) => Ok(format!("(nil {})", print_sort(expr.sort()))),
+            operator => {
+                let mut s = match operator {
+                    Operator::Cast(sort) => format!("((cast {})", print_sort(sort)),
+                    _ => format!("({}", operator),
+                };
+                for operand in expr.operands() {
+                    s.push(' ');
+                    s.push_str(&self.expression(operand)?);
+                }
+                s.push(')');
+                Ok(s)
+            }
+        }
+    }
+
+    fn operation(&mut self, operation: &Operation) -> Result<String> {
+        Ok(match operation {
+            Operation::Assign { variable, expr } => format!(
+                "(assign {} {})",
+                self.variable(variable)?,
+                self.expression(expr)?
+            ),
+            Operation::Store {
+                address,
+                expr,
+                memory_in,
+                memory_out,
+            } => format!(
+                "(store {} {} {} {})",
+                self.variable(memory_out)?,
+                self.variable(memory_in)?,
+                self.expression(address)?,
+                self.expression(expr)?
+            ),
+            Operation::Load {
+                variable,
+                address,
+                memory,
+            } => format!(
+                "(load {} {} {})",
+                self.variable(variable)?,
+                self.variable(memory)?,
+                self.expression(address)?
+            ),
+            Operation::Call { target } => format!("(call {})", self.expression(target)?),
+            Operation::Branch { target } => format!("(branch {})", self.expression(target)?),
+            Operation::ConditionalBranch { condition, target } => format!(
+                "(branch-if {} {})",
+                self.expression(condition)?,
+                self.expression(target)?
+            ),
+            Operation::Skip => "(skip)".to_owned(),
+            Operation::Barrier { kind } => format!("(barrier {})", kind),
+            Operation::CacheFlush { address } => {
+                format!("(cache-flush {})", self.expression(address)?)
+            }
+            Operation::Prefetch { address } => {
+                format!("(prefetch {})", self.expression(address)?)
+            }
+            Operation::Assert { condition } => {
+                format!("(assert {})", self.expression(condition)?)
+            }
+            Operation::Assume { condition } => {
+                format!("(assume {})", self.expression(condition)?)
+            }
+            Operation::Observable { expr } => format!("(observable {})", self.expression(expr)?),
+            Operation::Indistinguishable { expr } => {
+                format!("(indistinguishable {})", self.expression(expr)?)
+            }
+        })
+    }
+
+    fn effect(&mut self, effect: &Effect) -> Result<String> {
+        Ok(match effect {
+            Effect::Conditional { condition, effect } => format!(
+                "(only-if {} {})",
+                self.expression(condition)?,
+                self.effect(effect)?
+            ),
+            Effect::CacheFetch { address, bit_width } => {
+                format!("(cache-fetch {} {})", self.expression(address)?, bit_width)
+            }
+            Effect::CacheEvict { address, bit_width } => {
+                format!("(cache-evict {} {})", self.expression(address)?, bit_width)
+            }
+            Effect::BranchTarget { location, target } => format!(
+                "(branch-target {} {})",
+                self.expression(location)?,
+                self.expression(target)?
+            ),
+            Effect::BranchCondition {
+                location,
+                condition,
+            } => format!(
+                "(branch-condition {} {})",
+                self.expression(location)?,
+                self.expression(condition)?
+            ),
+            Effect::StoreLocation { location, address } => format!(
+                "(store-location {} {})",
+                self.expression(location)?,
+                self.expression(address)?
+            ),
+            Effect::LoadDependence { address } => {
+                format!("(load-dependence {})", self.expression(address)?)
+            }
+        })
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) -> Result<String> {
+        let mut s = self.operation(instruction.operation())?;
+        s.pop(); // closing parenthesis, attributes follow the operands
+
+        if let Some(address) = instruction.address() {
+            s.push_str(&format!(" :address 0x{:X}", address));
+        }
+        if let Some(disassembly) = instruction.disassembly() {
+            s.push_str(&format!(" :disassembly \"{}\"", disassembly));
+        }
+        if instruction.labels().is_pseudo() {
+            s.push_str(" :pseudo");
+        }
+        if instruction.labels().is_helper() {
+            s.push_str(" :helper");
+        }
+        if let Some(provenance) = instruction.provenance() {
+            s.push_str(&format!(" :assertion {}", provenance_text(provenance)));
+        }
+        for effect in instruction.effects() {
+            s.push_str(&format!(" :effect {}", self.effect(effect)?));
+        }
+        for origin in instruction.origins() {
+            s.push_str(&format!(" :origin {}", origin_text(origin)));
+        }
+
+        s.push(')');
+        Ok(s)
+    }
+
+    fn phi_node(&mut self, phi_node: &PhiNode) -> Result<String> {
+        let mut s = format!("(phi {}", self.variable(phi_node.out())?);
+        for (block_index, variable) in phi_node.incoming() {
+            s.push_str(&format!(
+                " (0x{:X} {})",
+                block_index,
+                self.variable(variable)?
+            ));
+        }
+        s.push(')');
+        Ok(s)
+    }
+
+    fn block(&mut self, block: &Block) -> Result<String> {
+        let mut s = format!("(block 0x{:X}", block.index());
+        if block.is_transient() {
+            s.push_str(" :transient");
+        }
+        if let Some(loop_id) = block.loop_id() {
+            s.push_str(&format!(" :loop 0x{:X}", loop_id));
+        }
+        for phi_node in block.phi_nodes() {
+            s.push_str("\n  ");
+            s.push_str(&self.phi_node(phi_node)?);
+        }
+        for instruction in block.instructions() {
+            s.push_str("\n  ");
+            s.push_str(&self.instruction(instruction)?);
+        }
+        s.push(')');
+        Ok(s)
+    }
+
+    fn edge(&mut self, edge: &Edge) -> Result<String> {
+        let mut s = format!("(edge 0x{:X} 0x{:X}", edge.head(), edge.tail());
+        if let Some(condition) = edge.condition() {
+            s.push_str(&format!(" :condition {}", self.expression(condition)?));
+        }
+        let labels = edge.labels();
+        for (is_set, label) in [
+            (labels.is_taken(), ":taken"),
+            (labels.is_speculate(), ":speculate"),
+            (labels.is_rollback(), ":rollback"),
+            (labels.is_call(), ":call"),
+            (labels.is_return(), ":return"),
+        ] {
+            if is_set {
+                s.push(' ');
+                s.push_str(label);
+            }
+        }
+        s.push(')');
+        Ok(s)
+    }
+}
+
+fn origin_text(origin: &Origin) -> String {
+    match origin {
+        Origin::LoopIteration { loop_id, iteration } => {
+            format!("(loop 0x{:X} {})", loop_id, iteration)
+        }
+        Origin::TransientExecution { address } => format!("(transient 0x{:X})", address),
+        Origin::Transform(name) => format!("(transform \"{}\")", name),
+    }
+}
+
+fn provenance_text(provenance: &Provenance) -> String {
+    let mut s = format!("({}", print_assertion_kind(provenance.kind()));
+    if let Some(address) = provenance.address() {
+        s.push_str(&format!(" :address 0x{:X}", address));
+    }
+    if let Some(transform) = provenance.transform() {
+        s.push_str(&format!(" :transform \"{}\"", transform));
+    }
+    s.push(')');
+    s
+}
@@ -0,0 +1,106 @@
+use crate::error::Result;
+use crate::hir::{BarrierKind, ControlFlowGraph, Instruction};
+use crate::ir::Transform;
+use std::collections::BTreeSet;
+
+/// Inserts speculation(-only) barriers in front of the instructions with the given addresses.
+///
+/// Instructions may occur multiple times in the CFG (e.g. because of inlining),
+/// barriers are inserted in front of each of them.
+#[derive(Default, Builder, Debug)]
+pub struct BarrierInsertion {
+    addresses: BTreeSet<u64>,
+}
+
+impl BarrierInsertion {
+    /// Returns the addresses at which barriers can mitigate speculation,
+    /// which are the targets of all conditional branches.
+    pub fn candidates(cfg: &ControlFlowGraph) -> Result<BTreeSet<u64>> {
+        let mut candidates = BTreeSet::new();
+
+        for edge in cfg.edges() {
+            if !edge.is_conditional() {
+                continue;
+            }
+            if let Some(address) = cfg.block(edge.tail())?.address() {
+                candidates.insert(address);
+            }
+        }
+
+        Ok(candidates)
+    }
+}
+
+impl Transform<ControlFlowGraph> for BarrierInsertion {
+    fn name(&self) -> &'static str {
+        "BarrierInsertion"
+    }
+
+    fn description(&self) -> String {
+        format!("Insert {} speculation barrier(s)", self.addresses.len())
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        for block in cfg.blocks_mut() {
+            let mut insertion_points = Vec::new();
+            let mut previous_address = None;
+            for (index, inst) in block.instructions().iter().enumerate() {
+                let address = inst.address();
+                if address != previous_address {
+                    if let Some(address) = address {
+                        if self.addresses.contains(&address) {
+                            insertion_points.push((index, address));
+                        }
+                    }
+                }
+                previous_address = address;
+            }
+
+            for (index, address) in insertion_points.into_iter().rev() {
+                let mut barrier = Instruction::barrier(BarrierKind::Speculation);
+                barrier.set_address(Some(address));
+                block.insert_instruction(index, barrier)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Boolean};
+
+    #[test]
+    fn test_insert_barrier_in_front_of_instruction() {
+        // Given
+        let mut cfg = ControlFlowGraph::new();
+        let block = cfg.new_block();
+        block
+            .assign(BitVector::variable("x", 64), BitVector::word_constant(1))
+            .unwrap()
+            .set_address(Some(0x10));
+        block
+            .assign(BitVector::variable("y", 64), BitVector::word_constant(2))
+            .unwrap()
+            .set_address(Some(0x14));
+        block
+            .assume(Boolean::constant(true))
+            .unwrap()
+            .set_address(Some(0x14));
+
+        // When
+        let barrier_insertion = BarrierInsertionBuilder::default()
+            .addresses(vec![0x14].into_iter().collect())
+            .build()
+            .unwrap();
+        barrier_insertion.transform(&mut cfg).unwrap();
+
+        // Then
+        let instructions = cfg.blocks()[0].instructions();
+        assert_eq!(instructions.len(), 4);
+        assert!(instructions[1].is_barrier());
+        assert_eq!(instructions[1].address(), Some(0x14));
+    }
+}
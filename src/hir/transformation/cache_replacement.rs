@@ -0,0 +1,487 @@
+use crate::environment::CacheReplacementPolicy;
+use crate::error::Result;
+use crate::expr::{BitVector, Boolean, Cache, Expression, Operator, Sort, Variable};
+use crate::hir::{Block, Instruction, Operation};
+use crate::ir::Transform;
+
+/// Models a set-associative cache with bounded associativity and LRU or tree-PLRU replacement.
+///
+/// Each explicit cache fetch (see `ExplicitEffects`) additionally updates the replacement state
+/// of the accessed set and evicts the victim line from the cache if the set is full.
+/// Explicit cache evictions (e.g. by a cache flush) invalidate the way holding the line.
+/// Therefore, secret-dependent evictions (e.g. Prime+Probe) become observable via the cache.
+///
+/// Cache fetches are expected to be line-aligned, meaning that each line is represented by a single byte.
+#[derive(Builder, Debug)]
+pub struct CacheReplacement {
+    policy: CacheReplacementPolicy,
+    line_size: u64,
+    sets: usize,
+    ways: usize,
+}
+
+impl Default for CacheReplacement {
+    fn default() -> Self {
+        Self {
+            policy: CacheReplacementPolicy::Lru,
+            line_size: 64,
+            sets: 1,
+            ways: 8,
+        }
+    }
+}
+
+impl CacheReplacement {
+    /// Returns the variables holding the replacement state.
+    ///
+    /// The replacement state is persistent across rollbacks, like the cache itself.
+    pub fn state_variables(&self) -> Vec<Variable> {
+        let mut variables = Vec::new();
+        for set in 0..self.sets {
+            for way in 0..self.ways {
+                variables.push(tag_variable(set, way));
+                variables.push(valid_variable(set, way));
+            }
+            if self.policy == CacheReplacementPolicy::TreePlru {
+                for node in 1..self.ways {
+                    variables.push(plru_variable(set, node));
+                }
+            }
+        }
+        variables
+    }
+
+    /// Returns the replacement operations (incl. the original access) for the given cache fetch/evict,
+    /// or `None` if the operation isn't a cache access.
+    fn replace_access(&self, operation: &Operation) -> Result<Option<Vec<Operation>>> {
+        let (access, condition, line) = match cache_access(operation) {
+            Some(access) => access,
+            None => return Ok(None),
+        };
+
+        let line_var = access_variable("line", Sort::word());
+        let mut operations = vec![Operation::assign(line_var.clone(), line)?];
+
+        let set_index = BitVector::urem(
+            BitVector::udiv(
+                line_var.clone().into(),
+                BitVector::word_constant(self.line_size),
+            )?,
+            BitVector::word_constant(self.sets as u64),
+        )?;
+
+        for set in 0..self.sets {
+            let mut access =
+                Expression::equal(set_index.clone(), BitVector::word_constant(set as u64))?;
+            if let Some(condition) = &condition {
+                access = Boolean::and(condition.clone(), access)?;
+            }
+            let access_var = access_variable("set", Sort::boolean());
+            operations.push(Operation::assign(access_var.clone(), access)?);
+
+            match (access, self.policy) {
+                (_, CacheReplacementPolicy::None) => {}
+                (CacheAccess::Evict, _) => {
+                    operations.extend(self.invalidate(set, &access_var, &line_var)?)
+                }
+                (CacheAccess::Fetch, CacheReplacementPolicy::Lru) => {
+                    operations.extend(self.lru_update(set, &access_var, &line_var)?)
+                }
+                (CacheAccess::Fetch, CacheReplacementPolicy::TreePlru) => {
+                    operations.extend(self.plru_update(set, &access_var, &line_var)?)
+                }
+            }
+        }
+
+        operations.push(operation.clone());
+
+        Ok(Some(operations))
+    }
+
+    /// Ways are ordered from most (way 0) to least recently used.
+    /// The accessed line moves to way 0, all lines which were more recently used shift by one way.
+    fn lru_update(&self, set: usize, access: &Variable, line: &Variable) -> Result<Vec<Operation>> {
+        let mut operations = self.hit_operations(set, line)?;
+
+        let hits: Vec<Expression> = (0..self.ways).map(|way| hit_variable(way).into()).collect();
+        let miss = Boolean::not(Boolean::disjunction(&hits)?)?;
+
+        let lru_way = self.ways - 1;
+        operations.push(self.evict(
+            Boolean::conjunction(&[
+                access.clone().into(),
+                miss,
+                valid_variable(set, lru_way).into(),
+            ])?,
+            tag_variable(set, lru_way).into(),
+        )?);
+
+        // Update from the least recently used way downwards, as each way takes over its predecessor
+        for way in (1..self.ways).rev() {
+            let hit_before = Boolean::disjunction(&hits[..way])?;
+            let shift = Boolean::and(access.clone().into(), Boolean::not(hit_before)?)?;
+            operations.push(conditional_assign(
+                tag_variable(set, way),
+                shift.clone(),
+                tag_variable(set, way - 1).into(),
+            )?);
+            operations.push(conditional_assign(
+                valid_variable(set, way),
+                shift,
+                valid_variable(set, way - 1).into(),
+            )?);
+        }
+
+        operations.push(conditional_assign(
+            tag_variable(set, 0),
+            access.clone().into(),
+            line.clone().into(),
+        )?);
+        operations.push(conditional_assign(
+            valid_variable(set, 0),
+            access.clone().into(),
+            Boolean::constant(true),
+        )?);
+
+        Ok(operations)
+    }
+
+    /// The PLRU tree is stored as heap (node `n` has the children `2n` and `2n+1`, the root is node 1),
+    /// the leaves `ways..2*ways` correspond to the ways.
+    /// A node bit of `false` points to the victim in the left subtree, `true` to the right subtree.
+    fn plru_update(
+        &self,
+        set: usize,
+        access: &Variable,
+        line: &Variable,
+    ) -> Result<Vec<Operation>> {
+        let mut operations = self.hit_operations(set, line)?;
+
+        let hits: Vec<Expression> = (0..self.ways).map(|way| hit_variable(way).into()).collect();
+        let miss = Boolean::not(Boolean::disjunction(&hits)?)?;
+
+        // The accessed way is either the hit way or the victim way
+        let mut victim_tag: Expression = tag_variable(set, self.ways - 1).into();
+        let mut victim_valid: Expression = valid_variable(set, self.ways - 1).into();
+        for way in 0..self.ways {
+            let victim = self.plru_victim(set, way)?;
+            if way < self.ways - 1 {
+                victim_tag =
+                    Expression::ite(victim.clone(), tag_variable(set, way).into(), victim_tag)?;
+                victim_valid = Expression::ite(
+                    victim.clone(),
+                    valid_variable(set, way).into(),
+                    victim_valid,
+                )?;
+            }
+            let selected = Boolean::or(hits[way].clone(), Boolean::and(miss.clone(), victim)?)?;
+            operations.push(Operation::assign(selected_variable(way), selected)?);
+        }
+
+        operations.push(self.evict(
+            Boolean::conjunction(&[access.clone().into(), miss, victim_valid])?,
+            victim_tag,
+        )?);
+
+        for way in 0..self.ways {
+            let update = Boolean::and(access.clone().into(), selected_variable(way).into())?;
+            operations.push(conditional_assign(
+                tag_variable(set, way),
+                update.clone(),
+                line.clone().into(),
+            )?);
+            operations.push(conditional_assign(
+                valid_variable(set, way),
+                update,
+                Boolean::constant(true),
+            )?);
+        }
+
+        // Let the nodes on the path of the accessed way point away from it
+        for node in 1..self.ways {
+            let selected_in = |child: usize| -> Result<Expression> {
+                let ways: Vec<Expression> = self
+                    .leaf_ways(child)
+                    .map(|way| selected_variable(way).into())
+                    .collect();
+                Boolean::and(access.clone().into(), Boolean::disjunction(&ways)?)
+            };
+            let bit = plru_variable(set, node);
+            operations.push(Operation::assign(
+                bit.clone(),
+                Expression::ite(
+                    selected_in(2 * node)?,
+                    Boolean::constant(true),
+                    Expression::ite(
+                        selected_in(2 * node + 1)?,
+                        Boolean::constant(false),
+                        bit.into(),
+                    )?,
+                )?,
+            )?);
+        }
+
+        Ok(operations)
+    }
+
+    /// Returns the condition under which the tree points to `way` as victim.
+    fn plru_victim(&self, set: usize, way: usize) -> Result<Expression> {
+        let mut conditions = Vec::new();
+        let mut node = self.ways + way;
+        while node > 1 {
+            let parent = node / 2;
+            let bit: Expression = plru_variable(set, parent).into();
+            if node % 2 == 0 {
+                conditions.push(Boolean::not(bit)?);
+            } else {
+                conditions.push(bit);
+            }
+            node = parent;
+        }
+        Boolean::conjunction(&conditions)
+    }
+
+    /// Returns the ways covered by the subtree of the given tree node.
+    fn leaf_ways(&self, node: usize) -> impl Iterator<Item = usize> {
+        let mut first = node;
+        let mut last = node;
+        while first < self.ways {
+            first *= 2;
+            last = 2 * last + 1;
+        }
+        (first - self.ways)..(last - self.ways + 1)
+    }
+
+    /// Invalidates the way holding the line (if any), the replacement order stays unchanged.
+    fn invalidate(&self, set: usize, access: &Variable, line: &Variable) -> Result<Vec<Operation>> {
+        (0..self.ways)
+            .map(|way| {
+                let evicted = Boolean::and(
+                    access.clone().into(),
+                    Expression::equal(tag_variable(set, way).into(), line.clone().into())?,
+                )?;
+                conditional_assign(valid_variable(set, way), evicted, Boolean::constant(false))
+            })
+            .collect()
+    }
+
+    fn hit_operations(&self, set: usize, line: &Variable) -> Result<Vec<Operation>> {
+        (0..self.ways)
+            .map(|way| {
+                let hit = Boolean::and(
+                    valid_variable(set, way).into(),
+                    Expression::equal(tag_variable(set, way).into(), line.clone().into())?,
+                )?;
+                Operation::assign(hit_variable(way), hit)
+            })
+            .collect()
+    }
+
+    fn evict(&self, condition: Expression, line: Expression) -> Result<Operation> {
+        let cache = Cache::variable();
+        let evict = Cache::evict(8, cache.clone().into(), line)?;
+        conditional_assign(cache, condition, evict)
+    }
+}
+
+impl Transform<Block> for CacheReplacement {
+    fn name(&self) -> &'static str {
+        "CacheReplacement"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Model cache replacement ({} sets, {} ways, {:?})",
+            self.sets, self.ways, self.policy
+        )
+    }
+
+    fn transform(&self, block: &mut Block) -> Result<()> {
+        if self.policy == CacheReplacementPolicy::None {
+            return Ok(());
+        }
+
+        let mut instructions = Vec::with_capacity(block.instructions().len());
+
+        for inst in block.instructions() {
+            match self.replace_access(inst.operation())? {
+                Some(operations) => {
+                    for operation in operations {
+                        let mut replacement = Instruction::new(operation);
+                        replacement.set_address(inst.address());
+                        replacement.labels_mut().pseudo();
+                        instructions.push(replacement);
+                    }
+                }
+                None => instructions.push(inst.clone()),
+            }
+        }
+
+        block.set_instructions(&instructions);
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CacheAccess {
+    Fetch,
+    Evict,
+}
+
+/// Matches the explicit (and possibly conditional) cache access `_cache := [ite(c,] fetch/evict(_cache, line)[, _cache)]`
+/// and returns its kind, condition and line.
+fn cache_access(operation: &Operation) -> Option<(CacheAccess, Option<Expression>, Expression)> {
+    let accessed_line = |expr: &Expression| match (expr.operator(), expr.operands()) {
+        (Operator::Cache(Cache::Fetch(_)), [_, line]) => Some((CacheAccess::Fetch, line.clone())),
+        (Operator::Cache(Cache::Evict(_)), [_, line]) => Some((CacheAccess::Evict, line.clone())),
+        _ => None,
+    };
+
+    match operation {
+        Operation::Assign { variable, expr } if *variable == Cache::variable() => {
+            match (expr.operator(), expr.operands()) {
+                (Operator::Ite, [condition, then, _]) => accessed_line(then)
+                    .map(|(access, line)| (access, Some(condition.clone()), line)),
+                _ => accessed_line(expr).map(|(access, line)| (access, None, line)),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn conditional_assign(
+    variable: Variable,
+    condition: Expression,
+    expr: Expression,
+) -> Result<Operation> {
+    let expr = Expression::ite(condition, expr, variable.clone().into())?;
+    Operation::assign(variable, expr)
+}
+
+fn state_variable(name: String, sort: Sort) -> Variable {
+    let mut var = Variable::new(&name, sort);
+    var.set_rollback_persistent(true);
+    var
+}
+
+fn tag_variable(set: usize, way: usize) -> Variable {
+    state_variable(format!("_cache_set{}_way{}", set, way), Sort::word())
+}
+
+fn valid_variable(set: usize, way: usize) -> Variable {
+    state_variable(
+        format!("_cache_set{}_way{}_valid", set, way),
+        Sort::boolean(),
+    )
+}
+
+fn plru_variable(set: usize, node: usize) -> Variable {
+    state_variable(format!("_cache_set{}_plru{}", set, node), Sort::boolean())
+}
+
+fn access_variable(name: &str, sort: Sort) -> Variable {
+    Variable::new(&format!("_cache_access_{}", name), sort)
+}
+
+fn hit_variable(way: usize) -> Variable {
+    access_variable(&format!("hit{}", way), Sort::boolean())
+}
+
+fn selected_variable(way: usize) -> Variable {
+    access_variable(&format!("selected{}", way), Sort::boolean())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch_block(line: u64) -> Block {
+        let mut block = Block::new(0);
+        let cache = Cache::variable();
+        block
+            .assign(
+                cache.clone(),
+                Cache::fetch(8, cache.into(), BitVector::word_constant(line)).unwrap(),
+            )
+            .unwrap();
+        block
+    }
+
+    fn assigned_variables(block: &Block) -> Vec<String> {
+        block
+            .instructions()
+            .iter()
+            .filter_map(|inst| match inst.operation() {
+                Operation::Assign { variable, .. } => Some(variable.name().to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lru_updates_accessed_set_and_keeps_fetch() {
+        // Given:
+        let mut block = fetch_block(0x40);
+
+        // When:
+        let replacement = CacheReplacementBuilder::default()
+            .policy(CacheReplacementPolicy::Lru)
+            .line_size(64)
+            .sets(1)
+            .ways(2)
+            .build()
+            .unwrap();
+        replacement.transform(&mut block).unwrap();
+
+        // Then:
+        let assigned = assigned_variables(&block);
+        assert_eq!(
+            assigned,
+            vec![
+                "_cache_access_line",
+                "_cache_access_set",
+                "_cache_access_hit0",
+                "_cache_access_hit1",
+                "_cache",
+                "_cache_set0_way1",
+                "_cache_set0_way1_valid",
+                "_cache_set0_way0",
+                "_cache_set0_way0_valid",
+                "_cache",
+            ]
+        );
+        assert!(block
+            .instructions()
+            .iter()
+            .all(|inst| inst.labels().is_pseudo()));
+    }
+
+    #[test]
+    fn test_plru_leaf_ways_and_victim_path() {
+        let replacement = CacheReplacementBuilder::default()
+            .policy(CacheReplacementPolicy::TreePlru)
+            .line_size(64)
+            .sets(1)
+            .ways(4)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            replacement.leaf_ways(1).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(replacement.leaf_ways(2).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(replacement.leaf_ways(3).collect::<Vec<_>>(), vec![2, 3]);
+
+        // Way 2 is the victim if the root points right and node 3 points left
+        assert_eq!(
+            replacement.plru_victim(0, 2).unwrap(),
+            Boolean::conjunction(&[
+                Boolean::not(plru_variable(0, 3).into()).unwrap(),
+                plru_variable(0, 1).into(),
+            ])
+            .unwrap()
+        );
+    }
+}
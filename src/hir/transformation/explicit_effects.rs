@@ -1,5 +1,7 @@
 use crate::error::Result;
-use crate::expr::{BranchTargetBuffer, Cache, Expression, PatternHistoryTable};
+use crate::expr::{
+    Array, BranchTargetBuffer, Cache, Expression, MemoryDependencePredictor, PatternHistoryTable,
+};
 use crate::hir::{Block, Effect, Instruction, Operation};
 use crate::ir::Transform;
 
@@ -54,11 +56,16 @@ fn encode_effect(effect: &Effect) -> Result<Operation> {
             }
         }
         Effect::CacheFetch { address, bit_width } => encode_cache_fetch_effect(address, *bit_width),
+        Effect::CacheEvict { address, bit_width } => encode_cache_evict_effect(address, *bit_width),
         Effect::BranchTarget { location, target } => encode_branch_target_effect(location, target),
         Effect::BranchCondition {
             location,
             condition,
         } => encode_branch_condition_effect(location, condition),
+        Effect::StoreLocation { location, address } => {
+            encode_store_location_effect(location, address)
+        }
+        Effect::LoadDependence { address } => encode_load_dependence_effect(address),
     }
 }
 
@@ -68,6 +75,12 @@ fn encode_cache_fetch_effect(address: &Expression, bit_width: usize) -> Result<O
     Operation::assign(cache, fetch)
 }
 
+fn encode_cache_evict_effect(address: &Expression, bit_width: usize) -> Result<Operation> {
+    let cache = Cache::variable();
+    let evict = Cache::evict(bit_width, cache.clone().into(), address.clone())?;
+    Operation::assign(cache, evict)
+}
+
 fn encode_branch_target_effect(location: &Expression, target: &Expression) -> Result<Operation> {
     let btb = BranchTargetBuffer::variable();
     let track = BranchTargetBuffer::track(btb.clone().into(), location.clone(), target.clone())?;
@@ -83,3 +96,17 @@ fn encode_branch_condition_effect(
     let not_taken = PatternHistoryTable::not_taken(pht.clone().into(), location.clone())?;
     Operation::assign(pht, Expression::ite(condition.clone(), taken, not_taken)?)
 }
+
+fn encode_store_location_effect(location: &Expression, address: &Expression) -> Result<Operation> {
+    let stores = MemoryDependencePredictor::store_locations_variable();
+    let track = Array::store(stores.clone().into(), address.clone(), location.clone())?;
+    Operation::assign(stores, track)
+}
+
+fn encode_load_dependence_effect(address: &Expression) -> Result<Operation> {
+    let mdp = MemoryDependencePredictor::variable();
+    let stores = MemoryDependencePredictor::store_locations_variable();
+    let store_location = Array::select(stores.into(), address.clone())?;
+    let dependent = MemoryDependencePredictor::dependent(mdp.clone().into(), store_location)?;
+    Operation::assign(mdp, dependent)
+}
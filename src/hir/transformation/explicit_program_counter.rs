@@ -1,5 +1,6 @@
 use crate::error::Result;
 use crate::expr::{BitVector, Expression, Variable};
+use crate::hir::transformation::instruction_effects::align_address;
 use crate::hir::{Block, Instruction, Operation};
 use crate::ir::Transform;
 
@@ -7,6 +8,14 @@ use crate::ir::Transform;
 pub struct ExplicitProgramCounter {
     observe_program_counter: bool,
     observe_memory_loads: bool,
+    observe_memory_stores: bool,
+    /// Memory addresses are observed at this alignment (e.g. cache line size), if given.
+    memory_address_alignment: Option<u64>,
+    /// Computed branch targets are observed relative to the (randomized) image base,
+    /// such that differences which only reveal the load address aren't observable.
+    /// Computed targets are run-time addresses (derived from the code pointers set up by `InitImageBase`),
+    /// constant branch targets are link-time addresses and therefore observed as they are.
+    relative_to_image_base: bool,
 }
 
 impl ExplicitProgramCounter {
@@ -21,6 +30,22 @@ impl ExplicitProgramCounter {
         var.set_rollback_persistent(true);
         var
     }
+
+    /// The load address of the program image (ASLR slide), chosen freely for each execution by `InitImageBase`.
+    pub fn image_base_variable() -> Variable {
+        let mut var = BitVector::word_variable("_image_base");
+        var.set_rollback_persistent(true);
+        var
+    }
+
+    /// The observed value of the given branch target.
+    fn observed_target(&self, target: &Expression) -> Result<Expression> {
+        if self.relative_to_image_base && !target.is_constant() {
+            BitVector::sub(target.clone(), Self::image_base_variable().into())
+        } else {
+            Ok(target.clone())
+        }
+    }
 }
 
 impl Transform<Block> for ExplicitProgramCounter {
@@ -38,10 +63,18 @@ impl Transform<Block> for ExplicitProgramCounter {
         for (index, inst) in block.instructions().iter().enumerate() {
             match inst.operation() {
                 Operation::Load { address, .. } | Operation::Store { address, .. } => {
-                    if self.observe_memory_loads {
+                    let observed = match inst.operation() {
+                        Operation::Load { .. } => self.observe_memory_loads,
+                        _ => self.observe_memory_stores,
+                    };
+                    if observed {
+                        let address = match self.memory_address_alignment {
+                            Some(alignment) => align_address(address.clone(), alignment)?,
+                            None => address.clone(),
+                        };
                         observations.push((
                             index,
-                            Instruction::assign(Self::address_variable(), address.clone())?,
+                            Instruction::assign(Self::address_variable(), address)?,
                         ));
                     }
                 }
@@ -49,7 +82,10 @@ impl Transform<Block> for ExplicitProgramCounter {
                     if self.observe_program_counter {
                         observations.push((
                             index,
-                            Instruction::assign(Self::pc_variable(), target.clone())?,
+                            Instruction::assign(
+                                Self::pc_variable(),
+                                self.observed_target(target)?,
+                            )?,
                         ));
                     }
                 }
@@ -58,7 +94,11 @@ impl Transform<Block> for ExplicitProgramCounter {
                 } => {
                     if self.observe_program_counter {
                         let next = BitVector::word_constant(inst.address().unwrap_or_default() + 8); // FIXME
-                        let pc = Expression::ite(condition.clone(), target.clone(), next)?;
+                        let pc = Expression::ite(
+                            condition.clone(),
+                            self.observed_target(target)?,
+                            next,
+                        )?;
                         observations.push((index, Instruction::assign(Self::pc_variable(), pc)?));
                     }
                 }
@@ -74,3 +114,45 @@ impl Transform<Block> for ExplicitProgramCounter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_computed_branch_target_is_observed_relative_to_image_base() {
+        // Given: a computed and a constant branch target
+        let rdi = BitVector::word_variable("rdi");
+        let mut block = Block::new(0);
+        block.branch(rdi.clone().into()).unwrap();
+        block.branch(BitVector::word_constant(0x1000)).unwrap();
+
+        let transform = ExplicitProgramCounterBuilder::default()
+            .observe_program_counter(true)
+            .relative_to_image_base(true)
+            .build()
+            .unwrap();
+
+        // When:
+        Transform::<Block>::transform(&transform, &mut block).unwrap();
+
+        // Then: only the computed target is observed relative to the image base
+        let pc = ExplicitProgramCounter::pc_variable();
+        assert_eq!(
+            block.instruction(0).unwrap().operation(),
+            &Operation::assign(
+                pc.clone(),
+                BitVector::sub(
+                    rdi.into(),
+                    ExplicitProgramCounter::image_base_variable().into()
+                )
+                .unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            block.instruction(2).unwrap().operation(),
+            &Operation::assign(pc, BitVector::word_constant(0x1000)).unwrap()
+        );
+    }
+}
@@ -1,11 +1,53 @@
-use crate::error::Result;
-use crate::hir::{Block, ControlFlowGraph, InlinedProgram, Operation, Program};
-use std::collections::{BTreeMap, HashSet};
+use crate::environment::{
+    AddressRange, CallingConvention, ExternalCallPolicy, HeapModel, InliningBudgetPolicy, WORD_SIZE,
+};
+use crate::error::{Error, Result};
+use crate::expr::{BitVector, Boolean, Expression, Sort, Variable};
+use crate::hir::analysis::indirect_call_targets;
+use crate::hir::{
+    Block, ControlFlowGraph, Function, InlinedProgram, Instruction, Operation, Program,
+};
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 #[derive(Default, Builder, Debug)]
 pub struct FunctionInlining {
     recursion_limit: usize,
     ignored_functions: HashSet<String>,
+    /// Resolve the targets of indirect calls and inline all of them.
+    #[builder(default)]
+    resolve_indirect_calls: bool,
+    /// How calls to unknown or indirect targets are handled.
+    #[builder(default = "ExternalCallPolicy::Ignore")]
+    external_call_policy: ExternalCallPolicy,
+    /// Registers which are havoced by external calls.
+    #[builder(default)]
+    clobbered_registers: Vec<String>,
+    /// Memory which is havoced by external calls (only for `ExternalCallPolicy::HavocMemory`).
+    #[builder(default)]
+    clobbered_memory: Vec<AddressRange>,
+    /// Havoced registers and memory get public values (opt-in), otherwise they get secret values.
+    #[builder(default)]
+    havoc_low: bool,
+    /// Max. depth of nested inlined calls.
+    #[builder(default)]
+    max_call_depth: Option<usize>,
+    /// Max. number of instructions of the inlined program.
+    #[builder(default)]
+    max_instructions: Option<usize>,
+    /// How calls which would exceed the call depth or instruction budget are handled.
+    #[builder(default)]
+    budget_policy: InliningBudgetPolicy,
+    /// Calls of these functions aren't inlined but replaced by a havoc summary (modular checking),
+    /// in which only the given registers get public values.
+    #[builder(default)]
+    call_summaries: HashMap<String, HashSet<String>>,
+    /// Calls of the allocation/deallocation functions are replaced by the allocator model.
+    #[builder(default)]
+    heap: Option<HeapModel>,
+    /// Locates the size argument and the return value of allocations.
+    #[builder(default)]
+    calling_convention: CallingConvention,
 }
 
 type CallDepth = BTreeMap<u64, usize>;
@@ -18,6 +60,9 @@ impl FunctionInlining {
 
         let mut cfg = entry_func.control_flow_graph().clone();
         self.inline_calls(&mut cfg, program)?;
+        if let Some(heap) = &self.heap {
+            init_heap(&mut cfg, heap)?;
+        }
         cfg.simplify()?;
 
         Ok(InlinedProgram::new(cfg))
@@ -25,6 +70,7 @@ impl FunctionInlining {
 
     fn inline_calls(&self, cfg: &mut ControlFlowGraph, program: &Program) -> Result<()> {
         let mut remaining_block_indices: Vec<(usize, CallDepth)> = Vec::new();
+        let mut instruction_count = instruction_count(cfg);
 
         cfg.blocks()
             .into_iter()
@@ -36,70 +82,414 @@ impl FunctionInlining {
         while let Some((block_index, call_depth_in_caller)) = remaining_block_indices.pop() {
             let block = cfg.block(block_index)?;
 
-            if let Some((call_inst_index, address)) = find_next_call_in_block(block) {
-                if let Some(func) = program.function_by_address(address) {
-                    let func_call_depth = call_depth_in_caller
-                        .get(&address)
-                        .cloned()
-                        .unwrap_or_default();
-                    if func_call_depth > self.recursion_limit {
+            let (call_inst_index, callee) = match self.find_next_call_in_block(block, program) {
+                Some(call) => call,
+                None => continue,
+            };
+
+            let (address, func) = match callee {
+                Some(callee) => callee,
+                None => {
+                    if let Some(dispatch_block_indices) =
+                        self.dispatch_indirect_call(cfg, block_index, call_inst_index, program)?
+                    {
+                        // Indirect call: visit the direct calls of the dispatch (and the return block)
+                        for dispatch_block_index in dispatch_block_indices {
+                            remaining_block_indices
+                                .push((dispatch_block_index, call_depth_in_caller.clone()));
+                        }
                         continue;
                     }
-                    if let Some(name) = func.name() {
-                        if self.ignored_functions.contains(name) {
-                            continue;
-                        }
+
+                    // External call: replace it with its havoc summary and revisit the block
+                    let block = cfg.block_mut(block_index)?;
+                    self.havoc_external_call(block, call_inst_index)?;
+                    remaining_block_indices.push((block_index, call_depth_in_caller));
+                    continue;
+                }
+            };
+
+            let func_call_depth = call_depth_in_caller
+                .get(&address)
+                .cloned()
+                .unwrap_or_default();
+            if func_call_depth > self.recursion_limit {
+                continue;
+            }
+            if let Some(name) = func.name() {
+                if self.ignored_functions.contains(name) {
+                    continue;
+                }
+                if let Some(heap) = &self.heap {
+                    let is_allocation = heap.allocators.contains(name);
+                    if is_allocation || heap.deallocators.contains(name) {
+                        // Replace the call with the allocator model and revisit the block
+                        let block = cfg.block_mut(block_index)?;
+                        self.model_heap_call(block, call_inst_index, heap, is_allocation)?;
+                        remaining_block_indices.push((block_index, call_depth_in_caller));
+                        continue;
                     }
+                }
+                if let Some(low_registers) = self.call_summaries.get(name) {
+                    // Replace the call with the summary given by the contract and revisit the block
+                    let block = cfg.block_mut(block_index)?;
+                    self.havoc_call(block, call_inst_index, true, Some(low_registers))?;
+                    remaining_block_indices.push((block_index, call_depth_in_caller));
+                    continue;
+                }
+            }
 
-                    let ret_block_index = cfg.split_block_at(block_index, call_inst_index + 1)?;
-
-                    let func_block_index_mapping = cfg.insert(func.control_flow_graph())?;
-                    let func_entry_block_index = func_block_index_mapping
-                        .get(&func.control_flow_graph().entry()?)
-                        .unwrap();
-                    let func_exit_block_index = func_block_index_mapping
-                        .get(&func.control_flow_graph().exit()?)
-                        .unwrap();
-
-                    cfg.unconditional_edge(block_index, *func_entry_block_index)?
-                        .labels_mut()
-                        .call();
-
-                    cfg.unconditional_edge(*func_exit_block_index, ret_block_index)?
-                        .labels_mut()
-                        .r#return();
-
-                    // Increase call depth for callee
-                    let mut call_depth_in_callee = call_depth_in_caller.clone();
-                    call_depth_in_callee
-                        .entry(address)
-                        .and_modify(|depth| *depth += 1)
-                        .or_insert(1);
-
-                    for &callee_block_index in func_block_index_mapping.values() {
-                        remaining_block_indices
-                            .push((callee_block_index, call_depth_in_callee.clone()));
+            let call_depth: usize = call_depth_in_caller.values().sum();
+            let func_instruction_count = instruction_count(func.control_flow_graph());
+            let exceeded_budget = if self
+                .max_call_depth
+                .map_or(false, |max_depth| call_depth >= max_depth)
+            {
+                Some(format!("call depth of {}", call_depth + 1))
+            } else if self.max_instructions.map_or(false, |max_instructions| {
+                instruction_count + func_instruction_count > max_instructions
+            }) {
+                Some(format!(
+                    "{} instructions, max. {}",
+                    instruction_count + func_instruction_count,
+                    self.max_instructions.unwrap()
+                ))
+            } else {
+                None
+            };
+            if let Some(exceeded_budget) = exceeded_budget {
+                match self.budget_policy {
+                    InliningBudgetPolicy::Error => {
+                        return Err(format!(
+                            "Inlining function {} at 0x{:X} exceeds the inlining budget ({}), \
+                             consider ignoring functions (`analysis.inline_ignore`) \
+                             or `analysis.inlining_budget_exceeded: havoc`",
+                            func.name().unwrap_or("<unknown>"),
+                            address,
+                            exceeded_budget
+                        )
+                        .into());
+                    }
+                    InliningBudgetPolicy::Havoc => {
+                        // Replace the call with its havoc summary and revisit the block
+                        let block = cfg.block_mut(block_index)?;
+                        self.havoc_call(block, call_inst_index, true, None)?;
+                        remaining_block_indices.push((block_index, call_depth_in_caller));
+                        continue;
                     }
+                }
+            }
+            instruction_count += func_instruction_count;
+
+            let ret_block_index = cfg.split_block_at(block_index, call_inst_index + 1)?;
+
+            let func_block_index_mapping = cfg.insert(func.control_flow_graph())?;
+            let func_entry_block_index = func_block_index_mapping
+                .get(&func.control_flow_graph().entry()?)
+                .unwrap();
+            let func_exit_block_index = func_block_index_mapping
+                .get(&func.control_flow_graph().exit()?)
+                .unwrap();
 
-                    // Continue at the return block with the caller call depth
-                    remaining_block_indices.push((ret_block_index, call_depth_in_caller));
+            cfg.unconditional_edge(block_index, *func_entry_block_index)?
+                .labels_mut()
+                .call();
+
+            cfg.unconditional_edge(*func_exit_block_index, ret_block_index)?
+                .labels_mut()
+                .r#return();
+
+            // Increase call depth for callee
+            let mut call_depth_in_callee = call_depth_in_caller.clone();
+            call_depth_in_callee
+                .entry(address)
+                .and_modify(|depth| *depth += 1)
+                .or_insert(1);
+
+            for &callee_block_index in func_block_index_mapping.values() {
+                remaining_block_indices.push((callee_block_index, call_depth_in_callee.clone()));
+            }
+
+            // Continue at the return block with the caller call depth
+            remaining_block_indices.push((ret_block_index, call_depth_in_caller));
+        }
+
+        Ok(())
+    }
+
+    /// Finds the next call in the given block.
+    ///
+    /// Returns the index of the call instruction together with the callee,
+    /// or `None` as callee if the call target is unknown (external call).
+    /// External calls are skipped if they should be ignored.
+    fn find_next_call_in_block<'p>(
+        &self,
+        block: &Block,
+        program: &'p Program,
+    ) -> Option<(usize, Option<(u64, &'p Function)>)> {
+        for (index, inst) in block.instructions().iter().enumerate() {
+            if let Operation::Call { target } = inst.operation() {
+                let callee = target.try_into().ok().and_then(|address| {
+                    program
+                        .function_by_address(address)
+                        .map(|func| (address, func))
+                });
+
+                if callee.is_some()
+                    || self.external_call_policy != ExternalCallPolicy::Ignore
+                    || !self.indirect_targets(block, index, program).is_empty()
+                {
+                    return Some((index, callee));
                 }
             }
         }
+        None
+    }
+
+    /// Returns the possible targets of the indirect call at the given index,
+    /// or an empty set if the targets are unknown or shouldn't be resolved.
+    ///
+    /// Unresolved indirect calls are assumed to call one of the functions whose address is taken.
+    fn indirect_targets(&self, block: &Block, index: usize, program: &Program) -> BTreeSet<u64> {
+        if !self.resolve_indirect_calls {
+            return BTreeSet::new();
+        }
+        match block.instruction(index).map(Instruction::operation) {
+            Some(Operation::Call { target }) if !target.is_constant() => {
+                indirect_call_targets(program.code_pointers(), block, index)
+                    .unwrap_or_else(|| program.address_taken_functions())
+            }
+            _ => BTreeSet::new(),
+        }
+    }
+
+    /// Replaces the indirect call at the given index by direct calls to all of its possible targets,
+    /// i.e. `call t` becomes `if t == f1 then call f1 else if t == f2 then call f2 ...`.
+    ///
+    /// Returns the indices of the new blocks (including the return block),
+    /// or `None` if the call is no indirect call or if it has no known targets.
+    fn dispatch_indirect_call(
+        &self,
+        cfg: &mut ControlFlowGraph,
+        block_index: usize,
+        call_inst_index: usize,
+        program: &Program,
+    ) -> Result<Option<Vec<usize>>> {
+        let block = cfg.block(block_index)?;
+        let targets = self.indirect_targets(block, call_inst_index, program);
+        if targets.is_empty() {
+            return Ok(None);
+        }
+        let target = match block
+            .instruction(call_inst_index)
+            .map(Instruction::operation)
+        {
+            Some(Operation::Call { target }) => target.clone(),
+            _ => return Ok(None),
+        };
+
+        let ret_block_index = cfg.split_block_at(block_index, call_inst_index + 1)?;
+        let call_inst = cfg
+            .block_mut(block_index)?
+            .remove_instruction(call_inst_index)?;
+
+        let mut dispatch_block_indices = vec![ret_block_index];
+        for &address in &targets {
+            let call_block = cfg.new_block();
+            call_block
+                .call(BitVector::word_constant(address))?
+                .set_address(call_inst.address());
+            let call_block_index = call_block.index();
+
+            if targets.len() == 1 {
+                cfg.unconditional_edge(block_index, call_block_index)?;
+            } else {
+                let condition =
+                    Expression::equal(target.clone(), BitVector::word_constant(address))?;
+                cfg.conditional_edge(block_index, call_block_index, condition)?;
+            }
+            cfg.unconditional_edge(call_block_index, ret_block_index)?;
+
+            dispatch_block_indices.push(call_block_index);
+        }
+
+        Ok(Some(dispatch_block_indices))
+    }
+
+    /// Replaces the external call at the given index by the havoc summary
+    /// according to the `ExternalCallPolicy`.
+    ///
+    /// The outputs of the external function are only assumed to be public (low-equivalent) with `havoc_low`.
+    fn havoc_external_call(&self, block: &mut Block, call_inst_index: usize) -> Result<()> {
+        match self.external_call_policy {
+            ExternalCallPolicy::Ignore => {
+                unreachable!("ignored external calls are never visited")
+            }
+            ExternalCallPolicy::Error => {
+                let call_inst = block.instruction(call_inst_index).unwrap();
+                Err(Error::UnknownFunction {
+                    instruction: call_inst.to_string(),
+                    address: call_inst.address(),
+                })
+            }
+            ExternalCallPolicy::HavocRegisters => {
+                self.havoc_call(block, call_inst_index, false, None)
+            }
+            ExternalCallPolicy::HavocMemory => self.havoc_call(block, call_inst_index, true, None),
+        }
+    }
+
+    /// Replaces the call at the given index by a havoc of the clobbered registers
+    /// (and the clobbered memory if `clobber_memory` is set).
+    ///
+    /// Havoced registers get public values with `havoc_low` (secret values otherwise), unless `low_registers` is given,
+    /// in which case only the registers contained in `low_registers` are public.
+    /// Havoced memory gets public values with `havoc_low` only.
+    fn havoc_call(
+        &self,
+        block: &mut Block,
+        call_inst_index: usize,
+        clobber_memory: bool,
+        low_registers: Option<&HashSet<String>>,
+    ) -> Result<()> {
+        let call_inst = block.remove_instruction(call_inst_index)?;
+
+        let mut havoc = Vec::new();
+        havoc_registers(&mut havoc, &self.clobbered_registers, |register| {
+            low_registers.map_or(self.havoc_low, |low| low.contains(register))
+        })?;
+        if clobber_memory {
+            havoc_memory(&mut havoc, &self.clobbered_memory, self.havoc_low)?;
+        }
+
+        for (offset, mut inst) in havoc.into_iter().enumerate() {
+            inst.set_address(call_inst.address());
+            block.insert_instruction(call_inst_index + offset, inst)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the call of an allocation function by the allocation of a new chunk from the heap region,
+    /// and the call of a deallocation function by nothing (chunks are never reused).
+    ///
+    /// The chunk base is symbolic, but above all previously allocated chunks (bump allocator),
+    /// aligned and within the heap region. The attacker knows the heap layout, i.e. the chunk base is public.
+    fn model_heap_call(
+        &self,
+        block: &mut Block,
+        call_inst_index: usize,
+        heap: &HeapModel,
+        is_allocation: bool,
+    ) -> Result<()> {
+        let call_inst = block.remove_instruction(call_inst_index)?;
+        if !is_allocation {
+            return Ok(());
+        }
+
+        if !heap.alignment.is_power_of_two() {
+            return Err(format!(
+                "Heap alignment must be a power of two, but is {}",
+                heap.alignment
+            )
+            .into());
+        }
+
+        let top = heap_top_variable();
+        let chunk = BitVector::word_variable("_heap_chunk");
+        let size: Expression =
+            BitVector::word_variable(self.calling_convention.argument_registers()[0]).into();
+        let end = BitVector::word_constant(heap.region.end());
+
+        let allocation = vec![
+            Instruction::assign(chunk.clone(), Expression::nondet(Sort::word()))?,
+            Instruction::assume(Boolean::conjunction(&[
+                BitVector::ule(top.clone().into(), chunk.clone().into())?,
+                Expression::equal(
+                    BitVector::and(
+                        chunk.clone().into(),
+                        BitVector::word_constant(heap.alignment - 1),
+                    )?,
+                    BitVector::word_constant(0),
+                )?,
+                BitVector::ule(chunk.clone().into(), end.clone())?,
+                BitVector::ule(size.clone(), BitVector::sub(end, chunk.clone().into())?)?,
+            ])?)?,
+            Instruction::indistinguishable(chunk.clone().into()),
+            Instruction::assign(top, BitVector::add(chunk.clone().into(), size)?)?,
+            Instruction::assign(
+                BitVector::word_variable(self.calling_convention.return_register()),
+                chunk.into(),
+            )?,
+        ];
+
+        for (offset, mut inst) in allocation.into_iter().enumerate() {
+            inst.set_address(call_inst.address());
+            block.insert_instruction(call_inst_index + offset, inst)?;
+        }
 
         Ok(())
     }
 }
 
-fn find_next_call_in_block(block: &Block) -> Option<(usize, u64)> {
-    for (index, inst) in block.instructions().iter().enumerate() {
-        if let Operation::Call { target } = inst.operation() {
-            if let Ok(address) = target.try_into() {
-                return Some((index, address));
+/// Top of the heap, all chunks allocated so far are below it.
+fn heap_top_variable() -> Variable {
+    BitVector::word_variable("_heap_top")
+}
+
+/// Initially, the whole heap region is free.
+fn init_heap(cfg: &mut ControlFlowGraph, heap: &HeapModel) -> Result<()> {
+    let mut init = Instruction::assign(
+        heap_top_variable(),
+        BitVector::word_constant(heap.region.start()),
+    )?;
+    init.labels_mut().pseudo();
+    cfg.entry_block_mut()?.insert_instruction(0, init)
+}
+
+fn instruction_count(cfg: &ControlFlowGraph) -> usize {
+    cfg.blocks().into_iter().map(Block::instruction_count).sum()
+}
+
+fn havoc_registers<F>(havoc: &mut Vec<Instruction>, registers: &[String], is_low: F) -> Result<()>
+where
+    F: Fn(&str) -> bool,
+{
+    for register in registers {
+        let var = BitVector::word_variable(register);
+        havoc.push(Instruction::assign(
+            var.clone(),
+            Expression::nondet(var.sort().clone()),
+        )?);
+        if is_low(register) {
+            havoc.push(Instruction::indistinguishable(var.into()));
+        }
+    }
+    Ok(())
+}
+
+/// Havocs each memory range word by word, each word gets its own unknown value
+/// (the last word of a range may be shorter).
+fn havoc_memory(havoc: &mut Vec<Instruction>, memory: &[AddressRange], is_low: bool) -> Result<()> {
+    let word_bytes = (WORD_SIZE / 8) as u64;
+    for range in memory {
+        for address in (range.start()..range.end()).step_by(word_bytes as usize) {
+            let bits = cmp::min(word_bytes, range.end() - address) as usize * 8;
+            let content = BitVector::variable(&format!("_havoc_memory_{}", bits), bits);
+            havoc.push(Instruction::assign(
+                content.clone(),
+                Expression::nondet(Sort::bit_vector(bits)),
+            )?);
+            if is_low {
+                havoc.push(Instruction::indistinguishable(content.clone().into()));
             }
+            havoc.push(Instruction::store(
+                BitVector::word_constant(address),
+                content.into(),
+            )?);
         }
     }
-    None
+    Ok(())
 }
 
 #[cfg(test)]
@@ -992,4 +1382,533 @@ mod tests {
 
         assert_eq!(expected_program, inlined_program);
     }
+
+    #[test]
+    fn test_external_call_with_havoc_registers() {
+        // Given: Function a calls an unknown function
+        let program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block
+                .assign(Boolean::variable("a"), Boolean::constant(false))
+                .unwrap();
+            block.call(BitVector::constant_u64(10, 64)).unwrap();
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            let mut program = Program::new();
+            program
+                .insert_function(Function::new(0, Some("a".to_owned()), cfg))
+                .unwrap();
+            program
+                .set_entry(ProgramEntry::Name("a".to_owned()))
+                .unwrap();
+
+            program
+        };
+
+        // When: Inline
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .external_call_policy(ExternalCallPolicy::HavocRegisters)
+            .clobbered_registers(vec!["rax".to_owned()])
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: The call is replaced by a havoc of rax with a secret value
+        let expected_program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block
+                .assign(Boolean::variable("a"), Boolean::constant(false))
+                .unwrap();
+            block
+                .assign(
+                    BitVector::word_variable("rax"),
+                    Expression::nondet(Sort::word()),
+                )
+                .unwrap();
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            InlinedProgram::new(cfg)
+        };
+
+        assert_eq!(expected_program, inlined_program);
+    }
+
+    #[test]
+    fn test_external_call_with_havoc_memory_policy() {
+        // Given: Function a calls an unknown function which may modify memory [0x100, 0x110)
+        let program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block.call(BitVector::constant_u64(10, 64)).unwrap();
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            let mut program = Program::new();
+            program
+                .insert_function(Function::new(0, Some("a".to_owned()), cfg))
+                .unwrap();
+            program
+                .set_entry(ProgramEntry::Name("a".to_owned()))
+                .unwrap();
+
+            program
+        };
+
+        // When: Inline
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .external_call_policy(ExternalCallPolicy::HavocMemory)
+            .clobbered_memory(vec![AddressRange::new(0x100, 0x110)])
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: The range is havoced word by word with secret values
+        let expected_program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let content = BitVector::variable("_havoc_memory_64", 64);
+            let mut block = Block::new(0);
+            for address in [0x100, 0x108] {
+                block
+                    .assign(content.clone(), Expression::nondet(Sort::word()))
+                    .unwrap();
+                block
+                    .store(BitVector::word_constant(address), content.clone().into())
+                    .unwrap();
+            }
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            InlinedProgram::new(cfg)
+        };
+
+        assert_eq!(expected_program, inlined_program);
+    }
+
+    #[test]
+    fn test_external_call_with_low_havoc_memory() {
+        // Given: Function a calls an unknown function which may modify memory [0x100, 0x10C)
+        let program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block.call(BitVector::constant_u64(10, 64)).unwrap();
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            let mut program = Program::new();
+            program
+                .insert_function(Function::new(0, Some("a".to_owned()), cfg))
+                .unwrap();
+            program
+                .set_entry(ProgramEntry::Name("a".to_owned()))
+                .unwrap();
+
+            program
+        };
+
+        // When: Inline with public havoc values
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .external_call_policy(ExternalCallPolicy::HavocMemory)
+            .clobbered_memory(vec![AddressRange::new(0x100, 0x10C)])
+            .havoc_low(true)
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: A word and the remaining half word are havoced with low-equivalent values
+        let expected_program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            for (address, bits) in [(0x100, 64), (0x108, 32)] {
+                let content = BitVector::variable(&format!("_havoc_memory_{}", bits), bits);
+                block
+                    .assign(content.clone(), Expression::nondet(Sort::bit_vector(bits)))
+                    .unwrap();
+                block.indistinguishable(content.clone().into());
+                block
+                    .store(BitVector::word_constant(address), content.into())
+                    .unwrap();
+            }
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            InlinedProgram::new(cfg)
+        };
+
+        assert_eq!(expected_program, inlined_program);
+    }
+
+    #[test]
+    fn test_external_call_with_error_policy() {
+        // Given: Function a calls an unknown function
+        let program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block.call(BitVector::constant_u64(10, 64)).unwrap();
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            let mut program = Program::new();
+            program
+                .insert_function(Function::new(0, Some("a".to_owned()), cfg))
+                .unwrap();
+            program
+                .set_entry(ProgramEntry::Name("a".to_owned()))
+                .unwrap();
+
+            program
+        };
+
+        // When: Inline
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .external_call_policy(ExternalCallPolicy::Error)
+            .build()
+            .unwrap();
+
+        // Then: Inlining fails
+        assert!(inliner.inline(&program).is_err());
+    }
+
+    #[test]
+    fn test_inline_all_targets_of_indirect_call() {
+        // Given: Functions a, b and c; a calls b or c through a function pointer table at 0x100
+        let single_block_cfg = |block: Block| {
+            let mut cfg = ControlFlowGraph::new();
+            cfg.add_block(block).unwrap();
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+            cfg
+        };
+
+        let program = {
+            let mut block_a = Block::new(0);
+            block_a
+                .load(
+                    BitVector::word_variable("rax"),
+                    BitVector::add(
+                        BitVector::word_constant(0x100),
+                        BitVector::word_variable("rdi").into(),
+                    )
+                    .unwrap(),
+                )
+                .unwrap();
+            block_a
+                .call(BitVector::word_variable("rax").into())
+                .unwrap();
+
+            let mut block_b = Block::new(0);
+            block_b
+                .assign(Boolean::variable("b"), Boolean::constant(true))
+                .unwrap();
+
+            let mut block_c = Block::new(0);
+            block_c
+                .assign(Boolean::variable("c"), Boolean::constant(true))
+                .unwrap();
+
+            let mut program = Program::new();
+            program
+                .insert_function(Function::new(
+                    0,
+                    Some("a".to_owned()),
+                    single_block_cfg(block_a),
+                ))
+                .unwrap();
+            program
+                .insert_function(Function::new(
+                    10,
+                    Some("b".to_owned()),
+                    single_block_cfg(block_b),
+                ))
+                .unwrap();
+            program
+                .insert_function(Function::new(
+                    20,
+                    Some("c".to_owned()),
+                    single_block_cfg(block_c),
+                ))
+                .unwrap();
+            program.insert_code_pointer(0x100, 10);
+            program.insert_code_pointer(0x108, 20);
+
+            program
+                .set_entry(ProgramEntry::Name("a".to_owned()))
+                .unwrap();
+
+            program
+        };
+
+        // When: Inline
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .resolve_indirect_calls(true)
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: Both targets are inlined
+        let cfg = inlined_program.control_flow_graph();
+        let assigned: HashSet<String> = cfg
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions())
+            .flat_map(|inst| inst.variables_written())
+            .map(|var| var.name().to_owned())
+            .collect();
+        assert!(assigned.contains("b"));
+        assert!(assigned.contains("c"));
+
+        let dispatch_conditions = cfg
+            .edges()
+            .iter()
+            .filter(|edge| edge.is_conditional())
+            .count();
+        assert_eq!(dispatch_conditions, 2);
+    }
+
+    /// Function a calls function b (at address 10)
+    fn program_a_calls_b() -> Program {
+        let mut program = Program::new();
+
+        let mut cfg = ControlFlowGraph::new();
+        let mut block = Block::new(0);
+        block.call(BitVector::constant_u64(10, 64)).unwrap();
+        cfg.add_block(block).unwrap();
+        cfg.set_entry(0).unwrap();
+        cfg.set_exit(0).unwrap();
+        program
+            .insert_function(Function::new(0, Some("a".to_owned()), cfg))
+            .unwrap();
+
+        let mut cfg = ControlFlowGraph::new();
+        let mut block = Block::new(0);
+        block
+            .assign(Boolean::variable("b"), Boolean::constant(true))
+            .unwrap();
+        cfg.add_block(block).unwrap();
+        cfg.set_entry(0).unwrap();
+        cfg.set_exit(0).unwrap();
+        program
+            .insert_function(Function::new(10, Some("b".to_owned()), cfg))
+            .unwrap();
+
+        program
+            .set_entry(ProgramEntry::Name("a".to_owned()))
+            .unwrap();
+
+        program
+    }
+
+    #[test]
+    fn test_exceeding_call_depth_with_error_policy() {
+        // Given
+        let program = program_a_calls_b();
+
+        // When: Inline without allowing any call
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .max_call_depth(Some(0))
+            .budget_policy(InliningBudgetPolicy::Error)
+            .build()
+            .unwrap();
+
+        // Then: Inlining fails
+        assert!(inliner.inline(&program).is_err());
+    }
+
+    #[test]
+    fn test_exceeding_instruction_budget_with_havoc_policy() {
+        // Given
+        let program = program_a_calls_b();
+
+        // When: Inline with a budget which is too small for b
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .clobbered_registers(vec!["rax".to_owned()])
+            .max_instructions(Some(1))
+            .budget_policy(InliningBudgetPolicy::Havoc)
+            .havoc_low(true)
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: The call is replaced by a low-equivalent havoc of rax (opted in)
+        let expected_program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block
+                .assign(
+                    BitVector::word_variable("rax"),
+                    Expression::nondet(Sort::word()),
+                )
+                .unwrap();
+            block.indistinguishable(BitVector::word_variable("rax").into());
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            InlinedProgram::new(cfg)
+        };
+
+        assert_eq!(expected_program, inlined_program);
+    }
+
+    #[test]
+    fn test_call_summary_keeps_only_ensured_registers_public() {
+        // Given
+        let program = program_a_calls_b();
+
+        // When: b has a contract which ensures that only rax is public
+        let mut call_summaries = HashMap::new();
+        call_summaries.insert("b".to_owned(), vec!["rax".to_owned()].into_iter().collect());
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .clobbered_registers(vec!["rax".to_owned(), "rcx".to_owned()])
+            .call_summaries(call_summaries)
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: b isn't inlined, rax and rcx are havoced but only rax is low-equivalent
+        let expected_program = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let mut block = Block::new(0);
+            block
+                .assign(
+                    BitVector::word_variable("rax"),
+                    Expression::nondet(Sort::word()),
+                )
+                .unwrap();
+            block.indistinguishable(BitVector::word_variable("rax").into());
+            block
+                .assign(
+                    BitVector::word_variable("rcx"),
+                    Expression::nondet(Sort::word()),
+                )
+                .unwrap();
+            cfg.add_block(block).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(0).unwrap();
+
+            InlinedProgram::new(cfg)
+        };
+
+        assert_eq!(expected_program, inlined_program);
+    }
+
+    #[test]
+    fn test_allocation_is_replaced_by_heap_model() {
+        // Given
+        let program = program_a_calls_b();
+
+        // When: b is an allocation function
+        let heap = HeapModel {
+            region: AddressRange::new(0x1000, 0x2000),
+            allocators: vec!["b".to_owned()].into_iter().collect(),
+            ..HeapModel::default()
+        };
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .heap(Some(heap))
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: b isn't inlined, rax gets a public chunk base above the heap top
+        let cfg = inlined_program.control_flow_graph();
+        let instructions = cfg.entry_block().unwrap().instructions();
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(
+            instructions[0].operation(),
+            &Operation::Assign {
+                variable: heap_top_variable(),
+                expr: BitVector::word_constant(0x1000)
+            }
+        );
+        assert_eq!(
+            instructions[3].operation(),
+            &Operation::Indistinguishable {
+                expr: BitVector::word_variable("_heap_chunk").into()
+            }
+        );
+        assert_eq!(
+            instructions[5].operation(),
+            &Operation::Assign {
+                variable: BitVector::word_variable("rax"),
+                expr: BitVector::word_variable("_heap_chunk").into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_deallocation_is_removed_by_heap_model() {
+        // Given
+        let program = program_a_calls_b();
+
+        // When: b is a deallocation function
+        let heap = HeapModel {
+            deallocators: vec!["b".to_owned()].into_iter().collect(),
+            ..HeapModel::default()
+        };
+        let inliner = FunctionInliningBuilder::default()
+            .recursion_limit(0)
+            .ignored_functions(HashSet::default())
+            .heap(Some(heap))
+            .build()
+            .unwrap();
+
+        let inlined_program = inliner.inline(&program).unwrap();
+
+        // Then: Only the heap initialization is left
+        let cfg = inlined_program.control_flow_graph();
+        assert_eq!(cfg.entry_block().unwrap().instructions().len(), 1);
+    }
 }
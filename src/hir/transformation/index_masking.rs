@@ -0,0 +1,170 @@
+use crate::error::Result;
+use crate::expr::{BitVector, Boolean, Expression, Variable};
+use crate::hir::{ControlFlowGraph, Instruction, Operation};
+use crate::ir::Transform;
+use std::collections::BTreeSet;
+
+/// Masks the addresses of loads with the given addresses by the condition of the guarding branch,
+/// i.e. `load(address)` becomes `load(ite(condition, address, 0))`.
+///
+/// Only loads in blocks which are entered by a single conditional edge are masked.
+/// The masking is data-dependent, therefore mispredicting the branch doesn't affect it
+/// and the masked address is clipped to zero during transient execution.
+#[derive(Default, Builder, Debug)]
+pub struct IndexMasking {
+    addresses: BTreeSet<u64>,
+}
+
+impl IndexMasking {
+    /// Returns the addresses of all loads which can be masked.
+    pub fn candidates(cfg: &ControlFlowGraph) -> Result<BTreeSet<u64>> {
+        let mut candidates = BTreeSet::new();
+
+        for block in cfg.blocks() {
+            if guard_condition(cfg, block.index())?.is_none() {
+                continue;
+            }
+            for inst in block.instructions() {
+                if let (Operation::Load { .. }, Some(address)) = (inst.operation(), inst.address())
+                {
+                    candidates.insert(address);
+                }
+            }
+        }
+
+        Ok(candidates)
+    }
+}
+
+impl Transform<ControlFlowGraph> for IndexMasking {
+    fn name(&self) -> &'static str {
+        "IndexMasking"
+    }
+
+    fn description(&self) -> String {
+        format!("Mask the address of {} load(s)", self.addresses.len())
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        let block_indices: Vec<usize> = cfg.blocks().iter().map(|block| block.index()).collect();
+
+        for block_index in block_indices {
+            let condition = match guard_condition(cfg, block_index)? {
+                Some(condition) => condition,
+                None => continue,
+            };
+
+            let block = cfg.block_mut(block_index)?;
+
+            let mut masked = false;
+            let guard = guard_variable(block_index);
+            for inst in block.instructions_mut() {
+                if !inst
+                    .address()
+                    .map_or(false, |a| self.addresses.contains(&a))
+                {
+                    continue;
+                }
+                if let Operation::Load { address, .. } = inst.operation_mut() {
+                    let bits = address.sort().unwrap_bit_vector();
+                    *address = Expression::ite(
+                        guard.clone().into(),
+                        address.clone(),
+                        BitVector::constant_u64(0, bits),
+                    )?;
+                    masked = true;
+                }
+            }
+
+            if masked {
+                // Capture the branch condition on block entry, before any variable is overwritten.
+                let mut capture = Instruction::assign(guard, condition)?;
+                capture.set_address(block.address());
+                capture.labels_mut().helper();
+                block.insert_instruction(0, capture)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the condition of the conditional edge leading into the block (if it's the only one).
+fn guard_condition(cfg: &ControlFlowGraph, block_index: usize) -> Result<Option<Expression>> {
+    match cfg.edges_in(block_index)?.as_slice() {
+        [edge] => Ok(edge.condition().cloned()),
+        _ => Ok(None),
+    }
+}
+
+fn guard_variable(block_index: usize) -> Variable {
+    Boolean::variable(&format!("_index_mask_guard_{}", block_index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_load_address_with_branch_condition() {
+        // Given
+        let mut cfg = ControlFlowGraph::new();
+        let block0_index = cfg.new_block().index();
+        let block1 = cfg.new_block();
+        block1
+            .load(
+                BitVector::variable("x", 8),
+                BitVector::word_variable("a").into(),
+            )
+            .unwrap()
+            .set_address(Some(0x20));
+        let block1_index = block1.index();
+        let block2_index = cfg.new_block().index();
+
+        let condition: Expression = Boolean::variable("c").into();
+        cfg.conditional_edge(block0_index, block1_index, condition.clone())
+            .unwrap();
+        cfg.conditional_edge(
+            block0_index,
+            block2_index,
+            Boolean::not(condition.clone()).unwrap(),
+        )
+        .unwrap();
+        cfg.unconditional_edge(block1_index, block2_index).unwrap();
+        cfg.set_entry(block0_index).unwrap();
+        cfg.set_exit(block2_index).unwrap();
+
+        assert_eq!(
+            IndexMasking::candidates(&cfg).unwrap(),
+            vec![0x20].into_iter().collect()
+        );
+
+        // When
+        let index_masking = IndexMaskingBuilder::default()
+            .addresses(vec![0x20].into_iter().collect())
+            .build()
+            .unwrap();
+        index_masking.transform(&mut cfg).unwrap();
+
+        // Then
+        let guard = guard_variable(block1_index);
+        let instructions = cfg.block(block1_index).unwrap().instructions();
+        assert_eq!(
+            instructions[0].operation(),
+            &Operation::assign(guard.clone(), condition).unwrap()
+        );
+        assert_eq!(
+            instructions[1].operation(),
+            &Operation::load(
+                BitVector::variable("x", 8),
+                Expression::ite(
+                    guard.into(),
+                    BitVector::word_variable("a").into(),
+                    BitVector::constant_u64(0, 64)
+                )
+                .unwrap()
+            )
+            .unwrap()
+        );
+    }
+}
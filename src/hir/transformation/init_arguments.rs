@@ -0,0 +1,136 @@
+use crate::environment::{SecurityLevel, WORD_SIZE};
+use crate::error::Result;
+use crate::expr::{BitVector, Expression, Memory, Sort};
+use crate::hir::ControlFlowGraph;
+use crate::ir::Transform;
+use std::collections::BTreeMap;
+
+/// Sets the security levels of the arguments which are passed on the stack.
+///
+/// Arguments passed in registers are initialized along with all other global variables.
+/// The stack slots are addressed relative to the stack pointer, which therefore has to be initialized
+/// in the entry block already (by `InitStack` or `InitGlobalVariables`).
+/// If it isn't, the program never reads the stack pointer and can't access its arguments on the stack anyway.
+#[derive(Default, Builder, Debug)]
+pub struct InitArguments {
+    stack_pointer: String,
+    /// Security level by stack slot offset (relative to the stack pointer at program entry)
+    stack_arguments: BTreeMap<u64, SecurityLevel>,
+}
+
+impl Transform<ControlFlowGraph> for InitArguments {
+    fn name(&self) -> &'static str {
+        "InitArguments"
+    }
+
+    fn description(&self) -> String {
+        "Set up initial state of the stack arguments".to_string()
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        let entry_block = cfg.entry_block_mut()?;
+
+        let stack_pointer = BitVector::word_variable(&self.stack_pointer);
+        if !entry_block.variables_written().contains(&&stack_pointer) {
+            return Ok(());
+        }
+
+        for (&offset, level) in &self.stack_arguments {
+            let address = BitVector::add(
+                stack_pointer.clone().into(),
+                BitVector::word_constant(offset),
+            )?;
+            match level {
+                SecurityLevel::Low => {
+                    let argument = Memory::load(WORD_SIZE, Memory::variable().into(), address)?;
+                    entry_block
+                        .indistinguishable(argument)
+                        .labels_mut()
+                        .pseudo();
+                }
+                SecurityLevel::High => {
+                    let argument = Expression::nondet(Sort::word());
+                    entry_block.store(address, argument)?.labels_mut().pseudo();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hir::Operation;
+
+    fn cfg_with_stack_pointer(initialized: bool) -> ControlFlowGraph {
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block();
+        if initialized {
+            entry
+                .assign(
+                    BitVector::word_variable("rsp"),
+                    Expression::nondet(Sort::word()),
+                )
+                .unwrap();
+        }
+        let entry_index = entry.index();
+        cfg.set_entry(entry_index).unwrap();
+        cfg.set_exit(entry_index).unwrap();
+        cfg
+    }
+
+    fn init_arguments() -> InitArguments {
+        InitArgumentsBuilder::default()
+            .stack_pointer("rsp".to_owned())
+            .stack_arguments(
+                vec![(8, SecurityLevel::Low), (16, SecurityLevel::High)]
+                    .into_iter()
+                    .collect(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_stack_arguments_are_initialized_relative_to_stack_pointer() {
+        // Given
+        let mut cfg = cfg_with_stack_pointer(true);
+
+        // When
+        init_arguments().transform(&mut cfg).unwrap();
+
+        // Then: low slot at rsp + 8 is indistinguishable, high slot at rsp + 16 is havocked
+        let instructions = cfg.entry_block().unwrap().instructions();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(
+            instructions[1].operation(),
+            &Operation::Indistinguishable {
+                expr: Memory::load(
+                    WORD_SIZE,
+                    Memory::variable().into(),
+                    BitVector::add(
+                        BitVector::word_variable("rsp").into(),
+                        BitVector::word_constant(8)
+                    )
+                    .unwrap()
+                )
+                .unwrap()
+            }
+        );
+        assert!(instructions[2].operation().is_store());
+    }
+
+    #[test]
+    fn test_stack_arguments_are_skipped_without_stack_pointer() {
+        // Given
+        let mut cfg = cfg_with_stack_pointer(false);
+
+        // When
+        init_arguments().transform(&mut cfg).unwrap();
+
+        // Then
+        assert!(cfg.entry_block().unwrap().instructions().is_empty());
+    }
+}
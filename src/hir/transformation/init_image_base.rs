@@ -0,0 +1,105 @@
+use crate::error::Result;
+use crate::expr::{BitVector, Expression, Variable};
+use crate::hir::transformation::ExplicitProgramCounter;
+use crate::hir::{Block, ControlFlowGraph};
+use crate::ir::Transform;
+use std::collections::BTreeSet;
+
+/// The image base is page-aligned.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Chooses the image base (ASLR slide) freely for each execution.
+///
+/// Code pointers held by `code_pointer_registers` are run-time addresses, i.e. the image base
+/// plus an offset. Only the offset is low, such that executions may differ in the slide alone.
+#[derive(Default, Builder, Debug)]
+pub struct InitImageBase {
+    code_pointer_registers: BTreeSet<String>,
+}
+
+impl Transform<ControlFlowGraph> for InitImageBase {
+    fn name(&self) -> &'static str {
+        "InitImageBase"
+    }
+
+    fn description(&self) -> String {
+        "Set up randomized image base and code pointers".to_string()
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        let entry_block = cfg.entry_block_mut()?;
+
+        let image_base = ExplicitProgramCounter::image_base_variable();
+        havoc_variable(entry_block, image_base.clone())?;
+
+        entry_block
+            .assume(Expression::equal(
+                BitVector::and(
+                    image_base.clone().into(),
+                    BitVector::word_constant(PAGE_SIZE - 1),
+                )?,
+                BitVector::word_constant(0),
+            )?)?
+            .labels_mut()
+            .pseudo();
+
+        for register in &self.code_pointer_registers {
+            let code_pointer = BitVector::word_variable(register);
+            havoc_variable(entry_block, code_pointer.clone())?;
+            let offset = BitVector::sub(code_pointer.into(), image_base.clone().into())?;
+            entry_block.indistinguishable(offset).labels_mut().pseudo();
+        }
+
+        Ok(())
+    }
+}
+
+fn havoc_variable(block: &mut Block, var: Variable) -> Result<()> {
+    let value = Expression::nondet(var.sort().clone());
+    block.assign(var, value)?.labels_mut().pseudo();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hir::Operation;
+
+    #[test]
+    fn test_code_pointers_may_differ_in_slide_only() {
+        // Given: code pointers which may differ in the slide only
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(entry).unwrap();
+
+        let transform = InitImageBaseBuilder::default()
+            .code_pointer_registers(vec!["rdi".to_owned()].into_iter().collect())
+            .build()
+            .unwrap();
+
+        // When:
+        transform.transform(&mut cfg).unwrap();
+
+        // Then: the image base itself isn't indistinguishable, only the offset of the pointer
+        let image_base = ExplicitProgramCounter::image_base_variable();
+        let rdi = BitVector::word_variable("rdi");
+        let indistinguishable: Vec<&Expression> = cfg
+            .entry_block()
+            .unwrap()
+            .instructions()
+            .iter()
+            .filter_map(|inst| match inst.operation() {
+                Operation::Indistinguishable { expr } => Some(expr),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            indistinguishable,
+            vec![&BitVector::sub(rdi.into(), image_base.clone().into()).unwrap()]
+        );
+        assert!(!indistinguishable
+            .iter()
+            .any(|expr| **expr == image_base.clone().into()));
+    }
+}
@@ -1,29 +1,102 @@
-use crate::environment::SecurityLevel;
+use crate::environment::{AddressRange, SecurityLevel};
 use crate::error::Result;
-use crate::expr::{BitVector, Expression, Memory, Variable};
-use crate::hir::{Block, ControlFlowGraph};
+use crate::expr::{BitVector, Boolean, Expression, Memory, Variable};
+use crate::hir::{Block, ControlFlowGraph, Instruction, Operation};
 use crate::ir::Transform;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeMap;
 
+/// Initializes the memory according to the memory security policy.
+///
+/// Security levels of memory regions are encoded as range constraints on the accessed address,
+/// so the size of the regions doesn't affect the size of the encoding:
+/// Each load from a low-security address `a` reads the same initial value `_low_memory[a]`
+/// in all compositions, where `_low_memory` is an indistinguishable array.
 #[derive(Builder, Debug)]
 pub struct InitMemory {
     default_memory_security_level: SecurityLevel,
-    low_security_memory_addresses: BTreeSet<u64>,
-    high_security_memory_addresses: BTreeSet<u64>,
+    low_security_memory: Vec<AddressRange>,
+    high_security_memory: Vec<AddressRange>,
     initial_memory_content: BTreeMap<u64, u8>,
+    // Width of the value stored at a single address, initial contents are zero-extended to it
+    #[builder(default = "8")]
+    memory_cell_width: usize,
 }
 
 impl Default for InitMemory {
     fn default() -> Self {
         Self {
             default_memory_security_level: SecurityLevel::High,
-            low_security_memory_addresses: BTreeSet::new(),
-            high_security_memory_addresses: BTreeSet::new(),
+            low_security_memory: Vec::new(),
+            high_security_memory: Vec::new(),
             initial_memory_content: BTreeMap::new(),
+            memory_cell_width: 8,
         }
     }
 }
 
+impl InitMemory {
+    /// The memory regions which differ from the default security level.
+    fn exceptional_memory(&self) -> &[AddressRange] {
+        match self.default_memory_security_level {
+            SecurityLevel::Low => &self.high_security_memory,
+            SecurityLevel::High => &self.low_security_memory,
+        }
+    }
+
+    /// Returns a formula which is true iff the (cell) address has low security.
+    fn is_low_security_address(&self, address: Expression) -> Result<Expression> {
+        let in_exceptional_memory = in_address_ranges(address, self.exceptional_memory())?;
+        match self.default_memory_security_level {
+            SecurityLevel::Low => Boolean::not(in_exceptional_memory),
+            SecurityLevel::High => Ok(in_exceptional_memory),
+        }
+    }
+
+    /// Constrains each cell read by the load at the given index to be equal to `_low_memory`
+    /// if the cell address has low security.
+    fn constrain_load_at(&self, block: &mut Block, index: usize) -> Result<()> {
+        let (bit_width, address) = match block.instruction(index).map(Instruction::operation) {
+            Some(Operation::Load {
+                variable, address, ..
+            }) => (variable.sort().unwrap_bit_vector(), address.clone()),
+            _ => return Ok(()),
+        };
+
+        let cell_width = self.memory_cell_width;
+        let mut constraints = Vec::new();
+        for offset in 0..(bit_width + cell_width - 1) / cell_width {
+            let cell_address = if offset == 0 {
+                address.clone()
+            } else {
+                BitVector::add(address.clone(), BitVector::word_constant(offset as u64))?
+            };
+            let initial_cell = Memory::load(
+                cell_width,
+                initial_memory_variable().into(),
+                cell_address.clone(),
+            )?;
+            let low_cell = Memory::load(
+                cell_width,
+                low_memory_variable().into(),
+                cell_address.clone(),
+            )?;
+            let mut constraint = Instruction::assume(Boolean::imply(
+                self.is_low_security_address(cell_address)?,
+                Expression::equal(initial_cell, low_cell)?,
+            )?)?;
+            constraint.set_address(block.instruction(index).unwrap().address());
+            constraint.labels_mut().pseudo();
+            constraints.push(constraint);
+        }
+
+        for constraint in constraints.into_iter().rev() {
+            block.insert_instruction(index, constraint)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Transform<ControlFlowGraph> for InitMemory {
     fn name(&self) -> &'static str {
         "InitMemory"
@@ -35,36 +108,52 @@ impl Transform<ControlFlowGraph> for InitMemory {
 
     /// Initialize memory and make low-addresses indistinguishable
     fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
-        let entry_block = cfg.entry_block_mut()?;
+        let exceptional_memory = self
+            .exceptional_memory()
+            .iter()
+            .any(|range| !range.is_empty());
 
-        havoc_variable(entry_block, Memory::variable())?;
+        {
+            let entry_block = cfg.entry_block_mut()?;
 
-        match self.default_memory_security_level {
-            SecurityLevel::Low => {
-                low_equivalent(entry_block, Memory::variable().into());
-                for &address in &self.high_security_memory_addresses {
-                    let secret_var = BitVector::variable("_secret", 8);
-                    havoc_variable(entry_block, secret_var.clone())?;
-                    let addr = BitVector::word_constant(address);
-                    entry_block
-                        .store(addr, secret_var.into())?
-                        .labels_mut()
-                        .pseudo();
+            havoc_variable(entry_block, Memory::variable())?;
+
+            if !exceptional_memory {
+                // Uniform security level, no need for range constraints
+                if self.default_memory_security_level == SecurityLevel::Low {
+                    low_equivalent(entry_block, Memory::variable().into());
                 }
+            } else {
+                assign_variable(
+                    entry_block,
+                    initial_memory_variable(),
+                    Memory::variable().into(),
+                )?;
+                havoc_variable(entry_block, low_memory_variable())?;
+                low_equivalent(entry_block, low_memory_variable().into());
             }
-            SecurityLevel::High => {
-                for &address in &self.low_security_memory_addresses {
-                    let addr = BitVector::word_constant(address);
-                    let memory_content_at_address =
-                        Memory::load(8, Memory::variable().into(), addr)?;
-                    low_equivalent(entry_block, memory_content_at_address);
+        }
+
+        if exceptional_memory {
+            for block in cfg.blocks_mut() {
+                let load_indices: Vec<usize> = block
+                    .instructions()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, inst)| inst.operation().is_load())
+                    .map(|(index, _)| index)
+                    .collect();
+
+                for index in load_indices.into_iter().rev() {
+                    self.constrain_load_at(block, index)?;
                 }
             }
         }
 
+        let entry_block = cfg.entry_block_mut()?;
         for (&address, &byte) in &self.initial_memory_content {
             let addr = BitVector::word_constant(address);
-            let value = BitVector::constant_u64(byte.into(), 8);
+            let value = BitVector::constant_u64(byte.into(), self.memory_cell_width);
             entry_block.store(addr, value)?;
         }
 
@@ -72,6 +161,32 @@ impl Transform<ControlFlowGraph> for InitMemory {
     }
 }
 
+/// Returns a formula which is true iff the address lies within one of the ranges.
+fn in_address_ranges(address: Expression, ranges: &[AddressRange]) -> Result<Expression> {
+    let mut conditions = Vec::new();
+    for range in ranges.iter().filter(|range| !range.is_empty()) {
+        conditions.push(Boolean::and(
+            BitVector::ule(BitVector::word_constant(range.start()), address.clone())?,
+            BitVector::ult(address.clone(), BitVector::word_constant(range.end()))?,
+        )?);
+    }
+    Boolean::disjunction(&conditions)
+}
+
+/// The memory content on program entry.
+fn initial_memory_variable() -> Variable {
+    let mut var = Variable::new("_initial_memory", Memory::variable().sort().clone());
+    var.set_rollback_persistent(true);
+    var
+}
+
+/// The initial memory content of low-security addresses, which is equal in all compositions.
+fn low_memory_variable() -> Variable {
+    let mut var = Variable::new("_low_memory", Memory::variable().sort().clone());
+    var.set_rollback_persistent(true);
+    var
+}
+
 fn havoc_variable(block: &mut Block, var: Variable) -> Result<()> {
     let value = Expression::nondet(var.sort().clone());
     assign_variable(block, var, value)
@@ -85,3 +200,77 @@ fn assign_variable(block: &mut Block, var: Variable, value: Expression) -> Resul
 fn low_equivalent(block: &mut Block, expr: Expression) {
     block.indistinguishable(expr).labels_mut().pseudo();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty entry block followed by a block with a 16-bit load
+    fn load_cfg() -> (ControlFlowGraph, usize) {
+        let mut cfg = ControlFlowGraph::new();
+        let entry_index = cfg.new_block().index();
+        let block = cfg.new_block();
+        block
+            .load(
+                BitVector::variable("x", 16),
+                BitVector::word_variable("a").into(),
+            )
+            .unwrap();
+        let load_index = block.index();
+        cfg.unconditional_edge(entry_index, load_index).unwrap();
+        cfg.set_entry(entry_index).unwrap();
+        cfg.set_exit(load_index).unwrap();
+        (cfg, load_index)
+    }
+
+    #[test]
+    fn test_uniform_low_memory_is_indistinguishable() {
+        // Given
+        let (mut cfg, load_index) = load_cfg();
+        let init_memory = InitMemoryBuilder::default()
+            .default_memory_security_level(SecurityLevel::Low)
+            .low_security_memory(vec![])
+            .high_security_memory(vec![])
+            .initial_memory_content(BTreeMap::new())
+            .build()
+            .unwrap();
+
+        // When
+        init_memory.transform(&mut cfg).unwrap();
+
+        // Then: havoc memory, memory indistinguishable
+        let instructions = cfg.entry_block().unwrap().instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            instructions[1].operation(),
+            &Operation::indistinguishable(Memory::variable().into())
+        );
+        assert_eq!(cfg.block(load_index).unwrap().instruction_count(), 1);
+    }
+
+    #[test]
+    fn test_loads_are_constrained_per_byte_for_low_memory_range() {
+        // Given
+        let (mut cfg, load_index) = load_cfg();
+        let init_memory = InitMemoryBuilder::default()
+            .default_memory_security_level(SecurityLevel::High)
+            .low_security_memory(vec![AddressRange::new(0x1000, 0x100000)])
+            .high_security_memory(vec![])
+            .initial_memory_content(BTreeMap::new())
+            .build()
+            .unwrap();
+
+        // When
+        init_memory.transform(&mut cfg).unwrap();
+
+        // Then: one constraint per loaded byte in front of the load
+        let instructions = cfg.block(load_index).unwrap().instructions();
+        assert_eq!(instructions.len(), 3);
+        assert!(instructions[0].operation().is_assume());
+        assert!(instructions[1].operation().is_assume());
+        assert!(instructions[2].operation().is_load());
+
+        // Init instructions are independent of the range size
+        assert_eq!(cfg.entry_block().unwrap().instruction_count(), 4);
+    }
+}
@@ -1,32 +1,96 @@
 use crate::error::Result;
-use crate::expr::BitVector;
+use crate::expr::{BitVector, Expression};
 use crate::hir::{Effect, Instruction, Operation};
 use crate::ir::Transform;
 
+/// Adds the microarchitectural effects (cache, BTB, PHT, MDP) to each instruction.
+///
+/// Unaligned accesses may cross cache line/page boundaries and therefore fetch two lines/pages.
+/// Cache flushes and prefetches evict/fetch the whole cache line holding the address.
 #[derive(Default, Builder, Debug)]
 pub struct InstructionEffects {
-    model_cache_effects: bool,
+    model_cache_load_effects: bool,
+    model_cache_store_effects: bool,
     model_btb_effects: bool,
     model_pht_effects: bool,
+    #[builder(default)]
+    model_mdp_effects: bool,
+    /// Cache effects are tracked at this alignment (e.g. cache line size), if given.
+    cache_address_alignment: Option<u64>,
+    #[builder(default = "64")]
+    cache_line_size: u64,
 }
 
 impl InstructionEffects {
-    fn instruction_effects(&self, instruction: &Instruction) -> Vec<Effect> {
+    fn cache_fetch(&self, address: &Expression, bit_width: usize) -> Result<Vec<Effect>> {
+        match self.cache_address_alignment {
+            Some(alignment) => {
+                // A single byte represents the whole line/page
+                let aligned_address = align_address(address.clone(), alignment)?;
+                let mut effects = vec![Effect::cache_fetch(aligned_address, 8)];
+
+                // The last byte of an unaligned access may lie in the next line/page
+                let bytes = (bit_width / 8) as u64;
+                if bytes > 1 {
+                    let last_byte =
+                        BitVector::add(address.clone(), BitVector::word_constant(bytes - 1))?;
+                    let aligned_address = align_address(last_byte, alignment)?;
+                    effects.push(Effect::cache_fetch(aligned_address, 8));
+                }
+
+                Ok(effects)
+            }
+            None => Ok(vec![Effect::cache_fetch(address.clone(), bit_width)]),
+        }
+    }
+
+    /// Returns the (aligned) address and bit width representing the cache line holding the address.
+    fn cache_line(&self, address: &Expression) -> Result<(Expression, usize)> {
+        match self.cache_address_alignment {
+            Some(alignment) => Ok((align_address(address.clone(), alignment)?, 8)),
+            None => Ok((
+                align_address(address.clone(), self.cache_line_size)?,
+                (self.cache_line_size * 8) as usize,
+            )),
+        }
+    }
+
+    fn instruction_effects(&self, instruction: &Instruction) -> Result<Vec<Effect>> {
         let mut effects = Vec::new();
 
         match instruction.operation() {
             Operation::Store { address, expr, .. } => {
-                if self.model_cache_effects {
+                if self.model_cache_store_effects {
                     let bit_width = expr.sort().unwrap_bit_vector();
-                    effects.push(Effect::cache_fetch(address.clone(), bit_width));
+                    effects.extend(self.cache_fetch(address, bit_width)?);
+                }
+                if self.model_mdp_effects {
+                    let location =
+                        BitVector::word_constant(instruction.address().unwrap_or_default());
+                    effects.push(Effect::store_location(location, address.clone()));
                 }
             }
             Operation::Load {
                 variable, address, ..
             } => {
-                if self.model_cache_effects {
+                if self.model_cache_load_effects {
                     let bit_width = variable.sort().unwrap_bit_vector();
-                    effects.push(Effect::cache_fetch(address.clone(), bit_width));
+                    effects.extend(self.cache_fetch(address, bit_width)?);
+                }
+                if self.model_mdp_effects {
+                    effects.push(Effect::load_dependence(address.clone()));
+                }
+            }
+            Operation::CacheFlush { address } => {
+                if self.model_cache_load_effects || self.model_cache_store_effects {
+                    let (line, bit_width) = self.cache_line(address)?;
+                    effects.push(Effect::cache_evict(line, bit_width));
+                }
+            }
+            Operation::Prefetch { address } => {
+                if self.model_cache_load_effects {
+                    let (line, bit_width) = self.cache_line(address)?;
+                    effects.push(Effect::cache_fetch(line, bit_width));
                 }
             }
             Operation::Call { target } | Operation::Branch { target } => {
@@ -53,10 +117,17 @@ impl InstructionEffects {
             _ => (),
         }
 
-        effects
+        Ok(effects)
     }
 }
 
+/// Masks off the lower address bits, such that the address is aligned to `alignment`.
+pub fn align_address(address: Expression, alignment: u64) -> Result<Expression> {
+    debug_assert!(alignment.is_power_of_two());
+    let mask = !(alignment - 1);
+    BitVector::and(address, BitVector::word_constant(mask))
+}
+
 impl Transform<Instruction> for InstructionEffects {
     fn name(&self) -> &'static str {
         "InstructionEffects"
@@ -67,9 +138,80 @@ impl Transform<Instruction> for InstructionEffects {
     }
 
     fn transform(&self, instruction: &mut Instruction) -> Result<()> {
-        let effects = self.instruction_effects(instruction);
+        let effects = self.instruction_effects(instruction)?;
         instruction.add_effects(&effects);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{Sort, Variable};
+
+    fn cache_load_effects(alignment: Option<u64>, bit_width: usize) -> Vec<Effect> {
+        let effects = InstructionEffectsBuilder::default()
+            .model_cache_load_effects(true)
+            .model_cache_store_effects(false)
+            .model_btb_effects(false)
+            .model_pht_effects(false)
+            .cache_address_alignment(alignment)
+            .build()
+            .unwrap();
+
+        let mut load = Instruction::load(
+            Variable::new("v", Sort::bit_vector(bit_width)),
+            BitVector::word_variable("x").into(),
+        )
+        .unwrap();
+        effects.transform(&mut load).unwrap();
+        load.effects().to_vec()
+    }
+
+    #[test]
+    fn test_wide_access_fetches_the_line_of_its_last_byte() {
+        // Given:
+        let x: Expression = BitVector::word_variable("x").into();
+
+        // When:
+        let effects = cache_load_effects(Some(64), 128);
+
+        // Then: an unaligned 16-byte load may end in the next line
+        let last_byte = BitVector::add(x.clone(), BitVector::word_constant(15)).unwrap();
+        assert_eq!(
+            effects,
+            vec![
+                Effect::cache_fetch(align_address(x, 64).unwrap(), 8),
+                Effect::cache_fetch(align_address(last_byte, 64).unwrap(), 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_byte_access_fetches_one_line() {
+        // Given:
+        let x: Expression = BitVector::word_variable("x").into();
+
+        // When:
+        let effects = cache_load_effects(Some(64), 8);
+
+        // Then:
+        assert_eq!(
+            effects,
+            vec![Effect::cache_fetch(align_address(x, 64).unwrap(), 8)]
+        );
+    }
+
+    #[test]
+    fn test_unaligned_tracking_fetches_the_accessed_bytes() {
+        // Given:
+        let x: Expression = BitVector::word_variable("x").into();
+
+        // When:
+        let effects = cache_load_effects(None, 128);
+
+        // Then:
+        assert_eq!(effects, vec![Effect::cache_fetch(x, 128)]);
+    }
+}
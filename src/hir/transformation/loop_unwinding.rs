@@ -1,6 +1,8 @@
+use crate::diagnostics::{self, Code, Diagnostic, Location};
 use crate::environment::UnwindingGuard;
-use crate::error::Result;
-use crate::hir::{ControlFlowGraph, RemovedEdgeGuard};
+use crate::error::{Error, Result};
+use crate::hir::analysis;
+use crate::hir::{ControlFlowGraph, Origin, RemovedEdgeGuard};
 use crate::ir::Transform;
 use falcon::graph::LoopTree;
 use std::collections::{BTreeMap, BTreeSet};
@@ -11,13 +13,24 @@ pub struct LoopUnwinding {
     unwinding_bound: BTreeMap<usize, usize>,
     default_unwinding_bound: usize,
     unwinding_guard: UnwindingGuard,
+    /// infer the unwinding bound of loops with constant trip count
+    #[builder(default)]
+    infer_unwinding_bound: bool,
+    /// unwind irreducible CFGs anyway (unsound), instead of failing
+    #[builder(default)]
+    allow_irreducible: bool,
+    /// max. number of blocks of the unwound CFG, checked before each iteration is added
+    #[builder(default)]
+    max_blocks: Option<usize>,
 }
 
 impl LoopUnwinding {
-    fn unwinding_bound_for_loop(&self, loop_id: usize) -> usize {
+    /// Explicitly given unwinding bounds take precedence over inferred ones.
+    fn unwinding_bound_for_loop(&self, loop_id: usize, inferred_bound: Option<usize>) -> usize {
         self.unwinding_bound
             .get(&loop_id)
             .copied()
+            .or(inferred_bound)
             .unwrap_or(self.default_unwinding_bound)
     }
 
@@ -26,6 +39,7 @@ impl LoopUnwinding {
         cfg: &mut ControlFlowGraph,
         loop_header: usize,
         loop_nodes: &BTreeSet<usize>,
+        inferred_bound: Option<usize>,
     ) -> Result<BTreeSet<usize>> {
         // Compute all loops nodes which have an outgoing edge (aka back edge) to the loop header
         let back_nodes: Vec<usize> = cfg
@@ -35,7 +49,7 @@ impl LoopUnwinding {
             .collect();
 
         let loop_id = cfg.block(loop_header).unwrap().loop_id().unwrap();
-        let k = self.unwinding_bound_for_loop(loop_id);
+        let k = self.unwinding_bound_for_loop(loop_id, inferred_bound);
 
         if k == 0 {
             // No unwinding, only delete back edges to get rid of the loop and we are done
@@ -51,8 +65,10 @@ impl LoopUnwinding {
         // First, create a copy for the last iteration.
         // All back edges of the last iteration are removed (replaced by unwinding assumptions).
         let last_loop_header = {
+            self.check_block_budget(cfg, loop_nodes)?;
             let new_block_indices = cfg.duplicate_blocks(loop_nodes)?;
             let last_loop_header = new_block_indices[&loop_header];
+            record_loop_iteration(cfg, &new_block_indices, loop_id, k + 1)?;
 
             // Remove back edges
             for back_node in &back_nodes {
@@ -71,9 +87,11 @@ impl LoopUnwinding {
         // Then repeatedly duplicate the loop nodes for the remaining k-2 iterations.
         // The back edges of iteration i are rewired to the iteration i+1.
         let mut next_loop_header = last_loop_header;
-        for _ in 1..k {
+        for i in 1..k {
+            self.check_block_budget(cfg, loop_nodes)?;
             let new_block_indices = cfg.duplicate_blocks(loop_nodes)?;
             let current_loop_header = new_block_indices[&loop_header];
+            record_loop_iteration(cfg, &new_block_indices, loop_id, k + 1 - i)?;
 
             // Rewire the back edges of the current iteration to the loop header of the next iteration.
             for back_node in &back_nodes {
@@ -97,21 +115,63 @@ impl LoopUnwinding {
         Ok(loop_nodes_unwound)
     }
 
+    /// Fails early if adding another iteration of the loop would exceed the block budget.
+    fn check_block_budget(
+        &self,
+        cfg: &ControlFlowGraph,
+        loop_nodes: &BTreeSet<usize>,
+    ) -> Result<()> {
+        super::check_block_budget(
+            cfg,
+            loop_nodes.len(),
+            self.max_blocks,
+            "Number of blocks during LoopUnwinding",
+        )
+    }
+
     pub fn loop_tree(cfg: &ControlFlowGraph) -> Result<LoopTree> {
         let entry = cfg.entry()?;
 
         if !cfg.graph().is_reducible(entry)? {
-            println!("Warning: CFG is not reducible!");
+            diagnostics::report(
+                Diagnostic::warning(
+                    Code::IrreducibleControlFlowGraph,
+                    "CFG is not reducible, loop unwinding may be unsound",
+                )
+                .at(Location {
+                    block: Some(entry),
+                    ..Location::default()
+                }),
+            );
         }
 
         Ok(cfg.graph().compute_loop_tree(entry)?)
     }
 
     pub fn unwind_cfg(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        if !self.allow_irreducible {
+            let irreducible_edges = analysis::irreducible_edges(cfg)?;
+            if !irreducible_edges.is_empty() {
+                let entries: BTreeSet<usize> = irreducible_edges
+                    .into_iter()
+                    .map(|(_, tail)| tail)
+                    .collect();
+                return Err(Error::IrreducibleControlFlowGraph(
+                    entries.into_iter().collect(),
+                ));
+            }
+        }
+
         let loop_tree = Self::loop_tree(cfg)?;
         let parent_loop_ids = loop_tree.compute_predecessors()?;
         let loops = loop_tree.vertices();
 
+        let inferred_bounds = if self.infer_unwinding_bound {
+            self.infer_unwinding_bounds(cfg, &loop_tree)?
+        } else {
+            BTreeMap::new()
+        };
+
         // First set the loop IDs of all loop header blocks to keep track of them during unwinding.
         // Block duplication causes that head block IDs and loop IDs will diverge.
         for l in &loops {
@@ -131,7 +191,9 @@ impl LoopUnwinding {
         for &loop_header in top_sort.iter().rev() {
             let loop_nodes = &all_loop_nodes[&loop_header];
 
-            let loop_nodes_unwound = self.unwind_loop(cfg, loop_header, loop_nodes)?;
+            let inferred_bound = inferred_bounds.get(&loop_header).copied();
+            let loop_nodes_unwound =
+                self.unwind_loop(cfg, loop_header, loop_nodes, inferred_bound)?;
             assert!(loop_nodes_unwound.is_superset(loop_nodes));
 
             // Now push all newly created loop nodes to the parent loops
@@ -153,6 +215,35 @@ impl LoopUnwinding {
         Ok(())
     }
 
+    fn infer_unwinding_bounds(
+        &self,
+        cfg: &ControlFlowGraph,
+        loop_tree: &LoopTree,
+    ) -> Result<BTreeMap<usize, usize>> {
+        let bounds = analysis::loop_bounds(cfg, loop_tree)?;
+
+        for (&loop_header, &bound) in &bounds {
+            if bound > self.default_unwinding_bound {
+                diagnostics::report(
+                    Diagnostic::warning(
+                        Code::UnwindingBoundTooSmall,
+                        format!(
+                            "Loop {} has a constant trip count of {}, which exceeds the default unwinding bound (k={})",
+                            loop_header, bound, self.default_unwinding_bound
+                        ),
+                    )
+                    .at(Location {
+                        address: cfg.block(loop_header)?.address(),
+                        block: Some(loop_header),
+                        ..Location::default()
+                    }),
+                );
+            }
+        }
+
+        Ok(bounds)
+    }
+
     fn removed_edge_guard(&self) -> RemovedEdgeGuard {
         match self.unwinding_guard {
             UnwindingGuard::Assumption => RemovedEdgeGuard::AssumeEdgeNotTaken,
@@ -161,6 +252,21 @@ impl LoopUnwinding {
     }
 }
 
+/// Records the loop iteration in all instructions of the duplicated blocks (the original blocks are the first iteration).
+fn record_loop_iteration(
+    cfg: &mut ControlFlowGraph,
+    duplicated_blocks: &BTreeMap<usize, usize>,
+    loop_id: usize,
+    iteration: usize,
+) -> Result<()> {
+    for &block_index in duplicated_blocks.values() {
+        for inst in cfg.block_mut(block_index)?.instructions_mut() {
+            inst.add_origin(Origin::LoopIteration { loop_id, iteration });
+        }
+    }
+    Ok(())
+}
+
 impl Transform<ControlFlowGraph> for LoopUnwinding {
     fn name(&self) -> &'static str {
         "LoopUnwinding"
@@ -210,6 +316,17 @@ mod tests {
         block.index()
     }
 
+    fn add_loop_origin(
+        cfg: &mut ControlFlowGraph,
+        block_index: usize,
+        inst_index: usize,
+        loop_id: usize,
+        iteration: usize,
+    ) {
+        cfg.block_mut(block_index).unwrap().instructions_mut()[inst_index]
+            .add_origin(Origin::LoopIteration { loop_id, iteration });
+    }
+
     fn debug_cfg(
         test_name: &str,
         given_cfg: &ControlFlowGraph,
@@ -371,6 +488,10 @@ mod tests {
                 .unwrap()
                 .set_loop_id(Some(block0_index));
 
+            add_loop_origin(&mut cfg, block2_index, 0, block0_index, 4);
+            add_loop_origin(&mut cfg, block3_index, 0, block0_index, 3);
+            add_loop_origin(&mut cfg, block4_index, 0, block0_index, 2);
+
             cfg.set_entry(block0_index).unwrap();
             cfg.set_exit(block1_index).unwrap();
 
@@ -490,6 +611,14 @@ mod tests {
                 .unwrap()
                 .set_loop_id(Some(block1_index));
 
+            add_loop_origin(&mut cfg, block4_index, 0, block1_index, 2);
+            add_loop_origin(&mut cfg, block5_index, 0, block0_index, 2);
+            add_loop_origin(&mut cfg, block6_index, 0, block0_index, 2);
+            add_loop_origin(&mut cfg, block7_index, 0, block0_index, 2);
+            add_loop_origin(&mut cfg, block8_index, 0, block1_index, 2);
+            add_loop_origin(&mut cfg, block8_index, 0, block0_index, 2);
+            add_loop_origin(&mut cfg, block8_index, 1, block0_index, 2);
+
             cfg.set_entry(block0_index).unwrap();
             cfg.set_exit(block3_index).unwrap();
 
@@ -642,6 +771,9 @@ mod tests {
                 .unwrap()
                 .set_loop_id(Some(block1_index));
 
+            add_loop_origin(&mut cfg, block4_index, 0, block1_index, 2);
+            add_loop_origin(&mut cfg, block5_index, 0, block1_index, 2);
+
             cfg.set_entry(block1_index).unwrap();
             cfg.set_exit(block3_index).unwrap();
 
@@ -795,6 +927,8 @@ mod tests {
                 .unwrap()
                 .set_loop_id(Some(block1_index));
 
+            add_loop_origin(&mut cfg, block4_index, 0, block1_index, 2);
+
             cfg.set_entry(block1_index).unwrap();
             cfg.set_exit(block3_index).unwrap();
 
@@ -958,6 +1092,9 @@ mod tests {
                 .unwrap()
                 .set_loop_id(Some(block1_index));
 
+            add_loop_origin(&mut cfg, block5_index, 0, block1_index, 2);
+            add_loop_origin(&mut cfg, block6_index, 0, block1_index, 2);
+
             cfg.set_entry(block1_index).unwrap();
             cfg.set_exit(block4_index).unwrap();
 
@@ -973,4 +1110,98 @@ mod tests {
 
         assert_eq!(expected_cfg, unwound_cfg);
     }
+
+    #[test]
+    fn test_unwind_irreducible_loop_fails_unless_allowed() {
+        // Given: a loop c1 <-> c2 which can be entered at both blocks
+        let irreducible_cfg = || {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = add_block_with_id(&mut cfg, "c0");
+            let block1_index = add_block_with_id(&mut cfg, "c1");
+            let block2_index = add_block_with_id(&mut cfg, "c2");
+            let block3_index = add_block_with_id(&mut cfg, "c3");
+
+            cfg.unconditional_edge(block0_index, block1_index).unwrap();
+            cfg.unconditional_edge(block0_index, block2_index).unwrap();
+            cfg.unconditional_edge(block1_index, block2_index).unwrap();
+            cfg.unconditional_edge(block2_index, block1_index).unwrap();
+            cfg.unconditional_edge(block2_index, block3_index).unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block3_index).unwrap();
+
+            cfg
+        };
+        let unwinder = |allow_irreducible: bool| {
+            LoopUnwindingBuilder::default()
+                .default_unwinding_bound(1)
+                .unwinding_bound(BTreeMap::default())
+                .unwinding_guard(UnwindingGuard::Assumption)
+                .allow_irreducible(allow_irreducible)
+                .build()
+                .unwrap()
+        };
+
+        // When:
+        let denied = unwinder(false).unwind_cfg(&mut irreducible_cfg());
+        let allowed = unwinder(true).unwind_cfg(&mut irreducible_cfg());
+
+        // Then:
+        assert!(matches!(
+            denied,
+            Err(Error::IrreducibleControlFlowGraph(blocks)) if blocks.len() == 1
+        ));
+        assert!(allowed.is_ok());
+    }
+
+    #[test]
+    fn test_unwinding_beyond_the_block_budget_fails_early() {
+        let l: Expression = Variable::new("L", Sort::boolean()).into();
+        let not_l = Boolean::not(l.clone()).unwrap();
+
+        // Given: Self loop at block 0
+        let mut cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = add_block_with_id(&mut cfg, "c0");
+            let block1_index = add_block_with_id(&mut cfg, "c1");
+
+            cfg.conditional_edge(block0_index, block0_index, l).unwrap(); // loop
+            cfg.conditional_edge(block0_index, block1_index, not_l)
+                .unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block1_index).unwrap();
+
+            cfg
+        };
+
+        // When: Unwind with k=3, but at most 3 blocks
+        let unwinder = LoopUnwindingBuilder::default()
+            .default_unwinding_bound(3)
+            .unwinding_bound(BTreeMap::default())
+            .unwinding_guard(UnwindingGuard::Assumption)
+            .max_blocks(Some(3))
+            .build()
+            .unwrap();
+        let result = unwinder.unwind_cfg(&mut cfg);
+
+        // Then: the second iteration already exceeds the budget
+        match result {
+            Err(Error::BudgetExceeded {
+                what,
+                budget,
+                measured,
+                limit,
+                ..
+            }) => {
+                assert_eq!(what, "Number of blocks during LoopUnwinding");
+                assert_eq!(budget, "analysis.max_blocks");
+                assert_eq!(measured, 4);
+                assert_eq!(limit, 3);
+            }
+            other => panic!("Expected exceeded block budget, but got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,215 @@
+use crate::environment::AddressRange;
+use crate::error::Result;
+use crate::expr::{BitVector, Boolean, Expression, Sort, Variable};
+use crate::hir::{Block, ControlFlowGraph, Instruction, Operation};
+use crate::ir::Transform;
+
+/// Models memory-mapped I/O (device registers) instead of treating it as ordinary memory.
+///
+/// Loads overlapping an MMIO region return a fresh nondeterministic value, as the device may change
+/// the register content at any time. The device input is assumed to be public,
+/// i.e. the loaded values are the same in all self-compositions.
+/// Non-transient stores to an MMIO region are observable side effects (address and value),
+/// whereas transient stores never reach the device.
+#[derive(Default, Builder, Debug)]
+pub struct MemoryMappedIo {
+    regions: Vec<AddressRange>,
+}
+
+impl MemoryMappedIo {
+    /// Returns the variable holding the value read from a device register.
+    pub fn device_value_variable(bit_width: usize) -> Variable {
+        Variable::new(
+            format!("_mmio_value_{}", bit_width),
+            Sort::bit_vector(bit_width),
+        )
+    }
+
+    /// Returns a formula which is true iff any of the `bytes` bytes starting at `address`
+    /// lies within one of the MMIO regions.
+    fn is_mmio_access(&self, address: &Expression, bytes: u64) -> Result<Expression> {
+        let mut conditions = Vec::new();
+        for range in self.regions.iter().filter(|range| !range.is_empty()) {
+            conditions.push(Boolean::and(
+                BitVector::ule(
+                    BitVector::word_constant(range.start().saturating_sub(bytes - 1)),
+                    address.clone(),
+                )?,
+                BitVector::ult(address.clone(), BitVector::word_constant(range.end()))?,
+            )?);
+        }
+        Boolean::disjunction(&conditions)
+    }
+
+    /// Returns the instructions which replace the loaded value by the device input
+    /// if the load accesses an MMIO region.
+    fn device_load(&self, variable: &Variable, address: &Expression) -> Result<Vec<Instruction>> {
+        let bit_width = variable.sort().unwrap_bit_vector();
+        let bytes = ((bit_width + 7) / 8) as u64;
+        let device_value = Self::device_value_variable(bit_width);
+
+        Ok(vec![
+            Instruction::assign(
+                device_value.clone(),
+                Expression::nondet(device_value.sort().clone()),
+            )?,
+            Instruction::indistinguishable(device_value.clone().into()),
+            Instruction::assign(
+                variable.clone(),
+                Expression::ite(
+                    self.is_mmio_access(address, bytes)?,
+                    device_value.into(),
+                    variable.clone().into(),
+                )?,
+            )?,
+        ])
+    }
+
+    /// Returns the instructions which make the address and value of the store observable
+    /// if the store accesses an MMIO region.
+    fn device_store(&self, address: &Expression, expr: &Expression) -> Result<Vec<Instruction>> {
+        let bit_width = expr.sort().unwrap_bit_vector();
+        let bytes = ((bit_width + 7) / 8) as u64;
+        let is_mmio = self.is_mmio_access(address, bytes)?;
+
+        Ok(vec![
+            Instruction::observable(Expression::ite(
+                is_mmio.clone(),
+                address.clone(),
+                BitVector::word_constant(0),
+            )?),
+            Instruction::observable(Expression::ite(
+                is_mmio,
+                expr.clone(),
+                BitVector::constant_u64(0, bit_width),
+            )?),
+        ])
+    }
+
+    fn model_device_accesses(&self, block: &mut Block) -> Result<()> {
+        let is_transient = block.is_transient();
+
+        let mut device_accesses = Vec::new();
+        for (index, inst) in block.instructions().iter().enumerate() {
+            let mut instructions = match inst.operation() {
+                Operation::Load {
+                    variable, address, ..
+                } => self.device_load(variable, address)?,
+                Operation::Store { address, expr, .. } if !is_transient => {
+                    self.device_store(address, expr)?
+                }
+                _ => continue,
+            };
+            for device_inst in &mut instructions {
+                device_inst.set_address(inst.address());
+                device_inst.labels_mut().pseudo();
+            }
+            device_accesses.push((index, instructions));
+        }
+
+        for (index, instructions) in device_accesses.into_iter().rev() {
+            for inst in instructions.into_iter().rev() {
+                block.insert_instruction(index + 1, inst)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Transform<ControlFlowGraph> for MemoryMappedIo {
+    fn name(&self) -> &'static str {
+        "MemoryMappedIo"
+    }
+
+    fn description(&self) -> String {
+        "Model loads from and stores to memory-mapped I/O regions".to_string()
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        if self.regions.iter().all(AddressRange::is_empty) {
+            return Ok(());
+        }
+
+        for block in cfg.blocks_mut() {
+            self.model_device_accesses(block)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mmio_loads_are_nondeterministic_and_stores_observable() {
+        // Given: a load and a store in a normal block, a store in a transient block
+        let mut cfg = ControlFlowGraph::new();
+
+        let block = cfg.new_block();
+        block
+            .load(
+                BitVector::variable("x", 32),
+                BitVector::word_variable("a").into(),
+            )
+            .unwrap();
+        block
+            .store(
+                BitVector::word_variable("a").into(),
+                BitVector::variable("x", 32).into(),
+            )
+            .unwrap();
+        let normal_index = block.index();
+
+        let block = cfg.new_block();
+        block.set_transient();
+        block
+            .store(
+                BitVector::word_variable("a").into(),
+                BitVector::variable("x", 32).into(),
+            )
+            .unwrap();
+        let transient_index = block.index();
+
+        cfg.unconditional_edge(normal_index, transient_index)
+            .unwrap();
+        cfg.set_entry(normal_index).unwrap();
+        cfg.set_exit(transient_index).unwrap();
+
+        // When:
+        MemoryMappedIoBuilder::default()
+            .regions(vec![AddressRange::new(0xfee0_0000, 0xfee0_1000)])
+            .build()
+            .unwrap()
+            .transform(&mut cfg)
+            .unwrap();
+
+        // Then:
+        let instructions = cfg.block(normal_index).unwrap().instructions();
+        assert_eq!(instructions.len(), 7);
+        assert!(instructions[0].operation().is_load());
+        assert_eq!(
+            instructions[1].variables_written(),
+            vec![&MemoryMappedIo::device_value_variable(32)]
+        );
+        assert!(matches!(
+            instructions[2].operation(),
+            Operation::Indistinguishable { .. }
+        ));
+        assert_eq!(
+            instructions[3].variables_written(),
+            vec![&BitVector::variable("x", 32)]
+        );
+        assert!(instructions[4].operation().is_store());
+        assert!(instructions[5].is_observable());
+        assert!(instructions[6].is_observable());
+        assert!(instructions
+            .iter()
+            .skip(1)
+            .all(|inst| inst.labels().is_pseudo() || inst.operation().is_store()));
+
+        assert_eq!(cfg.block(transient_index).unwrap().instruction_count(), 1);
+    }
+}
@@ -1,39 +1,63 @@
 use crate::environment;
 use crate::expr;
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+mod barrier_insertion;
+mod cache_replacement;
 mod explicit_effects;
 mod explicit_program_counter;
 mod function_inlining;
+mod index_masking;
+mod init_arguments;
 mod init_global_variables;
+mod init_image_base;
 mod init_memory;
 mod init_stack;
 mod instruction_effects;
 mod loop_unwinding;
+mod memory_mapped_io;
+mod node_splitting;
 mod non_spec_obs_equiv;
+mod observation_model;
 mod observations;
 mod optimization;
+mod predictor_taming;
+mod sibling_thread;
+mod speculative_bounds_check;
 mod ssa_transformation;
 mod trace_observations;
 mod transient_execution;
 
+pub use self::barrier_insertion::{BarrierInsertion, BarrierInsertionBuilder};
+pub use self::cache_replacement::{CacheReplacement, CacheReplacementBuilder};
 pub use self::explicit_effects::{ExplicitEffects, ExplicitEffectsBuilder};
 pub use self::explicit_program_counter::{ExplicitProgramCounter, ExplicitProgramCounterBuilder};
 pub use self::function_inlining::{FunctionInlining, FunctionInliningBuilder};
+pub use self::index_masking::{IndexMasking, IndexMaskingBuilder};
+pub use self::init_arguments::{InitArguments, InitArgumentsBuilder};
 pub use self::init_global_variables::{InitGlobalVariables, InitGlobalVariablesBuilder};
+pub use self::init_image_base::{InitImageBase, InitImageBaseBuilder};
 pub use self::init_memory::{InitMemory, InitMemoryBuilder};
 pub use self::init_stack::{InitStack, InitStackBuilder};
 pub use self::instruction_effects::{InstructionEffects, InstructionEffectsBuilder};
 pub use self::loop_unwinding::{LoopUnwinding, LoopUnwindingBuilder};
+pub use self::memory_mapped_io::{MemoryMappedIo, MemoryMappedIoBuilder};
+pub use self::node_splitting::{NodeSplitting, NodeSplittingBuilder};
 pub use self::non_spec_obs_equiv::{NonSpecObsEquivalence, NonSpecObsEquivalenceBuilder};
+pub use self::observation_model::{
+    default_observations, observation_model, ComponentsModel, ObservationModel, ProgramCounterModel,
+};
 pub use self::observations::{Observations, ObservationsBuilder};
 pub use self::optimization::Optimizer;
+pub use self::predictor_taming::{PredictorTaming, PredictorTamingBuilder};
+pub use self::sibling_thread::{SiblingThread, SiblingThreadBuilder};
+pub use self::speculative_bounds_check::{SpeculativeBoundsCheck, SpeculativeBoundsCheckBuilder};
 pub use self::ssa_transformation::{SSAForm, SSATransformation};
-pub use self::trace_observations::{TraceObservations, TraceObservationsBuilder};
+pub use self::trace_observations::{TraceEncoding, TraceObservations, TraceObservationsBuilder};
 pub use self::transient_execution::{TransientExecution, TransientExecutionBuilder};
 
-use crate::error::Result;
-use crate::hir::{Block, ControlFlowGraph, Function, InlinedProgram, Instruction};
+use crate::error::{Error, Result};
+use crate::hir::{Block, ControlFlowGraph, Function, InlinedProgram, Instruction, Origin};
 use crate::ir::Transform;
 
 impl<T: Transform<Instruction>> Transform<Block> for T {
@@ -98,99 +122,229 @@ impl<T: Transform<ControlFlowGraph>> Transform<InlinedProgram> for T {
     fn transform(&self, program: &mut InlinedProgram) -> Result<()> {
         let cfg = program.control_flow_graph_mut();
         self.transform(cfg)?;
+        record_origin_of_pseudo_instructions(cfg, self.name());
         Ok(())
     }
 }
 
+/// Pseudo instructions are only added by transformations, therefore each pseudo instruction
+/// without origin must have been added by the transformation which was just applied.
+fn record_origin_of_pseudo_instructions(cfg: &mut ControlFlowGraph, transform: &str) {
+    for block in cfg.blocks_mut() {
+        for inst in block.instructions_mut() {
+            if inst.labels().is_pseudo() && inst.origins().is_empty() {
+                inst.add_origin(Origin::Transform(transform.to_owned()));
+            }
+        }
+    }
+}
+
+/// Creates the transformations given by the environment, observing according to the built-in model
+/// selected by `analysis.model`.
 pub fn create_transformations(
     env: &environment::Environment,
 ) -> Result<Vec<Box<dyn Transform<InlinedProgram>>>> {
-    let mut steps: Vec<Box<dyn Transform<InlinedProgram>>> = vec![
-        Box::new(loop_unwinding(env)),
-        Box::new(instruction_effects(env)),
-    ];
+    create_transformations_with(env, observation_model(env)?.as_ref())
+}
 
-    if env.analysis.check != environment::Check::OnlyNormalExecutionLeaks {
-        steps.push(Box::new(transient_execution(env)));
+/// Creates the transformations given by the environment, observing according to `model`.
+pub fn create_transformations_with(
+    env: &environment::Environment,
+    model: &dyn ObservationModel,
+) -> Result<Vec<Box<dyn Transform<InlinedProgram>>>> {
+    let mut steps: Vec<Box<dyn Transform<InlinedProgram>>> = Vec::new();
+
+    if env.analysis.split_irreducible {
+        steps.push(Box::new(
+            NodeSplittingBuilder::default()
+                .keep_irreducible(env.analysis.allow_irreducible)
+                .build()
+                .unwrap(),
+        ));
     }
 
-    let mut observable_variables = HashSet::new();
+    steps.push(Box::new(loop_unwinding(env)));
+    steps.push(Box::new(instruction_effects(env)));
 
-    match env.analysis.model {
-        environment::Model::Components => {
-            steps.push(Box::new(ExplicitEffects::default()));
+    let observable_variables = observable_variables(env, model);
 
-            if env.architecture.cache {
-                observable_variables.insert(expr::Cache::variable());
-            }
-            if env.architecture.branch_target_buffer {
-                observable_variables.insert(expr::BranchTargetBuffer::variable());
-            }
-            if env.architecture.pattern_history_table {
-                observable_variables.insert(expr::PatternHistoryTable::variable());
-            }
+    if env.analysis.check != environment::Check::OnlyNormalExecutionLeaks {
+        steps.push(Box::new(transient_execution(env, &observable_variables)));
 
-            steps.push(observations(env, &observable_variables)?);
+        if !env.analysis.predictor_constraints.is_unconstrained() {
+            steps.push(Box::new(predictor_taming(env)));
         }
-        environment::Model::ProgramCounter => {
-            steps.push(Box::new(explicit_program_counter(env)));
+    }
 
-            observable_variables.insert(ExplicitProgramCounter::pc_variable());
-            observable_variables.insert(ExplicitProgramCounter::address_variable());
+    if !env.setup.mmio.is_empty() {
+        steps.push(Box::new(memory_mapped_io(env)?));
+    }
 
-            steps.push(observations_pc(env, &observable_variables)?);
-        }
+    if env.analysis.check == environment::Check::SpeculativeMemorySafety {
+        steps.push(Box::new(speculative_bounds_check(env)?));
+    } else {
+        steps.extend(model.transformations(env, &observable_variables)?);
     }
 
-    steps.push(Box::new(init_memory(env)));
+    steps.push(Box::new(init_memory(env)?));
 
     if env.setup.init_stack {
         steps.push(Box::new(InitStack::default()));
     }
 
+    if env
+        .architecture
+        .observe
+        .branch_targets_relative_to_image_base
+    {
+        // Defines the code pointer registers, such that their security level doesn't apply
+        steps.push(Box::new(init_image_base(env)?));
+    }
+
     steps.push(Box::new(init_global_variables(env, &observable_variables)));
 
-    if env.analysis.check == environment::Check::OnlyTransientExecutionLeaks {
+    if !stack_arguments(env).is_empty() {
+        // Stack slots are addressed by the stack pointer, which is initialized by the steps above
+        steps.push(Box::new(init_arguments(env)));
+    }
+
+    if matches!(
+        env.analysis.check,
+        environment::Check::OnlyTransientExecutionLeaks
+            | environment::Check::SpeculativeInterference
+    ) {
         steps.push(Box::new(NonSpecObsEquivalence::default()));
     }
 
     steps.push(Box::new(SSATransformation::new(SSAForm::Pruned)));
 
-    match env.optimization_level {
-        environment::OptimizationLevel::Disabled => {}
-        environment::OptimizationLevel::Basic => {
-            steps.push(Box::new(Optimizer::basic()));
-        }
-        environment::OptimizationLevel::Full => {
-            steps.push(Box::new(Optimizer::full()));
+    let optimizer = match env.optimization_level {
+        environment::OptimizationLevel::Disabled => None,
+        environment::OptimizationLevel::Basic => Some(Optimizer::basic()),
+        environment::OptimizationLevel::Full => Some(Optimizer::full()),
+    };
+    if let Some(optimizer) = optimizer {
+        if env.analysis.observe == environment::Observe::Parallel {
+            steps.push(Box::new(optimizer.with_redundant_observation_elimination()));
+        } else {
+            steps.push(Box::new(optimizer));
         }
     }
 
     Ok(steps)
 }
 
+/// Builds the copy of the transient graph which is inserted for the speculating instruction at `address`,
+/// by applying all transformations which precede the transient execution to `program`.
+pub fn transient_copy(
+    env: &environment::Environment,
+    program: &InlinedProgram,
+    address: u64,
+) -> Result<ControlFlowGraph> {
+    if env.analysis.check == environment::Check::OnlyNormalExecutionLeaks {
+        return Err("Transient execution isn't modeled for `check: only_normal_leaks`".into());
+    }
+
+    let model = observation_model(env)?;
+    let transient_execution = transient_execution(env, &observable_variables(env, model.as_ref()));
+    let transient_execution_name = Transform::<ControlFlowGraph>::name(&transient_execution);
+
+    let mut program = program.clone();
+    for step in create_transformations(env)?
+        .iter()
+        .take_while(|step| step.name() != transient_execution_name)
+    {
+        step.transform(&mut program)?;
+    }
+
+    transient_execution
+        .transient_copy(program.control_flow_graph(), address)?
+        .ok_or_else(|| format!("No transient execution starts at 0x{:X}", address).into())
+}
+
 fn loop_unwinding(env: &environment::Environment) -> LoopUnwinding {
     LoopUnwindingBuilder::default()
         .default_unwinding_bound(env.analysis.unwind)
         .unwinding_bound(env.analysis.unwind_loop.clone())
         .unwinding_guard(env.analysis.unwinding_guard)
+        .infer_unwinding_bound(env.analysis.infer_unwind)
+        .allow_irreducible(env.analysis.allow_irreducible)
+        .max_blocks(env.analysis.max_blocks)
         .build()
         .unwrap()
 }
 
 fn instruction_effects(env: &environment::Environment) -> InstructionEffects {
+    let observe = &env.architecture.observe;
     InstructionEffectsBuilder::default()
-        .model_cache_effects(env.architecture.cache)
-        .model_btb_effects(env.architecture.branch_target_buffer)
-        .model_pht_effects(env.architecture.pattern_history_table)
+        .model_cache_load_effects(env.architecture.cache && observe.loads)
+        .model_cache_store_effects(env.architecture.cache && observe.stores)
+        .model_btb_effects(env.architecture.branch_target_buffer && observe.branch_targets)
+        .model_pht_effects(env.architecture.pattern_history_table && observe.branch_conditions)
+        .model_mdp_effects(env.architecture.memory_dependence_predictor)
+        .cache_address_alignment(env.architecture.cache_address_alignment())
+        .cache_line_size(env.architecture.cache_line_size)
         .build()
         .unwrap()
 }
 
-fn transient_execution(env: &environment::Environment) -> TransientExecution {
+fn cache_replacement(env: &environment::Environment) -> Result<CacheReplacement> {
+    let arch = &env.architecture;
+    let line_size = arch
+        .cache_address_alignment()
+        .ok_or("Cache replacement requires 'line' or 'page' cache granularity")?;
+    if arch.cache_sets == 0 || arch.cache_ways == 0 {
+        return Err("Cache must have at least one set and one way".into());
+    }
+    if arch.cache_replacement == environment::CacheReplacementPolicy::TreePlru
+        && !arch.cache_ways.is_power_of_two()
+    {
+        return Err("Tree-PLRU requires the number of cache ways to be a power of two".into());
+    }
+
+    Ok(CacheReplacementBuilder::default()
+        .policy(arch.cache_replacement)
+        .line_size(line_size)
+        .sets(arch.cache_sets)
+        .ways(arch.cache_ways)
+        .build()
+        .unwrap())
+}
+
+fn sibling_thread(env: &environment::Environment) -> Result<SiblingThread> {
+    if env.architecture.cache_replacement != environment::CacheReplacementPolicy::None {
+        return Err("Sibling thread evictions require cache replacement 'none'".into());
+    }
+
+    Ok(SiblingThreadBuilder::default()
+        .evictions(env.architecture.sibling_thread_evictions)
+        .build()
+        .unwrap())
+}
+
+/// Variables holding the state observed by the attacker (according to the observation model).
+fn observable_variables(
+    env: &environment::Environment,
+    model: &dyn ObservationModel,
+) -> HashSet<expr::Variable> {
+    if env.analysis.check == environment::Check::SpeculativeMemorySafety {
+        // Memory safety doesn't depend on any observation
+        return HashSet::new();
+    }
+    model.observable_variables(env)
+}
+
+fn transient_execution(
+    env: &environment::Environment,
+    observable_variables: &HashSet<expr::Variable>,
+) -> TransientExecution {
+    let observe_resolution = env.analysis.check == environment::Check::SpeculativeInterference;
+
+    // Observing the resolution requires intermediate resolve, as otherwise each transient execution
+    // runs until the max. speculation window is reached
     let intermediate_resolve = match env.analysis.observe {
         environment::Observe::Sequential | environment::Observe::Full => true,
-        environment::Observe::Parallel | environment::Observe::Trace => false,
+        environment::Observe::Parallel | environment::Observe::Trace => observe_resolution,
     };
 
     let mut ignored_stl_registers = HashSet::new();
@@ -204,6 +358,27 @@ fn transient_execution(env: &environment::Environment) -> TransientExecution {
         .predictor_strategy(env.analysis.predictor_strategy)
         .speculation_window(env.architecture.speculation_window)
         .intermediate_resolve(intermediate_resolve)
+        .share_transient_copies(env.analysis.share_transient_copies)
+        .parametric_speculation_window(env.architecture.parametric_speculation_window)
+        .max_blocks(env.analysis.max_blocks)
+        .nested_speculation_depth(env.analysis.nested_speculation_depth)
+        .memory_dependence_predictor(env.architecture.memory_dependence_predictor)
+        .spectre_bcbs(env.analysis.spectre_bcbs)
+        .prune_constant_branches(env.analysis.prune_constant_transient_branches)
+        .minimize_rollback(env.analysis.minimize_rollback)
+        .observable_variables(observable_variables.clone())
+        .observe_resolution(observe_resolution)
+        .build()
+        .unwrap()
+}
+
+fn predictor_taming(env: &environment::Environment) -> PredictorTaming {
+    let constraints = &env.analysis.predictor_constraints;
+    PredictorTamingBuilder::default()
+        .always_mispredict(constraints.always_mispredict)
+        .max_mispredictions(constraints.max_mispredictions)
+        .never_speculate_at(constraints.never_speculate_at.clone())
+        .single_speculation(constraints.single_speculation)
         .build()
         .unwrap()
 }
@@ -219,6 +394,7 @@ fn observations(
                 .observe_variable_writes(false)
                 .observe_at_control_flow_joins(false)
                 .observe_at_end_of_program(true)
+                .observe_at(env.analysis.observe_at.clone())
                 .build()
                 .unwrap(),
         )),
@@ -228,24 +404,34 @@ fn observations(
                 .observe_variable_writes(true)
                 .observe_at_control_flow_joins(true)
                 .observe_at_end_of_program(true)
+                .observe_at(env.analysis.observe_at.clone())
                 .build()
                 .unwrap(),
         )),
-        environment::Observe::Trace => {
-            if env.solver == environment::Solver::Yices2 {
-                // Requires theory of lists and user-defined datatypes
-                return Err("Trace observe with Yices2 solver is currently not supported".into());
-            }
-            Ok(Box::new(
-                TraceObservationsBuilder::default()
-                    .observable_variables(observable_variables.clone())
-                    .build()
-                    .unwrap(),
-            ))
-        }
+        environment::Observe::Trace => Ok(Box::new(trace_observations(env, observable_variables))),
     }
 }
 
+fn trace_observations(
+    env: &environment::Environment,
+    observable_variables: &HashSet<expr::Variable>,
+) -> TraceObservations {
+    // Yices2 and the native Z3 backend don't support lists (user-defined datatypes)
+    let encoding = match (env.solver, env.solver_backend) {
+        _ if env.analysis.bounded_trace => TraceEncoding::Bounded,
+        (_, environment::SolverBackend::Native) | (environment::Solver::Yices2, _) => {
+            TraceEncoding::Array
+        }
+        _ => TraceEncoding::List,
+    };
+
+    TraceObservationsBuilder::default()
+        .observable_variables(observable_variables.clone())
+        .encoding(encoding)
+        .build()
+        .unwrap()
+}
+
 fn observations_pc(
     env: &environment::Environment,
     observable_variables: &HashSet<expr::Variable>,
@@ -260,51 +446,127 @@ fn observations_pc(
                 .observe_variable_writes(true)
                 .observe_at_control_flow_joins(false)
                 .observe_at_end_of_program(false)
+                .observe_at(env.analysis.observe_at.clone())
                 .build()
                 .unwrap(),
         )),
-        environment::Observe::Trace => {
-            if env.solver == environment::Solver::Yices2 {
-                // Requires theory of lists and user-defined datatypes
-                return Err("Trace observe with Yices2 solver is currently not supported".into());
-            }
-            Ok(Box::new(
-                TraceObservationsBuilder::default()
-                    .observable_variables(observable_variables.clone())
-                    .build()
-                    .unwrap(),
-            ))
-        }
+        environment::Observe::Trace => Ok(Box::new(trace_observations(env, observable_variables))),
     }
 }
 
 fn explicit_program_counter(env: &environment::Environment) -> ExplicitProgramCounter {
+    let observe = &env.architecture.observe;
     ExplicitProgramCounterBuilder::default()
         .observe_program_counter(
-            env.architecture.branch_target_buffer || env.architecture.pattern_history_table,
+            (env.architecture.branch_target_buffer && observe.branch_targets)
+                || (env.architecture.pattern_history_table && observe.branch_conditions),
         )
-        .observe_memory_loads(env.architecture.cache)
+        .observe_memory_loads(env.architecture.cache && observe.loads)
+        .observe_memory_stores(env.architecture.cache && observe.stores)
+        .memory_address_alignment(env.architecture.cache_address_alignment())
+        .relative_to_image_base(observe.branch_targets_relative_to_image_base)
         .build()
         .unwrap()
 }
 
+fn init_image_base(env: &environment::Environment) -> Result<InitImageBase> {
+    if env.setup.code_pointer_registers.is_empty() {
+        return Err(
+            "`branch_targets_relative_to_image_base` requires `setup.code_pointer_registers`"
+                .into(),
+        );
+    }
+
+    Ok(InitImageBaseBuilder::default()
+        .code_pointer_registers(env.setup.code_pointer_registers.iter().cloned().collect())
+        .build()
+        .unwrap())
+}
+
+fn speculative_bounds_check(env: &environment::Environment) -> Result<SpeculativeBoundsCheck> {
+    Ok(SpeculativeBoundsCheckBuilder::default()
+        .valid_memory(sorted_address_ranges(&env.analysis.valid_memory)?)
+        .build()
+        .unwrap())
+}
+
+fn memory_mapped_io(env: &environment::Environment) -> Result<MemoryMappedIo> {
+    Ok(MemoryMappedIoBuilder::default()
+        .regions(sorted_address_ranges(&env.setup.mmio)?)
+        .build()
+        .unwrap())
+}
+
 fn init_global_variables(
     env: &environment::Environment,
     observable_variables: &HashSet<expr::Variable>,
 ) -> InitGlobalVariables {
     let mut low_security_variables = env.policy.registers.low.clone();
     low_security_variables.insert(expr::Predictor::variable().name().to_owned());
+    low_security_variables.insert(
+        PredictorTaming::speculation_source_variable()
+            .name()
+            .to_owned(),
+    );
+    if env.architecture.memory_dependence_predictor {
+        // The initial predictor state is trained by the attacker
+        low_security_variables.insert(
+            expr::MemoryDependencePredictor::variable()
+                .name()
+                .to_owned(),
+        );
+        low_security_variables.insert(
+            expr::MemoryDependencePredictor::store_locations_variable()
+                .name()
+                .to_owned(),
+        );
+    }
     for var in observable_variables {
         low_security_variables.insert(var.name().to_owned());
     }
+    let replacement_state = if env.architecture.cache
+        && env.architecture.cache_replacement != environment::CacheReplacementPolicy::None
+    {
+        cache_replacement(env)
+            .map(|replacement| replacement.state_variables())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    for var in &replacement_state {
+        // The initial replacement state is known to (or primed by) the attacker
+        low_security_variables.insert(var.name().to_owned());
+    }
 
-    let high_security_variables = env.policy.registers.high.clone();
+    let mut high_security_variables = env.policy.registers.high.clone();
+
+    // Explicitly given register levels take precedence over the argument levels
+    let argument_registers = env.analysis.calling_convention.argument_registers();
+    for (register, level) in argument_registers.iter().zip(&env.policy.arguments) {
+        let register = register.to_string();
+        match level {
+            environment::SecurityLevel::Low if !env.policy.registers.high.contains(&register) => {
+                low_security_variables.insert(register);
+            }
+            environment::SecurityLevel::High if !env.policy.registers.low.contains(&register) => {
+                high_security_variables.insert(register);
+            }
+            _ => {}
+        }
+    }
 
     let mut initial_variable_value = HashMap::new();
     if env.analysis.start_with_empty_cache {
         let empty_cache =
             expr::Expression::constant(expr::CacheValue::empty().into(), expr::Sort::cache());
         initial_variable_value.insert(expr::Cache::variable().name().to_owned(), empty_cache);
+        for var in &replacement_state {
+            if var.sort().is_boolean() {
+                // All ways are invalid (and PLRU trees point to the leftmost way)
+                initial_variable_value
+                    .insert(var.name().to_owned(), expr::Boolean::constant(false));
+            }
+        }
     }
     for (reg, &value) in &env.setup.register_content {
         initial_variable_value.insert(reg.clone(), expr::BitVector::word_constant(value));
@@ -322,10 +584,29 @@ fn init_global_variables(
         .unwrap()
 }
 
-fn init_memory(env: &environment::Environment) -> InitMemory {
-    let low_security_memory_addresses = address_ranges_to_addresses(&env.policy.memory.low);
-    let high_security_memory_addresses = address_ranges_to_addresses(&env.policy.memory.high);
+/// Security levels of the program entry's arguments passed on the stack, by stack slot offset.
+fn stack_arguments(env: &environment::Environment) -> BTreeMap<u64, environment::SecurityLevel> {
+    let calling_convention = env.analysis.calling_convention;
+    env.policy
+        .arguments
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &level)| {
+            let offset = calling_convention.stack_argument_offset(index)?;
+            Some((offset, level))
+        })
+        .collect()
+}
 
+fn init_arguments(env: &environment::Environment) -> InitArguments {
+    InitArgumentsBuilder::default()
+        .stack_pointer(env.analysis.calling_convention.stack_pointer().to_owned())
+        .stack_arguments(stack_arguments(env))
+        .build()
+        .unwrap()
+}
+
+fn init_memory(env: &environment::Environment) -> Result<InitMemory> {
     let mut initial_memory_content = BTreeMap::new();
     for (&address, bytes) in &env.setup.memory_content {
         for (i, &byte) in bytes.iter().enumerate() {
@@ -333,25 +614,218 @@ fn init_memory(env: &environment::Environment) -> InitMemory {
         }
     }
 
-    InitMemoryBuilder::default()
+    Ok(InitMemoryBuilder::default()
         .default_memory_security_level(env.policy.memory.default_level)
-        .low_security_memory_addresses(low_security_memory_addresses)
-        .high_security_memory_addresses(high_security_memory_addresses)
+        .low_security_memory(sorted_address_ranges(&env.policy.memory.low)?)
+        .high_security_memory(sorted_address_ranges(&env.policy.memory.high)?)
         .initial_memory_content(initial_memory_content)
+        .memory_cell_width(env.architecture.memory_cell_width)
         .build()
-        .unwrap()
+        .unwrap())
 }
 
-fn address_ranges_to_addresses(
-    address_ranges: &HashSet<environment::AddressRange>,
-) -> BTreeSet<u64> {
-    let mut addresses = BTreeSet::new();
+/// Fails early if the CFG would grow beyond `max_blocks` by adding `additional_blocks` to it,
+/// e.g. by unwinding loops or adding transient execution.
+pub(crate) fn check_block_budget(
+    cfg: &ControlFlowGraph,
+    additional_blocks: usize,
+    max_blocks: Option<usize>,
+    what: &str,
+) -> Result<()> {
+    let limit = match max_blocks {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    let measured = cfg.blocks().len() + additional_blocks;
+    if measured > limit {
+        return Err(Error::BudgetExceeded {
+            what: what.to_owned(),
+            budget: "analysis.max_blocks",
+            measured,
+            limit,
+            hint: "consider a smaller `analysis.unwind` or `architecture.speculation_window`",
+        });
+    }
+    Ok(())
+}
 
-    address_ranges.iter().for_each(|range| {
-        for addr in range.addresses() {
-            addresses.insert(addr);
-        }
-    });
+fn sorted_address_ranges(
+    regions: &HashSet<environment::MemoryRegion>,
+) -> Result<Vec<environment::AddressRange>> {
+    let mut ranges = Vec::new();
+    for region in regions {
+        let range = region
+            .address_range()
+            .ok_or_else(|| format!("Memory region '{}' is not resolved", region))?;
+        ranges.push(range);
+    }
+    ranges.sort_by_key(|range| (range.start(), range.end()));
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::BitVector;
+    use crate::hir::Effect;
+
+    /// Returns the number of observed memory addresses and program counters
+    /// when adding the explicit program counter to a block with a load, a store and both kinds of branches.
+    fn observations(env: &environment::Environment) -> (usize, usize) {
+        let x: expr::Expression = BitVector::word_variable("x").into();
+        let mut block = Block::new(0);
+        block
+            .load(BitVector::word_variable("v"), x.clone())
+            .unwrap();
+        block.store(x.clone(), x.clone()).unwrap();
+        block.branch(x.clone()).unwrap();
+        block
+            .conditional_branch(expr::Boolean::variable("c").into(), x)
+            .unwrap();
+
+        Transform::<Block>::transform(&explicit_program_counter(env), &mut block).unwrap();
+
+        let count = |variable: expr::Variable| {
+            block
+                .variables_written()
+                .into_iter()
+                .filter(|var| **var == variable)
+                .count()
+        };
+        (
+            count(ExplicitProgramCounter::address_variable()),
+            count(ExplicitProgramCounter::pc_variable()),
+        )
+    }
+
+    /// Returns the effects of a conditional branch (at address 0).
+    fn branch_effects(env: &environment::Environment) -> Vec<Effect> {
+        let mut branch = Instruction::conditional_branch(
+            expr::Boolean::variable("c").into(),
+            BitVector::word_variable("x").into(),
+        )
+        .unwrap();
+        instruction_effects(env).transform(&mut branch).unwrap();
+        branch.effects().to_vec()
+    }
+
+    fn btb_effect() -> Effect {
+        Effect::branch_target(
+            BitVector::word_constant(0),
+            BitVector::word_variable("x").into(),
+        )
+        .only_if(expr::Boolean::variable("c").into())
+    }
+
+    fn pht_effect() -> Effect {
+        Effect::branch_condition(
+            BitVector::word_constant(0),
+            expr::Boolean::variable("c").into(),
+        )
+    }
+
+    fn env_observing(observe: environment::ObservedAccesses) -> environment::Environment {
+        let mut env = environment::Environment::default();
+        env.architecture.observe = observe;
+        env
+    }
+
+    #[test]
+    fn test_all_accesses_are_observed_by_default() {
+        // Given:
+        let env = env_observing(environment::ObservedAccesses::default());
 
-    addresses
+        // When:
+        let (addresses, pcs) = observations(&env);
+
+        // Then:
+        assert_eq!(addresses, 2);
+        assert_eq!(pcs, 2);
+        assert_eq!(branch_effects(&env), vec![btb_effect(), pht_effect()]);
+    }
+
+    #[test]
+    fn test_unobserved_loads() {
+        // Given:
+        let env = env_observing(environment::ObservedAccesses {
+            loads: false,
+            ..Default::default()
+        });
+
+        // When:
+        let (addresses, pcs) = observations(&env);
+
+        // Then: only the store address is observed
+        assert_eq!(addresses, 1);
+        assert_eq!(pcs, 2);
+    }
+
+    #[test]
+    fn test_unobserved_stores() {
+        // Given:
+        let env = env_observing(environment::ObservedAccesses {
+            stores: false,
+            ..Default::default()
+        });
+
+        // When:
+        let (addresses, pcs) = observations(&env);
+
+        // Then: only the load address is observed
+        assert_eq!(addresses, 1);
+        assert_eq!(pcs, 2);
+    }
+
+    #[test]
+    fn test_unobserved_branch_targets_are_still_observed_through_the_pht() {
+        // Given:
+        let env = env_observing(environment::ObservedAccesses {
+            branch_targets: false,
+            ..Default::default()
+        });
+
+        // When:
+        let (addresses, pcs) = observations(&env);
+
+        // Then:
+        assert_eq!(addresses, 2);
+        assert_eq!(pcs, 2);
+        assert_eq!(branch_effects(&env), vec![pht_effect()]);
+    }
+
+    #[test]
+    fn test_unobserved_branch_conditions_are_still_observed_through_the_btb() {
+        // Given:
+        let env = env_observing(environment::ObservedAccesses {
+            branch_conditions: false,
+            ..Default::default()
+        });
+
+        // When:
+        let (addresses, pcs) = observations(&env);
+
+        // Then:
+        assert_eq!(addresses, 2);
+        assert_eq!(pcs, 2);
+        assert_eq!(branch_effects(&env), vec![btb_effect()]);
+    }
+
+    #[test]
+    fn test_only_write_traffic_is_observed() {
+        // Given: e.g. an attacker snooping on the memory bus
+        let env = env_observing(environment::ObservedAccesses {
+            loads: false,
+            branch_targets: false,
+            branch_conditions: false,
+            ..Default::default()
+        });
+
+        // When:
+        let (addresses, pcs) = observations(&env);
+
+        // Then: the program counter isn't observed, even though BTB and PHT are modeled
+        assert_eq!(addresses, 1);
+        assert_eq!(pcs, 0);
+        assert!(branch_effects(&env).is_empty());
+    }
 }
@@ -0,0 +1,121 @@
+use crate::error::{Error, Result};
+use crate::hir::analysis;
+use crate::hir::ControlFlowGraph;
+use crate::ir::Transform;
+use std::collections::BTreeSet;
+
+/// Converts irreducible CFGs into reducible ones by node splitting.
+///
+/// For each edge entering a loop at a block other than its header, the entered block is duplicated
+/// and the edge is redirected to the copy, such that all remaining loop entries go through a single header.
+/// Splitting may grow the CFG exponentially, therefore it fails after `max_splits` duplicated blocks.
+#[derive(Builder, Debug)]
+pub struct NodeSplitting {
+    #[builder(default = "1000")]
+    max_splits: usize,
+    /// Keep the remaining irreducible loops instead of failing once `max_splits` is reached
+    #[builder(default)]
+    keep_irreducible: bool,
+}
+
+impl Default for NodeSplitting {
+    fn default() -> Self {
+        Self {
+            max_splits: 1000,
+            keep_irreducible: false,
+        }
+    }
+}
+
+impl Transform<ControlFlowGraph> for NodeSplitting {
+    fn name(&self) -> &'static str {
+        "NodeSplitting"
+    }
+
+    fn description(&self) -> String {
+        "Make irreducible loops reducible by node splitting".to_string()
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        let mut splits = 0;
+        loop {
+            let irreducible_edges = analysis::irreducible_edges(cfg)?;
+            let (head, tail) = match irreducible_edges.iter().next() {
+                Some(&edge) => edge,
+                None => return Ok(()),
+            };
+
+            if splits >= self.max_splits {
+                if self.keep_irreducible {
+                    return Ok(());
+                }
+                let entries: BTreeSet<usize> =
+                    irreducible_edges.iter().map(|&(_, tail)| tail).collect();
+                return Err(Error::IrreducibleControlFlowGraph(
+                    entries.into_iter().collect(),
+                ));
+            }
+
+            let copies = cfg.duplicate_blocks(&vec![tail].into_iter().collect())?;
+            cfg.rewire_edge(head, tail, head, copies[&tail])?;
+            splits += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_loop_with_two_entries() {
+        // Given: entry -> a, entry -> b, a <-> b
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        let a = cfg.new_block().index();
+        let b = cfg.new_block().index();
+        let exit = cfg.new_block().index();
+        cfg.unconditional_edge(entry, a).unwrap();
+        cfg.unconditional_edge(entry, b).unwrap();
+        cfg.unconditional_edge(a, b).unwrap();
+        cfg.unconditional_edge(b, a).unwrap();
+        cfg.unconditional_edge(b, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+
+        // When:
+        NodeSplitting::default().transform(&mut cfg).unwrap();
+
+        // Then: one block is duplicated and the CFG is reducible
+        assert_eq!(cfg.blocks().len(), 5);
+        assert!(analysis::irreducible_edges(&cfg).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_fails_when_exceeding_max_splits() {
+        // Given: entry -> a, entry -> b, a <-> b
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        let a = cfg.new_block().index();
+        let b = cfg.new_block().index();
+        cfg.unconditional_edge(entry, a).unwrap();
+        cfg.unconditional_edge(entry, b).unwrap();
+        cfg.unconditional_edge(a, b).unwrap();
+        cfg.unconditional_edge(b, a).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(b).unwrap();
+
+        // When:
+        let result = NodeSplittingBuilder::default()
+            .max_splits(0)
+            .build()
+            .unwrap()
+            .transform(&mut cfg);
+
+        // Then:
+        assert!(matches!(
+            result,
+            Err(Error::IrreducibleControlFlowGraph(blocks)) if blocks.len() == 1
+        ));
+    }
+}
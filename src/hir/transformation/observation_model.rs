@@ -0,0 +1,231 @@
+//! Observation Models
+//!
+//! An observation model defines what the attacker observes: which state is made explicit
+//! (and where the observations of it are inserted into the program) and which variables hold
+//! the observed state.
+//! Besides the built-in components and program counter models, library code can pass custom models
+//! to the `HirTransform` stage (`analysis.model: {custom: <name>}` requires such a model).
+
+use crate::environment::{CacheReplacementPolicy, Environment, Model};
+use crate::error::{Error, Result};
+use crate::expr::{self, Variable};
+use crate::hir::InlinedProgram;
+use crate::ir::Transform;
+use std::collections::HashSet;
+
+use super::{ExplicitEffects, ExplicitProgramCounter};
+
+pub trait ObservationModel {
+    /// Name of the model.
+    fn name(&self) -> &str;
+
+    /// Variables holding the observed state (the observed expressions).
+    ///
+    /// They are known to the attacker and therefore low in the initial state.
+    fn observable_variables(&self, env: &Environment) -> HashSet<Variable>;
+
+    /// Transformations which make the observed state explicit and insert the observations of
+    /// `observable_variables` into the program (the insertion points).
+    fn transformations(
+        &self,
+        env: &Environment,
+        observable_variables: &HashSet<Variable>,
+    ) -> Result<Vec<Box<dyn Transform<InlinedProgram>>>>;
+}
+
+/// Observes microarchitectural components like cache, branch-target buffer, ...
+pub struct ComponentsModel {}
+
+impl ObservationModel for ComponentsModel {
+    fn name(&self) -> &str {
+        "components"
+    }
+
+    fn observable_variables(&self, env: &Environment) -> HashSet<Variable> {
+        let mut observable_variables = HashSet::new();
+        if env.architecture.cache {
+            observable_variables.insert(expr::Cache::variable());
+        }
+        if env.architecture.branch_target_buffer {
+            observable_variables.insert(expr::BranchTargetBuffer::variable());
+        }
+        if env.architecture.pattern_history_table {
+            observable_variables.insert(expr::PatternHistoryTable::variable());
+        }
+        observable_variables
+    }
+
+    fn transformations(
+        &self,
+        env: &Environment,
+        observable_variables: &HashSet<Variable>,
+    ) -> Result<Vec<Box<dyn Transform<InlinedProgram>>>> {
+        if env
+            .architecture
+            .observe
+            .branch_targets_relative_to_image_base
+        {
+            return Err(
+                "`branch_targets_relative_to_image_base` is only supported by the `pc` model"
+                    .into(),
+            );
+        }
+
+        let mut steps: Vec<Box<dyn Transform<InlinedProgram>>> = Vec::new();
+
+        steps.push(Box::new(ExplicitEffects::default()));
+
+        if env.architecture.cache
+            && env.architecture.cache_replacement != CacheReplacementPolicy::None
+        {
+            steps.push(Box::new(super::cache_replacement(env)?));
+        }
+
+        steps.push(default_observations(env, observable_variables)?);
+
+        if env.architecture.cache && env.architecture.sibling_thread_evictions > 0 {
+            steps.push(Box::new(super::sibling_thread(env)?));
+        }
+
+        Ok(steps)
+    }
+}
+
+/// Observes the program counter and memory addresses.
+pub struct ProgramCounterModel {}
+
+impl ObservationModel for ProgramCounterModel {
+    fn name(&self) -> &str {
+        "pc"
+    }
+
+    fn observable_variables(&self, _env: &Environment) -> HashSet<Variable> {
+        let mut observable_variables = HashSet::new();
+        observable_variables.insert(ExplicitProgramCounter::pc_variable());
+        observable_variables.insert(ExplicitProgramCounter::address_variable());
+        observable_variables
+    }
+
+    fn transformations(
+        &self,
+        env: &Environment,
+        observable_variables: &HashSet<Variable>,
+    ) -> Result<Vec<Box<dyn Transform<InlinedProgram>>>> {
+        Ok(vec![
+            Box::new(super::explicit_program_counter(env)),
+            super::observations_pc(env, observable_variables)?,
+        ])
+    }
+}
+
+/// Observations of `observable_variables` as configured by `analysis.observe`.
+///
+/// Observes at writes of the variables, at control-flow joins and at the end of the program
+/// (depending on the observe mode), which is a reasonable choice for most custom models.
+pub fn default_observations(
+    env: &Environment,
+    observable_variables: &HashSet<Variable>,
+) -> Result<Box<dyn Transform<InlinedProgram>>> {
+    super::observations(env, observable_variables)
+}
+
+/// Returns the built-in observation model selected by `analysis.model`.
+///
+/// Custom models aren't known to the environment, they have to be passed explicitly
+/// (see `create_transformations_with`).
+pub fn observation_model(env: &Environment) -> Result<Box<dyn ObservationModel>> {
+    match &env.analysis.model {
+        Model::Components => Ok(Box::new(ComponentsModel {})),
+        Model::ProgramCounter => Ok(Box::new(ProgramCounterModel {})),
+        Model::Custom(name) => Err(Error::CustomObservationModel(name.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::BitVector;
+
+    /// Observes only the cache set index of memory accesses.
+    struct CacheSetModel {}
+
+    impl ObservationModel for CacheSetModel {
+        fn name(&self) -> &str {
+            "cache_sets"
+        }
+
+        fn observable_variables(&self, _env: &Environment) -> HashSet<Variable> {
+            vec![BitVector::word_variable("_cache_set")]
+                .into_iter()
+                .collect()
+        }
+
+        fn transformations(
+            &self,
+            env: &Environment,
+            observable_variables: &HashSet<Variable>,
+        ) -> Result<Vec<Box<dyn Transform<InlinedProgram>>>> {
+            Ok(vec![default_observations(env, observable_variables)?])
+        }
+    }
+
+    #[test]
+    fn test_custom_observation_model_is_passed_explicitly() {
+        // Given
+        let mut env = Environment::default();
+        env.analysis.model = Model::Custom("cache_sets".to_owned());
+
+        // When
+        let steps = super::super::create_transformations_with(&env, &CacheSetModel {}).unwrap();
+
+        // Then
+        assert!(steps.iter().any(|step| step.name() == "Observations"));
+    }
+
+    #[test]
+    fn test_custom_observation_model_is_not_known_to_the_environment() {
+        // Given
+        let mut env = Environment::default();
+        env.analysis.model = Model::Custom("cache_sets".to_owned());
+
+        // When
+        let result = observation_model(&env);
+
+        // Then
+        assert!(matches!(result, Err(Error::CustomObservationModel(name)) if name == "cache_sets"));
+    }
+
+    #[test]
+    fn test_components_model_rejects_branch_targets_relative_to_image_base() {
+        // Given
+        let mut env = Environment::default();
+        env.architecture
+            .observe
+            .branch_targets_relative_to_image_base = true;
+
+        // When
+        let result = ComponentsModel {}.transformations(&env, &HashSet::new());
+
+        // Then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_built_in_models_observe_their_components() {
+        // Given
+        let mut env = Environment::default();
+        env.architecture.cache = true;
+        env.architecture.branch_target_buffer = false;
+        env.architecture.pattern_history_table = false;
+
+        // When
+        let components = ComponentsModel {}.observable_variables(&env);
+        let pc = ProgramCounterModel {}.observable_variables(&env);
+
+        // Then
+        assert_eq!(components.len(), 1);
+        assert!(components.contains(&expr::Cache::variable()));
+        assert!(pc.contains(&ExplicitProgramCounter::pc_variable()));
+        assert!(pc.contains(&ExplicitProgramCounter::address_variable()));
+    }
+}
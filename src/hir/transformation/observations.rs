@@ -1,8 +1,8 @@
 use crate::error::Result;
 use crate::expr::Variable;
 use crate::hir::{Block, ControlFlowGraph, Instruction};
-use crate::ir::Transform;
-use std::collections::HashSet;
+use crate::ir::{Provenance, Transform};
+use std::collections::{BTreeSet, HashSet};
 
 #[derive(Default, Builder, Debug)]
 pub struct Observations {
@@ -10,12 +10,15 @@ pub struct Observations {
     observe_variable_writes: bool,
     observe_at_control_flow_joins: bool,
     observe_at_end_of_program: bool,
+    /// Addresses of the instructions in front of which observations are placed
+    #[builder(default)]
+    observe_at: BTreeSet<u64>,
 }
 
 impl Observations {
     fn place_observe_at_variable_writes(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
         for block in cfg.blocks_mut() {
-            let observable_writes: Vec<(usize, Vec<Variable>)> = block
+            let observable_writes: Vec<(usize, Option<u64>, Vec<Variable>)> = block
                 .instructions()
                 .iter()
                 .enumerate()
@@ -30,15 +33,16 @@ impl Observations {
                     if vars.is_empty() {
                         None
                     } else {
-                        Some((index, vars))
+                        Some((index, inst.address(), vars))
                     }
                 })
                 .collect();
 
-            for (index, vars) in observable_writes.iter().rev() {
+            for (index, address, vars) in observable_writes.iter().rev() {
                 for var in vars {
                     let mut obs = Instruction::observable(var.clone().into());
                     obs.labels_mut().pseudo();
+                    obs.set_provenance(observation_provenance(*address));
                     block.insert_instruction(index + 1, obs)?;
                 }
             }
@@ -70,6 +74,29 @@ impl Observations {
         Ok(())
     }
 
+    fn place_observe_at_addresses(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        for block in cfg.blocks_mut() {
+            // Only the first of multiple instructions with the same address marks the program point
+            let mut indices = Vec::new();
+            let mut last_address = None;
+            for (index, inst) in block.instructions().iter().enumerate() {
+                let address = inst.address();
+                if address != last_address
+                    && address.map_or(false, |a| self.observe_at.contains(&a))
+                {
+                    indices.push(index);
+                }
+                last_address = address;
+            }
+
+            for index in indices.into_iter().rev() {
+                self.insert_observe_instruction_at(block, index)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn place_observe_at_end_of_program(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
         let exit_block = cfg.exit_block_mut()?;
         self.append_observe_instruction(exit_block);
@@ -78,9 +105,14 @@ impl Observations {
     }
 
     fn insert_observe_instruction_at(&self, block: &mut Block, index: usize) -> Result<()> {
+        let address = block
+            .instruction(index)
+            .and_then(Instruction::address)
+            .or_else(|| block.address());
         for var in &self.observable_variables {
             let mut obs = Instruction::observable(var.clone().into());
             obs.labels_mut().pseudo();
+            obs.set_provenance(observation_provenance(address));
             block.insert_instruction(index, obs)?;
         }
 
@@ -88,13 +120,21 @@ impl Observations {
     }
 
     fn append_observe_instruction(&self, block: &mut Block) {
+        let address = block.address();
         for var in &self.observable_variables {
             let obs = block.observable(var.clone().into());
             obs.labels_mut().pseudo();
+            obs.set_provenance(observation_provenance(address));
         }
     }
 }
 
+fn observation_provenance(address: Option<u64>) -> Provenance {
+    Provenance::observation()
+        .with_address(address)
+        .with_transform("Observations")
+}
+
 impl Transform<ControlFlowGraph> for Observations {
     fn name(&self) -> &'static str {
         "Observations"
@@ -113,6 +153,10 @@ impl Transform<ControlFlowGraph> for Observations {
             self.place_observe_at_control_flow_joins(cfg)?;
         }
 
+        if !self.observe_at.is_empty() {
+            self.place_observe_at_addresses(cfg)?;
+        }
+
         if self.observe_at_end_of_program {
             self.place_observe_at_end_of_program(cfg)?;
         }
@@ -9,6 +9,7 @@ mod dead_code_elimination;
 mod expression_simplification;
 mod phi_elimination;
 mod redundant_instruction_elimination;
+mod redundant_observation_elimination;
 
 use constant_folding::ConstantFolding;
 use constant_propagation::ConstantPropagation;
@@ -16,6 +17,7 @@ use copy_propagation::CopyPropagation;
 use dead_code_elimination::DeadCodeElimination;
 use expression_simplification::ExpressionSimplification;
 use phi_elimination::PhiElimination;
+use redundant_observation_elimination::RedundantObservationElimination;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum OptimizationResult {
@@ -68,6 +70,16 @@ impl Optimizer {
     }
 }
 
+impl Optimizer {
+    /// Additionally removes observations which are implied by observations of dominating blocks
+    /// (only worthwhile for the parallel observe mode, which observes after each effect).
+    pub fn with_redundant_observation_elimination(mut self) -> Self {
+        self.post_optimizations
+            .push(Box::new(RedundantObservationElimination::new()));
+        self
+    }
+}
+
 impl Transform<ControlFlowGraph> for Optimizer {
     fn name(&self) -> &'static str {
         "Optimization"
@@ -0,0 +1,207 @@
+//! Redundant Observation Elimination
+//!
+//! Observations (self-composition assertions) of an expression are redundant
+//! if the same expression is already observed earlier in the same block or in a dominating block,
+//! i.e. the observed component has not been changed by any effect in-between (same SSA version).
+//! This is mainly the case for the parallel observe mode, which observes after each effect and on each control-flow join.
+//!
+//! Observations are only required to be equal if all compositions executed the observing block.
+//! Each composition which executes a block has executed all of its dominators as well,
+//! therefore the observation in the dominator already implies the redundant one.
+//! Observations on all (but mutually exclusive) paths don't imply each other.
+//!
+//! This optimization requires that the program is in SSA form.
+
+use crate::error::Result;
+use crate::expr::Expression;
+use crate::hir::transformation::optimization::{Optimization, OptimizationResult};
+use crate::hir::{ControlFlowGraph, Operation};
+use std::collections::{HashMap, HashSet};
+
+pub struct RedundantObservationElimination {}
+
+impl RedundantObservationElimination {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Optimization for RedundantObservationElimination {
+    fn optimize(&self, cfg: &mut ControlFlowGraph) -> Result<OptimizationResult> {
+        let dominators = cfg.dominators()?;
+
+        // The observed expressions of each block.
+        let observed: HashMap<usize, HashSet<&Expression>> = cfg
+            .blocks()
+            .into_iter()
+            .map(|block| {
+                let observed_exprs = block
+                    .instructions()
+                    .iter()
+                    .filter_map(|inst| match inst.operation() {
+                        Operation::Observable { expr } => Some(expr),
+                        _ => None,
+                    })
+                    .collect();
+                (block.index(), observed_exprs)
+            })
+            .collect();
+
+        let mut redundant_observations: HashMap<usize, Vec<usize>> = HashMap::new();
+        for block in cfg.blocks() {
+            let strict_dominators: Vec<usize> = dominators
+                .dominators(block.index())
+                .into_iter()
+                .skip(1)
+                .collect();
+
+            let mut observed_in_block = HashSet::new();
+            for (index, inst) in block.instructions().iter().enumerate() {
+                if let Operation::Observable { expr } = inst.operation() {
+                    let already_observed = !observed_in_block.insert(expr)
+                        || strict_dominators
+                            .iter()
+                            .any(|dominator| observed[dominator].contains(expr));
+                    if already_observed {
+                        redundant_observations
+                            .entry(block.index())
+                            .or_default()
+                            .push(index);
+                    }
+                }
+            }
+        }
+
+        if redundant_observations.is_empty() {
+            return Ok(OptimizationResult::Unchanged);
+        }
+
+        for (block_index, instruction_indices) in redundant_observations {
+            cfg.block_mut(block_index)?
+                .remove_instructions(&instruction_indices)?;
+        }
+
+        Ok(OptimizationResult::Changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{Boolean, Variable};
+    use crate::hir::{Block, Instruction};
+
+    fn ssa_var(name: &str, version: usize) -> Variable {
+        let mut var = Boolean::variable(name);
+        var.set_version(Some(version));
+        var
+    }
+
+    #[test]
+    fn test_remove_observations_observed_in_dominator() {
+        // GIVEN
+        let mut cfg = {
+            let mut block0 = Block::new(0);
+            block0
+                .observable(ssa_var("cache", 1).into())
+                .set_address(Some(1));
+
+            let mut block1 = Block::new(1);
+            block1
+                .observable(ssa_var("cache", 1).into())
+                .set_address(Some(2));
+
+            let mut block2 = Block::new(2);
+            block2
+                .observable(ssa_var("cache", 2).into())
+                .set_address(Some(3));
+
+            let mut block3 = Block::new(3);
+            block3
+                .observable(ssa_var("cache", 1).into())
+                .set_address(Some(4));
+            block3
+                .observable(ssa_var("cache", 2).into())
+                .set_address(Some(4));
+
+            let mut cfg = ControlFlowGraph::new();
+            cfg.add_block(block0).unwrap();
+            cfg.add_block(block1).unwrap();
+            cfg.add_block(block2).unwrap();
+            cfg.add_block(block3).unwrap();
+
+            cfg.unconditional_edge(0, 1).unwrap();
+            cfg.unconditional_edge(0, 2).unwrap();
+            cfg.unconditional_edge(1, 3).unwrap();
+            cfg.unconditional_edge(2, 3).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(3).unwrap();
+
+            cfg
+        };
+
+        // WHEN
+        let result = RedundantObservationElimination::new()
+            .optimize(&mut cfg)
+            .unwrap();
+
+        // THEN: the observations of cache_1 in block 1 and 3 are redundant (observed in block 0)
+        assert_eq!(result, OptimizationResult::Changed);
+        assert_eq!(cfg.block(1).unwrap().instruction_count(), 0);
+        assert_eq!(cfg.block(2).unwrap().instruction_count(), 1);
+        let mut expected = Instruction::observable(ssa_var("cache", 2).into());
+        expected.set_address(Some(4));
+        assert_eq!(cfg.block(3).unwrap().instructions(), &vec![expected]);
+    }
+
+    #[test]
+    fn test_keep_observations_observed_on_divergent_paths() {
+        // GIVEN: cache_1 is observed in both branches, but not in their common dominator
+        let mut cfg = {
+            let block0 = Block::new(0);
+
+            let mut block1 = Block::new(1);
+            block1
+                .observable(ssa_var("cache", 1).into())
+                .set_address(Some(1));
+
+            let mut block2 = Block::new(2);
+            block2
+                .observable(ssa_var("cache", 1).into())
+                .set_address(Some(2));
+
+            let mut block3 = Block::new(3);
+            block3
+                .observable(ssa_var("cache", 1).into())
+                .set_address(Some(3));
+
+            let mut cfg = ControlFlowGraph::new();
+            cfg.add_block(block0).unwrap();
+            cfg.add_block(block1).unwrap();
+            cfg.add_block(block2).unwrap();
+            cfg.add_block(block3).unwrap();
+
+            cfg.unconditional_edge(0, 1).unwrap();
+            cfg.unconditional_edge(0, 2).unwrap();
+            cfg.unconditional_edge(1, 3).unwrap();
+            cfg.unconditional_edge(2, 3).unwrap();
+
+            cfg.set_entry(0).unwrap();
+            cfg.set_exit(3).unwrap();
+
+            cfg
+        };
+
+        // WHEN
+        let result = RedundantObservationElimination::new()
+            .optimize(&mut cfg)
+            .unwrap();
+
+        // THEN: the compositions may execute different branches, the observation at the join is required
+        assert_eq!(result, OptimizationResult::Unchanged);
+        assert_eq!(cfg.block(1).unwrap().instruction_count(), 1);
+        assert_eq!(cfg.block(2).unwrap().instruction_count(), 1);
+        assert_eq!(cfg.block(3).unwrap().instruction_count(), 1);
+    }
+}
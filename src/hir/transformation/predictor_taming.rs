@@ -0,0 +1,282 @@
+use crate::error::Result;
+use crate::expr::{BitVector, Boolean, Expression, Operator, Predictor, Variable};
+use crate::hir::{ControlFlowGraph, Instruction};
+use crate::ir::Transform;
+use std::collections::BTreeSet;
+
+/// Constrains the speculation decisions of the predictor, e.g. to model an attacker
+/// which trained the predictor in a specific way.
+///
+/// The constraints are added as assumptions on `speculate` for all program locations
+/// at which speculation can happen, and therefore require `TransientExecution` to be applied first.
+#[derive(Default, Builder, Debug)]
+pub struct PredictorTaming {
+    /// The predictor mispredicts at every speculating instruction (except `never_speculate_at`).
+    #[builder(default)]
+    always_mispredict: bool,
+    /// Max. number of program locations at which the predictor mispredicts.
+    #[builder(default)]
+    max_mispredictions: Option<usize>,
+    /// Program locations at which the predictor never mispredicts.
+    #[builder(default)]
+    never_speculate_at: BTreeSet<u64>,
+    /// The predictor mispredicts at most at a single program location (the speculation source).
+    #[builder(default)]
+    single_speculation: bool,
+}
+
+impl PredictorTaming {
+    /// Returns the variable holding the only program location at which the predictor mispredicts
+    /// (no misprediction at all if it holds none of the speculating locations).
+    pub fn speculation_source_variable() -> Variable {
+        BitVector::word_variable("_speculation_source")
+    }
+
+    /// Max. number of program locations at which the predictor mispredicts.
+    fn max_mispredictions(&self) -> Option<usize> {
+        if self.single_speculation {
+            Some(1)
+        } else {
+            self.max_mispredictions
+        }
+    }
+
+    /// Returns the locations of all speculation decisions in the CFG.
+    fn speculation_locations(cfg: &ControlFlowGraph) -> BTreeSet<u64> {
+        let edge_conditions = cfg.edges().into_iter().filter_map(|edge| edge.condition());
+        let instruction_expressions = cfg
+            .blocks()
+            .into_iter()
+            .flat_map(|block| block.expressions());
+
+        edge_conditions
+            .chain(instruction_expressions)
+            .flat_map(Expression::subexpressions)
+            .filter_map(|expr| match (expr.operator(), expr.operands()) {
+                (Operator::Predictor(Predictor::Speculate), [_, location]) => {
+                    u64::try_from(location).ok()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn assumptions(&self, locations: &BTreeSet<u64>) -> Result<Vec<Expression>> {
+        let speculate = |location: u64| {
+            Predictor::speculate(
+                Predictor::variable().into(),
+                BitVector::word_constant(location),
+            )
+        };
+
+        let mut assumptions = Vec::new();
+
+        for &location in locations.intersection(&self.never_speculate_at) {
+            assumptions.push(Boolean::not(speculate(location)?)?);
+        }
+
+        let mispredicting: Vec<u64> = locations
+            .difference(&self.never_speculate_at)
+            .copied()
+            .collect();
+
+        if self.always_mispredict {
+            if let Some(max) = self.max_mispredictions() {
+                if mispredicting.len() > max {
+                    return Err(format!(
+                        "Predictor can't always mispredict at {} locations with at most {} mispredictions",
+                        mispredicting.len(),
+                        max
+                    )
+                    .into());
+                }
+            }
+            for &location in &mispredicting {
+                assumptions.push(speculate(location)?);
+            }
+        } else if let Some(max) = self.max_mispredictions {
+            // A single speculation source doesn't need counting, see `evaluate_speculation`
+            if mispredicting.len() > max && !self.single_speculation {
+                // Count the mispredictions as word, there can't be more than locations
+                let mut count = BitVector::word_constant(0);
+                for &location in &mispredicting {
+                    count = BitVector::add(
+                        count,
+                        Expression::ite(
+                            speculate(location)?,
+                            BitVector::word_constant(1),
+                            BitVector::word_constant(0),
+                        )?,
+                    )?;
+                }
+                assumptions.push(BitVector::ule(count, BitVector::word_constant(max as u64))?);
+            }
+        }
+
+        Ok(assumptions)
+    }
+
+    /// Partially evaluates the predictor for a single speculation source,
+    /// i.e. `speculate(predictor, location)` becomes `_speculation_source = location`.
+    ///
+    /// This implies that at most one location mispredicts without any cardinality constraint.
+    fn evaluate_speculation(expr: &mut Expression) -> Result<()> {
+        if let (Operator::Predictor(Predictor::Speculate), [_, location]) =
+            (expr.operator(), expr.operands())
+        {
+            *expr =
+                Expression::equal(Self::speculation_source_variable().into(), location.clone())?;
+            return Ok(());
+        }
+
+        for operand in expr.operands_mut() {
+            Self::evaluate_speculation(operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl Transform<ControlFlowGraph> for PredictorTaming {
+    fn name(&self) -> &'static str {
+        "PredictorTaming"
+    }
+
+    fn description(&self) -> String {
+        "Constrain predictor".to_string()
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        let locations = Self::speculation_locations(cfg);
+
+        let entry_block = cfg.entry_block_mut()?;
+        for (index, condition) in self.assumptions(&locations)?.into_iter().enumerate() {
+            let mut assumption = Instruction::assume(condition)?;
+            assumption.labels_mut().pseudo();
+            entry_block.insert_instruction(index, assumption)?;
+        }
+
+        if self.single_speculation {
+            for edge in cfg.edges_mut() {
+                if let Some(condition) = edge.condition_mut() {
+                    Self::evaluate_speculation(condition)?;
+                }
+            }
+            for block in cfg.blocks_mut() {
+                for expr in block.expressions_mut() {
+                    Self::evaluate_speculation(expr)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Single block with speculation decisions at the given locations
+    fn cfg_speculating_at(locations: &[u64]) -> ControlFlowGraph {
+        let mut cfg = ControlFlowGraph::new();
+        let block = cfg.new_block();
+        for &location in locations {
+            block
+                .assume(
+                    Predictor::speculate(
+                        Predictor::variable().into(),
+                        BitVector::word_constant(location),
+                    )
+                    .unwrap(),
+                )
+                .unwrap();
+        }
+        let index = block.index();
+        cfg.set_entry(index).unwrap();
+        cfg.set_exit(index).unwrap();
+        cfg
+    }
+
+    fn speculate(location: u64) -> Expression {
+        Predictor::speculate(
+            Predictor::variable().into(),
+            BitVector::word_constant(location),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_always_mispredict_except_never_speculate_locations() {
+        // Given
+        let mut cfg = cfg_speculating_at(&[1, 2]);
+        let taming = PredictorTamingBuilder::default()
+            .always_mispredict(true)
+            .never_speculate_at(vec![2, 3].into_iter().collect())
+            .build()
+            .unwrap();
+
+        // When
+        taming.transform(&mut cfg).unwrap();
+
+        // Then
+        let assumptions: Vec<&Expression> = cfg.entry_block().unwrap().instructions()[..2]
+            .iter()
+            .flat_map(Instruction::expressions)
+            .collect();
+        assert_eq!(
+            assumptions,
+            vec![&Boolean::not(speculate(2)).unwrap(), &speculate(1)]
+        );
+    }
+
+    #[test]
+    fn test_single_speculation_replaces_predictor_by_speculation_source() {
+        // Given
+        let mut cfg = cfg_speculating_at(&[1, 2]);
+        let taming = PredictorTamingBuilder::default()
+            .single_speculation(true)
+            .never_speculate_at(vec![2].into_iter().collect())
+            .build()
+            .unwrap();
+
+        // When
+        taming.transform(&mut cfg).unwrap();
+
+        // Then
+        let source = |location: u64| {
+            Expression::equal(
+                PredictorTaming::speculation_source_variable().into(),
+                BitVector::word_constant(location),
+            )
+            .unwrap()
+        };
+        let assumptions: Vec<&Expression> = cfg
+            .entry_block()
+            .unwrap()
+            .instructions()
+            .iter()
+            .flat_map(Instruction::expressions)
+            .collect();
+        assert_eq!(
+            assumptions,
+            vec![&Boolean::not(source(2)).unwrap(), &source(1), &source(2)]
+        );
+    }
+
+    #[test]
+    fn test_always_mispredict_contradicting_max_mispredictions_fails() {
+        // Given
+        let mut cfg = cfg_speculating_at(&[1, 2]);
+        let taming = PredictorTamingBuilder::default()
+            .always_mispredict(true)
+            .max_mispredictions(Some(1))
+            .build()
+            .unwrap();
+
+        // When
+        let result = taming.transform(&mut cfg);
+
+        // Then
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::error::Result;
+use crate::expr::{Cache, Expression, Sort, Variable};
+use crate::hir::{Block, ControlFlowGraph, Instruction, Operation};
+use crate::ir::Transform;
+
+/// Models an attacker thread running concurrently on a sibling hardware thread (co-residency).
+///
+/// The attacker can asynchronously evict arbitrary cache lines, which is modeled by evicting
+/// `evictions` freely chosen addresses in front of each cache observation.
+/// As the attacker doesn't know the secret, the evicted addresses are the same in all self-compositions.
+/// Therefore, differences in evicted lines are no longer distinguishable.
+///
+/// Cache evictions are expected to be explicit (see `ExplicitEffects`).
+#[derive(Default, Builder, Debug)]
+pub struct SiblingThread {
+    /// Number of cache lines evicted in front of each observation
+    evictions: usize,
+}
+
+impl SiblingThread {
+    /// Returns the variable holding the address evicted by the sibling thread.
+    pub fn eviction_address_variable() -> Variable {
+        Variable::new("_sibling_evict", Sort::word())
+    }
+
+    fn evict_in_front_of_cache_observations(&self, block: &mut Block) -> Result<()> {
+        let indices: Vec<usize> = block
+            .instructions()
+            .iter()
+            .enumerate()
+            .filter(|(_, inst)| observes_cache(inst))
+            .map(|(index, _)| index)
+            .collect();
+
+        for index in indices.into_iter().rev() {
+            for inst in self.eviction_instructions()?.into_iter().rev() {
+                block.insert_instruction(index, inst)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn eviction_instructions(&self) -> Result<Vec<Instruction>> {
+        let address = Self::eviction_address_variable();
+        let cache = Cache::variable();
+
+        let mut instructions = Vec::new();
+        for _ in 0..self.evictions {
+            instructions.push(Instruction::assign(
+                address.clone(),
+                Expression::nondet(address.sort().clone()),
+            )?);
+            instructions.push(Instruction::indistinguishable(address.clone().into()));
+            instructions.push(Instruction::assign(
+                cache.clone(),
+                Cache::evict(8, cache.clone().into(), address.clone().into())?,
+            )?);
+        }
+
+        for inst in &mut instructions {
+            inst.labels_mut().pseudo();
+        }
+
+        Ok(instructions)
+    }
+}
+
+fn observes_cache(inst: &Instruction) -> bool {
+    match inst.operation() {
+        Operation::Observable { expr } => expr.variables().contains(&&Cache::variable()),
+        _ => false,
+    }
+}
+
+impl Transform<ControlFlowGraph> for SiblingThread {
+    fn name(&self) -> &'static str {
+        "SiblingThread"
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Evict {} cache line(s) by a sibling thread in front of each observation",
+            self.evictions
+        )
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        for block in cfg.blocks_mut() {
+            self.evict_in_front_of_cache_observations(block)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_in_front_of_cache_observation() {
+        // Given:
+        let mut cfg = ControlFlowGraph::new();
+        let block = cfg.new_block();
+        block.observable(Cache::variable().into());
+        let block_index = block.index();
+        cfg.set_entry(block_index).unwrap();
+        cfg.set_exit(block_index).unwrap();
+
+        // When:
+        SiblingThreadBuilder::default()
+            .evictions(2)
+            .build()
+            .unwrap()
+            .transform(&mut cfg)
+            .unwrap();
+
+        // Then: two evictions of the same (low-equivalent) address followed by the observation
+        let instructions = cfg.block(block_index).unwrap().instructions();
+        assert_eq!(instructions.len(), 7);
+        for eviction in instructions[..6].chunks(3) {
+            assert!(eviction[0].is_assign());
+            assert!(matches!(
+                eviction[1].operation(),
+                Operation::Indistinguishable { .. }
+            ));
+            assert_eq!(eviction[2].variables_written(), vec![&Cache::variable()]);
+        }
+        assert!(instructions[6].is_observable());
+    }
+}
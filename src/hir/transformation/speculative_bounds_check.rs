@@ -0,0 +1,182 @@
+use crate::environment::AddressRange;
+use crate::error::Result;
+use crate::expr::{BitVector, Boolean, Expression};
+use crate::hir::{Block, ControlFlowGraph, Instruction, Operation};
+use crate::ir::{Provenance, Transform};
+
+/// Asserts that transient loads only access valid memory (speculative memory safety).
+///
+/// Each load in a transient block is preceded by an assertion requiring that all loaded bytes
+/// lie within one of the valid memory ranges. This is independent of any observation,
+/// i.e. any out-of-bounds transient load is reported, whether it leaks or not.
+#[derive(Default, Builder, Debug)]
+pub struct SpeculativeBoundsCheck {
+    valid_memory: Vec<AddressRange>,
+}
+
+impl SpeculativeBoundsCheck {
+    /// Returns a formula which is true iff the `bytes` bytes starting at `address`
+    /// lie within one of the valid memory ranges.
+    fn is_valid_access(&self, address: &Expression, bytes: u64) -> Result<Expression> {
+        let mut conditions = Vec::new();
+        for range in &self.valid_memory {
+            if range.end() < range.start() + bytes {
+                continue; // access doesn't fit into the range
+            }
+            conditions.push(Boolean::and(
+                BitVector::ule(BitVector::word_constant(range.start()), address.clone())?,
+                BitVector::ule(
+                    address.clone(),
+                    BitVector::word_constant(range.end() - bytes),
+                )?,
+            )?);
+        }
+        Boolean::disjunction(&conditions)
+    }
+
+    fn check_load_at(&self, block: &mut Block, index: usize) -> Result<()> {
+        let inst = block.instruction(index).unwrap();
+        let (bit_width, address) = match inst.operation() {
+            Operation::Load {
+                variable, address, ..
+            } => (variable.sort().unwrap_bit_vector(), address.clone()),
+            _ => return Ok(()),
+        };
+        let inst_address = inst.address();
+
+        let bytes = ((bit_width + 7) / 8) as u64;
+        let mut check = Instruction::assert(self.is_valid_access(&address, bytes)?)?;
+        check.set_address(inst_address);
+        check.labels_mut().pseudo();
+        check.set_provenance(
+            Provenance::memory_safety()
+                .with_address(inst_address)
+                .with_transform("SpeculativeBoundsCheck"),
+        );
+
+        block.insert_instruction(index, check)
+    }
+}
+
+impl Transform<ControlFlowGraph> for SpeculativeBoundsCheck {
+    fn name(&self) -> &'static str {
+        "SpeculativeBoundsCheck"
+    }
+
+    fn description(&self) -> String {
+        "Assert that transient loads access valid memory only".to_string()
+    }
+
+    fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+        if self.valid_memory.iter().all(AddressRange::is_empty) {
+            return Err("Speculative memory safety check requires valid memory ranges".into());
+        }
+
+        for block in cfg
+            .blocks_mut()
+            .into_iter()
+            .filter(|block| block.is_transient())
+        {
+            let load_indices: Vec<usize> = block
+                .instructions()
+                .iter()
+                .enumerate()
+                .filter(|(_, inst)| inst.operation().is_load())
+                .map(|(index, _)| index)
+                .collect();
+
+            for index in load_indices.into_iter().rev() {
+                self.check_load_at(block, index)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Block with a 32-bit load followed by a transient block with a 32-bit load
+    fn load_cfg() -> (ControlFlowGraph, usize, usize) {
+        let mut cfg = ControlFlowGraph::new();
+
+        let block = cfg.new_block();
+        block
+            .load(
+                BitVector::variable("x", 32),
+                BitVector::word_variable("a").into(),
+            )
+            .unwrap();
+        let normal_index = block.index();
+
+        let block = cfg.new_block();
+        block.set_transient();
+        block
+            .load(
+                BitVector::variable("y", 32),
+                BitVector::word_variable("b").into(),
+            )
+            .unwrap();
+        let transient_index = block.index();
+
+        cfg.unconditional_edge(normal_index, transient_index)
+            .unwrap();
+        cfg.set_entry(normal_index).unwrap();
+        cfg.set_exit(transient_index).unwrap();
+        (cfg, normal_index, transient_index)
+    }
+
+    #[test]
+    fn test_transient_loads_are_bounds_checked() {
+        // Given
+        let (mut cfg, normal_index, transient_index) = load_cfg();
+        let bounds_check = SpeculativeBoundsCheckBuilder::default()
+            .valid_memory(vec![AddressRange::new(0x1000, 0x2000)])
+            .build()
+            .unwrap();
+
+        // When
+        bounds_check.transform(&mut cfg).unwrap();
+
+        // Then
+        assert_eq!(cfg.block(normal_index).unwrap().instruction_count(), 1);
+        let instructions = cfg.block(transient_index).unwrap().instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            instructions[0].operation(),
+            &Operation::assert(
+                Boolean::disjunction(&[Boolean::and(
+                    BitVector::ule(
+                        BitVector::word_constant(0x1000),
+                        BitVector::word_variable("b").into()
+                    )
+                    .unwrap(),
+                    BitVector::ule(
+                        BitVector::word_variable("b").into(),
+                        BitVector::word_constant(0x1FFC)
+                    )
+                    .unwrap(),
+                )
+                .unwrap()])
+                .unwrap()
+            )
+            .unwrap()
+        );
+        assert!(instructions[1].operation().is_load());
+    }
+
+    #[test]
+    fn test_bounds_check_without_valid_memory_fails() {
+        // Given
+        let (mut cfg, _, _) = load_cfg();
+        let bounds_check = SpeculativeBoundsCheck::default();
+
+        // When
+        let result = bounds_check.transform(&mut cfg);
+
+        // Then
+        assert!(result.is_err());
+    }
+}
@@ -80,7 +80,7 @@ fn insert_phi_nodes(cfg: &mut ControlFlowGraph, form: SSAForm) -> Result<()> {
         return Err("The CFG must not have any predecessors".into());
     }
 
-    let dominance_frontiers = cfg.graph().compute_dominance_frontiers(entry)?;
+    let dominators = cfg.dominators()?;
 
     let (global_variables, live_variables) = match form {
         SSAForm::Minimal => (None, None),
@@ -98,7 +98,10 @@ fn insert_phi_nodes(cfg: &mut ControlFlowGraph, form: SSAForm) -> Result<()> {
         let mut phi_insertions: HashSet<usize> = HashSet::new();
         let mut queue: VecDeque<usize> = defs.iter().cloned().collect();
         while let Some(block_index) = queue.pop_front() {
-            for df_index in &dominance_frontiers[&block_index] {
+            if !dominators.is_reachable(block_index) {
+                continue; // unreachable blocks have no dominance frontier
+            }
+            for df_index in dominators.dominance_frontier(block_index)? {
                 if let Some(live_vars) = &live_variables {
                     if !live_vars.live_at_entry(*df_index)?.contains(&variable) {
                         continue; // ignore dead variables
@@ -309,7 +312,7 @@ impl SSARename for ControlFlowGraph {
 mod tests {
     use super::*;
     use crate::expr::{BitVector, Memory};
-    use crate::hir::Operation;
+    use crate::hir::{BarrierKind, Operation};
 
     fn expr_const(value: u64) -> Expression {
         BitVector::constant_u64(value, 64)
@@ -562,11 +565,11 @@ mod tests {
 
         let block0 = cfg.new_block();
         block0.assign(variable("x"), expr_const(1)).unwrap();
-        block0.barrier();
+        block0.barrier(BarrierKind::Full);
 
         let block1 = cfg.new_block();
         block1.assign(variable("x"), variable("x").into()).unwrap();
-        block1.barrier();
+        block1.barrier(BarrierKind::Full);
 
         let block2 = cfg.new_block();
         block2.assign(variable("x"), variable("x").into()).unwrap();
@@ -1,44 +1,120 @@
+use crate::environment::WORD_SIZE;
 use crate::error::Result;
-use crate::expr::{List, Sort, Tuple, Variable};
+use crate::expr::{Array, BitVector, Expression, List, Sort, Tuple, Variable};
 use crate::hir::{Block, ControlFlowGraph, Instruction};
-use crate::ir::Transform;
-use std::collections::HashSet;
+use crate::ir::{Provenance, Transform};
+use std::cmp;
+use std::collections::{BTreeMap, HashSet};
+
+/// How the trace of observations is encoded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceEncoding {
+    /// A single list of state tuples (requires lists and datatypes).
+    List,
+    /// One index-addressed array per observable variable plus a common trace length.
+    Array,
+    /// Same as `Array` but the arrays are indexed by a step counter which is just wide enough
+    /// to count the max. number of trace steps of the (unrolled) program, i.e. fixed-size arrays.
+    Bounded,
+}
+
+impl Default for TraceEncoding {
+    fn default() -> Self {
+        Self::List
+    }
+}
 
 #[derive(Default, Builder, Debug)]
 pub struct TraceObservations {
     observable_variables: HashSet<Variable>,
+    #[builder(default)]
+    encoding: TraceEncoding,
 }
 
 impl TraceObservations {
-    fn trace_observable_variables(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+    /// Indices of the instructions of `block` which write to an observable variable.
+    fn observable_writes(&self, block: &Block) -> Vec<usize> {
+        block
+            .instructions()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, inst)| {
+                let writes_to_observable_variable = inst
+                    .variables_written()
+                    .into_iter()
+                    .any(|var| self.observable_variables.contains(var));
+
+                if writes_to_observable_variable {
+                    Some(index)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Max. number of trace steps along any path of the (acyclic) CFG.
+    fn max_trace_steps(&self, cfg: &ControlFlowGraph) -> Result<usize> {
+        let mut steps: BTreeMap<usize, usize> = BTreeMap::new();
+
+        for index in cfg.graph().compute_topological_ordering()? {
+            let most_predecessor_steps = cfg
+                .predecessor_indices(index)?
+                .into_iter()
+                .filter_map(|predecessor| steps.get(&predecessor))
+                .max()
+                .cloned()
+                .unwrap_or_default();
+
+            let block_steps = self.observable_writes(cfg.block(index)?).len();
+            steps.insert(index, most_predecessor_steps + block_steps);
+        }
+
+        Ok(steps.values().max().cloned().unwrap_or_default())
+    }
+
+    /// Bit width of the trace index (and length).
+    fn index_bits(&self, cfg: &ControlFlowGraph) -> Result<usize> {
+        match self.encoding {
+            TraceEncoding::List | TraceEncoding::Array => Ok(WORD_SIZE),
+            TraceEncoding::Bounded => {
+                let steps = self.max_trace_steps(cfg)?;
+                Ok(cmp::max(1, (usize::BITS - steps.leading_zeros()) as usize))
+            }
+        }
+    }
+
+    fn trace_observable_variables(
+        &self,
+        cfg: &mut ControlFlowGraph,
+        index_bits: usize,
+    ) -> Result<()> {
         for block in cfg.blocks_mut() {
-            let observable_writes: Vec<usize> = block
-                .instructions()
-                .iter()
-                .enumerate()
-                .filter_map(|(index, inst)| {
-                    let writes_to_observable_variable = inst
-                        .variables_written()
-                        .into_iter()
-                        .any(|var| self.observable_variables.contains(var));
-
-                    if writes_to_observable_variable {
-                        Some(index)
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+            let observable_writes = self.observable_writes(block);
 
             for index in observable_writes.iter().rev() {
-                self.trace_observable_variables_at(block, index + 1)?;
+                self.trace_observable_variables_at(block, index + 1, index_bits)?;
             }
         }
 
         Ok(())
     }
 
-    fn trace_observable_variables_at(&self, block: &mut Block, index: usize) -> Result<()> {
+    fn trace_observable_variables_at(
+        &self,
+        block: &mut Block,
+        index: usize,
+        index_bits: usize,
+    ) -> Result<()> {
+        match self.encoding {
+            TraceEncoding::List => self.append_to_trace_list_at(block, index),
+            TraceEncoding::Array | TraceEncoding::Bounded => {
+                self.append_to_trace_arrays_at(block, index, index_bits)
+            }
+        }
+    }
+
+    fn append_to_trace_list_at(&self, block: &mut Block, index: usize) -> Result<()> {
         let elements = self
             .observable_variables
             .iter()
@@ -54,29 +130,109 @@ impl TraceObservations {
         block.insert_instruction(index, trace_append)
     }
 
-    fn init_trace_at_entry(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+    /// Stores the current state at position `_trace_length` of the trace arrays
+    /// and increments the trace length.
+    fn append_to_trace_arrays_at(
+        &self,
+        block: &mut Block,
+        index: usize,
+        index_bits: usize,
+    ) -> Result<()> {
+        let length = trace_length_var(index_bits);
+
+        let mut trace_append = Vec::new();
+        for var in &self.observable_variables {
+            let trace = trace_array_var(var, index_bits);
+            trace_append.push(Instruction::assign(
+                trace.clone(),
+                Array::store(trace.into(), length.clone().into(), var.clone().into())?,
+            )?);
+        }
+        trace_append.push(Instruction::assign(
+            length.clone(),
+            BitVector::add(length.into(), BitVector::constant_u64(1, index_bits))?,
+        )?);
+
+        for mut inst in trace_append.into_iter().rev() {
+            inst.labels_mut().pseudo();
+            block.insert_instruction(index, inst)?;
+        }
+
+        Ok(())
+    }
+
+    fn init_trace_at_entry(&self, cfg: &mut ControlFlowGraph, index_bits: usize) -> Result<()> {
         let entry_block = cfg.entry_block_mut()?;
 
-        entry_block
-            .assign(self.trace_var(), List::nil(self.trace_sort()))?
-            .labels_mut()
-            .pseudo();
+        match self.encoding {
+            TraceEncoding::List => {
+                entry_block
+                    .assign(self.trace_var(), List::nil(self.trace_sort()))?
+                    .labels_mut()
+                    .pseudo();
+
+                entry_block
+                    .indistinguishable(self.trace_var().into())
+                    .labels_mut()
+                    .pseudo();
+            }
+            TraceEncoding::Array | TraceEncoding::Bounded => {
+                entry_block
+                    .assign(
+                        trace_length_var(index_bits),
+                        BitVector::constant_u64(0, index_bits),
+                    )?
+                    .labels_mut()
+                    .pseudo();
 
-        entry_block
-            .indistinguishable(self.trace_var().into())
-            .labels_mut()
-            .pseudo();
+                // The arrays are equal beyond the trace length,
+                // therefore array equality implies trace equality.
+                for var in &self.observable_variables {
+                    let trace = trace_array_var(var, index_bits);
+                    entry_block
+                        .assign(trace.clone(), Expression::nondet(trace.sort().clone()))?
+                        .labels_mut()
+                        .pseudo();
+
+                    entry_block
+                        .indistinguishable(trace.into())
+                        .labels_mut()
+                        .pseudo();
+                }
+            }
+        }
 
         Ok(())
     }
 
-    fn observe_trace_at_exit(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
+    fn observe_trace_at_exit(&self, cfg: &mut ControlFlowGraph, index_bits: usize) -> Result<()> {
         let exit_block = cfg.exit_block_mut()?;
 
-        exit_block
-            .observable(self.trace_var().into())
-            .labels_mut()
-            .pseudo();
+        let observed: Vec<Variable> = match self.encoding {
+            TraceEncoding::List => vec![self.trace_var()],
+            TraceEncoding::Array | TraceEncoding::Bounded => {
+                std::iter::once(trace_length_var(index_bits))
+                    .chain(
+                        self.observable_variables
+                            .iter()
+                            .map(|var| trace_array_var(var, index_bits)),
+                    )
+                    .collect()
+            }
+        };
+
+        let address = exit_block.address();
+        for var in observed {
+            exit_block
+                .observable(var.into())
+                .set_provenance(
+                    Provenance::observation()
+                        .with_address(address)
+                        .with_transform("TraceObservations"),
+                )
+                .labels_mut()
+                .pseudo();
+        }
 
         Ok(())
     }
@@ -101,6 +257,19 @@ impl TraceObservations {
     }
 }
 
+fn trace_length_var(index_bits: usize) -> Variable {
+    let mut var = BitVector::variable("_trace_length", index_bits);
+    var.set_rollback_persistent(true);
+    var
+}
+
+fn trace_array_var(observable: &Variable, index_bits: usize) -> Variable {
+    let sort = Sort::array(Sort::bit_vector(index_bits), observable.sort().clone());
+    let mut var = Variable::new(format!("_trace_{}", observable.name()), sort);
+    var.set_rollback_persistent(true);
+    var
+}
+
 impl Transform<ControlFlowGraph> for TraceObservations {
     fn name(&self) -> &'static str {
         "TraceObservations"
@@ -111,10 +280,192 @@ impl Transform<ControlFlowGraph> for TraceObservations {
     }
 
     fn transform(&self, cfg: &mut ControlFlowGraph) -> Result<()> {
-        self.init_trace_at_entry(cfg)?;
-        self.trace_observable_variables(cfg)?;
-        self.observe_trace_at_exit(cfg)?;
+        let index_bits = self.index_bits(cfg)?;
+        self.init_trace_at_entry(cfg, index_bits)?;
+        self.trace_observable_variables(cfg, index_bits)?;
+        self.observe_trace_at_exit(cfg, index_bits)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hir::Operation;
+
+    #[test]
+    fn test_bounded_trace_index_counts_longest_path() {
+        // Given: entry -> (one write | two writes) -> exit
+        let observable = BitVector::word_variable("x");
+        let mut cfg = ControlFlowGraph::new();
+        let add_block = |cfg: &mut ControlFlowGraph, writes: usize| {
+            let block = cfg.new_block();
+            for _ in 0..writes {
+                block
+                    .assign(observable.clone(), BitVector::word_constant(0))
+                    .unwrap();
+            }
+            block.index()
+        };
+        let entry = add_block(&mut cfg, 0);
+        let short = add_block(&mut cfg, 1);
+        let long = add_block(&mut cfg, 2);
+        let exit = add_block(&mut cfg, 0);
+        cfg.unconditional_edge(entry, short).unwrap();
+        cfg.unconditional_edge(entry, long).unwrap();
+        cfg.unconditional_edge(short, exit).unwrap();
+        cfg.unconditional_edge(long, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+
+        // When:
+        TraceObservationsBuilder::default()
+            .observable_variables(vec![observable].into_iter().collect())
+            .encoding(TraceEncoding::Bounded)
+            .build()
+            .unwrap()
+            .transform(&mut cfg)
+            .unwrap();
+
+        // Then: the trace length counts up to 2 steps
+        let observed: Vec<&Expression> = cfg
+            .block(exit)
+            .unwrap()
+            .instructions()
+            .iter()
+            .filter_map(|inst| match inst.operation() {
+                Operation::Observable { expr } => Some(expr),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(observed.len(), 2);
+        assert_eq!(observed[0].sort(), &Sort::bit_vector(2));
+        assert_eq!(
+            observed[1].sort(),
+            &Sort::array(Sort::bit_vector(2), Sort::word())
+        );
+    }
+
+    /// Traces `x` in the CFG `entry -> body -> exit`, where `body` writes `x` once.
+    fn traced_cfg(encoding: TraceEncoding) -> (ControlFlowGraph, [usize; 3]) {
+        let x = BitVector::word_variable("x");
+        let mut cfg = ControlFlowGraph::new();
+        let entry = cfg.new_block().index();
+        let body = {
+            let block = cfg.new_block();
+            block
+                .assign(x.clone(), BitVector::word_constant(1))
+                .unwrap();
+            block.index()
+        };
+        let exit = cfg.new_block().index();
+        cfg.unconditional_edge(entry, body).unwrap();
+        cfg.unconditional_edge(body, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+
+        TraceObservationsBuilder::default()
+            .observable_variables(vec![x].into_iter().collect())
+            .encoding(encoding)
+            .build()
+            .unwrap()
+            .transform(&mut cfg)
+            .unwrap();
+
+        (cfg, [entry, body, exit])
+    }
+
+    fn operations(cfg: &ControlFlowGraph, block: usize) -> Vec<Operation> {
+        cfg.block(block)
+            .unwrap()
+            .instructions()
+            .iter()
+            .map(|inst| inst.operation().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_array_trace_starts_empty_with_indistinguishable_arrays() {
+        // Given:
+        let x = BitVector::word_variable("x");
+        let length = trace_length_var(WORD_SIZE);
+        let trace = trace_array_var(&x, WORD_SIZE);
+
+        // When:
+        let (cfg, [entry, _, _]) = traced_cfg(TraceEncoding::Array);
+
+        // Then: the (word-indexed) arrays are arbitrary beyond the trace length, but equal in all executions
+        assert_eq!(
+            operations(&cfg, entry),
+            vec![
+                Operation::assign(length, BitVector::word_constant(0)).unwrap(),
+                Operation::assign(trace.clone(), Expression::nondet(trace.sort().clone())).unwrap(),
+                Operation::indistinguishable(trace.into()),
+            ]
+        );
+        assert!(cfg
+            .block(entry)
+            .unwrap()
+            .instructions()
+            .iter()
+            .all(|inst| inst.labels().is_pseudo()));
+    }
+
+    #[test]
+    fn test_array_trace_stores_each_write_at_the_trace_length() {
+        // Given:
+        let x = BitVector::word_variable("x");
+        let length = trace_length_var(WORD_SIZE);
+        let trace = trace_array_var(&x, WORD_SIZE);
+
+        // When:
+        let (cfg, [_, body, _]) = traced_cfg(TraceEncoding::Array);
+
+        // Then: the write is followed by appending x to the trace
+        assert_eq!(
+            operations(&cfg, body),
+            vec![
+                Operation::assign(x.clone(), BitVector::word_constant(1)).unwrap(),
+                Operation::assign(
+                    trace.clone(),
+                    Array::store(trace.into(), length.clone().into(), x.into()).unwrap()
+                )
+                .unwrap(),
+                Operation::assign(
+                    length.clone(),
+                    BitVector::add(length.into(), BitVector::word_constant(1)).unwrap()
+                )
+                .unwrap(),
+            ]
+        );
+        assert!(cfg.block(body).unwrap().instructions()[1..]
+            .iter()
+            .all(|inst| inst.labels().is_pseudo()));
+    }
+
+    #[test]
+    fn test_array_trace_length_and_arrays_are_observed_at_exit() {
+        // Given:
+        let x = BitVector::word_variable("x");
+
+        // When:
+        let (cfg, [_, _, exit]) = traced_cfg(TraceEncoding::Array);
+
+        // Then:
+        assert_eq!(
+            operations(&cfg, exit),
+            vec![
+                Operation::observable(trace_length_var(WORD_SIZE).into()),
+                Operation::observable(trace_array_var(&x, WORD_SIZE).into()),
+            ]
+        );
+        assert!(cfg
+            .block(exit)
+            .unwrap()
+            .instructions()
+            .iter()
+            .all(|inst| inst.provenance().map(Provenance::transform)
+                == Some(Some("TraceObservations"))));
+    }
+}
@@ -1,9 +1,12 @@
 use crate::environment::{PredictorStrategy, SPECULATION_WINDOW_SIZE};
 use crate::error::Result;
-use crate::expr::{BitVector, Boolean, Expression, Predictor, Sort, Variable};
-use crate::hir::{Block, ControlFlowGraph, Edge, Operation, RemovedEdgeGuard};
-use crate::ir::Transform;
-use std::collections::{BTreeMap, HashSet};
+use crate::expr::{
+    BitVector, Boolean, Expression, Fold, MemoryDependencePredictor, Predictor, Sort, Variable,
+};
+use crate::hir::analysis::{self, LiveVariables};
+use crate::hir::{Block, ControlFlowGraph, Edge, Operation, Origin, RemovedEdgeGuard};
+use crate::ir::{Provenance, Transform};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Builder)]
 struct InstructionRef {
@@ -57,6 +60,57 @@ pub struct TransientExecution {
     // With intermediate resolve there may exists a spec win X, s.t. {21} and {42} is in cache,
     // therefore we get an control-flow leak because of secret condition.
     intermediate_resolve: bool,
+    // If enabled, speculating instructions with structurally identical (reduced) transient graphs
+    // share a single copy of it, as long as this doesn't introduce a cycle into the CFG.
+    // This is common for instructions in mutually exclusive paths, e.g. inlined functions.
+    #[builder(default)]
+    share_transient_copies: bool,
+    // If enabled, the speculation window is bounded by the free variable `_spec_win_bound`
+    // (in addition to the max. speculation window), which can be fixed by the solver afterwards.
+    // This allows checking multiple speculation windows using the same encoding.
+    #[builder(default)]
+    parametric_speculation_window: bool,
+    // Max. depth of nested speculation (0 = disabled).
+    // If enabled, speculating instructions within transient execution start a nested transient execution,
+    // which is rolled back on its own such that the outer transient execution continues on the correct path.
+    // Without nesting, mispredictions during transient execution continue until the outer transient execution resolves.
+    // Note: The size of the encoding grows exponentially with the depth.
+    #[builder(default)]
+    nested_speculation_depth: usize,
+    // If enabled, stores are bypassed as predicted by the (trained) memory dependence predictor
+    // instead of the free predictor, see `MemoryDependencePredictor`.
+    #[builder(default)]
+    memory_dependence_predictor: bool,
+    // If enabled, indirect branches (e.g. returns) during transient execution follow their
+    // branch target, which may have been overwritten by a transient store (Spectre-BCBS aka. v1.1).
+    // Targets are limited to blocks which come later in the transient graph, as the graph must be acyclic.
+    #[builder(default)]
+    spectre_bcbs: bool,
+    // If enabled, the transient graph of each speculating instruction is further reduced by
+    // removing edges whose (predictor-independent) condition is constant false after propagating
+    // the constant assignments executed since the start of the transient execution.
+    #[builder(default)]
+    prune_constant_branches: bool,
+    // If enabled, only variables which are written on some transient path from the transient entry
+    // and are live at the rollback point are saved and restored, instead of all variables written
+    // by the transient graph. Nested transient executions still save all written variables.
+    // Not supported in combination with Spectre-BCBS, as transient indirect branches may jump to
+    // blocks which aren't reachable from the rollback point in the CFG.
+    #[builder(default)]
+    minimize_rollback: bool,
+    // Variables read by the observations, which are inserted after this transformation.
+    // They aren't live yet, therefore they are always restored on rollback (if written transiently).
+    #[builder(default)]
+    observable_variables: HashSet<Variable>,
+    // If enabled, the remaining speculation window is observed when the (outermost) transient execution resolves.
+    // This captures speculative interference, where secret-dependent transient work delays the resolution
+    // (and therefore the retirement of the following instructions), even if the final component states are equal.
+    // Requires intermediate resolve, as otherwise the speculation window isn't tracked.
+    #[builder(default)]
+    observe_resolution: bool,
+    // Max. number of blocks of the transformed CFG, checked before each transient copy is inserted.
+    #[builder(default)]
+    max_blocks: Option<usize>,
 }
 
 impl TransientExecution {
@@ -84,14 +138,19 @@ impl TransientExecution {
 
                 match inst.operation() {
                     Operation::Store { address, .. } => {
-                        if self.spectre_stl && !self.skip_stl(address) {
+                        if self.spectre_stl
+                            && !self.skip_stl(address)
+                            && !is_bypass_stopped_by_barrier(block, inst_index)
+                        {
                             // The `Store` instruction can speculatively be by-passed.
                             add_transient_execution_start(
                                 &mut default_cfg,
                                 &mut transient_start_rollback_points,
                                 &inst_ref,
+                                self.store_bypass(&inst_ref)?,
                                 self.speculation_window,
                                 self.intermediate_resolve,
+                                self.parametric_speculation_window,
                             )?;
                         }
                     }
@@ -102,8 +161,10 @@ impl TransientExecution {
                                 &mut default_cfg,
                                 &mut transient_start_rollback_points,
                                 &inst_ref,
+                                speculation_condition(&inst_ref)?,
                                 self.speculation_window,
                                 self.intermediate_resolve,
+                                self.parametric_speculation_window,
                             )?;
                         }
                     }
@@ -149,6 +210,7 @@ impl TransientExecution {
                                 &mut transient_cfg,
                                 &mut transient_entry_points,
                                 &inst_ref,
+                                self.store_bypass(&inst_ref)?,
                             )?;
                         }
                     }
@@ -162,15 +224,21 @@ impl TransientExecution {
                             )?;
                         }
                     }
-                    Operation::Barrier => {
-                        transient_barrier(&mut transient_cfg, &inst_ref)?;
+                    Operation::Barrier { kind } => {
+                        if kind.stops_speculation() {
+                            transient_barrier(&mut transient_cfg, &inst_ref)?;
+                        }
                     }
                     _ => (),
                 }
             }
         }
 
-        if self.intermediate_resolve {
+        if self.spectre_bcbs {
+            transient_indirect_branches(&mut transient_cfg)?;
+        }
+
+        if self.intermediate_resolve || self.parametric_speculation_window {
             add_transient_resolve_edges(&mut transient_cfg)?;
             append_spec_win_decrease_to_all_blocks(&mut transient_cfg)?;
         }
@@ -183,6 +251,190 @@ impl TransientExecution {
         Ok((transient_cfg, transient_entry_points))
     }
 
+    /// Builds the transient graph of the given speculation level (1 = outermost transient execution).
+    ///
+    /// As long as the max. nesting depth isn't exceeded, each speculating instruction of the transient graph
+    /// starts a nested transient execution (copy of the next level's transient graph) instead of directly
+    /// continuing on the mis-predicted path. On resolve, the nested transient execution rolls back to
+    /// the speculating instruction and the (outer) transient execution continues.
+    fn build_nested_transient_cfg(
+        &self,
+        cfg: &ControlFlowGraph,
+        level: usize,
+    ) -> Result<(ControlFlowGraph, BTreeMap<InstructionRef, usize>)> {
+        let (mut transient_cfg, transient_entry_points) = self.build_transient_cfg(cfg)?;
+        if level > self.nested_speculation_depth {
+            return Ok((transient_cfg, transient_entry_points));
+        }
+
+        let (nested_cfg, nested_entry_points) = self.build_nested_transient_cfg(cfg, level + 1)?;
+        let nested_level = level + 1;
+
+        for (inst_ref, &entry) in &transient_entry_points {
+            let nested_entry_point = nested_entry_points[inst_ref];
+
+            // The mis-predicted path is entered by the only speculate edge into the entry point,
+            // the other successor of its head continues on the correct path.
+            let (head, condition) = match transient_cfg
+                .edges_in(entry)?
+                .into_iter()
+                .find(|edge| edge.labels().is_speculate())
+            {
+                Some(edge) => (edge.head(), edge.condition().cloned()),
+                None => continue,
+            };
+            let rollback = match transient_cfg
+                .successor_indices(head)?
+                .into_iter()
+                .find(|&successor| successor != entry)
+            {
+                Some(rollback) => rollback,
+                None => continue,
+            };
+            let condition = condition.ok_or("Expected conditional speculate edge")?;
+
+            let mut reduced_nested_cfg = nested_cfg.clone();
+            if self.prune_constant_branches {
+                remove_constant_false_transient_edges(
+                    &mut reduced_nested_cfg,
+                    &[nested_entry_point],
+                )?;
+            }
+            remove_unreachable_transient_edges(
+                &mut reduced_nested_cfg,
+                &[nested_entry_point],
+                self.speculation_window,
+            )?;
+
+            record_transient_origin(&mut reduced_nested_cfg, inst_ref);
+
+            // The speculation window is shared with the outer transient execution
+            let saved_vars: HashSet<&Variable> = reorder_buffer_vars(&reduced_nested_cfg)
+                .into_iter()
+                .filter(|&var| *var != spec_win())
+                .collect();
+
+            let block_map = transient_cfg.insert(&reduced_nested_cfg)?;
+            let nested_entry = block_map[&nested_entry_point];
+            let nested_resolve = block_map[&reduced_nested_cfg.exit()?];
+
+            let nested_start = {
+                let block = transient_cfg.new_block();
+                block.set_transient();
+                if self.intermediate_resolve {
+                    start_nested_spec_win(block, inst_ref, nested_level)?;
+                }
+                block.index()
+            };
+
+            save_variables_of_level(
+                transient_cfg.block_mut(nested_entry)?,
+                &saved_vars,
+                nested_level,
+            )?;
+
+            let nested_resolve_block = transient_cfg.block_mut(nested_resolve)?;
+            restore_variables_of_level(nested_resolve_block, &saved_vars, nested_level)?;
+            if self.intermediate_resolve {
+                resolve_nested_spec_win(nested_resolve_block, nested_level)?;
+            }
+
+            transient_cfg.remove_edge(head, entry, RemovedEdgeGuard::Ignore)?;
+            transient_cfg
+                .conditional_edge(head, nested_start, condition)?
+                .labels_mut()
+                .speculate();
+            transient_cfg.unconditional_edge(nested_start, nested_entry)?;
+            transient_cfg
+                .unconditional_edge(nested_resolve, rollback)?
+                .labels_mut()
+                .rollback();
+        }
+
+        Ok((transient_cfg, transient_entry_points))
+    }
+
+    /// Reduces the transient graph to the part which is reachable from the given transient entry point
+    /// within the max. speculation window.
+    fn reduce_transient_cfg(
+        &self,
+        transient_cfg: &ControlFlowGraph,
+        transient_entry_point: usize,
+    ) -> Result<ControlFlowGraph> {
+        let mut reduced_transient_cfg = transient_cfg.clone();
+        if self.prune_constant_branches {
+            remove_constant_false_transient_edges(
+                &mut reduced_transient_cfg,
+                &[transient_entry_point],
+            )?;
+        }
+        remove_unreachable_transient_edges(
+            &mut reduced_transient_cfg,
+            &[transient_entry_point],
+            self.speculation_window,
+        )?;
+        Ok(reduced_transient_cfg)
+    }
+
+    /// Returns the copy of the transient graph which is inserted for the speculating instruction
+    /// at `address` (before insertion, i.e. without saving and restoring the modified variables),
+    /// or `None` if no transient execution starts at `address`.
+    ///
+    /// The copy only contains the blocks reachable from its transient entry, which is the entry of the
+    /// returned graph. If multiple instructions share the address (e.g. inlined functions), the first one is chosen.
+    pub fn transient_copy(
+        &self,
+        cfg: &ControlFlowGraph,
+        address: u64,
+    ) -> Result<Option<ControlFlowGraph>> {
+        let (_, transient_start_rollback_points) = self.build_default_cfg(cfg)?;
+        let (transient_cfg, transient_entry_points) = self.build_nested_transient_cfg(cfg, 1)?;
+
+        let inst_ref = match transient_start_rollback_points
+            .keys()
+            .find(|inst_ref| inst_ref.address() == address)
+        {
+            Some(inst_ref) => inst_ref,
+            None => return Ok(None),
+        };
+        let transient_entry_point = transient_entry_points[inst_ref];
+
+        let mut transient_copy =
+            self.reduce_transient_cfg(&transient_cfg, transient_entry_point)?;
+        record_transient_origin(&mut transient_copy, inst_ref);
+
+        let reachable = reachable_blocks(&transient_copy, transient_entry_point)?;
+        let unreachable: Vec<usize> = transient_copy
+            .blocks()
+            .iter()
+            .map(|block| block.index())
+            .filter(|index| !reachable.contains(index))
+            .collect();
+        for index in unreachable {
+            transient_copy.remove_block(index, RemovedEdgeGuard::Ignore)?;
+        }
+        transient_copy.set_entry(transient_entry_point)?;
+
+        Ok(Some(transient_copy))
+    }
+
+    /// The variable bounding the speculation window if the speculation window is parametric.
+    pub fn speculation_window_bound_variable() -> Variable {
+        spec_win_bound()
+    }
+
+    /// Returns the condition under which the store is speculatively by-passed.
+    fn store_bypass(&self, inst_ref: &InstructionRef) -> Result<Expression> {
+        if self.memory_dependence_predictor {
+            MemoryDependencePredictor::bypass(
+                MemoryDependencePredictor::variable().into(),
+                BitVector::word_constant(inst_ref.address()),
+            )
+        } else {
+            speculation_condition(inst_ref)
+        }
+    }
+
     fn skip_stl(&self, address: &Expression) -> bool {
         address
             .variables()
@@ -200,6 +452,16 @@ impl Default for TransientExecution {
             predictor_strategy: PredictorStrategy::default(),
             speculation_window: 100,
             intermediate_resolve: true,
+            share_transient_copies: false,
+            parametric_speculation_window: false,
+            nested_speculation_depth: 0,
+            memory_dependence_predictor: false,
+            spectre_bcbs: false,
+            prune_constant_branches: false,
+            minimize_rollback: false,
+            observable_variables: HashSet::default(),
+            observe_resolution: false,
+            max_blocks: None,
         }
     }
 }
@@ -225,10 +487,22 @@ impl Transform<ControlFlowGraph> for TransientExecution {
             )
             .into());
         }
+        if self.observe_resolution && !self.intermediate_resolve {
+            return Err("Observing the resolution requires intermediate resolve".into());
+        }
 
         let (mut default_cfg, transient_start_rollback_points) = self.build_default_cfg(cfg)?;
 
-        let (transient_cfg, transient_entry_points) = self.build_transient_cfg(cfg)?;
+        let (transient_cfg, transient_entry_points) = self.build_nested_transient_cfg(cfg, 1)?;
+
+        let live_variables = if self.minimize_rollback && !self.spectre_bcbs {
+            Some(analysis::live_variables(cfg)?)
+        } else {
+            None
+        };
+
+        // Inserted copies of the transient graph, grouped by their structure.
+        let mut inserted_copies: HashMap<TransientStructure, Vec<TransientCopy>> = HashMap::new();
 
         // Add copy of the transient graph for each speculating instruction into the default graph.
         // The transient graph is embedded into the default graph by adding transient start and
@@ -237,14 +511,60 @@ impl Transform<ControlFlowGraph> for TransientExecution {
             let transient_entry_point = transient_entry_points.get(&inst_ref).cloned().unwrap();
 
             // Reduce the size of the transient graph (depth limit by max. speculation window)
-            let mut reduced_transient_cfg = transient_cfg.clone();
-            remove_unreachable_transient_edges(
-                &mut reduced_transient_cfg,
-                &[transient_entry_point],
-                self.speculation_window,
+            let mut reduced_transient_cfg =
+                self.reduce_transient_cfg(&transient_cfg, transient_entry_point)?;
+
+            // Modified variables (registers + memory) which have to be restored on rollback
+            let saved_vars: HashSet<Variable> = match &live_variables {
+                Some(live_variables) => {
+                    let live = live_variables_at(cfg, live_variables, &inst_ref)?;
+                    transient_written_vars(&reduced_transient_cfg, transient_entry_point)?
+                        .into_iter()
+                        .filter(|var| live.contains(var) || self.observable_variables.contains(var))
+                        .collect()
+                }
+                None => reorder_buffer_vars(&reduced_transient_cfg)
+                    .into_iter()
+                    .cloned()
+                    .collect(),
+            };
+
+            let structure = if self.share_transient_copies {
+                Some(transient_structure(
+                    &reduced_transient_cfg,
+                    transient_entry_point,
+                )?)
+            } else {
+                None
+            };
+
+            if let Some(copies) = structure
+                .as_ref()
+                .and_then(|structure| inserted_copies.get_mut(structure))
+            {
+                let mut shared = false;
+                for copy in copies.iter_mut() {
+                    if copy.saved_vars.is_superset(&saved_vars)
+                        && copy.can_share(&default_cfg, start, rollback)?
+                    {
+                        copy.share(&mut default_cfg, start, rollback)?;
+                        shared = true;
+                        break;
+                    }
+                }
+                if shared {
+                    continue;
+                }
+            }
+
+            super::check_block_budget(
+                &default_cfg,
+                reduced_transient_cfg.blocks().len(),
+                self.max_blocks,
+                "Number of blocks during TransientExecution",
             )?;
 
-            let saved_vars = reorder_buffer_vars(&reduced_transient_cfg);
+            record_transient_origin(&mut reduced_transient_cfg, &inst_ref);
 
             let block_map = default_cfg.insert(&reduced_transient_cfg)?;
             let transient_entry = block_map[&transient_entry_point];
@@ -252,11 +572,15 @@ impl Transform<ControlFlowGraph> for TransientExecution {
 
             // Save modified variables (registers + memory) for restore on rollback
             let transient_entry_block = default_cfg.block_mut(transient_entry)?;
-            save_variables(transient_entry_block, &saved_vars)?;
+            save_variables(transient_entry_block, &saved_vars.iter().collect())?;
 
-            // "Discard mis-predicted reorder buffer entries" by restoring the saved variables
             let transient_resolve_block = default_cfg.block_mut(transient_resolve)?;
-            restore_variables(transient_resolve_block, &saved_vars)?;
+            if self.observe_resolution {
+                observe_remaining_spec_win(transient_resolve_block, &inst_ref);
+            }
+
+            // "Discard mis-predicted reorder buffer entries" by restoring the saved variables
+            restore_variables(transient_resolve_block, &saved_vars.iter().collect())?;
 
             default_cfg
                 .unconditional_edge(start, transient_entry)
@@ -266,6 +590,18 @@ impl Transform<ControlFlowGraph> for TransientExecution {
                 .unwrap()
                 .labels_mut()
                 .rollback();
+
+            if let Some(structure) = structure {
+                inserted_copies
+                    .entry(structure)
+                    .or_default()
+                    .push(TransientCopy {
+                        entry: transient_entry,
+                        resolve: transient_resolve,
+                        start_rollback_points: vec![(start, rollback)],
+                        saved_vars,
+                    });
+            }
         }
 
         default_cfg.remove_dead_end_blocks(RemovedEdgeGuard::Ignore)?;
@@ -277,6 +613,17 @@ impl Transform<ControlFlowGraph> for TransientExecution {
     }
 }
 
+/// Records the speculating instruction in all instructions of its transient copy.
+fn record_transient_origin(cfg: &mut ControlFlowGraph, inst_ref: &InstructionRef) {
+    for block in cfg.blocks_mut() {
+        for inst in block.instructions_mut() {
+            inst.add_origin(Origin::TransientExecution {
+                address: inst_ref.address(),
+            });
+        }
+    }
+}
+
 /// The set of variables (registers & memory) which would usually end up in the reorder buffer.
 fn reorder_buffer_vars(cfg: &ControlFlowGraph) -> HashSet<&Variable> {
     cfg.variables_written()
@@ -285,13 +632,64 @@ fn reorder_buffer_vars(cfg: &ControlFlowGraph) -> HashSet<&Variable> {
         .collect()
 }
 
-fn saved_variable_for(var: &Variable) -> Variable {
-    Variable::new(format!("_RB_{}", var.name()), var.sort().clone())
+/// The (non-persistent) variables written by any block reachable from the transient entry.
+fn transient_written_vars(cfg: &ControlFlowGraph, entry: usize) -> Result<HashSet<Variable>> {
+    let mut written = HashSet::new();
+    for index in reachable_blocks(cfg, entry)? {
+        for var in cfg.block(index)?.variables_written() {
+            if !var.is_rollback_persistent() {
+                written.insert(var.clone());
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// The variables live in front of the given instruction (of the original CFG),
+/// which is the rollback point of the transient execution started by the instruction.
+fn live_variables_at(
+    cfg: &ControlFlowGraph,
+    live_variables: &LiveVariables,
+    inst_ref: &InstructionRef,
+) -> Result<HashSet<Variable>> {
+    let block = cfg.block(inst_ref.block())?;
+    let mut live = live_variables.live_at_exit(block.index())?.clone();
+    for inst in block.instructions()[inst_ref.index()..].iter().rev() {
+        for var in inst.variables_written() {
+            live.remove(var);
+        }
+        for var in inst.variables_read() {
+            live.insert(var.clone());
+        }
+    }
+    Ok(live)
+}
+
+fn saved_variable_for(var: &Variable, level: usize) -> Variable {
+    let name = if level <= 1 {
+        format!("_RB_{}", var.name())
+    } else {
+        format!("_RB{}_{}", level, var.name())
+    };
+    Variable::new(name, var.sort().clone())
 }
 
 fn save_variables(block: &mut Block, variables: &HashSet<&Variable>) -> Result<()> {
+    save_variables_of_level(block, variables, 1)
+}
+
+fn restore_variables(block: &mut Block, variables: &HashSet<&Variable>) -> Result<()> {
+    restore_variables_of_level(block, variables, 1)
+}
+
+/// Save the variables of the given speculation level, nested levels use dedicated copies.
+fn save_variables_of_level(
+    block: &mut Block,
+    variables: &HashSet<&Variable>,
+    level: usize,
+) -> Result<()> {
     for &var in variables.iter() {
-        let saved_var = saved_variable_for(var);
+        let saved_var = saved_variable_for(var, level);
         block
             .assign(saved_var, var.clone().into())?
             .labels_mut()
@@ -300,9 +698,13 @@ fn save_variables(block: &mut Block, variables: &HashSet<&Variable>) -> Result<(
     Ok(())
 }
 
-fn restore_variables(block: &mut Block, variables: &HashSet<&Variable>) -> Result<()> {
+fn restore_variables_of_level(
+    block: &mut Block,
+    variables: &HashSet<&Variable>,
+    level: usize,
+) -> Result<()> {
     for &var in variables.iter() {
-        let saved_var = saved_variable_for(var);
+        let saved_var = saved_variable_for(var, level);
         block
             .assign(var.clone(), saved_var.into())?
             .labels_mut()
@@ -311,11 +713,193 @@ fn restore_variables(block: &mut Block, variables: &HashSet<&Variable>) -> Resul
     Ok(())
 }
 
+/// Structure of the (reduced) transient graph reachable from a transient entry point.
+///
+/// Block indices are replaced by the order in which the blocks are discovered,
+/// the resolve block is identified by its (replaced) index.
+type TransientStructure = (Option<usize>, Vec<(Block, Vec<Edge>)>);
+
+fn transient_structure(cfg: &ControlFlowGraph, entry: usize) -> Result<TransientStructure> {
+    let mut canonical_indices: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut discovered = Vec::new();
+
+    let mut stack = vec![entry];
+    while let Some(index) = stack.pop() {
+        if canonical_indices.contains_key(&index) {
+            continue;
+        }
+        canonical_indices.insert(index, discovered.len());
+        discovered.push(index);
+        for edge in cfg.edges_out(index)?.iter().rev() {
+            stack.push(edge.tail());
+        }
+    }
+
+    let mut blocks = Vec::with_capacity(discovered.len());
+    for index in discovered {
+        let canonical_index = canonical_indices[&index];
+        let block = cfg.block(index)?.clone_new_index(canonical_index);
+        let edges = cfg
+            .edges_out(index)?
+            .into_iter()
+            .map(|edge| edge.clone_new_head_tail(canonical_index, canonical_indices[&edge.tail()]))
+            .collect();
+        blocks.push((block, edges));
+    }
+
+    let resolve = canonical_indices.get(&cfg.exit()?).cloned();
+
+    Ok((resolve, blocks))
+}
+
+/// A copy of the transient graph inserted into the default graph,
+/// which is entered from (and rolls back to) one or more start/rollback points.
+struct TransientCopy {
+    entry: usize,
+    resolve: usize,
+    start_rollback_points: Vec<(usize, usize)>,
+    /// Variables restored on rollback (sharing requires that they cover the variables of the other pair)
+    saved_vars: HashSet<Variable>,
+}
+
+impl TransientCopy {
+    /// The copy can be shared with another start/rollback pair if this doesn't introduce a cycle,
+    /// i.e. the start isn't reachable from the copy and the copy isn't reachable from the rollback.
+    fn can_share(&self, cfg: &ControlFlowGraph, start: usize, rollback: usize) -> Result<bool> {
+        let reachable_from_entry = reachable_blocks(cfg, self.entry)?;
+        let reachable_from_rollback = reachable_blocks(cfg, rollback)?;
+        Ok(!reachable_from_entry.contains(&start)
+            && !reachable_from_rollback.contains(&self.resolve)
+            && !reachable_from_rollback.contains(&start))
+    }
+
+    /// Connects the start/rollback pair to this copy.
+    ///
+    /// The start block records its rollback point, which then selects the rollback edge on resolve.
+    fn share(&mut self, cfg: &mut ControlFlowGraph, start: usize, rollback: usize) -> Result<()> {
+        if let [(first_start, first_rollback)] = self.start_rollback_points[..] {
+            // The first pair was connected by an unconditional rollback edge
+            cfg.remove_edge(self.resolve, first_rollback, RemovedEdgeGuard::Ignore)?;
+            select_rollback_point(cfg, first_start, self.resolve, first_rollback)?;
+        }
+
+        cfg.unconditional_edge(start, self.entry)?;
+        select_rollback_point(cfg, start, self.resolve, rollback)?;
+
+        self.start_rollback_points.push((start, rollback));
+
+        Ok(())
+    }
+}
+
+fn select_rollback_point(
+    cfg: &mut ControlFlowGraph,
+    start: usize,
+    resolve: usize,
+    rollback: usize,
+) -> Result<()> {
+    let rollback_point = BitVector::word_constant(rollback as u64);
+
+    cfg.block_mut(start)?
+        .assign(rollback_point_variable(), rollback_point.clone())?
+        .labels_mut()
+        .pseudo();
+
+    let selected = Expression::equal(rollback_point_variable().into(), rollback_point)?;
+    cfg.conditional_edge(resolve, rollback, selected)?
+        .labels_mut()
+        .rollback();
+
+    Ok(())
+}
+
+/// Block indices reachable from the given block (including the block itself).
+fn reachable_blocks(cfg: &ControlFlowGraph, index: usize) -> Result<HashSet<usize>> {
+    let mut reachable = HashSet::new();
+    let mut stack = vec![index];
+    while let Some(index) = stack.pop() {
+        if reachable.insert(index) {
+            stack.extend(cfg.successor_indices(index)?);
+        }
+    }
+    Ok(reachable)
+}
+
+/// Rollback-Point Variable (index of the rollback block of a shared transient copy)
+fn rollback_point_variable() -> Variable {
+    BitVector::word_variable("_rollback_point")
+}
+
 /// Speculation-Window Variable
 fn spec_win() -> Variable {
     Variable::new("_spec_win", Sort::bit_vector(SPECULATION_WINDOW_SIZE))
 }
 
+/// Speculation-Window Bound Variable (parametric speculation window)
+fn spec_win_bound() -> Variable {
+    Variable::new("_spec_win_bound", Sort::bit_vector(SPECULATION_WINDOW_SIZE))
+}
+
+/// Remaining speculation window of the outer transient execution after a nested transient execution.
+fn spec_win_remaining(level: usize) -> Variable {
+    Variable::new(
+        format!("_spec_win_remaining_{}", level),
+        Sort::bit_vector(SPECULATION_WINDOW_SIZE),
+    )
+}
+
+/// The nested transient execution uses a part of the remaining (outer) speculation window:
+///   _spec_win_remaining := _spec_win - w
+///   _spec_win := w
+/// with 0 < w <= _spec_win.
+fn start_nested_spec_win(block: &mut Block, inst_ref: &InstructionRef, level: usize) -> Result<()> {
+    let spec_window = Predictor::speculation_window(
+        Predictor::variable().into(),
+        BitVector::word_constant(inst_ref.address()),
+    )?;
+    let zero = BitVector::constant_u64(0, SPECULATION_WINDOW_SIZE);
+
+    block
+        .assign(
+            spec_win_remaining(level),
+            BitVector::sub(spec_win().into(), spec_window.clone())?,
+        )?
+        .labels_mut()
+        .pseudo();
+    block
+        .assume(BitVector::sgt(spec_window.clone(), zero.clone())?)?
+        .labels_mut()
+        .pseudo();
+    block
+        .assume(BitVector::sge(spec_win_remaining(level).into(), zero)?)?
+        .labels_mut()
+        .pseudo();
+    block.assign(spec_win(), spec_window)?.labels_mut().pseudo();
+
+    Ok(())
+}
+
+/// The outer transient execution continues with the remaining speculation window.
+fn resolve_nested_spec_win(block: &mut Block, level: usize) -> Result<()> {
+    block
+        .assign(spec_win(), spec_win_remaining(level).into())?
+        .labels_mut()
+        .pseudo();
+    Ok(())
+}
+
+/// Makes the remaining speculation window observable,
+/// i.e. the number of transient instructions executed until the transient execution resolved.
+fn observe_remaining_spec_win(block: &mut Block, inst_ref: &InstructionRef) {
+    let obs = block.observable(spec_win().into());
+    obs.labels_mut().pseudo();
+    obs.set_provenance(
+        Provenance::observation()
+            .with_address(Some(inst_ref.address()))
+            .with_transform("TransientExecution"),
+    );
+}
+
 /// For transient execution start/rollback split the given block into 2 blocks [head] and [tail],
 /// add an additional [transient] block and add the following three edges between them:
 ///   - Conditional edge with "mis-predicted" from head to transient -> start transient execution
@@ -325,8 +909,10 @@ fn add_transient_execution_start(
     cfg: &mut ControlFlowGraph,
     transient_start_rollback_points: &mut BTreeMap<InstructionRef, (usize, usize)>,
     inst_ref: &InstructionRef,
+    transient_exec: Expression,
     max_spec_window: usize,
     intermediate_resolve: bool,
+    parametric_spec_window: bool,
 ) -> Result<()> {
     let head_index = inst_ref.block();
     let tail_index = cfg.split_block_at(head_index, inst_ref.index())?;
@@ -335,12 +921,17 @@ fn add_transient_execution_start(
         let transient_start = cfg.new_block();
         transient_start.set_transient();
 
-        if intermediate_resolve {
+        if intermediate_resolve || parametric_spec_window {
             // initial speculation window size
-            let spec_window = Predictor::speculation_window(
-                Predictor::variable().into(),
-                BitVector::word_constant(inst_ref.address()),
-            )?;
+            let spec_window = if intermediate_resolve {
+                Predictor::speculation_window(
+                    Predictor::variable().into(),
+                    BitVector::word_constant(inst_ref.address()),
+                )?
+            } else {
+                // Without intermediate resolve the whole window is used
+                spec_win_bound().into()
+            };
             transient_start
                 .assign(spec_win(), spec_window)?
                 .labels_mut()
@@ -362,16 +953,18 @@ fn add_transient_execution_start(
                 )?)?
                 .labels_mut()
                 .pseudo();
+
+            if intermediate_resolve && parametric_spec_window {
+                transient_start
+                    .assume(BitVector::sle(spec_win().into(), spec_win_bound().into())?)?
+                    .labels_mut()
+                    .pseudo();
+            }
         }
 
         transient_start.index()
     };
 
-    let transient_exec = Predictor::speculate(
-        Predictor::variable().into(),
-        BitVector::word_constant(inst_ref.address()),
-    )?;
-
     let normal_exec = Boolean::not(transient_exec.clone())?;
 
     cfg.conditional_edge(head_index, tail_index, normal_exec)?;
@@ -385,6 +978,14 @@ fn add_transient_execution_start(
     Ok(())
 }
 
+/// The speculation decision of the predictor for the instruction.
+fn speculation_condition(inst_ref: &InstructionRef) -> Result<Expression> {
+    Predictor::speculate(
+        Predictor::variable().into(),
+        BitVector::word_constant(inst_ref.address()),
+    )
+}
+
 /// The `Store` instruction can speculatively be by-passed during transient execution.
 /// Therefore, split the given block into 3 blocks [head], [store] and [tail]
 /// and add the following three edges between them:
@@ -395,15 +996,12 @@ fn transient_store(
     cfg: &mut ControlFlowGraph,
     transient_entry_points: &mut BTreeMap<InstructionRef, usize>,
     inst_ref: &InstructionRef,
+    bypass: Expression,
 ) -> Result<()> {
     let head_index = inst_ref.block();
     let store_index = cfg.split_block_at(head_index, inst_ref.index())?;
     let tail_index = cfg.split_block_at(store_index, 1)?;
 
-    let bypass = Predictor::speculate(
-        Predictor::variable().into(),
-        BitVector::word_constant(inst_ref.address()),
-    )?;
     let execute = Boolean::not(bypass.clone())?;
 
     cfg.conditional_edge(head_index, tail_index, bypass)?
@@ -556,7 +1154,69 @@ fn transient_conditional_branch(
     Ok(())
 }
 
-/// The `Barrier` instruction immediately stops the transient execution.
+/// Indirect branches (`Branch` with non-constant target) may jump to a transiently overwritten target.
+/// Therefore, split the block after each indirect branch and add conditional edges from the branch
+/// to each block (of a later topological position) whose address matches the branch target.
+/// Targets which don't match any of these blocks continue on the regular path.
+fn transient_indirect_branches(cfg: &mut ControlFlowGraph) -> Result<()> {
+    let top_sort = cfg.graph().compute_topological_ordering()?;
+
+    // Candidate targets (first block per address) after each topological position
+    let mut branches: Vec<(usize, usize, Expression, BTreeMap<u64, usize>)> = Vec::new();
+    for (position, &block_index) in top_sort.iter().enumerate() {
+        let block = cfg.block(block_index)?;
+        for (index, inst) in block.instructions().iter().enumerate().rev() {
+            if let Operation::Branch { target } = inst.operation() {
+                if target.is_constant() {
+                    continue;
+                }
+                let mut targets = BTreeMap::new();
+                for &candidate in &top_sort[position + 1..] {
+                    if let Some(address) = cfg.block(candidate)?.address() {
+                        targets.entry(address).or_insert(candidate);
+                    }
+                }
+                branches.push((block_index, index, target.clone(), targets));
+            }
+        }
+    }
+
+    for (block_index, index, target, targets) in branches {
+        if targets.is_empty() {
+            continue;
+        }
+
+        let tail_index = cfg.split_block_at(block_index, index + 1)?;
+
+        let mut hijacked = Vec::new();
+        for (address, target_index) in targets {
+            let is_target = Expression::equal(target.clone(), BitVector::word_constant(address))?;
+            cfg.conditional_edge(block_index, target_index, is_target.clone())?;
+            hijacked.push(is_target);
+        }
+
+        let regular = Boolean::not(Boolean::disjunction(&hijacked)?)?;
+        cfg.conditional_edge(block_index, tail_index, regular)?;
+    }
+
+    Ok(())
+}
+
+/// Returns true if the store at `inst_index` is followed by a barrier which stops speculation
+/// before any load of the block, i.e. the bypassed store can't be observed by any transient load.
+fn is_bypass_stopped_by_barrier(block: &Block, inst_index: usize) -> bool {
+    for inst in &block.instructions()[inst_index + 1..] {
+        match inst.operation() {
+            Operation::Barrier { kind } if kind.stops_speculation() => return true,
+            Operation::Load { .. } => return false,
+            _ => (),
+        }
+    }
+    false
+}
+
+/// A `Barrier` instruction which stops speculation (any kind except store fences) immediately
+/// stops the transient execution.
 /// Therefore, split the block and add an unconditional edge from head to the resolve block.
 fn transient_barrier(cfg: &mut ControlFlowGraph, inst_ref: &InstructionRef) -> Result<()> {
     let head_index = inst_ref.block();
@@ -734,13 +1394,107 @@ fn remove_unreachable_transient_edges(
     Ok(())
 }
 
+/// Removes all transient edges which can't be taken because their condition is constant false.
+///
+/// Constant assignments are propagated forward from the transient entry points, where nothing is known
+/// about the values of the variables. Each transient block starts with the constants all of its (feasible)
+/// predecessors agree on. Edge conditions which depend on the predictor never fold to a constant,
+/// therefore only the predictor-independent conditions (i.e. the actual branch conditions) are pruned.
+///
+/// This function may yield unreachable blocks which can be removed by simplifying the CFG.
+fn remove_constant_false_transient_edges(
+    cfg: &mut ControlFlowGraph,
+    transient_entries: &[usize],
+) -> Result<()> {
+    let mut constants_out: BTreeMap<usize, HashMap<Variable, Expression>> = BTreeMap::new();
+
+    let mut queue = transient_entries.to_owned();
+    while let Some(index) = queue.pop() {
+        let mut constants = if transient_entries.contains(&index) {
+            HashMap::new()
+        } else {
+            let mut incoming = cfg.edges_in(index)?.into_iter().filter_map(|edge| {
+                constants_out
+                    .get(&edge.head())
+                    .filter(|constants| !is_constant_false(edge, constants))
+            });
+            let mut constants = incoming.next().cloned().unwrap_or_default();
+            for other in incoming {
+                constants.retain(|var, value| other.get(var) == Some(value));
+            }
+            constants
+        };
+
+        for inst in cfg.block(index)?.instructions() {
+            match inst.operation() {
+                Operation::Assign { variable, expr } => {
+                    let mut value = expr.substitute(&constants);
+                    value.fold();
+                    if value.is_constant() {
+                        constants.insert(variable.clone(), value);
+                    } else {
+                        constants.remove(variable);
+                    }
+                }
+                _ => {
+                    for var in inst.variables_written() {
+                        constants.remove(var);
+                    }
+                }
+            }
+        }
+
+        if constants_out.get(&index) == Some(&constants) {
+            continue;
+        }
+
+        for edge in cfg.edges_out(index)? {
+            if !is_constant_false(edge, &constants) {
+                queue.push(edge.tail());
+            }
+        }
+        constants_out.insert(index, constants);
+    }
+
+    let infeasible_edges: Vec<(usize, usize)> = cfg
+        .edges()
+        .into_iter()
+        .filter(|edge| {
+            constants_out
+                .get(&edge.head())
+                .map_or(false, |constants| is_constant_false(edge, constants))
+        })
+        .map(|edge| (edge.head(), edge.tail()))
+        .collect();
+
+    for (head, tail) in infeasible_edges {
+        cfg.remove_edge(head, tail, RemovedEdgeGuard::Ignore)?;
+    }
+
+    Ok(())
+}
+
+fn is_constant_false(edge: &Edge, constants: &HashMap<Variable, Expression>) -> bool {
+    match edge.condition() {
+        Some(condition) => {
+            let mut condition = condition.substitute(constants);
+            condition.fold();
+            condition == Boolean::constant(false)
+        }
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::error::Error;
     use crate::expr::{BitVector, Boolean, Expression, Sort, Variable};
+    use crate::hir::BarrierKind;
     use crate::util::RenderGraph;
 
+    use std::collections::BTreeSet;
     use std::path::Path;
 
     fn debug_cfg(
@@ -796,7 +1550,13 @@ mod tests {
         // When:
         let mut encoded_cfg = given_cfg.clone();
         let mut transient_entry_points = BTreeMap::new();
-        transient_store(&mut encoded_cfg, &mut transient_entry_points, &inst_ref).unwrap();
+        transient_store(
+            &mut encoded_cfg,
+            &mut transient_entry_points,
+            &inst_ref,
+            speculation_condition(&inst_ref).unwrap(),
+        )
+        .unwrap();
 
         // Then:
         let expected_cfg = {
@@ -1231,7 +1991,7 @@ mod tests {
                     .assign(var.clone(), Boolean::constant(false))
                     .unwrap()
                     .set_address(Some(1));
-                block.barrier().set_address(Some(2));
+                block.barrier(BarrierKind::Full).set_address(Some(2));
                 block
                     .assign(var.clone(), Boolean::constant(true))
                     .unwrap()
@@ -1293,7 +2053,7 @@ mod tests {
 
             let block3_index = {
                 let block = cfg.new_block();
-                block.barrier().set_address(Some(2));
+                block.barrier(BarrierKind::Full).set_address(Some(2));
                 block
                     .assign(var.clone(), Boolean::constant(true))
                     .unwrap()
@@ -1319,4 +2079,674 @@ mod tests {
 
         assert_eq!(expected_cfg, encoded_cfg);
     }
+
+    #[test]
+    fn test_share_transient_copy_of_mutually_exclusive_stores() {
+        let addr: Expression = BitVector::word_variable("a").into();
+        let var = Variable::new("x", Sort::word());
+
+        // Given: two identical stores in mutually exclusive paths
+        let mut cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = {
+                let block = cfg.new_block();
+                block
+                    .assign(var.clone(), BitVector::word_constant(0))
+                    .unwrap()
+                    .set_address(Some(1));
+                block.index()
+            };
+
+            let store_block = |cfg: &mut ControlFlowGraph| {
+                let block = cfg.new_block();
+                block
+                    .store(addr.clone(), var.clone().into())
+                    .unwrap()
+                    .set_address(Some(2));
+                block.index()
+            };
+            let block1_index = store_block(&mut cfg);
+            let block2_index = store_block(&mut cfg);
+
+            let block3_index = {
+                let block = cfg.new_block();
+                block
+                    .load(var.clone(), addr.clone())
+                    .unwrap()
+                    .set_address(Some(3));
+                block.index()
+            };
+
+            let cond: Expression = Boolean::variable("c").into();
+            cfg.conditional_edge(block0_index, block1_index, cond.clone())
+                .unwrap();
+            cfg.conditional_edge(block0_index, block2_index, Boolean::not(cond).unwrap())
+                .unwrap();
+            cfg.unconditional_edge(block1_index, block3_index).unwrap();
+            cfg.unconditional_edge(block2_index, block3_index).unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block3_index).unwrap();
+
+            cfg
+        };
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .share_transient_copies(true)
+            .build()
+            .unwrap();
+        transient_execution.transform(&mut cfg).unwrap();
+
+        // Then: both stores roll back from the same transient copy
+        let rollback_edges: Vec<&Edge> = cfg
+            .edges()
+            .into_iter()
+            .filter(|edge| edge.labels().is_rollback())
+            .collect();
+        assert_eq!(rollback_edges.len(), 2);
+        assert_eq!(rollback_edges[0].head(), rollback_edges[1].head());
+        assert!(rollback_edges.iter().all(|edge| edge.is_conditional()));
+    }
+
+    #[test]
+    fn test_store_bypass_predicted_by_memory_dependence_predictor() {
+        // Given: a single store
+        let mut cfg = {
+            let mut cfg = ControlFlowGraph::new();
+            let block = cfg.new_block();
+            block
+                .store(
+                    BitVector::word_variable("a").into(),
+                    BitVector::word_constant(0),
+                )
+                .unwrap()
+                .set_address(Some(1));
+            let index = block.index();
+            cfg.set_entry(index).unwrap();
+            cfg.set_exit(index).unwrap();
+            cfg
+        };
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .memory_dependence_predictor(true)
+            .build()
+            .unwrap();
+        transient_execution.transform(&mut cfg).unwrap();
+
+        // Then: all speculate edges are conditioned on the MDP instead of the free predictor
+        let expected_bypass = MemoryDependencePredictor::bypass(
+            MemoryDependencePredictor::variable().into(),
+            BitVector::word_constant(1),
+        )
+        .unwrap();
+        let speculate_edges: Vec<&Edge> = cfg
+            .edges()
+            .into_iter()
+            .filter(|edge| edge.labels().is_speculate())
+            .collect();
+        assert!(!speculate_edges.is_empty());
+        for edge in speculate_edges {
+            assert_eq!(edge.condition(), Some(&expected_bypass));
+        }
+    }
+
+    #[test]
+    fn test_only_barriers_stopping_speculation_prevent_store_bypass() {
+        let speculate_edge_count = |kind: BarrierKind| {
+            // Given: a store followed by a barrier and a load
+            let mut cfg = {
+                let mut cfg = ControlFlowGraph::new();
+                let block = cfg.new_block();
+                block
+                    .store(
+                        BitVector::word_variable("a").into(),
+                        BitVector::word_constant(0),
+                    )
+                    .unwrap()
+                    .set_address(Some(1));
+                block.barrier(kind).set_address(Some(2));
+                block
+                    .load(
+                        BitVector::word_variable("x"),
+                        BitVector::word_variable("a").into(),
+                    )
+                    .unwrap()
+                    .set_address(Some(3));
+                let index = block.index();
+                cfg.set_entry(index).unwrap();
+                cfg.set_exit(index).unwrap();
+                cfg
+            };
+
+            // When:
+            let transient_execution = TransientExecutionBuilder::default()
+                .spectre_pht(false)
+                .spectre_stl(true)
+                .stl_ignored_variables(HashSet::default())
+                .predictor_strategy(PredictorStrategy::default())
+                .speculation_window(10)
+                .intermediate_resolve(false)
+                .build()
+                .unwrap();
+            transient_execution.transform(&mut cfg).unwrap();
+
+            cfg.edges()
+                .into_iter()
+                .filter(|edge| edge.labels().is_speculate())
+                .count()
+        };
+
+        // Then: only the store fence leaves the store bypass in place
+        assert_eq!(speculate_edge_count(BarrierKind::Full), 0);
+        assert_eq!(speculate_edge_count(BarrierKind::Load), 0);
+        assert_eq!(speculate_edge_count(BarrierKind::Speculation), 0);
+        assert!(speculate_edge_count(BarrierKind::Store) > 0);
+    }
+
+    #[test]
+    fn test_observe_remaining_speculation_window_on_resolve() {
+        let addr: Expression = BitVector::word_variable("a").into();
+
+        // Given: a single store
+        let mut cfg = ControlFlowGraph::new();
+        let block_index = {
+            let block = cfg.new_block();
+            block
+                .store(addr.clone(), BitVector::word_variable("x").into())
+                .unwrap()
+                .set_address(Some(1));
+            block.load(Variable::new("y", Sort::word()), addr).unwrap();
+            block.index()
+        };
+        cfg.set_entry(block_index).unwrap();
+        cfg.set_exit(block_index).unwrap();
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(true)
+            .observe_resolution(true)
+            .build()
+            .unwrap();
+        transient_execution.transform(&mut cfg).unwrap();
+
+        // Then: the block rolling back observes the remaining speculation window
+        let rollback_edges: Vec<&Edge> = cfg
+            .edges()
+            .into_iter()
+            .filter(|edge| edge.labels().is_rollback())
+            .collect();
+        assert_eq!(rollback_edges.len(), 1);
+        let resolve_block = cfg.block(rollback_edges[0].head()).unwrap();
+        assert!(resolve_block.is_transient());
+        assert!(resolve_block.instructions().iter().any(|inst| {
+            inst.operation() == &Operation::observable(spec_win().into())
+                && inst.provenance().map(Provenance::address) == Some(Some(1))
+        }));
+    }
+
+    #[test]
+    fn test_observe_resolution_requires_intermediate_resolve() {
+        // Given:
+        let mut cfg = ControlFlowGraph::new();
+        let block_index = cfg.new_block().index();
+        cfg.set_entry(block_index).unwrap();
+        cfg.set_exit(block_index).unwrap();
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(true)
+            .spectre_stl(false)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .observe_resolution(true)
+            .build()
+            .unwrap();
+
+        // Then:
+        assert!(transient_execution.transform(&mut cfg).is_err());
+    }
+
+    #[test]
+    fn test_minimized_rollback_restores_observable_variables() {
+        let observed = Variable::new("_observed", Sort::word());
+
+        // Given: a store followed by a write of an observable variable, which isn't read (yet)
+        let mut cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = {
+                let block = cfg.new_block();
+                block
+                    .store(
+                        BitVector::word_variable("a").into(),
+                        BitVector::word_variable("x").into(),
+                    )
+                    .unwrap()
+                    .set_address(Some(1));
+                block.index()
+            };
+
+            let block1_index = {
+                let block = cfg.new_block();
+                block
+                    .assign(observed.clone(), BitVector::word_constant(1))
+                    .unwrap()
+                    .set_address(Some(2));
+                block.index()
+            };
+
+            cfg.unconditional_edge(block0_index, block1_index).unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block1_index).unwrap();
+
+            cfg
+        };
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .minimize_rollback(true)
+            .observable_variables(vec![observed.clone()].into_iter().collect())
+            .build()
+            .unwrap();
+        transient_execution.transform(&mut cfg).unwrap();
+
+        // Then: the observable variable is saved at the transient entry
+        let saved = saved_variable_for(&observed, 1);
+        assert!(cfg.blocks().iter().any(|block| block
+            .instructions()
+            .iter()
+            .any(|inst| inst.operation()
+                == &Operation::assign(saved.clone(), observed.clone().into()).unwrap())));
+    }
+
+    #[test]
+    fn test_nested_speculation_rolls_back_into_transient_execution() {
+        let addr: Expression = BitVector::word_variable("a").into();
+
+        // Given: two consecutive stores
+        let mut cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = {
+                let block = cfg.new_block();
+                block
+                    .store(addr.clone(), BitVector::word_variable("x").into())
+                    .unwrap()
+                    .set_address(Some(1));
+                block.index()
+            };
+
+            let block1_index = {
+                let block = cfg.new_block();
+                block
+                    .store(addr.clone(), BitVector::word_variable("y").into())
+                    .unwrap()
+                    .set_address(Some(2));
+                block.index()
+            };
+
+            cfg.unconditional_edge(block0_index, block1_index).unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block1_index).unwrap();
+
+            cfg
+        };
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .nested_speculation_depth(1)
+            .build()
+            .unwrap();
+        transient_execution.transform(&mut cfg).unwrap();
+
+        // Then: bypassing the second store during the transient execution of the first store
+        // rolls back into the (outer) transient execution
+        let nested_rollback_edges = cfg
+            .edges()
+            .into_iter()
+            .filter(|edge| {
+                edge.labels().is_rollback() && cfg.block(edge.tail()).unwrap().is_transient()
+            })
+            .count();
+        assert_eq!(nested_rollback_edges, 1);
+    }
+
+    #[test]
+    fn test_transient_indirect_branch_follows_overwritten_target() {
+        let target: Expression = BitVector::word_variable("r").into();
+
+        // Given: a store followed by an indirect branch which falls through to two blocks
+        let mut cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = {
+                let block = cfg.new_block();
+                block
+                    .store(
+                        BitVector::word_variable("a").into(),
+                        BitVector::word_variable("x").into(),
+                    )
+                    .unwrap()
+                    .set_address(Some(1));
+                block.index()
+            };
+
+            let block1_index = {
+                let block = cfg.new_block();
+                block.branch(target.clone()).unwrap().set_address(Some(2));
+                block.index()
+            };
+
+            let block2_index = {
+                let block = cfg.new_block();
+                block
+                    .assign(Variable::new("y", Sort::word()), target.clone())
+                    .unwrap()
+                    .set_address(Some(3));
+                block.index()
+            };
+
+            let block3_index = {
+                let block = cfg.new_block();
+                block
+                    .assign(Variable::new("z", Sort::word()), target.clone())
+                    .unwrap()
+                    .set_address(Some(4));
+                block.index()
+            };
+
+            cfg.unconditional_edge(block0_index, block1_index).unwrap();
+            cfg.unconditional_edge(block1_index, block2_index).unwrap();
+            cfg.unconditional_edge(block2_index, block3_index).unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block3_index).unwrap();
+
+            cfg
+        };
+
+        // When:
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .spectre_bcbs(true)
+            .build()
+            .unwrap();
+        transient_execution.transform(&mut cfg).unwrap();
+
+        // Then: the transient indirect branch may jump to both of the following blocks
+        let transient_targets: HashSet<u64> = cfg
+            .edges()
+            .into_iter()
+            .filter(|edge| cfg.block(edge.head()).unwrap().is_transient())
+            .filter_map(|edge| {
+                let tail = cfg.block(edge.tail()).unwrap();
+                let expected_condition =
+                    Expression::equal(target.clone(), BitVector::word_constant(tail.address()?))
+                        .unwrap();
+                if edge.condition() == Some(&expected_condition) {
+                    tail.address()
+                } else {
+                    None
+                }
+            })
+            .collect();
+        assert_eq!(transient_targets, vec![3, 4].into_iter().collect());
+    }
+
+    #[test]
+    fn test_remove_constant_false_transient_edges() {
+        let x = BitVector::word_variable("x");
+        let y = BitVector::word_variable("y");
+        let x_is_one = Expression::equal(x.clone().into(), BitVector::word_constant(1)).unwrap();
+        let y_is_zero = Expression::equal(y.into(), BitVector::word_constant(0)).unwrap();
+
+        // Given: a branch on the constant `x` and a branch on the unknown `y`
+        let mut cfg = ControlFlowGraph::new();
+        let block0_index = {
+            let block = cfg.new_block();
+            block.assign(x, BitVector::word_constant(1)).unwrap();
+            block.index()
+        };
+        let block1_index = cfg.new_block().index();
+        let block2_index = cfg.new_block().index();
+        let block3_index = cfg.new_block().index();
+
+        cfg.conditional_edge(block0_index, block1_index, x_is_one.clone())
+            .unwrap();
+        cfg.conditional_edge(block0_index, block2_index, Boolean::not(x_is_one).unwrap())
+            .unwrap();
+        cfg.conditional_edge(block1_index, block3_index, y_is_zero.clone())
+            .unwrap();
+        cfg.conditional_edge(block1_index, block2_index, Boolean::not(y_is_zero).unwrap())
+            .unwrap();
+        cfg.unconditional_edge(block2_index, block3_index).unwrap();
+        cfg.set_entry(block0_index).unwrap();
+        cfg.set_exit(block3_index).unwrap();
+
+        // When:
+        remove_constant_false_transient_edges(&mut cfg, &[block0_index]).unwrap();
+
+        // Then: only the edge depending on the constant `x` is removed
+        let edges: BTreeSet<(usize, usize)> = cfg
+            .edges()
+            .into_iter()
+            .map(|edge| (edge.head(), edge.tail()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (block0_index, block1_index),
+                (block1_index, block2_index),
+                (block1_index, block3_index),
+                (block2_index, block3_index)
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn test_rollback_saves_only_live_transiently_written_variables() {
+        let x = BitVector::word_variable("x");
+        let y = BitVector::word_variable("y");
+        let z = BitVector::word_variable("z");
+
+        // Given: `y` is dead at the rollback point (instruction 1 of block 0)
+        let mut cfg = ControlFlowGraph::new();
+        let block0_index = {
+            let block = cfg.new_block();
+            block
+                .assign(y.clone(), BitVector::word_constant(0))
+                .unwrap();
+            block
+                .load(z.clone(), x.clone().into())
+                .unwrap()
+                .set_address(Some(2));
+            block.assign(y.clone(), z.clone().into()).unwrap();
+            block.index()
+        };
+        let block1_index = {
+            let block = cfg.new_block();
+            block.observable(x.clone().into());
+            block.index()
+        };
+        cfg.unconditional_edge(block0_index, block1_index).unwrap();
+        cfg.set_entry(block0_index).unwrap();
+        cfg.set_exit(block1_index).unwrap();
+
+        let inst_ref = InstructionRefBuilder::default()
+            .block(block0_index)
+            .index(1)
+            .address(2)
+            .build()
+            .unwrap();
+
+        // When:
+        let live_variables = analysis::live_variables(&cfg).unwrap();
+        let live = live_variables_at(&cfg, &live_variables, &inst_ref).unwrap();
+        let written = transient_written_vars(&cfg, block0_index).unwrap();
+
+        // Then: neither `y` nor `z` has to be restored, as both are overwritten before being read
+        assert!(live.contains(&x));
+        assert_eq!(written, vec![y, z].into_iter().collect());
+        assert!(written.iter().all(|var| !live.contains(var)));
+    }
+
+    #[test]
+    fn test_transient_copy_of_single_speculating_instruction() {
+        let addr: Expression = BitVector::word_variable("a").into();
+
+        // Given: two consecutive stores
+        let cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block0_index = {
+                let block = cfg.new_block();
+                block
+                    .store(addr.clone(), BitVector::word_variable("x").into())
+                    .unwrap()
+                    .set_address(Some(1));
+                block.index()
+            };
+
+            let block1_index = {
+                let block = cfg.new_block();
+                block
+                    .store(addr.clone(), BitVector::word_variable("y").into())
+                    .unwrap()
+                    .set_address(Some(2));
+                block.index()
+            };
+
+            cfg.unconditional_edge(block0_index, block1_index).unwrap();
+
+            cfg.set_entry(block0_index).unwrap();
+            cfg.set_exit(block1_index).unwrap();
+
+            cfg
+        };
+
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .build()
+            .unwrap();
+
+        // When:
+        let transient_copy = transient_execution.transient_copy(&cfg, 2).unwrap();
+        let no_transient_copy = transient_execution.transient_copy(&cfg, 3).unwrap();
+
+        // Then: the copy of the second store only contains transient blocks reachable from its entry
+        let transient_copy = transient_copy.unwrap();
+        let entry = transient_copy.entry().unwrap();
+        let reachable = reachable_blocks(&transient_copy, entry).unwrap();
+        assert_eq!(reachable.len(), transient_copy.blocks().len());
+        assert!(transient_copy
+            .blocks()
+            .iter()
+            .all(|block| block.is_transient()));
+        assert!(no_transient_copy.is_none());
+    }
+
+    #[test]
+    fn test_transient_copy_exceeding_the_block_budget_is_not_inserted() {
+        let addr: Expression = BitVector::word_variable("a").into();
+
+        // Given: a single store
+        let given_cfg = {
+            let mut cfg = ControlFlowGraph::new();
+
+            let block_index = {
+                let block = cfg.new_block();
+                block
+                    .store(addr, BitVector::word_variable("x").into())
+                    .unwrap()
+                    .set_address(Some(1));
+                block.index()
+            };
+
+            cfg.set_entry(block_index).unwrap();
+            cfg.set_exit(block_index).unwrap();
+
+            cfg
+        };
+
+        let transient_execution = TransientExecutionBuilder::default()
+            .spectre_pht(false)
+            .spectre_stl(true)
+            .stl_ignored_variables(HashSet::default())
+            .predictor_strategy(PredictorStrategy::default())
+            .speculation_window(10)
+            .intermediate_resolve(false)
+            .max_blocks(Some(3))
+            .build()
+            .unwrap();
+
+        // When:
+        let mut cfg = given_cfg.clone();
+        let result = transient_execution.transform(&mut cfg);
+
+        // Then: fails before the transient copy is inserted, leaving the CFG untouched
+        match result {
+            Err(Error::BudgetExceeded {
+                what,
+                budget,
+                measured,
+                limit,
+                ..
+            }) => {
+                assert_eq!(what, "Number of blocks during TransientExecution");
+                assert_eq!(budget, "analysis.max_blocks");
+                assert!(measured > 3);
+                assert_eq!(limit, 3);
+            }
+            other => panic!("Expected exceeded block budget, but got {:?}", other),
+        }
+        assert_eq!(cfg.blocks().len(), given_cfg.blocks().len());
+    }
 }
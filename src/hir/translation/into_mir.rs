@@ -1,24 +1,53 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::expr::{Boolean, Expression, Memory};
 use crate::hir;
-use crate::ir::TryTranslateInto;
+use crate::ir::{Provenance, TryTranslateInto};
 use crate::mir;
-
-/// We have a 2-safety hyperproperty
-const NUMBER_OF_SELF_COMPOSITIONS: usize = 2;
+use std::collections::BTreeMap;
 
 impl TryTranslateInto<mir::Program> for hir::InlinedProgram {
     fn try_translate_into(&self) -> Result<mir::Program> {
-        let block_graph = translate_control_flow_graph(self.control_flow_graph())?;
-        Ok(mir::Program::new(block_graph, NUMBER_OF_SELF_COMPOSITIONS))
+        let self_compositions = self.self_compositions();
+        if self_compositions < 2 {
+            return Err("Self-composition requires at least 2 compositions".into());
+        }
+
+        let block_graph =
+            translate_control_flow_graph(self.control_flow_graph(), Some(self_compositions))?;
+        Ok(mir::Program::new(block_graph, self_compositions))
+    }
+}
+
+/// Translates different programs into their product program, composition `i` executes `self[i]`.
+///
+/// The programs are related by their initial and final states only: Indistinguishable and observable
+/// instructions are required in the entry and exit blocks, where they are paired across the programs by
+/// their expression (ignoring SSA versions). Indistinguishable expressions become shared low inputs,
+/// observable expressions have to be equal in all programs.
+impl TryTranslateInto<mir::Program> for [hir::InlinedProgram] {
+    fn try_translate_into(&self) -> Result<mir::Program> {
+        let mut block_graphs = Vec::new();
+        for program in self {
+            // The hyper nodes are added to the product instead of the individual block graphs
+            block_graphs.push(translate_control_flow_graph(
+                program.control_flow_graph(),
+                None,
+            )?);
+        }
+
+        let product_nodes = product_hyper_nodes(self)?;
+        mir::Program::product(block_graphs, product_nodes)
     }
 }
 
-fn translate_control_flow_graph(cfg: &hir::ControlFlowGraph) -> Result<mir::BlockGraph> {
+fn translate_control_flow_graph(
+    cfg: &hir::ControlFlowGraph,
+    self_compositions: Option<usize>,
+) -> Result<mir::BlockGraph> {
     let mut block_graph = mir::BlockGraph::new();
 
     for block in cfg.blocks() {
-        block_graph.add_block(translate_block(cfg, block)?)?;
+        block_graph.add_block(translate_block(cfg, block, self_compositions)?)?;
     }
 
     for edge in cfg.edges() {
@@ -35,7 +64,12 @@ fn translate_control_flow_graph(cfg: &hir::ControlFlowGraph) -> Result<mir::Bloc
 ///   - making the control-flow explicit by computing the block execution condition
 ///   - translating all instructions into corresponding MIR nodes
 ///   - translating phi nodes into MIR assignments
-fn translate_block(cfg: &hir::ControlFlowGraph, src_block: &hir::Block) -> Result<mir::Block> {
+///   - adding the address (and disassembly) of the translated machine instructions as comments
+fn translate_block(
+    cfg: &hir::ControlFlowGraph,
+    src_block: &hir::Block,
+    self_compositions: Option<usize>,
+) -> Result<mir::Block> {
     let mut block = mir::Block::new(src_block.index());
 
     block.set_execution_condition(compute_execution_condition(cfg, block.index())?);
@@ -45,8 +79,15 @@ fn translate_block(cfg: &hir::ControlFlowGraph, src_block: &hir::Block) -> Resul
         block.add_node(mir::Node::assign(phi_node.out().clone(), expr)?);
     }
 
+    let mut current_address = None;
     for instruction in src_block.instructions() {
-        if let Some(node) = translate_operation(instruction.operation())? {
+        if instruction.address().is_some() && instruction.address() != current_address {
+            current_address = instruction.address();
+            block.add_node(mir::Node::comment(address_comment(instruction)));
+        }
+        if let Some(node) =
+            translate_instruction(instruction, self_compositions, src_block.is_transient())?
+        {
             block.add_node(node);
         }
     }
@@ -54,20 +95,58 @@ fn translate_block(cfg: &hir::ControlFlowGraph, src_block: &hir::Block) -> Resul
     Ok(block)
 }
 
-fn translate_operation(operation: &hir::Operation) -> Result<Option<mir::Node>> {
+fn address_comment(instruction: &hir::Instruction) -> String {
+    let address = instruction.address().unwrap_or_default();
+    match instruction.disassembly() {
+        Some(disassembly) => format!("0x{:X}: {}", address, disassembly),
+        None => format!("0x{:X}", address),
+    }
+}
+
+/// Assertions without explicit provenance get a default one based on their operation.
+fn provenance(instruction: &hir::Instruction, default: Provenance, transient: bool) -> Provenance {
+    let provenance = instruction
+        .provenance()
+        .cloned()
+        .unwrap_or(default)
+        .with_transient(transient);
+    if provenance.address().is_some() {
+        provenance
+    } else {
+        provenance.with_address(instruction.address())
+    }
+}
+
+/// `transient` tells whether the instruction is executed transiently (classifies assertions).
+/// Observable and indistinguishable instructions are skipped without `self_compositions` (product programs).
+fn translate_instruction(
+    instruction: &hir::Instruction,
+    self_compositions: Option<usize>,
+    transient: bool,
+) -> Result<Option<mir::Node>> {
     use hir::Operation::*;
-    let node = match operation {
+    let self_compositions = match self_compositions {
+        Some(self_compositions) => self_compositions,
+        None if instruction.is_observable() || instruction.is_indistinguishable() => {
+            return Ok(None)
+        }
+        None => 1, // only used by observable and indistinguishable instructions
+    };
+    let node = match instruction.operation() {
         Assign { variable, expr } => Some(mir::Node::assign(variable.clone(), expr.clone())?),
-        Assert { condition } => Some(mir::Node::assert(condition.clone())?),
+        Assert { condition } => Some(mir::Node::assert(
+            condition.clone(),
+            provenance(instruction, Provenance::user(), transient),
+        )?),
         Assume { condition } => Some(mir::Node::assume(condition.clone())?),
-        Observable { expr } => Some(mir::Node::hyper_assert(equal_under_self_composition(&[
-            expr.clone(),
-        ]))?),
-        Indistinguishable { expr } => {
-            Some(mir::Node::hyper_assume(equal_under_self_composition(&[
-                expr.clone(),
-            ]))?)
-        }
+        Observable { expr } => Some(mir::Node::hyper_assert(
+            equal_under_self_composition(&[expr.clone()], self_compositions),
+            provenance(instruction, Provenance::observation(), transient),
+        )?),
+        Indistinguishable { expr } => Some(mir::Node::hyper_assume(equal_under_self_composition(
+            &[expr.clone()],
+            self_compositions,
+        ))?),
         Store {
             address,
             expr,
@@ -93,7 +172,11 @@ fn translate_operation(operation: &hir::Operation) -> Result<Option<mir::Node>>
             )?)
         }
         Skip { .. } => None,
-        Call { .. } | Branch { .. } | ConditionalBranch { .. } | Barrier => {
+        CacheFlush { .. } | Prefetch { .. } => {
+            // Ignore because their effects are encoded explicitly
+            None
+        }
+        Call { .. } | Branch { .. } | ConditionalBranch { .. } | Barrier { .. } => {
             // Ignore because they are already implicitly encoded into the CFG
             None
         }
@@ -168,14 +251,255 @@ fn compute_phi_expr(
     Ok(phi_expr.unwrap())
 }
 
-/// Create an expression to enforce equality of all given expression under 2-way self composition.
-/// For example, let `exprs` be `[x, y]` then this function will produce `x@0 == x@1 /\ y@0 == y@1`.
-fn equal_under_self_composition(exprs: &[Expression]) -> Expression {
+/// Create an expression to enforce equality of all given expression under k-way self composition.
+/// For example, let `exprs` be `[x, y]` and k be 3 then this function will produce
+/// `x@0 == x@1 /\ x@0 == x@2 /\ y@0 == y@1 /\ y@0 == y@2`.
+fn equal_under_self_composition(exprs: &[Expression], self_compositions: usize) -> Expression {
     Boolean::conjunction(
         &exprs
             .iter()
-            .map(|e| Expression::equal(e.self_compose(0), e.self_compose(1)).unwrap())
+            .flat_map(|e| {
+                (1..self_compositions)
+                    .map(move |i| Expression::equal(e.self_compose(0), e.self_compose(i)).unwrap())
+            })
             .collect::<Vec<Expression>>(),
     )
     .unwrap()
 }
+
+/// Observable and indistinguishable instructions of a program, keyed by kind and expression shape.
+type HyperInstructions<'a> = BTreeMap<(bool, String), Vec<(usize, &'a hir::Instruction)>>;
+
+/// Pairs the observable and indistinguishable instructions of the entry and exit blocks of all programs.
+fn product_hyper_nodes(programs: &[hir::InlinedProgram]) -> Result<Vec<mir::Node>> {
+    let hyper_instructions = programs
+        .iter()
+        .enumerate()
+        .map(|(composition, program)| hyper_instructions(program, composition))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut nodes = Vec::new();
+    for ((observable, shape), instructions) in &hyper_instructions[0] {
+        for (position, (block, instruction)) in instructions.iter().enumerate() {
+            let mut executed = vec![execution_condition(*block, 0)];
+            let mut equalities = Vec::new();
+            for (composition, other_instructions) in hyper_instructions.iter().enumerate().skip(1) {
+                let counterpart = other_instructions
+                    .get(&(*observable, shape.clone()))
+                    .and_then(|instructions| instructions.get(position));
+                match counterpart {
+                    Some((other_block, other_instruction)) => {
+                        executed.push(execution_condition(*other_block, composition));
+                        equalities.push(Expression::equal(
+                            hyper_expression(instruction).self_compose(0),
+                            hyper_expression(other_instruction).self_compose(composition),
+                        )?);
+                    }
+                    // Low inputs which are only read by some of the programs aren't shared
+                    None if !observable => {}
+                    None => {
+                        return Err(Error::MissingObservationCounterpart {
+                            observation: shape.clone(),
+                            program: 0,
+                            other_program: composition,
+                        })
+                    }
+                }
+            }
+            if equalities.is_empty() {
+                continue;
+            }
+
+            let condition = Boolean::imply(
+                Boolean::conjunction(&executed)?, // only if executed
+                Boolean::conjunction(&equalities)?,
+            )?;
+            nodes.push(if *observable {
+                mir::Node::hyper_assert(
+                    condition,
+                    provenance(instruction, Provenance::observation(), false),
+                )?
+            } else {
+                mir::Node::hyper_assume(condition)?
+            });
+        }
+    }
+
+    // Observations of the other programs have to be paired as well
+    for (composition, instructions) in hyper_instructions.iter().enumerate().skip(1) {
+        for ((observable, shape), instructions) in instructions {
+            let paired = hyper_instructions[0]
+                .get(&(*observable, shape.clone()))
+                .map_or(0, Vec::len);
+            if *observable && instructions.len() > paired {
+                return Err(Error::MissingObservationCounterpart {
+                    observation: shape.clone(),
+                    program: composition,
+                    other_program: 0,
+                });
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn hyper_instructions(
+    program: &hir::InlinedProgram,
+    composition: usize,
+) -> Result<HyperInstructions<'_>> {
+    let cfg = program.control_flow_graph();
+    let (entry, exit) = (cfg.entry()?, cfg.exit()?);
+
+    let mut instructions = HyperInstructions::new();
+    for block in cfg.blocks() {
+        for instruction in block.instructions() {
+            if !instruction.is_observable() && !instruction.is_indistinguishable() {
+                continue;
+            }
+            if block.index() != entry && block.index() != exit {
+                return Err(format!(
+                    "Product programs can only relate the initial and final states, but program {} observes `{}` in block 0x{:X} \
+                     (use `observe: trace` or `observe: sequential`)",
+                    composition,
+                    instruction.operation(),
+                    block.index()
+                )
+                .into());
+            }
+            let key = (
+                instruction.is_observable(),
+                unversioned(hyper_expression(instruction)).to_string(),
+            );
+            instructions
+                .entry(key)
+                .or_default()
+                .push((block.index(), instruction));
+        }
+    }
+
+    Ok(instructions)
+}
+
+fn hyper_expression(instruction: &hir::Instruction) -> &Expression {
+    match instruction.operation() {
+        hir::Operation::Observable { expr } | hir::Operation::Indistinguishable { expr } => expr,
+        _ => unreachable!("expected observable or indistinguishable instruction"),
+    }
+}
+
+/// The expression without SSA versions, which identifies it across programs.
+fn unversioned(expr: &Expression) -> Expression {
+    let mut expr = expr.clone();
+    for var in expr.variables_mut() {
+        var.set_version(None);
+    }
+    expr
+}
+
+fn execution_condition(block: usize, composition: usize) -> Expression {
+    mir::Block::execution_condition_variable_for_index(block)
+        .self_compose(composition)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Variable};
+
+    fn ssa_var(name: &str, version: usize) -> Variable {
+        let mut var = BitVector::word_variable(name);
+        var.set_version(Some(version));
+        var
+    }
+
+    /// Program with entry `indistinguishable(x)`, a block in between and exit `observable(y)`.
+    fn program(version: usize, observe_in_between: bool) -> hir::InlinedProgram {
+        let mut cfg = hir::ControlFlowGraph::new();
+        let entry = {
+            let block = cfg.new_block();
+            block.indistinguishable(ssa_var("x", version).into());
+            block.index()
+        };
+        let body = {
+            let block = cfg.new_block();
+            if observe_in_between {
+                block.observable(ssa_var("x", version).into());
+            }
+            block.index()
+        };
+        let exit = {
+            let block = cfg.new_block();
+            block.observable(ssa_var("y", version).into());
+            block.index()
+        };
+        cfg.unconditional_edge(entry, body).unwrap();
+        cfg.unconditional_edge(body, exit).unwrap();
+        cfg.set_entry(entry).unwrap();
+        cfg.set_exit(exit).unwrap();
+        hir::InlinedProgram::new(cfg)
+    }
+
+    #[test]
+    fn test_product_relates_entry_and_exit_of_different_programs() {
+        // Given: the same variables have different SSA versions in both programs
+        let programs = vec![program(1, false), program(2, false)];
+
+        // When
+        let product: mir::Program = programs.as_slice().try_translate_into().unwrap();
+
+        // Then: shared low input x and equal observation of y
+        assert!(product.is_product());
+        assert_eq!(product.self_compositions(), 2);
+        assert_eq!(product.product_nodes().len(), 2);
+        let observation = product
+            .product_nodes()
+            .iter()
+            .find(|node| node.is_hyper_assert())
+            .unwrap();
+        assert!(observation
+            .variables()
+            .contains(&&ssa_var("y", 1).self_compose(0)));
+        assert!(observation
+            .variables()
+            .contains(&&ssa_var("y", 2).self_compose(1)));
+    }
+
+    #[test]
+    fn test_product_rejects_observations_in_between() {
+        // Given
+        let programs = vec![program(1, true), program(1, false)];
+
+        // When
+        let result: Result<mir::Program> = programs.as_slice().try_translate_into();
+
+        // Then
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_product_rejects_observation_without_counterpart() {
+        // Given: only program 1 additionally observes z at its exit
+        let mut other = program(2, false);
+        other
+            .control_flow_graph_mut()
+            .exit_block_mut()
+            .unwrap()
+            .observable(ssa_var("z", 2).into());
+        let programs = vec![program(1, false), other];
+
+        // When
+        let result: Result<mir::Program> = programs.as_slice().try_translate_into();
+
+        // Then
+        assert!(matches!(
+            result,
+            Err(Error::MissingObservationCounterpart {
+                program: 1,
+                other_program: 0,
+                ..
+            })
+        ));
+    }
+}
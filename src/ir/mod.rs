@@ -1,7 +1,9 @@
+mod provenance;
 mod transform;
 mod translate;
 mod validate;
 
+pub use provenance::{AssertionKind, Provenance, ViolationClass, ViolationPolicy};
 pub use transform::Transform;
 pub use translate::{TryTranslateFrom, TryTranslateInto};
 pub use validate::Validate;
@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The reason why an assertion exists.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AssertionKind {
+    /// Observations must be equal in all compositions.
+    #[serde(rename = "observation")]
+    Observation,
+    /// Loops must not iterate more often than the unwinding bound.
+    #[serde(rename = "unwinding")]
+    Unwinding,
+    /// Assertion given in the analyzed program.
+    #[serde(rename = "user")]
+    User,
+    /// Transient loads must only access valid memory.
+    #[serde(rename = "memory_safety")]
+    MemorySafety,
+}
+
+impl fmt::Display for AssertionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Observation => write!(f, "observation"),
+            Self::Unwinding => write!(f, "unwinding"),
+            Self::User => write!(f, "user"),
+            Self::MemorySafety => write!(f, "memory safety"),
+        }
+    }
+}
+
+/// Provenance of an assertion, carried from HIR down to the SMT encoding.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    kind: AssertionKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transform: Option<String>,
+    /// The assertion is checked during transient execution
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    transient: bool,
+}
+
+impl Provenance {
+    pub fn new(kind: AssertionKind) -> Self {
+        Self {
+            kind,
+            address: None,
+            transform: None,
+            transient: false,
+        }
+    }
+
+    pub fn observation() -> Self {
+        Self::new(AssertionKind::Observation)
+    }
+
+    pub fn unwinding() -> Self {
+        Self::new(AssertionKind::Unwinding)
+    }
+
+    pub fn user() -> Self {
+        Self::new(AssertionKind::User)
+    }
+
+    pub fn memory_safety() -> Self {
+        Self::new(AssertionKind::MemorySafety)
+    }
+
+    /// Sets the source address the assertion refers to.
+    pub fn with_address(mut self, address: Option<u64>) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets the name of the transformation which added the assertion.
+    pub fn with_transform<S: Into<String>>(mut self, transform: S) -> Self {
+        self.transform = Some(transform.into());
+        self
+    }
+
+    /// Marks whether the assertion is checked during transient execution.
+    pub fn with_transient(mut self, transient: bool) -> Self {
+        self.transient = transient;
+        self
+    }
+
+    pub fn kind(&self) -> AssertionKind {
+        self.kind
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+
+    /// Returns the class of a violation of this assertion.
+    pub fn violation_class(&self) -> ViolationClass {
+        match self.kind {
+            AssertionKind::Observation if self.transient => ViolationClass::TransientLeak,
+            AssertionKind::Observation => ViolationClass::NormalLeak,
+            AssertionKind::Unwinding => ViolationClass::Unwinding,
+            AssertionKind::User => ViolationClass::User,
+            AssertionKind::MemorySafety => ViolationClass::MemorySafety,
+        }
+    }
+
+    pub fn address(&self) -> Option<u64> {
+        self.address
+    }
+
+    pub fn transform(&self) -> Option<&str> {
+        self.transform.as_deref()
+    }
+
+    /// Returns the name of the `index`-th assertion in the SMT encoding,
+    /// e.g. `_assertion3_observation_401a20`.
+    pub fn assertion_name(&self, index: usize) -> String {
+        match self.address {
+            Some(address) => format!("_assertion{}_{}_{:x}", index, self.kind, address),
+            None => format!("_assertion{}_{}", index, self.kind),
+        }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if self.transient {
+            write!(f, " (transient)")?;
+        }
+        if let Some(address) = self.address {
+            write!(f, " at 0x{:X}", address)?;
+        }
+        if let Some(transform) = &self.transform {
+            write!(f, " (added by {})", transform)?;
+        }
+        Ok(())
+    }
+}
+
+/// Class of assertion violations, e.g. to decide which violations fail a check.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ViolationClass {
+    /// An observation differs during transient execution.
+    TransientLeak,
+    /// An observation differs during normal (architectural) execution.
+    NormalLeak,
+    Unwinding,
+    User,
+    MemorySafety,
+}
+
+impl ViolationClass {
+    pub const NAMES: &'static [&'static str] = &[
+        "transient-leak",
+        "normal-leak",
+        "unwinding",
+        "user",
+        "memory-safety",
+    ];
+}
+
+impl fmt::Display for ViolationClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransientLeak => write!(f, "transient-leak"),
+            Self::NormalLeak => write!(f, "normal-leak"),
+            Self::Unwinding => write!(f, "unwinding"),
+            Self::User => write!(f, "user"),
+            Self::MemorySafety => write!(f, "memory-safety"),
+        }
+    }
+}
+
+impl FromStr for ViolationClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transient-leak" => Ok(Self::TransientLeak),
+            "normal-leak" => Ok(Self::NormalLeak),
+            "unwinding" => Ok(Self::Unwinding),
+            "user" => Ok(Self::User),
+            "memory-safety" => Ok(Self::MemorySafety),
+            _ => Err(format!("Unknown violation class '{}'", s)),
+        }
+    }
+}
+
+/// Decides which violations fail a check and which are warnings only (`--fail-on`, `--warn-only`).
+#[derive(Clone, Debug, Default)]
+pub struct ViolationPolicy {
+    /// Classes whose violations fail the check, all if not given
+    fail_on: Option<Vec<ViolationClass>>,
+    /// Classes whose violations are warnings only, takes precedence over `fail_on`
+    warn_only: Vec<ViolationClass>,
+}
+
+impl ViolationPolicy {
+    pub const EXIT_CODE_FAILED: i32 = 2;
+
+    pub fn new(fail_on: Option<Vec<ViolationClass>>, warn_only: Vec<ViolationClass>) -> Self {
+        Self { fail_on, warn_only }
+    }
+
+    /// Checks if a violation of the assertion fails the check, or is a warning only.
+    pub fn is_failing(&self, provenance: &Provenance) -> bool {
+        let class = provenance.violation_class();
+        let failing = self
+            .fail_on
+            .as_ref()
+            .map_or(true, |classes| classes.contains(&class));
+        failing && !self.warn_only.contains(&class)
+    }
+
+    /// Returns the exit code of a check which violates the given assertions,
+    /// `EXIT_CODE_FAILED` if any violation fails the check and 0 otherwise (warnings only).
+    pub fn exit_code<'a, I>(&self, violated: I) -> i32
+    where
+        I: IntoIterator<Item = &'a Provenance>,
+    {
+        if violated
+            .into_iter()
+            .any(|provenance| self.is_failing(provenance))
+        {
+            Self::EXIT_CODE_FAILED
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violations() -> Vec<Provenance> {
+        vec![
+            Provenance::observation().with_transient(true),
+            Provenance::observation(),
+            Provenance::unwinding(),
+        ]
+    }
+
+    #[test]
+    fn test_all_violations_fail_by_default() {
+        // Given:
+        let policy = ViolationPolicy::default();
+
+        // When:
+        let failing: Vec<bool> = violations()
+            .iter()
+            .map(|provenance| policy.is_failing(provenance))
+            .collect();
+
+        // Then:
+        assert_eq!(failing, vec![true, true, true]);
+        assert_eq!(policy.exit_code(&violations()), 2);
+        assert_eq!(policy.exit_code(&[]), 0);
+    }
+
+    #[test]
+    fn test_fail_on_turns_other_violations_into_warnings() {
+        // Given:
+        let policy = ViolationPolicy::new(Some(vec![ViolationClass::TransientLeak]), Vec::new());
+
+        // When:
+        let normal_leak = policy.exit_code(&[Provenance::observation()]);
+        let transient_leak = policy.exit_code(&violations());
+
+        // Then:
+        assert_eq!(normal_leak, 0);
+        assert_eq!(transient_leak, 2);
+    }
+
+    #[test]
+    fn test_warn_only_takes_precedence_over_fail_on() {
+        // Given:
+        let policy = ViolationPolicy::new(
+            Some(vec![ViolationClass::NormalLeak, ViolationClass::Unwinding]),
+            vec![ViolationClass::NormalLeak],
+        );
+
+        // When:
+        let normal_leak = policy.exit_code(&[Provenance::observation()]);
+        let unwinding = policy.exit_code(&[Provenance::observation(), Provenance::unwinding()]);
+
+        // Then:
+        assert_eq!(normal_leak, 0);
+        assert_eq!(unwinding, 2);
+    }
+
+    #[test]
+    fn test_warn_only_normal_leaks_still_fails_on_transient_leaks() {
+        // Given: e.g. in CI, only transient leaks fail the check
+        let policy = ViolationPolicy::new(None, vec![ViolationClass::NormalLeak]);
+
+        // When:
+        let normal_leak = policy.exit_code(&[Provenance::observation()]);
+        let transient_leak = policy.exit_code(&violations());
+
+        // Then:
+        assert_eq!(normal_leak, 0);
+        assert_eq!(transient_leak, 2);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Result;
 use crate::expr::{Expression, Variable};
+use crate::ir::Provenance;
 use std::fmt;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
@@ -9,7 +10,10 @@ pub enum Node {
     /// Bind the expression to a variable.
     Let { var: Variable, expr: Expression },
     /// Assert that the condition is true.
-    Assert { condition: Expression },
+    Assert {
+        condition: Expression,
+        provenance: Provenance,
+    },
     /// Assume that the condition is true.
     Assume { condition: Expression },
 }
@@ -30,9 +34,12 @@ impl Node {
     }
 
     /// Create a new assertion.
-    pub fn assert(condition: Expression) -> Result<Self> {
+    pub fn assert(condition: Expression, provenance: Provenance) -> Result<Self> {
         condition.sort().expect_boolean()?;
-        Ok(Self::Assert { condition })
+        Ok(Self::Assert {
+            condition,
+            provenance,
+        })
     }
 
     /// Create a new assumption.
@@ -65,7 +72,7 @@ impl Node {
     pub fn variables_used(&self) -> Vec<&Variable> {
         match self {
             Self::Let { expr, .. } => expr.variables(),
-            Self::Assert { condition } | Self::Assume { condition } => condition.variables(),
+            Self::Assert { condition, .. } | Self::Assume { condition } => condition.variables(),
             Self::Comment(_) => Vec::new(),
         }
     }
@@ -74,7 +81,9 @@ impl Node {
     pub fn variables_used_mut(&mut self) -> Vec<&mut Variable> {
         match self {
             Self::Let { expr, .. } => expr.variables_mut(),
-            Self::Assert { condition } | Self::Assume { condition } => condition.variables_mut(),
+            Self::Assert { condition, .. } | Self::Assume { condition } => {
+                condition.variables_mut()
+            }
             Self::Comment(_) => Vec::new(),
         }
     }
@@ -107,7 +116,7 @@ impl Node {
     pub fn expressions(&self) -> Vec<&Expression> {
         match self {
             Self::Let { expr, .. } => vec![expr],
-            Self::Assert { condition } | Self::Assume { condition } => vec![condition],
+            Self::Assert { condition, .. } | Self::Assume { condition } => vec![condition],
             Self::Comment(_) => Vec::new(),
         }
     }
@@ -116,7 +125,7 @@ impl Node {
     pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
         match self {
             Self::Let { expr, .. } => vec![expr],
-            Self::Assert { condition } | Self::Assume { condition } => vec![condition],
+            Self::Assert { condition, .. } | Self::Assume { condition } => vec![condition],
             Self::Comment(_) => Vec::new(),
         }
     }
@@ -127,7 +136,10 @@ impl fmt::Display for Node {
         match self {
             Self::Comment(text) => write!(f, "// {}", text),
             Self::Let { var, expr } => write!(f, "let {} = {}", var, expr),
-            Self::Assert { condition } => write!(f, "assert {}", condition),
+            Self::Assert {
+                condition,
+                provenance,
+            } => write!(f, "assert {} // {}", condition, provenance),
             Self::Assume { condition } => write!(f, "assume {}", condition),
         }
     }
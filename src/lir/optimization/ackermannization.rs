@@ -0,0 +1,195 @@
+//! Ackermannization
+//!
+//! Replaces the applications of the uninterpreted predictor functions (speculate, taken, speculation window)
+//! by fresh variables and adds the functional-consistency constraints explicitly:
+//! `args(f_i) = args(f_j) -> f_i = f_j`.
+//!
+//! Syntactically equal applications share the same variable and applications with different constant
+//! arguments (usually the program location) can never be equal, therefore most pairs don't need a constraint.
+//! The applications of a function are only replaced if the number of constraints doesn't exceed a limit,
+//! otherwise the formula would grow quadratically in the number of applications.
+//!
+//! Only the predictor functions are ackermannized, the store chains on the cache, BTB and PHT arrays
+//! are kept as they are (they are encoded as SMT arrays, not as uninterpreted functions).
+//!
+//! This optimization requires that the program is in SSA form.
+
+use crate::error::Result;
+use crate::expr::{Boolean, Expression, Operator, Predictor, Variable};
+use crate::lir::optimization::{Guarantee, Optimization, OptimizationResult};
+use crate::lir::{Node, Program};
+use std::collections::HashMap;
+
+/// Max. number of functional-consistency constraints per function.
+const MAX_CONSISTENCY_CONSTRAINTS: usize = 1000;
+
+pub struct Ackermannization {}
+
+impl Ackermannization {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Optimization for Ackermannization {
+    fn optimize(&self, program: &mut Program) -> Result<OptimizationResult> {
+        let mut fresh_variables: HashMap<Expression, Variable> = HashMap::new();
+        let mut constraints = Vec::new();
+
+        for (function, applications) in predictor_applications(program) {
+            let mut function_constraints = Vec::new();
+            'pairs: for (i, application) in applications.iter().enumerate() {
+                for other in &applications[i + 1..] {
+                    if !may_be_equal(application.operands(), other.operands()) {
+                        continue;
+                    }
+                    function_constraints.push((application.clone(), other.clone()));
+                    if function_constraints.len() > MAX_CONSISTENCY_CONSTRAINTS {
+                        break 'pairs;
+                    }
+                }
+            }
+            if function_constraints.len() > MAX_CONSISTENCY_CONSTRAINTS {
+                continue; // not beneficial, keep the function uninterpreted
+            }
+
+            for (index, application) in applications.into_iter().enumerate() {
+                let var = Variable::new(
+                    format!("_{}_{}", function.to_string().replace('-', "_"), index),
+                    application.sort().clone(),
+                );
+                fresh_variables.insert(application, var);
+            }
+            constraints.extend(function_constraints);
+        }
+
+        if fresh_variables.is_empty() {
+            return Ok(OptimizationResult::Unchanged);
+        }
+
+        for expr in program.expressions_mut() {
+            replace_applications(expr, &fresh_variables);
+        }
+
+        // Fresh variables are unconstrained apart from the functional consistency
+        let mut variables: Vec<&Variable> = fresh_variables.values().collect();
+        variables.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut declarations = Vec::new();
+        for var in variables {
+            declarations.push(Node::assign(
+                var.clone(),
+                Expression::nondet(var.sort().clone()),
+            )?);
+        }
+        program.nodes_mut().splice(0..0, declarations);
+
+        for (application, other) in constraints {
+            let mut args_equal = Vec::new();
+            for (arg, other_arg) in application.operands().iter().zip(other.operands()) {
+                if arg != other_arg {
+                    args_equal.push(Expression::equal(arg.clone(), other_arg.clone())?);
+                }
+            }
+            let results_equal = Expression::equal(
+                fresh_variables[&application].clone().into(),
+                fresh_variables[&other].clone().into(),
+            )?;
+            program.assume(Boolean::imply(
+                Boolean::conjunction(&args_equal)?,
+                results_equal,
+            )?)?;
+        }
+
+        Ok(OptimizationResult::Changed)
+    }
+
+    fn breaks(&self) -> &'static [Guarantee] {
+        &[Guarantee::Counterexample]
+    }
+}
+
+/// Returns the distinct applications of each predictor function in order of their occurrence.
+fn predictor_applications(program: &Program) -> Vec<(Predictor, Vec<Expression>)> {
+    let mut applications: Vec<(Predictor, Vec<Expression>)> = Vec::new();
+
+    for expr in program.expressions() {
+        for subexpr in expr.subexpressions() {
+            if let Operator::Predictor(function) = subexpr.operator() {
+                let index = match applications.iter().position(|(f, _)| f == function) {
+                    Some(index) => index,
+                    None => {
+                        applications.push((function.clone(), Vec::new()));
+                        applications.len() - 1
+                    }
+                };
+                let function_applications = &mut applications[index].1;
+                if !function_applications.contains(subexpr) {
+                    function_applications.push(subexpr.clone());
+                }
+            }
+        }
+    }
+
+    applications
+}
+
+/// Two applications may only be equal if none of the arguments are distinct constants.
+fn may_be_equal(args: &[Expression], other_args: &[Expression]) -> bool {
+    args.iter()
+        .zip(other_args)
+        .all(|(arg, other_arg)| !(arg.is_constant() && other_arg.is_constant() && arg != other_arg))
+}
+
+fn replace_applications(expr: &mut Expression, fresh_variables: &HashMap<Expression, Variable>) {
+    if let Some(var) = fresh_variables.get(expr) {
+        *expr = var.clone().into();
+        return;
+    }
+    for operand in expr.operands_mut() {
+        replace_applications(operand, fresh_variables);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Sort};
+
+    fn speculate(predictor: &str, location: u64) -> Expression {
+        Predictor::speculate(
+            Variable::new(predictor, Sort::predictor()).into(),
+            BitVector::word_constant(location),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replace_applications_with_consistency_constraints() {
+        // Given
+        let mut program = Program::new();
+        program
+            .assume(Boolean::and(speculate("p0", 1), speculate("p1", 1)).unwrap())
+            .unwrap();
+        program
+            .assume(Boolean::or(speculate("p0", 1), speculate("p0", 2)).unwrap())
+            .unwrap();
+
+        // When
+        let result = Ackermannization::new().optimize(&mut program).unwrap();
+
+        // Then: 3 distinct applications, only p0@1 and p1@1 may be equal
+        assert!(result == OptimizationResult::Changed);
+        let declarations = program.nodes().iter().filter(|node| node.is_let()).count();
+        assert_eq!(declarations, 3);
+        let assumptions = program
+            .nodes()
+            .iter()
+            .filter(|node| node.is_assume())
+            .count();
+        assert_eq!(assumptions, 3);
+        assert!(program.expressions().iter().all(|expr| expr
+            .subexpressions()
+            .iter()
+            .all(|subexpr| !matches!(subexpr.operator(), Operator::Predictor(_)))));
+    }
+}
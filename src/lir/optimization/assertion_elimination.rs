@@ -6,7 +6,7 @@
 
 use crate::error::Result;
 use crate::expr::Expression;
-use crate::lir::optimization::{Optimization, OptimizationResult};
+use crate::lir::optimization::{Guarantee, Optimization, OptimizationResult};
 use crate::lir::{Node, Program};
 use std::collections::HashSet;
 
@@ -32,7 +32,7 @@ impl Optimization for AssertionElimination {
             .iter()
             .enumerate()
             .filter_map(|(index, node)| {
-                if let Node::Assert { condition } = node {
+                if let Node::Assert { condition, .. } = node {
                     if assumed_exprs.contains(condition) {
                         Some(index)
                     } else {
@@ -54,4 +54,8 @@ impl Optimization for AssertionElimination {
 
         Ok(OptimizationResult::Changed)
     }
+
+    fn breaks(&self) -> &'static [Guarantee] {
+        &[Guarantee::LeakLocalization]
+    }
 }
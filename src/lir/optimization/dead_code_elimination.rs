@@ -8,7 +8,7 @@
 
 use crate::error::Result;
 use crate::expr::Variable;
-use crate::lir::optimization::{Optimization, OptimizationResult};
+use crate::lir::optimization::{Guarantee, Optimization, OptimizationResult};
 use crate::lir::{Node, Program};
 use bit_vec::BitVec;
 use std::collections::HashMap;
@@ -37,6 +37,10 @@ impl Optimization for DeadCodeElimination {
 
         Ok(OptimizationResult::Changed)
     }
+
+    fn breaks(&self) -> &'static [Guarantee] {
+        &[Guarantee::Counterexample]
+    }
 }
 
 trait DceCritical {
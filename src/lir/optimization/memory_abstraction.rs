@@ -0,0 +1,362 @@
+//! Memory Abstraction
+//!
+//! Encodes memory as an uninterpreted function from addresses to memory cells (cells if cell-addressable)
+//! instead of an array.
+//! All stores are eliminated by instantiating the read-over-write (McCarthy) axioms on demand,
+//! i.e. only for the cells which are actually loaded:
+//! `load(store(m, a, v), b) = ite(a = b, v, load(m, b))`.
+//!
+//! The remaining loads only read unmodified memories (e.g. the initial memory),
+//! which are functionally consistent uninterpreted functions.
+//! Memories which are no longer used afterwards are removed.
+//!
+//! This optimization requires that the program is in SSA form.
+
+use crate::environment::Endianness;
+use crate::error::Result;
+use crate::expr::{BitVector, Expression, Memory, Operator, Sort, Variable};
+use crate::lir::optimization::{Optimization, OptimizationResult};
+use crate::lir::{Node, Program};
+use std::collections::HashMap;
+
+pub struct MemoryAbstraction {
+    endianness: Endianness,
+    cell_width: usize,
+}
+
+impl MemoryAbstraction {
+    pub fn new(endianness: Endianness, cell_width: usize) -> Self {
+        Self {
+            endianness,
+            cell_width,
+        }
+    }
+}
+
+impl Optimization for MemoryAbstraction {
+    fn optimize(&self, program: &mut Program) -> Result<OptimizationResult> {
+        let has_stores = program.expressions().iter().any(|expr| {
+            expr.subexpressions()
+                .iter()
+                .any(|subexpr| matches!(subexpr.operator(), Operator::Memory(Memory::Store(_))))
+        });
+        if !has_stores {
+            return Ok(OptimizationResult::Unchanged);
+        }
+
+        let mut reads = CellReads::new(self.endianness, self.cell_width);
+        let mut nodes = Vec::with_capacity(program.nodes().len());
+        for mut node in program.nodes_mut().drain(..) {
+            for expr in node.expressions_mut() {
+                *expr = reads.eliminate_loads(expr)?;
+            }
+            nodes.append(&mut reads.instantiations);
+            if let Node::Let { var, expr } = &node {
+                if var.sort().is_memory() {
+                    reads.definitions.insert(var.clone(), expr.clone());
+                }
+            }
+            nodes.push(node);
+        }
+
+        remove_unused_memories(&mut nodes);
+        *program.nodes_mut() = nodes;
+
+        Ok(OptimizationResult::Changed)
+    }
+}
+
+struct CellReads {
+    endianness: Endianness,
+    cell_width: usize,
+    /// Definitions of the memory variables (with loads already eliminated)
+    definitions: HashMap<Variable, Expression>,
+    /// Cell reads which are already instantiated, per memory variable and address
+    instantiated: HashMap<(Variable, Expression), Variable>,
+    /// Bindings of the instantiated cell reads which are not yet added to the program
+    instantiations: Vec<Node>,
+}
+
+impl CellReads {
+    fn new(endianness: Endianness, cell_width: usize) -> Self {
+        Self {
+            endianness,
+            cell_width,
+            definitions: HashMap::new(),
+            instantiated: HashMap::new(),
+            instantiations: Vec::new(),
+        }
+    }
+
+    /// Replaces all loads in `expr` by the (concatenated) cells read.
+    fn eliminate_loads(&mut self, expr: &Expression) -> Result<Expression> {
+        let operands = expr
+            .operands()
+            .iter()
+            .map(|operand| self.eliminate_loads(operand))
+            .collect::<Result<Vec<Expression>>>()?;
+
+        match (expr.operator(), operands.as_slice()) {
+            (Operator::Memory(Memory::Load(bit_width)), [memory, address]) => {
+                let mut cells = Vec::new();
+                for cell in self.endianness.cell_offsets(*bit_width, self.cell_width) {
+                    let cell_address = offset_address(address, cell)?;
+                    cells.push(self.read_cell(memory, &cell_address)?);
+                }
+                BitVector::concat(&cells)
+            }
+            _ => Ok(Expression::new(
+                expr.operator().clone(),
+                operands,
+                expr.sort().clone(),
+            )),
+        }
+    }
+
+    /// Returns the cell read from `memory` at `address`.
+    fn read_cell(&mut self, memory: &Expression, address: &Expression) -> Result<Expression> {
+        match memory.operator() {
+            Operator::Variable(var) => {
+                let definition = match self.definitions.get(var) {
+                    Some(definition) if is_modified_memory(definition) => definition.clone(),
+                    _ => return Memory::load(self.cell_width, memory.clone(), address.clone()),
+                };
+
+                let key = (var.clone(), address.clone());
+                if let Some(read) = self.instantiated.get(&key) {
+                    return Ok(read.clone().into());
+                }
+
+                let value = self.read_cell(&definition, address)?;
+                let read = Variable::new(
+                    format!("_mem_read_{}", self.instantiated.len()),
+                    Sort::bit_vector(self.cell_width),
+                );
+                self.instantiations.push(Node::assign(read.clone(), value)?);
+                self.instantiated.insert(key, read.clone());
+                Ok(read.into())
+            }
+            Operator::Memory(Memory::Store(bit_width)) => {
+                let (inner, store_address, value) = match memory.operands() {
+                    [inner, store_address, value] => (inner, store_address, value),
+                    _ => return Err("Invalid operands for memory store".into()),
+                };
+
+                let mut cell_addresses = Vec::new();
+                let cell_offsets = self.endianness.cell_offsets(*bit_width, self.cell_width);
+                for (significance, &cell) in cell_offsets.iter().rev().enumerate() {
+                    let cell_address = offset_address(store_address, cell)?;
+                    let bit_offset = significance * self.cell_width;
+                    let cell_value = BitVector::extract(
+                        bit_offset + self.cell_width - 1,
+                        bit_offset,
+                        value.clone(),
+                    )?;
+                    if &cell_address == address {
+                        // Overwritten for sure
+                        return Ok(cell_value);
+                    }
+                    cell_addresses.push((cell_address, cell_value));
+                }
+
+                let mut result = self.read_cell(inner, address)?;
+                for (cell_address, cell_value) in cell_addresses {
+                    result = Expression::ite(
+                        Expression::equal(address.clone(), cell_address)?,
+                        cell_value,
+                        result,
+                    )?;
+                }
+                Ok(result)
+            }
+            Operator::Ite => match memory.operands() {
+                [condition, then, else_] => Expression::ite(
+                    condition.clone(),
+                    self.read_cell(then, address)?,
+                    self.read_cell(else_, address)?,
+                ),
+                _ => Err("Invalid operands for ite".into()),
+            },
+            _ => Memory::load(self.cell_width, memory.clone(), address.clone()),
+        }
+    }
+}
+
+/// Memories defined by stores, conditionals or copies are resolved,
+/// all other memories (e.g. nondet) are kept uninterpreted.
+fn is_modified_memory(definition: &Expression) -> bool {
+    matches!(
+        definition.operator(),
+        Operator::Variable(_) | Operator::Ite | Operator::Memory(Memory::Store(_))
+    )
+}
+
+fn offset_address(address: &Expression, offset: usize) -> Result<Expression> {
+    if offset == 0 {
+        Ok(address.clone())
+    } else {
+        BitVector::add(address.clone(), BitVector::word_constant(offset as u64))
+    }
+}
+
+/// Removes the bindings of memory variables which are (transitively) unused.
+fn remove_unused_memories(nodes: &mut Vec<Node>) {
+    let mut uses: HashMap<Variable, usize> = HashMap::new();
+    for node in nodes.iter() {
+        for var in node.variables_used() {
+            *uses.entry(var.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let memory_definitions: HashMap<Variable, usize> = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, node)| match node {
+            Node::Let { var, .. } if var.sort().is_memory() => Some((var.clone(), index)),
+            _ => None,
+        })
+        .collect();
+
+    let mut removed = vec![false; nodes.len()];
+    let mut work_list: Vec<usize> = memory_definitions
+        .iter()
+        .filter(|(var, _)| !uses.contains_key(var))
+        .map(|(_, &index)| index)
+        .collect();
+    while let Some(index) = work_list.pop() {
+        removed[index] = true;
+        for var in nodes[index].variables_used() {
+            let count = uses.get_mut(var).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                if let Some(&definition) = memory_definitions.get(var) {
+                    work_list.push(definition);
+                }
+            }
+        }
+    }
+
+    let mut index = 0;
+    nodes.retain(|_| {
+        index += 1;
+        !removed[index - 1]
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(name: &str) -> Variable {
+        Variable::new(name, Sort::memory())
+    }
+
+    #[test]
+    fn test_eliminate_stores_by_read_over_write() {
+        // Given
+        let value = BitVector::variable("value", 32);
+        let mut program = Program::new();
+        program
+            .assign(memory("m0"), Expression::nondet(Sort::memory()))
+            .unwrap();
+        program
+            .assign(
+                memory("m1"),
+                Memory::store(
+                    memory("m0").into(),
+                    BitVector::word_constant(0x10),
+                    value.into(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        program
+            .assume(
+                Expression::equal(
+                    Memory::load(16, memory("m1").into(), BitVector::word_constant(0x12)).unwrap(),
+                    BitVector::constant_u64(0, 16),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        // When
+        let result = MemoryAbstraction::new(Endianness::Little, 8)
+            .optimize(&mut program)
+            .unwrap();
+
+        // Then: m1 is gone and all remaining loads read the unmodified m0
+        assert!(result == OptimizationResult::Changed);
+        assert!(program
+            .nodes()
+            .iter()
+            .all(|node| !node.variables_defined().contains(&&memory("m1"))));
+        for expr in program.expressions() {
+            for subexpr in expr.subexpressions() {
+                match subexpr.operator() {
+                    Operator::Memory(Memory::Store(_)) => panic!("Unexpected store"),
+                    Operator::Memory(Memory::Load(bit_width)) => {
+                        assert_eq!(*bit_width, 8);
+                        assert_eq!(subexpr.operands()[0], memory("m0").into());
+                    }
+                    _ => (),
+                }
+            }
+        }
+        let reads = program
+            .nodes()
+            .iter()
+            .filter(|node| match node {
+                Node::Let { var, .. } => var.name().starts_with("_mem_read_"),
+                _ => false,
+            })
+            .count();
+        assert_eq!(reads, 2);
+    }
+
+    #[test]
+    fn test_eliminate_stores_with_word_cells() {
+        // Given: 16-bit memory cells, a 32-bit store followed by a 16-bit load of its upper half
+        let value = BitVector::variable("value", 32);
+        let mut program = Program::new();
+        program
+            .assign(memory("m0"), Expression::nondet(Sort::memory()))
+            .unwrap();
+        program
+            .assign(
+                memory("m1"),
+                Memory::store(
+                    memory("m0").into(),
+                    BitVector::word_constant(0x10),
+                    value.clone().into(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        program
+            .assume(
+                Expression::equal(
+                    Memory::load(16, memory("m1").into(), BitVector::word_constant(0x11)).unwrap(),
+                    BitVector::constant_u64(0, 16),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        // When
+        MemoryAbstraction::new(Endianness::Little, 16)
+            .optimize(&mut program)
+            .unwrap();
+
+        // Then: the load reads the upper cell of the stored value
+        let expected = BitVector::extract(31, 16, value.into()).unwrap();
+        assert!(program.nodes().iter().any(|node| match node {
+            Node::Let { var, expr } => var.name().starts_with("_mem_read_") && expr == &expected,
+            _ => false,
+        }));
+        assert!(program.expressions().iter().all(|expr| expr
+            .subexpressions()
+            .iter()
+            .all(|subexpr| !matches!(subexpr.operator(), Operator::Memory(_)))));
+    }
+}
@@ -1,22 +1,30 @@
-use crate::environment::{Environment, OptimizationLevel};
+use crate::environment::{Check, Environment, MemoryModel, OptimizationLevel};
 use crate::error::Result;
 use crate::lir::Program;
+use crate::solver;
+use std::collections::BTreeSet;
 
+mod ackermannization;
 mod assertion_elimination;
 mod constant_folding;
 mod constant_propagation;
 mod copy_propagation;
 mod dead_code_elimination;
 mod expression_simplification;
+mod memory_abstraction;
 mod redundant_node_elimination;
+mod solver_simplification;
 
+use ackermannization::Ackermannization;
 use assertion_elimination::AssertionElimination;
 use constant_folding::ConstantFolding;
 use constant_propagation::ConstantPropagation;
 use copy_propagation::CopyPropagation;
 use dead_code_elimination::DeadCodeElimination;
 use expression_simplification::ExpressionSimplification;
+use memory_abstraction::MemoryAbstraction;
 use redundant_node_elimination::RedundantNodeElimination;
+use solver_simplification::SolverSimplification;
 
 #[derive(Eq, PartialEq)]
 pub enum OptimizationResult {
@@ -24,8 +32,63 @@ pub enum OptimizationResult {
     Unchanged,
 }
 
+/// Properties of the LIR program which a check may rely on and which some optimizations don't preserve.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Guarantee {
+    /// All assignments (incl. the predictor functions) are kept, such that counterexamples can be reconstructed.
+    Counterexample,
+    /// Each assertion is kept along with its provenance, such that violations can be attributed to their origin.
+    LeakLocalization,
+}
+
 pub trait Optimization {
     fn optimize(&self, program: &mut Program) -> Result<OptimizationResult>;
+
+    /// Guarantees which no longer hold after applying this optimization.
+    fn breaks(&self) -> &'static [Guarantee] {
+        &[]
+    }
+}
+
+/// The guarantees a check requires from the optimized program.
+///
+/// Optimizations which break any of them are removed from the optimizer.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OptimizationPreset {
+    required: BTreeSet<Guarantee>,
+}
+
+impl OptimizationPreset {
+    pub fn for_check(check: Check) -> Self {
+        let mut preset = Self::default();
+        match check {
+            Check::OnlyTransientExecutionLeaks
+            | Check::OnlyNormalExecutionLeaks
+            | Check::SpeculativeInterference => {}
+            // Leaks are classified (normal/transient) and bounds violations are reported per load,
+            // both by the provenance of the violated assertion
+            Check::AllLeaks | Check::SpeculativeMemorySafety => {
+                preset.require(Guarantee::LeakLocalization);
+            }
+        }
+        preset
+    }
+
+    pub fn require(&mut self, guarantee: Guarantee) {
+        self.required.insert(guarantee);
+    }
+
+    pub fn requires(&self, guarantee: Guarantee) -> bool {
+        self.required.contains(&guarantee)
+    }
+
+    /// Checks if `optimization` preserves all required guarantees.
+    pub fn admits(&self, optimization: &dyn Optimization) -> bool {
+        optimization
+            .breaks()
+            .iter()
+            .all(|guarantee| !self.requires(*guarantee))
+    }
 }
 
 pub struct Optimizer {
@@ -36,19 +99,55 @@ pub struct Optimizer {
 }
 
 impl Optimizer {
-    pub fn new_from_env(env: &Environment) -> Self {
-        match env.optimization_level {
+    pub fn new_from_env(env: &Environment) -> Result<Self> {
+        let mut preset = OptimizationPreset::for_check(env.analysis.check);
+        if env.generate_counterexample {
+            preset.require(Guarantee::Counterexample);
+        }
+
+        let mut optimizer = match env.optimization_level {
             OptimizationLevel::Disabled => Self::none(),
             OptimizationLevel::Basic => Self::basic(),
-            OptimizationLevel::Full => {
-                if env.generate_counterexample {
-                    // Dead code elimination on LIR-level doesn't play nicely with our current CEX construction approach
-                    Self::full_without_dce()
-                } else {
-                    Self::full()
-                }
-            }
+            OptimizationLevel::Full => Self::full(),
+        };
+
+        if env.architecture.memory_model == MemoryModel::UninterpretedFunction {
+            optimizer
+                .pre_optimizations
+                .push(Box::new(MemoryAbstraction::new(
+                    env.architecture.endianness,
+                    env.architecture.memory_cell_width,
+                )));
+        }
+
+        if let Some(max_size) = env.solver_simplification {
+            optimizer
+                .pre_optimizations
+                .push(Box::new(SolverSimplification::new(
+                    solver::create_simplifier(env)?,
+                    max_size,
+                )));
+        }
+
+        if env.ackermannization {
+            optimizer
+                .post_optimizations
+                .push(Box::new(Ackermannization::new()));
         }
+
+        optimizer.restrict_to(&preset);
+
+        Ok(optimizer)
+    }
+
+    /// Removes all optimizations which aren't admitted by `preset`.
+    pub fn restrict_to(&mut self, preset: &OptimizationPreset) {
+        self.pre_optimizations
+            .retain(|optimization| preset.admits(optimization.as_ref()));
+        self.repeated_optimizations
+            .retain(|optimization| preset.admits(optimization.as_ref()));
+        self.post_optimizations
+            .retain(|optimization| preset.admits(optimization.as_ref()));
     }
 
     pub fn none() -> Self {
@@ -87,23 +186,6 @@ impl Optimizer {
         }
     }
 
-    fn full_without_dce() -> Self {
-        Self {
-            pre_optimizations: Vec::new(),
-            repeated_optimizations: vec![
-                Box::new(ConstantFolding::new()),
-                Box::new(ConstantPropagation::new()),
-                Box::new(ExpressionSimplification::new()),
-                Box::new(CopyPropagation::new()),
-            ],
-            post_optimizations: vec![
-                Box::new(AssertionElimination::new()),
-                Box::new(RedundantNodeElimination::new()),
-            ],
-            repetitions: 5,
-        }
-    }
-
     pub fn optimize(&self, program: &mut Program) -> Result<()> {
         for optimization in &self.pre_optimizations {
             optimization.optimize(program)?;
@@ -131,3 +213,43 @@ impl Optimizer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimization_count(optimizer: &Optimizer) -> usize {
+        optimizer.pre_optimizations.len()
+            + optimizer.repeated_optimizations.len()
+            + optimizer.post_optimizations.len()
+    }
+
+    #[test]
+    fn test_preset_without_requirements_admits_all_optimizations() {
+        // Given
+        let mut optimizer = Optimizer::full();
+        let preset = OptimizationPreset::for_check(Check::OnlyTransientExecutionLeaks);
+
+        // When
+        optimizer.restrict_to(&preset);
+
+        // Then
+        assert_eq!(optimization_count(&optimizer), 7);
+    }
+
+    #[test]
+    fn test_preset_removes_optimizations_breaking_required_guarantees() {
+        // Given: all leaks are classified and a counterexample is generated
+        let mut optimizer = Optimizer::full();
+        let mut preset = OptimizationPreset::for_check(Check::AllLeaks);
+        preset.require(Guarantee::Counterexample);
+
+        // When
+        optimizer.restrict_to(&preset);
+
+        // Then: dead code, assertion and redundant node elimination are removed
+        assert_eq!(optimizer.repeated_optimizations.len(), 4);
+        assert!(optimizer.post_optimizations.is_empty());
+        assert!(!preset.admits(&Ackermannization::new()));
+    }
+}
@@ -5,7 +5,8 @@
 //! This optimization requires that the program is in SSA form.
 
 use crate::error::Result;
-use crate::lir::optimization::{Optimization, OptimizationResult};
+use crate::expr::Expression;
+use crate::lir::optimization::{Guarantee, Optimization, OptimizationResult};
 use crate::lir::{Node, Program};
 use std::collections::{BTreeSet, HashMap};
 
@@ -31,12 +32,18 @@ impl Optimization for RedundantNodeElimination {
 
         Ok(OptimizationResult::Changed)
     }
+
+    fn breaks(&self) -> &'static [Guarantee] {
+        &[Guarantee::LeakLocalization]
+    }
 }
 
 /// Returns a set of nodes indices which can safely be removed.
 fn compute_duplicated_node_indices(program: &Program) -> BTreeSet<usize> {
     // Limit to assertions and assumptions only because assignments are never duplicated (-> SSA)
-    let mut available_assertion_assumptions: HashMap<&Node, Vec<usize>> = HashMap::new();
+    // Assertions are compared by condition only, the provenance of the first one is retained.
+    let mut available_assertion_assumptions: HashMap<(bool, Vec<&Expression>), Vec<usize>> =
+        HashMap::new();
     program
         .nodes()
         .iter()
@@ -44,7 +51,7 @@ fn compute_duplicated_node_indices(program: &Program) -> BTreeSet<usize> {
         .filter(|(_, node)| node.is_assert() || node.is_assume())
         .for_each(|(index, node)| {
             available_assertion_assumptions
-                .entry(node)
+                .entry((node.is_assert(), node.expressions()))
                 .and_modify(|indices| indices.push(index))
                 .or_insert_with(|| vec![index]);
         });
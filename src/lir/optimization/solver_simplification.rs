@@ -0,0 +1,164 @@
+//! Solver-backed Simplification
+//!
+//! Sends subexpressions of at most `max_size` operators to the solver's simplifier
+//! and substitutes the simplified results back, e.g. collapsing the ite-chains of translated phi nodes.
+//! The largest simplifiable subexpressions within the size limit are chosen, results are only
+//! substituted if they are smaller than the original expression.
+
+use crate::error::Result;
+use crate::expr::{Expression, Operator};
+use crate::lir::optimization::{Optimization, OptimizationResult};
+use crate::lir::Program;
+use crate::solver::ExpressionSimplifier;
+
+pub struct SolverSimplification {
+    simplifier: Box<dyn ExpressionSimplifier>,
+    max_size: usize,
+}
+
+impl SolverSimplification {
+    pub fn new(simplifier: Box<dyn ExpressionSimplifier>, max_size: usize) -> Self {
+        Self {
+            simplifier,
+            max_size,
+        }
+    }
+
+    fn simplify(&self, expr: &mut Expression) -> Result<bool> {
+        let size = expr.subexpressions().len();
+        if size <= self.max_size && is_simplifiable(expr) {
+            if size == 1 {
+                return Ok(false);
+            }
+            return match self.simplifier.simplify(expr)? {
+                Some(simplified)
+                    if simplified.sort() == expr.sort()
+                        && simplified.subexpressions().len() < size =>
+                {
+                    *expr = simplified;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            };
+        }
+
+        let mut simplified = false;
+        for operand in expr.operands_mut() {
+            simplified |= self.simplify(operand)?;
+        }
+        Ok(simplified)
+    }
+}
+
+impl Optimization for SolverSimplification {
+    fn optimize(&self, program: &mut Program) -> Result<OptimizationResult> {
+        let mut simplified = false;
+        for expr in program.expressions_mut() {
+            simplified |= self.simplify(expr)?;
+        }
+
+        if simplified {
+            Ok(OptimizationResult::Changed)
+        } else {
+            Ok(OptimizationResult::Unchanged)
+        }
+    }
+}
+
+/// Only Boolean and bit-vector terms can be read back from the solver.
+fn is_simplifiable(expr: &Expression) -> bool {
+    expr.subexpressions().iter().all(|subexpr| {
+        let supported_sort = subexpr.sort().is_boolean() || subexpr.sort().is_bit_vector();
+        let supported_operator = matches!(
+            subexpr.operator(),
+            Operator::Variable(_)
+                | Operator::Constant(_)
+                | Operator::Ite
+                | Operator::Equal
+                | Operator::Boolean(_)
+                | Operator::BitVector(_)
+        );
+        supported_sort && supported_operator
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Boolean, Memory, Variable};
+    use crate::lir::Node;
+
+    /// Simplifies `ite(c, x, x)` to `x`, everything else is returned unchanged.
+    struct IteSimplifier {}
+
+    impl ExpressionSimplifier for IteSimplifier {
+        fn simplify(&self, expr: &Expression) -> Result<Option<Expression>> {
+            match (expr.operator(), expr.operands()) {
+                (Operator::Ite, [_, then, otherwise]) if then == otherwise => {
+                    Ok(Some(then.clone()))
+                }
+                _ => Ok(Some(expr.clone())),
+            }
+        }
+    }
+
+    fn ite(var: &str) -> Expression {
+        Expression::ite(
+            Boolean::variable("c").into(),
+            BitVector::word_variable(var).into(),
+            BitVector::word_variable(var).into(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_simplify_subexpressions_within_size_limit() {
+        // Given: y := load(mem, ite(c, x, x)) + ite(c, z, z)
+        let load = Memory::load(64, Memory::variable().into(), ite("x")).unwrap();
+        let sum = BitVector::add(load, ite("z")).unwrap();
+        let mut program = Program::new();
+        program.assign(BitVector::word_variable("y"), sum).unwrap();
+        let optimization = SolverSimplification::new(Box::new(IteSimplifier {}), 4);
+
+        // When
+        let result = optimization.optimize(&mut program).unwrap();
+
+        // Then: both ites are simplified, the load (memory isn't simplifiable) is kept
+        let expected = BitVector::add(
+            Memory::load(
+                64,
+                Memory::variable().into(),
+                BitVector::word_variable("x").into(),
+            )
+            .unwrap(),
+            BitVector::word_variable("z").into(),
+        )
+        .unwrap();
+        assert!(result == OptimizationResult::Changed);
+        assert_eq!(
+            program.node(0).unwrap(),
+            &Node::assign(BitVector::word_variable("y"), expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_keep_expressions_which_are_not_smaller() {
+        // Given
+        let sum = BitVector::add(
+            BitVector::word_variable("x").into(),
+            BitVector::word_variable("z").into(),
+        )
+        .unwrap();
+        let mut program = Program::new();
+        program
+            .assign(Variable::new("y", sum.sort().clone()), sum)
+            .unwrap();
+        let optimization = SolverSimplification::new(Box::new(IteSimplifier {}), 10);
+
+        // When
+        let result = optimization.optimize(&mut program).unwrap();
+
+        // Then
+        assert!(result == OptimizationResult::Unchanged);
+    }
+}
@@ -1,8 +1,8 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::expr::{Expression, Variable};
-use crate::ir::Validate;
+use crate::ir::{Provenance, Validate};
 use crate::lir::Node;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Default)]
@@ -38,6 +38,33 @@ impl Program {
         &mut self.nodes
     }
 
+    /// Returns an iterator over all nodes of this program along with their index.
+    pub fn indexed_nodes(&self) -> impl Iterator<Item = (usize, &Node)> {
+        self.nodes.iter().enumerate()
+    }
+
+    /// Adds a node to the end of this program.
+    pub fn add_node(&mut self, node: Node) {
+        self.nodes.push(node);
+    }
+
+    /// Inserts a node at the given index, shifting all following nodes.
+    pub fn insert_node(&mut self, index: usize, node: Node) -> Result<()> {
+        if index > self.nodes.len() {
+            return Err(Error::InvalidNodeIndex(index));
+        }
+        self.nodes.insert(index, node);
+        Ok(())
+    }
+
+    /// Removes the node at the given index and returns it.
+    pub fn remove_node(&mut self, index: usize) -> Result<Node> {
+        if index >= self.nodes.len() {
+            return Err(Error::NodeNotFound(index));
+        }
+        Ok(self.nodes.remove(index))
+    }
+
     /// Adds a comment to the end of this program.
     pub fn comment<S>(&mut self, text: S)
     where
@@ -53,11 +80,151 @@ impl Program {
     }
 
     /// Adds an assertion to the end of this program.
-    pub fn assert(&mut self, condition: Expression) -> Result<()> {
-        self.nodes.push(Node::assert(condition)?);
+    pub fn assert(&mut self, condition: Expression, provenance: Provenance) -> Result<()> {
+        self.nodes.push(Node::assert(condition, provenance)?);
         Ok(())
     }
 
+    /// Returns the provenance of all assertions of this program (in program order).
+    pub fn assertions(&self) -> Vec<&Provenance> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Assert { provenance, .. } => Some(provenance),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Splits this program into one program per assertion.
+    ///
+    /// Each program contains a single assertion together with its cone of influence,
+    /// i.e. the bindings of all variables the assertion (transitively) depends on
+    /// and the assumptions constraining any of these variables.
+    /// Assumptions without variables are kept in every program.
+    pub fn assertion_slices(&self) -> Vec<Self> {
+        self.assertion_cones()
+            .into_iter()
+            .map(|indices| Self {
+                nodes: indices
+                    .into_iter()
+                    .map(|index| self.nodes[index].clone())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Formats the slice of each assertion (see `assertion_slices`), headed by the provenance
+    /// of the assertion. The nodes of each slice are grouped by the comments preceding them in
+    /// this program (e.g. source blocks and instruction addresses).
+    pub fn assertion_slices_to_string(&self) -> String {
+        // Index of the closest comment in front of each node
+        let mut groups = Vec::with_capacity(self.nodes.len());
+        let mut group = None;
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.is_comment() {
+                group = Some(index);
+            }
+            groups.push(group);
+        }
+
+        let mut s = String::new();
+        for (slice_index, indices) in self.assertion_cones().into_iter().enumerate() {
+            // Each slice contains exactly one assertion
+            let assertion = indices
+                .iter()
+                .map(|&index| &self.nodes[index])
+                .find(|node| node.is_assert());
+            if let Some(Node::Assert { provenance, .. }) = assertion {
+                s.push_str(&format!(
+                    "// ===== Assertion {}: {} =====\n",
+                    slice_index, provenance
+                ));
+            }
+
+            let mut current_group = None;
+            for index in indices {
+                if groups[index] != current_group {
+                    current_group = groups[index];
+                    if let Some(comment) = current_group {
+                        s.push_str(&format!("{}\n", self.nodes[comment]));
+                    }
+                }
+                s.push_str(&format!("{}\n", self.nodes[index]));
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Gives the (sorted) indices of the nodes in the cone of influence of each assertion.
+    fn assertion_cones(&self) -> Vec<Vec<usize>> {
+        let definitions: HashMap<&Variable, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node {
+                Node::Let { var, .. } => Some((var, index)),
+                _ => None,
+            })
+            .collect();
+
+        let assumptions: Vec<usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_assume())
+            .map(|(index, _)| index)
+            .collect();
+
+        let mut cones = Vec::new();
+
+        for (assertion_index, node) in self.nodes.iter().enumerate() {
+            if !node.is_assert() {
+                continue;
+            }
+
+            let mut included: HashSet<usize> = HashSet::new();
+            let mut cone: HashSet<&Variable> = HashSet::new();
+            let mut work_list: Vec<&Variable> = node.variables_used();
+            included.insert(assertion_index);
+
+            loop {
+                while let Some(var) = work_list.pop() {
+                    if !cone.insert(var) {
+                        continue;
+                    }
+                    if let Some(&index) = definitions.get(var) {
+                        if included.insert(index) {
+                            work_list.extend(self.nodes[index].variables_used());
+                        }
+                    }
+                }
+
+                for &index in &assumptions {
+                    if included.contains(&index) {
+                        continue;
+                    }
+                    let vars = self.nodes[index].variables_used();
+                    if vars.is_empty() || vars.iter().any(|var| cone.contains(var)) {
+                        included.insert(index);
+                        work_list.extend(vars);
+                    }
+                }
+
+                if work_list.is_empty() {
+                    break;
+                }
+            }
+
+            let mut indices: Vec<usize> = included.into_iter().collect();
+            indices.sort_unstable();
+            cones.push(indices);
+        }
+
+        cones
+    }
+
     /// Adds an assumption to the end of this program.
     pub fn assume(&mut self, condition: Expression) -> Result<()> {
         self.nodes.push(Node::assume(condition)?);
@@ -137,7 +304,10 @@ impl Validate for Program {
         for (index, node) in self.nodes.iter().enumerate() {
             for var in node.variables_defined() {
                 if !defs.insert(var) {
-                    return Err(format!("@{}: Re-assignment of variable `{}`", index, var).into());
+                    return Err(Error::VariableReassignment {
+                        index,
+                        variable: var.clone(),
+                    });
                 }
             }
         }
@@ -146,7 +316,10 @@ impl Validate for Program {
         for (index, node) in self.nodes.iter().enumerate() {
             for var in node.variables_used() {
                 if !defs.contains(var) {
-                    return Err(format!("@{}: Use of undefined variable `{}`", index, var).into());
+                    return Err(Error::UndefinedVariable {
+                        index,
+                        variable: var.clone(),
+                    });
                 }
             }
         }
@@ -176,6 +349,10 @@ mod tests {
 
         // THEN
         assert_eq!(result.is_err(), true);
+        assert!(matches!(
+            result,
+            Err(Error::VariableReassignment { index: 1, .. })
+        ));
     }
 
     #[test]
@@ -191,6 +368,10 @@ mod tests {
 
         // THEN
         assert_eq!(result.is_err(), true);
+        assert!(matches!(
+            result,
+            Err(Error::UndefinedVariable { index: 0, .. })
+        ));
     }
 
     #[test]
@@ -213,4 +394,103 @@ mod tests {
         // THEN
         assert_eq!(result.is_ok(), true);
     }
+
+    #[test]
+    fn test_assertion_slices_should_contain_cone_of_influence_only() {
+        // GIVEN
+        let x = Variable::new("x", Sort::boolean());
+        let y = Variable::new("y", Sort::boolean());
+        let z = Variable::new("z", Sort::boolean());
+
+        let mut program = Program::new();
+        program
+            .assign(x.clone(), Expression::nondet(Sort::boolean()))
+            .unwrap();
+        program
+            .assign(y.clone(), Expression::nondet(Sort::boolean()))
+            .unwrap();
+        program.assign(z.clone(), x.clone().into()).unwrap();
+        program.assume(x.clone().into()).unwrap();
+        program
+            .assert(z.clone().into(), Provenance::user())
+            .unwrap();
+        program
+            .assert(y.clone().into(), Provenance::user())
+            .unwrap();
+
+        // WHEN
+        let slices = program.assertion_slices();
+
+        // THEN
+        assert_eq!(slices.len(), 2);
+        assert_eq!(slices[0].nodes().len(), 4); // x, z, assume x, assert z
+        assert!(slices[0].variables_defined().contains(&&x));
+        assert!(!slices[0].variables_defined().contains(&&y));
+        assert_eq!(slices[1].nodes().len(), 2); // y, assert y
+        assert!(slices[1].validate().is_ok());
+    }
+
+    #[test]
+    fn test_assertion_slices_to_string_should_group_nodes_by_comments() {
+        // GIVEN
+        let x = Variable::new("x", Sort::boolean());
+        let y = Variable::new("y", Sort::boolean());
+
+        let mut program = Program::new();
+        program.comment("Block 0x0@0");
+        program
+            .assign(x.clone(), Expression::nondet(Sort::boolean()))
+            .unwrap();
+        program
+            .assign(y.clone(), Expression::nondet(Sort::boolean()))
+            .unwrap();
+        program.comment("Block 0x1@0");
+        program
+            .assert(x.clone().into(), Provenance::observation())
+            .unwrap();
+
+        // WHEN
+        let text = program.assertion_slices_to_string();
+
+        // THEN
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("// ===== Assertion 0: "));
+        assert_eq!(lines[1], "// Block 0x0@0");
+        assert!(lines[2].starts_with("let x"));
+        assert_eq!(lines[3], "// Block 0x1@0");
+        assert!(lines[4].starts_with("assert x"));
+        assert_eq!(lines[5], "");
+    }
+
+    #[test]
+    fn test_insert_and_remove_nodes_at_arbitrary_positions() {
+        // GIVEN
+        let x = Variable::new("x", Sort::boolean());
+        let mut program = Program::new();
+        program.comment("first");
+        program.assume(x.clone().into()).unwrap();
+
+        // WHEN
+        program
+            .insert_node(1, Node::assign(x, Boolean::constant(true)).unwrap())
+            .unwrap();
+        let removed = program.remove_node(0).unwrap();
+
+        // THEN
+        assert!(removed.is_comment());
+        assert!(program.validate().is_ok());
+        assert_eq!(
+            program.indexed_nodes().map(|(index, _)| index).max(),
+            Some(1)
+        );
+        assert!(matches!(
+            program.insert_node(3, Node::comment("out of bounds")),
+            Err(Error::InvalidNodeIndex(3))
+        ));
+        assert!(matches!(
+            program.remove_node(2),
+            Err(Error::NodeNotFound(2))
+        ));
+    }
 }
@@ -0,0 +1,161 @@
+//! On-disk cache of lifted programs.
+//!
+//! Lifting large binaries takes a long time, therefore the lifted `hir::Program` is stored
+//! in the cache directory and reused as long as the binary (and the lifter settings) are unchanged.
+//! Cache entries are keyed by a hash over the binary content, the lifter settings and the
+//! version of specbmc (as the serialized format may change between versions).
+//! The diagnostics reported while lifting are cached as well and reported again on reuse.
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::error::Result;
+use crate::hir;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Loads the lifted program of `file_path` from the cache,
+/// or lifts it with `lift` and stores the result in the cache.
+///
+/// Cache failures are never fatal, the program is lifted instead.
+pub fn load_or_lift<S, F>(file_path: &Path, settings: &S, lift: F) -> Result<hir::Program>
+where
+    S: Hash,
+    F: FnOnce() -> Result<hir::Program>,
+{
+    let entry_path = match (cache_dir(), cache_key(file_path, settings)) {
+        (Some(dir), Ok(key)) => dir.join(format!("{:016x}.json", key)),
+        _ => return lift(),
+    };
+
+    if let Some(entry) = read_entry(&entry_path) {
+        entry.diagnostics.into_iter().for_each(diagnostics::report);
+        return Ok(entry.program);
+    }
+
+    // Separate the diagnostics of the lifter from the ones reported before
+    let previous_diagnostics = diagnostics::take();
+    let program = lift();
+    let lifter_diagnostics = diagnostics::take();
+    previous_diagnostics
+        .into_iter()
+        .chain(lifter_diagnostics.iter().cloned())
+        .for_each(diagnostics::report);
+
+    let entry = CacheEntry {
+        program: program?,
+        diagnostics: lifter_diagnostics,
+    };
+    // A failed write only means that the program is lifted again next time
+    let _ = write_entry(&entry_path, &entry);
+
+    Ok(entry.program)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    program: hir::Program,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// The cache directory, i.e. `$XDG_CACHE_HOME/specbmc` or `$HOME/.cache/specbmc`.
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .map(|dir| dir.join("specbmc"))
+}
+
+fn cache_key<S: Hash>(file_path: &Path, settings: &S) -> Result<u64> {
+    let content = fs::read(file_path)?;
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    settings.hash(&mut hasher);
+    content.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    let file = File::open(path).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+fn write_entry(path: &Path, entry: &CacheEntry) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first, concurrent runs must never see partial entries
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    let file = File::create(&tmp_path)?;
+    serde_json::to_writer(BufWriter::new(file), entry)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Code;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("specbmc-cache-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_content_and_settings() {
+        // Given:
+        let path = temp_path("binary");
+        fs::write(&path, b"\x7fELF").unwrap();
+
+        // When:
+        let key = cache_key(&path, &("x86", true)).unwrap();
+        let same_key = cache_key(&path, &("x86", true)).unwrap();
+        let other_settings = cache_key(&path, &("x86", false)).unwrap();
+        fs::write(&path, b"\x7fELF\x02").unwrap();
+        let other_content = cache_key(&path, &("x86", true)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Then:
+        assert_eq!(key, same_key);
+        assert_ne!(key, other_settings);
+        assert_ne!(key, other_content);
+    }
+
+    #[test]
+    fn test_entry_is_read_back_with_its_diagnostics() {
+        // Given:
+        let path = temp_path("entry").join("0.json");
+        let entry = CacheEntry {
+            program: hir::Program::new(),
+            diagnostics: vec![Diagnostic::warning(Code::LiftingFailed, "lifting failed")],
+        };
+
+        // When:
+        write_entry(&path, &entry).unwrap();
+        let read = read_entry(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        // Then:
+        let read = read.unwrap();
+        assert_eq!(read.diagnostics.len(), 1);
+        assert_eq!(read.diagnostics[0].code(), Code::LiftingFailed);
+        assert_eq!(read.diagnostics[0].message(), "lifting failed");
+    }
+
+    #[test]
+    fn test_broken_entry_is_ignored() {
+        // Given:
+        let path = temp_path("broken.json");
+        fs::write(&path, "{").unwrap();
+
+        // When:
+        let read = read_entry(&path);
+        fs::remove_file(&path).unwrap();
+
+        // Then:
+        assert!(read.is_none());
+    }
+}
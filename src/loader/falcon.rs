@@ -1,35 +1,139 @@
-use crate::error::Result;
+use crate::diagnostics::{self, Code, Diagnostic, Location};
+use crate::environment::{UnsupportedInstructionPolicy, WORD_SIZE};
+use crate::error::{Error, Result};
 use crate::expr;
 use crate::hir;
 use crate::loader;
 use falcon::il;
 use falcon::loader::{Elf, Loader};
 use falcon::translator;
-use std::collections::{BTreeMap, HashSet};
+use falcon_capstone::capstone;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
 #[rustfmt::skip]
-const SPECULATION_BARRIERS: &[&str] = &[
+const BARRIERS: &[(&str, hir::BarrierKind)] = &[
     // Intel
-    "mfence", "lfence", "cpuid",
+    ("mfence", hir::BarrierKind::Full),
+    ("cpuid", hir::BarrierKind::Full),
+    ("lfence", hir::BarrierKind::Load),
+    ("sfence", hir::BarrierKind::Store),
 ];
 
+#[rustfmt::skip]
+const CACHE_FLUSHES: &[&str] = &[
+    // Intel
+    "clflush", "clflushopt",
+];
+
+#[rustfmt::skip]
+const PREFETCHES: &[&str] = &[
+    // Intel
+    "prefetcht0", "prefetcht1", "prefetcht2", "prefetchnta", "prefetchw", "prefetchwt1",
+];
+
+// x86-64 ELF relocation types
+const R_X86_64_64: u32 = 1;
+const R_X86_64_GLOB_DAT: u32 = 6;
+const R_X86_64_JUMP_SLOT: u32 = 7;
+const R_X86_64_RELATIVE: u32 = 8;
+
+// ELF symbol type of data objects
+const STT_OBJECT: u8 = 1;
+
+// ELF program header type of loadable segments
+const PT_LOAD: u32 = 1;
+
 pub struct FalconLoader {
     file_path: PathBuf,
+    unsupported_instructions: UnsupportedInstructionPolicy,
+    lift_cache: bool,
+    /// Load address of position-independent executables, the base address of the binary if not given
+    load_base: Option<u64>,
 }
 
 impl FalconLoader {
-    pub fn new(file_path: &Path) -> Self {
+    pub fn new(
+        file_path: &Path,
+        unsupported_instructions: UnsupportedInstructionPolicy,
+        lift_cache: bool,
+        load_base: Option<u64>,
+    ) -> Self {
         Self {
             file_path: file_path.to_owned(),
+            unsupported_instructions,
+            lift_cache,
+            load_base,
         }
     }
+
+    fn load_elf(&self) -> Result<Elf> {
+        match self.load_base {
+            Some(base_address) => Ok(Elf::from_file_with_base_address(
+                &self.file_path,
+                base_address,
+            )?),
+            None => Ok(Elf::from_file(&self.file_path)?),
+        }
+    }
+
+    fn lift_program(&self) -> Result<hir::Program> {
+        let elf = self.load_elf()?;
+        let program = lift_elf(&elf)?;
+
+        let function_addresses: HashSet<u64> = program
+            .functions()
+            .into_iter()
+            .map(il::Function::address)
+            .collect();
+
+        let relocations = relocations(&elf, &std::fs::read(&self.file_path)?);
+        let code_pointers = code_pointers(&elf, &relocations, &function_addresses)?;
+
+        let mut hir_prog = hir::Program::new();
+
+        for function in program.functions() {
+            let mut hir_func = translate_function(function, self.unsupported_instructions)?;
+            reconstruct_calls(&mut hir_func, &function_addresses, &code_pointers);
+            add_disassembly(&elf, hir_func.control_flow_graph_mut())?;
+            hir_prog.insert_function(hir_func)?;
+        }
+
+        for (&location, &target) in &code_pointers {
+            hir_prog.insert_code_pointer(location, target);
+        }
+
+        if hir_prog
+            .set_entry(hir::ProgramEntry::Address(elf.program_entry()))
+            .is_err()
+        {
+            diagnostics::report(Diagnostic::warning(
+                Code::MissingProgramEntry,
+                "Failed to set ELF program entry, no default program entry will be set",
+            ));
+        }
+
+        for (&start_address, section) in elf.memory()?.sections() {
+            let end_address = start_address + section.len() as u64;
+            let permissions = translate_memory_permissions(section.permissions());
+            let mem_section = hir::MemorySection::new(start_address, end_address, permissions);
+            hir_prog.memory_mut().insert_section(mem_section);
+        }
+
+        insert_symbols(&elf, hir_prog.memory_mut());
+
+        for (&location, &value) in &relocations {
+            hir_prog.memory_mut().insert_relocation(location, value);
+        }
+
+        Ok(hir_prog)
+    }
 }
 
 impl loader::Loader for FalconLoader {
     fn assembly_info(&self) -> Result<loader::AssemblyInfo> {
-        let elf = load_elf(&self.file_path)?;
+        let elf = self.load_elf()?;
 
         let mut functions = Vec::new();
         for f in elf.function_entries()? {
@@ -50,50 +154,178 @@ impl loader::Loader for FalconLoader {
             });
         }
 
+        let data_symbols = named_symbols(&elf)
+            .into_iter()
+            .filter(|symbol| symbol.is_object && symbol.size > 0)
+            .map(|symbol| loader::SymbolInfo {
+                name: symbol.name,
+                address: symbol.address,
+                size: symbol.size,
+            })
+            .collect();
+
         Ok(loader::AssemblyInfo {
             entry: elf.program_entry(),
             functions,
             memory_sections,
+            data_symbols,
         })
     }
 
     fn load_program(&self) -> Result<hir::Program> {
-        let elf = load_elf(&self.file_path)?;
-        let program = lift_elf(&elf)?;
+        if self.lift_cache {
+            let settings = (self.unsupported_instructions, self.load_base);
+            loader::cache::load_or_lift(&self.file_path, &settings, || self.lift_program())
+        } else {
+            self.lift_program()
+        }
+    }
+}
 
-        let function_addresses: HashSet<u64> = program
-            .functions()
-            .into_iter()
-            .map(il::Function::address)
-            .collect();
+/// A named symbol of the symbol tables (address relative to the base address of the binary).
+struct NamedSymbol {
+    name: String,
+    address: u64,
+    size: u64,
+    is_object: bool,
+}
 
-        let mut hir_prog = hir::Program::new();
+/// Returns all named symbols of the (static and dynamic) symbol tables, each name only once.
+fn named_symbols(elf: &Elf) -> Vec<NamedSymbol> {
+    let base_address = elf.base_address();
+    let goblin_elf = elf.elf();
 
-        for function in program.functions() {
-            let mut hir_func = translate_function(function)?;
-            reconstruct_calls(&mut hir_func, &function_addresses);
-            hir_prog.insert_function(hir_func)?;
+    let symbols = goblin_elf
+        .syms
+        .iter()
+        .filter_map(|sym| Some((goblin_elf.strtab.get_at(sym.st_name)?, sym)))
+        .chain(
+            goblin_elf
+                .dynsyms
+                .iter()
+                .filter_map(|sym| Some((goblin_elf.dynstrtab.get_at(sym.st_name)?, sym))),
+        );
+
+    let mut names = HashSet::new();
+    let mut named_symbols = Vec::new();
+    for (name, sym) in symbols {
+        if name.is_empty() || sym.st_value == 0 || !names.insert(name.to_owned()) {
+            continue;
         }
+        named_symbols.push(NamedSymbol {
+            name: name.to_owned(),
+            address: base_address.wrapping_add(sym.st_value),
+            size: sym.st_size,
+            is_object: sym.st_type() == STT_OBJECT,
+        });
+    }
+    named_symbols
+}
 
-        if hir_prog
-            .set_entry(hir::ProgramEntry::Address(elf.program_entry()))
-            .is_err()
+/// Adds all (named) symbols of the symbol tables to the memory.
+fn insert_symbols(elf: &Elf, memory: &mut hir::Memory) {
+    for symbol in named_symbols(elf) {
+        memory.insert_symbol(hir::Symbol::new(symbol.name, symbol.address, symbol.size));
+    }
+}
+
+/// Collects the code pointers (pointers to function entries) stored in the binary.
+///
+/// Code pointers are given by relocations (e.g. vtables of position-independent code)
+/// and by pointer-sized values in non-executable sections (e.g. function pointer tables).
+fn code_pointers(
+    elf: &Elf,
+    relocations: &BTreeMap<u64, u64>,
+    function_addresses: &HashSet<u64>,
+) -> Result<BTreeMap<u64, u64>> {
+    let pointer_size = WORD_SIZE / 8;
+
+    let mut code_pointers = BTreeMap::new();
+
+    for (&start_address, section) in elf.memory()?.sections() {
+        if section
+            .permissions()
+            .contains(falcon::memory::MemoryPermissions::EXECUTE)
         {
-            println!("Failed to set ELF program entry, no default program entry will be set");
+            continue;
+        }
+        for (index, bytes) in section.data().chunks_exact(pointer_size).enumerate() {
+            let value = u64::from_le_bytes(bytes.try_into().unwrap());
+            if function_addresses.contains(&value) {
+                let location = start_address + (index * pointer_size) as u64;
+                code_pointers.insert(location, value);
+            }
         }
+    }
 
-        for (&start_address, section) in elf.memory()?.sections() {
-            let end_address = start_address + section.len() as u64;
-            let permissions = translate_memory_permissions(section.permissions());
-            let mem_section = hir::MemorySection::new(start_address, end_address, permissions);
-            hir_prog.memory_mut().insert_section(mem_section);
+    // Relocated pointers are not (fully) present in the section data
+    for (&location, &target) in relocations {
+        if function_addresses.contains(&target) {
+            code_pointers.insert(location, target);
         }
+    }
 
-        Ok(hir_prog)
+    Ok(code_pointers)
+}
+
+/// Applies the dynamic relocations, which gives the pointers written by the dynamic loader
+/// (location -> value, both relative to the load address).
+///
+/// Relocations of undefined (external) symbols are skipped, as their value is only known at run time.
+fn relocations(elf: &Elf, image: &[u8]) -> BTreeMap<u64, u64> {
+    let base_address = elf.base_address();
+    let goblin_elf = elf.elf();
+
+    let mut relocations = BTreeMap::new();
+    // PLT relocations (`.rela.plt`) hold the JUMP_SLOT entries of the GOT
+    let relocs = goblin_elf
+        .dynrelas
+        .iter()
+        .chain(goblin_elf.dynrels.iter())
+        .chain(goblin_elf.pltrelocs.iter());
+    for reloc in relocs {
+        let addend = match reloc.r_addend {
+            Some(addend) => addend as u64,
+            // REL relocations store the addend at the relocated location
+            None => implicit_addend(elf, image, reloc.r_offset).unwrap_or_default(),
+        };
+        let symbol = || {
+            goblin_elf
+                .dynsyms
+                .get(reloc.r_sym)
+                .filter(|sym| sym.st_value != 0)
+                .map(|sym| base_address.wrapping_add(sym.st_value))
+        };
+        let value = match reloc.r_type {
+            R_X86_64_RELATIVE => Some(base_address.wrapping_add(addend)),
+            R_X86_64_64 => symbol().map(|address| address.wrapping_add(addend)),
+            R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => symbol(),
+            _ => None,
+        };
+        if let Some(value) = value {
+            relocations.insert(base_address.wrapping_add(reloc.r_offset), value);
+        }
     }
+    relocations
+}
+
+/// Reads the (pointer-sized) implicit addend of a REL relocation at the given link-time address from the image.
+fn implicit_addend(elf: &Elf, image: &[u8], address: u64) -> Option<u64> {
+    let segment = elf.elf().program_headers.iter().find(|header| {
+        header.p_type == PT_LOAD
+            && header.p_vaddr <= address
+            && address + 8 <= header.p_vaddr + header.p_filesz
+    })?;
+    let offset = (segment.p_offset + address - segment.p_vaddr) as usize;
+    let bytes = image.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
 }
 
-fn reconstruct_calls(func: &mut hir::Function, function_addresses: &HashSet<u64>) {
+fn reconstruct_calls(
+    func: &mut hir::Function,
+    function_addresses: &HashSet<u64>,
+    code_pointers: &BTreeMap<u64, u64>,
+) {
     let cfg = func.control_flow_graph_mut();
 
     let mut calls: BTreeMap<usize, Vec<usize>> = BTreeMap::default();
@@ -101,7 +333,18 @@ fn reconstruct_calls(func: &mut hir::Function, function_addresses: &HashSet<u64>
     for block in cfg.blocks() {
         for (index, inst) in block.instructions().iter().enumerate() {
             if let hir::Operation::Branch { target } = inst.operation() {
-                if let Ok(target_address) = target.try_into() {
+                if !target.is_constant() {
+                    // Indirect branches through code pointers (e.g. vtables or jump tables)
+                    // are (tail) calls if all of their targets are functions.
+                    let is_indirect_call =
+                        hir::analysis::indirect_call_targets(code_pointers, block, index)
+                            .map_or(false, |targets| {
+                                targets.iter().all(|t| function_addresses.contains(t))
+                            });
+                    if is_indirect_call {
+                        calls.entry(block.index()).or_default().push(index);
+                    }
+                } else if let Ok(target_address) = target.try_into() {
                     // Check if the branch instruction is a valid jump, by checking if its target
                     // address matches with the address of the subsequent instruction.
                     // The reason for this check is, that while some branch targets are valid function addresses,
@@ -152,8 +395,71 @@ fn reconstruct_calls(func: &mut hir::Function, function_addresses: &HashSet<u64>
     }
 }
 
-fn load_elf(file_path: &Path) -> Result<Elf> {
-    Ok(Elf::from_file(file_path)?)
+/// Attaches the disassembly of the original machine instruction to the first instruction lifted from it.
+fn add_disassembly(elf: &Elf, cfg: &mut hir::ControlFlowGraph) -> Result<()> {
+    let addresses: BTreeSet<u64> = cfg
+        .blocks()
+        .iter()
+        .flat_map(|block| block.instructions())
+        .filter_map(hir::Instruction::address)
+        .collect();
+    let mut disassembly = disassemble(elf, &addresses)?;
+
+    for block in cfg.blocks_mut() {
+        for inst in block.instructions_mut() {
+            if let Some(address) = inst.address() {
+                inst.set_disassembly(disassembly.remove(&address));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Disassembles the machine instructions at the given addresses (mnemonic and operands).
+fn disassemble(elf: &Elf, addresses: &BTreeSet<u64>) -> Result<BTreeMap<u64, String>> {
+    let cs = capstone::Capstone::new(
+        capstone::cs_arch::CS_ARCH_X86,
+        capstone::cs_mode::CS_MODE_64,
+    )
+    .map_err(|e| format!("Failed to initialize disassembler: {:?}", e))?;
+
+    let mut disassembly = BTreeMap::new();
+    for (&start_address, section) in elf.memory()?.sections() {
+        let end_address = start_address + section.len() as u64;
+        for &address in addresses.range(start_address..end_address) {
+            let offset = (address - start_address) as usize;
+            let instructions = match cs.disasm(&section.data()[offset..], address, 1) {
+                Ok(instructions) => instructions,
+                Err(_) => continue,
+            };
+            if let Some(instruction) = instructions.get(0) {
+                let text = format!("{} {}", instruction.mnemonic, instruction.op_str);
+                disassembly.insert(address, text.trim_end().to_owned());
+            }
+        }
+    }
+
+    Ok(disassembly)
+}
+
+/// Lifts a built-in x86-64 sample (`mov rax, rbx; ret`) to check that the lifter works.
+pub fn lift_sample() -> Result<()> {
+    const SAMPLE: &[u8] = &[0x48, 0x89, 0xd8, 0xc3];
+
+    use translator::Translator;
+    let options = translator::OptionsBuilder::default().build();
+    let result = translator::x86::Amd64::new().translate_block(SAMPLE, 0x1000, &options)?;
+    if result.length() != SAMPLE.len() {
+        return Err(format!(
+            "Lifted {} of {} sample bytes",
+            result.length(),
+            SAMPLE.len()
+        )
+        .into());
+    }
+
+    Ok(())
 }
 
 fn lift_elf(elf: &Elf) -> Result<il::Program> {
@@ -165,10 +471,16 @@ fn lift_elf(elf: &Elf) -> Result<il::Program> {
     match result {
         Ok((program, lifting_errors)) => {
             lifting_errors.iter().for_each(|(func, err)| {
-                println!(
-                    "Lifting {} failed with: {}",
-                    func.name().unwrap_or("unknown"),
-                    err
+                diagnostics::report(
+                    Diagnostic::warning(
+                        Code::LiftingFailed,
+                        format!("Lifting failed with: {}", err),
+                    )
+                    .at(Location {
+                        function: Some(func.name().unwrap_or("unknown").to_owned()),
+                        address: Some(func.address()),
+                        ..Location::default()
+                    }),
                 )
             });
             Ok(program)
@@ -193,8 +505,12 @@ fn translate_memory_permissions(
     permissions
 }
 
-fn translate_function(function: &il::Function) -> Result<hir::Function> {
-    let cfg = translate_control_flow_graph(function.control_flow_graph())?;
+fn translate_function(
+    function: &il::Function,
+    unsupported_instructions: UnsupportedInstructionPolicy,
+) -> Result<hir::Function> {
+    let cfg =
+        translate_control_flow_graph(function.control_flow_graph(), unsupported_instructions)?;
     Ok(hir::Function::new(
         function.address(),
         Some(function.name()),
@@ -202,11 +518,14 @@ fn translate_function(function: &il::Function) -> Result<hir::Function> {
     ))
 }
 
-fn translate_control_flow_graph(src_cfg: &il::ControlFlowGraph) -> Result<hir::ControlFlowGraph> {
+fn translate_control_flow_graph(
+    src_cfg: &il::ControlFlowGraph,
+    unsupported_instructions: UnsupportedInstructionPolicy,
+) -> Result<hir::ControlFlowGraph> {
     let mut cfg = hir::ControlFlowGraph::new();
 
     for block in src_cfg.blocks() {
-        cfg.add_block(translate_block(block)?)?;
+        cfg.add_block(translate_block(block, unsupported_instructions)?)?;
     }
 
     for src_edge in src_cfg.edges() {
@@ -257,12 +576,24 @@ fn translate_control_flow_graph(src_cfg: &il::ControlFlowGraph) -> Result<hir::C
     Ok(cfg)
 }
 
-fn translate_block(src_block: &il::Block) -> Result<hir::Block> {
+fn translate_block(
+    src_block: &il::Block,
+    unsupported_instructions: UnsupportedInstructionPolicy,
+) -> Result<hir::Block> {
     let mut block = hir::Block::new(src_block.index());
 
     for instruction in src_block.instructions() {
-        let inst = translate_operation(&mut block, instruction.operation())?;
-        inst.set_address(instruction.address());
+        // A single operation may be translated into multiple instructions (e.g. havoc of unsupported instructions)
+        let first_index = block.instruction_count();
+        translate_operation(
+            &mut block,
+            instruction.operation(),
+            instruction.address(),
+            unsupported_instructions,
+        )?;
+        for inst in &mut block.instructions_mut()[first_index..] {
+            inst.set_address(instruction.address());
+        }
     }
 
     label_helper_instructions(&mut block);
@@ -273,6 +604,8 @@ fn translate_block(src_block: &il::Block) -> Result<hir::Block> {
 fn translate_operation<'a>(
     block: &'a mut hir::Block,
     operation: &il::Operation,
+    address: Option<u64>,
+    unsupported_instructions: UnsupportedInstructionPolicy,
 ) -> Result<&'a mut hir::Instruction> {
     match operation {
         il::Operation::Assign { dst, src } => {
@@ -322,15 +655,28 @@ fn translate_operation<'a>(
             }
         }
         il::Operation::Intrinsic { intrinsic } => {
-            if SPECULATION_BARRIERS.contains(&intrinsic.mnemonic()) {
-                Ok(block.barrier())
+            let mnemonic = intrinsic.mnemonic();
+            if let Some((_, kind)) = BARRIERS.iter().find(|(name, _)| *name == mnemonic) {
+                Ok(block.barrier(*kind))
             } else {
-                Ok(block.skip())
+                // Flushes/prefetches with unsupported memory operands are handled like unsupported instructions
+                match parse_memory_operand(intrinsic.instruction_str()) {
+                    Some(operand) if CACHE_FLUSHES.contains(&mnemonic) => {
+                        block.cache_flush(operand)
+                    }
+                    Some(operand) if PREFETCHES.contains(&mnemonic) => block.prefetch(operand),
+                    _ => translate_unsupported_instruction(
+                        block,
+                        intrinsic,
+                        address,
+                        unsupported_instructions,
+                    ),
+                }
             }
         }
         il::Operation::Nop { placeholder } => {
             if let Some(operation) = placeholder {
-                translate_operation(block, operation)
+                translate_operation(block, operation, address, unsupported_instructions)
             } else {
                 Ok(block.skip())
             }
@@ -338,11 +684,133 @@ fn translate_operation<'a>(
     }
 }
 
+/// Translates an instruction which is not supported by the lifter according to the policy.
+///
+/// Havocing over-approximates the effects on the written registers,
+/// written memory and any other side effects (e.g. on the cache) are not modeled.
+fn translate_unsupported_instruction<'a>(
+    block: &'a mut hir::Block,
+    intrinsic: &il::Intrinsic,
+    address: Option<u64>,
+    unsupported_instructions: UnsupportedInstructionPolicy,
+) -> Result<&'a mut hir::Instruction> {
+    if unsupported_instructions == UnsupportedInstructionPolicy::Error {
+        return Err(Error::UnsupportedInstruction {
+            instruction: intrinsic.instruction_str().to_owned(),
+            address,
+        });
+    }
+
+    let mut written = Vec::new();
+    if let Some(written_expressions) = intrinsic.written_expressions() {
+        for expr in written_expressions {
+            if let il::Expression::Scalar(scalar) = expr {
+                written.push(translate_scalar(scalar)?);
+            }
+        }
+    }
+
+    let effect = if written.is_empty() {
+        "its effects are ignored".to_owned()
+    } else {
+        let names: Vec<&str> = written.iter().map(expr::Variable::name).collect();
+        format!("havocing {}", names.join(", "))
+    };
+    diagnostics::report(
+        Diagnostic::warning(
+            Code::UnsupportedInstruction,
+            format!(
+                "Unsupported instruction '{}' ({}), {}",
+                intrinsic.instruction_str(),
+                intrinsic.mnemonic(),
+                effect
+            ),
+        )
+        .at(Location {
+            address,
+            ..Location::default()
+        }),
+    );
+
+    let last = match written.pop() {
+        Some(var) => var,
+        None => return Ok(block.skip()),
+    };
+    for var in written {
+        let value = expr::Expression::nondet(var.sort().clone());
+        block.assign(var, value)?;
+    }
+    let value = expr::Expression::nondet(last.sort().clone());
+    block.assign(last, value)
+}
+
 /// If multiple consecutive instructions have the same address, then all but the first
 /// instruction will be labeled as helper instructions.
 ///
 /// This is necessary because Falcon may add multiple IL instructions for a single assembly instruction,
 /// e.g. to encode the status register modifications.
+/// Parses the memory operand of an instruction in Intel syntax (e.g. `clflush byte ptr [rax + rbx*8 + 0x10]`)
+/// into an address expression.
+///
+/// Returns `None` for operands which can't be expressed without further context,
+/// e.g. RIP-relative or segment-based addressing.
+fn parse_memory_operand(instruction: &str) -> Option<expr::Expression> {
+    let start = instruction.find('[')?;
+    let end = instruction.rfind(']')?;
+    if start > end || instruction[..start].contains(':') {
+        return None;
+    }
+
+    let mut address = expr::BitVector::word_constant(0);
+    let mut negate = false;
+    for token in instruction[start + 1..end]
+        .replace('-', " - ")
+        .replace('+', " + ")
+        .split_whitespace()
+    {
+        match token {
+            "+" => negate = false,
+            "-" => negate = true,
+            term => {
+                let mut value = parse_memory_operand_term(term)?;
+                if negate {
+                    value = expr::BitVector::neg(value).ok()?;
+                }
+                address = expr::BitVector::add(address, value).ok()?;
+            }
+        }
+    }
+
+    Some(address)
+}
+
+/// Parses `reg`, `reg*scale` or a constant.
+fn parse_memory_operand_term(term: &str) -> Option<expr::Expression> {
+    let parse_constant = |s: &str| match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse::<u64>().ok(),
+    };
+
+    if let Some(value) = parse_constant(term) {
+        return Some(expr::BitVector::word_constant(value));
+    }
+
+    let (register, scale) = match term.split_once('*') {
+        Some((register, scale)) => (register, parse_constant(scale)?),
+        None => (term, 1),
+    };
+    if register == "rip" || !register.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let register: expr::Expression = expr::BitVector::word_variable(register).into();
+    if scale == 1 {
+        Some(register)
+    } else {
+        expr::BitVector::mul(register, expr::BitVector::word_constant(scale)).ok()
+    }
+}
+
 fn label_helper_instructions(block: &mut hir::Block) {
     let mut last_address: Option<u64> = None;
     for inst in block.instructions_mut() {
@@ -526,3 +994,33 @@ fn is_taken_edge(cfg: &il::ControlFlowGraph, edge: &il::Edge) -> Result<bool> {
 
     Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory_operand() {
+        let rax: expr::Expression = expr::BitVector::word_variable("rax").into();
+        let rbx: expr::Expression = expr::BitVector::word_variable("rbx").into();
+        let word = expr::BitVector::word_constant;
+        let add = |lhs, rhs| expr::BitVector::add(lhs, rhs).unwrap();
+
+        assert_eq!(
+            parse_memory_operand("clflush byte ptr [rax]"),
+            Some(add(word(0), rax.clone()))
+        );
+        assert_eq!(
+            parse_memory_operand("prefetcht0 byte ptr [rax + rbx*8 - 0x10]"),
+            Some(add(
+                add(
+                    add(word(0), rax),
+                    expr::BitVector::mul(rbx, word(8)).unwrap()
+                ),
+                expr::BitVector::neg(word(0x10)).unwrap()
+            ))
+        );
+        assert_eq!(parse_memory_operand("clflush byte ptr [rip + 0x10]"), None);
+        assert_eq!(parse_memory_operand("clflush byte ptr fs:[rax]"), None);
+    }
+}
@@ -1,9 +1,12 @@
+use crate::environment::UnsupportedInstructionPolicy;
 use crate::error::Result;
 use crate::hir;
+use std::collections::BTreeSet;
 use std::ffi::OsStr;
 use std::fmt;
 use std::path::Path;
 
+mod cache;
 mod falcon;
 mod muasm;
 
@@ -12,13 +15,48 @@ pub trait Loader {
     fn load_program(&self) -> Result<hir::Program>;
 }
 
-pub fn loader_for_file(file_path: &Path) -> Option<Box<dyn Loader>> {
+/// Returns the loader for the given file.
+///
+/// If `lift_cache` is set, lifted binaries are cached on disk and reused by later runs.
+/// Position-independent binaries are loaded at `load_base` (if given).
+pub fn loader_for_file(
+    file_path: &Path,
+    unsupported_instructions: UnsupportedInstructionPolicy,
+    lift_cache: bool,
+    load_base: Option<u64>,
+) -> Option<Box<dyn Loader>> {
     match file_path.extension().and_then(OsStr::to_str) {
         Some("muasm") => Some(Box::new(muasm::MuasmLoader::new(file_path))),
-        _ => Some(Box::new(falcon::FalconLoader::new(file_path))),
+        _ => Some(Box::new(falcon::FalconLoader::new(
+            file_path,
+            unsupported_instructions,
+            lift_cache,
+            load_base,
+        ))),
     }
 }
 
+/// Returns the loader for muasm source code given directly instead of a file.
+pub fn loader_for_muasm_source(source: &str) -> Box<dyn Loader> {
+    Box::new(muasm::MuasmLoader::from_source(source))
+}
+
+/// Checks that binaries can be lifted by lifting a built-in sample.
+pub fn check_lifter() -> Result<()> {
+    falcon::lift_sample()
+}
+
+/// Returns the muasm source code with a speculation barrier in front of each instruction
+/// whose address is contained in `addresses`.
+/// Includes (relative to `directory`) and macros are expanded in the returned source code.
+pub fn insert_muasm_barriers(
+    source: &str,
+    directory: &Path,
+    addresses: &BTreeSet<u64>,
+) -> Result<String> {
+    muasm::insert_barriers(source, directory, addresses)
+}
+
 pub struct FunctionInfo {
     pub address: u64,
     pub name: Option<String>,
@@ -50,10 +88,30 @@ impl fmt::Display for MemorySectionInfo {
     }
 }
 
+/// A data object (e.g. a global variable) of the symbol table.
+pub struct SymbolInfo {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+impl fmt::Display for SymbolInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:X} - 0x{:X}: {}",
+            self.address,
+            self.address + self.size,
+            self.name
+        )
+    }
+}
+
 pub struct AssemblyInfo {
     pub entry: u64,
     pub functions: Vec<FunctionInfo>,
     pub memory_sections: Vec<MemorySectionInfo>,
+    pub data_symbols: Vec<SymbolInfo>,
 }
 
 impl fmt::Display for AssemblyInfo {
@@ -69,6 +127,12 @@ impl fmt::Display for AssemblyInfo {
                 writeln!(f, "  {}", section)?;
             }
         }
+        if !self.data_symbols.is_empty() {
+            writeln!(f, "Data Symbols:")?;
+            for symbol in &self.data_symbols {
+                writeln!(f, "  {}", symbol)?;
+            }
+        }
         Ok(())
     }
 }
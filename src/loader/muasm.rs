@@ -1,25 +1,57 @@
 use crate::error::Result;
 use crate::expr;
 use crate::hir;
-use crate::loader::{AssemblyInfo, FunctionInfo, Loader};
+use crate::loader::{AssemblyInfo, FunctionInfo, Loader, MemorySectionInfo, SymbolInfo};
 use muasm_parser::{ir, parser};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 const MAIN_ADDRESS: u64 = 0;
 const MAIN_NAME: &str = "main";
 
+/// Max. depth of nested macro invocations (guards against recursive macros)
+const MAX_MACRO_DEPTH: usize = 32;
+
+/// Start address of data sections without explicit address
+const DEFAULT_DATA_ADDRESS: u64 = 0x1000_0000;
+/// Alignment of labeled data
+const DATA_ALIGNMENT: u64 = 8;
+
+enum Source {
+    File(PathBuf),
+    Inline(String),
+}
+
 pub struct MuasmLoader {
-    file_path: PathBuf,
+    source: Source,
 }
 
 impl MuasmLoader {
     pub fn new(file_path: &Path) -> Self {
         Self {
-            file_path: file_path.to_owned(),
+            source: Source::File(file_path.to_owned()),
         }
     }
+
+    /// Loader for muasm source code which is given directly instead of a file.
+    pub fn from_source(source: &str) -> Self {
+        Self {
+            source: Source::Inline(source.to_owned()),
+        }
+    }
+
+    /// Returns the preprocessed source code (without data sections) along with the data.
+    fn load_source(&self) -> Result<(String, Vec<Data>)> {
+        let source = match &self.source {
+            Source::File(file_path) => {
+                let directory = file_path.parent().unwrap_or_else(|| Path::new("."));
+                preprocess(&fs::read_to_string(file_path)?, directory)?
+            }
+            Source::Inline(source) => preprocess(source, Path::new("."))?,
+        };
+        extract_data_sections(&source)
+    }
 }
 
 impl Loader for MuasmLoader {
@@ -29,29 +61,457 @@ impl Loader for MuasmLoader {
             name: Some(MAIN_NAME.to_owned()),
         };
 
+        let (_, data) = self.load_source()?;
+
         Ok(AssemblyInfo {
             entry: MAIN_ADDRESS,
             functions: vec![main],
-            memory_sections: Vec::default(),
+            memory_sections: data
+                .iter()
+                .map(|data| MemorySectionInfo {
+                    start_address: data.address,
+                    end_address: data.end_address(),
+                    permissions: hir::MemoryPermissions::READ | hir::MemoryPermissions::WRITE,
+                })
+                .collect(),
+            data_symbols: data
+                .iter()
+                .map(|data| SymbolInfo {
+                    name: data.label.clone(),
+                    address: data.address,
+                    size: data.bytes.len() as u64,
+                })
+                .collect(),
         })
     }
 
     fn load_program(&self) -> Result<hir::Program> {
-        let source = fs::read_to_string(&self.file_path)?;
+        let (source, data) = self.load_source()?;
         let ir = parser::parse_program(&source)?;
 
-        let cfg = translate_ir_to_hir(&ir)?;
+        let data_addresses = data
+            .iter()
+            .map(|data| {
+                (
+                    expr::BitVector::word_variable(&data.label),
+                    expr::BitVector::word_constant(data.address),
+                )
+            })
+            .collect();
+
+        let cfg = translate_ir_to_hir(&ir, &source_instructions(&source), &data_addresses)?;
         let function = hir::Function::new(MAIN_ADDRESS, Some(MAIN_NAME.to_owned()), cfg);
 
         let mut program = hir::Program::new();
         program.insert_function(function)?;
         program.set_entry(hir::ProgramEntry::Address(MAIN_ADDRESS))?;
 
+        let memory = program.memory_mut();
+        for data in data {
+            memory.insert_section(hir::MemorySection::new(
+                data.address,
+                data.end_address(),
+                hir::MemoryPermissions::READ | hir::MemoryPermissions::WRITE,
+            ));
+            memory.insert_symbol(hir::Symbol::new(
+                data.label.clone(),
+                data.address,
+                data.bytes.len() as u64,
+            ));
+            memory.insert_data(data.address, data.bytes);
+        }
+
         Ok(program)
     }
 }
 
-fn translate_ir_to_hir(program: &ir::Program) -> Result<hir::ControlFlowGraph> {
+/// Labeled data of a muasm data section.
+#[derive(Debug, PartialEq)]
+struct Data {
+    label: String,
+    address: u64,
+    bytes: Vec<u8>,
+}
+
+impl Data {
+    fn end_address(&self) -> u64 {
+        self.address + self.bytes.len() as u64
+    }
+}
+
+/// Removes the data sections from the source code and returns them separately.
+///
+/// A data section starts with `#data [ADDRESS]` and ends with `#enddata`, each data item is written as
+/// `[LABEL:] .byte VALUE, ...`, `[LABEL:] .word VALUE, ...` (64-bit, little-endian) or `[LABEL:] .zero SIZE`.
+/// Items without label extend the data of the preceding label, labels are aligned to 8 bytes.
+/// Sections without address follow the previous section (or start at `DEFAULT_DATA_ADDRESS`).
+/// The lines of the data sections are kept as empty lines in the returned source code.
+fn extract_data_sections(source: &str) -> Result<(String, Vec<Data>)> {
+    let mut code = String::new();
+    let mut data: Vec<Data> = Vec::new();
+    let mut next_address = DEFAULT_DATA_ADDRESS;
+    let mut in_data_section = false;
+
+    for line in source.lines() {
+        let text = line.split('%').next().unwrap_or_default().trim();
+
+        if let Some(address) = text.strip_prefix("#data") {
+            if in_data_section {
+                return Err("Nested data section (missing #enddata)".into());
+            }
+            if !address.trim().is_empty() {
+                next_address = parse_number(address.trim())?;
+            }
+            in_data_section = true;
+        } else if text == "#enddata" {
+            if !in_data_section {
+                return Err("#enddata without data section".into());
+            }
+            in_data_section = false;
+        } else if in_data_section && !text.is_empty() {
+            let (item, bytes) = match split_label(text) {
+                Some((label, end)) => {
+                    if data.iter().any(|data| data.label == label) {
+                        return Err(format!("Duplicate data label '{}'", label).into());
+                    }
+                    let address =
+                        (next_address + DATA_ALIGNMENT - 1) / DATA_ALIGNMENT * DATA_ALIGNMENT;
+                    data.push(Data {
+                        label: label.to_owned(),
+                        address,
+                        bytes: Vec::new(),
+                    });
+                    (text[end..].trim(), parse_data_item(text[end..].trim())?)
+                }
+                None => (text, parse_data_item(text)?),
+            };
+            let labeled = data
+                .last_mut()
+                .ok_or_else(|| format!("Data '{}' without label", item))?;
+            labeled.bytes.extend(bytes);
+            next_address = labeled.end_address();
+        } else if !in_data_section {
+            code.push_str(line);
+        }
+        code.push('\n');
+    }
+
+    if in_data_section {
+        return Err("Missing #enddata of data section".into());
+    }
+
+    Ok((code, data))
+}
+
+/// Parses `.byte VALUE, ...`, `.word VALUE, ...` or `.zero SIZE` into bytes.
+fn parse_data_item(item: &str) -> Result<Vec<u8>> {
+    let (directive, values) = item.split_once(char::is_whitespace).unwrap_or((item, ""));
+    let values = values
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(parse_number)
+        .collect::<Result<Vec<u64>>>()?;
+
+    match directive {
+        ".byte" => values
+            .into_iter()
+            .map(|value| {
+                u8::try_from(value).map_err(|_| format!("Byte value {} out of range", value).into())
+            })
+            .collect(),
+        ".word" => Ok(values
+            .into_iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect()),
+        ".zero" if values.len() == 1 => Ok(vec![0; values[0] as usize]),
+        _ => Err(format!("Invalid data '{}'", item).into()),
+    }
+}
+
+fn parse_number(text: &str) -> Result<u64> {
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    value.map_err(|_| format!("Invalid number '{}'", text).into())
+}
+
+/// Inserts a speculation barrier (`spbarr`) in front of each instruction of the muasm source
+/// whose address is contained in `addresses`, comments and labels are kept as they are.
+///
+/// Includes and macros are expanded first (relative to `directory`),
+/// as barriers may be required within shared code.
+pub fn insert_barriers(
+    source: &str,
+    directory: &Path,
+    addresses: &BTreeSet<u64>,
+) -> Result<String> {
+    let trailing_newline = source.ends_with('\n');
+    let source = preprocess(source, directory)?;
+    // Data sections are kept as they are
+    let (code, _) = extract_data_sections(&source)?;
+    let program = parser::parse_program(&code)?;
+    let instruction_addresses: Vec<u64> = program
+        .instructions()
+        .iter()
+        .map(|instruction| instruction.address())
+        .collect();
+    let mut instruction_addresses = instruction_addresses.into_iter();
+
+    let mut patched = String::new();
+    for (line, code) in source.lines().zip(code.lines()) {
+        let code = code.split('%').next().unwrap_or_default();
+        let label = split_label(code);
+        let instruction = &code[label.map_or(0, |(_, end)| end)..];
+        if instruction.trim().is_empty() {
+            patched.push_str(line);
+            patched.push('\n');
+            continue;
+        }
+
+        let address = instruction_addresses
+            .next()
+            .ok_or("Unable to map muasm instructions to source lines")?;
+        if !addresses.contains(&address) {
+            patched.push_str(line);
+            patched.push('\n');
+            continue;
+        }
+
+        // The label has to point to the barrier, such that jumps to the instruction are fenced as well
+        match label {
+            Some((label, end)) => {
+                patched.push_str(&format!("{}:\n    spbarr\n", label));
+                patched.push_str(&format!("    {}\n", line[end..].trim_start()));
+            }
+            None => {
+                // The barrier is indented like the fenced instruction, such that the layout is kept
+                let indentation = &line[..line.len() - line.trim_start().len()];
+                patched.push_str(&format!("{}spbarr\n", indentation));
+                patched.push_str(line);
+                patched.push('\n');
+            }
+        }
+    }
+
+    if instruction_addresses.next().is_some() {
+        return Err("Unable to map muasm instructions to source lines".into());
+    }
+
+    if !trailing_newline {
+        patched.pop();
+    }
+
+    Ok(patched)
+}
+
+/// Expands `#include "FILE"` directives and macros of the muasm source.
+///
+/// Included files are resolved relative to `directory` (the directory of the including file).
+/// Macros are defined by `#macro NAME(PARAM, ...)` up to `#endmacro` and invoked by `NAME(ARG, ...)`
+/// in place of an instruction, each parameter in the body is replaced by the corresponding argument.
+fn preprocess(source: &str, directory: &Path) -> Result<String> {
+    let mut preprocessor = Preprocessor::default();
+    let mut expanded = String::new();
+    preprocessor.expand_source(source, directory, &mut expanded)?;
+    Ok(expanded)
+}
+
+#[derive(Default)]
+struct Preprocessor {
+    macros: HashMap<String, Macro>,
+    /// Files which are currently included (to detect recursive includes)
+    includes: Vec<PathBuf>,
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+impl Preprocessor {
+    fn expand_source(&mut self, source: &str, directory: &Path, out: &mut String) -> Result<()> {
+        let mut lines = source.lines();
+        while let Some(line) = lines.next() {
+            let code = line.split('%').next().unwrap_or_default().trim();
+
+            if let Some(file) = code.strip_prefix("#include") {
+                let file = file
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|file| file.strip_suffix('"'))
+                    .ok_or_else(|| format!("Invalid include directive '{}'", code))?;
+                self.include(&directory.join(file), out)?;
+            } else if let Some(signature) = code.strip_prefix("#macro") {
+                let (name, parameters) = parse_invocation(signature.trim())
+                    .ok_or_else(|| format!("Invalid macro definition '{}'", code))?;
+                let mut body = Vec::new();
+                loop {
+                    let line = lines
+                        .next()
+                        .ok_or_else(|| format!("Missing #endmacro of macro '{}'", name))?;
+                    if line.split('%').next().unwrap_or_default().trim() == "#endmacro" {
+                        break;
+                    }
+                    body.push(line.to_owned());
+                }
+                self.macros
+                    .insert(name.to_owned(), Macro { parameters, body });
+            } else if code.starts_with('#') && !is_data_directive(code) {
+                return Err(format!("Unknown directive '{}'", code).into());
+            } else {
+                self.expand_line(line, 0, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, file_path: &Path, out: &mut String) -> Result<()> {
+        // Canonicalize, such that different spellings of the same file (e.g. `./a` vs. `a`) match
+        let file_path = fs::canonicalize(file_path)
+            .map_err(|e| format!("Unable to include '{}': {}", file_path.display(), e))?;
+        if self.includes.contains(&file_path) {
+            return Err(format!("Recursive include of '{}'", file_path.display()).into());
+        }
+        let source = fs::read_to_string(&file_path)
+            .map_err(|e| format!("Unable to include '{}': {}", file_path.display(), e))?;
+
+        let directory = file_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_owned();
+        self.includes.push(file_path);
+        self.expand_source(&source, &directory, out)?;
+        self.includes.pop();
+
+        Ok(())
+    }
+
+    /// Expands the line if it is a macro invocation, otherwise it is kept as it is.
+    fn expand_line(&self, line: &str, depth: usize, out: &mut String) -> Result<()> {
+        let code = line.split('%').next().unwrap_or_default();
+        let label = split_label(code);
+        let instruction = code[label.map_or(0, |(_, end)| end)..].trim();
+
+        let (name, arguments) = match parse_invocation(instruction) {
+            Some((name, arguments)) if self.macros.contains_key(name) => (name, arguments),
+            _ => {
+                out.push_str(line);
+                out.push('\n');
+                return Ok(());
+            }
+        };
+
+        if depth >= MAX_MACRO_DEPTH {
+            return Err(format!("Macro '{}' is nested too deeply (recursive macro?)", name).into());
+        }
+
+        let definition = &self.macros[name];
+        if arguments.len() != definition.parameters.len() {
+            return Err(format!(
+                "Macro '{}' expects {} arguments but {} were given",
+                name,
+                definition.parameters.len(),
+                arguments.len()
+            )
+            .into());
+        }
+
+        // The label of the invocation points to the first instruction of the expanded body
+        if let Some((label, _)) = label {
+            out.push_str(label);
+            out.push_str(":\n");
+        }
+        for body_line in &definition.body {
+            let body_line = substitute(body_line, &definition.parameters, &arguments);
+            self.expand_line(&body_line, depth + 1, out)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `NAME(ARG, ...)` into the name and the (trimmed) arguments.
+fn parse_invocation(text: &str) -> Option<(&str, Vec<String>)> {
+    let (name, arguments) = text.strip_suffix(')')?.split_once('(')?;
+    let name = name.trim();
+    if !is_identifier(name) {
+        return None;
+    }
+    let arguments = if arguments.trim().is_empty() {
+        Vec::new()
+    } else {
+        arguments
+            .split(',')
+            .map(|argument| argument.trim().to_owned())
+            .collect()
+    };
+    Some((name, arguments))
+}
+
+/// Replaces each occurrence of a parameter (as whole identifier) by the corresponding argument.
+fn substitute(line: &str, parameters: &[String], arguments: &[String]) -> String {
+    let mut substituted = String::new();
+    let mut identifier = String::new();
+    for c in line.chars().chain(std::iter::once('\n')) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            identifier.push(c);
+            continue;
+        }
+        match parameters
+            .iter()
+            .position(|parameter| *parameter == identifier)
+        {
+            Some(index) => substituted.push_str(&arguments[index]),
+            None => substituted.push_str(&identifier),
+        }
+        identifier.clear();
+        substituted.push(c);
+    }
+    substituted.pop(); // Trailing newline
+    substituted
+}
+
+fn is_data_directive(code: &str) -> bool {
+    code == "#data" || code.starts_with("#data ") || code == "#enddata"
+}
+
+fn is_identifier(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the source code of each muasm instruction (without label and comment).
+fn source_instructions(source: &str) -> Vec<&str> {
+    source
+        .lines()
+        .map(|line| {
+            let code = line.split('%').next().unwrap_or_default();
+            let label = split_label(code);
+            code[label.map_or(0, |(_, end)| end)..].trim()
+        })
+        .filter(|instruction| !instruction.is_empty())
+        .collect()
+}
+
+/// Returns the label of the muasm code (if any) along with the offset of the code following it.
+fn split_label(code: &str) -> Option<(&str, usize)> {
+    let trimmed = code.trim_start();
+    let (label, _) = trimmed.split_once(':')?;
+    if !is_identifier(label) {
+        return None;
+    }
+    let indentation = code.len() - trimmed.len();
+    Some((label, indentation + label.len() + 1))
+}
+
+/// Translates the muasm program into a CFG, `disassembly` gives the source code of each instruction.
+/// Data labels used as operands are replaced by their address (`data_addresses`).
+fn translate_ir_to_hir(
+    program: &ir::Program,
+    disassembly: &[&str],
+    data_addresses: &HashMap<expr::Variable, expr::Expression>,
+) -> Result<hir::ControlFlowGraph> {
     let mut cfg = hir::ControlFlowGraph::new();
 
     // Mapping from instruction address to instruction graph entry/exit
@@ -75,8 +535,15 @@ fn translate_ir_to_hir(program: &ir::Program) -> Result<hir::ControlFlowGraph> {
         }
     };
 
+    // The source code can only be attached if each instruction has been mapped to a source line
+    let disassembly = if disassembly.len() == program.instructions().len() {
+        disassembly
+    } else {
+        &[]
+    };
+
     // Add instruction graph for each instruction to CFG
-    for instruction in program.instructions() {
+    for (index, instruction) in program.instructions().iter().enumerate() {
         let mut instruction_graph = hir::ControlFlowGraph::new();
 
         match instruction.operation() {
@@ -103,9 +570,26 @@ fn translate_ir_to_hir(program: &ir::Program) -> Result<hir::ControlFlowGraph> {
             }
         }?;
 
+        if !data_addresses.is_empty() {
+            for block in instruction_graph.blocks_mut() {
+                for expr in block.expressions_mut() {
+                    *expr = expr.substitute(data_addresses);
+                }
+            }
+        }
+
         let address = instruction.address();
         instruction_graph.set_address(Some(address));
 
+        if let Some(inst) = instruction_graph
+            .blocks_mut()
+            .into_iter()
+            .flat_map(|block| block.instructions_mut())
+            .next()
+        {
+            inst.set_disassembly(disassembly.get(index).map(|text| text.to_string()));
+        }
+
         let block_renamings = cfg.insert(&instruction_graph)?;
 
         let entry = instruction_graph.entry().unwrap();
@@ -295,7 +779,7 @@ mod semantics {
         let block_index = {
             let block = cfg.new_block();
 
-            block.barrier();
+            block.barrier(hir::BarrierKind::Speculation);
 
             block.index()
         };
@@ -456,3 +940,152 @@ mod semantics {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_barriers_in_front_of_instructions() {
+        // Given:
+        let source = "    x <- 1\nL1:\n    load y, x\n    % comment\nL2: skip\n";
+        let addresses = vec![1, 2].into_iter().collect();
+
+        // When:
+        let patched = insert_barriers(source, Path::new("."), &addresses).unwrap();
+
+        // Then:
+        assert_eq!(
+            patched,
+            "    x <- 1\nL1:\n    spbarr\n    load y, x\n    % comment\nL2:\n    spbarr\n    skip\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_barriers_keeps_spectector_benchmark_layout() {
+        // Given:
+        let source = "  c <- x < size\n  beqz c, End % bounds check\n  load v, a + x\n  jmp End\nEnd:\n  spbarr";
+
+        // When:
+        let unchanged = insert_barriers(source, Path::new("."), &BTreeSet::new()).unwrap();
+        let patched =
+            insert_barriers(source, Path::new("."), &vec![2].into_iter().collect()).unwrap();
+
+        // Then:
+        assert_eq!(unchanged, source);
+        assert_eq!(
+            patched,
+            "  c <- x < size\n  beqz c, End % bounds check\n  spbarr\n  load v, a + x\n  jmp End\nEnd:\n  spbarr"
+        );
+    }
+
+    #[test]
+    fn test_preprocess_expands_macros() {
+        // Given:
+        let source = "#macro load_secret(reg, addr)\n    load reg, addr % secret\n#endmacro\n\
+                      #macro prologue()\n    x <- 0\n    load_secret(s, x)\n#endmacro\n\
+                      L1: prologue()\n    load_secret(t, s)\n";
+
+        // When:
+        let expanded = preprocess(source, Path::new(".")).unwrap();
+
+        // Then:
+        assert_eq!(
+            expanded,
+            "L1:\n    x <- 0\n    load s, x % secret\n    load t, s % secret\n"
+        );
+    }
+
+    #[test]
+    fn test_preprocess_rejects_wrong_number_of_macro_arguments() {
+        // Given:
+        let source = "#macro m(a, b)\n    a <- b\n#endmacro\n    m(x)\n";
+
+        // When:
+        let result = preprocess(source, Path::new("."));
+
+        // Then:
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_preprocess_rejects_recursive_include_through_different_path() {
+        // Given: a file which includes itself through a differently spelled path
+        let directory =
+            std::env::temp_dir().join(format!("specbmc-include-{}", std::process::id()));
+        fs::create_dir_all(directory.join("sub")).unwrap();
+        fs::write(directory.join("a.muasm"), "#include \"./sub/../a.muasm\"\n").unwrap();
+
+        // When:
+        let result = preprocess("#include \"a.muasm\"\n", &directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        // Then:
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("Recursive include"), "{}", error);
+    }
+
+    #[test]
+    fn test_extract_data_sections() {
+        // Given:
+        let source = "#data 0x100\narray: .byte 1, 2 % values\n       .byte 3\n\
+                      size: .word 0x3\n#enddata\n    load x, array\n#data\nbuf: .zero 2\n#enddata\n";
+
+        // When:
+        let (code, data) = extract_data_sections(source).unwrap();
+
+        // Then:
+        assert_eq!(code, "\n\n\n\n\n    load x, array\n\n\n\n");
+        assert_eq!(
+            data,
+            vec![
+                Data {
+                    label: "array".to_owned(),
+                    address: 0x100,
+                    bytes: vec![1, 2, 3],
+                },
+                Data {
+                    label: "size".to_owned(),
+                    address: 0x108,
+                    bytes: vec![3, 0, 0, 0, 0, 0, 0, 0],
+                },
+                Data {
+                    label: "buf".to_owned(),
+                    address: 0x110,
+                    bytes: vec![0, 0],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_program_with_data_labels_as_addresses() {
+        // Given:
+        let loader =
+            MuasmLoader::from_source("#data 0x100\narray: .byte 7\n#enddata\n    load x, array\n");
+
+        // When:
+        let program = loader.load_program().unwrap();
+
+        // Then:
+        assert_eq!(program.memory().data().get(&0x100), Some(&vec![7]));
+        assert_eq!(program.memory().symbol("array").unwrap().address(), 0x100);
+        let function = program.function_by_address(MAIN_ADDRESS).unwrap();
+        assert!(!function
+            .control_flow_graph()
+            .variables_read()
+            .contains(&&expr::BitVector::word_variable("array")));
+    }
+
+    #[test]
+    fn test_source_instructions_without_labels_and_comments() {
+        // Given:
+        let source = "    x <- 1\nL1:\n    load y, x % load\n    % comment\nL2: skip\n";
+
+        // When:
+        let instructions = source_instructions(source);
+
+        // Then:
+        assert_eq!(instructions, vec!["x <- 1", "load y, x", "skip"]);
+    }
+}
@@ -1,16 +1,26 @@
 #[macro_use]
 extern crate clap;
-use clap::Arg;
+
+mod batch;
+mod debug_dir;
+mod doctor;
+mod env_init;
+use clap::{AppSettings, Arg, SubCommand};
 use colored::*;
 use console::style;
+use debug_dir::DebugDir;
 
 use specbmc::environment;
-use specbmc::error::Result;
-use specbmc::ir::{TryTranslateFrom, Validate};
+use specbmc::error::{Error, Result};
+use specbmc::ir::{ViolationClass, ViolationPolicy};
 use specbmc::loader;
+use specbmc::pipeline::{self, Stage};
+use specbmc::repair;
 use specbmc::solver::*;
 use specbmc::util::{DumpToFile, RenderGraph};
-use specbmc::{cex, hir, lir, mir};
+use specbmc::{cex, comparison, diagnostics, expr, hir, lir};
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
 use std::path::Path;
 use std::process;
 
@@ -18,14 +28,12 @@ fn main() {
     let arguments = parse_arguments();
     if let Err(e) = spec_bmc(&arguments) {
         println!("{}", style(&e).bold().red());
-        if let Some(backtrace) = e.backtrace() {
-            println!("{:?}", backtrace);
-        }
         process::exit(1);
     }
 }
 
 struct Arguments {
+    command: Command,
     environment_file: Option<String>,
     optimization_level: Option<environment::OptimizationLevel>,
     check: Option<environment::Check>,
@@ -33,233 +41,630 @@ struct Arguments {
     predictor_strategy: Option<environment::PredictorStrategy>,
     observe: Option<environment::Observe>,
     model: Option<environment::Model>,
+    compatibility: Option<environment::Compatibility>,
     program_entry: Option<String>,
     unwind: Option<usize>,
+    max_unwind: Option<usize>,
     unwinding_guard: Option<environment::UnwindingGuard>,
+    allow_irreducible: bool,
+    overrides: Vec<(String, String)>,
     recursion_limit: Option<usize>,
     speculation_window: Option<usize>,
+    sweep_speculation_windows: Option<Vec<usize>>,
+    min_speculation_window: bool,
     debug: bool,
+    debug_dir: Option<String>,
     skip_solving: bool,
     skip_cex: bool,
+    violation_policy: ViolationPolicy,
+    no_cache: bool,
     cex_file: String,
+    cex_diff: bool,
+    cex_text: bool,
+    cex_gdb_file: Option<String>,
+    cex_litmus_file: Option<String>,
+    cex_store_file: Option<String>,
+    report_file: Option<String>,
+    certificate_dir: Option<String>,
+    suggest_fences: bool,
+    suggest_masking: bool,
+    fence_patch_file: Option<String>,
+    intended_fences: BTreeSet<u64>,
     cfg_file: Option<String>,
     transient_cfg_file: Option<String>,
+    trans_only_cfg_file: Option<String>,
+    trans_only_address: Option<u64>,
     call_graph_file: Option<String>,
     loop_tree_file: Option<String>,
+    hir_file: Option<String>,
     mir_file: Option<String>,
     lir_file: Option<String>,
+    lir_slices: bool,
     smt_file: Option<String>,
+    smt_split_dir: Option<String>,
     input_file: String,
-    print_assembly_info: bool,
+    inline_asm: Option<String>,
+    batch_dir: Option<String>,
+    jobs: usize,
+    batch_report_file: Option<String>,
+    compare_file: Option<String>,
+    product_file: Option<String>,
+    compare_models: bool,
     show_environment: bool,
+    deny_warnings: bool,
+    diagnostics_file: Option<String>,
 }
 
-fn parse_arguments() -> Arguments {
-    use environment::*;
+/// The (sub)command to be executed.
+enum Command {
+    /// Checks the program for leaks
+    Check,
+    /// Prints assembly info of the program
+    Info,
+    /// Renders program graphs without checking the program
+    Render,
+    /// Prints the environment (defaults and arguments applied)
+    ShowEnvironment,
+    /// Prints the default environment
+    GenerateEnvironment { output_file: Option<String> },
+    /// Writes a starter environment for the given binary
+    InitEnvironment {
+        binary_file: String,
+        output_file: Option<String>,
+        force: bool,
+    },
+    /// Validates the given environment files
+    CheckEnvironment { files: Vec<String> },
+    /// Renders the counterexample stored in the given file
+    RenderCounterExample { file: String },
+    /// Checks the solver installation and the lifter
+    Doctor,
+}
 
-    fn is_positive_number(s: String) -> std::result::Result<(), String> {
-        if s.parse::<usize>().is_ok() {
-            Ok(())
-        } else {
-            Err(format!("{} isn't a positive number", s))
-        }
+fn is_positive_number(s: String) -> std::result::Result<(), String> {
+    if s.parse::<usize>().is_ok() {
+        Ok(())
+    } else {
+        Err(format!("{} isn't a positive number", s))
+    }
+}
+
+fn parse_address(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn is_address(s: String) -> std::result::Result<(), String> {
+    if parse_address(&s).is_some() {
+        Ok(())
+    } else {
+        Err(format!("{} isn't an address", s))
     }
+}
+
+fn is_override(s: String) -> std::result::Result<(), String> {
+    if s.split_once('=')
+        .map_or(false, |(path, _)| !path.is_empty())
+    {
+        Ok(())
+    } else {
+        Err(format!("{} isn't of the form PATH=VALUE", s))
+    }
+}
+
+/// Arguments selecting the input program,
+/// the input file is required unless one of `alternatives` is given.
+fn input_args(alternatives: &'static [&'static str]) -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("input_file")
+            .value_name("FILE")
+            .help("Input file to be checked ('-' reads a muasm program from stdin)")
+            .required_unless_one(alternatives)
+            .index(1),
+        Arg::with_name("inline_asm")
+            .long("inline-asm")
+            .value_name("SOURCE")
+            .help("Checks the given muasm program instead of an input file")
+            .conflicts_with("input_file")
+            .takes_value(true),
+    ]
+}
+
+/// Arguments overwriting the environment.
+fn environment_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("environment_file")
+            .short("e")
+            .long("env")
+            .value_name("FILE")
+            .help("Sets environment file to use (arguments overwrite it)")
+            .takes_value(true),
+        Arg::with_name("optimization_level")
+            .short("o")
+            .long("opt")
+            .value_name("LEVEL")
+            .possible_values(&["none", "basic", "full"])
+            .help("Sets optimization level")
+            .takes_value(true),
+        Arg::with_name("check")
+            .short("c")
+            .long("check")
+            .value_name("TYPE")
+            .possible_values(&["all", "normal", "transient", "oob", "interference"])
+            .help("Sets leak check type (oob: speculative out-of-bounds loads, interference: transient leaks incl. resolution timing)")
+            .takes_value(true),
+        Arg::with_name("observe")
+            .long("observe")
+            .value_name("OBSERVE")
+            .possible_values(&["sequential", "parallel", "full", "trace"])
+            .help("Sets observation type")
+            .takes_value(true),
+        Arg::with_name("model")
+            .long("model")
+            .value_name("MODEL")
+            .possible_values(&["components", "pc"])
+            .help("Sets analysis model type")
+            .takes_value(true),
+        Arg::with_name("compatibility")
+            .long("compat")
+            .value_name("TOOL")
+            .possible_values(&["none", "spectector"])
+            .help("Matches the observation semantics of another tool (overrides check, model and observation type)")
+            .takes_value(true),
+        Arg::with_name("predictor_strategy")
+            .short("p")
+            .long("predictor")
+            .value_name("STRATEGY")
+            .possible_values(&["invert", "choose"])
+            .help("Sets predictor strategy")
+            .takes_value(true),
+        Arg::with_name("solver")
+            .long("solver")
+            .value_name("SOLVER")
+            .possible_values(&["z3", "cvc4", "yices2"])
+            .help("Sets solver")
+            .takes_value(true),
+        Arg::with_name("program_entry")
+            .long("entry")
+            .value_name("NAME|ADDRESS")
+            .help("Sets name/address of the program entry function")
+            .takes_value(true),
+        Arg::with_name("unwind")
+            .short("k")
+            .long("unwind")
+            .value_name("k")
+            .help("Unwinds loops k times")
+            .validator(is_positive_number)
+            .takes_value(true),
+        Arg::with_name("max_unwind")
+            .long("unwind-max")
+            .value_name("K")
+            .help("Unwinds loops incrementally (starting at k) until no unwinding assertion is violated, at most K times")
+            .validator(is_positive_number)
+            .takes_value(true),
+        Arg::with_name("unwinding_guard")
+            .long("unwinding-guard")
+            .value_name("GUARD")
+            .possible_values(&["assumption", "assertion"])
+            .help("Sets unwinding guard")
+            .takes_value(true),
+        Arg::with_name("allow_irreducible")
+            .long("allow-irreducible")
+            .help("Unwinds irreducible loops which can't be split instead of failing (unsound)"),
+        Arg::with_name("recursion_limit")
+            .short("r")
+            .long("recursion")
+            .value_name("LIMIT")
+            .help("Inlines recursive functions at most LIMIT times")
+            .validator(is_positive_number)
+            .takes_value(true),
+        Arg::with_name("speculation_window")
+            .short("s")
+            .long("spec-win")
+            .value_name("WINDOW")
+            .help("Sets maximum length of the speculation window")
+            .validator(is_positive_number)
+            .takes_value(true),
+        Arg::with_name("overrides")
+            .long("set")
+            .value_name("PATH=VALUE")
+            .help("Overrides the environment field given by its dotted path, e.g. analysis.spectre_stl=true (applied last)")
+            .validator(is_override)
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name("debug")
+            .short("d")
+            .long("debug")
+            .help("Enables debug mode"),
+        Arg::with_name("debug_dir")
+            .long("debug-dir")
+            .value_name("DIR")
+            .help("Enables debug mode and writes the artifacts of all stages into a timestamped directory within DIR")
+            .takes_value(true),
+        Arg::with_name("no_cache")
+            .long("no-cache")
+            .help("Lifts the binary again instead of reusing the cached program"),
+        Arg::with_name("show_environment")
+            .long("show-env")
+            .help("Prints the environment (annotated with the source of each value) to console"),
+        Arg::with_name("deny_warnings")
+            .long("deny-warnings")
+            .help("Aborts the analysis if any warning is reported"),
+        Arg::with_name("diagnostics_file")
+            .long("diagnostics")
+            .value_name("FILE")
+            .help("Prints diagnostics into file (JSON)")
+            .takes_value(true),
+    ]
+}
+
+/// Arguments for rendering program graphs.
+fn graph_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("cfg_file")
+            .long("cfg")
+            .value_name("FILE")
+            .help("Prints control-flow graph into file (DOT)")
+            .takes_value(true),
+        Arg::with_name("transient_cfg_file")
+            .long("trans-cfg")
+            .value_name("FILE")
+            .help("Prints CFG (with transient behavior) into file (DOT)")
+            .takes_value(true),
+        Arg::with_name("trans_only_cfg_file")
+            .long("trans-only-cfg")
+            .value_name("FILE")
+            .help("Prints the transient behavior modeled for a single speculating instruction (before insertion) into file (DOT)")
+            .requires("trans_only_address")
+            .takes_value(true),
+        Arg::with_name("trans_only_address")
+            .long("trans-only-at")
+            .value_name("ADDRESS")
+            .help("Sets address of the speculating instruction (branch or store) printed by --trans-only-cfg")
+            .validator(is_address)
+            .takes_value(true),
+        Arg::with_name("call_graph_file")
+            .long("call-graph")
+            .value_name("FILE")
+            .help("Prints call graph into file (DOT)")
+            .takes_value(true),
+        Arg::with_name("loop_tree_file")
+            .long("loop-tree")
+            .value_name("FILE")
+            .help("Prints loop tree into file (DOT)")
+            .takes_value(true),
+        Arg::with_name("hir_file")
+            .long("hir")
+            .value_name("FILE")
+            .help("Prints transformed HIR program into file (textual HIR, can be used as input)")
+            .takes_value(true),
+        Arg::with_name("mir_file")
+            .long("mir")
+            .value_name("FILE")
+            .help("Prints MIR program into file (DOT)")
+            .takes_value(true),
+    ]
+}
+
+/// Arguments for rendering counterexamples.
+fn cex_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("cex_file")
+            .long("cex")
+            .value_name("FILE")
+            .help("Prints counterexample into file (DOT)")
+            .default_value("cex.dot")
+            .takes_value(true),
+        Arg::with_name("cex_diff")
+            .long("cex-diff")
+            .help("Shows only state differing between compositions in counterexample"),
+        Arg::with_name("cex_format")
+            .long("cex-format")
+            .value_name("FORMAT")
+            .help("Format of the counterexample: dot (graph), text (executed instructions and their effects in order)")
+            .possible_values(&["dot", "text"])
+            .default_value("dot")
+            .takes_value(true),
+        Arg::with_name("cex_gdb_file")
+            .long("cex-gdb")
+            .value_name("FILE")
+            .help("Prints GDB script reproducing the counterexample into file")
+            .takes_value(true),
+        Arg::with_name("report_file")
+            .long("report")
+            .value_name("FILE")
+            .help("Prints leak report into file (JSON)")
+            .takes_value(true),
+    ]
+}
+
+/// Arguments of the check command (in addition to the environment, graph and counterexample arguments).
+fn check_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("sweep_speculation_windows")
+            .long("sweep-spec-win")
+            .value_name("WINDOWS")
+            .help("Checks each of the (comma-separated) speculation windows within a single solver session")
+            .validator(is_positive_number)
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true),
+        Arg::with_name("min_speculation_window")
+            .long("min-spec-win")
+            .help("Searches the smallest speculation window (up to the given one) for which a leak exists within a single solver session")
+            .conflicts_with("sweep_speculation_windows"),
+        Arg::with_name("skip_solving")
+            .long("skip-solving")
+            .help("Skips solving SMT formula"),
+        Arg::with_name("skip_cex")
+            .long("skip-cex")
+            .help("Skips generating counterexample"),
+        Arg::with_name("fail_on")
+            .long("fail-on")
+            .value_name("CLASSES")
+            .help("Fails (exit code 2) only on violations of the (comma-separated) classes, others are warnings")
+            .possible_values(ViolationClass::NAMES)
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true),
+        Arg::with_name("warn_only")
+            .long("warn-only")
+            .value_name("CLASSES")
+            .help("Reports violations of the (comma-separated) classes as warnings without failing")
+            .possible_values(ViolationClass::NAMES)
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true),
+        Arg::with_name("suggest_fences")
+            .long("suggest-fences")
+            .help("Suggests speculation barriers which make the program safe if a leak is found"),
+        Arg::with_name("fence_patch_file")
+            .long("fence-patch")
+            .value_name("FILE")
+            .help("Prints suggested fences into file (patched muasm program, objcopy options marking the fence locations for binaries)")
+            .requires("suggest_fences")
+            .takes_value(true),
+        Arg::with_name("intended_fences")
+            .long("with-fences")
+            .value_name("ADDRESSES")
+            .help("Checks the program with speculation barriers inserted in front of the instructions at the (comma-separated) addresses")
+            .validator(is_address)
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true),
+        Arg::with_name("suggest_masking")
+            .long("suggest-masking")
+            .help("Suggests load address masking which makes the program safe if a leak is found"),
+        Arg::with_name("cex_litmus_file")
+            .long("cex-litmus")
+            .value_name("FILE")
+            .help("Prints muasm litmus test reproducing the counterexample into file (plus expectation as YAML)")
+            .takes_value(true),
+        Arg::with_name("cex_store_file")
+            .long("cex-store")
+            .value_name("FILE")
+            .help("Stores counterexample into file (JSON), it can be rendered again via the cex command")
+            .takes_value(true),
+        Arg::with_name("compare_file")
+            .long("compare")
+            .value_name("FILE")
+            .help("Compares the leaks of the input program with the (hardened) program in file")
+            .takes_value(true),
+        Arg::with_name("product_file")
+            .long("product")
+            .value_name("FILE")
+            .help("Checks that the input program and the program in file produce the same observations for the same low inputs")
+            .conflicts_with("compare_file")
+            .takes_value(true),
+        Arg::with_name("compare_models")
+            .long("compare-models")
+            .help("Checks the program under the components and the pc model and compares their leaks")
+            .conflicts_with("compare_file"),
+        Arg::with_name("certificate_dir")
+            .long("certificate")
+            .value_name("DIR")
+            .help("Prints certificate (formula, solver version, proof, environment) into directory if the program is safe")
+            .takes_value(true),
+        Arg::with_name("lir_file")
+            .long("lir")
+            .value_name("FILE")
+            .help("Prints LIR program into file (plain text, grouped by block and instruction address)")
+            .takes_value(true),
+        Arg::with_name("lir_slices")
+            .long("lir-slices")
+            .requires("lir_file")
+            .help("Prints only the cone of influence of each assertion into the LIR file"),
+        Arg::with_name("smt_file")
+            .long("smt")
+            .value_name("FILE")
+            .help("Prints SMT-2 formula into file (plain text)")
+            .takes_value(true),
+        Arg::with_name("smt_split_dir")
+            .long("smt-split")
+            .value_name("DIR")
+            .help("Prints one SMT-2 formula per assertion (with its cone of influence) into directory")
+            .takes_value(true),
+        Arg::with_name("batch_dir")
+            .long("batch")
+            .value_name("DIR")
+            .help("Checks all muasm programs and ELF files in the given directory and prints a summary")
+            .conflicts_with_all(&["input_file", "inline_asm"])
+            .takes_value(true),
+        Arg::with_name("jobs")
+            .long("jobs")
+            .value_name("N")
+            .help("Number of programs checked in parallel in batch mode (default: number of CPUs)")
+            .requires("batch_dir")
+            .validator(is_positive_number)
+            .takes_value(true),
+        Arg::with_name("batch_report_file")
+            .long("batch-report")
+            .value_name("FILE")
+            .help("Prints the batch summary into file (JSON)")
+            .requires("batch_dir")
+            .takes_value(true),
+    ]
+}
+
+fn parse_arguments() -> Arguments {
+    use environment::*;
 
     let matches = app_from_crate!()
-        .arg(
-            Arg::with_name("environment_file")
-                .short("e")
-                .long("env")
-                .value_name("FILE")
-                .help("Sets environment file to use (arguments overwrite it)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("optimization_level")
-                .short("o")
-                .long("opt")
-                .value_name("LEVEL")
-                .possible_values(&["none", "basic", "full"])
-                .help("Sets optimization level")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("check")
-                .short("c")
-                .long("check")
-                .value_name("TYPE")
-                .possible_values(&["all", "normal", "transient"])
-                .help("Sets leak check type")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("observe")
-                .long("observe")
-                .value_name("OBSERVE")
-                .possible_values(&["sequential", "parallel", "full", "trace"])
-                .help("Sets observation type")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("model")
-                .long("model")
-                .value_name("MODEL")
-                .possible_values(&["components", "pc"])
-                .help("Sets analysis model type")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("predictor_strategy")
-                .short("p")
-                .long("predictor")
-                .value_name("STRATEGY")
-                .possible_values(&["invert", "choose"])
-                .help("Sets predictor strategy")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("solver")
-                .long("solver")
-                .value_name("SOLVER")
-                .possible_values(&["z3", "cvc4", "yices2"])
-                .help("Sets solver")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("program_entry")
-                .long("entry")
-                .value_name("NAME|ADDRESS")
-                .help("Sets name/address of the program entry function")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("unwind")
-                .short("k")
-                .long("unwind")
-                .value_name("k")
-                .help("Unwinds loops k times")
-                .validator(is_positive_number)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("unwinding_guard")
-                .long("unwinding-guard")
-                .value_name("GUARD")
-                .possible_values(&["assumption", "assertion"])
-                .help("Sets unwinding guard")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("recursion_limit")
-                .short("r")
-                .long("recursion")
-                .value_name("LIMIT")
-                .help("Inlines recursive functions at most LIMIT times")
-                .validator(is_positive_number)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("speculation_window")
-                .short("s")
-                .long("spec-win")
-                .value_name("WINDOW")
-                .help("Sets maximum length of the speculation window")
-                .validator(is_positive_number)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("debug")
-                .short("d")
-                .long("debug")
-                .help("Enables debug mode"),
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Checks the program for leaks")
+                .args(&input_args(&["inline_asm", "batch_dir"]))
+                .args(&environment_args())
+                .args(&graph_args())
+                .args(&cex_args())
+                .args(&check_args()),
         )
-        .arg(
-            Arg::with_name("skip_solving")
-                .long("skip-solving")
-                .help("Skips solving SMT formula"),
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Prints assembly info (functions, entry point) of the program")
+                .args(&input_args(&["inline_asm"])),
         )
-        .arg(
-            Arg::with_name("skip_cex")
-                .long("skip-cex")
-                .help("Skips generating counterexample"),
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("Renders program graphs without checking the program")
+                .args(&input_args(&["inline_asm"]))
+                .args(&environment_args())
+                .args(&graph_args()),
         )
-        .arg(
-            Arg::with_name("cex_file")
-                .long("cex")
-                .value_name("FILE")
-                .help("Prints counterexample into file (DOT)")
-                .default_value("cex.dot")
-                .takes_value(true),
+        .subcommand(
+            SubCommand::with_name("env")
+                .about("Environment file utilities")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Prints the environment used for the program (arguments applied)")
+                        .arg(
+                            Arg::with_name("input_file")
+                                .value_name("FILE")
+                                .help("Input file whose environment file should be used")
+                                .index(1),
+                        )
+                        .args(&environment_args()),
+                )
+                .subcommand(
+                    SubCommand::with_name("generate")
+                        .about("Prints the default environment (all options set)")
+                        .arg(
+                            Arg::with_name("output_file")
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Prints the environment into file instead of the console")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("init")
+                        .about("Writes a starter environment (entry, functions, policy templates) for the binary")
+                        .arg(
+                            Arg::with_name("binary_file")
+                                .value_name("FILE")
+                                .help("Binary to be inspected")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("output_file")
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Writes the environment into file [default: <FILE> with extension yaml]")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Overwrites an existing environment file"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Validates environment files (unknown keys, invalid values)")
+                        .arg(
+                            Arg::with_name("files")
+                                .value_name("FILE")
+                                .help("Environment files to be checked")
+                                .multiple(true)
+                                .required(true),
+                        ),
+                ),
         )
-        .arg(
-            Arg::with_name("cfg_file")
-                .long("cfg")
-                .value_name("FILE")
-                .help("Prints control-flow graph into file (DOT)")
-                .takes_value(true),
+        .subcommand(
+            SubCommand::with_name("cex")
+                .about("Renders a counterexample stored via 'check --cex-store'")
+                .arg(
+                    Arg::with_name("stored_cex_file")
+                        .value_name("FILE")
+                        .help("Stored counterexample (JSON)")
+                        .required(true)
+                        .index(1),
+                )
+                .args(&cex_args()),
         )
-        .arg(
-            Arg::with_name("transient_cfg_file")
-                .long("trans-cfg")
-                .value_name("FILE")
-                .help("Prints CFG (with transient behavior) into file (DOT)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("call_graph_file")
-                .long("call-graph")
-                .value_name("FILE")
-                .help("Prints call graph into file (DOT)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("loop_tree_file")
-                .long("loop-tree")
-                .value_name("FILE")
-                .help("Prints loop tree into file (DOT)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("mir_file")
-                .long("mir")
-                .value_name("FILE")
-                .help("Prints MIR program into file (DOT)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("lir_file")
-                .long("lir")
-                .value_name("FILE")
-                .help("Prints LIR program into file (plain text)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("smt_file")
-                .long("smt")
-                .value_name("FILE")
-                .help("Prints SMT-2 formula into file (plain text)")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("input_file")
-                .value_name("FILE")
-                .help("Input file to be checked")
-                .required(true)
-                .index(1),
-        )
-        .arg(
-            Arg::with_name("print_assembly_info")
-                .short("a")
-                .long("assembly-info")
-                .help("Prints assembly info and exits"),
-        )
-        .arg(
-            Arg::with_name("show_environment")
-                .long("show-env")
-                .help("Prints the environment to console"),
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Checks the installed solvers (version, supported features) and the lifter")
+                .arg(
+                    Arg::with_name("environment_file")
+                        .short("e")
+                        .long("env")
+                        .value_name("FILE")
+                        .help("Sets environment file whose solver should be checked")
+                        .takes_value(true),
+                ),
         )
         .get_matches();
 
+    let (command, matches) = match matches.subcommand() {
+        ("check", Some(matches)) => (Command::Check, matches),
+        ("info", Some(matches)) => (Command::Info, matches),
+        ("render", Some(matches)) => (Command::Render, matches),
+        ("env", Some(env)) => match env.subcommand() {
+            ("show", Some(matches)) => (Command::ShowEnvironment, matches),
+            ("generate", Some(matches)) => (
+                Command::GenerateEnvironment {
+                    output_file: matches.value_of("output_file").map(String::from),
+                },
+                matches,
+            ),
+            ("init", Some(matches)) => (
+                Command::InitEnvironment {
+                    binary_file: matches.value_of("binary_file").unwrap().to_owned(),
+                    output_file: matches.value_of("output_file").map(String::from),
+                    force: matches.is_present("force"),
+                },
+                matches,
+            ),
+            ("check", Some(matches)) => (
+                Command::CheckEnvironment {
+                    files: matches
+                        .values_of("files")
+                        .unwrap()
+                        .map(String::from)
+                        .collect(),
+                },
+                matches,
+            ),
+            _ => unreachable!("env subcommand is required"),
+        },
+        ("cex", Some(matches)) => (
+            Command::RenderCounterExample {
+                file: matches.value_of("stored_cex_file").unwrap().to_owned(),
+            },
+            matches,
+        ),
+        ("doctor", Some(matches)) => (Command::Doctor, matches),
+        _ => unreachable!("subcommand is required"),
+    };
+
     let parse_optimization_level = |level: &str| match level {
         "none" => OptimizationLevel::Disabled,
         "basic" => OptimizationLevel::Basic,
@@ -271,6 +676,8 @@ fn parse_arguments() -> Arguments {
         "all" => Check::AllLeaks,
         "normal" => Check::OnlyNormalExecutionLeaks,
         "transient" => Check::OnlyTransientExecutionLeaks,
+        "oob" => Check::SpeculativeMemorySafety,
+        "interference" => Check::SpeculativeInterference,
         _ => panic!("unknown check type"),
     };
 
@@ -294,6 +701,12 @@ fn parse_arguments() -> Arguments {
         _ => panic!("unknown model type"),
     };
 
+    let parse_compatibility = |compatibility: &str| match compatibility {
+        "none" => Compatibility::None,
+        "spectector" => Compatibility::Spectector,
+        _ => panic!("unknown compatibility mode"),
+    };
+
     let parse_solver = |solver: &str| match solver {
         "z3" => Solver::Z3,
         "cvc4" => Solver::CVC4,
@@ -307,7 +720,26 @@ fn parse_arguments() -> Arguments {
         _ => panic!("unknown unwinding guard"),
     };
 
+    // Programs given inline (or via stdin) are labeled by their origin instead of a file name
+    let (input_file, inline_asm) = match matches.value_of("input_file") {
+        Some("-") => {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .expect("failed to read muasm program from stdin");
+            ("<stdin>".to_owned(), Some(source))
+        }
+        Some(input_file) => (input_file.to_owned(), None),
+        None if matches.is_present("batch_dir") => ("<batch>".to_owned(), None),
+        None => match matches.value_of("inline_asm") {
+            Some(source) => ("<inline-asm>".to_owned(), Some(source.to_owned())),
+            // Commands which don't require a program, e.g. showing the environment
+            None => (String::new(), None),
+        },
+    };
+
     Arguments {
+        command,
         environment_file: matches.value_of("environment_file").map(String::from),
         optimization_level: matches
             .value_of("optimization_level")
@@ -319,61 +751,141 @@ fn parse_arguments() -> Arguments {
             .map(parse_predictory_strategy),
         observe: matches.value_of("observe").map(parse_observe),
         model: matches.value_of("model").map(parse_model),
+        compatibility: matches.value_of("compatibility").map(parse_compatibility),
         program_entry: matches.value_of("program_entry").map(String::from),
         unwind: matches
             .value_of("unwind")
             .map(|v| v.parse::<usize>().unwrap()),
+        max_unwind: matches
+            .value_of("max_unwind")
+            .map(|v| v.parse::<usize>().unwrap()),
         unwinding_guard: matches
             .value_of("unwinding_guard")
             .map(parse_unwinding_guard),
+        allow_irreducible: matches.is_present("allow_irreducible"),
+        overrides: matches
+            .values_of("overrides")
+            .map(|values| {
+                values
+                    .filter_map(|v| v.split_once('='))
+                    .map(|(path, value)| (path.to_owned(), value.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default(),
         recursion_limit: matches
             .value_of("recursion_limit")
             .map(|v| v.parse::<usize>().unwrap()),
         speculation_window: matches
             .value_of("speculation_window")
             .map(|v| v.parse::<usize>().unwrap()),
+        sweep_speculation_windows: matches
+            .values_of("sweep_speculation_windows")
+            .map(|values| values.map(|v| v.parse::<usize>().unwrap()).collect()),
+        min_speculation_window: matches.is_present("min_speculation_window"),
         debug: matches.is_present("debug"),
+        debug_dir: matches.value_of("debug_dir").map(String::from),
         skip_solving: matches.is_present("skip_solving"),
         skip_cex: matches.is_present("skip_cex"),
-        cex_file: matches.value_of("cex_file").map(String::from).unwrap(),
+        violation_policy: ViolationPolicy::new(
+            matches
+                .values_of("fail_on")
+                .map(|values| values.map(|v| v.parse().unwrap()).collect()),
+            matches
+                .values_of("warn_only")
+                .map(|values| values.map(|v| v.parse().unwrap()).collect())
+                .unwrap_or_default(),
+        ),
+        no_cache: matches.is_present("no_cache"),
+        cex_file: matches.value_of("cex_file").unwrap_or("cex.dot").to_owned(),
+        cex_diff: matches.is_present("cex_diff"),
+        cex_text: matches.value_of("cex_format") == Some("text"),
+        cex_gdb_file: matches.value_of("cex_gdb_file").map(String::from),
+        cex_litmus_file: matches.value_of("cex_litmus_file").map(String::from),
+        cex_store_file: matches.value_of("cex_store_file").map(String::from),
+        report_file: matches.value_of("report_file").map(String::from),
+        certificate_dir: matches.value_of("certificate_dir").map(String::from),
+        suggest_fences: matches.is_present("suggest_fences"),
+        fence_patch_file: matches.value_of("fence_patch_file").map(String::from),
+        intended_fences: matches
+            .values_of("intended_fences")
+            .map(|values| values.map(|v| parse_address(v).unwrap()).collect())
+            .unwrap_or_default(),
+        suggest_masking: matches.is_present("suggest_masking"),
         cfg_file: matches.value_of("cfg_file").map(String::from),
         transient_cfg_file: matches.value_of("transient_cfg_file").map(String::from),
+        trans_only_cfg_file: matches.value_of("trans_only_cfg_file").map(String::from),
+        trans_only_address: matches
+            .value_of("trans_only_address")
+            .map(|v| parse_address(v).unwrap()),
         call_graph_file: matches.value_of("call_graph_file").map(String::from),
         loop_tree_file: matches.value_of("loop_tree_file").map(String::from),
+        hir_file: matches.value_of("hir_file").map(String::from),
         mir_file: matches.value_of("mir_file").map(String::from),
         lir_file: matches.value_of("lir_file").map(String::from),
+        lir_slices: matches.is_present("lir_slices"),
         smt_file: matches.value_of("smt_file").map(String::from),
-        input_file: matches.value_of("input_file").map(String::from).unwrap(),
-        print_assembly_info: matches.is_present("print_assembly_info"),
+        smt_split_dir: matches.value_of("smt_split_dir").map(String::from),
+        input_file,
+        inline_asm,
+        batch_dir: matches.value_of("batch_dir").map(String::from),
+        jobs: matches
+            .value_of("jobs")
+            .map(|v| v.parse::<usize>().unwrap())
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+            }),
+        batch_report_file: matches.value_of("batch_report_file").map(String::from),
+        compare_file: matches.value_of("compare_file").map(String::from),
+        product_file: matches.value_of("product_file").map(String::from),
+        compare_models: matches.is_present("compare_models"),
         show_environment: matches.is_present("show_environment"),
+        deny_warnings: matches.is_present("deny_warnings"),
+        diagnostics_file: matches.value_of("diagnostics_file").map(String::from),
     }
 }
 
 fn build_environment(arguments: &Arguments) -> Result<environment::Environment> {
+    build_environment_with_sources(arguments).map(|(env, _)| env)
+}
+
+/// Builds the environment and tracks where the value of each field comes from.
+fn build_environment_with_sources(
+    arguments: &Arguments,
+) -> Result<(environment::Environment, environment::EnvironmentSources)> {
     use environment::*;
 
+    let mut sources = EnvironmentSources::new();
+
     let mut env = if let Some(file_path) = &arguments.environment_file {
         // Load given environment file
-        Environment::from_file(Path::new(file_path))?
+        let env = Environment::from_file(Path::new(file_path))?;
+        sources.record_file(Path::new(file_path))?;
+        env
+    } else if arguments.inline_asm.is_some() || arguments.input_file.is_empty() {
+        // There is no input file next to which an environment file could be found
+        println!("Using default environment.");
+        Environment::default()
     } else {
         // Try to find a environment file for the current input and use it if it exists
         let input_file = Path::new(&arguments.input_file);
         let env_file = input_file.with_extension("yaml");
-        match Environment::from_file(&env_file) {
-            Ok(env) => {
-                println!(
-                    "Using environment defined in '{}'.",
-                    style(&env_file.to_str().unwrap()).yellow()
-                );
-                env
-            }
-            Err(_) => {
-                println!("Using default environment.");
-                Environment::default()
-            }
+        if env_file.is_file() {
+            // An invalid environment file is reported instead of falling back to the default
+            let env = Environment::from_file(&env_file)?;
+            sources.record_file(&env_file)?;
+            println!(
+                "Using environment defined in '{}'.",
+                style(&env_file.to_str().unwrap()).yellow()
+            );
+            env
+        } else {
+            println!("Using default environment.");
+            Environment::default()
         }
     };
 
+    sources.checkpoint(&env);
+
     if let Some(level) = arguments.optimization_level {
         env.optimization_level = level;
     }
@@ -390,8 +902,12 @@ fn build_environment(arguments: &Arguments) -> Result<environment::Environment>
         env.analysis.observe = observe;
     }
 
-    if let Some(model) = arguments.model {
-        env.analysis.model = model;
+    if let Some(model) = &arguments.model {
+        env.analysis.model = model.clone();
+    }
+
+    if let Some(compatibility) = arguments.compatibility {
+        env.analysis.compatibility = compatibility;
     }
 
     if let Some(solver) = arguments.solver {
@@ -406,6 +922,10 @@ fn build_environment(arguments: &Arguments) -> Result<environment::Environment>
         env.analysis.unwinding_guard = unwinding_guard;
     }
 
+    if arguments.allow_irreducible {
+        env.analysis.allow_irreducible = true;
+    }
+
     if let Some(recursion_limit) = arguments.recursion_limit {
         env.analysis.recursion_limit = recursion_limit;
     }
@@ -414,11 +934,24 @@ fn build_environment(arguments: &Arguments) -> Result<environment::Environment>
         env.architecture.speculation_window = speculation_window;
     }
 
+    if let Some(windows) = &arguments.sweep_speculation_windows {
+        // Encode once with the largest window, smaller ones are selected by the solver session
+        if let Some(&max_window) = windows.iter().max() {
+            env.architecture.speculation_window = max_window;
+        }
+        env.architecture.parametric_speculation_window = true;
+    }
+
+    if arguments.min_speculation_window {
+        // Encode once with the given window, smaller ones are selected by the solver session
+        env.architecture.parametric_speculation_window = true;
+    }
+
     if let Some(entry) = &arguments.program_entry {
         env.analysis.program_entry = Some(entry.clone());
     }
 
-    if arguments.debug {
+    if arguments.debug || arguments.debug_dir.is_some() {
         env.debug = true;
     }
 
@@ -426,116 +959,1197 @@ fn build_environment(arguments: &Arguments) -> Result<environment::Environment>
         env.generate_counterexample = false;
     }
 
-    Ok(env)
+    if arguments.no_cache {
+        env.lift_cache = false;
+    }
+
+    if arguments.certificate_dir.is_some() {
+        env.proof_generation = true;
+    }
+
+    // Applied after all arguments, as compatibility mode overrides the analysis settings
+    env.apply_compatibility();
+
+    sources.record_changes(&env, Source::Argument);
+
+    for (path, value) in &arguments.overrides {
+        env.set(path, value)?;
+        sources.record(path, Source::Override);
+    }
+
+    Ok((env, sources))
+}
+
+/// Loads the program (from `inline_asm` if given, otherwise from `input_file`)
+/// and resolves the environment settings which depend on it.
+fn load_program(
+    env: &mut environment::Environment,
+    input_file: &str,
+    inline_asm: Option<&str>,
+) -> Result<hir::Program> {
+    let program = match inline_asm {
+        Some(source) => pipeline::LoadMuasm::new().run(env, source)?,
+        None => pipeline::Load::new().run(env, Path::new(input_file))?,
+    };
+    pipeline::resolve_environment(env, &program)?;
+    Ok(program)
 }
 
+/// Returns true if the input file is an (already transformed) program in the textual HIR format.
+fn is_hir_input(arguments: &Arguments) -> bool {
+    arguments.inline_asm.is_none()
+        && Path::new(&arguments.input_file)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            == Some("hir")
+}
+
+/// HIR transformation stage which reports its progress (and dumps each step in debug mode).
+///
+/// The steps are dumped into `debug_dir` if given, otherwise into the working directory.
 fn hir_transformations(
     env: &environment::Environment,
-    program: &mut hir::InlinedProgram,
+    debug_dir: Option<DebugDir>,
+) -> pipeline::HirTransform {
+    let debug = env.debug;
+    pipeline::HirTransform::new().with_observer(move |step, steps, transformation, program| {
+        println!(
+            "-> {} {}",
+            style(format!("[{}/{}]", step, steps)).bold().dim(),
+            transformation.description(),
+        );
+
+        match &debug_dir {
+            Some(dir) => {
+                let file_name = DebugDir::hir_file_name(step, transformation.name());
+                program
+                    .control_flow_graph()
+                    .render_to_file(&dir.file(&file_name))?;
+                dir.update_index()?;
+            }
+            None if debug => {
+                program
+                    .control_flow_graph()
+                    .render_to_file(Path::new(&format!("dbg_hir_{}.dot", transformation.name())))?;
+            }
+            None => {}
+        }
+
+        Ok(())
+    })
+}
+
+/// Checks `program` for leaks and prints whether it is safe.
+fn check_for_leaks(
+    env: environment::Environment,
+    program: hir::InlinedProgram,
+    symbols: hir::Program,
+) -> Result<comparison::CheckedProgram> {
+    let checked = comparison::CheckedProgram::check(env, program, symbols)?;
+    if checked.is_safe() {
+        println!("{}", "Program is safe.".bold().green());
+    } else {
+        println!("{}", "Leak detected!".bold().red());
+    }
+    Ok(checked)
+}
+
+/// Checks the original (input) and the hardened program with the same environment
+/// and reports which leaks of the original program are gone in the hardened program.
+///
+/// Leaks which are only shown for one of both programs are re-checked in the other program.
+fn compare_programs(arguments: &Arguments, hardened_file: &str) -> Result<()> {
+    let bullet_point = style(">>").bold().dim();
+
+    let mut checked = Vec::new();
+    let programs = [
+        (
+            arguments.input_file.as_str(),
+            arguments.inline_asm.as_deref(),
+        ),
+        (hardened_file, None),
+    ];
+    for (input_file, inline_asm) in programs {
+        // Symbols are resolved per program, therefore each program gets its own environment
+        let mut env = build_environment(arguments)?;
+
+        println!("{} Check program '{}'", bullet_point, input_file.yellow());
+        let program = load_program(&mut env, input_file, inline_asm)?;
+        let hir_program = pipeline::Inline::new().run(&env, &program)?;
+        checked.push(check_for_leaks(env, hir_program, program)?);
+    }
+
+    println!("{} Re-check leaks shown for one program only", bullet_point);
+    let comparison = comparison::compare(&checked[0], &checked[1])?;
+    println!("{}", "Leak comparison:".bold());
+    for line in comparison.to_string().lines() {
+        println!("   {}", line);
+    }
+
+    if !checked[1].is_safe() {
+        process::exit(2);
+    }
+
+    println!("{}", "Hardened program is free of leaks.".bold().green());
+    Ok(())
+}
+
+/// Checks that the input program and the other program are observationally equivalent,
+/// by checking their product program with shared low inputs (e.g. original vs. compiler-hardened function).
+fn check_product(arguments: &Arguments, other_file: &str) -> Result<()> {
+    let bullet_point = style(">>").bold().dim();
+
+    let env = build_environment(arguments)?;
+    let mut programs = Vec::new();
+    let inputs = [
+        (
+            arguments.input_file.as_str(),
+            arguments.inline_asm.as_deref(),
+        ),
+        (other_file, None),
+    ];
+    for (input_file, inline_asm) in inputs {
+        // Symbols are resolved per program, therefore each program gets its own environment
+        let mut program_env = env.clone();
+
+        println!("{} Load program '{}'", bullet_point, input_file.yellow());
+        let program = load_program(&mut program_env, input_file, inline_asm)?;
+        let hir_program = pipeline::Inline::new().run(&program_env, &program)?;
+        programs.push(pipeline::HirTransform::new().run(&program_env, hir_program)?);
+    }
+
+    println!("{} Check product program", bullet_point);
+    match pipeline::verify_product(&env, &programs)? {
+        CheckResult::AssertionsHold => {
+            println!(
+                "{}",
+                "Programs are observationally equivalent.".bold().green()
+            );
+            Ok(())
+        }
+        CheckResult::AssertionViolated { .. } => {
+            println!("{}", "Programs are distinguishable!".bold().red());
+            process::exit(2);
+        }
+    }
+}
+
+/// Checks each function with a contract on its own (modular checking).
+///
+/// Calls of functions with a contract are summarized by their postcondition,
+/// all other callees are inlined as usual.
+/// The verdict of each function is added to the report file as soon as the function is checked.
+fn check_functions_modularly(
+    arguments: &Arguments,
+    mut env: environment::Environment,
 ) -> Result<()> {
-    let transformations = hir::transformation::create_transformations(env)?;
+    let bullet_point = style(">>").bold().dim();
+
+    if env.analysis.contracts.is_empty() {
+        return Err("Modular checking requires function contracts (`analysis.contracts`)".into());
+    }
 
-    for (idx, transformation) in transformations.iter().enumerate() {
+    println!(
+        "{} Load program '{}'",
+        bullet_point,
+        arguments.input_file.yellow()
+    );
+    let mut program = load_program(
+        &mut env,
+        &arguments.input_file,
+        arguments.inline_asm.as_deref(),
+    )?;
+
+    let registers = env.policy.registers.clone();
+    let contracts = env.analysis.contracts.clone();
+    let mut leaky_functions = Vec::new();
+    let mut verdicts = Vec::new();
+    for (name, contract) in &contracts {
+        println!("{} Check function {}", bullet_point, name.yellow());
+        program.set_entry(hir::ProgramEntry::Name(name.clone()))?;
+
+        // Precondition: only the required (and explicitly low) registers are low
+        env.policy.registers = registers.clone();
+        env.policy.registers.default_level = environment::SecurityLevel::High;
+        env.policy
+            .registers
+            .low
+            .extend(contract.requires_low.iter().cloned());
+
+        let hir_program = pipeline::Inline::new().run(&env, &program)?;
+        let report = comparison::leak_report(&env, &hir_program, &program)?;
+        match &report {
+            None => println!("{}", "Function is safe.".bold().green()),
+            Some(report) => {
+                println!("{}", "Leak detected!".bold().red());
+                for leak in report.leaks() {
+                    println!("   {}", leak);
+                }
+                leaky_functions.push(name);
+            }
+        }
+
+        if let Some(path) = &arguments.report_file {
+            verdicts.push(serde_json::json!({
+                "function": name,
+                "verdict": if report.is_some() { "leak" } else { "safe" },
+                "leaks": report.map(|report| report.leaks().to_vec()).unwrap_or_default(),
+            }));
+            serde_json::to_string_pretty(&verdicts)?.dump_to_file(Path::new(path))?;
+        }
+    }
+
+    if !leaky_functions.is_empty() {
         println!(
-            "-> {} {}",
-            style(format!("[{}/{}]", idx + 1, transformations.len()))
+            "{} {} of {} function(s) leak, re-run with `analysis.program_entry` for a counterexample",
+            bullet_point,
+            leaky_functions.len(),
+            contracts.len()
+        );
+        process::exit(2);
+    }
+
+    Ok(())
+}
+
+/// Checks the program once under the components and once under the program counter model
+/// and reports which leaks are only found by one of both observers.
+///
+/// Leaks which are only shown under one model are re-checked under the other model.
+fn compare_models(arguments: &Arguments) -> Result<()> {
+    let bullet_point = style(">>").bold().dim();
+
+    let mut env = build_environment(arguments)?;
+    let program = load_program(
+        &mut env,
+        &arguments.input_file,
+        arguments.inline_asm.as_deref(),
+    )?;
+    let hir_program = pipeline::Inline::new().run(&env, &program)?;
+
+    let mut checked = Vec::new();
+    for (model, name) in [
+        (environment::Model::Components, "components"),
+        (environment::Model::ProgramCounter, "pc"),
+    ] {
+        println!(
+            "{} Check program with {} model",
+            bullet_point,
+            name.yellow()
+        );
+        env.analysis.model = model;
+        checked.push(check_for_leaks(
+            env.clone(),
+            hir_program.clone(),
+            program.clone(),
+        )?);
+    }
+
+    // Leaks of the components model which are gone under the pc model are the "fixed" ones
+    println!("{} Re-check leaks shown under one model only", bullet_point);
+    let comparison = comparison::compare(&checked[0], &checked[1])?;
+    println!("{}", "Model comparison:".bold());
+    for leak in comparison.remaining() {
+        println!("   both:            {}", leak);
+    }
+    for leak in comparison.fixed() {
+        println!("   components only: {}", leak);
+    }
+    for leak in comparison.introduced() {
+        println!("   pc only:         {}", leak);
+    }
+    for leak in comparison.not_shown() {
+        println!("   not shown:       {}", leak);
+    }
+
+    if comparison.fixed().is_empty()
+        && comparison.introduced().is_empty()
+        && comparison.not_shown().is_empty()
+    {
+        println!(
+            "{}",
+            "Both models agree on the leaks of the program."
                 .bold()
-                .dim(),
-            transformation.description(),
+                .green()
         );
+    }
 
-        transformation.transform(program)?;
+    if checked.iter().any(|checked| !checked.is_safe()) {
+        process::exit(2);
+    }
 
-        if env.debug {
-            program
-                .control_flow_graph()
-                .render_to_file(Path::new(&format!("dbg_hir_{}.dot", transformation.name())))?;
+    Ok(())
+}
+
+/// Prints how much of the speculation window each transient execution of the counterexample used.
+fn print_speculation_window_usage(
+    env: &environment::Environment,
+    program: &hir::InlinedProgram,
+    counter_example: &cex::CounterExample,
+) {
+    let window = env.architecture.speculation_window;
+    let mut is_first = true;
+    for composition in cex::Composition::all(program.self_compositions()) {
+        for usage in counter_example.speculation_window_usage(composition) {
+            if is_first {
+                println!("{}", "Speculation window usage:".bold());
+                is_first = false;
+            }
+            let location = usage
+                .location()
+                .map_or_else(|| "unknown address".to_owned(), |a| format!("0x{:X}", a));
+            println!(
+                "   {}: speculation at {} used {} of {} instructions",
+                composition,
+                location,
+                usage.used(),
+                window
+            );
         }
     }
+}
 
+/// Prints the cache, BTB and PHT contents at the end of each composition (if changed by the counterexample).
+fn print_component_state(program: &hir::InlinedProgram, counter_example: &cex::CounterExample) {
+    let mut is_first = true;
+    for composition in cex::Composition::all(program.self_compositions()) {
+        if let Some(components) = counter_example
+            .components(composition)
+            .filter(|components| !components.is_empty())
+        {
+            if is_first {
+                println!("{}", "Final component state:".bold());
+                is_first = false;
+            }
+            println!("   {}: {}", composition, components);
+        }
+    }
+}
+
+/// Prints the largest part of the speculation window which is reachable by any transient execution.
+fn print_max_speculation_window(
+    env: &environment::Environment,
+    program: &hir::InlinedProgram,
+) -> Result<()> {
+    let window = env.architecture.speculation_window;
+    let reachable = hir::analysis::max_transient_path_length(program.control_flow_graph())?;
+    println!(
+        "   Max. reachable speculation window: {} of {} instructions",
+        reachable, window
+    );
+    if reachable < window {
+        println!("   (larger speculation windows don't change the result)");
+    }
     Ok(())
 }
 
-fn spec_bmc(arguments: &Arguments) -> Result<()> {
-    if arguments.print_assembly_info {
-        print_assembly_info(arguments)?;
-        return Ok(());
+/// Prints the instructions at the given addresses which differ between both programs as diff.
+fn print_patch_diff(
+    original: &hir::InlinedProgram,
+    patched: &hir::InlinedProgram,
+    addresses: &BTreeSet<u64>,
+) {
+    let instructions_at = |program: &hir::InlinedProgram, address: u64| -> BTreeSet<String> {
+        program
+            .control_flow_graph()
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions())
+            .filter(|inst| inst.address() == Some(address) && !inst.labels().is_pseudo())
+            .map(|inst| inst.to_string())
+            .collect()
+    };
+
+    for &address in addresses {
+        let before = instructions_at(original, address);
+        let after = instructions_at(patched, address);
+        println!("@@ 0x{:X} @@", address);
+        for line in before.difference(&after) {
+            println!("{}", format!("- {}", line).red());
+        }
+        for line in after.difference(&before) {
+            println!("{}", format!("+ {}", line).green());
+        }
     }
+}
 
-    check_program(arguments)
+/// Returns false if `result` only violates assertions whose violation is a warning only,
+/// i.e. failing violations may still be hidden behind warnings.
+fn is_conclusive(arguments: &Arguments, program: &lir::Program, result: &CheckResult) -> bool {
+    match result {
+        CheckResult::AssertionViolated { model } => {
+            pipeline::violated_assertions(program, model.as_ref())
+                .into_iter()
+                .any(|provenance| arguments.violation_policy.is_failing(provenance))
+        }
+        CheckResult::AssertionsHold => true,
+    }
 }
 
-fn print_assembly_info(arguments: &Arguments) -> Result<()> {
-    let input_file = Path::new(&arguments.input_file);
+/// Checks again with all assertions assumed to hold whose violation is a warning only,
+/// such that failing violations aren't hidden behind warnings.
+fn check_failing_assertions(
+    arguments: &Arguments,
+    solver: &mut dyn Solver,
+    program: &lir::Program,
+) -> Result<CheckResult> {
+    solver.push()?;
+    pipeline::assume_warnings_hold(solver, program, |provenance| {
+        arguments.violation_policy.is_failing(provenance)
+    })?;
+    solver.check_assertions()
+}
+
+/// Prints all diagnostics reported so far and writes them to the diagnostics file (if requested).
+///
+/// Fails if warnings have been reported and `--deny-warnings` is set.
+fn emit_diagnostics(
+    arguments: &Arguments,
+    emitted: &mut Vec<diagnostics::Diagnostic>,
+) -> Result<()> {
+    let reported = diagnostics::take();
+
+    for diagnostic in &reported {
+        if diagnostic.is_warning() {
+            println!("{}", style(diagnostic).yellow());
+        } else {
+            println!("{}", style(diagnostic).dim());
+        }
+    }
+
+    emitted.extend(reported);
+
+    if let Some(path) = &arguments.diagnostics_file {
+        diagnostics::to_json(emitted)?.dump_to_file(Path::new(path))?;
+    }
+
+    if arguments.deny_warnings {
+        diagnostics::deny_warnings(emitted)?;
+    }
+
+    Ok(())
+}
+
+/// Checks if the speculation window is searched within a single solver session
+/// (`--sweep-spec-win`, `--min-spec-win`).
+fn searches_speculation_windows(arguments: &Arguments) -> bool {
+    arguments.sweep_speculation_windows.is_some() || arguments.min_speculation_window
+}
+
+/// Checks the program for each of the given speculation windows and prints the result of each check.
+fn sweep_speculation_windows(
+    arguments: &Arguments,
+    solver: &mut dyn Solver,
+    program: &lir::Program,
+    windows: &[usize],
+) -> Result<()> {
+    pipeline::sweep_speculation_windows(
+        solver,
+        program,
+        windows,
+        |provenance| arguments.violation_policy.is_failing(provenance),
+        |window, leaks| {
+            let result = if leaks {
+                "Leak detected!".bold().red()
+            } else {
+                "Program is safe.".bold().green()
+            };
+            println!("   Speculation window {}: {}", window, result);
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Writes one standalone SMT-2 file per assertion into `dir`,
+/// each containing only the cone of influence of the assertion.
+fn split_proof_obligations(
+    env: &environment::Environment,
+    program: &lir::Program,
+    dir: &Path,
+) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+
+    for (index, slice) in program.assertion_slices().iter().enumerate() {
+        let path = dir.join(format!("assertion{}.smt2", index));
+        {
+            // A fresh solver per obligation, the file is complete once the solver is dropped
+            let mut solver = create_solver(env)?;
+            solver.dump_formula_to_file(&path)?;
+            solver.encode_program(slice)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
+        writeln!(file, "(check-sat)")?;
+    }
+
+    Ok(())
+}
 
-    let input_file_path = Path::new(input_file);
-    let loader = loader::loader_for_file(input_file_path).ok_or("No compatible loader found")?;
+fn spec_bmc(arguments: &Arguments) -> Result<()> {
+    match &arguments.command {
+        Command::Check => {
+            if let Some(dir) = &arguments.batch_dir {
+                let exit_code = batch::run_batch(
+                    Path::new(dir),
+                    arguments.jobs,
+                    arguments.batch_report_file.as_deref().map(Path::new),
+                )?;
+                process::exit(exit_code);
+            }
+
+            if let Some(hardened_file) = &arguments.compare_file {
+                return compare_programs(arguments, hardened_file);
+            }
+
+            if let Some(other_file) = &arguments.product_file {
+                return check_product(arguments, other_file);
+            }
+
+            if arguments.compare_models {
+                return compare_models(arguments);
+            }
 
+            check_program(arguments)
+        }
+        Command::Info => print_assembly_info(arguments),
+        Command::Render => render_program(arguments),
+        Command::ShowEnvironment => {
+            let (env, sources) = build_environment_with_sources(arguments)?;
+            println!("{}", env.to_annotated_string(&sources));
+            Ok(())
+        }
+        Command::GenerateEnvironment { output_file } => match output_file {
+            Some(path) => environment::Environment::default().dump_to_file(Path::new(path)),
+            None => {
+                println!("{}", environment::Environment::default());
+                Ok(())
+            }
+        },
+        Command::InitEnvironment {
+            binary_file,
+            output_file,
+            force,
+        } => init_environment(binary_file, output_file.as_deref(), *force),
+        Command::CheckEnvironment { files } => check_environment_files(files),
+        Command::RenderCounterExample { file } => render_stored_counter_example(arguments, file),
+        Command::Doctor => {
+            let env = build_environment(arguments)?;
+            doctor::run(&env)
+        }
+    }
+}
+
+/// Writes the starter environment of the binary into `output_file`
+/// (by default next to the binary, such that it is loaded automatically).
+fn init_environment(binary_file: &str, output_file: Option<&str>, force: bool) -> Result<()> {
+    let binary_path = Path::new(binary_file);
+    let output_path = output_file
+        .map(Path::new)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| binary_path.with_extension("yaml"));
+
+    if output_path.exists() && !force {
+        return Err(format!(
+            "Environment file '{}' already exists (use --force to overwrite it)",
+            output_path.display()
+        )
+        .into());
+    }
+
+    let loader = loader::loader_for_file(
+        binary_path,
+        environment::UnsupportedInstructionPolicy::default(),
+        false,
+        None,
+    )
+    .ok_or_else(|| Error::NoCompatibleLoader(binary_path.to_path_buf()))?;
     let info = loader.assembly_info()?;
-    println!("{}", info);
+
+    env_init::starter_environment(binary_file, &info).dump_to_file(&output_path)?;
+    println!(
+        "Starter environment written to '{}'.",
+        style(output_path.display()).yellow()
+    );
 
     Ok(())
 }
 
-fn parse_program_entry(s: &str) -> hir::ProgramEntry {
-    if s.starts_with("0x") {
-        let s = s.trim_start_matches("0x");
-        if let Ok(addr) = u64::from_str_radix(s, 16) {
-            return hir::ProgramEntry::Address(addr);
+/// Validates the given environment files and reports all invalid ones.
+fn check_environment_files(files: &[String]) -> Result<()> {
+    let mut invalid = 0;
+    for file in files {
+        match environment::Environment::from_file(Path::new(file)) {
+            Ok(_) => println!("{} {}", "ok".green(), file),
+            Err(e) => {
+                println!("{} {}", "error".red(), e);
+                invalid += 1;
+            }
         }
     }
 
-    hir::ProgramEntry::Name(s.to_owned())
+    if invalid > 0 {
+        return Err(format!(
+            "{} of {} environment files are invalid",
+            invalid,
+            files.len()
+        )
+        .into());
+    }
+
+    Ok(())
 }
 
-fn check_program(arguments: &Arguments) -> Result<()> {
-    let input_file = &arguments.input_file;
+/// Writes the litmus harness into `path` and its expectation into `path` with extension `yaml`,
+/// such that the harness can be added to the regression tests.
+fn write_litmus_harness(
+    env: &environment::Environment,
+    arguments: &Arguments,
+    counter_example: &cex::CounterExample,
+    path: &str,
+) -> Result<()> {
+    let source = match &arguments.inline_asm {
+        Some(source) => source.clone(),
+        None => {
+            let input_file_path = Path::new(&arguments.input_file);
+            if input_file_path.extension().and_then(|ext| ext.to_str()) != Some("muasm") {
+                println!(
+                    "{}",
+                    "Litmus harness is only available for muasm programs".yellow()
+                );
+                return Ok(());
+            }
+            std::fs::read_to_string(input_file_path)?
+        }
+    };
+    let harness = cex::LitmusHarness::new(counter_example, &env.policy);
 
-    let env = build_environment(arguments)?;
+    let harness_path = Path::new(path);
+    harness.program(&source).dump_to_file(harness_path)?;
+    harness
+        .expectation(env)?
+        .dump_to_file(&harness_path.with_extension("yaml"))?;
 
-    if arguments.show_environment || env.debug {
-        println!("{}:\n{}\n---", "Environment".bold(), style(&env).cyan());
+    Ok(())
+}
+
+/// Writes the suggested fences into file: the patched program for muasm programs,
+/// and objcopy options adding a symbol at each fence location (`objcopy @FILE`) for binaries.
+fn write_fence_patch(arguments: &Arguments, fences: &BTreeSet<u64>, path: &str) -> Result<()> {
+    let input_file_path = Path::new(&arguments.input_file);
+    let muasm_source = match &arguments.inline_asm {
+        Some(source) => Some(source.clone()),
+        None if input_file_path.extension().and_then(|ext| ext.to_str()) == Some("muasm") => {
+            Some(std::fs::read_to_string(input_file_path)?)
+        }
+        None => None,
+    };
+
+    let patch = match muasm_source {
+        Some(source) => {
+            let directory = match &arguments.inline_asm {
+                Some(_) => Path::new("."),
+                None => input_file_path.parent().unwrap_or_else(|| Path::new(".")),
+            };
+            loader::insert_muasm_barriers(&source, directory, fences)?
+        }
+        None => fences
+            .iter()
+            .map(|address| {
+                format!(
+                    "--add-symbol specbmc_fence_{:x}=0x{:x},global\n",
+                    address, address
+                )
+            })
+            .collect(),
+    };
+    patch.dump_to_file(Path::new(path))
+}
+
+const CERTIFICATE_FORMULA_FILE: &str = "formula.smt2";
+
+/// Encodes and solves the program with the fallback solvers (`solver_fallbacks`) in order,
+/// after the configured solver failed with `error` (e.g. crashed or ran out of memory).
+///
+/// If all fallback solvers fail too, the state required to resume the analysis is written
+/// (see `write_resume_state`) and the last error is returned.
+fn solve_with_fallbacks(
+    env: &environment::Environment,
+    arguments: &Arguments,
+    hir_program: &hir::InlinedProgram,
+    lir_program: &lir::Program,
+    formula_file: Option<&Path>,
+    error: Error,
+) -> Result<(Box<dyn Solver>, CheckResult)> {
+    let solved = specbmc::solver::solve_with_fallbacks(
+        env.solver,
+        error,
+        &env.solver_fallbacks,
+        |fallback| {
+            let mut solver = create_process_solver(env, fallback)?;
+            if let Some(path) = formula_file {
+                solver.dump_formula_to_file(path)?;
+            }
+            solver.encode_program(lir_program)?;
+            let result = solver.check_assertions()?;
+            Ok((solver, result))
+        },
+        |failed, error, fallback| {
+            println!(
+                "{} {} {} failed ({}), retry with {} ...",
+                style(">>").bold().dim(),
+                "Solver".yellow(),
+                failed,
+                error,
+                fallback
+            )
+        },
+    );
+    let error = match solved {
+        Err(e) if e.is_solver_failure() => e,
+        solved => return solved,
+    };
+
+    let dir = write_resume_state(env, arguments, hir_program, lir_program)?;
+    println!(
+        "{} Solver failed, resume with `specbmc check -e {} {}`",
+        style(">>").bold().dim(),
+        dir.join(pipeline::RESUME_ENVIRONMENT_FILE).display(),
+        dir.join(pipeline::RESUME_PROGRAM_FILE).display()
+    );
+
+    Err(error)
+}
+
+/// Writes the transformed program (textual HIR), the environment and the SMT formula
+/// into the directory `<input file>.resume`, such that the analysis can be resumed
+/// (or the formula be solved by other means) without repeating the front-end work.
+fn write_resume_state(
+    env: &environment::Environment,
+    arguments: &Arguments,
+    hir_program: &hir::InlinedProgram,
+    lir_program: &lir::Program,
+) -> Result<std::path::PathBuf> {
+    let name = match &arguments.inline_asm {
+        Some(_) => "inline_asm".to_owned(),
+        None => Path::new(&arguments.input_file).file_stem().map_or_else(
+            || "stdin".to_owned(),
+            |stem| stem.to_string_lossy().into_owned(),
+        ),
+    };
+    let dir = Path::new(&format!("{}.resume", name)).to_path_buf();
+    pipeline::write_resume_state(env, hir_program, &dir)?;
+
+    // The formula is written while encoding, a failing solver may leave it incomplete
+    let formula_file = dir.join(CERTIFICATE_FORMULA_FILE);
+    let encoded = create_solver(env).and_then(|mut solver| {
+        solver.dump_formula_to_file(&formula_file)?;
+        solver.encode_program(lir_program)
+    });
+    if let Err(e) = encoded {
+        println!(
+            "{} {} the SMT formula may be incomplete ({})",
+            style(">>").bold().dim(),
+            "Warning:".yellow(),
+            e
+        );
     }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&formula_file)?;
+    writeln!(file, "(check-sat)")?;
+
+    Ok(dir)
+}
+
+/// Writes everything required to independently re-check a safe result into `dir`:
+/// the SMT formula (unsatisfiable iff the program is safe), the solver version,
+/// the unsat proof (if produced by the solver) and the environment.
+/// A `certificate.json` manifest refers to the individual files.
+fn write_certificate(
+    env: &environment::Environment,
+    arguments: &Arguments,
+    solver: Box<dyn Solver>,
+    formula_file: &Path,
+    dir: &Path,
+) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+
+    let solver_version = solver.solver_version()?;
+
+    let proof_file = match solver.unsat_proof()? {
+        Some(proof) => {
+            proof.dump_to_file(&dir.join("proof.smt2"))?;
+            Some("proof.smt2")
+        }
+        None => None,
+    };
+
+    // The formula file is complete once the solver is dropped
+    drop(solver);
+    let mut formula = std::fs::read_to_string(formula_file)?;
+    if !formula.contains("(check-sat)") {
+        // Only solver processes get the check command (the native backend dumps the assertions)
+        formula.push_str("\n(check-sat)\n");
+    }
+    formula.dump_to_file(&dir.join(CERTIFICATE_FORMULA_FILE))?;
+
+    env.dump_to_file(&dir.join("environment.yaml"))?;
 
+    let manifest = serde_json::json!({
+        "program": arguments.input_file,
+        "result": "unsat",
+        "solver": env.solver.to_string(),
+        "solver_version": solver_version,
+        "formula": CERTIFICATE_FORMULA_FILE,
+        "proof": proof_file,
+        "environment": "environment.yaml",
+    });
+    serde_json::to_string_pretty(&manifest)?.dump_to_file(&dir.join("certificate.json"))?;
+
+    Ok(())
+}
+
+/// Prints the leak report of the counterexample (and writes it into the report file if requested).
+fn print_leak_report(
+    arguments: &Arguments,
+    policy: &environment::SecurityPolicy,
+    counter_example: &cex::CounterExample,
+) -> Result<()> {
+    let report = cex::LeakReport::new(counter_example, policy);
+    println!("{}", "Leak report:".bold());
+    for line in report.to_string().lines() {
+        println!("   {}", line);
+    }
+    if let Some(path) = &arguments.report_file {
+        let json = serde_json::to_string_pretty(&report)?;
+        json.dump_to_file(Path::new(path))?;
+    }
+    Ok(())
+}
+
+/// Renders the counterexample (and its GDB script if requested) into the requested files.
+fn render_counter_example(
+    arguments: &Arguments,
+    policy: &environment::SecurityPolicy,
+    mut counter_example: cex::CounterExample,
+) -> Result<()> {
+    if let Some(path) = &arguments.cex_gdb_file {
+        let gdb_script = cex::GdbScript::new(&counter_example, policy, cex::Composition::A);
+        gdb_script.dump_to_file(Path::new(path))?;
+    }
+
+    if arguments.cex_diff {
+        counter_example
+            .control_flow_graph_mut()
+            .elide_identical_state();
+    }
+    counter_example.destruct_ssa();
+    if arguments.cex_text {
+        cex::Timeline::new(&counter_example).dump_to_file(Path::new(&arguments.cex_file))
+    } else {
+        counter_example
+            .control_flow_graph()
+            .render_to_file(Path::new(&arguments.cex_file))
+    }
+}
+
+fn render_stored_counter_example(arguments: &Arguments, file: &str) -> Result<()> {
     let bullet_point = style(">>").bold().dim();
 
-    println!("{} Load program '{}'", bullet_point, input_file.yellow());
-    let input_file_path = Path::new(input_file);
-    let loader = loader::loader_for_file(input_file_path).ok_or("No compatible loader found")?;
-    let mut program = loader.load_program()?;
+    println!("{} Load counterexample '{}'", bullet_point, file.yellow());
+    let stored = cex::StoredCounterExample::from_file(Path::new(file))?;
+    let counter_example = stored.counter_example()?;
+
+    print_leak_report(arguments, stored.policy(), &counter_example)?;
+
+    println!(
+        "{} Render counterexample ({})",
+        bullet_point, arguments.cex_file
+    );
+    render_counter_example(arguments, stored.policy(), counter_example)
+}
+
+/// Renders the transient behavior modeled for the speculating instruction given by `--trans-only-at`
+/// (if requested), `program` must not be transformed yet.
+fn render_transient_copy(
+    arguments: &Arguments,
+    env: &environment::Environment,
+    program: &hir::InlinedProgram,
+) -> Result<()> {
+    if let (Some(path), Some(address)) =
+        (&arguments.trans_only_cfg_file, arguments.trans_only_address)
+    {
+        hir::transformation::transient_copy(env, program, address)?
+            .render_to_file(Path::new(path))?;
+    }
+    Ok(())
+}
+
+/// Renders the requested program graphs, the program is only transformed as far as required.
+fn render_program(arguments: &Arguments) -> Result<()> {
+    let (mut env, sources) = build_environment_with_sources(arguments)?;
 
-    // Overwrite program entry if set
-    if let Some(entry) = &env.analysis.program_entry {
-        let entry = parse_program_entry(entry);
-        program.set_entry(entry)?;
+    if arguments.show_environment || env.debug {
+        println!(
+            "{}:\n{}\n---",
+            "Environment".bold(),
+            style(env.to_annotated_string(&sources)).cyan()
+        );
     }
 
-    println!("{} Inline functions", bullet_point);
-    if let Some(path) = &arguments.call_graph_file {
-        let call_graph = hir::analysis::call_graph(&program);
-        call_graph.render_to_file(Path::new(path))?;
+    let bullet_point = style(">>").bold().dim();
+    let mut emitted_diagnostics = Vec::new();
+
+    let hir_program = if is_hir_input(arguments) {
+        if arguments.trans_only_cfg_file.is_some() {
+            return Err(
+                "Rendering a single transient copy requires an untransformed program".into(),
+            );
+        }
+        println!(
+            "{} Load transformed HIR program '{}'",
+            bullet_point,
+            arguments.input_file.yellow()
+        );
+        pipeline::LoadHir::new().run(&env, Path::new(&arguments.input_file))?
+    } else {
+        println!(
+            "{} Load program '{}'",
+            bullet_point,
+            arguments.input_file.yellow()
+        );
+        let program = load_program(
+            &mut env,
+            &arguments.input_file,
+            arguments.inline_asm.as_deref(),
+        )?;
+
+        if let Some(path) = &arguments.call_graph_file {
+            hir::analysis::call_graph(&program).render_to_file(Path::new(path))?;
+        }
+
+        println!("{} Inline functions", bullet_point);
+        let hir_program = pipeline::Inline::new().run(&env, &program)?;
+        emit_diagnostics(arguments, &mut emitted_diagnostics)?;
+
+        if let Some(path) = &arguments.cfg_file {
+            hir_program
+                .control_flow_graph()
+                .render_to_file(Path::new(path))?;
+        }
+
+        if let Some(path) = &arguments.loop_tree_file {
+            let loop_tree =
+                hir::transformation::LoopUnwinding::loop_tree(hir_program.control_flow_graph())?;
+            loop_tree.render_to_file(Path::new(path))?;
+        }
+
+        render_transient_copy(arguments, &env, &hir_program)?;
+
+        if arguments.transient_cfg_file.is_none()
+            && arguments.mir_file.is_none()
+            && arguments.hir_file.is_none()
+        {
+            return Ok(());
+        }
+
+        println!("{} Transform HIR ...", bullet_point);
+        let hir_program = hir_transformations(&env, None).run(&env, hir_program)?;
+        emit_diagnostics(arguments, &mut emitted_diagnostics)?;
+
+        hir_program
+    };
+
+    if let Some(path) = &arguments.hir_file {
+        hir::text::print_program(&hir_program)?.dump_to_file(Path::new(path))?;
     }
-    let function_inlining = hir::transformation::FunctionInliningBuilder::default()
-        .recursion_limit(env.analysis.recursion_limit)
-        .ignored_functions(env.analysis.inline_ignore.clone())
-        .build()
-        .unwrap();
-    let mut hir_program = function_inlining.inline(&program)?;
 
-    if let Some(path) = &arguments.cfg_file {
+    if let Some(path) = &arguments.transient_cfg_file {
         hir_program
             .control_flow_graph()
             .render_to_file(Path::new(path))?;
     }
 
-    if let Some(path) = &arguments.loop_tree_file {
-        let loop_tree =
-            hir::transformation::LoopUnwinding::loop_tree(hir_program.control_flow_graph())?;
-        loop_tree.render_to_file(Path::new(path))?;
+    if let Some(path) = &arguments.mir_file {
+        println!("{} Translate into MIR", bullet_point);
+        let mir_program = pipeline::ToMir::new().run(&env, &hir_program)?;
+        mir_program.block_graph().render_to_file(Path::new(path))?;
+    }
+
+    Ok(())
+}
+
+fn print_assembly_info(arguments: &Arguments) -> Result<()> {
+    let loader = match &arguments.inline_asm {
+        Some(source) => loader::loader_for_muasm_source(source),
+        None => loader::loader_for_file(
+            Path::new(&arguments.input_file),
+            environment::UnsupportedInstructionPolicy::default(),
+            false,
+            None,
+        )
+        .ok_or_else(|| Error::NoCompatibleLoader(arguments.input_file.clone().into()))?,
+    };
+
+    let info = loader.assembly_info()?;
+    println!("{}", info);
+
+    Ok(())
+}
+
+fn check_program(arguments: &Arguments) -> Result<()> {
+    let input_file = &arguments.input_file;
+
+    let (mut env, sources) = build_environment_with_sources(arguments)?;
+
+    if arguments.show_environment || env.debug {
+        println!(
+            "{}:\n{}\n---",
+            "Environment".bold(),
+            style(env.to_annotated_string(&sources)).cyan()
+        );
     }
 
-    println!("{} Transform HIR ...", bullet_point);
-    hir_transformations(&env, &mut hir_program)?;
+    if env.analysis.modular && !is_hir_input(arguments) {
+        return check_functions_modularly(arguments, env);
+    }
+
+    let bullet_point = style(">>").bold().dim();
+    let mut emitted_diagnostics = Vec::new();
+
+    let debug_dir = match &arguments.debug_dir {
+        Some(parent) => {
+            let dir = DebugDir::create(Path::new(parent))?;
+            println!(
+                "{} Write debug artifacts into '{}'",
+                bullet_point,
+                dir.path().display()
+            );
+            env.dump_to_file(&dir.file("environment.yaml"))?;
+            dir.update_index()?;
+            Some(dir)
+        }
+        None => None,
+    };
+
+    let (hir_program, unpatched_program, searched) = if is_hir_input(arguments) {
+        if arguments.suggest_fences || arguments.suggest_masking {
+            return Err("Suggesting fences or masking requires an untransformed program".into());
+        }
+        if !arguments.intended_fences.is_empty() {
+            return Err("Inserting fences requires an untransformed program".into());
+        }
+        if arguments.max_unwind.is_some() {
+            return Err("Incremental unwinding requires an untransformed program".into());
+        }
+        if arguments.trans_only_cfg_file.is_some() {
+            return Err(
+                "Rendering a single transient copy requires an untransformed program".into(),
+            );
+        }
+        println!(
+            "{} Load transformed HIR program '{}'",
+            bullet_point,
+            input_file.yellow()
+        );
+        let hir_program = pipeline::LoadHir::new().run(&env, Path::new(input_file))?;
+        (hir_program, None, None)
+    } else {
+        println!("{} Load program '{}'", bullet_point, input_file.yellow());
+        let program = load_program(&mut env, input_file, arguments.inline_asm.as_deref())?;
+
+        println!("{} Inline functions", bullet_point);
+        if let Some(path) = &arguments.call_graph_file {
+            let call_graph = hir::analysis::call_graph(&program);
+            call_graph.render_to_file(Path::new(path))?;
+        }
+        let hir_program = pipeline::Inline::new().run(&env, &program)?;
+        emit_diagnostics(arguments, &mut emitted_diagnostics)?;
+
+        if let Some(path) = &arguments.cfg_file {
+            hir_program
+                .control_flow_graph()
+                .render_to_file(Path::new(path))?;
+        }
+
+        if let Some(path) = &arguments.loop_tree_file {
+            let loop_tree =
+                hir::transformation::LoopUnwinding::loop_tree(hir_program.control_flow_graph())?;
+            loop_tree.render_to_file(Path::new(path))?;
+        }
+
+        render_transient_copy(arguments, &env, &hir_program)?;
+
+        let hir_program = if arguments.intended_fences.is_empty() {
+            hir_program
+        } else {
+            println!(
+                "{} Insert {} fence(s) at {}",
+                bullet_point,
+                arguments.intended_fences.len(),
+                arguments
+                    .intended_fences
+                    .iter()
+                    .map(|address| format!("0x{:X}", address))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            repair::insert_fences(hir_program, &arguments.intended_fences)?
+        };
+
+        // Keep the untransformed program for re-verification with barriers
+        let unpatched_program = if arguments.suggest_fences || arguments.suggest_masking {
+            Some(hir_program.clone())
+        } else {
+            None
+        };
+
+        if let Some(max_unwind) = arguments.max_unwind {
+            println!("{} Search unwinding bounds ...", bullet_point);
+            let pipeline::UnwindingBounds {
+                env: found_env,
+                program,
+                mir_program,
+                lir_program,
+                solver,
+                result,
+            } = pipeline::search_unwinding_bounds(&env, &hir_program, max_unwind, |env| {
+                println!(
+                    "-> Check with k={}{}",
+                    env.analysis.unwind,
+                    env.analysis
+                        .unwind_loop
+                        .iter()
+                        .map(|(loop_id, k)| format!(", loop {}: k={}", loop_id, k))
+                        .collect::<String>()
+                );
+            })?;
+            emit_diagnostics(arguments, &mut emitted_diagnostics)?;
+
+            // The search already transformed and checked the program with the found bounds
+            env = found_env;
+            let searched = (mir_program, lir_program, solver, result);
+            (program, unpatched_program, Some(searched))
+        } else {
+            println!("{} Transform HIR ...", bullet_point);
+            let hir_program =
+                hir_transformations(&env, debug_dir.clone()).run(&env, hir_program)?;
+            emit_diagnostics(arguments, &mut emitted_diagnostics)?;
+
+            (hir_program, unpatched_program, None)
+        }
+    };
+
+    if let Some(path) = &arguments.hir_file {
+        hir::text::print_program(&hir_program)?.dump_to_file(Path::new(path))?;
+    }
+
+    if let Some(dir) = &debug_dir {
+        hir::text::print_program(&hir_program)?.dump_to_file(&dir.file("program.hir"))?;
+        dir.update_index()?;
+    }
 
     if let Some(path) = &arguments.transient_cfg_file {
         hir_program
@@ -543,61 +2157,351 @@ fn check_program(arguments: &Arguments) -> Result<()> {
             .render_to_file(Path::new(path))?;
     }
 
-    println!("{} Translate into MIR", bullet_point);
-    let mir_program = mir::Program::try_translate_from(&hir_program)?;
+    let (mir_program, searched) = match searched {
+        Some((mir_program, lir_program, solver, result)) => {
+            (mir_program, Some((lir_program, solver, result)))
+        }
+        None => {
+            println!("{} Translate into MIR", bullet_point);
+            (pipeline::ToMir::new().run(&env, &hir_program)?, None)
+        }
+    };
 
     if let Some(path) = &arguments.mir_file {
         mir_program.block_graph().render_to_file(Path::new(path))?;
     }
 
-    println!("{} Translate into LIR", bullet_point);
-    let mut lir_program = lir::Program::try_translate_from(&mir_program)?;
-    lir_program.validate()?;
+    if let Some(dir) = &debug_dir {
+        mir_program
+            .block_graph()
+            .render_to_file(&dir.file("mir.dot"))?;
+        dir.update_index()?;
+    }
+
+    let (lir_program, searched_check) = match searched {
+        Some((lir_program, solver, result)) => (lir_program, Some((solver, result))),
+        None => {
+            println!("{} Translate into LIR", bullet_point);
+            let lir_program = pipeline::ToLir::new().run(&env, &mir_program)?;
 
-    println!("{} Optimize LIR", bullet_point);
-    let lir_optimizer = lir::optimization::Optimizer::new_from_env(&env);
-    lir_optimizer.optimize(&mut lir_program)?;
+            println!("{} Optimize LIR", bullet_point);
+            let lir_program = pipeline::Optimize::new().run(&env, lir_program)?;
+            (lir_program, None)
+        }
+    };
 
     if let Some(path) = &arguments.lir_file {
-        lir_program.dump_to_file(Path::new(path))?;
+        if arguments.lir_slices {
+            lir_program
+                .assertion_slices_to_string()
+                .dump_to_file(Path::new(path))?;
+        } else {
+            lir_program.dump_to_file(Path::new(path))?;
+        }
     }
 
-    let mut solver = create_solver(&env)?;
-    if let Some(path) = &arguments.smt_file {
-        solver.dump_formula_to_file(Path::new(path))?
+    if let Some(dir) = &debug_dir {
+        lir_program.dump_to_file(&dir.file("lir.txt"))?;
+        dir.update_index()?;
     }
 
-    println!(
-        "{} Encode LIR as SMT formula (solver={})",
-        bullet_point, env.solver
-    );
-    solver.encode_program(&lir_program)?;
+    if let Some(dir) = &arguments.smt_split_dir {
+        println!("{} Split proof obligations by assertion", bullet_point);
+        split_proof_obligations(&env, &lir_program, Path::new(dir))?;
+    }
+
+    let formula_file = match (&arguments.smt_file, &arguments.certificate_dir) {
+        (Some(path), _) => Some(Path::new(path).to_path_buf()),
+        (None, Some(dir)) => {
+            std::fs::create_dir_all(dir)?;
+            Some(Path::new(dir).join(CERTIFICATE_FORMULA_FILE))
+        }
+        (None, None) => None,
+    };
+
+    // Reuse the final check of the unwinding bound search, unless the formula is required anyway
+    // or the violation was simulated and consists of warnings only
+    let searched_check = searched_check.filter(|(solver, result)| {
+        !arguments.skip_solving
+            && !searches_speculation_windows(arguments)
+            && formula_file.is_none()
+            && (solver.is_some() || is_conclusive(arguments, &lir_program, result))
+    });
+
+    // The type check can only prove, skip it if the solver is required anyway
+    if env.analysis.static_check
+        && searched_check.is_none()
+        && !arguments.skip_solving
+        && !searches_speculation_windows(arguments)
+        && formula_file.is_none()
+    {
+        println!("{} Check security types ...", bullet_point);
+        let levels = pipeline::TypeCheck::new().run(&env, &hir_program)?;
+        match levels.violations().first() {
+            None => {
+                println!("{}", "Program is safe (static).".bold().green());
+                return Ok(());
+            }
+            Some(reason) => println!(
+                "{} Type check failed: {} ({} in total)",
+                bullet_point,
+                reason,
+                levels.violations().len()
+            ),
+        }
+    }
+
+    // Simulation can only falsify, skip it if the solver is required anyway
+    let simulated_result = if env.analysis.simulation_runs > 0
+        && searched_check.is_none()
+        && !arguments.skip_solving
+        && !searches_speculation_windows(arguments)
+        && formula_file.is_none()
+    {
+        println!(
+            "{} Simulate {} random executions (seed={}) ...",
+            bullet_point, env.analysis.simulation_runs, env.analysis.simulation_seed
+        );
+        pipeline::Simulate::new().run(&env, &lir_program)?
+    } else {
+        None
+    };
+    // Warnings found by simulation don't tell whether there are failing violations
+    let simulated_result =
+        simulated_result.filter(|result| is_conclusive(arguments, &lir_program, result));
+
+    let (mut solver, mut result) = match (searched_check, simulated_result) {
+        (Some(check), _) => check,
+        (None, Some(result)) => (None, result),
+        (None, None) => {
+            let mut encode = pipeline::Encode::new();
+            if let Some(path) = &formula_file {
+                encode = encode.with_formula_file(path);
+            } else if let Some(dir) = &debug_dir {
+                encode = encode.with_formula_file(dir.file("formula.smt2"));
+            }
+
+            println!(
+                "{} Encode LIR as SMT formula (solver={})",
+                bullet_point, env.solver
+            );
+            let encoded = encode.run(&env, &lir_program);
+            if let Some(dir) = &debug_dir {
+                dir.update_index()?;
+            }
+
+            if arguments.skip_solving {
+                encoded?;
+                return Ok(());
+            }
+
+            if let Some(windows) = &arguments.sweep_speculation_windows {
+                println!(
+                    "{} Search for leaks (speculation window sweep) ...",
+                    bullet_point
+                );
+                return sweep_speculation_windows(
+                    arguments,
+                    encoded?.as_mut(),
+                    &lir_program,
+                    windows,
+                );
+            }
+
+            if arguments.min_speculation_window {
+                println!(
+                    "{} Search for the smallest leaking speculation window ...",
+                    bullet_point
+                );
+                let max_window = env.architecture.speculation_window;
+                let min_window = pipeline::min_leaking_speculation_window(
+                    encoded?.as_mut(),
+                    &lir_program,
+                    max_window,
+                    |provenance| arguments.violation_policy.is_failing(provenance),
+                    |window, leaks| {
+                        println!(
+                            "   Speculation window {}: {}",
+                            window,
+                            if leaks { "leaks" } else { "safe" }
+                        )
+                    },
+                )?;
+                match min_window {
+                    Some(window) => {
+                        println!(
+                            "{}",
+                            format!("Leaks for speculation window >= {}", window)
+                                .bold()
+                                .red()
+                        );
+                        process::exit(2);
+                    }
+                    None => println!(
+                        "{}",
+                        format!("Program is safe for speculation windows <= {}", max_window)
+                            .bold()
+                            .green()
+                    ),
+                }
+                return Ok(());
+            }
+
+            println!("{} Search for leaks ...", bullet_point);
+            let solved = encoded.and_then(|mut solver| {
+                let result = pipeline::Solve::new().run(&env, solver.as_mut())?;
+                Ok((solver, result))
+            });
+            let (solver, result) = match solved {
+                Err(e) if e.is_solver_failure() => solve_with_fallbacks(
+                    &env,
+                    arguments,
+                    &hir_program,
+                    &lir_program,
+                    formula_file.as_deref(),
+                    e,
+                )?,
+                solved => solved?,
+            };
+            (Some(solver), result)
+        }
+    };
 
-    if arguments.skip_solving {
-        return Ok(());
+    let mut warnings = Vec::new();
+    if let CheckResult::AssertionViolated { model } = &result {
+        let violated = pipeline::violated_assertions(&lir_program, model.as_ref());
+        if !violated
+            .iter()
+            .any(|provenance| arguments.violation_policy.is_failing(provenance))
+        {
+            warnings = violated;
+        }
+    }
+    if let (false, Some(solver)) = (warnings.is_empty(), solver.as_mut()) {
+        println!(
+            "{} Search for failing leaks (ignoring warnings) ...",
+            bullet_point
+        );
+        result = check_failing_assertions(arguments, solver.as_mut(), &lir_program)?;
     }
 
-    println!("{} Search for leaks ...", bullet_point);
-    match solver.check_assertions()? {
+    match result {
+        CheckResult::AssertionsHold if !warnings.is_empty() => {
+            println!("{}", "Leak detected (warning only)".bold().yellow());
+            for provenance in &warnings {
+                println!(
+                    "   Violated assertion: {} [{}]",
+                    provenance,
+                    provenance.violation_class()
+                );
+            }
+        }
         CheckResult::AssertionsHold => {
             println!("{}", "Program is safe.".bold().green());
+
+            if env.analysis.check != environment::Check::OnlyNormalExecutionLeaks {
+                print_max_speculation_window(&env, &hir_program)?;
+            }
+
+            if let (Some(dir), Some(formula_file), Some(solver)) =
+                (&arguments.certificate_dir, &formula_file, solver)
+            {
+                println!("{} Write certificate ({})", bullet_point, dir);
+                write_certificate(&env, arguments, solver, formula_file, Path::new(dir))?;
+            }
         }
         CheckResult::AssertionViolated { model } => {
             println!("{}", "Leak detected!".bold().red());
 
+            let violated = pipeline::violated_assertions(&lir_program, model.as_ref());
+            let exit_code = arguments
+                .violation_policy
+                .exit_code(violated.iter().copied());
+            for provenance in violated {
+                println!(
+                    "   Violated assertion: {} [{}]",
+                    provenance,
+                    provenance.violation_class()
+                );
+            }
+
             if env.generate_counterexample {
                 println!(
                     "{} Generate counterexample ({})",
                     bullet_point, arguments.cex_file
                 );
 
-                let counter_example = cex::build_counter_example(&hir_program, model.as_ref())?;
-                counter_example
-                    .control_flow_graph()
-                    .render_to_file(Path::new(&arguments.cex_file))?;
+                let counter_example = match &arguments.cex_store_file {
+                    Some(path) => {
+                        // Record the model, such that the counterexample can be rebuilt later on
+                        let recorder = ModelRecorder::new(model.as_ref());
+                        let counter_example = cex::build_counter_example(&hir_program, &recorder)?;
+                        cex::StoredCounterExample::new(
+                            &hir_program,
+                            recorder.into_recorded_model(),
+                            &env.policy,
+                        )
+                        .dump_to_file(Path::new(path))?;
+                        counter_example
+                    }
+                    None => cex::build_counter_example(&hir_program, model.as_ref())?,
+                };
+
+                if let Some(path) = &arguments.cex_litmus_file {
+                    write_litmus_harness(&env, arguments, &counter_example, path)?;
+                }
+
+                print_leak_report(arguments, &env.policy, &counter_example)?;
+
+                print_speculation_window_usage(&env, &hir_program, &counter_example);
+
+                print_component_state(&hir_program, &counter_example);
+
+                render_counter_example(arguments, &env.policy, counter_example)?;
             }
 
-            process::exit(2);
+            if let Some(program) = unpatched_program
+                .as_ref()
+                .filter(|_| arguments.suggest_fences)
+            {
+                println!("{} Search for speculation barriers ...", bullet_point);
+                match repair::suggest_fences(&env, program)? {
+                    Some(fences) => {
+                        println!("{}", "Suggested fences:".bold());
+                        for address in &fences {
+                            println!("   lfence before 0x{:X}", address);
+                        }
+                        if let Some(path) = &arguments.fence_patch_file {
+                            println!("{} Write fence patch ({})", bullet_point, path);
+                            write_fence_patch(arguments, &fences, path)?;
+                        }
+                    }
+                    None => {
+                        println!(
+                            "{}",
+                            "No fence placement found (leak is not caused by speculation)".yellow()
+                        );
+                    }
+                }
+            }
+
+            if let Some(program) = unpatched_program
+                .as_ref()
+                .filter(|_| arguments.suggest_masking)
+            {
+                println!("{} Search for load address masking ...", bullet_point);
+                match repair::suggest_index_masking(&env, program)? {
+                    Some((patched_program, addresses)) => {
+                        println!("{}", "Suggested index masking:".bold());
+                        print_patch_diff(program, &patched_program, &addresses);
+                    }
+                    None => {
+                        println!("{}", "No index masking found".yellow());
+                    }
+                }
+            }
+
+            process::exit(exit_code);
         }
     }
 
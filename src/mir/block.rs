@@ -1,3 +1,4 @@
+use crate::error::{Error, Result};
 use crate::expr::{Boolean, Expression, Sort, Variable};
 use crate::mir::Node;
 use falcon::graph;
@@ -55,6 +56,28 @@ impl Block {
     pub fn add_node(&mut self, node: Node) {
         self.nodes.push(node);
     }
+
+    /// Returns an iterator over all nodes of this block along with their index.
+    pub fn indexed_nodes(&self) -> impl Iterator<Item = (usize, &Node)> {
+        self.nodes.iter().enumerate()
+    }
+
+    /// Inserts a node at the given index, shifting all following nodes.
+    pub fn insert_node(&mut self, index: usize, node: Node) -> Result<()> {
+        if index > self.nodes.len() {
+            return Err(Error::InvalidNodeIndex(index));
+        }
+        self.nodes.insert(index, node);
+        Ok(())
+    }
+
+    /// Removes the node at the given index and returns it.
+    pub fn remove_node(&mut self, index: usize) -> Result<Node> {
+        if index >= self.nodes.len() {
+            return Err(Error::NodeNotFound(index));
+        }
+        Ok(self.nodes.remove(index))
+    }
 }
 
 impl graph::Vertex for Block {
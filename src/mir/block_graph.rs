@@ -1,4 +1,4 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::mir::{Block, Edge};
 use crate::util::RenderGraph;
 use falcon::graph::Graph;
@@ -29,13 +29,13 @@ impl BlockGraph {
 
     /// Get the entry `Block` index of this `BlockGraph`.
     pub fn entry(&self) -> Result<usize> {
-        self.entry.ok_or_else(|| "CFG entry must be set".into())
+        self.entry.ok_or(Error::MissingEntry)
     }
 
     /// Sets the entry point for this `BlockGraph` to the given `Block` index.
     pub fn set_entry(&mut self, entry: usize) -> Result<()> {
         if !self.graph.has_vertex(entry) {
-            return Err("Index does not exist for set_entry".into());
+            return Err(Error::BlockNotFound(entry));
         }
         self.entry = Some(entry);
         Ok(())
@@ -43,13 +43,13 @@ impl BlockGraph {
 
     /// Get the exit `Block` index of this `BlockGraph`.
     pub fn exit(&self) -> Result<usize> {
-        self.exit.ok_or_else(|| "CFG exit must be set".into())
+        self.exit.ok_or(Error::MissingExit)
     }
 
     /// Sets the exit point for this `BlockGraph` to the given `Block` index.
     pub fn set_exit(&mut self, exit: usize) -> Result<()> {
         if !self.graph.has_vertex(exit) {
-            return Err("Index does not exist for set_exit".into());
+            return Err(Error::BlockNotFound(exit));
         }
         self.exit = Some(exit);
         Ok(())
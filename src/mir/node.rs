@@ -1,5 +1,6 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::expr::{Expression, Variable};
+use crate::ir::Provenance;
 use std::fmt;
 
 #[allow(clippy::large_enum_variant)]
@@ -10,12 +11,18 @@ pub enum Node {
     /// Bind the expression to a variable.
     Let { var: Variable, expr: Expression },
     /// Assert that the condition is true in each composition.
-    Assert { condition: Expression },
+    Assert {
+        condition: Expression,
+        provenance: Provenance,
+    },
     /// Assume that the condition is true in each composition.
     Assume { condition: Expression },
     /// Assert that the condition is true.
     /// The condition may refer to variables from different compositions.
-    HyperAssert { condition: Expression },
+    HyperAssert {
+        condition: Expression,
+        provenance: Provenance,
+    },
     /// Assume that the condition is true.
     /// The condition may refer to variables from different compositions.
     HyperAssume { condition: Expression },
@@ -35,26 +42,36 @@ impl Node {
         expr.sort().expect_sort(var.sort())?;
 
         if var.composition().is_some() {
-            return Err("Target variable must not refer to a composition.".into());
+            return Err(Error::UnexpectedComposition {
+                what: "Target variable",
+                hint: None,
+            });
         }
         if has_variables_with_composition(&expr) {
-            return Err("Expression variables must not refer to a composition.".into());
+            return Err(Error::UnexpectedComposition {
+                what: "Expression variables",
+                hint: None,
+            });
         }
 
         Ok(Self::Let { var, expr })
     }
 
     /// Create a new assertion.
-    pub fn assert(condition: Expression) -> Result<Self> {
+    pub fn assert(condition: Expression, provenance: Provenance) -> Result<Self> {
         condition.sort().expect_boolean()?;
 
         if has_variables_with_composition(&condition) {
-            return Err(
-                "Condition variables must not refer to a composition, use hyper_assert.".into(),
-            );
+            return Err(Error::UnexpectedComposition {
+                what: "Condition variables",
+                hint: Some("hyper_assert"),
+            });
         }
 
-        Ok(Self::Assert { condition })
+        Ok(Self::Assert {
+            condition,
+            provenance,
+        })
     }
 
     /// Create a new assumption.
@@ -62,9 +79,10 @@ impl Node {
         condition.sort().expect_boolean()?;
 
         if has_variables_with_composition(&condition) {
-            return Err(
-                "Condition variables must not refer to a composition, use hyper_assume.".into(),
-            );
+            return Err(Error::UnexpectedComposition {
+                what: "Condition variables",
+                hint: Some("hyper_assume"),
+            });
         }
 
         Ok(Self::Assume { condition })
@@ -73,14 +91,19 @@ impl Node {
     /// Create a new hyper-assertion.
     ///
     /// The condition may refer to variables from different compositions.
-    pub fn hyper_assert(condition: Expression) -> Result<Self> {
+    pub fn hyper_assert(condition: Expression, provenance: Provenance) -> Result<Self> {
         condition.sort().expect_boolean()?;
 
         if has_variables_without_composition(&condition) {
-            return Err("All condition variables must refer to a composition.".into());
+            return Err(Error::MissingComposition {
+                what: "condition variables",
+            });
         }
 
-        Ok(Self::HyperAssert { condition })
+        Ok(Self::HyperAssert {
+            condition,
+            provenance,
+        })
     }
 
     // Create a new hyper-assumption.
@@ -90,7 +113,9 @@ impl Node {
         condition.sort().expect_boolean()?;
 
         if has_variables_without_composition(&condition) {
-            return Err("All condition variables must refer to a composition.".into());
+            return Err(Error::MissingComposition {
+                what: "condition variables",
+            });
         }
 
         Ok(Self::HyperAssume { condition })
@@ -130,9 +155,9 @@ impl Node {
     pub fn variables_used(&self) -> Vec<&Variable> {
         match self {
             Self::Let { expr, .. } => expr.variables(),
-            Self::Assert { condition }
+            Self::Assert { condition, .. }
             | Self::Assume { condition }
-            | Self::HyperAssert { condition }
+            | Self::HyperAssert { condition, .. }
             | Self::HyperAssume { condition } => condition.variables(),
             Self::Comment(_) => Vec::new(),
         }
@@ -142,9 +167,9 @@ impl Node {
     pub fn variables_used_mut(&mut self) -> Vec<&mut Variable> {
         match self {
             Self::Let { expr, .. } => expr.variables_mut(),
-            Self::Assert { condition }
+            Self::Assert { condition, .. }
             | Self::Assume { condition }
-            | Self::HyperAssert { condition }
+            | Self::HyperAssert { condition, .. }
             | Self::HyperAssume { condition } => condition.variables_mut(),
             Self::Comment(_) => Vec::new(),
         }
@@ -188,9 +213,15 @@ impl fmt::Display for Node {
         match self {
             Self::Comment(text) => write!(f, "// {}", text),
             Self::Let { var, expr } => write!(f, "let {} = {}", var, expr),
-            Self::Assert { condition } => write!(f, "assert {}", condition),
+            Self::Assert {
+                condition,
+                provenance,
+            } => write!(f, "assert {} ({})", condition, provenance),
             Self::Assume { condition } => write!(f, "assume {}", condition),
-            Self::HyperAssert { condition } => write!(f, "hyper-assert {}", condition),
+            Self::HyperAssert {
+                condition,
+                provenance,
+            } => write!(f, "hyper-assert {} ({})", condition, provenance),
             Self::HyperAssume { condition } => write!(f, "hyper-assume {}", condition),
         }
     }
@@ -207,3 +238,31 @@ fn has_variables_without_composition(expr: &Expression) -> bool {
         .iter()
         .any(|var| var.composition().is_none())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::BitVector;
+
+    #[test]
+    fn test_assertion_of_composed_variables_requires_hyper_assertion() {
+        // Given:
+        let mut x = BitVector::word_variable("x");
+        x.set_composition(Some(1));
+        let condition = Expression::equal(x.into(), BitVector::word_constant(0)).unwrap();
+
+        // When:
+        let assertion = Node::assert(condition.clone(), Provenance::observation());
+        let hyper_assertion = Node::hyper_assert(condition, Provenance::observation());
+
+        // Then:
+        assert!(matches!(
+            assertion,
+            Err(Error::UnexpectedComposition {
+                hint: Some("hyper_assert"),
+                ..
+            })
+        ));
+        assert!(hyper_assertion.is_ok());
+    }
+}
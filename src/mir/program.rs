@@ -1,35 +1,91 @@
-use crate::mir::BlockGraph;
+use crate::error::Result;
+use crate::mir::{BlockGraph, Node};
 use std::fmt;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Program {
-    block_graph: BlockGraph,
+    /// The block graph of each composition, a single one if all compositions execute the same program.
+    block_graphs: Vec<BlockGraph>,
     self_compositions: usize, // The number of required self-compositions
+    /// Hyper-assertions/assumptions relating the compositions of a product program.
+    /// Their conditions include the execution conditions of the related blocks.
+    product_nodes: Vec<Node>,
 }
 
 impl Program {
     pub fn new(block_graph: BlockGraph, self_compositions: usize) -> Self {
         Self {
-            block_graph,
+            block_graphs: vec![block_graph],
             self_compositions,
+            product_nodes: Vec::new(),
         }
     }
 
+    /// Creates the product of different programs, composition `i` executes `block_graphs[i]`.
+    pub fn product(block_graphs: Vec<BlockGraph>, product_nodes: Vec<Node>) -> Result<Self> {
+        if block_graphs.len() < 2 {
+            return Err("A product program requires at least 2 programs".into());
+        }
+        if product_nodes
+            .iter()
+            .any(|node| !node.is_hyper_assert() && !node.is_hyper_assume())
+        {
+            return Err(
+                "Product programs can only be related by hyper-assertions/assumptions".into(),
+            );
+        }
+
+        Ok(Self {
+            self_compositions: block_graphs.len(),
+            block_graphs,
+            product_nodes,
+        })
+    }
+
+    /// The block graph of the (first) program.
     pub fn block_graph(&self) -> &BlockGraph {
-        &self.block_graph
+        &self.block_graphs[0]
     }
 
     pub fn block_graph_mut(&mut self) -> &mut BlockGraph {
-        &mut self.block_graph
+        &mut self.block_graphs[0]
+    }
+
+    /// The block graph executed by the given composition.
+    pub fn block_graph_of(&self, composition: usize) -> &BlockGraph {
+        self.block_graphs
+            .get(composition)
+            .unwrap_or(&self.block_graphs[0])
     }
 
     pub fn self_compositions(&self) -> usize {
         self.self_compositions
     }
+
+    /// Checks if the compositions execute different programs.
+    pub fn is_product(&self) -> bool {
+        self.block_graphs.len() > 1
+    }
+
+    pub fn product_nodes(&self) -> &[Node] {
+        &self.product_nodes
+    }
 }
 
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.block_graph().fmt(f)
+        if !self.is_product() {
+            return self.block_graph().fmt(f);
+        }
+
+        for (composition, block_graph) in self.block_graphs.iter().enumerate() {
+            writeln!(f, "Program @{}", composition)?;
+            block_graph.fmt(f)?;
+        }
+        writeln!(f, "Product")?;
+        for node in &self.product_nodes {
+            writeln!(f, "{}", node)?;
+        }
+        Ok(())
     }
 }
@@ -23,7 +23,7 @@ fn translate_program_composition(
     mir_program: &mir::Program,
     composition: usize,
 ) -> Result<()> {
-    for block in mir_program.block_graph().blocks() {
+    for block in mir_program.block_graph_of(composition).blocks() {
         translate_block(lir_program, block, composition)?;
     }
     Ok(())
@@ -55,14 +55,20 @@ fn translate_block(
                     expr.self_compose(composition),
                 )?;
             }
-            mir::Node::Assert { condition } => {
-                program.assert(Boolean::imply(
-                    block
-                        .execution_condition_variable()
-                        .self_compose(composition)
-                        .into(), // only if executed
-                    condition.self_compose(composition),
-                )?)?;
+            mir::Node::Assert {
+                condition,
+                provenance,
+            } => {
+                program.assert(
+                    Boolean::imply(
+                        block
+                            .execution_condition_variable()
+                            .self_compose(composition)
+                            .into(), // only if executed
+                        condition.self_compose(composition),
+                    )?,
+                    provenance.clone(),
+                )?;
             }
             mir::Node::Assume { condition } => {
                 program.assume(Boolean::imply(
@@ -87,14 +93,28 @@ fn add_self_composition_constraints(
     lir_program.comment("Self-Composition Constraints");
 
     for block in mir_program.block_graph().blocks() {
+        let has_hyper_nodes = block
+            .nodes()
+            .iter()
+            .any(|node| node.is_hyper_assert() || node.is_hyper_assume());
+        if has_hyper_nodes {
+            lir_program.comment(format!("Block 0x{:X}", block.index()));
+        }
+
         for node in block.nodes() {
             match node {
-                mir::Node::HyperAssert { condition } => {
+                mir::Node::HyperAssert {
+                    condition,
+                    provenance,
+                } => {
                     let compositions = involved_compositions(condition)?;
-                    lir_program.assert(Boolean::imply(
-                        hyper_execution_condition(block, &compositions)?, // only if executed
-                        condition.clone(),
-                    )?)?;
+                    lir_program.assert(
+                        Boolean::imply(
+                            hyper_execution_condition(block, &compositions)?, // only if executed
+                            condition.clone(),
+                        )?,
+                        provenance.clone(),
+                    )?;
                 }
                 mir::Node::HyperAssume { condition } => {
                     let compositions = involved_compositions(condition)?;
@@ -108,6 +128,26 @@ fn add_self_composition_constraints(
         }
     }
 
+    if mir_program.is_product() {
+        lir_program.comment("Product Constraints");
+    }
+
+    // The conditions already include the execution conditions of the related blocks
+    for node in mir_program.product_nodes() {
+        match node {
+            mir::Node::HyperAssert {
+                condition,
+                provenance,
+            } => {
+                lir_program.assert(condition.clone(), provenance.clone())?;
+            }
+            mir::Node::HyperAssume { condition } => {
+                lir_program.assume(condition.clone())?;
+            }
+            _ => (),
+        }
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,1302 @@
+//! The verification pipeline as composable stages.
+//!
+//! Each stage consumes the artifact of the previous stage and produces the artifact of the next one:
+//!
+//! ```text
+//! Load -> Inline -> HirTransform -> [TypeCheck] -> ToMir -> ToLir -> Optimize -> [Simulate] -> Encode -> Solve
+//! ```
+//!
+//! `TypeCheck` is optional, it tries to prove the program secure by security type inference
+//! before translating it.
+//! `Simulate` is optional, it tries to falsify the program by random execution before encoding it.
+//!
+//! `LoadHir` loads an already transformed program (textual HIR) in place of `Load`, `Inline` and
+//! `HirTransform`.
+//!
+//! Stages which only read their input take it by reference, such that the artifact can still be
+//! inspected afterwards (e.g. the transformed HIR program is required to build counterexamples).
+//! Stages which modify their input in-place (`HirTransform`, `Optimize`) take it by value.
+//! The pipeline can therefore be stopped after any stage, and any stage can be replaced by a custom
+//! implementation of `Stage`.
+
+use crate::environment::{Environment, UnwindingGuard, SPECULATION_WINDOW_SIZE};
+use crate::error::{Error, Result};
+use crate::hir::{self, InlinedProgram};
+use crate::ir::{
+    AssertionKind, Provenance, Transform, TryTranslateFrom, TryTranslateInto, Validate,
+};
+use crate::solver::{self, create_solver, CheckResult, Solver};
+use crate::util::DumpToFile;
+use crate::{expr, lir, loader, mir};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// A single step of the verification pipeline, turning an artifact `I` into `Self::Output`.
+pub trait Stage<I> {
+    type Output;
+
+    /// Name of the stage.
+    fn name(&self) -> &'static str;
+
+    /// Runs the stage on `input`.
+    fn run(&self, env: &Environment, input: I) -> Result<Self::Output>;
+}
+
+/// Loads the program from the given file and sets the program entry (if given by the environment).
+#[derive(Default)]
+pub struct Load {}
+
+impl Load {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a Path> for Load {
+    type Output = hir::Program;
+
+    fn name(&self) -> &'static str {
+        "Load"
+    }
+
+    fn run(&self, env: &Environment, input_file: &'a Path) -> Result<Self::Output> {
+        let loader = loader::loader_for_file(
+            input_file,
+            env.analysis.unsupported_instructions,
+            env.lift_cache,
+            env.setup.load_base,
+        )
+        .ok_or_else(|| Error::NoCompatibleLoader(input_file.to_owned()))?;
+        let mut program = loader.load_program()?;
+        set_program_entry(env, &mut program)?;
+        Ok(program)
+    }
+}
+
+/// Loads the program from muasm source code and sets the program entry (if given by the environment).
+#[derive(Default)]
+pub struct LoadMuasm {}
+
+impl LoadMuasm {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a str> for LoadMuasm {
+    type Output = hir::Program;
+
+    fn name(&self) -> &'static str {
+        "Load"
+    }
+
+    fn run(&self, env: &Environment, source: &'a str) -> Result<Self::Output> {
+        let mut program = loader::loader_for_muasm_source(source).load_program()?;
+        set_program_entry(env, &mut program)?;
+        Ok(program)
+    }
+}
+
+/// Loads an (already transformed) program from a file in the textual HIR format.
+///
+/// The loaded program replaces the artifact of `HirTransform`, i.e. it isn't inlined or transformed again.
+#[derive(Default)]
+pub struct LoadHir {}
+
+impl LoadHir {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a Path> for LoadHir {
+    type Output = InlinedProgram;
+
+    fn name(&self) -> &'static str {
+        "LoadHir"
+    }
+
+    fn run(&self, _env: &Environment, input_file: &'a Path) -> Result<Self::Output> {
+        let source = std::fs::read_to_string(input_file)?;
+        hir::text::parse_program(&source, input_file)
+    }
+}
+
+/// File names of the transformed program and the environment within a resume state directory.
+pub const RESUME_PROGRAM_FILE: &str = "program.hir";
+pub const RESUME_ENVIRONMENT_FILE: &str = "environment.yaml";
+
+/// Writes the transformed program (textual HIR) and the environment into `dir`,
+/// such that the analysis can be resumed (see `LoadHir`) without repeating the front-end work.
+pub fn write_resume_state(env: &Environment, program: &InlinedProgram, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    hir::text::print_program(program)?.dump_to_file(&dir.join(RESUME_PROGRAM_FILE))?;
+    env.dump_to_file(&dir.join(RESUME_ENVIRONMENT_FILE))
+}
+
+fn set_program_entry(env: &Environment, program: &mut hir::Program) -> Result<()> {
+    if let Some(entry) = &env.analysis.program_entry {
+        program.set_entry(hir::ProgramEntry::parse(entry))?;
+    }
+    Ok(())
+}
+
+/// Resolves the environment settings which depend on the loaded program,
+/// i.e. memory regions given by symbol names, relocated pointers and initialized data.
+pub fn resolve_environment(env: &mut Environment, program: &hir::Program) -> Result<()> {
+    let lookup = |name: &str| {
+        program
+            .memory()
+            .symbol(name)
+            .map(|symbol| (symbol.address(), symbol.size()))
+    };
+    env.policy.memory.resolve_symbols(lookup)?;
+    env.analysis.valid_memory = env
+        .analysis
+        .valid_memory
+        .iter()
+        .map(|region| region.resolve(lookup))
+        .collect::<Result<_>>()?;
+    env.setup.mmio = env
+        .setup
+        .mmio
+        .iter()
+        .map(|region| region.resolve(lookup))
+        .collect::<Result<_>>()?;
+
+    if env.setup.apply_relocations {
+        // Initial memory given by the user takes precedence over relocated pointers
+        for (&location, &value) in program.memory().relocations() {
+            env.setup
+                .memory_content
+                .entry(location)
+                .or_insert_with(|| value.to_le_bytes().to_vec());
+        }
+    }
+
+    for (&address, bytes) in program.memory().data() {
+        env.setup
+            .memory_content
+            .entry(address)
+            .or_insert_with(|| bytes.clone());
+    }
+
+    Ok(())
+}
+
+/// Inlines all functions into the program entry.
+#[derive(Default)]
+pub struct Inline {}
+
+impl Inline {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a hir::Program> for Inline {
+    type Output = InlinedProgram;
+
+    fn name(&self) -> &'static str {
+        "Inline"
+    }
+
+    fn run(&self, env: &Environment, program: &'a hir::Program) -> Result<Self::Output> {
+        let function_inlining = hir::transformation::FunctionInliningBuilder::default()
+            .recursion_limit(env.analysis.recursion_limit)
+            .max_call_depth(env.analysis.max_call_depth)
+            .max_instructions(env.analysis.max_inlined_instructions)
+            .budget_policy(env.analysis.inlining_budget_exceeded)
+            .ignored_functions(env.analysis.inline_ignore.clone())
+            .resolve_indirect_calls(env.analysis.resolve_indirect_calls)
+            .external_call_policy(env.analysis.external_calls)
+            .clobbered_registers(
+                env.analysis
+                    .calling_convention
+                    .caller_saved_registers()
+                    .iter()
+                    .map(|register| register.to_string())
+                    .collect(),
+            )
+            .clobbered_memory(env.analysis.external_call_memory.iter().cloned().collect())
+            .havoc_low(env.analysis.havoc_low)
+            .call_summaries(call_summaries(env))
+            .heap(env.analysis.heap.clone())
+            .calling_convention(env.analysis.calling_convention)
+            .build()
+            .unwrap();
+        let mut inlined_program = function_inlining.inline(program)?;
+        inlined_program.set_self_compositions(env.analysis.self_compositions);
+
+        if env.analysis.modular {
+            let contract = program
+                .entry_function()
+                .and_then(|func| func.name())
+                .and_then(|name| env.analysis.contracts.get(name));
+            if let Some(contract) = contract {
+                add_postcondition(&mut inlined_program, &contract.ensures_low)?;
+            }
+        }
+
+        Ok(inlined_program)
+    }
+}
+
+/// In modular mode, calls of functions with a contract are summarized by their postcondition.
+fn call_summaries(env: &Environment) -> HashMap<String, HashSet<String>> {
+    if !env.analysis.modular {
+        return HashMap::new();
+    }
+    env.analysis
+        .contracts
+        .iter()
+        .map(|(name, contract)| (name.clone(), contract.ensures_low.clone()))
+        .collect()
+}
+
+/// Makes the given registers observable at the end of the program,
+/// such that they must be equal in all self-compositions on return.
+fn add_postcondition(program: &mut InlinedProgram, registers: &HashSet<String>) -> Result<()> {
+    let cfg = program.control_flow_graph_mut();
+    let exit = cfg.exit()?;
+    let block = cfg.block_mut(exit)?;
+
+    let mut registers: Vec<&String> = registers.iter().collect();
+    registers.sort();
+    for register in registers {
+        let inst = block.observable(expr::BitVector::word_variable(register).into());
+        inst.labels_mut().pseudo();
+        inst.set_provenance(Provenance::observation().with_transform("Postcondition"));
+    }
+    Ok(())
+}
+
+/// Callback which is invoked after each HIR transformation with
+/// the (1-based) step, the number of steps, the applied transformation and the transformed program.
+pub type TransformObserver =
+    Box<dyn Fn(usize, usize, &dyn Transform<InlinedProgram>, &InlinedProgram) -> Result<()>>;
+
+/// Applies the HIR transformations, by default the ones given by the environment.
+#[derive(Default)]
+pub struct HirTransform {
+    transformations: Option<Vec<Box<dyn Transform<InlinedProgram>>>>,
+    observation_model: Option<Box<dyn hir::transformation::ObservationModel>>,
+    observer: Option<TransformObserver>,
+}
+
+impl HirTransform {
+    /// Applies the transformations given by the environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the given transformations instead of the ones given by the environment.
+    pub fn with_transformations(
+        mut self,
+        transformations: Vec<Box<dyn Transform<InlinedProgram>>>,
+    ) -> Self {
+        self.transformations = Some(transformations);
+        self
+    }
+
+    /// Observes according to the given (e.g. custom) model instead of the one selected by `analysis.model`.
+    pub fn with_observation_model(
+        mut self,
+        model: Box<dyn hir::transformation::ObservationModel>,
+    ) -> Self {
+        self.observation_model = Some(model);
+        self
+    }
+
+    /// Sets the callback which is invoked after each transformation.
+    pub fn with_observer<F>(mut self, observer: F) -> Self
+    where
+        F: Fn(usize, usize, &dyn Transform<InlinedProgram>, &InlinedProgram) -> Result<()>
+            + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    fn apply(
+        &self,
+        env: &Environment,
+        transformations: &[Box<dyn Transform<InlinedProgram>>],
+        program: &mut InlinedProgram,
+    ) -> Result<()> {
+        for (idx, transformation) in transformations.iter().enumerate() {
+            transformation.transform(program)?;
+            hir::transformation::check_block_budget(
+                program.control_flow_graph(),
+                0,
+                env.analysis.max_blocks,
+                &format!("Number of blocks after {}", transformation.name()),
+            )?;
+            if let Some(observer) = &self.observer {
+                observer(
+                    idx + 1,
+                    transformations.len(),
+                    transformation.as_ref(),
+                    program,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stage<InlinedProgram> for HirTransform {
+    type Output = InlinedProgram;
+
+    fn name(&self) -> &'static str {
+        "HirTransform"
+    }
+
+    fn run(&self, env: &Environment, mut program: InlinedProgram) -> Result<Self::Output> {
+        match &self.transformations {
+            Some(transformations) => self.apply(env, transformations, &mut program)?,
+            None => {
+                let transformations = match &self.observation_model {
+                    Some(model) => {
+                        hir::transformation::create_transformations_with(env, model.as_ref())?
+                    }
+                    None => hir::transformation::create_transformations(env)?,
+                };
+                self.apply(env, &transformations, &mut program)?;
+            }
+        }
+        Ok(program)
+    }
+}
+
+/// Translates the (transformed) HIR program into MIR.
+#[derive(Default)]
+pub struct ToMir {}
+
+impl ToMir {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a InlinedProgram> for ToMir {
+    type Output = mir::Program;
+
+    fn name(&self) -> &'static str {
+        "ToMir"
+    }
+
+    fn run(&self, _env: &Environment, program: &'a InlinedProgram) -> Result<Self::Output> {
+        mir::Program::try_translate_from(program)
+    }
+}
+
+/// Translates different (transformed) programs into their product program.
+impl<'a> Stage<&'a [InlinedProgram]> for ToMir {
+    type Output = mir::Program;
+
+    fn name(&self) -> &'static str {
+        "ToMir"
+    }
+
+    fn run(&self, _env: &Environment, programs: &'a [InlinedProgram]) -> Result<Self::Output> {
+        programs.try_translate_into()
+    }
+}
+
+/// Translates the MIR program into (validated) LIR.
+#[derive(Default)]
+pub struct ToLir {}
+
+impl ToLir {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a mir::Program> for ToLir {
+    type Output = lir::Program;
+
+    fn name(&self) -> &'static str {
+        "ToLir"
+    }
+
+    fn run(&self, env: &Environment, program: &'a mir::Program) -> Result<Self::Output> {
+        let program = lir::Program::try_translate_from(program)?;
+        if let Some(limit) = env.analysis.max_lir_nodes {
+            let measured = program.nodes().len();
+            if measured > limit {
+                return Err(Error::BudgetExceeded {
+                    what: "Number of LIR nodes".to_owned(),
+                    budget: "analysis.max_lir_nodes",
+                    measured,
+                    limit,
+                    hint:
+                        "consider a smaller `analysis.unwind` or `architecture.speculation_window`",
+                });
+            }
+        }
+        program.validate()?;
+        Ok(program)
+    }
+}
+
+/// Optimizes the LIR program according to the optimization level of the environment.
+#[derive(Default)]
+pub struct Optimize {}
+
+impl Optimize {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Stage<lir::Program> for Optimize {
+    type Output = lir::Program;
+
+    fn name(&self) -> &'static str {
+        "Optimize"
+    }
+
+    fn run(&self, env: &Environment, mut program: lir::Program) -> Result<Self::Output> {
+        lir::optimization::Optimizer::new_from_env(env)?.optimize(&mut program)?;
+        Ok(program)
+    }
+}
+
+/// Infers the security levels of the transformed HIR program.
+///
+/// The program is secure if the type check succeeds, otherwise it has to be checked by the solver.
+#[derive(Default)]
+pub struct TypeCheck {}
+
+impl TypeCheck {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a InlinedProgram> for TypeCheck {
+    type Output = hir::analysis::SecurityLevels;
+
+    fn name(&self) -> &'static str {
+        "TypeCheck"
+    }
+
+    fn run(&self, _env: &Environment, program: &'a InlinedProgram) -> Result<Self::Output> {
+        hir::analysis::security_levels(program.control_flow_graph())
+    }
+}
+
+/// Executes the LIR program on random inputs and predictor choices.
+///
+/// Returns a counterexample if any run violates an assertion, otherwise `None`
+/// (which doesn't mean that the assertions hold).
+#[derive(Default)]
+pub struct Simulate {}
+
+impl Simulate {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a lir::Program> for Simulate {
+    type Output = Option<CheckResult>;
+
+    fn name(&self) -> &'static str {
+        "Simulate"
+    }
+
+    fn run(&self, env: &Environment, program: &'a lir::Program) -> Result<Self::Output> {
+        // The simulated memory holds bytes
+        if env.architecture.memory_cell_width != 8 {
+            return Ok(None);
+        }
+
+        let model = solver::simulate(
+            program,
+            env.analysis.simulation_runs,
+            env.analysis.simulation_seed,
+            env.architecture.endianness,
+        );
+        Ok(model.map(|model| CheckResult::AssertionViolated {
+            model: Box::new(model),
+        }))
+    }
+}
+
+/// Encodes the LIR program into a fresh solver instance.
+#[derive(Default)]
+pub struct Encode {
+    formula_file: Option<PathBuf>,
+}
+
+impl Encode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Additionally writes the SMT formula into the given file.
+    pub fn with_formula_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.formula_file = Some(path.into());
+        self
+    }
+}
+
+impl<'a> Stage<&'a lir::Program> for Encode {
+    type Output = Box<dyn Solver>;
+
+    fn name(&self) -> &'static str {
+        "Encode"
+    }
+
+    fn run(&self, env: &Environment, program: &'a lir::Program) -> Result<Self::Output> {
+        let mut solver = create_solver(env)?;
+        if let Some(path) = &self.formula_file {
+            solver.dump_formula_to_file(path)?;
+        }
+        solver.encode_program(program)?;
+        Ok(solver)
+    }
+}
+
+/// Checks whether the encoded assertions hold.
+#[derive(Default)]
+pub struct Solve {}
+
+impl Solve {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl<'a> Stage<&'a mut dyn Solver> for Solve {
+    type Output = CheckResult;
+
+    fn name(&self) -> &'static str {
+        "Solve"
+    }
+
+    fn run(&self, _env: &Environment, solver: &'a mut dyn Solver) -> Result<Self::Output> {
+        solver.check_assertions()
+    }
+}
+
+/// Runs all stages after inlining on `program` without any output.
+///
+/// Returns the transformed HIR program (required to build counterexamples)
+/// along with the check result.
+pub fn verify(env: &Environment, program: InlinedProgram) -> Result<(InlinedProgram, CheckResult)> {
+    let program = HirTransform::new().run(env, program)?;
+    if env.analysis.static_check && TypeCheck::new().run(env, &program)?.is_secure() {
+        return Ok((program, CheckResult::AssertionsHold));
+    }
+    let mir_program = ToMir::new().run(env, &program)?;
+    let lir_program = ToLir::new().run(env, &mir_program)?;
+    let lir_program = Optimize::new().run(env, lir_program)?;
+    if let Some(result) = Simulate::new().run(env, &lir_program)? {
+        return Ok((program, result));
+    }
+    let mut solver = Encode::new().run(env, &lir_program)?;
+    let result = Solve::new().run(env, solver.as_mut())?;
+    Ok((program, result))
+}
+
+/// Checks whether the given (transformed) programs are observationally equivalent, i.e. produce the same
+/// observations for the same low inputs, by running all stages after `HirTransform` on their product program.
+///
+/// Counterexamples can't be built for product programs, therefore they aren't generated.
+pub fn verify_product(env: &Environment, programs: &[InlinedProgram]) -> Result<CheckResult> {
+    let mut env = env.clone();
+    env.generate_counterexample = false;
+
+    let mir_program = ToMir::new().run(&env, programs)?;
+    let lir_program = ToLir::new().run(&env, &mir_program)?;
+    let lir_program = Optimize::new().run(&env, lir_program)?;
+    if let Some(result) = Simulate::new().run(&env, &lir_program)? {
+        return Ok(result);
+    }
+    let mut solver = Encode::new().run(&env, &lir_program)?;
+    Solve::new().run(&env, solver.as_mut())
+}
+
+/// Checks only the observations at the given (source) addresses of `program`,
+/// which are placed in front of the instructions at these addresses in addition to the usual observations.
+/// All other assertions are assumed to hold.
+///
+/// Returns `None` if the transformed program has no observation at any of the addresses.
+pub fn verify_observations_at(
+    env: &Environment,
+    program: InlinedProgram,
+    addresses: &BTreeSet<u64>,
+) -> Result<Option<CheckResult>> {
+    let mut env = env.clone();
+    env.analysis.observe_at.extend(addresses.iter().copied());
+
+    let program = HirTransform::new().run(&env, program)?;
+    let mir_program = ToMir::new().run(&env, &program)?;
+    let lir_program = ToLir::new().run(&env, &mir_program)?;
+    let lir_program = Optimize::new().run(&env, lir_program)?;
+
+    let is_checked = |provenance: &Provenance| {
+        provenance.kind() == AssertionKind::Observation
+            && provenance
+                .address()
+                .map_or(false, |address| addresses.contains(&address))
+    };
+    if !lir_program.assertions().into_iter().any(is_checked) {
+        return Ok(None);
+    }
+
+    let mut solver = Encode::new().run(&env, &lir_program)?;
+    assume_warnings_hold(solver.as_mut(), &lir_program, is_checked)?;
+    Solve::new().run(&env, solver.as_mut()).map(Some)
+}
+
+/// Returns the provenance of all assertions of `program` which don't hold in the given model.
+pub fn violated_assertions<'a>(
+    program: &'a lir::Program,
+    model: &dyn solver::Model,
+) -> Vec<&'a Provenance> {
+    program
+        .assertions()
+        .into_iter()
+        .enumerate()
+        .filter(|(index, provenance)| {
+            let assertion =
+                expr::Variable::new(provenance.assertion_name(*index), expr::Sort::boolean());
+            model.get_interpretation(&assertion) == Some(expr::Constant::boolean(false))
+        })
+        .map(|(_, provenance)| provenance)
+        .collect()
+}
+
+/// Assumes all assertions of the encoded `program` to hold whose violation isn't failing (warnings only),
+/// such that a check only finds failing violations.
+pub fn assume_warnings_hold<F>(
+    solver: &mut dyn Solver,
+    program: &lir::Program,
+    is_failing: F,
+) -> Result<()>
+where
+    F: Fn(&Provenance) -> bool,
+{
+    for (index, provenance) in program.assertions().into_iter().enumerate() {
+        if !is_failing(provenance) {
+            let assertion =
+                expr::Variable::new(provenance.assertion_name(index), expr::Sort::boolean());
+            solver.assume(&assertion.into())?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the instances of the speculation window bound of a program encoded with a parametric window.
+pub fn speculation_window_bounds(program: &lir::Program) -> Vec<&expr::Variable> {
+    let bound = hir::transformation::TransientExecution::speculation_window_bound_variable();
+    program
+        .nodes()
+        .iter()
+        .filter_map(|node| match node {
+            lir::Node::Let { var, .. } if var.name() == bound.name() => Some(var),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks the encoded `program` for the given speculation window within its own solver scope.
+///
+/// Returns true if a failing assertion (`is_failing`) is violated.
+pub fn leaks_for_speculation_window<F>(
+    solver: &mut dyn Solver,
+    program: &lir::Program,
+    bound_instances: &[&expr::Variable],
+    window: usize,
+    is_failing: F,
+) -> Result<bool>
+where
+    F: Fn(&Provenance) -> bool,
+{
+    solver.push()?;
+
+    let value = expr::BitVector::constant_u64(window as u64, SPECULATION_WINDOW_SIZE);
+    for &var in bound_instances {
+        solver.assume(&expr::Expression::equal(var.clone().into(), value.clone())?)?;
+    }
+    assume_warnings_hold(solver, program, is_failing)?;
+
+    let leaks = match solver.check_assertions()? {
+        CheckResult::AssertionsHold => false,
+        CheckResult::AssertionViolated { .. } => true,
+    };
+
+    solver.pop()?;
+
+    Ok(leaks)
+}
+
+/// Checks the encoded `program` for each of the given speculation windows,
+/// each within its own solver scope of the program encoded once with a parametric speculation window.
+///
+/// Returns the windows for which a failing assertion (`is_failing`) is violated.
+/// `observer` is notified of the result of each checked window.
+pub fn sweep_speculation_windows<F, O>(
+    solver: &mut dyn Solver,
+    program: &lir::Program,
+    windows: &[usize],
+    is_failing: F,
+    mut observer: O,
+) -> Result<Vec<usize>>
+where
+    F: Fn(&Provenance) -> bool,
+    O: FnMut(usize, bool),
+{
+    let bound_instances = speculation_window_bounds(program);
+
+    let mut leaking_windows = Vec::new();
+    for &window in windows {
+        let leaks =
+            leaks_for_speculation_window(solver, program, &bound_instances, window, &is_failing)?;
+        observer(window, leaks);
+        if leaks {
+            leaking_windows.push(window);
+        }
+    }
+
+    Ok(leaking_windows)
+}
+
+/// Searches the smallest speculation window up to `max_window` for which the encoded `program` violates
+/// a failing assertion (`is_failing`), or `None` if it doesn't for `max_window`.
+///
+/// Larger speculation windows only add transient behavior, hence the windows are searched by binary search
+/// on a program encoded once with a parametric speculation window.
+/// `observer` is notified of the result of each checked window.
+pub fn min_leaking_speculation_window<F, O>(
+    solver: &mut dyn Solver,
+    program: &lir::Program,
+    max_window: usize,
+    is_failing: F,
+    mut observer: O,
+) -> Result<Option<usize>>
+where
+    F: Fn(&Provenance) -> bool,
+    O: FnMut(usize, bool),
+{
+    let bound_instances = speculation_window_bounds(program);
+
+    if !leaks_for_speculation_window(solver, program, &bound_instances, max_window, &is_failing)? {
+        return Ok(None);
+    }
+
+    // Invariant: the program leaks for `high`, but not for any window below `low`
+    let mut low = 0;
+    let mut high = max_window;
+    while low < high {
+        let window = low + (high - low) / 2;
+        let leaks =
+            leaks_for_speculation_window(solver, program, &bound_instances, window, &is_failing)?;
+        observer(window, leaks);
+        if leaks {
+            high = window;
+        } else {
+            low = window + 1;
+        }
+    }
+
+    Ok(Some(high))
+}
+
+/// Unwinding bounds found by `search_unwinding_bounds` along with the artifacts of the final check.
+pub struct UnwindingBounds {
+    /// Environment with the found bounds, it keeps unwinding assertions as the artifacts are built with them
+    pub env: Environment,
+    /// Transformed HIR program
+    pub program: InlinedProgram,
+    pub mir_program: mir::Program,
+    /// Optimized LIR program
+    pub lir_program: lir::Program,
+    /// Solver with the encoded LIR program, `None` if the result was found by simulation
+    pub solver: Option<Box<dyn Solver>>,
+    pub result: CheckResult,
+}
+
+/// Searches unwinding bounds for which no unwinding assertion of `program` is violated,
+/// starting with the bounds of `env`.
+///
+/// The program is checked with unwinding assertions, loops whose unwinding assertion is violated
+/// are unwound once more and the program is checked again. This stops as soon as no unwinding assertion
+/// is violated anymore (either the program is safe or another assertion is violated within the bounds)
+/// and fails if a loop would have to be unwound more than `max_bound` times.
+///
+/// `observer` is notified before each check. Returns the found bounds along with the artifacts of
+/// the final check, such that they don't have to be rebuilt.
+pub fn search_unwinding_bounds<F>(
+    env: &Environment,
+    program: &InlinedProgram,
+    max_bound: usize,
+    mut observer: F,
+) -> Result<UnwindingBounds>
+where
+    F: FnMut(&Environment),
+{
+    let mut env = env.clone();
+    env.analysis.unwinding_guard = UnwindingGuard::Assertion;
+
+    loop {
+        observer(&env);
+
+        let transformed_program = HirTransform::new().run(&env, program.clone())?;
+        let mir_program = ToMir::new().run(&env, &transformed_program)?;
+        let lir_program = ToLir::new().run(&env, &mir_program)?;
+        let lir_program = Optimize::new().run(&env, lir_program)?;
+        let (solver, result) = match Simulate::new().run(&env, &lir_program)? {
+            Some(result) => (None, result),
+            None => {
+                let mut solver = Encode::new().run(&env, &lir_program)?;
+                let result = Solve::new().run(&env, solver.as_mut())?;
+                (Some(solver), result)
+            }
+        };
+
+        let violated = match &result {
+            CheckResult::AssertionsHold => Vec::new(),
+            CheckResult::AssertionViolated { model } => {
+                violated_assertions(&lir_program, model.as_ref())
+            }
+        };
+        let only_unwinding_violated = !violated.is_empty()
+            && violated
+                .iter()
+                .all(|provenance| provenance.kind() == AssertionKind::Unwinding);
+        if !only_unwinding_violated {
+            return Ok(UnwindingBounds {
+                env,
+                program: transformed_program,
+                mir_program,
+                lir_program,
+                solver,
+                result,
+            });
+        }
+
+        let unwinding_addresses: BTreeSet<Option<u64>> = violated
+            .iter()
+            .map(|provenance| provenance.address())
+            .collect();
+
+        let loops = unwinding_loops(&transformed_program, &unwinding_addresses);
+        if loops.is_empty() {
+            // Unwinding assertion which can't be attributed to a loop, unwind all loops once more
+            if env.analysis.unwind >= max_bound {
+                return Err(Error::UnwindingBoundExceeded {
+                    loop_id: None,
+                    max_bound,
+                });
+            }
+            env.analysis.unwind += 1;
+            continue;
+        }
+        for loop_id in loops {
+            let bound = env
+                .analysis
+                .unwind_loop
+                .get(&loop_id)
+                .copied()
+                .unwrap_or(env.analysis.unwind);
+            if bound >= max_bound {
+                return Err(Error::UnwindingBoundExceeded {
+                    loop_id: Some(loop_id),
+                    max_bound,
+                });
+            }
+            env.analysis.unwind_loop.insert(loop_id, bound + 1);
+        }
+    }
+}
+
+/// Returns the IDs of the loops whose unwinding assertions are located at the given addresses.
+///
+/// Unwinding assertions are added to the last iteration of the loop body,
+/// its innermost loop is recorded as first loop iteration origin of the block's instructions.
+fn unwinding_loops(program: &InlinedProgram, addresses: &BTreeSet<Option<u64>>) -> BTreeSet<usize> {
+    let mut loops = BTreeSet::new();
+    for block in program.control_flow_graph().blocks() {
+        let has_unwinding_assertion = block.instructions().iter().any(|inst| {
+            inst.operation().is_assert()
+                && inst.provenance().map_or(false, |provenance| {
+                    provenance.kind() == AssertionKind::Unwinding
+                        && addresses.contains(&provenance.address())
+                })
+        });
+        if !has_unwinding_assertion {
+            continue;
+        }
+        let loop_id = block
+            .instructions()
+            .iter()
+            .flat_map(hir::Instruction::origins)
+            .find_map(|origin| match origin {
+                hir::Origin::LoopIteration { loop_id, .. } => Some(*loop_id),
+                _ => None,
+            });
+        loops.extend(loop_id);
+    }
+    loops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{AssertionCheck, Certify, DumpFormula, Model, SolverSession};
+
+    struct NoModel {}
+
+    impl Model for NoModel {
+        fn get_interpretation(&self, _variable: &expr::Variable) -> Option<expr::Constant> {
+            None
+        }
+
+        fn evaluate(&self, _expr: &expr::Expression) -> Option<expr::Constant> {
+            None
+        }
+    }
+
+    /// Leaks for all speculation windows >= `min_leaking_window`.
+    struct WindowSolver {
+        min_leaking_window: usize,
+        windows: Vec<Option<usize>>,
+        checked: Vec<usize>,
+    }
+
+    impl AssertionCheck for WindowSolver {
+        fn encode_program(&mut self, _program: &lir::Program) -> Result<()> {
+            Ok(())
+        }
+
+        fn check_assertions(&mut self) -> Result<CheckResult> {
+            let window = self
+                .windows
+                .iter()
+                .rev()
+                .find_map(|window| *window)
+                .unwrap();
+            self.checked.push(window);
+            if window >= self.min_leaking_window {
+                Ok(CheckResult::AssertionViolated {
+                    model: Box::new(NoModel {}),
+                })
+            } else {
+                Ok(CheckResult::AssertionsHold)
+            }
+        }
+    }
+
+    impl SolverSession for WindowSolver {
+        fn push(&mut self) -> Result<()> {
+            self.windows.push(None);
+            Ok(())
+        }
+
+        fn pop(&mut self) -> Result<()> {
+            self.windows.pop();
+            Ok(())
+        }
+
+        fn assume(&mut self, condition: &expr::Expression) -> Result<()> {
+            let bound =
+                hir::transformation::TransientExecution::speculation_window_bound_variable();
+            let window = (0..=100).find(|&window| {
+                *condition
+                    == expr::Expression::equal(
+                        bound.clone().into(),
+                        expr::BitVector::constant_u64(window as u64, SPECULATION_WINDOW_SIZE),
+                    )
+                    .unwrap()
+            });
+            *self.windows.last_mut().unwrap() = window;
+            Ok(())
+        }
+    }
+
+    impl DumpFormula for WindowSolver {
+        fn dump_formula_to_file(&self, _path: &Path) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Certify for WindowSolver {
+        fn solver_version(&self) -> Result<String> {
+            Ok("window solver".to_owned())
+        }
+
+        fn unsat_proof(&self) -> Result<Option<String>> {
+            Ok(None)
+        }
+    }
+
+    fn parametric_program() -> lir::Program {
+        let bound = hir::transformation::TransientExecution::speculation_window_bound_variable();
+        let mut program = lir::Program::new();
+        program
+            .assign(
+                bound,
+                expr::BitVector::constant_u64(100, SPECULATION_WINDOW_SIZE),
+            )
+            .unwrap();
+        program
+    }
+
+    #[test]
+    fn test_min_leaking_speculation_window_is_found_by_binary_search() {
+        // Given: a program which leaks for speculation windows >= 37
+        let program = parametric_program();
+        let mut solver = WindowSolver {
+            min_leaking_window: 37,
+            windows: Vec::new(),
+            checked: Vec::new(),
+        };
+
+        // When:
+        let window =
+            min_leaking_speculation_window(&mut solver, &program, 100, |_| true, |_, _| ())
+                .unwrap();
+
+        // Then:
+        assert_eq!(window, Some(37));
+        assert!(solver.checked.len() <= 1 + 7);
+        assert!(solver.windows.is_empty());
+    }
+
+    #[test]
+    fn test_sweep_checks_each_window_in_its_own_scope() {
+        // Given: a program which leaks for speculation windows >= 20
+        let program = parametric_program();
+        let mut solver = WindowSolver {
+            min_leaking_window: 20,
+            windows: Vec::new(),
+            checked: Vec::new(),
+        };
+        let mut observed = Vec::new();
+
+        // When:
+        let leaking = sweep_speculation_windows(
+            &mut solver,
+            &program,
+            &[30, 10, 20],
+            |_| true,
+            |window, leaks| observed.push((window, leaks)),
+        )
+        .unwrap();
+
+        // Then:
+        assert_eq!(leaking, vec![30, 20]);
+        assert_eq!(observed, vec![(30, true), (10, false), (20, true)]);
+        assert_eq!(solver.checked, vec![30, 10, 20]);
+        assert!(solver.windows.is_empty());
+    }
+
+    #[test]
+    fn test_no_leaking_speculation_window_below_max_window() {
+        // Given: a program which only leaks for speculation windows larger than the max. window
+        let program = parametric_program();
+        let mut solver = WindowSolver {
+            min_leaking_window: 101,
+            windows: Vec::new(),
+            checked: Vec::new(),
+        };
+
+        // When:
+        let window =
+            min_leaking_speculation_window(&mut solver, &program, 100, |_| true, |_, _| ())
+                .unwrap();
+
+        // Then:
+        assert_eq!(window, None);
+        assert_eq!(solver.checked, vec![100]);
+    }
+
+    /// Spectre v1 bounds check bypass
+    const SPECTRE_V1: &str = "    cond <- x < array1_size
+    beqz cond, EndIf
+Then:
+    load v, array1 + x
+EndIf:
+    load tmp, array2 + v << 8
+";
+
+    fn cache_only_env() -> Environment {
+        let mut env = Environment::default();
+        env.architecture.branch_target_buffer = false;
+        env.architecture.pattern_history_table = false;
+        env
+    }
+
+    fn transform(env: &Environment, source: &str) -> InlinedProgram {
+        let program = LoadMuasm::new().run(env, source).unwrap();
+        let program = Inline::new().run(env, &program).unwrap();
+        HirTransform::new().run(env, program).unwrap()
+    }
+
+    fn translate(env: &Environment, program: &InlinedProgram) -> lir::Program {
+        let mir_program = ToMir::new().run(env, program).unwrap();
+        let lir_program = ToLir::new().run(env, &mir_program).unwrap();
+        Optimize::new().run(env, lir_program).unwrap()
+    }
+
+    #[test]
+    fn test_stages_translate_muasm_source_into_lir() {
+        // Given:
+        let env = cache_only_env();
+
+        // When:
+        let program = transform(&env, SPECTRE_V1);
+        let lir_program = translate(&env, &program);
+
+        // Then: the cache observations of the transient load are asserted to be indistinguishable
+        assert!(!lir_program.assertions().is_empty());
+        assert!(lir_program
+            .assertions()
+            .iter()
+            .all(|provenance| provenance.kind() != AssertionKind::Unwinding));
+    }
+
+    #[test]
+    fn test_loaded_hir_program_is_translated_like_the_transformed_program() {
+        // Given: the transformed program written in the textual HIR format
+        let env = cache_only_env();
+        let program = transform(&env, SPECTRE_V1);
+        let path =
+            std::env::temp_dir().join(format!("specbmc-pipeline-{}.hir", std::process::id()));
+        std::fs::write(&path, hir::text::print_program(&program).unwrap()).unwrap();
+
+        // When:
+        let loaded = LoadHir::new().run(&env, &path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        // Then:
+        assert_eq!(
+            translate(&env, &loaded).assertions(),
+            translate(&env, &program).assertions()
+        );
+    }
+
+    #[test]
+    fn test_analysis_resumes_from_written_state() {
+        // Given:
+        let mut env = cache_only_env();
+        env.solver_fallbacks = vec![crate::environment::Solver::CVC4];
+        let program = transform(&env, SPECTRE_V1);
+        let dir =
+            std::env::temp_dir().join(format!("specbmc-resume-{}.resume", std::process::id()));
+
+        // When:
+        write_resume_state(&env, &program, &dir).unwrap();
+        let resumed_env = Environment::from_file(&dir.join(RESUME_ENVIRONMENT_FILE));
+        let resumed_program = LoadHir::new().run(&env, &dir.join(RESUME_PROGRAM_FILE));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Then: the resumed analysis checks the same assertions with the same environment
+        let resumed_env = resumed_env.unwrap();
+        assert_eq!(resumed_env.solver_fallbacks, env.solver_fallbacks);
+        assert!(!resumed_env.architecture.branch_target_buffer);
+        assert!(!resumed_env.architecture.pattern_history_table);
+        assert_eq!(
+            translate(&resumed_env, &resumed_program.unwrap()).assertions(),
+            translate(&env, &program).assertions()
+        );
+    }
+
+    #[test]
+    fn test_no_observation_is_checked_at_unknown_addresses() {
+        // Given:
+        let env = cache_only_env();
+        let program = LoadMuasm::new().run(&env, SPECTRE_V1).unwrap();
+        let program = Inline::new().run(&env, &program).unwrap();
+
+        // When:
+        let result =
+            verify_observations_at(&env, program, &vec![0x4200].into_iter().collect()).unwrap();
+
+        // Then: the program isn't encoded at all
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_transformation_exceeding_the_block_budget_fails() {
+        // Given:
+        let mut env = cache_only_env();
+        env.analysis.max_blocks = Some(2);
+        let program = LoadMuasm::new().run(&env, SPECTRE_V1).unwrap();
+        let program = Inline::new().run(&env, &program).unwrap();
+
+        // When:
+        let result = HirTransform::new().run(&env, program);
+
+        // Then:
+        assert!(matches!(
+            result,
+            Err(Error::BudgetExceeded {
+                budget: "analysis.max_blocks",
+                limit: 2,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_translation_exceeding_the_lir_node_budget_fails() {
+        // Given:
+        let mut env = cache_only_env();
+        let program = transform(&env, SPECTRE_V1);
+        let mir_program = ToMir::new().run(&env, &program).unwrap();
+        env.analysis.max_lir_nodes = Some(1);
+
+        // When:
+        let result = ToLir::new().run(&env, &mir_program);
+
+        // Then:
+        match result {
+            Err(Error::BudgetExceeded {
+                budget,
+                measured,
+                limit,
+                ..
+            }) => {
+                assert_eq!(budget, "analysis.max_lir_nodes");
+                assert!(measured > 1);
+                assert_eq!(limit, 1);
+            }
+            Err(err) => panic!("Expected exceeded LIR node budget, but got {}", err),
+            Ok(_) => panic!("Expected exceeded LIR node budget"),
+        }
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_solve_finds_spectre_v1_leak_unless_fenced() {
+        // Given:
+        let mut env = cache_only_env();
+        env.solver = crate::environment::Solver::Z3;
+        env.solver_backend = crate::environment::SolverBackend::Native;
+        let fenced = SPECTRE_V1.replace("EndIf:\n", "EndIf:\n    spbarr\n");
+
+        // When:
+        let solve = |source: &str| {
+            let lir_program = translate(&env, &transform(&env, source));
+            let mut solver = Encode::new().run(&env, &lir_program).unwrap();
+            Solve::new().run(&env, solver.as_mut()).unwrap()
+        };
+        let leaky_result = solve(SPECTRE_V1);
+        let fenced_result = solve(&fenced);
+
+        // Then:
+        assert!(matches!(
+            leaky_result,
+            CheckResult::AssertionViolated { .. }
+        ));
+        assert!(matches!(fenced_result, CheckResult::AssertionsHold));
+    }
+}
@@ -0,0 +1,272 @@
+//! Repair suggestions for leaking programs.
+//!
+//! A repair patches the (untransformed) program at a set of candidate addresses, e.g. by inserting
+//! speculation barriers, and re-verifies the patched program with the whole pipeline.
+//! Starting with all candidates, each one is dropped if the program stays safe without it.
+//! The resulting patch is minimal under removal (no address can be dropped), but it isn't
+//! necessarily the smallest patch which makes the program safe.
+
+use crate::environment::Environment;
+use crate::error::Result;
+use crate::hir::{self, InlinedProgram};
+use crate::ir::Transform;
+use crate::pipeline;
+use crate::solver::CheckResult;
+use std::collections::BTreeSet;
+
+/// Runs the whole verification pipeline on `program` without any output.
+///
+/// Returns true if the program is free of leaks.
+fn is_program_safe(env: &Environment, program: &InlinedProgram) -> Result<bool> {
+    match pipeline::verify(env, program.clone())? {
+        (_, CheckResult::AssertionsHold) => Ok(true),
+        (_, CheckResult::AssertionViolated { .. }) => Ok(false),
+    }
+}
+
+/// Searches a subset of `candidates` for which the patched program is safe (`is_safe`)
+/// and from which no address can be removed without losing safety.
+///
+/// Starts with all candidates and greedily removes each one which isn't required to keep the program safe,
+/// hence the patch is minimal under removal but not necessarily minimum in size.
+/// Returns `None` if even patching all candidates doesn't remove the leak.
+pub fn minimal_patch<F>(candidates: BTreeSet<u64>, is_safe: F) -> Result<Option<BTreeSet<u64>>>
+where
+    F: Fn(&BTreeSet<u64>) -> Result<bool>,
+{
+    if !is_safe(&candidates)? {
+        return Ok(None);
+    }
+
+    let mut patch = candidates.clone();
+    for address in candidates {
+        patch.remove(&address);
+        if !is_safe(&patch)? {
+            patch.insert(address);
+        }
+    }
+
+    Ok(Some(patch))
+}
+
+/// Searches addresses at which inserting speculation barriers makes the program safe,
+/// such that no barrier can be removed (see `minimal_patch`).
+pub fn suggest_fences(
+    env: &Environment,
+    program: &InlinedProgram,
+) -> Result<Option<BTreeSet<u64>>> {
+    let candidates =
+        hir::transformation::BarrierInsertion::candidates(program.control_flow_graph())?;
+
+    minimal_patch(candidates, |addresses| {
+        let mut patched_program = program.clone();
+        hir::transformation::BarrierInsertionBuilder::default()
+            .addresses(addresses.clone())
+            .build()
+            .unwrap()
+            .transform(&mut patched_program)?;
+        is_program_safe(env, &patched_program)
+    })
+}
+
+/// Inserts speculation barriers in front of the instructions at the given addresses,
+/// which allows checking a fence placement before recompiling the binary.
+pub fn insert_fences(
+    mut program: InlinedProgram,
+    addresses: &BTreeSet<u64>,
+) -> Result<InlinedProgram> {
+    let instruction_addresses: BTreeSet<u64> = program
+        .control_flow_graph()
+        .blocks()
+        .iter()
+        .flat_map(|block| block.instructions())
+        .filter_map(hir::Instruction::address)
+        .collect();
+    if let Some(address) = addresses.difference(&instruction_addresses).next() {
+        return Err(format!(
+            "Can't insert fence at 0x{:X}, the program has no instruction at this address",
+            address
+        )
+        .into());
+    }
+
+    hir::transformation::BarrierInsertionBuilder::default()
+        .addresses(addresses.clone())
+        .build()
+        .unwrap()
+        .transform(&mut program)?;
+    Ok(program)
+}
+
+/// Searches loads whose address masking makes the program safe,
+/// such that no masking can be removed (see `minimal_patch`).
+///
+/// Returns the patched program along with the masked load addresses.
+pub fn suggest_index_masking(
+    env: &Environment,
+    program: &InlinedProgram,
+) -> Result<Option<(InlinedProgram, BTreeSet<u64>)>> {
+    let patch = |addresses: &BTreeSet<u64>| -> Result<InlinedProgram> {
+        let mut patched_program = program.clone();
+        hir::transformation::IndexMaskingBuilder::default()
+            .addresses(addresses.clone())
+            .build()
+            .unwrap()
+            .transform(&mut patched_program)?;
+        Ok(patched_program)
+    };
+
+    let candidates = hir::transformation::IndexMasking::candidates(program.control_flow_graph())?;
+
+    match minimal_patch(candidates, |addresses| {
+        is_program_safe(env, &patch(addresses)?)
+    })? {
+        Some(addresses) => Ok(Some((patch(&addresses)?, addresses))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_minimal_patch_drops_redundant_candidates() {
+        // Given: the program is safe iff 2 or both 1 and 3 are patched
+        let is_safe = |patch: &BTreeSet<u64>| {
+            Ok(patch.contains(&2) || (patch.contains(&1) && patch.contains(&3)))
+        };
+
+        // When:
+        let patch = minimal_patch(vec![1, 2, 3].into_iter().collect(), is_safe).unwrap();
+
+        // Then: greedy removal keeps {2}, no address of it can be dropped
+        assert_eq!(patch, Some(vec![2].into_iter().collect()));
+    }
+
+    #[test]
+    fn test_minimal_patch_is_minimal_under_removal_only() {
+        // Given: the program is safe iff 1 or both 2 and 3 are patched
+        let is_safe = |patch: &BTreeSet<u64>| {
+            Ok(patch.contains(&1) || (patch.contains(&2) && patch.contains(&3)))
+        };
+
+        // When:
+        let patch = minimal_patch(vec![1, 2, 3].into_iter().collect(), is_safe).unwrap();
+
+        // Then: {2, 3} can't be reduced, although {1} is smaller
+        assert_eq!(patch, Some(vec![2, 3].into_iter().collect()));
+    }
+
+    #[test]
+    fn test_no_patch_if_all_candidates_are_not_enough() {
+        // Given:
+        let checked = RefCell::new(Vec::new());
+        let is_safe = |patch: &BTreeSet<u64>| {
+            checked.borrow_mut().push(patch.clone());
+            Ok(false)
+        };
+
+        // When:
+        let patch = minimal_patch(vec![1, 2].into_iter().collect(), is_safe).unwrap();
+
+        // Then: only the full patch is checked
+        assert_eq!(patch, None);
+        assert_eq!(checked.into_inner().len(), 1);
+    }
+
+    fn fence_addresses(program: &InlinedProgram) -> Vec<Option<u64>> {
+        program
+            .control_flow_graph()
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions())
+            .filter(|inst| matches!(inst.operation(), hir::Operation::Barrier { .. }))
+            .map(hir::Instruction::address)
+            .collect()
+    }
+
+    #[test]
+    fn test_fences_are_inserted_at_instruction_addresses() {
+        use crate::pipeline::Stage;
+
+        // Given:
+        let env = Environment::default();
+        let program = pipeline::LoadMuasm::new()
+            .run(&env, "    x <- 1\n    load y, x\n")
+            .unwrap();
+        let program = pipeline::Inline::new().run(&env, &program).unwrap();
+
+        // When:
+        let fenced = insert_fences(program.clone(), &vec![1].into_iter().collect()).unwrap();
+        let unknown = insert_fences(program, &vec![1, 7].into_iter().collect());
+
+        // Then:
+        assert_eq!(fence_addresses(&fenced), vec![Some(1)]);
+        assert!(unknown.is_err());
+    }
+
+    #[cfg(feature = "z3")]
+    fn z3_env() -> Environment {
+        let mut env = Environment::default();
+        env.solver = crate::environment::Solver::Z3;
+        env.solver_backend = crate::environment::SolverBackend::Native;
+        env
+    }
+
+    #[cfg(feature = "z3")]
+    fn spectre_v1(env: &Environment) -> InlinedProgram {
+        use crate::pipeline::Stage;
+
+        let source = "    cond <- x < array1_size
+    beqz cond, EndIf
+Then:
+    load v, array1 + x
+EndIf:
+    load tmp, array2 + v << 8
+";
+        let program = pipeline::LoadMuasm::new().run(env, source).unwrap();
+        pipeline::Inline::new().run(env, &program).unwrap()
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_single_fence_is_suggested_for_spectre_v1() {
+        // Given:
+        let env = z3_env();
+        let program = spectre_v1(&env);
+
+        // When:
+        let fences = suggest_fences(&env, &program).unwrap();
+
+        // Then: either fence stops the leak, the greedy search keeps one of them
+        assert_eq!(fences.map(|fences| fences.len()), Some(1));
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_guarded_load_is_masked_for_spectre_v1() {
+        // Given: masked loads read the (public) word at address 0
+        let mut env = z3_env();
+        env.policy
+            .memory
+            .low
+            .insert(crate::environment::MemoryRegion::Range(
+                crate::environment::AddressRange::new(0, 8),
+            ));
+        let program = spectre_v1(&env);
+
+        // When:
+        let masking = suggest_index_masking(&env, &program).unwrap();
+
+        // Then: only the bounds-checked load is masked
+        let (patched_program, addresses) = masking.unwrap();
+        assert_eq!(
+            addresses,
+            hir::transformation::IndexMasking::candidates(program.control_flow_graph()).unwrap()
+        );
+        assert_eq!(addresses.len(), 1);
+        assert!(is_program_safe(&env, &patched_program).unwrap());
+    }
+}
@@ -1,10 +1,22 @@
-use crate::environment::Environment;
-use crate::error::Result;
-use crate::expr::{Constant, Expression, Variable};
+use crate::environment::{self, Environment, SolverBackend};
+use crate::error::{Error, Result};
+use crate::expr::{Cache, Constant, Expression, Memory, Operator, Variable};
 use crate::lir::Program;
+use std::collections::BTreeSet;
 use std::path::Path;
 
+#[cfg(feature = "z3")]
+mod native_z3;
+mod recorded_model;
 mod rsmt;
+mod simplification;
+mod simulation;
+
+pub use self::recorded_model::{ModelRecorder, RecordedModel};
+pub use self::simplification::ExpressionSimplifier;
+#[cfg(test)]
+pub(crate) use self::simulation::{evaluate, Random};
+pub use self::simulation::{simulate, SimulatedModel};
 
 pub trait Model {
     fn get_interpretation(&self, variable: &Variable) -> Option<Constant>;
@@ -21,14 +33,247 @@ pub trait AssertionCheck {
     fn check_assertions(&mut self) -> Result<CheckResult>;
 }
 
+/// Incremental solving on top of an encoded program.
+///
+/// Assumptions made after `push` are discarded by the matching `pop`,
+/// which allows checking variants of the same program without re-encoding it.
+/// Models returned by `check_assertions` are only valid until the next `pop`.
+pub trait SolverSession {
+    fn push(&mut self) -> Result<()>;
+    fn pop(&mut self) -> Result<()>;
+    fn assume(&mut self, condition: &Expression) -> Result<()>;
+}
+
 pub trait DumpFormula {
     fn dump_formula_to_file(&self, path: &Path) -> Result<()>;
 }
 
-pub trait Solver: AssertionCheck + DumpFormula {}
-impl<T: AssertionCheck + DumpFormula> Solver for T {}
+/// Evidence which allows third parties to re-check a result of `check_assertions`.
+pub trait Certify {
+    /// Name and version of the solver.
+    fn solver_version(&self) -> Result<String>;
+    /// Proof of unsatisfiability found by the last check,
+    /// or `None` if the solver (backend) doesn't produce proofs.
+    fn unsat_proof(&self) -> Result<Option<String>>;
+}
+
+pub trait Solver: AssertionCheck + SolverSession + DumpFormula + Certify {}
+impl<T: AssertionCheck + SolverSession + DumpFormula + Certify> Solver for T {}
+
+/// Features of an installed solver (process backend), see `probe_solver`.
+#[derive(Debug, Clone)]
+pub struct SolverFeatures {
+    pub version: String,
+    /// Arrays of bit-vectors (logic `QF_AUFBV`), required by all analyses
+    pub bit_vector_arrays: bool,
+    /// Algebraic datatypes (tuples)
+    pub datatypes: bool,
+    /// Lists, required by unbounded trace observations
+    pub lists: bool,
+}
+
+/// Probes whether the given solver is installed and which features it supports.
+///
+/// Fails if the solver can't be started.
+pub fn probe_solver(solver: environment::Solver) -> Result<SolverFeatures> {
+    rsmt::probe(solver)
+}
 
 pub fn create_solver(env: &Environment) -> Result<Box<dyn Solver>> {
-    let solver = rsmt::RSMTSolver::new_from_env(env)?;
-    Ok(Box::new(solver))
+    match env.solver_backend {
+        SolverBackend::Process => {
+            let solver = rsmt::RSMTSolver::new_from_env(env)?;
+            Ok(Box::new(solver))
+        }
+        #[cfg(feature = "z3")]
+        SolverBackend::Native => {
+            let solver = native_z3::NativeZ3Solver::new_from_env(env)?;
+            Ok(Box::new(solver))
+        }
+        #[cfg(not(feature = "z3"))]
+        SolverBackend::Native => {
+            Err("Native solver backend is not available (compiled without feature 'z3')".into())
+        }
+    }
+}
+
+/// Creates the simplifier used by the solver-backed simplification of the LIR program.
+pub fn create_simplifier(env: &Environment) -> Result<Box<dyn ExpressionSimplifier>> {
+    #[cfg(feature = "z3")]
+    {
+        Ok(Box::new(native_z3::NativeZ3Simplifier::new_from_env(env)))
+    }
+    #[cfg(not(feature = "z3"))]
+    {
+        let _ = env;
+        Err("Solver-backed simplification is not available (compiled without feature 'z3')".into())
+    }
+}
+
+/// Creates a solver process of the given solver instead of the configured one (e.g. as fallback).
+pub fn create_process_solver(
+    env: &Environment,
+    solver: environment::Solver,
+) -> Result<Box<dyn Solver>> {
+    Ok(Box::new(rsmt::RSMTSolver::new(env, solver)?))
+}
+
+/// Retries with the `fallbacks` in order after the solver `failed` with `error`,
+/// as long as each of them fails too (see `Error::is_solver_failure`).
+///
+/// `notify` is called before each retry with the failed solver, its error and the next solver.
+/// Returns the first result which isn't a solver failure, or the last solver failure.
+pub fn solve_with_fallbacks<T, S, N>(
+    failed: environment::Solver,
+    error: Error,
+    fallbacks: &[environment::Solver],
+    mut solve: S,
+    mut notify: N,
+) -> Result<T>
+where
+    S: FnMut(environment::Solver) -> Result<T>,
+    N: FnMut(environment::Solver, &Error, environment::Solver),
+{
+    let mut failed = failed;
+    let mut error = error;
+
+    for &fallback in fallbacks {
+        notify(failed, &error, fallback);
+        match solve(fallback) {
+            Err(e) if e.is_solver_failure() => {
+                failed = fallback;
+                error = e;
+            }
+            solved => return solved,
+        }
+    }
+
+    Err(error)
+}
+
+/// Returns the widths (in bits) of all memory and cache accesses of the program.
+///
+/// Fails if an access width isn't a (positive) multiple of the memory cell width,
+/// as each address holds a single cell.
+fn access_widths(program: &Program, cell_width: usize) -> Result<Vec<usize>> {
+    if cell_width == 0 {
+        return Err(Error::UnsupportedCellWidth(cell_width));
+    }
+
+    let mut widths = BTreeSet::new();
+
+    for expr in program.expressions() {
+        for subexpr in expr.subexpressions() {
+            match subexpr.operator() {
+                Operator::Memory(Memory::Load(width))
+                | Operator::Memory(Memory::Store(width))
+                | Operator::Cache(Cache::Fetch(width))
+                | Operator::Cache(Cache::Evict(width)) => {
+                    widths.insert(*width);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(width) = widths
+        .iter()
+        .find(|&&width| width == 0 || width % cell_width != 0)
+    {
+        return Err(Error::UnsupportedAccessWidth {
+            width: *width,
+            cell_width,
+        });
+    }
+
+    Ok(widths.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Solver::{Yices2, CVC4, Z3};
+    use std::io;
+
+    fn crashed() -> Error {
+        io::Error::new(io::ErrorKind::BrokenPipe, "solver crashed").into()
+    }
+
+    #[test]
+    fn test_fallback_solvers_are_tried_in_order_until_one_succeeds() {
+        // Given: CVC4 crashes too, Z3 succeeds
+        let mut solved_with = Vec::new();
+        let mut retries = Vec::new();
+
+        // When:
+        let result = solve_with_fallbacks(
+            Yices2,
+            crashed(),
+            &[CVC4, Z3, Yices2],
+            |solver| {
+                solved_with.push(solver);
+                match solver {
+                    CVC4 => Err(crashed()),
+                    _ => Ok(solver),
+                }
+            },
+            |failed, _, next| retries.push((failed, next)),
+        );
+
+        // Then: the remaining fallbacks aren't tried
+        assert_eq!(result.unwrap(), Z3);
+        assert_eq!(solved_with, vec![CVC4, Z3]);
+        assert_eq!(retries, vec![(Yices2, CVC4), (CVC4, Z3)]);
+    }
+
+    #[test]
+    fn test_last_solver_failure_is_returned_if_all_fallbacks_fail() {
+        // Given:
+        let mut retries = 0;
+
+        // When:
+        let result: Result<()> = solve_with_fallbacks(
+            Yices2,
+            crashed(),
+            &[CVC4, Z3],
+            |_| Err(crashed()),
+            |_, _, _| retries += 1,
+        );
+
+        // Then: the analysis may be resumed
+        assert!(result.unwrap_err().is_solver_failure());
+        assert_eq!(retries, 2);
+    }
+
+    #[test]
+    fn test_other_errors_are_not_retried() {
+        // Given:
+        let mut solved_with = Vec::new();
+
+        // When:
+        let result: Result<()> = solve_with_fallbacks(
+            Yices2,
+            crashed(),
+            &[CVC4, Z3],
+            |solver| {
+                solved_with.push(solver);
+                Err("Unsupported expression".into())
+            },
+            |_, _, _| (),
+        );
+
+        // Then:
+        assert!(!result.unwrap_err().is_solver_failure());
+        assert_eq!(solved_with, vec![CVC4]);
+    }
+
+    #[test]
+    fn test_original_failure_is_returned_without_fallbacks() {
+        // When:
+        let result: Result<()> =
+            solve_with_fallbacks(Yices2, crashed(), &[], |_| Ok(()), |_, _, _| ());
+
+        // Then:
+        assert!(result.unwrap_err().is_solver_failure());
+    }
 }
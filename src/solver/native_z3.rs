@@ -0,0 +1,808 @@
+//! Solver backend using the native Z3 API (instead of the SMT-LIB process interface).
+//!
+//! The LIR program is encoded directly into Z3 terms within a single in-process context,
+//! which avoids printing and parsing large formulas and models.
+//! Lists and tuples are not supported by this backend, traces are encoded as arrays instead.
+
+use crate::environment;
+use crate::error::{Error, Result};
+use crate::expr;
+use crate::lir;
+use crate::solver::simplification::parse_term;
+use crate::solver::{
+    access_widths, AssertionCheck, Certify, CheckResult, DumpFormula, ExpressionSimplifier, Model,
+    SolverSession,
+};
+use num_bigint::BigUint;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use z3::ast::{self, Ast};
+
+type Dynamic = ast::Dynamic<'static>;
+
+thread_local!(static CONTEXTS: RefCell<HashMap<bool, &'static z3::Context>> = RefCell::new(HashMap::new()));
+
+/// Returns the Z3 context of the current thread, with or without proof generation.
+///
+/// Z3 terms are bound to the lifetime of their context, the contexts are therefore shared by all solvers
+/// and simplifiers of a thread and kept alive for the rest of the program (at most one per configuration).
+fn shared_context(proof_generation: bool) -> &'static z3::Context {
+    CONTEXTS.with(|contexts| {
+        *contexts
+            .borrow_mut()
+            .entry(proof_generation)
+            .or_insert_with(|| {
+                let mut config = z3::Config::new();
+                config.set_model_generation(true);
+                config.set_proof_generation(proof_generation);
+                Box::leak(Box::new(z3::Context::new(&config)))
+            })
+    })
+}
+
+pub struct NativeZ3Solver {
+    context: &'static z3::Context,
+    solver: Rc<RefCell<z3::Solver<'static>>>,
+    formula_file: RefCell<Option<PathBuf>>,
+    endianness: environment::Endianness,
+    memory_cell_width: usize,
+}
+
+impl NativeZ3Solver {
+    pub fn new_from_env(env: &environment::Environment) -> Result<Self> {
+        if env.solver != environment::Solver::Z3 {
+            return Err(
+                format!("Native solver backend is not available for {}", env.solver).into(),
+            );
+        }
+
+        // User-defined options (e.g. random seeds) to make results reproducible
+        for (option, value) in env.solver_options.of(env.solver) {
+            z3::set_global_param(option.trim_start_matches(':'), &value.to_string());
+        }
+
+        let context = shared_context(env.proof_generation);
+        let solver = Rc::new(RefCell::new(z3::Solver::new(context)));
+
+        Ok(Self {
+            context,
+            solver,
+            formula_file: RefCell::new(None),
+            endianness: env.architecture.endianness,
+            memory_cell_width: env.architecture.memory_cell_width,
+        })
+    }
+}
+
+impl DumpFormula for NativeZ3Solver {
+    fn dump_formula_to_file(&self, path: &Path) -> Result<()> {
+        // The formula is written once the program has been encoded
+        File::create(path)?;
+        *self.formula_file.borrow_mut() = Some(path.to_path_buf());
+        Ok(())
+    }
+}
+
+impl Certify for NativeZ3Solver {
+    fn solver_version(&self) -> Result<String> {
+        Ok(format!("Z3 {}", z3::full_version()))
+    }
+
+    fn unsat_proof(&self) -> Result<Option<String>> {
+        Ok(self
+            .solver
+            .borrow()
+            .get_proof()
+            .map(|proof| format!("{:?}", proof)))
+    }
+}
+
+impl AssertionCheck for NativeZ3Solver {
+    fn encode_program(&mut self, program: &lir::Program) -> Result<()> {
+        // Accesses of any width are encoded directly, but only whole cells are supported
+        access_widths(program, self.memory_cell_width)?;
+
+        let encoder = Encoder::new(self.context, self.endianness, self.memory_cell_width);
+        let solver = self.solver.borrow_mut();
+
+        let mut assertions: Vec<ast::Bool<'static>> = Vec::new();
+
+        for node in program.nodes() {
+            match node {
+                lir::Node::Comment(_) => {}
+                lir::Node::Let { var, expr } => {
+                    if !expr.is_nondet() {
+                        let var = encoder.variable(var)?;
+                        let value = encoder.encode(expr)?;
+                        solver.assert(&var._eq(&value));
+                    }
+                }
+                lir::Node::Assert {
+                    condition,
+                    provenance,
+                } => {
+                    let name = provenance.assertion_name(assertions.len());
+                    let assertion = ast::Bool::new_const(self.context, name);
+                    solver.assert(&assertion._eq(&encoder.encode_bool(condition)?));
+                    assertions.push(assertion);
+                }
+                lir::Node::Assume { condition } => {
+                    solver.assert(&encoder.encode_bool(condition)?);
+                }
+            }
+        }
+
+        let assertion_refs: Vec<&ast::Bool<'static>> = assertions.iter().collect();
+        solver.assert(&ast::Bool::and(self.context, &assertion_refs).not());
+
+        if let Some(path) = self.formula_file.borrow().as_ref() {
+            let mut file = File::create(path)?;
+            write!(file, "{}", solver)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_assertions(&mut self) -> Result<CheckResult> {
+        let solver = self.solver.borrow();
+
+        match solver.check() {
+            z3::SatResult::Sat => {
+                let model = solver.get_model().ok_or("Z3 did not produce a model")?;
+                let model = Box::new(NativeZ3Model {
+                    context: self.context,
+                    model,
+                    endianness: self.endianness,
+                    memory_cell_width: self.memory_cell_width,
+                });
+                Ok(CheckResult::AssertionViolated { model })
+            }
+            z3::SatResult::Unsat => Ok(CheckResult::AssertionsHold),
+            z3::SatResult::Unknown => Err(format!(
+                "Z3 returned unknown: {}",
+                solver.get_reason_unknown().unwrap_or_default()
+            )
+            .into()),
+        }
+    }
+}
+
+impl SolverSession for NativeZ3Solver {
+    fn push(&mut self) -> Result<()> {
+        self.solver.borrow().push();
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<()> {
+        self.solver.borrow().pop(1);
+        Ok(())
+    }
+
+    fn assume(&mut self, condition: &expr::Expression) -> Result<()> {
+        let encoder = Encoder::new(self.context, self.endianness, self.memory_cell_width);
+        self.solver
+            .borrow()
+            .assert(&encoder.encode_bool(condition)?);
+        Ok(())
+    }
+}
+
+/// Simplifies expressions by the rewriter of Z3 (`simplify`).
+pub struct NativeZ3Simplifier {
+    encoder: Encoder,
+}
+
+impl NativeZ3Simplifier {
+    pub fn new_from_env(env: &environment::Environment) -> Self {
+        Self {
+            encoder: Encoder::new(
+                shared_context(false),
+                env.architecture.endianness,
+                env.architecture.memory_cell_width,
+            ),
+        }
+    }
+}
+
+impl ExpressionSimplifier for NativeZ3Simplifier {
+    fn simplify(&self, expr: &expr::Expression) -> Result<Option<expr::Expression>> {
+        let encoded = match self.encoder.encode(expr) {
+            Ok(encoded) => encoded,
+            // Not encodable (e.g. lists), therefore not simplifiable
+            Err(_) => return Ok(None),
+        };
+
+        let variables: HashMap<String, expr::Variable> = expr
+            .variables()
+            .into_iter()
+            .map(|var| (var.identifier(), var.clone()))
+            .collect();
+
+        Ok(parse_term(&encoded.simplify().to_string(), &variables).ok())
+    }
+}
+
+/// Encodes `expr::Expression`s as Z3 terms.
+///
+/// Variables are encoded as constants named by their identifier,
+/// Z3 identifies equally named constants, therefore no declarations need to be kept.
+struct Encoder {
+    context: &'static z3::Context,
+    endianness: environment::Endianness,
+    memory_cell_width: usize,
+}
+
+impl Encoder {
+    pub fn new(
+        context: &'static z3::Context,
+        endianness: environment::Endianness,
+        memory_cell_width: usize,
+    ) -> Self {
+        Self {
+            context,
+            endianness,
+            memory_cell_width,
+        }
+    }
+
+    fn sort(&self, sort: &expr::Sort) -> Result<z3::Sort<'static>> {
+        let ctx = self.context;
+        match sort {
+            expr::Sort::Boolean => Ok(z3::Sort::bool(ctx)),
+            expr::Sort::Integer => Ok(z3::Sort::int(ctx)),
+            expr::Sort::BitVector(width) => Ok(z3::Sort::bitvector(ctx, *width as u32)),
+            expr::Sort::Array { range, domain } => Ok(z3::Sort::array(
+                ctx,
+                &self.sort(range)?,
+                &self.sort(domain)?,
+            )),
+            expr::Sort::Memory => self.sort(&expr::Sort::array(
+                expr::Sort::word(),
+                expr::Sort::bit_vector(self.memory_cell_width),
+            )),
+            expr::Sort::Cache
+            | expr::Sort::PatternHistoryTable
+            | expr::Sort::MemoryDependencePredictor => self.sort(&expr::Sort::array(
+                expr::Sort::word(),
+                expr::Sort::boolean(),
+            )),
+            expr::Sort::BranchTargetBuffer => {
+                self.sort(&expr::Sort::array(expr::Sort::word(), expr::Sort::word()))
+            }
+            expr::Sort::Predictor => Ok(z3::Sort::uninterpreted(ctx, "Predictor".into())),
+            expr::Sort::List { .. } | expr::Sort::Tuple { .. } => {
+                Err(Error::UnsupportedSort(sort.clone()))
+            }
+        }
+    }
+
+    pub fn variable(&self, var: &expr::Variable) -> Result<Dynamic> {
+        Ok(Dynamic::new_const(
+            self.context,
+            var.identifier(),
+            &self.sort(var.sort())?,
+        ))
+    }
+
+    pub fn encode_bool(&self, expr: &expr::Expression) -> Result<ast::Bool<'static>> {
+        as_bool(self.encode(expr)?)
+    }
+
+    pub fn encode(&self, expr: &expr::Expression) -> Result<Dynamic> {
+        let ctx = self.context;
+
+        let operands = expr
+            .operands()
+            .iter()
+            .map(|operand| self.encode(operand))
+            .collect::<Result<Vec<Dynamic>>>()?;
+
+        match (expr.operator(), operands.as_slice()) {
+            (expr::Operator::Variable(var), []) => self.variable(var),
+            (expr::Operator::Constant(c), []) => self.constant(c, expr.sort()),
+            (expr::Operator::Ite, [cond, then, otherwise]) => {
+                Ok(as_bool(cond.clone())?.ite(then, otherwise))
+            }
+            (expr::Operator::Equal, [lhs, rhs]) => Ok(Dynamic::from_ast(&lhs._eq(rhs))),
+            (expr::Operator::Cast(sort), [value]) => {
+                let array_sort = self.sort(sort)?;
+                let index_sort = array_sort
+                    .array_domain()
+                    .ok_or("Cast is only supported for array sorts")?;
+                Ok(Dynamic::from_ast(&ast::Array::const_array(
+                    ctx,
+                    &index_sort,
+                    value,
+                )))
+            }
+            (expr::Operator::Nondet, _) => Err("Incorrect use of nondet()".into()),
+            (expr::Operator::Boolean(op), operands) => self.boolean(op, operands),
+            (expr::Operator::Integer(op), operands) => self.integer(op, operands),
+            (expr::Operator::BitVector(op), operands) => self.bit_vector(op, operands),
+            (expr::Operator::Array(op), operands) => self.array(op, operands),
+            (expr::Operator::Memory(op), operands) => self.memory(op, operands),
+            (expr::Operator::Predictor(op), operands) => self.predictor(op, operands),
+            (expr::Operator::Cache(op), operands) => self.cache(op, operands),
+            (expr::Operator::BranchTargetBuffer(op), operands) => self.btb(op, operands),
+            (expr::Operator::PatternHistoryTable(op), operands) => self.pht(op, operands),
+            (expr::Operator::MemoryDependencePredictor(op), operands) => self.mdp(op, operands),
+            (expr::Operator::List(_), _) | (expr::Operator::Tuple(_), _) => Err(format!(
+                "Operator {} is not supported by the native Z3 backend",
+                expr.operator()
+            )
+            .into()),
+            _ => Err(Error::InvalidOperands {
+                operator: expr.operator().to_string(),
+            }),
+        }
+    }
+
+    fn constant(&self, constant: &expr::Constant, sort: &expr::Sort) -> Result<Dynamic> {
+        let ctx = self.context;
+        match constant {
+            expr::Constant::Boolean(value) => {
+                Ok(Dynamic::from_ast(&ast::Bool::from_bool(ctx, *value)))
+            }
+            expr::Constant::Integer(value) => {
+                Ok(Dynamic::from_ast(&ast::Int::from_u64(ctx, *value)))
+            }
+            expr::Constant::BitVector(bv) => Ok(Dynamic::from_ast(
+                &self.bit_vector_value(bv.value(), bv.bits())?,
+            )),
+            expr::Constant::Cache(value) => {
+                let (default_is_cached, addresses) = match value.addresses() {
+                    expr::CacheAddresses::EvictedFromFullCache(addresses) => (true, addresses),
+                    expr::CacheAddresses::FetchedIntoEmptyCache(addresses) => (false, addresses),
+                };
+                let mut cache = ast::Array::const_array(
+                    ctx,
+                    &z3::Sort::bitvector(ctx, environment::WORD_SIZE as u32),
+                    &ast::Bool::from_bool(ctx, default_is_cached),
+                );
+                for address in addresses {
+                    cache = cache.store(
+                        &ast::BV::from_u64(ctx, address, environment::WORD_SIZE as u32),
+                        &ast::Bool::from_bool(ctx, !default_is_cached),
+                    );
+                }
+                Ok(Dynamic::from_ast(&cache))
+            }
+            _ => Err(Error::UnsupportedSort(sort.clone())),
+        }
+    }
+
+    fn bit_vector_value(&self, value: &BigUint, bits: usize) -> Result<ast::BV<'static>> {
+        let int = ast::Int::from_str(self.context, &value.to_string())
+            .ok_or("Invalid bit-vector constant")?;
+        Ok(ast::BV::from_int(&int, bits as u32))
+    }
+
+    fn word(&self, value: u64) -> ast::BV<'static> {
+        ast::BV::from_u64(self.context, value, environment::WORD_SIZE as u32)
+    }
+
+    fn boolean(&self, op: &expr::Boolean, operands: &[Dynamic]) -> Result<Dynamic> {
+        let ctx = self.context;
+        let operands = operands
+            .iter()
+            .cloned()
+            .map(as_bool)
+            .collect::<Result<Vec<_>>>()?;
+        let result = match (op, operands.as_slice()) {
+            (expr::Boolean::Not, [b]) => b.not(),
+            (expr::Boolean::Imply, [a, b]) => a.implies(b),
+            (expr::Boolean::Xor, [a, b]) => a.xor(b),
+            (expr::Boolean::And, operands) => {
+                ast::Bool::and(ctx, &operands.iter().collect::<Vec<_>>())
+            }
+            (expr::Boolean::Or, operands) => {
+                ast::Bool::or(ctx, &operands.iter().collect::<Vec<_>>())
+            }
+            _ => {
+                return Err(Error::InvalidOperands {
+                    operator: op.to_string(),
+                })
+            }
+        };
+        Ok(Dynamic::from_ast(&result))
+    }
+
+    fn integer(&self, op: &expr::Integer, operands: &[Dynamic]) -> Result<Dynamic> {
+        let ctx = self.context;
+        let operands = operands
+            .iter()
+            .cloned()
+            .map(as_int)
+            .collect::<Result<Vec<_>>>()?;
+        let result = match (op, operands.as_slice()) {
+            (expr::Integer::Lt, [a, b]) => Dynamic::from_ast(&a.lt(b)),
+            (expr::Integer::Gt, [a, b]) => Dynamic::from_ast(&a.gt(b)),
+            (expr::Integer::Lte, [a, b]) => Dynamic::from_ast(&a.le(b)),
+            (expr::Integer::Gte, [a, b]) => Dynamic::from_ast(&a.ge(b)),
+            (expr::Integer::Mod, [a, b]) => Dynamic::from_ast(&a.modulo(b)),
+            (expr::Integer::Div, [a, b]) => Dynamic::from_ast(&a.div(b)),
+            (expr::Integer::Abs, [a]) => {
+                let zero = ast::Int::from_u64(ctx, 0);
+                Dynamic::from_ast(&a.lt(&zero).ite(&a.unary_minus(), a))
+            }
+            (expr::Integer::Neg, [a]) => Dynamic::from_ast(&a.unary_minus()),
+            (expr::Integer::Mul, operands) => {
+                Dynamic::from_ast(&ast::Int::mul(ctx, &operands.iter().collect::<Vec<_>>()))
+            }
+            (expr::Integer::Add, operands) => {
+                Dynamic::from_ast(&ast::Int::add(ctx, &operands.iter().collect::<Vec<_>>()))
+            }
+            (expr::Integer::Sub, operands) => {
+                Dynamic::from_ast(&ast::Int::sub(ctx, &operands.iter().collect::<Vec<_>>()))
+            }
+            _ => {
+                return Err(Error::InvalidOperands {
+                    operator: op.to_string(),
+                })
+            }
+        };
+        Ok(result)
+    }
+
+    fn bit_vector(&self, op: &expr::BitVector, operands: &[Dynamic]) -> Result<Dynamic> {
+        use expr::BitVector as Op;
+
+        if let (Op::ToBoolean, [a]) = (op, operands) {
+            let a = as_bv(a.clone())?;
+            let zero = ast::BV::from_u64(self.context, 0, a.get_size());
+            return Ok(Dynamic::from_ast(&a._eq(&zero).not()));
+        }
+
+        if let (Op::FromBoolean(bits), [a]) = (op, operands) {
+            let a = as_bool(a.clone())?;
+            let zero = ast::BV::from_u64(self.context, 0, *bits as u32);
+            let one = ast::BV::from_u64(self.context, 1, *bits as u32);
+            return Ok(Dynamic::from_ast(&a.ite(&one, &zero)));
+        }
+
+        let operands = operands
+            .iter()
+            .cloned()
+            .map(as_bv)
+            .collect::<Result<Vec<_>>>()?;
+
+        let bv = |result: ast::BV<'static>| Ok(Dynamic::from_ast(&result));
+        let bool = |result: ast::Bool<'static>| Ok(Dynamic::from_ast(&result));
+
+        match (op, operands.as_slice()) {
+            (Op::Concat, [first, rest @ ..]) => bv(rest
+                .iter()
+                .fold(first.clone(), |acc, next| acc.concat(next))),
+            (Op::Extract(i, j), [a]) => bv(a.extract(*i as u32, *j as u32)),
+            (Op::Truncate(i), [a]) => bv(a.extract(*i as u32 - 1, 0)),
+            (Op::Not, [a]) => bv(a.bvnot()),
+            (Op::Neg, [a]) => bv(a.bvneg()),
+            (Op::And, [a, b]) => bv(a.bvand(b)),
+            (Op::Or, [a, b]) => bv(a.bvor(b)),
+            (Op::Add, [a, b]) => bv(a.bvadd(b)),
+            (Op::Mul, [a, b]) => bv(a.bvmul(b)),
+            (Op::UDiv, [a, b]) => bv(a.bvudiv(b)),
+            (Op::URem, [a, b]) | (Op::UMod, [a, b]) => bv(a.bvurem(b)),
+            (Op::Shl, [a, b]) => bv(a.bvshl(b)),
+            (Op::LShr, [a, b]) => bv(a.bvlshr(b)),
+            (Op::AShr, [a, b]) => bv(a.bvashr(b)),
+            (Op::Nand, [a, b]) => bv(a.bvnand(b)),
+            (Op::Nor, [a, b]) => bv(a.bvnor(b)),
+            (Op::Xor, [a, b]) => bv(a.bvxor(b)),
+            (Op::Xnor, [a, b]) => bv(a.bvxnor(b)),
+            (Op::Comp, [a, b]) => {
+                let zero = ast::BV::from_u64(self.context, 0, 1);
+                let one = ast::BV::from_u64(self.context, 1, 1);
+                bv(a._eq(b).ite(&one, &zero))
+            }
+            (Op::Sub, [a, b]) => bv(a.bvsub(b)),
+            (Op::SaturatingSub, [a, b]) => {
+                let result = a.bvsub(b);
+                let zero = ast::BV::from_u64(self.context, 0, a.get_size());
+                bv(result.bvugt(a).ite(&zero, &result))
+            }
+            (Op::SDiv, [a, b]) => bv(a.bvsdiv(b)),
+            (Op::SRem, [a, b]) => bv(a.bvsrem(b)),
+            (Op::SMod, [a, b]) => bv(a.bvsmod(b)),
+            (Op::Repeat(n), [a]) => bv((1..*n).fold(a.clone(), |acc, _| acc.concat(a))),
+            (Op::ZeroExtend(i), [a]) => bv(a.zero_ext(*i as u32)),
+            (Op::SignExtend(i), [a]) => bv(a.sign_ext(*i as u32)),
+            (Op::RotateLeft(i), [a]) => {
+                bv(a.bvrotl(&ast::BV::from_u64(self.context, *i as u64, a.get_size())))
+            }
+            (Op::RotateRight(i), [a]) => {
+                bv(a.bvrotr(&ast::BV::from_u64(self.context, *i as u64, a.get_size())))
+            }
+            (Op::ULt, [a, b]) => bool(a.bvult(b)),
+            (Op::ULe, [a, b]) => bool(a.bvule(b)),
+            (Op::UGt, [a, b]) => bool(a.bvugt(b)),
+            (Op::UGe, [a, b]) => bool(a.bvuge(b)),
+            (Op::SLt, [a, b]) => bool(a.bvslt(b)),
+            (Op::SLe, [a, b]) => bool(a.bvsle(b)),
+            (Op::SGt, [a, b]) => bool(a.bvsgt(b)),
+            (Op::SGe, [a, b]) => bool(a.bvsge(b)),
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+
+    fn array(&self, op: &expr::Array, operands: &[Dynamic]) -> Result<Dynamic> {
+        match (op, operands) {
+            (expr::Array::Select, [array, index]) => Ok(as_array(array.clone())?.select(index)),
+            (expr::Array::Store, [array, index, value]) => Ok(Dynamic::from_ast(
+                &as_array(array.clone())?.store(index, value),
+            )),
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+
+    fn memory(&self, op: &expr::Memory, operands: &[Dynamic]) -> Result<Dynamic> {
+        match (op, operands) {
+            (expr::Memory::Load(width), [memory, address]) => {
+                let memory = as_array(memory.clone())?;
+                let address = as_bv(address.clone())?;
+                // ordered from the most to the least significant cell
+                let cells: Vec<ast::BV<'static>> = self
+                    .endianness
+                    .cell_offsets(*width, self.memory_cell_width)
+                    .into_iter()
+                    .map(|cell| {
+                        let cell_address = address.bvadd(&self.word(cell as u64));
+                        as_bv(memory.select(&cell_address))
+                    })
+                    .collect::<Result<_>>()?;
+                let (first, rest) = cells.split_first().ok_or(Error::UnsupportedAccessWidth {
+                    width: *width,
+                    cell_width: self.memory_cell_width,
+                })?;
+                let value = rest
+                    .iter()
+                    .fold(first.clone(), |acc, next| acc.concat(next));
+                Ok(Dynamic::from_ast(&value))
+            }
+            (expr::Memory::Store(width), [memory, address, value]) => {
+                let mut memory = as_array(memory.clone())?;
+                let address = as_bv(address.clone())?;
+                let value = as_bv(value.clone())?;
+                let cell_width = self.memory_cell_width as u32;
+                let cell_offsets = self.endianness.cell_offsets(*width, self.memory_cell_width);
+                for (significance, &cell) in cell_offsets.iter().rev().enumerate() {
+                    let bit_offset = significance as u32 * cell_width;
+                    memory = memory.store(
+                        &address.bvadd(&self.word(cell as u64)),
+                        &value.extract(bit_offset + cell_width - 1, bit_offset),
+                    );
+                }
+                Ok(Dynamic::from_ast(&memory))
+            }
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+
+    fn predictor(&self, op: &expr::Predictor, operands: &[Dynamic]) -> Result<Dynamic> {
+        let ctx = self.context;
+        let (name, range) = match op {
+            expr::Predictor::SpeculationWindow => (
+                "speculation-window",
+                z3::Sort::bitvector(ctx, environment::SPECULATION_WINDOW_SIZE as u32),
+            ),
+            expr::Predictor::Speculate => ("predictor-speculate", z3::Sort::bool(ctx)),
+            expr::Predictor::Taken => ("predictor-taken", z3::Sort::bool(ctx)),
+        };
+        let predictor_sort = self.sort(&expr::Sort::predictor())?;
+        let word_sort = self.sort(&expr::Sort::word())?;
+        let decl = z3::FuncDecl::new(ctx, name, &[&predictor_sort, &word_sort], &range);
+
+        let arguments: Vec<&dyn Ast<'static>> =
+            operands.iter().map(|op| op as &dyn Ast<'static>).collect();
+        Ok(decl.apply(&arguments))
+    }
+
+    fn cache(&self, op: &expr::Cache, operands: &[Dynamic]) -> Result<Dynamic> {
+        let (width, cached) = match op {
+            expr::Cache::Fetch(width) => (width, true),
+            expr::Cache::Evict(width) => (width, false),
+        };
+        match operands {
+            [cache, address] => {
+                let mut cache = as_array(cache.clone())?;
+                let address = as_bv(address.clone())?;
+                for cell in 0..(width / self.memory_cell_width) {
+                    cache = cache.store(
+                        &address.bvadd(&self.word(cell as u64)),
+                        &ast::Bool::from_bool(self.context, cached),
+                    );
+                }
+                Ok(Dynamic::from_ast(&cache))
+            }
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+
+    fn btb(&self, op: &expr::BranchTargetBuffer, operands: &[Dynamic]) -> Result<Dynamic> {
+        match (op, operands) {
+            (expr::BranchTargetBuffer::Track, [btb, location, target]) => Ok(Dynamic::from_ast(
+                &as_array(btb.clone())?.store(location, target),
+            )),
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+
+    fn pht(&self, op: &expr::PatternHistoryTable, operands: &[Dynamic]) -> Result<Dynamic> {
+        let taken = match op {
+            expr::PatternHistoryTable::Taken => true,
+            expr::PatternHistoryTable::NotTaken => false,
+        };
+        match operands {
+            [pht, location] => Ok(Dynamic::from_ast(
+                &as_array(pht.clone())?.store(location, &ast::Bool::from_bool(self.context, taken)),
+            )),
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+
+    fn mdp(&self, op: &expr::MemoryDependencePredictor, operands: &[Dynamic]) -> Result<Dynamic> {
+        match (op, operands) {
+            (expr::MemoryDependencePredictor::Bypass, [mdp, location]) => {
+                Ok(as_array(mdp.clone())?.select(location))
+            }
+            (expr::MemoryDependencePredictor::Dependent, [mdp, location]) => Ok(Dynamic::from_ast(
+                &as_array(mdp.clone())?.store(location, &ast::Bool::from_bool(self.context, false)),
+            )),
+            _ => Err(Error::InvalidOperands {
+                operator: op.to_string(),
+            }),
+        }
+    }
+}
+
+fn as_bool(value: Dynamic) -> Result<ast::Bool<'static>> {
+    value.as_bool().ok_or_else(|| "Expected Boolean".into())
+}
+
+fn as_int(value: Dynamic) -> Result<ast::Int<'static>> {
+    value.as_int().ok_or_else(|| "Expected Integer".into())
+}
+
+fn as_bv(value: Dynamic) -> Result<ast::BV<'static>> {
+    value.as_bv().ok_or_else(|| "Expected BitVector".into())
+}
+
+fn as_array(value: Dynamic) -> Result<ast::Array<'static>> {
+    value.as_array().ok_or_else(|| "Expected Array".into())
+}
+
+struct NativeZ3Model {
+    context: &'static z3::Context,
+    model: z3::Model<'static>,
+    endianness: environment::Endianness,
+    memory_cell_width: usize,
+}
+
+impl Model for NativeZ3Model {
+    fn get_interpretation(&self, variable: &expr::Variable) -> Option<expr::Constant> {
+        self.evaluate(&variable.clone().into())
+    }
+
+    fn evaluate(&self, expr: &expr::Expression) -> Option<expr::Constant> {
+        let encoder = Encoder::new(self.context, self.endianness, self.memory_cell_width);
+        let term = encoder.encode(expr).ok()?;
+        let value = self.model.eval(&term, true)?;
+
+        match expr.sort() {
+            expr::Sort::Boolean => value.as_bool()?.as_bool().map(expr::Constant::boolean),
+            expr::Sort::Integer => value.as_int()?.as_u64().map(expr::Constant::integer),
+            expr::Sort::BitVector(_) => {
+                let bv = value.as_bv()?;
+                // Numerals are printed as #x... or #b...
+                let literal = bv.to_string();
+                let value = if let Some(hex) = literal.strip_prefix("#x") {
+                    BigUint::parse_bytes(hex.as_bytes(), 16)?
+                } else if let Some(bin) = literal.strip_prefix("#b") {
+                    BigUint::parse_bytes(bin.as_bytes(), 2)?
+                } else {
+                    return None;
+                };
+                Some(expr::Constant::bit_vector_big_uint(value))
+            }
+            // Arrays are not required for counterexamples
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BitVector, Expression, Variable};
+    use crate::ir::Provenance;
+
+    fn z3_env() -> environment::Environment {
+        let mut env = environment::Environment::default();
+        env.solver = environment::Solver::Z3;
+        env
+    }
+
+    /// `x = 5; assert x == 6`
+    fn violated_program() -> (lir::Program, Variable) {
+        let x = BitVector::word_variable("x");
+        let mut program = lir::Program::new();
+        program
+            .assign(x.clone(), BitVector::word_constant(5))
+            .unwrap();
+        program
+            .assert(
+                Expression::equal(x.clone().into(), BitVector::word_constant(6)).unwrap(),
+                Provenance::observation(),
+            )
+            .unwrap();
+        (program, x)
+    }
+
+    #[test]
+    fn test_violated_assertion_is_reported_with_model() {
+        // Given:
+        let (program, x) = violated_program();
+        let mut solver = NativeZ3Solver::new_from_env(&z3_env()).unwrap();
+        solver.encode_program(&program).unwrap();
+
+        // When:
+        let result = solver.check_assertions().unwrap();
+
+        // Then:
+        match result {
+            CheckResult::AssertionViolated { model } => assert_eq!(
+                model.get_interpretation(&x),
+                Some(expr::Constant::bit_vector_big_uint(BigUint::from(5u64)))
+            ),
+            CheckResult::AssertionsHold => panic!("expected violated assertion"),
+        }
+    }
+
+    #[test]
+    fn test_assumptions_are_discarded_by_pop() {
+        // Given:
+        let (program, _) = violated_program();
+        let mut solver = NativeZ3Solver::new_from_env(&z3_env()).unwrap();
+        solver.encode_program(&program).unwrap();
+
+        // When:
+        solver.push().unwrap();
+        solver.assume(&expr::Boolean::constant(false)).unwrap();
+        let within_scope = solver.check_assertions().unwrap();
+        solver.pop().unwrap();
+        let after_scope = solver.check_assertions().unwrap();
+
+        // Then:
+        assert!(matches!(within_scope, CheckResult::AssertionsHold));
+        assert!(matches!(after_scope, CheckResult::AssertionViolated { .. }));
+    }
+
+    #[test]
+    fn test_solvers_share_the_context() {
+        // Given:
+        let env = z3_env();
+
+        // When:
+        let first = NativeZ3Solver::new_from_env(&env).unwrap();
+        let second = NativeZ3Solver::new_from_env(&env).unwrap();
+
+        // Then:
+        assert!(std::ptr::eq(first.context, second.context));
+    }
+}
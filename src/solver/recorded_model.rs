@@ -0,0 +1,133 @@
+use super::Model;
+use crate::expr::{Constant, Expression, Variable};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A `Model` which answers queries with the interpretations recorded from another model.
+///
+/// Recording the queries made while building a counterexample allows rebuilding it later
+/// without the solver.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RecordedEntries", into = "RecordedEntries")]
+pub struct RecordedModel {
+    interpretations: HashMap<Variable, Option<Constant>>,
+    evaluations: HashMap<Expression, Option<Constant>>,
+}
+
+impl Model for RecordedModel {
+    fn get_interpretation(&self, variable: &Variable) -> Option<Constant> {
+        self.interpretations.get(variable).cloned().flatten()
+    }
+
+    fn evaluate(&self, expr: &Expression) -> Option<Constant> {
+        self.evaluations.get(expr).cloned().flatten()
+    }
+}
+
+/// Serialized form of a `RecordedModel` (variables and expressions aren't valid map keys in JSON).
+#[derive(Serialize, Deserialize)]
+struct RecordedEntries {
+    interpretations: Vec<(Variable, Option<Constant>)>,
+    evaluations: Vec<(Expression, Option<Constant>)>,
+}
+
+impl From<RecordedEntries> for RecordedModel {
+    fn from(entries: RecordedEntries) -> Self {
+        Self {
+            interpretations: entries.interpretations.into_iter().collect(),
+            evaluations: entries.evaluations.into_iter().collect(),
+        }
+    }
+}
+
+impl From<RecordedModel> for RecordedEntries {
+    fn from(model: RecordedModel) -> Self {
+        Self {
+            interpretations: model.interpretations.into_iter().collect(),
+            evaluations: model.evaluations.into_iter().collect(),
+        }
+    }
+}
+
+/// Forwards all queries to the wrapped model and records the answers.
+pub struct ModelRecorder<'a> {
+    model: &'a dyn Model,
+    recorded: RefCell<RecordedModel>,
+}
+
+impl<'a> ModelRecorder<'a> {
+    pub fn new(model: &'a dyn Model) -> Self {
+        Self {
+            model,
+            recorded: RefCell::new(RecordedModel::default()),
+        }
+    }
+
+    /// Returns all answers recorded so far.
+    pub fn into_recorded_model(self) -> RecordedModel {
+        self.recorded.into_inner()
+    }
+}
+
+impl Model for ModelRecorder<'_> {
+    fn get_interpretation(&self, variable: &Variable) -> Option<Constant> {
+        let interpretation = self.model.get_interpretation(variable);
+        self.recorded
+            .borrow_mut()
+            .interpretations
+            .insert(variable.clone(), interpretation.clone());
+        interpretation
+    }
+
+    fn evaluate(&self, expr: &Expression) -> Option<Constant> {
+        let value = self.model.evaluate(expr);
+        self.recorded
+            .borrow_mut()
+            .evaluations
+            .insert(expr.clone(), value.clone());
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Sort;
+
+    struct FixedModel(HashMap<Variable, Constant>);
+
+    impl Model for FixedModel {
+        fn get_interpretation(&self, variable: &Variable) -> Option<Constant> {
+            self.0.get(variable).cloned()
+        }
+
+        fn evaluate(&self, _expr: &Expression) -> Option<Constant> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_recorded_model_answers_recorded_queries_after_roundtrip() {
+        // Given:
+        let x = Variable::new("x", Sort::word());
+        let y = Variable::new("y", Sort::word());
+        let mut interpretations = HashMap::new();
+        interpretations.insert(x.clone(), Constant::bit_vector_u64(42, 64));
+        let model = FixedModel(interpretations);
+
+        // When:
+        let recorder = ModelRecorder::new(&model);
+        recorder.get_interpretation(&x);
+        recorder.get_interpretation(&y);
+        let json = serde_json::to_string(&recorder.into_recorded_model()).unwrap();
+        let recorded: RecordedModel = serde_json::from_str(&json).unwrap();
+
+        // Then:
+        assert_eq!(
+            recorded.get_interpretation(&x),
+            Some(Constant::bit_vector_u64(42, 64))
+        );
+        assert_eq!(recorded.get_interpretation(&y), None);
+    }
+}
@@ -2,7 +2,10 @@ use crate::environment;
 use crate::error::Result;
 use crate::expr;
 use crate::lir;
-use crate::solver::{AssertionCheck, CheckResult, DumpFormula, Model};
+use crate::solver::{
+    access_widths, AssertionCheck, Certify, CheckResult, DumpFormula, Model, SolverFeatures,
+    SolverSession,
+};
 use num_bigint::BigUint;
 use rsmt2::parse::*;
 use rsmt2::print::{Expr2Smt, Sort2Smt, Sym2Smt};
@@ -10,6 +13,7 @@ use rsmt2::{Logic, SmtConf, SmtRes, Solver};
 use std::cell::RefCell;
 use std::fs::File;
 use std::path::Path;
+use std::process::Command;
 use std::rc::Rc;
 use std::str::FromStr;
 
@@ -18,25 +22,42 @@ type SolverType = environment::Solver;
 pub struct RSMTSolver {
     solver: Rc<RefCell<rsmt2::Solver<Parser>>>,
     solver_type: SolverType,
+    endianness: environment::Endianness,
+    memory_cell_width: usize,
 }
 
 impl RSMTSolver {
     pub fn new_from_env(env: &environment::Environment) -> Result<Self> {
-        let mut conf = match env.solver {
-            environment::Solver::Z3 => SmtConf::default_z3(),
-            environment::Solver::CVC4 => SmtConf::default_cvc4(),
-            environment::Solver::Yices2 => SmtConf::default_yices_2(),
-        };
+        Self::new(env, env.solver)
+    }
+
+    /// Creates a solver process of `solver_type` instead of the solver configured in `env`.
+    pub fn new(env: &environment::Environment, solver_type: SolverType) -> Result<Self> {
+        let mut conf = default_conf(solver_type);
 
         // Activate model production
         conf.models();
 
         let parser = Parser::new();
-        let solver = Rc::new(RefCell::new(Solver::new(conf, parser)?));
+        let mut solver = Solver::new(conf, parser)?;
+
+        // User-defined options (e.g. random seeds) to make results reproducible
+        for (option, value) in env.solver_options.of(solver_type) {
+            let option = if option.starts_with(':') {
+                option.clone()
+            } else {
+                format!(":{}", option)
+            };
+            solver.set_option(&option, value)?;
+        }
+
+        let solver = Rc::new(RefCell::new(solver));
 
         Ok(Self {
             solver,
-            solver_type: env.solver,
+            solver_type,
+            endianness: env.architecture.endianness,
+            memory_cell_width: env.architecture.memory_cell_width,
         })
     }
 }
@@ -49,6 +70,80 @@ impl DumpFormula for RSMTSolver {
     }
 }
 
+impl Certify for RSMTSolver {
+    fn solver_version(&self) -> Result<String> {
+        solver_version(self.solver_type)
+    }
+
+    fn unsat_proof(&self) -> Result<Option<String>> {
+        // Proofs are solver-specific and can't be retrieved via rsmt2
+        Ok(None)
+    }
+}
+
+fn solver_version(solver_type: SolverType) -> Result<String> {
+    // Same commands as used by the default configurations of rsmt2
+    let command = match solver_type {
+        SolverType::Z3 => "z3",
+        SolverType::CVC4 => "cvc4",
+        SolverType::Yices2 => "yices-smt2",
+    };
+    let output = Command::new(command).arg("--version").output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+fn default_conf(solver_type: SolverType) -> SmtConf {
+    match solver_type {
+        SolverType::Z3 => SmtConf::default_z3(),
+        SolverType::CVC4 => SmtConf::default_cvc4(),
+        SolverType::Yices2 => SmtConf::default_yices_2(),
+    }
+}
+
+/// Probes the features of the given solver, each feature is checked by a fresh solver process.
+pub fn probe(solver_type: SolverType) -> Result<SolverFeatures> {
+    let version = solver_version(solver_type)?;
+
+    let probe_with = |commands: &dyn Fn(&mut Solver<Parser>) -> Result<()>| -> bool {
+        let result = Solver::new(default_conf(solver_type), Parser::new())
+            .map_err(Into::into)
+            .and_then(|mut solver| {
+                commands(&mut solver)?;
+                Ok(solver.check_sat()?)
+            });
+        result.is_ok()
+    };
+
+    let bit_vector_arrays = probe_with(&|solver| {
+        solver.set_logic(Logic::QF_AUFBV)?;
+        solver.declare_const("memory", "(Array (_ BitVec 64) (_ BitVec 8))")?;
+        Ok(())
+    });
+
+    let datatypes = probe_with(&|solver| define_tuple(solver));
+
+    let lists = match solver_type {
+        SolverType::Yices2 => false,
+        SolverType::CVC4 => probe_with(&|solver| {
+            define_list(solver)?;
+            solver.declare_const("trace", "(List (_ BitVec 64))")?;
+            Ok(())
+        }),
+        SolverType::Z3 => probe_with(&|solver| {
+            // Builtin theory of lists
+            solver.declare_const("trace", "(List (_ BitVec 64))")?;
+            Ok(())
+        }),
+    };
+
+    Ok(SolverFeatures {
+        version,
+        bit_vector_arrays,
+        datatypes,
+        lists,
+    })
+}
+
 // There is no other (easy) way to propagate this information along expr_to_smt2.
 // The solver type is required for list encoding.
 thread_local!(static SOLVER_TYPE: RefCell<Option<SolverType>> = RefCell::new(None));
@@ -72,13 +167,20 @@ impl AssertionCheck for RSMTSolver {
             solver.set_logic(Logic::QF_AUFBV)?;
         }
 
-        let access_widths = vec![8, 16, 32, 64, 128, 256, 512];
+        // Only define load/store/fetch/evict functions for the widths actually used
+        let access_widths = access_widths(program, self.memory_cell_width)?;
 
         define_predictor(&mut solver)?;
-        define_memory(&mut solver, &access_widths)?;
-        define_cache(&mut solver, &access_widths)?;
+        define_memory(
+            &mut solver,
+            &access_widths,
+            self.endianness,
+            self.memory_cell_width,
+        )?;
+        define_cache(&mut solver, &access_widths, self.memory_cell_width)?;
         define_btb(&mut solver)?;
         define_pht(&mut solver)?;
+        define_mdp(&mut solver)?;
 
         match self.solver_type {
             SolverType::Yices2 => {
@@ -112,8 +214,12 @@ impl AssertionCheck for RSMTSolver {
                         solver.assert(&assignment)?
                     }
                 }
-                lir::Node::Assert { condition } => {
-                    let name = format!("_assertion{}", assertions.len());
+                lir::Node::Assert {
+                    condition,
+                    provenance,
+                } => {
+                    solver.comment(&format!("Assertion: {}", provenance))?;
+                    let name = provenance.assertion_name(assertions.len());
                     let assertion = expr::Variable::new(name, expr::Sort::boolean());
                     define_variable(&mut solver, &assertion, condition)?;
                     assertions.push(assertion.into())
@@ -142,6 +248,23 @@ impl AssertionCheck for RSMTSolver {
     }
 }
 
+impl SolverSession for RSMTSolver {
+    fn push(&mut self) -> Result<()> {
+        let mut solver = self.solver.borrow_mut();
+        Ok(solver.push(1)?)
+    }
+
+    fn pop(&mut self) -> Result<()> {
+        let mut solver = self.solver.borrow_mut();
+        Ok(solver.pop(1)?)
+    }
+
+    fn assume(&mut self, condition: &expr::Expression) -> Result<()> {
+        let mut solver = self.solver.borrow_mut();
+        Ok(solver.assert(condition)?)
+    }
+}
+
 fn declare_variable<T>(solver: &mut Solver<T>, variable: &expr::Variable) -> SmtRes<()> {
     solver.declare_const(variable, variable.sort())
 }
@@ -269,6 +392,7 @@ impl Expr2Smt<&expr::Sort> for expr::Operator {
             Self::Cache(op) => op.expr_to_smt2(w, sort),
             Self::BranchTargetBuffer(op) => op.expr_to_smt2(w, sort),
             Self::PatternHistoryTable(op) => op.expr_to_smt2(w, sort),
+            Self::MemoryDependencePredictor(op) => op.expr_to_smt2(w, sort),
         }
     }
 }
@@ -549,6 +673,19 @@ impl Expr2Smt<&expr::Sort> for expr::PatternHistoryTable {
     }
 }
 
+impl Expr2Smt<&expr::Sort> for expr::MemoryDependencePredictor {
+    fn expr_to_smt2<Writer>(&self, w: &mut Writer, _: &expr::Sort) -> SmtRes<()>
+    where
+        Writer: ::std::io::Write,
+    {
+        match self {
+            Self::Bypass => write!(w, "mdp-bypass")?,
+            Self::Dependent => write!(w, "mdp-dependent")?,
+        };
+        Ok(())
+    }
+}
+
 impl Sym2Smt<()> for expr::Variable {
     fn sym_to_smt2<Writer>(&self, w: &mut Writer, _: ()) -> SmtRes<()>
     where
@@ -593,25 +730,31 @@ impl Sort2Smt for expr::Sort {
             Self::Cache => write!(w, "Cache")?,
             Self::BranchTargetBuffer => write!(w, "BranchTargetBuffer")?,
             Self::PatternHistoryTable => write!(w, "PatternHistoryTable")?,
+            Self::MemoryDependencePredictor => write!(w, "MemoryDependencePredictor")?,
         };
         Ok(())
     }
 }
 
-fn define_memory<T>(solver: &mut Solver<T>, access_widths: &[usize]) -> Result<()> {
+fn define_memory<T>(
+    solver: &mut Solver<T>,
+    access_widths: &[usize],
+    endianness: environment::Endianness,
+    cell_width: usize,
+) -> Result<()> {
     // memory type
-    let mem_array_sort = expr::Sort::array(expr::Sort::word(), expr::Sort::bit_vector(8));
+    let mem_array_sort = expr::Sort::array(expr::Sort::word(), expr::Sort::bit_vector(cell_width));
     solver.define_null_sort(&expr::Sort::memory(), &mem_array_sort)?;
 
     // memory load functions
     for width in access_widths {
         let mut array_selects = vec![];
-        for byte in (0..(width / 8)).rev() {
+        for cell in endianness.cell_offsets(*width, cell_width) {
             array_selects.push(expr::Array::select(
                 expr::Variable::new("mem", mem_array_sort.clone()).into(),
                 expr::BitVector::add(
                     expr::Variable::new("addr", expr::Sort::word()).into(),
-                    expr::BitVector::word_constant(byte.try_into().unwrap()),
+                    expr::BitVector::word_constant(cell.try_into().unwrap()),
                 )?,
             )?);
         }
@@ -627,16 +770,17 @@ fn define_memory<T>(solver: &mut Solver<T>, access_widths: &[usize]) -> Result<(
     for width in access_widths {
         let mut store_expr: expr::Expression =
             expr::Variable::new("mem", mem_array_sort.clone()).into();
-        for byte in (0..(width / 8)).rev() {
-            let bit_offset = byte * 8;
+        let cell_offsets = endianness.cell_offsets(*width, cell_width);
+        for (significance, &cell) in cell_offsets.iter().rev().enumerate() {
+            let bit_offset = significance * cell_width;
             store_expr = expr::Array::store(
                 store_expr,
                 expr::BitVector::add(
                     expr::Variable::new("addr", expr::Sort::word()).into(),
-                    expr::BitVector::word_constant(byte.try_into().unwrap()),
+                    expr::BitVector::word_constant(cell.try_into().unwrap()),
                 )?,
                 expr::BitVector::extract(
-                    bit_offset + 7,
+                    bit_offset + cell_width - 1,
                     bit_offset,
                     expr::Variable::new("val", expr::Sort::bit_vector(*width)).into(),
                 )?,
@@ -681,7 +825,11 @@ fn define_predictor<T>(solver: &mut Solver<T>) -> Result<()> {
     Ok(())
 }
 
-fn define_cache<T>(solver: &mut Solver<T>, access_widths: &[usize]) -> Result<()> {
+fn define_cache<T>(
+    solver: &mut Solver<T>,
+    access_widths: &[usize],
+    cell_width: usize,
+) -> Result<()> {
     // cache type
     let cache_set_sort = expr::Sort::array(expr::Sort::word(), expr::Sort::boolean());
     solver.define_null_sort(&expr::Sort::cache(), &cache_set_sort)?;
@@ -690,12 +838,12 @@ fn define_cache<T>(solver: &mut Solver<T>, access_widths: &[usize]) -> Result<()
     for width in access_widths {
         let mut insert_expr: expr::Expression =
             expr::Variable::new("cache", cache_set_sort.clone()).into();
-        for byte in 0..(width / 8) {
+        for cell in 0..(width / cell_width) {
             insert_expr = expr::Array::store(
                 insert_expr,
                 expr::BitVector::add(
                     expr::Variable::new("addr", expr::Sort::word()).into(),
-                    expr::BitVector::word_constant(byte.try_into().unwrap()),
+                    expr::BitVector::word_constant(cell.try_into().unwrap()),
                 )?,
                 expr::Boolean::constant(true),
             )?;
@@ -712,12 +860,12 @@ fn define_cache<T>(solver: &mut Solver<T>, access_widths: &[usize]) -> Result<()
     for width in access_widths {
         let mut insert_expr: expr::Expression =
             expr::Variable::new("cache", cache_set_sort.clone()).into();
-        for byte in 0..(width / 8) {
+        for cell in 0..(width / cell_width) {
             insert_expr = expr::Array::store(
                 insert_expr,
                 expr::BitVector::add(
                     expr::Variable::new("addr", expr::Sort::word()).into(),
-                    expr::BitVector::word_constant(byte.try_into().unwrap()),
+                    expr::BitVector::word_constant(cell.try_into().unwrap()),
                 )?,
                 expr::Boolean::constant(false),
             )?;
@@ -788,6 +936,37 @@ fn define_pht<T>(solver: &mut Solver<T>) -> Result<()> {
     Ok(())
 }
 
+fn define_mdp<T>(solver: &mut Solver<T>) -> Result<()> {
+    // mdp type
+    solver.define_null_sort(
+        &expr::Sort::memory_dependence_predictor(),
+        &expr::Sort::array(expr::Sort::word(), expr::Sort::boolean()),
+    )?;
+
+    // mdp functions
+    solver.define_fun(
+        "mdp-bypass",
+        &[
+            ("mdp", expr::Sort::memory_dependence_predictor()),
+            ("location", expr::Sort::word()),
+        ],
+        &expr::Sort::boolean(),
+        "(select mdp location)",
+    )?;
+
+    solver.define_fun(
+        "mdp-dependent",
+        &[
+            ("mdp", expr::Sort::memory_dependence_predictor()),
+            ("location", expr::Sort::word()),
+        ],
+        &expr::Sort::memory_dependence_predictor(),
+        "(store mdp location false)",
+    )?;
+
+    Ok(())
+}
+
 fn define_tuple<T>(solver: &mut Solver<T>) -> Result<()> {
     for field_count in 1..10 {
         let sort_name = format!("Tuple{}", field_count);
@@ -893,8 +1072,9 @@ mod parser {
     use nom::{
         branch::alt,
         bytes::complete::{tag, take_while1},
-        character::complete::{char, digit1, hex_digit1, multispace1},
+        character::complete::{char, digit1, hex_digit1, multispace0, multispace1},
         combinator::{all_consuming, map, map_res, value},
+        multi::many1,
         sequence::{preceded, terminated, tuple},
         IResult,
     };
@@ -931,6 +1111,11 @@ mod parser {
         alt((
             value(expr::Sort::Boolean, tag("Bool")),
             value(expr::Sort::Integer, tag("Int")),
+            // must be tried before its prefix `Memory`
+            value(
+                expr::Sort::MemoryDependencePredictor,
+                tag("MemoryDependencePredictor"),
+            ),
             value(expr::Sort::Memory, tag("Memory")),
             value(expr::Sort::Predictor, tag("Predictor")),
             value(expr::Sort::Cache, tag("Cache")),
@@ -1028,8 +1213,100 @@ mod parser {
         alt((array_init, array_store))(input)
     }
 
+    fn symbol(input: &str) -> IResult<&str, &str> {
+        take_while1(|c: char| !c.is_whitespace() && c != '(' && c != ')')(input)
+    }
+
+    fn lambda_index_equal(input: &str) -> IResult<&str, expr::Constant> {
+        // (= x!1 #x0000000000000010) or (= #x0000000000000010 x!1)
+        let index_first = map(tuple((literal, multispace1, symbol)), |(index, _, _)| index);
+        let index_last = map(tuple((symbol, multispace1, literal)), |(_, _, index)| index);
+        map(
+            tuple((
+                tag("(="),
+                multispace1,
+                alt((index_first, index_last)),
+                multispace0,
+                char(')'),
+            )),
+            |(_, _, index, _, _)| index,
+        )(input)
+    }
+
+    fn lambda_ite(input: &str) -> IResult<&str, expr::ArrayValue> {
+        // (ite (= x!1 addr) value else)
+        map(
+            tuple((
+                tag("(ite"),
+                multispace1,
+                lambda_index_equal,
+                multispace1,
+                literal,
+                multispace1,
+                lambda_body,
+                multispace0,
+                char(')'),
+            )),
+            |(_, _, index, _, value, _, mut arr, _, _)| {
+                // The outermost condition takes precedence
+                arr.store(index, value);
+                arr
+            },
+        )(input)
+    }
+
+    fn lambda_set(input: &str) -> IResult<&str, expr::ArrayValue> {
+        // Boolean arrays (sets): (= x!1 addr) or (or (= x!1 addr1) (= x!1 addr2) ...)
+        let single = map(lambda_index_equal, |index| vec![index]);
+        let union = map(
+            tuple((
+                tag("(or"),
+                many1(preceded(multispace1, lambda_index_equal)),
+                multispace0,
+                char(')'),
+            )),
+            |(_, indices, _, _)| indices,
+        );
+        map(alt((single, union)), |indices| {
+            let mut arr = expr::ArrayValue::new(Some(expr::Constant::boolean(false)));
+            for index in indices {
+                arr.store(index, expr::Constant::boolean(true));
+            }
+            arr
+        })(input)
+    }
+
+    fn lambda_body(input: &str) -> IResult<&str, expr::ArrayValue> {
+        alt((
+            lambda_ite,
+            lambda_set,
+            map(literal, |value| expr::ArrayValue::new(Some(value))),
+        ))(input)
+    }
+
+    fn array_lambda(input: &str) -> IResult<&str, expr::ArrayValue> {
+        // Arrays as functions (e.g. Yices2):
+        // (lambda ((x!1 (_ BitVec 64))) (ite (= x!1 #x0000000000000010) #x0a #x00))
+        map(
+            tuple((
+                tag("(lambda"),
+                multispace1,
+                tag("(("),
+                symbol,
+                multispace1,
+                sort,
+                tag("))"),
+                multispace1,
+                lambda_body,
+                multispace0,
+                char(')'),
+            )),
+            |(_, _, _, _, _, _, _, _, arr, _, _)| arr,
+        )(input)
+    }
+
     fn array_literal(input: &str) -> IResult<&str, expr::Constant> {
-        map(array_nested, expr::Constant::array)(input)
+        map(alt((array_nested, array_lambda)), expr::Constant::array)(input)
     }
 
     fn literal(input: &str) -> IResult<&str, expr::Constant> {
@@ -1066,3 +1343,115 @@ impl<'a> ExprParser<String, (), &'a str> for Parser {
         Ok(input.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bv(value: u64) -> expr::Constant {
+        expr::Constant::bit_vector_big_uint(BigUint::from(value))
+    }
+
+    fn parse_array(input: &str) -> expr::ArrayValue {
+        parser::parse_literal(input).unwrap().unwrap_array().clone()
+    }
+
+    #[test]
+    fn test_yices2_nested_ite_lambda() {
+        // Given:
+        let input = "(lambda ((x!1 (_ BitVec 64))) \
+                     (ite (= x!1 #x0000000000000010) #x0a \
+                     (ite (= x!1 #x0000000000000011) #x0b #x00)))";
+
+        // When:
+        let arr = parse_array(input);
+
+        // Then:
+        assert_eq!(arr.select(&bv(0x10)), Some(&bv(0x0a)));
+        assert_eq!(arr.select(&bv(0x11)), Some(&bv(0x0b)));
+        assert_eq!(arr.select(&bv(0x12)), Some(&bv(0)));
+    }
+
+    #[test]
+    fn test_yices2_lambda_with_index_on_the_left() {
+        // Given:
+        let input = "(lambda ((x!1 (_ BitVec 8))) (ite (= #b00000100 x!1) #b00000001 #b00000000))";
+
+        // When:
+        let arr = parse_array(input);
+
+        // Then:
+        assert_eq!(arr.entries().len(), 1);
+        assert_eq!(arr.select(&bv(4)), Some(&bv(1)));
+        assert_eq!(arr.default_value(), Some(&bv(0)));
+    }
+
+    #[test]
+    fn test_yices2_boolean_set_lambda() {
+        // Given:
+        let input = "(lambda ((x!1 (_ BitVec 64))) \
+                     (or (= x!1 #x0000000000000040) (= #x0000000000000080 x!1)))";
+
+        // When:
+        let arr = parse_array(input);
+
+        // Then:
+        let true_value = expr::Constant::boolean(true);
+        assert_eq!(arr.select(&bv(0x40)), Some(&true_value));
+        assert_eq!(arr.select(&bv(0x80)), Some(&true_value));
+        assert_eq!(arr.select(&bv(0xc0)), Some(&expr::Constant::boolean(false)));
+    }
+
+    #[test]
+    fn test_yices2_single_element_set_lambda() {
+        // Given:
+        let input = "(lambda ((x!1 (_ BitVec 64))) (= x!1 #x0000000000000040))";
+
+        // When:
+        let arr = parse_array(input);
+
+        // Then:
+        assert_eq!(arr.entries().len(), 1);
+        assert_eq!(arr.select(&bv(0x40)), Some(&expr::Constant::boolean(true)));
+    }
+
+    #[test]
+    fn test_yices2_constant_lambda() {
+        // Given:
+        let input = "(lambda ((x!1 (_ BitVec 64))) #x00)";
+
+        // When:
+        let arr = parse_array(input);
+
+        // Then:
+        assert!(arr.entries().is_empty());
+        assert_eq!(arr.default_value(), Some(&bv(0)));
+    }
+
+    #[test]
+    fn test_yices2_outer_ite_overrides_inner_ite_of_same_index() {
+        // Given: the inner ite is shadowed by the outer one
+        let input = "(lambda ((x!1 (_ BitVec 64))) \
+                     (ite (= x!1 #x0000000000000010) #x0a \
+                     (ite (= x!1 #x0000000000000010) #x0b #x00)))";
+
+        // When:
+        let arr = parse_array(input);
+
+        // Then:
+        assert_eq!(arr.entries().len(), 1);
+        assert_eq!(arr.select(&bv(0x10)), Some(&bv(0x0a)));
+    }
+
+    #[test]
+    fn test_malformed_lambda_is_rejected() {
+        // Given: the condition doesn't compare the bound variable
+        let input = "(lambda ((x!1 (_ BitVec 64))) (ite (bvult x!1 #x0000000000000010) #x0a #x00))";
+
+        // When:
+        let result = parser::parse_literal(input);
+
+        // Then:
+        assert!(result.is_err());
+    }
+}
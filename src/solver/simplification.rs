@@ -0,0 +1,316 @@
+//! Solver-backed Expression Simplification
+//!
+//! Expressions are simplified by the rewriting engine of the solver (e.g. Z3's `simplify`),
+//! the result is read back from its SMT-LIB representation.
+//! Only Boolean and bit-vector terms can be read back, results using other theories are rejected.
+
+use crate::error::Result;
+use crate::expr::{BitVector, BitVectorValue, Boolean, Expression, Variable};
+use num_bigint::BigUint;
+use std::collections::HashMap;
+
+pub trait ExpressionSimplifier {
+    /// Returns the simplified expression, or `None` if the solver's result can't be represented.
+    fn simplify(&self, expr: &Expression) -> Result<Option<Expression>>;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>),
+}
+
+/// Parses the SMT-LIB term `text`, free symbols are resolved by `variables` (by identifier).
+pub(crate) fn parse_term(text: &str, variables: &HashMap<String, Variable>) -> Result<Expression> {
+    let tokens = tokenize(text)?;
+    let (sexpr, consumed) = parse_sexpr(&tokens)?;
+    if consumed != tokens.len() {
+        return Err("Unexpected trailing tokens in SMT-LIB term".into());
+    }
+    Decoder {
+        variables,
+        bindings: Vec::new(),
+    }
+    .decode(&sexpr)
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | ')' => tokens.push(c.to_string()),
+            '|' => {
+                let mut symbol = String::new();
+                loop {
+                    match chars.next() {
+                        Some('|') => break,
+                        Some(c) => symbol.push(c),
+                        None => return Err("Unterminated quoted symbol in SMT-LIB term".into()),
+                    }
+                }
+                tokens.push(symbol);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    atom.push(next);
+                    chars.next();
+                }
+                tokens.push(atom);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses the s-expression at the start of `tokens`, returns it along with the number of consumed tokens.
+fn parse_sexpr(tokens: &[String]) -> Result<(SExpr, usize)> {
+    match tokens.first().map(String::as_str) {
+        Some("(") => {
+            let mut elements = Vec::new();
+            let mut position = 1;
+            loop {
+                match tokens.get(position).map(String::as_str) {
+                    Some(")") => return Ok((SExpr::List(elements), position + 1)),
+                    Some(_) => {
+                        let (element, consumed) = parse_sexpr(&tokens[position..])?;
+                        elements.push(element);
+                        position += consumed;
+                    }
+                    None => return Err("Unbalanced parentheses in SMT-LIB term".into()),
+                }
+            }
+        }
+        Some(")") => Err("Unbalanced parentheses in SMT-LIB term".into()),
+        Some(atom) => Ok((SExpr::Atom(atom.to_owned()), 1)),
+        None => Err("Empty SMT-LIB term".into()),
+    }
+}
+
+struct Decoder<'a> {
+    variables: &'a HashMap<String, Variable>,
+    /// Scopes of `let` bindings, innermost last
+    bindings: Vec<HashMap<String, Expression>>,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self, sexpr: &SExpr) -> Result<Expression> {
+        match sexpr {
+            SExpr::Atom(atom) => self.atom(atom),
+            SExpr::List(elements) => match elements.as_slice() {
+                [SExpr::Atom(underscore), SExpr::Atom(value), SExpr::Atom(bits)]
+                    if underscore == "_" && value.starts_with("bv") =>
+                {
+                    let value = BigUint::parse_bytes(value[2..].as_bytes(), 10)
+                        .ok_or_else(|| format!("Invalid bit-vector literal '{}'", value))?;
+                    Ok(bit_vector_constant(value, parse_index(bits)?))
+                }
+                [SExpr::Atom(head), SExpr::List(bindings), body] if head == "let" => {
+                    self.let_binding(bindings, body)
+                }
+                [SExpr::Atom(head), args @ ..] => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.decode(arg))
+                        .collect::<Result<Vec<_>>>()?;
+                    apply(head, args)
+                }
+                [SExpr::List(indexed), args @ ..] => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.decode(arg))
+                        .collect::<Result<Vec<_>>>()?;
+                    apply_indexed(indexed, args)
+                }
+                _ => Err("Invalid SMT-LIB term".into()),
+            },
+        }
+    }
+
+    fn atom(&self, atom: &str) -> Result<Expression> {
+        if let Some(expr) = self.bindings.iter().rev().find_map(|scope| scope.get(atom)) {
+            return Ok(expr.clone());
+        }
+        if let Some(variable) = self.variables.get(atom) {
+            return Ok(variable.clone().into());
+        }
+        match atom {
+            "true" => Ok(Boolean::constant(true)),
+            "false" => Ok(Boolean::constant(false)),
+            _ => {
+                if let Some(hex) = atom.strip_prefix("#x") {
+                    let value = BigUint::parse_bytes(hex.as_bytes(), 16)
+                        .ok_or_else(|| format!("Invalid bit-vector literal '{}'", atom))?;
+                    Ok(bit_vector_constant(value, hex.len() * 4))
+                } else if let Some(bin) = atom.strip_prefix("#b") {
+                    let value = BigUint::parse_bytes(bin.as_bytes(), 2)
+                        .ok_or_else(|| format!("Invalid bit-vector literal '{}'", atom))?;
+                    Ok(bit_vector_constant(value, bin.len()))
+                } else {
+                    Err(format!("Unknown symbol '{}' in SMT-LIB term", atom).into())
+                }
+            }
+        }
+    }
+
+    /// Bindings of a `let` are parallel, i.e. they can't refer to each other.
+    fn let_binding(&mut self, bindings: &[SExpr], body: &SExpr) -> Result<Expression> {
+        let mut scope = HashMap::new();
+        for binding in bindings {
+            match binding {
+                SExpr::List(pair) => match pair.as_slice() {
+                    [SExpr::Atom(name), term] => {
+                        scope.insert(name.clone(), self.decode(term)?);
+                    }
+                    _ => return Err("Invalid let binding in SMT-LIB term".into()),
+                },
+                _ => return Err("Invalid let binding in SMT-LIB term".into()),
+            }
+        }
+
+        self.bindings.push(scope);
+        let result = self.decode(body);
+        self.bindings.pop();
+        result
+    }
+}
+
+fn bit_vector_constant(value: BigUint, bits: usize) -> Expression {
+    BitVector::constant(BitVectorValue::new_big(value, bits))
+}
+
+fn parse_index(index: &str) -> Result<usize> {
+    index
+        .parse()
+        .map_err(|_| format!("Invalid index '{}' in SMT-LIB term", index).into())
+}
+
+/// Applies the left-associative binary operator `op` to all arguments.
+fn fold(
+    args: Vec<Expression>,
+    op: fn(Expression, Expression) -> Result<Expression>,
+) -> Result<Expression> {
+    let mut args = args.into_iter();
+    let first = args.next().ok_or("Missing operands in SMT-LIB term")?;
+    args.try_fold(first, op)
+}
+
+fn apply(head: &str, args: Vec<Expression>) -> Result<Expression> {
+    match (head, args.as_slice()) {
+        ("not", [a]) => Boolean::not(a.clone()),
+        ("and", _) => Boolean::conjunction(&args),
+        ("or", _) => Boolean::disjunction(&args),
+        ("=>", [a, b]) => Boolean::imply(a.clone(), b.clone()),
+        ("xor", _) => fold(args, Boolean::xor),
+        ("=", [a, b]) => Expression::equal(a.clone(), b.clone()),
+        ("=", _) => Expression::all_equal(&args),
+        ("distinct", [a, b]) => Expression::unequal(a.clone(), b.clone()),
+        ("ite", [cond, then, otherwise]) => {
+            Expression::ite(cond.clone(), then.clone(), otherwise.clone())
+        }
+        ("concat", _) => BitVector::concat(&args),
+        ("bvnot", [a]) => BitVector::not(a.clone()),
+        ("bvneg", [a]) => BitVector::neg(a.clone()),
+        ("bvand", _) => fold(args, BitVector::and),
+        ("bvor", _) => fold(args, BitVector::or),
+        ("bvxor", _) => fold(args, BitVector::xor),
+        ("bvadd", _) => fold(args, BitVector::add),
+        ("bvmul", _) => fold(args, BitVector::mul),
+        ("bvsub", [a, b]) => BitVector::sub(a.clone(), b.clone()),
+        ("bvudiv", [a, b]) => BitVector::udiv(a.clone(), b.clone()),
+        ("bvurem", [a, b]) => BitVector::urem(a.clone(), b.clone()),
+        ("bvsdiv", [a, b]) => BitVector::sdiv(a.clone(), b.clone()),
+        ("bvsrem", [a, b]) => BitVector::srem(a.clone(), b.clone()),
+        ("bvsmod", [a, b]) => BitVector::smod(a.clone(), b.clone()),
+        ("bvshl", [a, b]) => BitVector::shl(a.clone(), b.clone()),
+        ("bvlshr", [a, b]) => BitVector::lshr(a.clone(), b.clone()),
+        ("bvashr", [a, b]) => BitVector::ashr(a.clone(), b.clone()),
+        ("bvnand", [a, b]) => BitVector::nand(a.clone(), b.clone()),
+        ("bvnor", [a, b]) => BitVector::nor(a.clone(), b.clone()),
+        ("bvxnor", [a, b]) => BitVector::xnor(a.clone(), b.clone()),
+        ("bvult", [a, b]) => BitVector::ult(a.clone(), b.clone()),
+        ("bvule", [a, b]) => BitVector::ule(a.clone(), b.clone()),
+        ("bvugt", [a, b]) => BitVector::ugt(a.clone(), b.clone()),
+        ("bvuge", [a, b]) => BitVector::uge(a.clone(), b.clone()),
+        ("bvslt", [a, b]) => BitVector::slt(a.clone(), b.clone()),
+        ("bvsle", [a, b]) => BitVector::sle(a.clone(), b.clone()),
+        ("bvsgt", [a, b]) => BitVector::sgt(a.clone(), b.clone()),
+        ("bvsge", [a, b]) => BitVector::sge(a.clone(), b.clone()),
+        _ => Err(format!("Unsupported operator '{}' in SMT-LIB term", head).into()),
+    }
+}
+
+fn apply_indexed(indexed: &[SExpr], args: Vec<Expression>) -> Result<Expression> {
+    let (name, indices) = match indexed {
+        [SExpr::Atom(underscore), SExpr::Atom(name), indices @ ..] if underscore == "_" => {
+            let indices = indices
+                .iter()
+                .map(|index| match index {
+                    SExpr::Atom(index) => parse_index(index),
+                    SExpr::List(_) => Err("Invalid index in SMT-LIB term".into()),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            (name.as_str(), indices)
+        }
+        _ => return Err("Invalid indexed operator in SMT-LIB term".into()),
+    };
+
+    match (name, indices.as_slice(), args.as_slice()) {
+        ("extract", [i, j], [a]) => BitVector::extract(*i, *j, a.clone()),
+        ("zero_extend", [n], [a]) => BitVector::zero_extend(*n, a.clone()),
+        ("sign_extend", [n], [a]) => BitVector::sign_extend(*n, a.clone()),
+        ("repeat", [n], [a]) => BitVector::repeat(*n, a.clone()),
+        ("rotate_left", [n], [a]) => BitVector::rotate_left(*n, a.clone()),
+        ("rotate_right", [n], [a]) => BitVector::rotate_right(*n, a.clone()),
+        _ => Err(format!("Unsupported operator '{}' in SMT-LIB term", name).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> HashMap<String, Variable> {
+        vec![BitVector::variable("x", 8), Boolean::variable("c")]
+            .into_iter()
+            .map(|var| (var.identifier(), var))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_term_with_let_bindings_and_indexed_operators() {
+        // Given:
+        let text = "(let ((a!1 ((_ zero_extend 8) x))) (ite c (bvadd a!1 #x0001 (_ bv2 16)) a!1))";
+
+        // When:
+        let expr = parse_term(text, &variables()).unwrap();
+
+        // Then:
+        let extended = BitVector::zero_extend(8, BitVector::variable("x", 8).into()).unwrap();
+        let sum = BitVector::add(
+            BitVector::add(extended.clone(), BitVector::constant_u64(1, 16)).unwrap(),
+            BitVector::constant_u64(2, 16),
+        )
+        .unwrap();
+        assert_eq!(
+            expr,
+            Expression::ite(Boolean::variable("c").into(), sum, extended).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_term_rejects_unknown_operators_and_symbols() {
+        // Given:
+        let unknown_operator = "(bvudiv_i x #x01)";
+        let unknown_symbol = "(bvadd x y)";
+
+        // When/Then:
+        assert!(parse_term(unknown_operator, &variables()).is_err());
+        assert!(parse_term(unknown_symbol, &variables()).is_err());
+    }
+}
@@ -34,7 +34,7 @@ def run_test(test_file, env_file):
         return False
 
     try:
-        args = [SPECBMC_BIN, test_file, '--env', env_file]
+        args = [SPECBMC_BIN, 'check', test_file, '--env', env_file]
         if SOLVER:
             args += ['--solver', SOLVER]
         args += shlex.split(DEFAULT_ARGS)